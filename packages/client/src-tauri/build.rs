@@ -1,3 +1,69 @@
 fn main() {
+    check_updater_key();
+    api_url();
     tauri_build::build()
 }
+
+/// The production server, as in src/config/environment.ts.
+const DEFAULT_API_URL: &str = "https://commhub-production.up.railway.app";
+
+/// Bakes in the API server the webview is built against, so the backend
+/// only ever sends the session token there. Vite reads `VITE_API_URL` from
+/// the environment or the client's `.env` files, in that order; this does
+/// the same, with the mode `tauri dev` and `tauri build` run it in.
+fn api_url() {
+    println!("cargo:rerun-if-env-changed=VITE_API_URL");
+    let mode = match std::env::var("PROFILE").as_deref() {
+        Ok("release") => "production",
+        _ => "development",
+    };
+    let files = [
+        format!("../.env.{mode}.local"),
+        format!("../.env.{mode}"),
+        "../.env.local".to_string(),
+        "../.env".to_string(),
+    ];
+    // A missing file would rerun this on every build
+    for file in files
+        .iter()
+        .filter(|file| std::path::Path::new(file).exists())
+    {
+        println!("cargo:rerun-if-changed={}", file);
+    }
+    let url = std::env::var("VITE_API_URL")
+        .ok()
+        .or_else(|| {
+            files.iter().find_map(|file| {
+                let text = std::fs::read_to_string(file).ok()?;
+                text.lines().find_map(|line| {
+                    let value = line.trim().strip_prefix("VITE_API_URL")?;
+                    let value = value.trim_start().strip_prefix('=')?.trim();
+                    Some(value.trim_matches(|c| c == '"' || c == '\'').to_string())
+                })
+            })
+        })
+        .filter(|url| !url.is_empty())
+        .unwrap_or_else(|| DEFAULT_API_URL.to_string());
+    println!("cargo:rustc-env=COMMHUB_API_URL={}", url);
+}
+
+/// The self-updater is off in a build without a public key, since it could
+/// verify nothing; say so rather than let a release ship that way unnoticed.
+fn check_updater_key() {
+    println!("cargo:rerun-if-changed=tauri.conf.json");
+    let config: serde_json::Value = std::fs::read_to_string("tauri.conf.json")
+        .ok()
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .expect("tauri.conf.json is missing or malformed");
+    let pubkey = config["tauri"]["updater"]["pubkey"]
+        .as_str()
+        .unwrap_or_default();
+
+    if pubkey.trim().is_empty() {
+        println!(
+            "cargo:warning=tauri.updater.pubkey in tauri.conf.json is empty, so this build \
+             won't update itself; generate a key pair with `cargo tauri signer generate` (or \
+             `commhub-release --generate-key`) and set the public key"
+        );
+    }
+}
@@ -0,0 +1,759 @@
+//! Generates latest.json for a release.
+//!
+//! ```text
+//! cargo run -p commhub-release -- [options]
+//!
+//!   --bundle-dir <dir>             bundles to publish (target/release/bundle)
+//!   --artifact <platform>=<path>   publish a file under an explicit platform key
+//!   --base-url <url>               download prefix (the GitHub release for the tag)
+//!   --notes <file>                 release notes (release-notes.toml at the repo root)
+//!   --output <file>                manifest to write (latest.json at the repo root)
+//!   --check                        only check that the versions agree
+//!   --generate-key                 print a new signing key and its public key
+//! ```
+//!
+//! Refuses to run unless Cargo.toml, tauri.conf.json, package.json and the
+//! README badge carry the same version. Bundles are signed with the same
+//! `TAURI_PRIVATE_KEY` and `TAURI_KEY_PASSWORD` that `tauri build` reads, so
+//! keys from `tauri signer generate` work here and the other way round.
+//! Platforms already in the output for the same version are kept, so bundles
+//! built on different machines can be added one by one.
+
+mod minisign;
+
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::SystemTime;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use minisign::SecretKey;
+
+const KEY_ENV: &str = "TAURI_PRIVATE_KEY";
+const PASSWORD_ENV: &str = "TAURI_KEY_PASSWORD";
+const RELEASES_URL: &str = "https://github.com/AnthonyRandom/commhub/releases/download";
+
+struct Options {
+    bundle_dir: PathBuf,
+    artifacts: Vec<(String, PathBuf)>,
+    base_url: Option<String>,
+    notes: PathBuf,
+    output: PathBuf,
+    check_only: bool,
+    generate_key: bool,
+}
+
+/// What the release author writes; rendered into `notes` and kept as-is in
+/// `release_notes` for clients that want the structure.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ReleaseNotes {
+    version: String,
+    summary: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    features: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    improvements: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    fixes: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Manifest {
+    version: String,
+    notes: String,
+    pub_date: String,
+    platforms: BTreeMap<String, Platform>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    release_notes: Option<ReleaseNotes>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Platform {
+    signature: String,
+    url: String,
+    #[serde(default)]
+    sha256: String,
+    #[serde(default)]
+    size: u64,
+}
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run() -> Result<(), String> {
+    let options = parse_args(std::env::args().skip(1))?;
+
+    let password = std::env::var(PASSWORD_ENV).unwrap_or_default();
+    if options.generate_key {
+        let key = SecretKey::generate();
+        println!("{}={}", KEY_ENV, key.encode(&password)?);
+        println!("tauri.updater.pubkey: {}", key.public_key());
+        return Ok(());
+    }
+
+    let version = agreed_version()?;
+    println!("Version {} agrees across the repo", version);
+    if options.check_only {
+        return Ok(());
+    }
+
+    let notes: ReleaseNotes = toml::from_str(&read(&options.notes)?)
+        .map_err(|e| format!("{}: {}", options.notes.display(), e))?;
+    if notes.version != version {
+        return Err(format!(
+            "{} is for {}, not {}",
+            options.notes.display(),
+            notes.version,
+            version
+        ));
+    }
+
+    let mut artifacts = options.artifacts.clone();
+    if artifacts.is_empty() {
+        artifacts = discover_bundles(&options.bundle_dir, &version)?;
+    }
+    if artifacts.is_empty() {
+        return Err(format!(
+            "no bundles for {} found in {}",
+            version,
+            options.bundle_dir.display()
+        ));
+    }
+
+    let key = std::env::var(KEY_ENV)
+        .map_err(|_| format!("{} is not set (see --generate-key)", KEY_ENV))
+        .and_then(|value| {
+            // Like `tauri build`, take either the key or a path to it
+            let path = Path::new(&value);
+            if path.is_file() {
+                read(path)
+            } else {
+                Ok(value)
+            }
+        })
+        .and_then(|encoded| SecretKey::decode(&encoded, &password))?;
+    let base_url = options
+        .base_url
+        .clone()
+        .unwrap_or_else(|| format!("{}/v{}", RELEASES_URL, version));
+
+    let now = DateTime::<Utc>::from(SystemTime::now());
+    let mut platforms = existing_platforms(&options.output, &version);
+    for (platform, path) in artifacts {
+        let entry = publish(&key, &path, &base_url, now.timestamp())?;
+        println!("{:<16} {} ({})", platform, entry.url, entry.sha256);
+        platforms.insert(platform, entry);
+    }
+
+    let manifest = Manifest {
+        version: version.clone(),
+        notes: render_notes(&notes),
+        pub_date: now.to_rfc3339_opts(SecondsFormat::Secs, true),
+        platforms,
+        release_notes: Some(notes),
+    };
+    let json = serde_json::to_string_pretty(&manifest).map_err(|e| e.to_string())?;
+    fs::write(&options.output, json + "\n")
+        .map_err(|e| format!("{}: {}", options.output.display(), e))?;
+    println!("Wrote {}", options.output.display());
+    Ok(())
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let app_dir = app_dir();
+    let repo_root = app_dir.join("../../..");
+    let mut options = Options {
+        bundle_dir: app_dir.join("target/release/bundle"),
+        artifacts: Vec::new(),
+        base_url: None,
+        notes: repo_root.join("release-notes.toml"),
+        output: repo_root.join("latest.json"),
+        check_only: false,
+        generate_key: false,
+    };
+
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("{} needs a value", arg));
+        match arg.as_str() {
+            "--bundle-dir" => options.bundle_dir = value()?.into(),
+            "--artifact" => {
+                let spec = value()?;
+                let (platform, path) = spec.split_once('=').ok_or(format!(
+                    "--artifact expects <platform>=<path>, got {}",
+                    spec
+                ))?;
+                options.artifacts.push((platform.to_string(), path.into()));
+            }
+            "--base-url" => options.base_url = Some(value()?.trim_end_matches('/').to_string()),
+            "--notes" => options.notes = value()?.into(),
+            "--output" => options.output = value()?.into(),
+            "--check" => options.check_only = true,
+            "--generate-key" => options.generate_key = true,
+            other => return Err(format!("unknown argument {}", other)),
+        }
+    }
+    Ok(options)
+}
+
+/// Reads every place the version is written down and fails unless they all
+/// match.
+fn agreed_version() -> Result<String, String> {
+    let app_dir = app_dir();
+    agree(
+        &read(&app_dir.join("Cargo.toml"))?,
+        &read(&app_dir.join("tauri.conf.json"))?,
+        &read(&app_dir.join("../package.json"))?,
+        &read(&app_dir.join("../../../README.md"))?,
+    )
+}
+
+/// The version the files' contents agree on.
+fn agree(
+    cargo_toml: &str,
+    tauri_conf: &str,
+    package_json: &str,
+    readme: &str,
+) -> Result<String, String> {
+    let cargo: toml::Table =
+        toml::from_str(cargo_toml).map_err(|e| format!("Cargo.toml: {}", e))?;
+    let tauri_conf: serde_json::Value =
+        serde_json::from_str(tauri_conf).map_err(|e| format!("tauri.conf.json: {}", e))?;
+    let package_json: serde_json::Value =
+        serde_json::from_str(package_json).map_err(|e| format!("package.json: {}", e))?;
+
+    let sources = [
+        (
+            "src-tauri/Cargo.toml",
+            cargo
+                .get("package")
+                .and_then(|package| package.get("version"))
+                .and_then(|version| version.as_str())
+                .map(str::to_string),
+        ),
+        (
+            "src-tauri/tauri.conf.json",
+            tauri_conf["package"]["version"]
+                .as_str()
+                .map(str::to_string),
+        ),
+        (
+            "packages/client/package.json",
+            package_json["version"].as_str().map(str::to_string),
+        ),
+        ("README.md badge", readme_badge_version(readme)),
+    ];
+
+    let first = sources[0].1.clone();
+    if sources
+        .iter()
+        .all(|(_, version)| version.is_some() && *version == first)
+    {
+        return Ok(first.unwrap_or_default());
+    }
+
+    let mut message = String::from("versions disagree, refusing to publish:");
+    for (source, version) in &sources {
+        message.push_str(&format!(
+            "\n  {:<28} {}",
+            source,
+            version.as_deref().unwrap_or("<missing>")
+        ));
+    }
+    Err(message)
+}
+
+/// src-tauri, which this crate sits in.
+fn app_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("..")
+}
+
+/// Pulls `1.2.3` out of the shields.io `badge/Version-1.2.3-green` URL.
+fn readme_badge_version(readme: &str) -> Option<String> {
+    let start = readme.find("badge/Version-")? + "badge/Version-".len();
+    let rest = &readme[start..];
+    // shields.io escapes a literal dash as `--`
+    let mut end = 0;
+    let bytes = rest.as_bytes();
+    while end < bytes.len() {
+        if bytes[end] == b'-' {
+            if bytes.get(end + 1) == Some(&b'-') {
+                end += 2;
+                continue;
+            }
+            break;
+        }
+        end += 1;
+    }
+    Some(rest[..end].replace("--", "-"))
+}
+
+/// Finds updater-compatible bundles for `version` in the layout `tauri
+/// build` produces. MSIs win over NSIS installers for the same platform.
+fn discover_bundles(bundle_dir: &Path, version: &str) -> Result<Vec<(String, PathBuf)>, String> {
+    let kinds: [(&str, &str, &str); 3] = [
+        ("msi", ".msi", "windows"),
+        ("nsis", "-setup.exe", "windows"),
+        ("appimage", ".AppImage", "linux"),
+    ];
+
+    let mut found: BTreeMap<String, PathBuf> = BTreeMap::new();
+    for (dir, suffix, os) in kinds {
+        let Ok(entries) = fs::read_dir(bundle_dir.join(dir)) else {
+            continue;
+        };
+        let mut paths: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .collect();
+        paths.sort();
+
+        for path in paths {
+            let name = path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            if !name.ends_with(suffix) || !name.contains(version) {
+                continue;
+            }
+            let Some(arch) = arch_from_name(&name) else {
+                eprintln!("warning: can't tell the architecture of {}, skipping", name);
+                continue;
+            };
+            found.entry(format!("{}-{}", os, arch)).or_insert(path);
+        }
+    }
+    for (platform, path) in discover_macos(bundle_dir, version) {
+        found.entry(platform).or_insert(path);
+    }
+    Ok(found.into_iter().collect())
+}
+
+/// The macOS updater artifact is the `.app` archived as `.app.tar.gz`, named
+/// without version or architecture. The version comes from the app's
+/// Info.plist and the architecture from the target directory, or the DMG
+/// built alongside it.
+fn discover_macos(bundle_dir: &Path, version: &str) -> Vec<(String, PathBuf)> {
+    let Ok(entries) = fs::read_dir(bundle_dir.join("macos")) else {
+        return Vec::new();
+    };
+    let mut archives: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.to_string_lossy().ends_with(".app.tar.gz"))
+        .collect();
+    archives.sort();
+
+    let mut found = Vec::new();
+    for archive in archives {
+        let name = archive
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let app = archive.with_file_name(name.trim_end_matches(".tar.gz"));
+        match bundle_version(&app) {
+            Some(found) if found == version => {}
+            Some(other) => {
+                eprintln!("warning: {} is version {}, skipping", name, other);
+                continue;
+            }
+            None => {
+                eprintln!("warning: can't read the version of {}, skipping", name);
+                continue;
+            }
+        }
+
+        let arches = macos_arches(bundle_dir, version);
+        if arches.is_empty() {
+            eprintln!("warning: can't tell the architecture of {}, skipping", name);
+        }
+        for arch in arches {
+            found.push((format!("darwin-{}", arch), archive.clone()));
+        }
+    }
+    found
+}
+
+/// `CFBundleShortVersionString` from the bundle's Info.plist.
+fn bundle_version(app: &Path) -> Option<String> {
+    let plist = fs::read_to_string(app.join("Contents/Info.plist")).ok()?;
+    let key = plist.find("<key>CFBundleShortVersionString</key>")?;
+    let rest = &plist[key..];
+    let start = rest.find("<string>")? + "<string>".len();
+    let end = rest[start..].find("</string>")? + start;
+    Some(rest[start..end].trim().to_string())
+}
+
+/// Universal builds serve both architectures from one archive.
+fn macos_arches(bundle_dir: &Path, version: &str) -> Vec<&'static str> {
+    let from_tag = |tag: &str| match tag {
+        "universal" => vec!["x86_64", "aarch64"],
+        tag => arch_from_name(tag).into_iter().collect(),
+    };
+
+    // target/<triple>/release/bundle when built with --target
+    let triple = bundle_dir.components().find_map(|component| {
+        let component = component.as_os_str().to_string_lossy();
+        component.strip_suffix("-apple-darwin").map(str::to_string)
+    });
+    if let Some(arch) = triple {
+        return from_tag(&arch);
+    }
+
+    // CommHub_1.2.3_aarch64.dmg
+    let dmg = fs::read_dir(bundle_dir.join("dmg"))
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .find(|name| name.ends_with(".dmg") && name.contains(version));
+    if let Some(dmg) = dmg {
+        let tag = dmg
+            .trim_end_matches(".dmg")
+            .rsplit('_')
+            .next()
+            .unwrap_or_default()
+            .to_string();
+        return from_tag(&tag);
+    }
+
+    // Built for the host, when that's a Mac
+    if cfg!(target_os = "macos") {
+        return from_tag(std::env::consts::ARCH);
+    }
+    Vec::new()
+}
+
+fn arch_from_name(name: &str) -> Option<&'static str> {
+    let name = name.to_ascii_lowercase();
+    if ["x64", "x86_64", "amd64"]
+        .iter()
+        .any(|tag| name.contains(tag))
+    {
+        Some("x86_64")
+    } else if ["aarch64", "arm64"].iter().any(|tag| name.contains(tag)) {
+        Some("aarch64")
+    } else if ["x86", "i686"].iter().any(|tag| name.contains(tag)) {
+        Some("i686")
+    } else {
+        None
+    }
+}
+
+/// Hashes and signs one bundle, leaving a `.sig` next to it as `tauri
+/// build` would.
+fn publish(
+    key: &SecretKey,
+    path: &Path,
+    base_url: &str,
+    timestamp: i64,
+) -> Result<Platform, String> {
+    let describe = |e: std::io::Error| format!("{}: {}", path.display(), e);
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .ok_or(format!("{} is not a file", path.display()))?;
+
+    let mut hasher = Sha256::new();
+    let mut file = File::open(path).map_err(describe)?;
+    let mut buf = vec![0u8; 64 * 1024];
+    let mut size = 0u64;
+    loop {
+        let read = file.read(&mut buf).map_err(describe)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buf[..read]);
+        size += read as u64;
+    }
+    let sha256 = hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+
+    let signature = key.sign_file(path, timestamp).map_err(describe)?;
+    let mut sig_path = path.as_os_str().to_owned();
+    sig_path.push(".sig");
+    fs::write(&sig_path, &signature).map_err(describe)?;
+
+    Ok(Platform {
+        signature,
+        url: format!("{}/{}", base_url, name),
+        sha256,
+        size,
+    })
+}
+
+fn existing_platforms(output: &Path, version: &str) -> BTreeMap<String, Platform> {
+    fs::read(output)
+        .ok()
+        .and_then(|bytes| serde_json::from_slice::<Manifest>(&bytes).ok())
+        .filter(|manifest| manifest.version == version)
+        .map(|manifest| manifest.platforms)
+        .unwrap_or_default()
+}
+
+fn render_notes(notes: &ReleaseNotes) -> String {
+    let mut out = notes.summary.trim().to_string();
+    for (heading, items) in [
+        ("New", &notes.features),
+        ("Improvements", &notes.improvements),
+        ("Fixes", &notes.fixes),
+    ] {
+        if items.is_empty() {
+            continue;
+        }
+        out.push_str(&format!("\n\n### {}\n", heading));
+        for item in items {
+            out.push_str(&format!("\n- {}", item.trim()));
+        }
+    }
+    out
+}
+
+fn read(path: &Path) -> Result<String, String> {
+    fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cargo_toml(version: &str) -> String {
+        format!(
+            "[package]\nname = \"commhub-client\"\nversion = \"{}\"\n",
+            version
+        )
+    }
+
+    fn tauri_conf(version: &str) -> String {
+        format!(
+            "{{\"package\": {{\"productName\": \"CommHub\", \"version\": \"{}\"}}}}",
+            version
+        )
+    }
+
+    fn package_json(version: &str) -> String {
+        format!(
+            "{{\"name\": \"commhub-client\", \"version\": \"{}\"}}",
+            version
+        )
+    }
+
+    fn readme(version: &str) -> String {
+        format!(
+            "# CommHub\n\n[![Version](https://img.shields.io/badge/Version-{}-green.svg)](#)\n",
+            version.replace('-', "--")
+        )
+    }
+
+    #[test]
+    fn versions_must_agree_everywhere() {
+        // Cargo.toml, tauri.conf.json, package.json, README, then the outcome
+        let cases: &[(&str, &str, &str, &str, Option<&str>)] = &[
+            ("1.2.3", "1.2.3", "1.2.3", "1.2.3", Some("1.2.3")),
+            (
+                "1.3.0-beta.1",
+                "1.3.0-beta.1",
+                "1.3.0-beta.1",
+                "1.3.0-beta.1",
+                Some("1.3.0-beta.1"),
+            ),
+            ("1.2.4", "1.2.3", "1.2.3", "1.2.3", None),
+            ("1.2.3", "1.2.4", "1.2.3", "1.2.3", None),
+            ("1.2.3", "1.2.3", "1.2.4", "1.2.3", None),
+            ("1.2.3", "1.2.3", "1.2.3", "1.2.1", None),
+        ];
+        for (cargo, tauri, package, badge, expected) in cases {
+            let outcome = agree(
+                &cargo_toml(cargo),
+                &tauri_conf(tauri),
+                &package_json(package),
+                &readme(badge),
+            );
+            match expected {
+                Some(version) => assert_eq!(outcome.as_deref(), Ok(*version)),
+                None => {
+                    let message = outcome.unwrap_err();
+                    assert!(message.starts_with("versions disagree"), "{}", message);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn missing_versions_are_refused() {
+        let message = agree(
+            "[package]\nname = \"commhub-client\"\n",
+            &tauri_conf("1.2.3"),
+            &package_json("1.2.3"),
+            &readme("1.2.3"),
+        )
+        .unwrap_err();
+        assert!(message.contains("<missing>"), "{}", message);
+
+        // Agreeing on nothing isn't agreeing
+        assert!(agree("[package]", "{}", "{}", "# CommHub").is_err());
+        assert!(agree("not toml", "{}", "{}", "").is_err());
+    }
+
+    #[test]
+    fn reads_the_readme_badge() {
+        assert_eq!(
+            readme_badge_version(&readme("1.2.3")).as_deref(),
+            Some("1.2.3")
+        );
+        assert_eq!(
+            readme_badge_version(&readme("1.3.0-beta.1")).as_deref(),
+            Some("1.3.0-beta.1")
+        );
+        assert_eq!(readme_badge_version("# CommHub\n"), None);
+    }
+
+    #[test]
+    fn tells_architectures_from_bundle_names() {
+        let cases: &[(&str, Option<&str>)] = &[
+            ("CommHub_1.2.3_x64_en-US.msi", Some("x86_64")),
+            ("CommHub_1.2.3_x64-setup.exe", Some("x86_64")),
+            ("commhub_1.2.3_amd64.AppImage", Some("x86_64")),
+            ("x86_64", Some("x86_64")),
+            ("CommHub_1.2.3_arm64-setup.exe", Some("aarch64")),
+            ("commhub_1.2.3_aarch64.AppImage", Some("aarch64")),
+            ("CommHub_1.2.3_x86-setup.exe", Some("i686")),
+            ("CommHub_1.2.3_en-US.msi", None),
+        ];
+        for (name, expected) in cases {
+            assert_eq!(arch_from_name(name), *expected, "{}", name);
+        }
+    }
+
+    fn bundle_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("commhub-release-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn touch(path: &Path, contents: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    fn mac_app(bundle_dir: &Path, version: &str) {
+        touch(&bundle_dir.join("macos/CommHub.app.tar.gz"), "archive");
+        touch(
+            &bundle_dir.join("macos/CommHub.app/Contents/Info.plist"),
+            &format!(
+                "<plist><dict>\n<key>CFBundleShortVersionString</key>\n<string>{}</string>\n</dict></plist>",
+                version
+            ),
+        );
+    }
+
+    fn platforms(found: &[(String, PathBuf)]) -> Vec<(&str, String)> {
+        found
+            .iter()
+            .map(|(platform, path)| {
+                let name = path.file_name().unwrap().to_string_lossy().into_owned();
+                (platform.as_str(), name)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn discovers_bundles_for_the_version() {
+        let dir = bundle_dir("bundles");
+        for name in [
+            "msi/CommHub_1.2.3_x64_en-US.msi",
+            "msi/CommHub_1.2.3_en-US.msi",
+            "nsis/CommHub_1.2.3_x64-setup.exe",
+            "nsis/CommHub_1.2.3_arm64-setup.exe",
+            "appimage/commhub_1.2.3_amd64.AppImage",
+            "appimage/commhub_1.2.2_aarch64.AppImage",
+            "dmg/CommHub_1.2.3_universal.dmg",
+        ] {
+            touch(&dir.join(name), "bundle");
+        }
+        mac_app(&dir, "1.2.3");
+
+        let found = discover_bundles(&dir, "1.2.3").unwrap();
+        assert_eq!(
+            platforms(&found),
+            [
+                ("darwin-aarch64", "CommHub.app.tar.gz".to_string()),
+                ("darwin-x86_64", "CommHub.app.tar.gz".to_string()),
+                ("linux-x86_64", "commhub_1.2.3_amd64.AppImage".to_string()),
+                (
+                    "windows-aarch64",
+                    "CommHub_1.2.3_arm64-setup.exe".to_string()
+                ),
+                ("windows-x86_64", "CommHub_1.2.3_x64_en-US.msi".to_string()),
+            ]
+        );
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn macos_archives_take_the_architecture_from_the_target() {
+        let root = bundle_dir("target");
+        let dir = root.join("aarch64-apple-darwin/release/bundle");
+        mac_app(&dir, "1.2.3");
+        let found = discover_bundles(&dir, "1.2.3").unwrap();
+        assert_eq!(
+            platforms(&found),
+            [("darwin-aarch64", "CommHub.app.tar.gz".to_string())]
+        );
+
+        // An archive of another version is left out
+        assert!(discover_bundles(&dir, "1.2.4").unwrap().is_empty());
+
+        let dir = root.join("universal-apple-darwin/release/bundle");
+        mac_app(&dir, "1.2.3");
+        let found = discover_bundles(&dir, "1.2.3").unwrap();
+        assert_eq!(
+            found
+                .iter()
+                .map(|(platform, _)| platform.as_str())
+                .collect::<Vec<_>>(),
+            ["darwin-aarch64", "darwin-x86_64"]
+        );
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn renders_notes_as_markdown_sections() {
+        let notes = ReleaseNotes {
+            version: "1.2.3".to_string(),
+            summary: "  Faster startup.\n".to_string(),
+            features: vec!["Pop-out chats".to_string()],
+            improvements: Vec::new(),
+            fixes: vec![
+                " Tray icon on Linux ".to_string(),
+                "Search typos".to_string(),
+            ],
+        };
+        assert_eq!(
+            render_notes(&notes),
+            "Faster startup.\n\n### New\n\n- Pop-out chats\n\n### Fixes\n\n- Tray icon on Linux\n- Search typos"
+        );
+
+        let bare = ReleaseNotes {
+            features: Vec::new(),
+            fixes: Vec::new(),
+            ..notes
+        };
+        assert_eq!(render_notes(&bare), "Faster startup.");
+    }
+}
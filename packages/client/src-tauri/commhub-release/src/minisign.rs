@@ -0,0 +1,323 @@
+//! Minisign keys and signatures in the formats `tauri signer` reads and
+//! writes, so keys from either tool work with both.
+//!
+//! A secret key is base64 of a minisign secret key file, as held in
+//! `TAURI_PRIVATE_KEY`, encrypted with scrypt under `TAURI_KEY_PASSWORD`.
+//! Signatures are prehashed (`ED`): ed25519 over the BLAKE2b-512 digest of the
+//! file, so large bundles never need to fit in memory.
+
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine as _;
+use blake2::digest::consts::U32;
+use blake2::{Blake2b, Blake2b512, Digest};
+use ed25519_dalek::{Signer, SigningKey};
+use rand::rngs::OsRng;
+use rand::RngCore;
+
+const SIG_ALG: &[u8; 2] = b"Ed";
+const KDF_ALG: &[u8; 2] = b"Sc";
+// minisign -W writes unencrypted keys with an empty KDF
+const KDF_NONE: &[u8; 2] = &[0, 0];
+const CHK_ALG: &[u8; 2] = b"B2";
+
+const KEY_ID_LEN: usize = 8;
+const SALT_LEN: usize = 32;
+const LIMIT_LEN: usize = 8;
+// key id, ed25519 secret key (seed and public key), checksum
+const KEYNUM_LEN: usize = KEY_ID_LEN + 64 + 32;
+const BOX_LEN: usize = 2 + 2 + 2 + SALT_LEN + LIMIT_LEN * 2 + KEYNUM_LEN;
+
+// What minisign and `tauri signer` encrypt new keys with
+const OPSLIMIT: u64 = 1_048_576;
+const MEMLIMIT: u64 = 33_554_432;
+
+pub struct SecretKey {
+    key_id: [u8; KEY_ID_LEN],
+    signing: SigningKey,
+}
+
+impl SecretKey {
+    pub fn generate() -> Self {
+        let mut key_id = [0u8; KEY_ID_LEN];
+        let mut seed = [0u8; 32];
+        OsRng.fill_bytes(&mut key_id);
+        OsRng.fill_bytes(&mut seed);
+        Self {
+            key_id,
+            signing: SigningKey::from_bytes(&seed),
+        }
+    }
+
+    /// Reads a key from `encode` or `tauri signer generate`.
+    pub fn decode(encoded: &str, password: &str) -> Result<Self, String> {
+        let file = STANDARD
+            .decode(encoded.trim())
+            .ok()
+            .and_then(|bytes| String::from_utf8(bytes).ok())
+            .ok_or("signing key is not a base64 minisign key file")?;
+        // The untrusted comment, then the key
+        let line = file
+            .lines()
+            .nth(1)
+            .ok_or("signing key file has no key line")?;
+        let bytes = STANDARD
+            .decode(line.trim())
+            .map_err(|e| format!("signing key is not base64: {}", e))?;
+        if bytes.len() != BOX_LEN {
+            return Err("signing key has the wrong length".into());
+        }
+
+        let (sig_alg, rest) = bytes.split_at(2);
+        let (kdf_alg, rest) = rest.split_at(2);
+        let (chk_alg, rest) = rest.split_at(2);
+        let (salt, rest) = rest.split_at(SALT_LEN);
+        let (opslimit, rest) = rest.split_at(LIMIT_LEN);
+        let (memlimit, keynum) = rest.split_at(LIMIT_LEN);
+        if sig_alg != SIG_ALG || chk_alg != CHK_ALG {
+            return Err("signing key is not an ed25519 minisign key".into());
+        }
+
+        let mut keynum: [u8; KEYNUM_LEN] = keynum.try_into().unwrap_or([0; KEYNUM_LEN]);
+        if kdf_alg == KDF_ALG {
+            let stream = key_stream(
+                password,
+                salt,
+                u64::from_le_bytes(opslimit.try_into().unwrap_or_default()),
+                u64::from_le_bytes(memlimit.try_into().unwrap_or_default()),
+            )?;
+            keynum
+                .iter_mut()
+                .zip(stream)
+                .for_each(|(byte, key)| *byte ^= key);
+        } else if kdf_alg != KDF_NONE {
+            return Err("signing key is encrypted with an unknown scheme".into());
+        }
+
+        let (key_id, rest) = keynum.split_at(KEY_ID_LEN);
+        let (secret, checksum) = rest.split_at(64);
+        if checksum != key_checksum(key_id, secret).as_slice() {
+            return Err("wrong signing key password, or the key is damaged".into());
+        }
+        let seed: [u8; 32] = secret[..32].try_into().unwrap_or_default();
+        let signing = SigningKey::from_bytes(&seed);
+        if signing.verifying_key().as_bytes() != &secret[32..] {
+            return Err("signing key is damaged".into());
+        }
+
+        Ok(Self {
+            key_id: key_id.try_into().unwrap_or_default(),
+            signing,
+        })
+    }
+
+    /// The value for `TAURI_PRIVATE_KEY`: base64 of a minisign secret key
+    /// file encrypted under `password`.
+    pub fn encode(&self, password: &str) -> Result<String, String> {
+        let mut secret = Vec::with_capacity(64);
+        secret.extend_from_slice(self.signing.as_bytes());
+        secret.extend_from_slice(self.signing.verifying_key().as_bytes());
+
+        let mut keynum = Vec::with_capacity(KEYNUM_LEN);
+        keynum.extend_from_slice(&self.key_id);
+        keynum.extend_from_slice(&secret);
+        keynum.extend_from_slice(&key_checksum(&self.key_id, &secret));
+
+        let mut salt = [0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        let stream = key_stream(password, &salt, OPSLIMIT, MEMLIMIT)?;
+        keynum
+            .iter_mut()
+            .zip(stream)
+            .for_each(|(byte, key)| *byte ^= key);
+
+        let mut raw = Vec::with_capacity(BOX_LEN);
+        raw.extend_from_slice(SIG_ALG);
+        raw.extend_from_slice(KDF_ALG);
+        raw.extend_from_slice(CHK_ALG);
+        raw.extend_from_slice(&salt);
+        raw.extend_from_slice(&OPSLIMIT.to_le_bytes());
+        raw.extend_from_slice(&MEMLIMIT.to_le_bytes());
+        raw.extend_from_slice(&keynum);
+
+        let file = format!(
+            "untrusted comment: rsign encrypted secret key\n{}\n",
+            STANDARD.encode(raw)
+        );
+        Ok(STANDARD.encode(file))
+    }
+
+    /// The value for `tauri.updater.pubkey`: base64 of a minisign public
+    /// key file.
+    pub fn public_key(&self) -> String {
+        let mut raw = Vec::with_capacity(2 + KEY_ID_LEN + 32);
+        raw.extend_from_slice(SIG_ALG);
+        raw.extend_from_slice(&self.key_id);
+        raw.extend_from_slice(self.signing.verifying_key().as_bytes());
+
+        let file = format!(
+            "untrusted comment: minisign public key: {}\n{}\n",
+            self.key_id_hex(),
+            STANDARD.encode(raw)
+        );
+        STANDARD.encode(file)
+    }
+
+    /// Signs `path`, returning base64 of the minisign signature file as it
+    /// goes into latest.json.
+    pub fn sign_file(&self, path: &Path, timestamp: i64) -> std::io::Result<String> {
+        let mut hasher = Blake2b512::new();
+        let mut file = File::open(path)?;
+        let mut buf = vec![0u8; 64 * 1024];
+        loop {
+            let read = file.read(&mut buf)?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&buf[..read]);
+        }
+        let digest = hasher.finalize();
+
+        let signature = self.signing.sign(&digest).to_bytes();
+        let mut raw = Vec::with_capacity(2 + KEY_ID_LEN + signature.len());
+        raw.extend_from_slice(b"ED");
+        raw.extend_from_slice(&self.key_id);
+        raw.extend_from_slice(&signature);
+
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let trusted_comment = format!("timestamp:{}\tfile:{}", timestamp, file_name);
+        // The global signature binds the trusted comment to the signature
+        let mut global = signature.to_vec();
+        global.extend_from_slice(trusted_comment.as_bytes());
+        let global_signature = self.signing.sign(&global).to_bytes();
+
+        let file = format!(
+            "untrusted comment: signature from tauri secret key\n{}\ntrusted comment: {}\n{}\n",
+            STANDARD.encode(raw),
+            trusted_comment,
+            STANDARD.encode(global_signature)
+        );
+        Ok(STANDARD.encode(file))
+    }
+
+    fn key_id_hex(&self) -> String {
+        // minisign prints the little-endian key id
+        self.key_id
+            .iter()
+            .rev()
+            .map(|byte| format!("{:02X}", byte))
+            .collect()
+    }
+}
+
+fn key_checksum(key_id: &[u8], secret: &[u8]) -> [u8; 32] {
+    let mut hasher = Blake2b::<U32>::new();
+    hasher.update(SIG_ALG);
+    hasher.update(key_id);
+    hasher.update(secret);
+    hasher.finalize().into()
+}
+
+/// The scrypt stream the key id and secret key are XORed with.
+fn key_stream(
+    password: &str,
+    salt: &[u8],
+    opslimit: u64,
+    memlimit: u64,
+) -> Result<[u8; KEYNUM_LEN], String> {
+    let (log_n, r, p) = scrypt_params(opslimit, memlimit);
+    let params = scrypt::Params::new(log_n, r, p, scrypt::Params::RECOMMENDED_LEN)
+        .map_err(|_| "signing key has invalid scrypt limits".to_string())?;
+    let mut stream = [0u8; KEYNUM_LEN];
+    scrypt::scrypt(password.as_bytes(), salt, &params, &mut stream)
+        .map_err(|e| format!("failed to derive the key password: {}", e))?;
+    Ok(stream)
+}
+
+/// libsodium's `pickparams`, which minisign uses to turn its ops and memory
+/// limits into scrypt's N, r and p.
+fn scrypt_params(opslimit: u64, memlimit: u64) -> (u8, u32, u32) {
+    let opslimit = opslimit.max(32_768);
+    let r = 8u64;
+    let max_n = if opslimit < memlimit / 32 {
+        opslimit / (r * 4)
+    } else {
+        memlimit / (r * 128)
+    };
+    let mut log_n = 1u8;
+    while log_n < 63 && (1u64 << log_n) <= max_n / 2 {
+        log_n += 1;
+    }
+    let p = if opslimit < memlimit / 32 {
+        1
+    } else {
+        ((opslimit / 4) >> log_n).min(0x3fff_ffff) / r
+    };
+    (log_n, r as u32, p as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    #[test]
+    fn default_limits_pick_minisigns_parameters() {
+        assert_eq!(scrypt_params(OPSLIMIT, MEMLIMIT), (15, 8, 1));
+    }
+
+    #[test]
+    fn keys_round_trip_only_with_the_password() {
+        let key = SecretKey::generate();
+        let encoded = key.encode("hunter2").unwrap();
+
+        let decoded = SecretKey::decode(&encoded, "hunter2").unwrap();
+        assert_eq!(decoded.key_id, key.key_id);
+        assert_eq!(decoded.public_key(), key.public_key());
+        assert!(SecretKey::decode(&encoded, "hunter3").is_err());
+        assert!(SecretKey::decode("not a key", "").is_err());
+    }
+
+    #[test]
+    fn encodes_minisign_secret_key_files() {
+        let encoded = SecretKey::generate().encode("").unwrap();
+        let file = String::from_utf8(STANDARD.decode(encoded).unwrap()).unwrap();
+        let mut lines = file.lines();
+        assert!(lines.next().unwrap().starts_with("untrusted comment: "));
+        let raw = STANDARD.decode(lines.next().unwrap()).unwrap();
+        assert_eq!(raw.len(), BOX_LEN);
+        assert_eq!(&raw[..6], b"EdScB2");
+        assert_eq!(raw[38..46], OPSLIMIT.to_le_bytes());
+        assert_eq!(raw[46..54], MEMLIMIT.to_le_bytes());
+    }
+
+    #[test]
+    fn signatures_verify_with_the_public_key() {
+        let key = SecretKey::generate();
+        let path = std::env::temp_dir().join(format!(
+            "commhub-release-{}-CommHub_1.2.3_x64_en-US.msi",
+            std::process::id()
+        ));
+        std::fs::write(&path, vec![7u8; 100_000]).unwrap();
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+        let signature = key.sign_file(&path, timestamp).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        let text = |encoded: &str| String::from_utf8(STANDARD.decode(encoded).unwrap()).unwrap();
+        let public_key = minisign_verify::PublicKey::decode(&text(&key.public_key())).unwrap();
+        let signature = minisign_verify::Signature::decode(&text(&signature)).unwrap();
+        let mut verifier = public_key.verify_stream(&signature).unwrap();
+        verifier.update(&[7u8; 100_000]);
+        assert!(verifier.finalize().is_ok());
+    }
+}
@@ -0,0 +1,100 @@
+//! Unread attention for the main window: a count badge on the window icon
+//! and a taskbar/dock attention request that escalates for mentions.
+//!
+//! The webview reports unread counts per source (`dm:<userId>`,
+//! `mentions:<channelId>`); [`state`] decides what that means for the window
+//! and this module applies it.
+
+mod badge;
+mod state;
+
+use std::sync::Mutex;
+
+use tauri::{AppHandle, Manager, State, UserAttentionType, Window};
+
+use crate::background::MAIN_WINDOW;
+use state::{Effect, Level, Tracker};
+
+#[derive(Default)]
+pub struct Attention {
+    tracker: Mutex<Tracker>,
+}
+
+impl Attention {
+    /// Called on focus changes; focusing acknowledges any pending request.
+    pub fn set_focused(&self, window: &Window, focused: bool) {
+        let effect = self.tracker.lock().unwrap().set_focused(focused);
+        apply(window, effect);
+    }
+
+    /// Redraws everything on a window that was just created.
+    pub fn restore(&self, window: &Window) {
+        let tracker = self.tracker.lock().unwrap();
+        apply(
+            window,
+            Effect {
+                badge: Some(tracker.total()),
+                level: Some(tracker.level()),
+            },
+        );
+    }
+
+    fn update(&self, app: &AppHandle, change: impl FnOnce(&mut Tracker) -> Effect) {
+        let effect = change(&mut self.tracker.lock().unwrap());
+        // Without a window there's nothing to draw on; `restore` catches up
+        if let Some(window) = app.get_window(MAIN_WINDOW) {
+            apply(&window, effect);
+        }
+    }
+}
+
+fn apply(window: &Window, effect: Effect) {
+    if let Some(count) = effect.badge {
+        let icon = if count == 0 {
+            badge::plain()
+        } else {
+            badge::render(count)
+        };
+        if let Err(e) = window.set_icon(icon) {
+            log!("[Attention] Failed to set window icon: {}", e);
+        }
+    }
+    if let Some(level) = effect.level {
+        let request = match level {
+            Level::Idle => None,
+            Level::Informational => Some(UserAttentionType::Informational),
+            Level::Critical => Some(UserAttentionType::Critical),
+        };
+        if let Err(e) = window.request_user_attention(request) {
+            log!("[Attention] Failed to request attention: {}", e);
+        }
+    }
+}
+
+/// A one-off informational request outside the unread state, for callers
+/// that only want a nudge.
+#[tauri::command]
+pub fn flash_taskbar(window: Window) -> Result<(), String> {
+    window
+        .request_user_attention(Some(UserAttentionType::Informational))
+        .map_err(|e| e.to_string())
+}
+
+/// Sets the unread count for one source. `urgent` sources (direct mentions)
+/// escalate to a critical request when they grow in the background.
+#[tauri::command]
+pub fn attention_set_unread(
+    app: AppHandle,
+    attention: State<'_, Attention>,
+    source: String,
+    count: u32,
+    urgent: bool,
+) {
+    attention.update(&app, |tracker| tracker.set_unread(&source, count, urgent));
+}
+
+/// Clears one source, or all of them when `source` is omitted.
+#[tauri::command]
+pub fn attention_clear(app: AppHandle, attention: State<'_, Attention>, source: Option<String>) {
+    attention.update(&app, |tracker| tracker.clear(source.as_deref()));
+}
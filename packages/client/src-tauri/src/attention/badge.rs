@@ -0,0 +1,80 @@
+//! Window icon overlay: the app icon with the unread count in a red badge.
+//!
+//! Tauri has no cross-platform overlay icon API, so the count is drawn into
+//! the window icon itself, which is what the taskbar and dock switcher show.
+
+use std::sync::OnceLock;
+
+use image::RgbaImage;
+
+use crate::tray::icon::{self, RING, UNREAD, WHITE};
+
+const SIZE: u32 = 64;
+
+// 3x5 glyphs, one row per byte with the leftmost pixel in bit 2
+const DIGITS: [[u8; 5]; 10] = [
+    [0b111, 0b101, 0b101, 0b101, 0b111],
+    [0b010, 0b110, 0b010, 0b010, 0b111],
+    [0b111, 0b001, 0b111, 0b100, 0b111],
+    [0b111, 0b001, 0b111, 0b001, 0b111],
+    [0b101, 0b101, 0b111, 0b001, 0b001],
+    [0b111, 0b100, 0b111, 0b001, 0b111],
+    [0b111, 0b100, 0b111, 0b101, 0b111],
+    [0b111, 0b001, 0b010, 0b010, 0b010],
+    [0b111, 0b101, 0b111, 0b101, 0b111],
+    [0b111, 0b101, 0b111, 0b001, 0b111],
+];
+const PLUS: [u8; 5] = [0b000, 0b010, 0b111, 0b010, 0b000];
+
+static BASE: OnceLock<RgbaImage> = OnceLock::new();
+
+fn base() -> &'static RgbaImage {
+    BASE.get_or_init(|| icon::app_icon(SIZE))
+}
+
+/// The plain app icon, for when nothing is unread.
+pub fn plain() -> tauri::Icon {
+    to_icon(base().clone())
+}
+
+pub fn render(count: u32) -> tauri::Icon {
+    let mut image = base().clone();
+    let (cx, cy) = (44.0, 20.0);
+    icon::fill_circle(&mut image, cx, cy, 20.0, RING);
+    icon::fill_circle(&mut image, cx, cy, 17.0, UNREAD);
+
+    let glyphs: Vec<[u8; 5]> = match count {
+        0..=9 => vec![DIGITS[count as usize]],
+        _ => vec![DIGITS[9], PLUS],
+    };
+    // Single digits get the larger scale; "9+" has to fit in the same disc
+    let scale = if glyphs.len() == 1 { 4.0 } else { 3.0 };
+    let width = glyphs.len() as f32 * 4.0 - 1.0;
+    let mut x = (cx - width * scale / 2.0).round();
+    let y = (cy - 2.5 * scale).round();
+    for glyph in glyphs {
+        draw_glyph(&mut image, glyph, x, y, scale);
+        x += 4.0 * scale;
+    }
+    to_icon(image)
+}
+
+fn draw_glyph(image: &mut RgbaImage, glyph: [u8; 5], x: f32, y: f32, scale: f32) {
+    for (row, bits) in glyph.iter().enumerate() {
+        for column in 0..3 {
+            if bits & (0b100 >> column) != 0 {
+                let px = x + column as f32 * scale;
+                let py = y + row as f32 * scale;
+                icon::fill_rect(image, px, py, scale, scale, WHITE);
+            }
+        }
+    }
+}
+
+fn to_icon(image: RgbaImage) -> tauri::Icon {
+    tauri::Icon::Rgba {
+        rgba: image.into_raw(),
+        width: SIZE,
+        height: SIZE,
+    }
+}
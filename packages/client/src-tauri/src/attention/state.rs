@@ -0,0 +1,220 @@
+//! The attention state machine, kept apart from the window so it can be
+//! driven directly.
+//!
+//! Unread counts are tracked per source (a DM conversation, a channel's
+//! mentions). When a count grows while the window is unfocused the level
+//! rises to `Informational`, or `Critical` for urgent sources such as direct
+//! mentions. It never drops on its own except to `Idle`: when the window is
+//! focused, or when nothing is left unread.
+
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    #[default]
+    Idle,
+    Informational,
+    Critical,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Unread {
+    count: u32,
+    urgent: bool,
+}
+
+/// What changed and has to be applied to the window.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Effect {
+    pub badge: Option<u32>,
+    pub level: Option<Level>,
+}
+
+#[derive(Debug, Default)]
+pub struct Tracker {
+    sources: BTreeMap<String, Unread>,
+    level: Level,
+    focused: bool,
+}
+
+impl Tracker {
+    pub fn total(&self) -> u32 {
+        self.sources
+            .values()
+            .map(|unread| unread.count)
+            .fold(0, u32::saturating_add)
+    }
+
+    pub fn level(&self) -> Level {
+        self.level
+    }
+
+    pub fn set_unread(&mut self, source: &str, count: u32, urgent: bool) -> Effect {
+        let before = (self.total(), self.level);
+        let previous = self.sources.get(source).map_or(0, |unread| unread.count);
+
+        if count == 0 {
+            self.sources.remove(source);
+        } else {
+            self.sources
+                .insert(source.to_string(), Unread { count, urgent });
+        }
+
+        if count > previous && !self.focused {
+            let wanted = if urgent {
+                Level::Critical
+            } else {
+                Level::Informational
+            };
+            self.level = self.level.max(wanted);
+        }
+        self.settle(before)
+    }
+
+    /// Clears one source, or everything when `source` is `None`.
+    pub fn clear(&mut self, source: Option<&str>) -> Effect {
+        let before = (self.total(), self.level);
+        match source {
+            Some(source) => {
+                self.sources.remove(source);
+            }
+            None => {
+                self.sources.clear();
+                self.level = Level::Idle;
+            }
+        }
+        self.settle(before)
+    }
+
+    /// Focusing the window acknowledges the request; the badge stays until
+    /// the messages are read.
+    pub fn set_focused(&mut self, focused: bool) -> Effect {
+        let before = (self.total(), self.level);
+        self.focused = focused;
+        if focused {
+            self.level = Level::Idle;
+        }
+        self.settle(before)
+    }
+
+    fn settle(&mut self, (total, level): (u32, Level)) -> Effect {
+        if self.sources.is_empty() {
+            self.level = Level::Idle;
+        } else if self.level == Level::Critical
+            && !self.sources.values().any(|unread| unread.urgent)
+        {
+            // The mention that escalated was read; keep asking, but quietly
+            self.level = Level::Informational;
+        }
+        Effect {
+            badge: (self.total() != total).then(|| self.total()),
+            level: (self.level != level).then_some(self.level),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn effect(badge: Option<u32>, level: Option<Level>) -> Effect {
+        Effect { badge, level }
+    }
+
+    #[test]
+    fn unread_while_blurred_asks_for_attention() {
+        let mut tracker = Tracker::default();
+        tracker.set_focused(false);
+
+        assert_eq!(
+            tracker.set_unread("dm:1", 2, false),
+            effect(Some(2), Some(Level::Informational))
+        );
+        // Already asking; only the badge moves
+        assert_eq!(
+            tracker.set_unread("channel:4", 1, false),
+            effect(Some(3), None)
+        );
+        assert_eq!(tracker.total(), 3);
+        assert_eq!(tracker.level(), Level::Informational);
+    }
+
+    #[test]
+    fn unread_while_focused_only_updates_the_badge() {
+        let mut tracker = Tracker::default();
+        tracker.set_focused(true);
+
+        assert_eq!(tracker.set_unread("dm:1", 1, true), effect(Some(1), None));
+        assert_eq!(tracker.level(), Level::Idle);
+
+        // Blurring doesn't raise the level for messages already seen
+        assert_eq!(tracker.set_focused(false), effect(None, None));
+        assert_eq!(tracker.set_unread("dm:1", 1, true), effect(None, None));
+    }
+
+    #[test]
+    fn mentions_escalate_and_settle_back() {
+        let mut tracker = Tracker::default();
+
+        tracker.set_unread("dm:1", 1, false);
+        assert_eq!(
+            tracker.set_unread("mention:7", 1, true),
+            effect(Some(2), Some(Level::Critical))
+        );
+        // A later ordinary message doesn't lower it
+        assert_eq!(tracker.set_unread("dm:1", 2, false), effect(Some(3), None));
+        assert_eq!(tracker.level(), Level::Critical);
+
+        // The mention was read; the DM still wants attention, quietly
+        assert_eq!(
+            tracker.set_unread("mention:7", 0, true),
+            effect(Some(2), Some(Level::Informational))
+        );
+        assert_eq!(
+            tracker.clear(Some("dm:1")),
+            effect(Some(0), Some(Level::Idle))
+        );
+    }
+
+    #[test]
+    fn focus_resets_the_level_but_keeps_the_badge() {
+        let mut tracker = Tracker::default();
+        tracker.set_unread("mention:7", 3, true);
+        assert_eq!(tracker.level(), Level::Critical);
+
+        assert_eq!(tracker.set_focused(true), effect(None, Some(Level::Idle)));
+        assert_eq!(tracker.total(), 3);
+
+        // New messages after blurring again ask afresh
+        tracker.set_focused(false);
+        assert_eq!(
+            tracker.set_unread("mention:7", 4, true),
+            effect(Some(4), Some(Level::Critical))
+        );
+    }
+
+    #[test]
+    fn badge_counts_every_source() {
+        let mut tracker = Tracker::default();
+        tracker.set_unread("dm:1", 2, false);
+        tracker.set_unread("dm:2", 5, false);
+        tracker.set_unread("mention:7", 1, true);
+        assert_eq!(tracker.total(), 8);
+
+        // Lowering a count doesn't change the level, only the badge
+        assert_eq!(tracker.set_unread("dm:2", 1, false), effect(Some(4), None));
+        assert_eq!(tracker.set_unread("dm:2", 1, false), effect(None, None));
+        assert_eq!(tracker.clear(Some("unknown")), effect(None, None));
+
+        assert_eq!(tracker.clear(None), effect(Some(0), Some(Level::Idle)));
+        assert_eq!(tracker.total(), 0);
+    }
+
+    #[test]
+    fn badge_total_saturates() {
+        let mut tracker = Tracker::default();
+        tracker.set_unread("dm:1", u32::MAX, false);
+        tracker.set_unread("dm:2", 5, false);
+        assert_eq!(tracker.total(), u32::MAX);
+    }
+}
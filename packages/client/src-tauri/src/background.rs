@@ -0,0 +1,309 @@
+//! Background mode: what closing the main window does, and what keeps working
+//! once it's gone.
+//!
+//! The socket, cache, tray and notifications live in the backend, so the
+//! session survives the webview. With the `destroy` policy the webview is torn
+//! down to save memory until the tray or a notification brings it back.
+
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tauri::{
+    AppHandle, GlobalWindowEvent, Manager, RunEvent, State, Window, WindowBuilder, WindowEvent,
+};
+
+use crate::attention::Attention;
+use crate::cache::Cache;
+use crate::clipboard::Clipboard;
+use crate::deeplink::DeepLinks;
+use crate::downloads::Downloads;
+use crate::socket::SocketManager;
+use crate::tray;
+use crate::uploads::Uploads;
+use crate::window_state::WindowState;
+
+pub const MAIN_WINDOW: &str = "main";
+
+const SETTINGS_FILE: &str = "background.json";
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("background settings io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("background settings are malformed: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("app directories are unavailable")]
+    NoAppDir,
+}
+
+/// What closing the main window does.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ClosePolicy {
+    /// Shut down gracefully and exit, as closing always did.
+    #[default]
+    Quit,
+    /// Hide the window; the webview keeps running.
+    Hide,
+    /// Destroy the webview and keep running in the tray.
+    Destroy,
+}
+
+/// Mirrors the background fields of `UserSettings` in stores/settings.ts.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct BackgroundSettings {
+    pub close_policy: ClosePolicy,
+    pub notifications: bool,
+}
+
+impl Default for BackgroundSettings {
+    fn default() -> Self {
+        Self {
+            close_policy: ClosePolicy::default(),
+            notifications: true,
+        }
+    }
+}
+
+/// A request the close policy answers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Request {
+    /// The user closed the main window.
+    CloseMain,
+    /// The last window closed, or `quit` is already shutting down.
+    Exit { quitting: bool },
+}
+
+/// How to answer a [`Request`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Response {
+    Allow,
+    /// Refuse it and keep running in the background.
+    Prevent,
+    /// Refuse it and hide the main window instead.
+    Hide,
+    /// Refuse it and shut down gracefully instead.
+    Quit,
+}
+
+fn respond(policy: ClosePolicy, request: Request) -> Response {
+    match (request, policy) {
+        (Request::CloseMain, ClosePolicy::Quit) => Response::Quit,
+        (Request::CloseMain, ClosePolicy::Hide) => Response::Hide,
+        // Let it close; the exit that follows is prevented
+        (Request::CloseMain, ClosePolicy::Destroy) => Response::Allow,
+        (Request::Exit { quitting: true }, _) | (Request::Exit { .. }, ClosePolicy::Quit) => {
+            Response::Allow
+        }
+        (Request::Exit { .. }, _) => Response::Prevent,
+    }
+}
+
+pub struct Background {
+    path: PathBuf,
+    settings: Mutex<BackgroundSettings>,
+    quitting: AtomicBool,
+}
+
+impl Background {
+    pub fn new(path: PathBuf) -> Self {
+        // A missing or unreadable file just means the defaults
+        let settings = fs::read(&path)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .unwrap_or_default();
+        Self {
+            path,
+            settings: Mutex::new(settings),
+            quitting: AtomicBool::new(false),
+        }
+    }
+
+    pub fn from_app(app: &AppHandle) -> Result<Self, Error> {
+        let dir = app
+            .path_resolver()
+            .app_config_dir()
+            .ok_or(Error::NoAppDir)?;
+        Ok(Self::new(dir.join(SETTINGS_FILE)))
+    }
+
+    pub fn settings(&self) -> BackgroundSettings {
+        *self.settings.lock().unwrap()
+    }
+
+    /// Persisted right away: the policy has to be known at the next launch
+    /// before the webview has loaded.
+    pub fn set_settings(&self, settings: BackgroundSettings) -> Result<(), Error> {
+        let mut current = self.settings.lock().unwrap();
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&self.path, serde_json::to_vec_pretty(&settings)?)?;
+        *current = settings;
+        Ok(())
+    }
+
+    fn is_quitting(&self) -> bool {
+        self.quitting.load(Ordering::SeqCst)
+    }
+}
+
+/// Returns the main window, rebuilding it from tauri.conf.json if the
+/// `destroy` policy closed it.
+pub fn main_window(app: &AppHandle) -> Option<Window> {
+    if let Some(window) = app.get_window(MAIN_WINDOW) {
+        return Some(window);
+    }
+    let config = app
+        .config()
+        .tauri
+        .windows
+        .iter()
+        .find(|window| window.label == MAIN_WINDOW)?
+        .clone();
+    match WindowBuilder::from_config(app, config).build() {
+        Ok(window) => {
+            app.state::<WindowState>().restore(&window);
+            app.state::<Attention>().restore(&window);
+            Some(window)
+        }
+        Err(e) => {
+            log!("[Background] Failed to recreate main window: {}", e);
+            None
+        }
+    }
+}
+
+pub fn handle_window_event(event: GlobalWindowEvent) {
+    let window = event.window();
+    if window.label() != MAIN_WINDOW {
+        return;
+    }
+    let app = window.app_handle();
+    match event.event() {
+        WindowEvent::CloseRequested { api, .. } => {
+            let policy = app.state::<Background>().settings().close_policy;
+            match respond(policy, Request::CloseMain) {
+                Response::Allow => {}
+                Response::Prevent => api.prevent_close(),
+                Response::Hide => {
+                    api.prevent_close();
+                    let _ = window.hide();
+                    tray::sync_visibility(&app);
+                }
+                Response::Quit => {
+                    api.prevent_close();
+                    quit(&app);
+                }
+            }
+        }
+        WindowEvent::Focused(focused) => app.state::<Attention>().set_focused(window, *focused),
+        WindowEvent::Destroyed => {
+            app.state::<DeepLinks>().reset();
+            tray::sync_visibility(&app);
+        }
+        _ => {}
+    }
+}
+
+pub fn handle_run_event(app: &AppHandle, event: RunEvent) {
+    match event {
+        // Fired when the last window closes
+        RunEvent::ExitRequested { api, .. } => {
+            let background = app.state::<Background>();
+            let request = Request::Exit {
+                quitting: background.is_quitting(),
+            };
+            if respond(background.settings().close_policy, request) != Response::Allow {
+                api.prevent_exit();
+            }
+        }
+        RunEvent::Exit => shutdown(app),
+        _ => {}
+    }
+}
+
+/// Says goodbye to the server and closes the cache so the WAL is
+/// checkpointed. Safe to call more than once.
+pub fn shutdown(app: &AppHandle) {
+    let background = app.state::<Background>();
+    if background.quitting.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    app.state::<Uploads>().cancel_all();
+    app.state::<Downloads>().cancel_all();
+    app.state::<SocketManager>().shutdown(SHUTDOWN_TIMEOUT);
+    app.state::<Cache>().close();
+    app.state::<Clipboard>().close();
+    if let Err(e) = app.state::<WindowState>().save() {
+        log!("[WindowState] Failed to save window state: {}", e);
+    }
+}
+
+/// `AppHandle::exit` skips `RunEvent::Exit`, so shut down first.
+pub fn quit(app: &AppHandle) {
+    shutdown(app);
+    app.exit(0);
+}
+
+#[tauri::command]
+pub fn background_get_settings(background: State<'_, Background>) -> BackgroundSettings {
+    background.settings()
+}
+
+#[tauri::command]
+pub fn background_set_settings(
+    background: State<'_, Background>,
+    settings: BackgroundSettings,
+) -> Result<(), String> {
+    background.set_settings(settings).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn close_policy_answers_close_and_exit_requests() {
+        let running = Request::Exit { quitting: false };
+        let quitting = Request::Exit { quitting: true };
+        let cases: &[(ClosePolicy, Request, Response)] = &[
+            (ClosePolicy::Quit, Request::CloseMain, Response::Quit),
+            (ClosePolicy::Hide, Request::CloseMain, Response::Hide),
+            (ClosePolicy::Destroy, Request::CloseMain, Response::Allow),
+            // Closing the last window doesn't end a background session
+            (ClosePolicy::Quit, running, Response::Allow),
+            (ClosePolicy::Hide, running, Response::Prevent),
+            (ClosePolicy::Destroy, running, Response::Prevent),
+            // Quitting from the tray or menu always gets through
+            (ClosePolicy::Quit, quitting, Response::Allow),
+            (ClosePolicy::Hide, quitting, Response::Allow),
+            (ClosePolicy::Destroy, quitting, Response::Allow),
+        ];
+        for (policy, request, expected) in cases {
+            assert_eq!(
+                respond(*policy, *request),
+                *expected,
+                "{:?} {:?}",
+                policy,
+                request
+            );
+        }
+    }
+
+    #[test]
+    fn settings_default_to_quitting_with_notifications() {
+        let settings: BackgroundSettings = serde_json::from_str("{}").unwrap();
+        assert_eq!(settings.close_policy, ClosePolicy::Quit);
+        assert!(settings.notifications);
+        let settings: BackgroundSettings =
+            serde_json::from_str(r#"{"closePolicy":"destroy"}"#).unwrap();
+        assert_eq!(settings.close_policy, ClosePolicy::Destroy);
+    }
+}
@@ -0,0 +1,961 @@
+//! On-disk cache of channel history, DMs, attachment metadata and users.
+//!
+//! One SQLite database per signed-in account lives under
+//! `$APPLOCALDATA/cache`, so the webview can paint history before the network
+//! answers and stays readable offline. Query commands mirror the REST
+//! pagination (`before` cursor plus `limit`) and return the same JSON shapes
+//! as `apiService`.
+
+mod migrations;
+
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use rusqlite::{params, Connection, OptionalExtension, Row, Transaction};
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, State};
+
+const DEFAULT_LIMIT: u32 = 50;
+// Same cap the server applies to history requests
+const MAX_LIMIT: u32 = 100;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("cache database error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+    #[error("cache io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("cache data is malformed: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error(transparent)]
+    Migration(#[from] migrations::Error),
+    #[error("cache is not open")]
+    NotOpen,
+    #[error("app directories are unavailable")]
+    NoAppDir,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserRef {
+    pub id: i64,
+    pub username: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChannelRef {
+    pub id: i64,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Attachment {
+    pub id: i64,
+    pub url: String,
+    pub filename: String,
+    pub mime_type: String,
+    pub size: i64,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplyTo {
+    pub id: i64,
+    pub content: String,
+    pub user: UserRef,
+}
+
+/// Mirrors `Message` in api.ts.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChannelMessage {
+    pub id: i64,
+    pub content: String,
+    pub user_id: i64,
+    pub channel_id: i64,
+    pub created_at: String,
+    #[serde(default)]
+    pub is_edited: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub edited_at: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reply_to_id: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reply_to: Option<ReplyTo>,
+    pub user: UserRef,
+    pub channel: ChannelRef,
+    #[serde(default)]
+    pub attachments: Vec<Attachment>,
+}
+
+/// Mirrors `DirectMessage` in api.ts.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DirectMessage {
+    pub id: i64,
+    pub content: String,
+    pub sender_id: i64,
+    pub receiver_id: i64,
+    pub created_at: String,
+    #[serde(default)]
+    pub is_edited: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub edited_at: Option<String>,
+    #[serde(default)]
+    pub is_read: bool,
+    pub sender: UserRef,
+    pub receiver: UserRef,
+    #[serde(default)]
+    pub attachments: Vec<Attachment>,
+}
+
+/// Mirrors `User` in api.ts.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedUser {
+    pub id: i64,
+    pub username: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+}
+
+/// Mirrors `WSMessage` in websocket.ts. The `message` socket event flattens
+/// the author and leaves out the channel name.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SocketMessage {
+    pub id: i64,
+    pub content: String,
+    pub user_id: i64,
+    pub username: String,
+    pub channel_id: i64,
+    pub created_at: String,
+    #[serde(default)]
+    pub is_edited: bool,
+    #[serde(default)]
+    pub edited_at: Option<String>,
+    #[serde(default)]
+    pub reply_to: Option<ReplyTo>,
+    #[serde(default)]
+    pub attachments: Vec<Attachment>,
+}
+
+impl SocketMessage {
+    fn into_channel_message(self, channel_name: String) -> ChannelMessage {
+        ChannelMessage {
+            id: self.id,
+            content: self.content,
+            user_id: self.user_id,
+            channel_id: self.channel_id,
+            created_at: self.created_at,
+            is_edited: self.is_edited,
+            edited_at: self.edited_at,
+            reply_to_id: self.reply_to.as_ref().map(|reply| reply.id),
+            reply_to: self.reply_to,
+            user: UserRef {
+                id: self.user_id,
+                username: self.username,
+            },
+            channel: ChannelRef {
+                id: self.channel_id,
+                name: channel_name,
+            },
+            attachments: self.attachments,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum MessageKind {
+    Channel,
+    Direct,
+}
+
+impl MessageKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            MessageKind::Channel => "channel",
+            MessageKind::Direct => "direct",
+        }
+    }
+}
+
+struct OpenCache {
+    account_id: i64,
+    conn: Connection,
+}
+
+pub struct Cache {
+    root: PathBuf,
+    app_version: Version,
+    open: Mutex<Option<OpenCache>>,
+}
+
+impl Cache {
+    pub fn new(root: PathBuf, app_version: Version) -> Self {
+        Self {
+            root,
+            app_version,
+            open: Mutex::new(None),
+        }
+    }
+
+    /// An open cache for `account_id` that lives only in memory.
+    #[cfg(test)]
+    pub(crate) fn in_memory(account_id: i64) -> Self {
+        let version = Version::new(99, 0, 0);
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrations::run(&mut conn, &version).unwrap();
+        Self {
+            root: PathBuf::new(),
+            app_version: version,
+            open: Mutex::new(Some(OpenCache { account_id, conn })),
+        }
+    }
+
+    pub fn from_app(app: &AppHandle) -> Result<Self, Error> {
+        let root = app
+            .path_resolver()
+            .app_local_data_dir()
+            .ok_or(Error::NoAppDir)?
+            .join("cache");
+        Ok(Self::new(root, app.package_info().version.clone()))
+    }
+
+    /// Opens (creating if needed) the cache for `user_id`, replacing whatever
+    /// account was open before.
+    pub fn open(&self, user_id: i64) -> Result<(), Error> {
+        fs::create_dir_all(&self.root)?;
+        let path = self.root.join(format!("{}.sqlite3", user_id));
+
+        let conn = match self.connect(&path) {
+            Err(Error::Migration(migrations::Error::NewerSchema(version))) => {
+                log!(
+                    "[Cache] Discarding cache written by CommHub {} (running {})",
+                    version,
+                    self.app_version
+                );
+                for suffix in ["", "-wal", "-shm"] {
+                    let _ = fs::remove_file(format!("{}{}", path.display(), suffix));
+                }
+                self.connect(&path)?
+            }
+            other => other?,
+        };
+
+        *self.open.lock().unwrap() = Some(OpenCache {
+            account_id: user_id,
+            conn,
+        });
+        Ok(())
+    }
+
+    pub fn close(&self) {
+        self.open.lock().unwrap().take();
+    }
+
+    /// The signed-in user whose cache is open.
+    pub fn account_id(&self) -> Option<i64> {
+        self.open
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|open| open.account_id)
+    }
+
+    fn connect(&self, path: &PathBuf) -> Result<Connection, Error> {
+        let mut conn = Connection::open(path)?;
+        conn.execute_batch("PRAGMA journal_mode = WAL; PRAGMA synchronous = NORMAL;")?;
+        migrations::run(&mut conn, &self.app_version)?;
+        Ok(conn)
+    }
+
+    pub(crate) fn with_conn<T>(
+        &self,
+        f: impl FnOnce(&mut Connection) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        let mut guard = self.open.lock().unwrap();
+        let open = guard.as_mut().ok_or(Error::NotOpen)?;
+        f(&mut open.conn)
+    }
+
+    pub fn store_channel_messages(&self, messages: &[ChannelMessage]) -> Result<(), Error> {
+        self.with_conn(|conn| {
+            let tx = conn.transaction()?;
+            for message in messages {
+                upsert_user_ref(&tx, &message.user)?;
+                tx.execute(
+                    "INSERT INTO channel_messages
+                        (id, channel_id, channel_name, user_id, content, created_at,
+                         is_edited, edited_at, reply_to_id, reply_to)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+                     ON CONFLICT (id) DO UPDATE SET
+                        channel_name = excluded.channel_name,
+                        content = excluded.content,
+                        is_edited = excluded.is_edited,
+                        edited_at = excluded.edited_at,
+                        reply_to_id = excluded.reply_to_id,
+                        reply_to = excluded.reply_to",
+                    params![
+                        message.id,
+                        message.channel_id,
+                        message.channel.name,
+                        message.user_id,
+                        message.content,
+                        message.created_at,
+                        message.is_edited,
+                        message.edited_at,
+                        message.reply_to_id,
+                        message
+                            .reply_to
+                            .as_ref()
+                            .map(serde_json::to_string)
+                            .transpose()?,
+                    ],
+                )?;
+                replace_attachments(&tx, MessageKind::Channel, message.id, &message.attachments)?;
+            }
+            tx.commit()?;
+            Ok(())
+        })
+    }
+
+    /// Stores a live `message` event, borrowing the channel name from history
+    /// already on disk; the next REST fetch fills it in otherwise.
+    pub fn store_socket_message(&self, message: SocketMessage) -> Result<(), Error> {
+        let channel_name: Option<String> = self.with_conn(|conn| {
+            Ok(conn
+                .query_row(
+                    "SELECT channel_name FROM channel_messages
+                     WHERE channel_id = ?1 AND channel_name <> '' LIMIT 1",
+                    [message.channel_id],
+                    |row| row.get(0),
+                )
+                .optional()?)
+        })?;
+        self.store_channel_messages(&[
+            message.into_channel_message(channel_name.unwrap_or_default())
+        ])
+    }
+
+    pub fn store_direct_messages(&self, messages: &[DirectMessage]) -> Result<(), Error> {
+        self.with_conn(|conn| {
+            let tx = conn.transaction()?;
+            for message in messages {
+                upsert_user_ref(&tx, &message.sender)?;
+                upsert_user_ref(&tx, &message.receiver)?;
+                tx.execute(
+                    "INSERT INTO direct_messages
+                        (id, sender_id, receiver_id, content, created_at,
+                         is_edited, edited_at, is_read)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+                     ON CONFLICT (id) DO UPDATE SET
+                        content = excluded.content,
+                        is_edited = excluded.is_edited,
+                        edited_at = excluded.edited_at,
+                        is_read = excluded.is_read",
+                    params![
+                        message.id,
+                        message.sender_id,
+                        message.receiver_id,
+                        message.content,
+                        message.created_at,
+                        message.is_edited,
+                        message.edited_at,
+                        message.is_read,
+                    ],
+                )?;
+                replace_attachments(&tx, MessageKind::Direct, message.id, &message.attachments)?;
+            }
+            tx.commit()?;
+            Ok(())
+        })
+    }
+
+    pub fn store_users(&self, users: &[CachedUser]) -> Result<(), Error> {
+        self.with_conn(|conn| {
+            let tx = conn.transaction()?;
+            for user in users {
+                tx.execute(
+                    "INSERT INTO users (id, username, email, status, updated_at)
+                     VALUES (?1, ?2, ?3, ?4, ?5)
+                     ON CONFLICT (id) DO UPDATE SET
+                        username = excluded.username,
+                        email = COALESCE(excluded.email, users.email),
+                        status = COALESCE(excluded.status, users.status),
+                        updated_at = excluded.updated_at",
+                    params![user.id, user.username, user.email, user.status, now_secs()],
+                )?;
+            }
+            tx.commit()?;
+            Ok(())
+        })
+    }
+
+    pub fn delete_channel_message(&self, id: i64) -> Result<(), Error> {
+        self.delete_message(MessageKind::Channel, id)
+    }
+
+    pub fn delete_direct_message(&self, id: i64) -> Result<(), Error> {
+        self.delete_message(MessageKind::Direct, id)
+    }
+
+    fn delete_message(&self, kind: MessageKind, id: i64) -> Result<(), Error> {
+        self.with_conn(|conn| {
+            let tx = conn.transaction()?;
+            let table = match kind {
+                MessageKind::Channel => "channel_messages",
+                MessageKind::Direct => "direct_messages",
+            };
+            tx.execute(&format!("DELETE FROM {} WHERE id = ?1", table), [id])?;
+            replace_attachments(&tx, kind, id, &[])?;
+            tx.commit()?;
+            Ok(())
+        })
+    }
+
+    /// Newest `limit` messages older than `before`, oldest first, like
+    /// `GET /channels/:id/messages`.
+    pub fn channel_messages(
+        &self,
+        channel_id: i64,
+        before: Option<i64>,
+        limit: Option<u32>,
+    ) -> Result<Vec<ChannelMessage>, Error> {
+        self.with_conn(|conn| {
+            let mut stmt = conn.prepare_cached(
+                "SELECT m.id, m.content, m.user_id, m.channel_id, m.created_at, m.is_edited,
+                        m.edited_at, m.reply_to_id, m.reply_to, m.channel_name,
+                        COALESCE(u.username, '')
+                 FROM channel_messages m
+                 LEFT JOIN users u ON u.id = m.user_id
+                 WHERE m.channel_id = ?1 AND (?2 IS NULL OR m.id < ?2)
+                 ORDER BY m.id DESC
+                 LIMIT ?3",
+            )?;
+            let mut messages = stmt
+                .query_map(params![channel_id, before, clamp_limit(limit)], |row| {
+                    channel_message_from_row(row)
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+            drop(stmt);
+
+            messages.reverse();
+            for message in &mut messages {
+                message.attachments = load_attachments(conn, MessageKind::Channel, message.id)?;
+            }
+            Ok(messages)
+        })
+    }
+
+    /// Conversation with `user_id`, paginated the same way as channel history.
+    pub fn conversation(
+        &self,
+        user_id: i64,
+        before: Option<i64>,
+        limit: Option<u32>,
+    ) -> Result<Vec<DirectMessage>, Error> {
+        self.with_conn(|conn| {
+            let mut stmt = conn.prepare_cached(
+                "SELECT m.id, m.content, m.sender_id, m.receiver_id, m.created_at, m.is_edited,
+                        m.edited_at, m.is_read,
+                        COALESCE(s.username, ''), COALESCE(r.username, '')
+                 FROM direct_messages m
+                 LEFT JOIN users s ON s.id = m.sender_id
+                 LEFT JOIN users r ON r.id = m.receiver_id
+                 WHERE (m.sender_id = ?1 OR m.receiver_id = ?1)
+                   AND (?2 IS NULL OR m.id < ?2)
+                 ORDER BY m.id DESC
+                 LIMIT ?3",
+            )?;
+            let mut messages = stmt
+                .query_map(params![user_id, before, clamp_limit(limit)], |row| {
+                    direct_message_from_row(row)
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+            drop(stmt);
+
+            messages.reverse();
+            for message in &mut messages {
+                message.attachments = load_attachments(conn, MessageKind::Direct, message.id)?;
+            }
+            Ok(messages)
+        })
+    }
+
+    pub fn user(&self, id: i64) -> Result<Option<CachedUser>, Error> {
+        self.with_conn(|conn| {
+            Ok(conn
+                .query_row(
+                    "SELECT id, username, email, status FROM users WHERE id = ?1",
+                    [id],
+                    |row| {
+                        Ok(CachedUser {
+                            id: row.get(0)?,
+                            username: row.get(1)?,
+                            email: row.get(2)?,
+                            status: row.get(3)?,
+                        })
+                    },
+                )
+                .optional()?)
+        })
+    }
+}
+
+fn clamp_limit(limit: Option<u32>) -> u32 {
+    limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT)
+}
+
+fn now_secs() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+// Message payloads only carry id and username; keep richer fields from store_users
+fn upsert_user_ref(tx: &Transaction, user: &UserRef) -> rusqlite::Result<()> {
+    tx.execute(
+        "INSERT INTO users (id, username, updated_at) VALUES (?1, ?2, ?3)
+         ON CONFLICT (id) DO UPDATE SET username = excluded.username",
+        params![user.id, user.username, now_secs()],
+    )?;
+    Ok(())
+}
+
+fn replace_attachments(
+    tx: &Transaction,
+    kind: MessageKind,
+    message_id: i64,
+    attachments: &[Attachment],
+) -> rusqlite::Result<()> {
+    tx.execute(
+        "DELETE FROM attachments WHERE message_kind = ?1 AND message_id = ?2",
+        params![kind.as_str(), message_id],
+    )?;
+    for attachment in attachments {
+        tx.execute(
+            "INSERT OR REPLACE INTO attachments
+                (id, message_kind, message_id, url, filename, mime_type, size, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                attachment.id,
+                kind.as_str(),
+                message_id,
+                attachment.url,
+                attachment.filename,
+                attachment.mime_type,
+                attachment.size,
+                attachment.created_at,
+            ],
+        )?;
+    }
+    Ok(())
+}
+
+fn load_attachments(
+    conn: &Connection,
+    kind: MessageKind,
+    message_id: i64,
+) -> rusqlite::Result<Vec<Attachment>> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT id, url, filename, mime_type, size, created_at
+         FROM attachments WHERE message_kind = ?1 AND message_id = ?2
+         ORDER BY id",
+    )?;
+    let rows = stmt.query_map(params![kind.as_str(), message_id], |row| {
+        Ok(Attachment {
+            id: row.get(0)?,
+            url: row.get(1)?,
+            filename: row.get(2)?,
+            mime_type: row.get(3)?,
+            size: row.get(4)?,
+            created_at: row.get(5)?,
+        })
+    })?;
+    rows.collect()
+}
+
+fn channel_message_from_row(row: &Row) -> rusqlite::Result<ChannelMessage> {
+    let user_id: i64 = row.get(2)?;
+    let channel_id: i64 = row.get(3)?;
+    let reply_to: Option<String> = row.get(8)?;
+    Ok(ChannelMessage {
+        id: row.get(0)?,
+        content: row.get(1)?,
+        user_id,
+        channel_id,
+        created_at: row.get(4)?,
+        is_edited: row.get(5)?,
+        edited_at: row.get(6)?,
+        reply_to_id: row.get(7)?,
+        // A snapshot that no longer parses just renders without the quote
+        reply_to: reply_to.and_then(|json| serde_json::from_str(&json).ok()),
+        user: UserRef {
+            id: user_id,
+            username: row.get(10)?,
+        },
+        channel: ChannelRef {
+            id: channel_id,
+            name: row.get(9)?,
+        },
+        attachments: Vec::new(),
+    })
+}
+
+fn direct_message_from_row(row: &Row) -> rusqlite::Result<DirectMessage> {
+    let sender_id: i64 = row.get(2)?;
+    let receiver_id: i64 = row.get(3)?;
+    Ok(DirectMessage {
+        id: row.get(0)?,
+        content: row.get(1)?,
+        sender_id,
+        receiver_id,
+        created_at: row.get(4)?,
+        is_edited: row.get(5)?,
+        edited_at: row.get(6)?,
+        is_read: row.get(7)?,
+        sender: UserRef {
+            id: sender_id,
+            username: row.get(8)?,
+        },
+        receiver: UserRef {
+            id: receiver_id,
+            username: row.get(9)?,
+        },
+        attachments: Vec::new(),
+    })
+}
+
+#[tauri::command]
+pub fn cache_open(cache: State<'_, Cache>, user_id: i64) -> Result<(), String> {
+    cache.open(user_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn cache_close(cache: State<'_, Cache>) {
+    cache.close();
+}
+
+#[tauri::command]
+pub fn cache_store_channel_messages(
+    cache: State<'_, Cache>,
+    messages: Vec<ChannelMessage>,
+) -> Result<(), String> {
+    cache
+        .store_channel_messages(&messages)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn cache_store_socket_message(
+    cache: State<'_, Cache>,
+    message: SocketMessage,
+) -> Result<(), String> {
+    cache
+        .store_socket_message(message)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn cache_store_direct_messages(
+    cache: State<'_, Cache>,
+    messages: Vec<DirectMessage>,
+) -> Result<(), String> {
+    cache
+        .store_direct_messages(&messages)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn cache_store_users(cache: State<'_, Cache>, users: Vec<CachedUser>) -> Result<(), String> {
+    cache.store_users(&users).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn cache_delete_channel_message(cache: State<'_, Cache>, id: i64) -> Result<(), String> {
+    cache.delete_channel_message(id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn cache_delete_direct_message(cache: State<'_, Cache>, id: i64) -> Result<(), String> {
+    cache.delete_direct_message(id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn cache_get_channel_messages(
+    cache: State<'_, Cache>,
+    channel_id: i64,
+    before: Option<i64>,
+    limit: Option<u32>,
+) -> Result<Vec<ChannelMessage>, String> {
+    cache
+        .channel_messages(channel_id, before, limit)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn cache_get_conversation(
+    cache: State<'_, Cache>,
+    user_id: i64,
+    before: Option<i64>,
+    limit: Option<u32>,
+) -> Result<Vec<DirectMessage>, String> {
+    cache
+        .conversation(user_id, before, limit)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn cache_get_user(cache: State<'_, Cache>, id: i64) -> Result<Option<CachedUser>, String> {
+    cache.user(id).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache() -> Cache {
+        Cache::in_memory(1)
+    }
+
+    fn user(id: i64) -> UserRef {
+        UserRef {
+            id,
+            username: format!("user{}", id),
+        }
+    }
+
+    fn attachment(id: i64, filename: &str) -> Attachment {
+        Attachment {
+            id,
+            url: format!("/uploads/{}", filename),
+            filename: filename.to_string(),
+            mime_type: "image/png".to_string(),
+            size: 10,
+            created_at: "2025-01-01T00:00:00.000Z".to_string(),
+        }
+    }
+
+    fn channel_message(id: i64, channel_id: i64) -> ChannelMessage {
+        ChannelMessage {
+            id,
+            content: format!("message {}", id),
+            user_id: 7,
+            channel_id,
+            created_at: "2025-01-01T00:00:00.000Z".to_string(),
+            is_edited: false,
+            edited_at: None,
+            reply_to_id: None,
+            reply_to: None,
+            user: user(7),
+            channel: ChannelRef {
+                id: channel_id,
+                name: format!("channel{}", channel_id),
+            },
+            attachments: Vec::new(),
+        }
+    }
+
+    fn direct_message(id: i64, sender_id: i64, receiver_id: i64) -> DirectMessage {
+        DirectMessage {
+            id,
+            content: format!("dm {}", id),
+            sender_id,
+            receiver_id,
+            created_at: "2025-01-01T00:00:00.000Z".to_string(),
+            is_edited: false,
+            edited_at: None,
+            is_read: false,
+            sender: user(sender_id),
+            receiver: user(receiver_id),
+            attachments: Vec::new(),
+        }
+    }
+
+    fn ids<T>(messages: &[T], id: impl Fn(&T) -> i64) -> Vec<i64> {
+        messages.iter().map(id).collect()
+    }
+
+    #[test]
+    fn pages_channel_history_oldest_first() {
+        let cache = cache();
+        let mut messages: Vec<_> = (1..=5).map(|id| channel_message(id, 1)).collect();
+        messages.push(channel_message(6, 2));
+        cache.store_channel_messages(&messages).unwrap();
+
+        let page = cache.channel_messages(1, None, Some(2)).unwrap();
+        assert_eq!(ids(&page, |m| m.id), [4, 5]);
+        let page = cache.channel_messages(1, Some(4), Some(2)).unwrap();
+        assert_eq!(ids(&page, |m| m.id), [2, 3]);
+        let page = cache.channel_messages(1, Some(2), None).unwrap();
+        assert_eq!(ids(&page, |m| m.id), [1]);
+        assert!(cache.channel_messages(1, Some(1), None).unwrap().is_empty());
+
+        let page = cache.channel_messages(1, None, None).unwrap();
+        assert_eq!(page[0].user.username, "user7");
+        assert_eq!(page[0].channel.name, "channel1");
+    }
+
+    #[test]
+    fn clamps_the_page_size() {
+        assert_eq!(clamp_limit(None), DEFAULT_LIMIT);
+        assert_eq!(clamp_limit(Some(0)), 1);
+        assert_eq!(clamp_limit(Some(1000)), MAX_LIMIT);
+
+        let cache = cache();
+        let messages: Vec<_> = (1..=3).map(|id| channel_message(id, 1)).collect();
+        cache.store_channel_messages(&messages).unwrap();
+        let page = cache.channel_messages(1, None, Some(0)).unwrap();
+        assert_eq!(ids(&page, |m| m.id), [3]);
+    }
+
+    #[test]
+    fn upserts_edits_and_replaces_attachments() {
+        let cache = cache();
+        let mut message = channel_message(1, 1);
+        message.attachments = vec![attachment(10, "a.png"), attachment(11, "b.png")];
+        cache.store_channel_messages(&[message.clone()]).unwrap();
+
+        message.content = "edited".to_string();
+        message.is_edited = true;
+        message.edited_at = Some("2025-01-02T00:00:00.000Z".to_string());
+        message.reply_to = Some(ReplyTo {
+            id: 9,
+            content: "earlier".to_string(),
+            user: user(8),
+        });
+        message.reply_to_id = Some(9);
+        message.attachments = vec![attachment(11, "b.png")];
+        cache.store_channel_messages(&[message]).unwrap();
+
+        let page = cache.channel_messages(1, None, None).unwrap();
+        assert_eq!(page.len(), 1);
+        let stored = &page[0];
+        assert_eq!(stored.content, "edited");
+        assert!(stored.is_edited);
+        assert_eq!(
+            stored.edited_at.as_deref(),
+            Some("2025-01-02T00:00:00.000Z")
+        );
+        assert_eq!(stored.reply_to_id, Some(9));
+        assert_eq!(stored.reply_to.as_ref().map(|r| r.id), Some(9));
+        assert_eq!(ids(&stored.attachments, |a| a.id), [11]);
+    }
+
+    #[test]
+    fn deleting_a_message_drops_its_attachments() {
+        let cache = cache();
+        let mut message = channel_message(1, 1);
+        message.attachments = vec![attachment(10, "a.png")];
+        cache.store_channel_messages(&[message]).unwrap();
+        cache.delete_channel_message(1).unwrap();
+
+        assert!(cache.channel_messages(1, None, None).unwrap().is_empty());
+        let remaining: i64 = cache
+            .with_conn(|conn| {
+                Ok(conn.query_row("SELECT COUNT(*) FROM attachments", [], |row| row.get(0))?)
+            })
+            .unwrap();
+        assert_eq!(remaining, 0);
+    }
+
+    #[test]
+    fn pages_a_conversation_in_both_directions() {
+        let cache = cache();
+        cache
+            .store_direct_messages(&[
+                direct_message(1, 1, 2),
+                direct_message(2, 2, 1),
+                direct_message(3, 1, 3),
+                direct_message(4, 1, 2),
+            ])
+            .unwrap();
+
+        let page = cache.conversation(2, None, None).unwrap();
+        assert_eq!(ids(&page, |m| m.id), [1, 2, 4]);
+        let page = cache.conversation(2, Some(4), Some(1)).unwrap();
+        assert_eq!(ids(&page, |m| m.id), [2]);
+        assert_eq!(page[0].sender.username, "user2");
+        assert_eq!(page[0].receiver.username, "user1");
+
+        let mut read = direct_message(2, 2, 1);
+        read.is_read = true;
+        cache.store_direct_messages(&[read]).unwrap();
+        assert!(cache.conversation(2, Some(4), Some(1)).unwrap()[0].is_read);
+    }
+
+    #[test]
+    fn message_authors_keep_profile_fields() {
+        let cache = cache();
+        cache
+            .store_users(&[CachedUser {
+                id: 7,
+                username: "old".to_string(),
+                email: Some("seven@example.com".to_string()),
+                status: Some("online".to_string()),
+            }])
+            .unwrap();
+        cache
+            .store_channel_messages(&[channel_message(1, 1)])
+            .unwrap();
+
+        let stored = cache.user(7).unwrap().unwrap();
+        assert_eq!(stored.username, "user7");
+        assert_eq!(stored.email.as_deref(), Some("seven@example.com"));
+        assert_eq!(stored.status.as_deref(), Some("online"));
+
+        // A partial profile update keeps what it leaves out
+        cache
+            .store_users(&[CachedUser {
+                id: 7,
+                username: "user7".to_string(),
+                email: None,
+                status: Some("away".to_string()),
+            }])
+            .unwrap();
+        let stored = cache.user(7).unwrap().unwrap();
+        assert_eq!(stored.email.as_deref(), Some("seven@example.com"));
+        assert_eq!(stored.status.as_deref(), Some("away"));
+        assert!(cache.user(8).unwrap().is_none());
+    }
+
+    #[test]
+    fn socket_messages_borrow_the_stored_channel_name() {
+        let cache = cache();
+        cache
+            .store_channel_messages(&[channel_message(1, 1)])
+            .unwrap();
+        let socket: SocketMessage = serde_json::from_value(serde_json::json!({
+            "id": 2,
+            "content": "live",
+            "userId": 8,
+            "username": "user8",
+            "channelId": 1,
+            "createdAt": "2025-01-01T00:00:01.000Z",
+        }))
+        .unwrap();
+        cache.store_socket_message(socket).unwrap();
+
+        let page = cache.channel_messages(1, None, None).unwrap();
+        assert_eq!(ids(&page, |m| m.id), [1, 2]);
+        assert_eq!(page[1].channel.name, "channel1");
+        assert_eq!(page[1].user.username, "user8");
+    }
+
+    #[test]
+    fn queries_need_an_open_cache() {
+        let cache = cache();
+        cache.close();
+        assert!(matches!(
+            cache.channel_messages(1, None, None),
+            Err(Error::NotOpen)
+        ));
+        assert_eq!(cache.account_id(), None);
+    }
+}
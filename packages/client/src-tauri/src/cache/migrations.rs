@@ -0,0 +1,268 @@
+//! Schema migrations for the message cache.
+//!
+//! Each migration is tagged with the app version (tauri.conf.json
+//! `package.version`) that introduced it. A build only applies migrations up to
+//! its own version, and a database touched by a newer build is rejected so the
+//! caller can throw it away; it is only a cache, the server stays authoritative.
+
+use rusqlite::{params, Connection};
+use semver::Version;
+
+pub struct Migration {
+    pub version: &'static str,
+    pub name: &'static str,
+    pub sql: &'static str,
+}
+
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: "1.2.3",
+        name: "initial",
+        sql: r#"
+        CREATE TABLE users (
+            id INTEGER PRIMARY KEY,
+            username TEXT NOT NULL,
+            email TEXT,
+            status TEXT,
+            updated_at INTEGER NOT NULL
+        );
+
+        CREATE TABLE channel_messages (
+            id INTEGER PRIMARY KEY,
+            channel_id INTEGER NOT NULL,
+            channel_name TEXT NOT NULL,
+            user_id INTEGER NOT NULL,
+            content TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            is_edited INTEGER NOT NULL DEFAULT 0,
+            edited_at TEXT,
+            reply_to_id INTEGER,
+            reply_to TEXT
+        );
+        CREATE INDEX idx_channel_messages_channel ON channel_messages (channel_id, id);
+
+        CREATE TABLE direct_messages (
+            id INTEGER PRIMARY KEY,
+            sender_id INTEGER NOT NULL,
+            receiver_id INTEGER NOT NULL,
+            content TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            is_edited INTEGER NOT NULL DEFAULT 0,
+            edited_at TEXT,
+            is_read INTEGER NOT NULL DEFAULT 0
+        );
+        CREATE INDEX idx_direct_messages_sender ON direct_messages (sender_id, id);
+        CREATE INDEX idx_direct_messages_receiver ON direct_messages (receiver_id, id);
+
+        CREATE TABLE attachments (
+            id INTEGER NOT NULL,
+            message_kind TEXT NOT NULL CHECK (message_kind IN ('channel', 'direct')),
+            message_id INTEGER NOT NULL,
+            url TEXT NOT NULL,
+            filename TEXT NOT NULL,
+            mime_type TEXT NOT NULL,
+            size INTEGER NOT NULL,
+            created_at TEXT NOT NULL,
+            PRIMARY KEY (message_kind, id)
+        );
+        CREATE INDEX idx_attachments_message ON attachments (message_kind, message_id);
+    "#,
+    },
+    // Full-text index for search.rs. Rows are keyed by id * 2 for channel
+    // messages and id * 2 + 1 for DMs so triggers can address them directly.
+    Migration {
+        version: "1.2.3",
+        name: "search_index",
+        sql: r#"
+        CREATE VIRTUAL TABLE message_search USING fts5(
+            content,
+            tokenize = 'unicode61 remove_diacritics 2'
+        );
+
+        INSERT INTO message_search (rowid, content)
+            SELECT id * 2, content FROM channel_messages;
+        INSERT INTO message_search (rowid, content)
+            SELECT id * 2 + 1, content FROM direct_messages;
+
+        CREATE TRIGGER channel_messages_search_insert AFTER INSERT ON channel_messages BEGIN
+            INSERT INTO message_search (rowid, content) VALUES (new.id * 2, new.content);
+        END;
+        CREATE TRIGGER channel_messages_search_update AFTER UPDATE OF content ON channel_messages BEGIN
+            UPDATE message_search SET content = new.content WHERE rowid = new.id * 2;
+        END;
+        CREATE TRIGGER channel_messages_search_delete AFTER DELETE ON channel_messages BEGIN
+            DELETE FROM message_search WHERE rowid = old.id * 2;
+        END;
+
+        CREATE TRIGGER direct_messages_search_insert AFTER INSERT ON direct_messages BEGIN
+            INSERT INTO message_search (rowid, content) VALUES (new.id * 2 + 1, new.content);
+        END;
+        CREATE TRIGGER direct_messages_search_update AFTER UPDATE OF content ON direct_messages BEGIN
+            UPDATE message_search SET content = new.content WHERE rowid = new.id * 2 + 1;
+        END;
+        CREATE TRIGGER direct_messages_search_delete AFTER DELETE ON direct_messages BEGIN
+            DELETE FROM message_search WHERE rowid = old.id * 2 + 1;
+        END;
+
+        CREATE VIEW searchable_messages AS
+            SELECT 'channel' AS kind, id * 2 AS search_rowid, id, channel_id, channel_name,
+                   user_id AS author_id, NULL AS receiver_id, content, created_at
+            FROM channel_messages
+            UNION ALL
+            SELECT 'direct', id * 2 + 1, id, NULL, NULL,
+                   sender_id, receiver_id, content, created_at
+            FROM direct_messages;
+    "#,
+    },
+];
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Sqlite(#[from] rusqlite::Error),
+    #[error("cache schema was written by a newer CommHub ({0})")]
+    NewerSchema(String),
+}
+
+/// Brings the schema up to date for `app_version`, returning how many
+/// migrations were applied.
+pub fn run(conn: &mut Connection, app_version: &Version) -> Result<usize, Error> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS schema_migrations (
+            name TEXT PRIMARY KEY,
+            version TEXT NOT NULL,
+            applied_at INTEGER NOT NULL
+        )",
+    )?;
+
+    let applied: Vec<(String, String)> = {
+        let mut stmt = conn.prepare("SELECT name, version FROM schema_migrations")?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect::<Result<_, _>>()?
+    };
+
+    for (name, version) in &applied {
+        let known = MIGRATIONS.iter().any(|m| m.name == name);
+        let newer = Version::parse(version).map_or(true, |v| v > *app_version);
+        if !known || newer {
+            return Err(Error::NewerSchema(version.clone()));
+        }
+    }
+
+    let mut count = 0;
+    for migration in MIGRATIONS {
+        if applied.iter().any(|(name, _)| name == migration.name) {
+            continue;
+        }
+        // Migrations shipped for a later release wait until that release
+        let version = Version::parse(migration.version).expect("invalid migration version");
+        if version > *app_version {
+            continue;
+        }
+
+        let tx = conn.transaction()?;
+        tx.execute_batch(migration.sql)?;
+        tx.execute(
+            "INSERT INTO schema_migrations (name, version, applied_at)
+             VALUES (?1, ?2, strftime('%s', 'now'))",
+            params![migration.name, migration.version],
+        )?;
+        tx.commit()?;
+        count += 1;
+    }
+
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn applied(conn: &Connection) -> Vec<String> {
+        let mut stmt = conn
+            .prepare("SELECT name FROM schema_migrations ORDER BY rowid")
+            .unwrap();
+        let rows = stmt.query_map([], |row| row.get(0)).unwrap();
+        rows.collect::<Result<_, _>>().unwrap()
+    }
+
+    fn latest() -> Version {
+        MIGRATIONS
+            .iter()
+            .map(|m| Version::parse(m.version).unwrap())
+            .max()
+            .unwrap()
+    }
+
+    #[test]
+    fn every_migration_has_a_valid_version_and_unique_name() {
+        for (i, migration) in MIGRATIONS.iter().enumerate() {
+            assert!(
+                Version::parse(migration.version).is_ok(),
+                "{} has version {:?}",
+                migration.name,
+                migration.version
+            );
+            assert!(
+                MIGRATIONS[..i].iter().all(|m| m.name != migration.name),
+                "{} is listed twice",
+                migration.name
+            );
+        }
+    }
+
+    #[test]
+    fn applies_everything_once() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        assert_eq!(run(&mut conn, &latest()).unwrap(), MIGRATIONS.len());
+        assert_eq!(run(&mut conn, &latest()).unwrap(), 0);
+        let names: Vec<_> = MIGRATIONS.iter().map(|m| m.name.to_string()).collect();
+        assert_eq!(applied(&conn), names);
+    }
+
+    #[test]
+    fn skips_migrations_from_later_releases() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        assert_eq!(run(&mut conn, &Version::new(0, 1, 0)).unwrap(), 0);
+        assert!(applied(&conn).is_empty());
+
+        assert_eq!(run(&mut conn, &latest()).unwrap(), MIGRATIONS.len());
+    }
+
+    #[test]
+    fn rejects_schema_from_a_newer_build() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        run(&mut conn, &latest()).unwrap();
+        conn.execute(
+            "UPDATE schema_migrations SET version = '99.0.0' WHERE name = ?1",
+            [MIGRATIONS[0].name],
+        )
+        .unwrap();
+
+        match run(&mut conn, &latest()) {
+            Err(Error::NewerSchema(version)) => assert_eq!(version, "99.0.0"),
+            other => panic!("expected NewerSchema, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_or_unreadable_migrations() {
+        for (name, version) in [("from_the_future", "1.0.0"), ("initial", "not a version")] {
+            let mut conn = Connection::open_in_memory().unwrap();
+            run(&mut conn, &latest()).unwrap();
+            conn.execute(
+                "INSERT OR REPLACE INTO schema_migrations (name, version, applied_at)
+                 VALUES (?1, ?2, 0)",
+                [name, version],
+            )
+            .unwrap();
+
+            assert!(
+                matches!(run(&mut conn, &latest()), Err(Error::NewerSchema(_))),
+                "{} {} was accepted",
+                name,
+                version
+            );
+        }
+    }
+}
@@ -0,0 +1,105 @@
+//! System clipboard access the webview doesn't have.
+//!
+//! WebKitGTK and WKWebView don't expose pasted screenshots as files, and
+//! `navigator.clipboard` can only write plain text. So images are read here
+//! and handed to the upload pipeline as PNG, messages are copied as both
+//! plain text and HTML, and pasted HTML is converted to message markdown.
+
+mod html;
+mod markdown;
+
+use std::io::Cursor;
+use std::sync::Mutex;
+
+use base64::Engine as _;
+use image::{ImageOutputFormat, RgbaImage};
+use tauri::{AppHandle, Manager};
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("clipboard error: {0}")]
+    Clipboard(#[from] arboard::Error),
+    #[error("clipboard image is malformed")]
+    MalformedImage,
+    #[error("failed to encode clipboard image: {0}")]
+    Encode(#[from] image::ImageError),
+}
+
+/// One clipboard for the app's lifetime. On X11 and Wayland what was copied
+/// lives in this process, so the instance has to outlive the copy.
+#[derive(Default)]
+pub struct Clipboard {
+    inner: Mutex<Option<arboard::Clipboard>>,
+}
+
+impl Clipboard {
+    fn with<T>(
+        &self,
+        f: impl FnOnce(&mut arboard::Clipboard) -> Result<T, arboard::Error>,
+    ) -> Result<T, Error> {
+        let mut inner = self.inner.lock().unwrap();
+        let clipboard = match inner.as_mut() {
+            Some(clipboard) => clipboard,
+            None => inner.insert(arboard::Clipboard::new()?),
+        };
+        Ok(f(clipboard)?)
+    }
+
+    /// The clipboard image as PNG, if there is one.
+    pub fn read_image(&self) -> Result<Option<Vec<u8>>, Error> {
+        let image = match self.with(|clipboard| clipboard.get_image()) {
+            Ok(image) => image,
+            Err(Error::Clipboard(arboard::Error::ContentNotAvailable)) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let rgba = RgbaImage::from_raw(
+            image.width as u32,
+            image.height as u32,
+            image.bytes.into_owned(),
+        )
+        .ok_or(Error::MalformedImage)?;
+        let mut png = Cursor::new(Vec::new());
+        rgba.write_to(&mut png, ImageOutputFormat::Png)?;
+        Ok(Some(png.into_inner()))
+    }
+
+    /// Copies a message so rich text editors keep its formatting and
+    /// everything else gets the markdown it was written in.
+    pub fn write_message(&self, content: &str) -> Result<(), Error> {
+        let html = html::markdown_to_html(content);
+        self.with(|clipboard| clipboard.set().html(html, Some(content.to_string())))
+    }
+
+    /// Gives up clipboard ownership. arboard has to be dropped before the
+    /// process exits; on Linux whatever was copied goes with it.
+    pub fn close(&self) {
+        self.inner.lock().unwrap().take();
+    }
+}
+
+/// Base64 rather than bytes: a `Vec<u8>` crosses IPC as a JSON array of
+/// numbers, several times the size of the image.
+///
+/// Read off the main thread, which sync commands run on, so converting a
+/// large image or waiting on a slow selection owner doesn't freeze the UI.
+#[tauri::command]
+pub async fn clipboard_read_image(app: AppHandle) -> Result<Option<String>, String> {
+    let png = tauri::async_runtime::spawn_blocking(move || app.state::<Clipboard>().read_image())
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())?;
+    Ok(png.map(|png| base64::engine::general_purpose::STANDARD.encode(png)))
+}
+
+#[tauri::command]
+pub async fn clipboard_write_message(app: AppHandle, content: String) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(move || app.state::<Clipboard>().write_message(&content))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn clipboard_html_to_markdown(html: String) -> String {
+    markdown::html_to_markdown(&html)
+}
@@ -0,0 +1,339 @@
+//! CommHub's markdown to HTML, for copying messages with their formatting.
+//!
+//! Only what messages render is recognised; everything else is copied as
+//! escaped text, so the HTML is always safe to paste anywhere.
+
+pub fn markdown_to_html(markdown: &str) -> String {
+    let mut html = String::new();
+    let mut lines = markdown.lines().peekable();
+    let mut paragraph: Vec<&str> = Vec::new();
+
+    while let Some(line) = lines.next() {
+        let trimmed = line.trim_start();
+
+        if let Some(language) = trimmed.strip_prefix("```") {
+            flush_paragraph(&mut html, &mut paragraph);
+            let mut code = Vec::new();
+            for line in lines.by_ref() {
+                if line.trim_start().starts_with("```") {
+                    break;
+                }
+                code.push(line);
+            }
+            let language = language.trim();
+            if language.is_empty() {
+                html.push_str("<pre><code>");
+            } else {
+                html.push_str(&format!(
+                    "<pre><code class=\"language-{}\">",
+                    escape(language)
+                ));
+            }
+            html.push_str(&escape(&code.join("\n")));
+            html.push_str("</code></pre>");
+            continue;
+        }
+
+        if trimmed.starts_with('>') {
+            flush_paragraph(&mut html, &mut paragraph);
+            let mut quoted = vec![strip_quote(trimmed)];
+            while let Some(next) = lines.peek().map(|l| l.trim_start()) {
+                if !next.starts_with('>') {
+                    break;
+                }
+                quoted.push(strip_quote(next));
+                lines.next();
+            }
+            html.push_str("<blockquote>");
+            html.push_str(&markdown_to_html(&quoted.join("\n")));
+            html.push_str("</blockquote>");
+            continue;
+        }
+
+        if let Some((kind, first)) = list_item(trimmed) {
+            flush_paragraph(&mut html, &mut paragraph);
+            let tag = match kind {
+                List::Bullet => "ul",
+                List::Numbered => "ol",
+            };
+            html.push_str(&format!("<{}><li>{}</li>", tag, inline(first)));
+            while let Some(item) = lines
+                .peek()
+                .and_then(|l| list_item(l.trim_start()))
+                .filter(|(next, _)| *next == kind)
+                .map(|(_, item)| item)
+            {
+                html.push_str(&format!("<li>{}</li>", inline(item)));
+                lines.next();
+            }
+            html.push_str(&format!("</{}>", tag));
+            continue;
+        }
+
+        if trimmed.is_empty() {
+            flush_paragraph(&mut html, &mut paragraph);
+        } else {
+            paragraph.push(line);
+        }
+    }
+    flush_paragraph(&mut html, &mut paragraph);
+    html
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum List {
+    Bullet,
+    Numbered,
+}
+
+fn list_item(line: &str) -> Option<(List, &str)> {
+    if let Some(item) = line.strip_prefix("- ").or_else(|| line.strip_prefix("* ")) {
+        return Some((List::Bullet, item));
+    }
+    let digits = line.bytes().take_while(u8::is_ascii_digit).count();
+    if digits > 0 {
+        if let Some(item) = line[digits..].strip_prefix(". ") {
+            return Some((List::Numbered, item));
+        }
+    }
+    None
+}
+
+fn strip_quote(line: &str) -> &str {
+    let line = &line[1..];
+    line.strip_prefix(' ').unwrap_or(line)
+}
+
+fn flush_paragraph(html: &mut String, paragraph: &mut Vec<&str>) {
+    if paragraph.is_empty() {
+        return;
+    }
+    let lines: Vec<String> = paragraph.drain(..).map(inline).collect();
+    html.push_str("<p>");
+    html.push_str(&lines.join("<br>"));
+    html.push_str("</p>");
+}
+
+/// Inline formatting of a single line.
+fn inline(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut html = String::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let rest = &chars[i..];
+        let c = chars[i];
+
+        if c == '\\' && rest.len() > 1 && rest[1].is_ascii_punctuation() {
+            html.push_str(&escape(&rest[1].to_string()));
+            i += 2;
+            continue;
+        }
+
+        if c == '`' {
+            let ticks = rest.iter().take_while(|&&c| c == '`').count();
+            if let Some(end) = find_run(&chars, i + ticks, '`', ticks) {
+                let code: String = chars[i + ticks..end].iter().collect();
+                let code = if ticks > 1 { code.trim() } else { &code };
+                html.push_str(&format!("<code>{}</code>", escape(code)));
+                i = end + ticks;
+                continue;
+            }
+        }
+
+        let emphasis = [("**", "strong"), ("~~", "del"), ("*", "em")];
+        if let Some((marker, tag, end)) = emphasis.iter().find_map(|&(marker, tag)| {
+            let len = marker.chars().count();
+            let marker_char = marker.chars().next().unwrap();
+            let opens = rest.iter().take(len).all(|&c| c == marker_char)
+                && rest.get(len).is_some_and(|c| !c.is_whitespace());
+            if !opens {
+                return None;
+            }
+            let end = find_run(&chars, i + len, marker_char, len)?;
+            (!chars[end - 1].is_whitespace()).then_some((len, tag, end))
+        }) {
+            let inner: String = chars[i + marker..end].iter().collect();
+            html.push_str(&format!("<{tag}>{}</{tag}>", inline(&inner)));
+            i = end + marker;
+            continue;
+        }
+
+        if c == '[' {
+            if let Some((label, url, len)) = link(rest) {
+                html.push_str(&format!(
+                    "<a href=\"{}\">{}</a>",
+                    escape(&url),
+                    inline(&label)
+                ));
+                i += len;
+                continue;
+            }
+        }
+
+        if c == 'h' && (i == 0 || !chars[i - 1].is_alphanumeric()) {
+            if let Some(len) = bare_url(rest) {
+                let url: String = rest[..len].iter().collect();
+                html.push_str(&format!("<a href=\"{url}\">{url}</a>", url = escape(&url)));
+                i += len;
+                continue;
+            }
+        }
+
+        html.push_str(&escape(&c.to_string()));
+        i += 1;
+    }
+    html
+}
+
+/// Start of the next run of exactly `len` `marker`s at or after `from`.
+fn find_run(chars: &[char], from: usize, marker: char, len: usize) -> Option<usize> {
+    let mut i = from;
+    while i < chars.len() {
+        if chars[i] == '\\' && marker != '`' {
+            i += 2;
+            continue;
+        }
+        if chars[i] == marker {
+            let run = chars[i..].iter().take_while(|&&c| c == marker).count();
+            if run == len && i > from {
+                return Some(i);
+            }
+            i += run;
+            continue;
+        }
+        i += 1;
+    }
+    None
+}
+
+/// `[label](http...)`, as label, url and length in chars.
+fn link(chars: &[char]) -> Option<(String, String, usize)> {
+    let close = chars.iter().position(|&c| c == ']')?;
+    if chars.get(close + 1) != Some(&'(') {
+        return None;
+    }
+    let end = close + 2 + chars[close + 2..].iter().position(|&c| c == ')')?;
+    let url: String = chars[close + 2..end].iter().collect();
+    let web = ["http://", "https://", "mailto:"]
+        .iter()
+        .any(|scheme| url.starts_with(scheme));
+    if !web || url.contains(char::is_whitespace) {
+        return None;
+    }
+    Some((chars[1..close].iter().collect(), url, end + 1))
+}
+
+/// Length of a bare http(s) URL, without trailing punctuation.
+fn bare_url(chars: &[char]) -> Option<usize> {
+    let text: String = chars.iter().take(8).collect();
+    if !text.starts_with("http://") && !text.starts_with("https://") {
+        return None;
+    }
+    let mut len = chars
+        .iter()
+        .take_while(|c| !c.is_whitespace() && !matches!(c, '<' | '>' | '"'))
+        .count();
+    while len > 0 && matches!(chars[len - 1], '.' | ',' | ';' | ':' | '!' | '?' | ')') {
+        len -= 1;
+    }
+    (len > "https://".len()).then_some(len)
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::markdown_to_html;
+
+    #[test]
+    fn paragraphs_and_line_breaks() {
+        assert_eq!(
+            markdown_to_html("one\ntwo\n\nthree"),
+            "<p>one<br>two</p><p>three</p>"
+        );
+    }
+
+    #[test]
+    fn inline_formatting() {
+        assert_eq!(
+            markdown_to_html("**bold** *italic* ~~gone~~ `a < b`"),
+            "<p><strong>bold</strong> <em>italic</em> <del>gone</del> <code>a &lt; b</code></p>"
+        );
+        assert_eq!(
+            markdown_to_html("**bold with *italic* inside**"),
+            "<p><strong>bold with <em>italic</em> inside</strong></p>"
+        );
+    }
+
+    #[test]
+    fn unmatched_markers_are_text() {
+        assert_eq!(markdown_to_html("2 * 3 * 4"), "<p>2 * 3 * 4</p>");
+        assert_eq!(markdown_to_html("\\*not italic\\*"), "<p>*not italic*</p>");
+    }
+
+    #[test]
+    fn html_is_escaped() {
+        assert_eq!(
+            markdown_to_html("<script>alert('x')</script>"),
+            "<p>&lt;script&gt;alert(&#39;x&#39;)&lt;/script&gt;</p>"
+        );
+    }
+
+    #[test]
+    fn links() {
+        assert_eq!(
+            markdown_to_html("see [the docs](https://example.com/a?b=1&c=2)"),
+            "<p>see <a href=\"https://example.com/a?b=1&amp;c=2\">the docs</a></p>"
+        );
+        assert_eq!(
+            markdown_to_html("at https://example.com/page."),
+            "<p>at <a href=\"https://example.com/page\">https://example.com/page</a>.</p>"
+        );
+        assert_eq!(
+            markdown_to_html("[click](javascript:alert(1))"),
+            "<p>[click](javascript:alert(1))</p>"
+        );
+    }
+
+    #[test]
+    fn code_blocks() {
+        assert_eq!(
+            markdown_to_html("before\n```rust\nfn main() {\n    *x = 1;\n}\n```\nafter"),
+            concat!(
+                "<p>before</p>",
+                "<pre><code class=\"language-rust\">fn main() {\n    *x = 1;\n}</code></pre>",
+                "<p>after</p>"
+            )
+        );
+    }
+
+    #[test]
+    fn quotes() {
+        assert_eq!(
+            markdown_to_html("> quoted **text**\n> more\nreply"),
+            "<blockquote><p>quoted <strong>text</strong><br>more</p></blockquote><p>reply</p>"
+        );
+    }
+
+    #[test]
+    fn lists() {
+        assert_eq!(
+            markdown_to_html("- one\n- two\n1. first\n2. second"),
+            "<ul><li>one</li><li>two</li></ul><ol><li>first</li><li>second</li></ol>"
+        );
+    }
+}
@@ -0,0 +1,494 @@
+//! Pasted HTML to CommHub's markdown.
+//!
+//! Messages support `**bold**`, `*italic*`, `~~strikethrough~~`, `` `code` ``,
+//! fenced code blocks, `> ` quotes, `- ` and `1. ` lists and
+//! `[text](url)` links. Browsers and IDEs put HTML on the clipboard next to
+//! the plain text; converting it keeps that formatting instead of pasting a
+//! flattened copy. Anything without an equivalent is reduced to its text.
+
+use kuchikiki::traits::TendrilSink;
+use kuchikiki::NodeRef;
+
+const SKIPPED: &[&str] = &[
+    "head", "script", "style", "title", "meta", "link", "noscript", "template", "img",
+];
+
+/// Separated from what's around them by a line break.
+const BLOCKS: &[&str] = &[
+    "address",
+    "article",
+    "aside",
+    "dd",
+    "div",
+    "dl",
+    "dt",
+    "figcaption",
+    "figure",
+    "footer",
+    "form",
+    "header",
+    "hr",
+    "li",
+    "main",
+    "nav",
+    "section",
+    "table",
+    "tbody",
+    "tfoot",
+    "thead",
+    "tr",
+];
+
+/// Separated from what's around them by a blank line.
+const PARAGRAPHS: &[&str] = &[
+    "p",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "blockquote",
+    "ul",
+    "ol",
+];
+
+pub fn html_to_markdown(html: &str) -> String {
+    let document = kuchikiki::parse_html().one(html);
+    let mut writer = Writer::default();
+    writer.children(&document);
+    writer.finish()
+}
+
+#[derive(Default)]
+struct Writer {
+    out: String,
+    // Line breaks owed before the next text, so blocks never leave
+    // trailing ones behind
+    breaks: usize,
+    space: bool,
+    quote_depth: usize,
+    // One entry per open list: None for bullets, or the next number
+    lists: Vec<Option<usize>>,
+    marker: Option<String>,
+}
+
+impl Writer {
+    fn children(&mut self, node: &NodeRef) {
+        for child in node.children() {
+            self.node(&child);
+        }
+    }
+
+    fn node(&mut self, node: &NodeRef) {
+        if let Some(text) = node.as_text() {
+            self.text(&text.borrow());
+            return;
+        }
+        let Some(element) = node.as_element() else {
+            // The document itself, doctypes and comments
+            self.children(node);
+            return;
+        };
+        let name: &str = &element.name.local;
+        let attributes = element.attributes.borrow();
+        if SKIPPED.contains(&name) {
+            return;
+        }
+
+        // IDEs copy code as styled divs rather than <pre>
+        let style = attributes
+            .get("style")
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        if name == "pre" || (style.contains("white-space: pre") && style.contains("monospace")) {
+            // Highlighters mark either the <pre> or the <code> inside it
+            let language = attributes.get("class").and_then(language_of).or_else(|| {
+                let code = node.select_first("code").ok()?;
+                let class = code.attributes.borrow().get("class").and_then(language_of);
+                class
+            });
+            self.code_block(&preformatted_text(node), language.as_deref());
+            return;
+        }
+
+        match name {
+            "br" => self.line_break(1),
+            "b" | "strong" => self.wrap(node, "**"),
+            "i" | "em" => self.wrap(node, "*"),
+            "s" | "del" | "strike" => self.wrap(node, "~~"),
+            "code" | "kbd" | "samp" | "tt" => self.inline_code(&node.text_contents()),
+            "a" => {
+                let href = attributes
+                    .get("href")
+                    .unwrap_or_default()
+                    .trim()
+                    .to_string();
+                drop(attributes);
+                self.link(node, &href);
+            }
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                self.line_break(2);
+                self.wrap(node, "**");
+                self.line_break(2);
+            }
+            "blockquote" => {
+                self.line_break(2);
+                self.quote_depth += 1;
+                self.children(node);
+                self.quote_depth -= 1;
+                self.line_break(2);
+            }
+            "ul" | "ol" => {
+                let start = attributes
+                    .get("start")
+                    .and_then(|start| start.parse().ok())
+                    .unwrap_or(1);
+                self.line_break(if self.lists.is_empty() { 2 } else { 1 });
+                self.lists.push((name == "ol").then_some(start));
+                self.children(node);
+                self.lists.pop();
+                self.line_break(if self.lists.is_empty() { 2 } else { 1 });
+            }
+            "li" => {
+                self.line_break(1);
+                let marker = match self.lists.last_mut() {
+                    Some(Some(number)) => {
+                        *number += 1;
+                        format!("{}. ", *number - 1)
+                    }
+                    _ => "- ".to_string(),
+                };
+                self.marker = Some(marker);
+                self.children(node);
+                self.marker = None;
+                self.line_break(1);
+            }
+            "td" | "th" => {
+                let first = node.preceding_siblings().all(|s| s.as_element().is_none());
+                if !first {
+                    self.raw(" | ");
+                }
+                self.children(node);
+            }
+            _ if PARAGRAPHS.contains(&name) => {
+                self.line_break(2);
+                self.children(node);
+                self.line_break(2);
+            }
+            _ if BLOCKS.contains(&name) => {
+                self.line_break(1);
+                self.children(node);
+                self.line_break(1);
+            }
+            _ => self.children(node),
+        }
+    }
+
+    fn text(&mut self, text: &str) {
+        for c in text.chars() {
+            if c.is_whitespace() {
+                self.space = true;
+                continue;
+            }
+            self.start_text();
+            match c {
+                '\\' | '*' | '`' => {
+                    self.out.push('\\');
+                    self.out.push(c);
+                }
+                '~' if self.out.ends_with('~') => {
+                    self.out.pop();
+                    self.out.push_str("\\~\\~");
+                }
+                _ => self.out.push(c),
+            }
+        }
+    }
+
+    /// Settles owed breaks, line prefixes and spaces before writing.
+    fn start_text(&mut self) {
+        if self.breaks > 0 {
+            for i in 0..self.breaks {
+                if i > 0 && self.quote_depth > 0 {
+                    self.out.push_str(&">".repeat(self.quote_depth));
+                }
+                self.out.push('\n');
+            }
+            self.breaks = 0;
+            self.space = false;
+        }
+        if self.at_line_start() {
+            self.space = false;
+            self.out.push_str(&"> ".repeat(self.quote_depth));
+            if let Some(marker) = self.marker.take() {
+                self.out
+                    .push_str(&"  ".repeat(self.lists.len().saturating_sub(1)));
+                self.out.push_str(&marker);
+            }
+        } else if self.space {
+            self.out.push(' ');
+            self.space = false;
+        }
+    }
+
+    fn at_line_start(&self) -> bool {
+        self.out.is_empty() || self.out.ends_with('\n')
+    }
+
+    fn raw(&mut self, text: &str) {
+        self.start_text();
+        self.out.push_str(text);
+    }
+
+    fn line_break(&mut self, count: usize) {
+        if !self.out.is_empty() {
+            self.breaks = self.breaks.max(count);
+        }
+    }
+
+    /// Wraps the children in `marker`, dropping it again if they were empty.
+    fn wrap(&mut self, node: &NodeRef, marker: &str) {
+        self.start_text();
+        let start = self.out.len();
+        self.out.push_str(marker);
+        self.children(node);
+        if self.out.len() == start + marker.len() {
+            self.out.truncate(start);
+        } else {
+            self.out.push_str(marker);
+        }
+    }
+
+    fn inline_code(&mut self, code: &str) {
+        let code = code.split_whitespace().collect::<Vec<_>>().join(" ");
+        if code.is_empty() {
+            return;
+        }
+        if code.contains('`') {
+            self.raw(&format!("`` {} ``", code));
+        } else {
+            self.raw(&format!("`{}`", code));
+        }
+    }
+
+    fn link(&mut self, node: &NodeRef, href: &str) {
+        let text = node.text_contents();
+        let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        let web = href.starts_with("http://")
+            || href.starts_with("https://")
+            || href.starts_with("mailto:");
+        if !web {
+            self.text(&text);
+        } else if text.is_empty() || text == href || text == href.trim_end_matches('/') {
+            self.raw(href);
+        } else {
+            self.raw("[");
+            self.text(&text.replace(['[', ']'], ""));
+            self.out.push_str(&format!("]({})", href));
+        }
+    }
+
+    fn code_block(&mut self, code: &str, language: Option<&str>) {
+        let code = code.trim_matches('\n');
+        if code.trim().is_empty() {
+            return;
+        }
+        self.line_break(1);
+        self.raw(&format!("```{}", language.unwrap_or_default()));
+        for line in code.lines() {
+            self.out.push('\n');
+            self.out.push_str(&"> ".repeat(self.quote_depth));
+            self.out.push_str(line);
+        }
+        self.out.push('\n');
+        self.out.push_str(&"> ".repeat(self.quote_depth));
+        self.out.push_str("```");
+        self.line_break(1);
+    }
+
+    fn finish(self) -> String {
+        self.out.trim_end().to_string()
+    }
+}
+
+/// Text of a code element with its line structure: `<br>`s and the
+/// per-line divs IDEs emit become newlines.
+fn preformatted_text(node: &NodeRef) -> String {
+    let mut text = String::new();
+    collect_preformatted(node, &mut text);
+    text.replace('\u{a0}', " ")
+}
+
+fn collect_preformatted(node: &NodeRef, text: &mut String) {
+    for child in node.children() {
+        if let Some(chunk) = child.as_text() {
+            text.push_str(&chunk.borrow());
+            continue;
+        }
+        let Some(element) = child.as_element() else {
+            continue;
+        };
+        match &*element.name.local {
+            "br" => text.push('\n'),
+            "div" | "p" => {
+                if !text.is_empty() && !text.ends_with('\n') {
+                    text.push('\n');
+                }
+                collect_preformatted(&child, text);
+                if !text.ends_with('\n') {
+                    text.push('\n');
+                }
+            }
+            _ => collect_preformatted(&child, text),
+        }
+    }
+}
+
+/// `language-rust` or `lang-rust`, as highlighters mark code up.
+fn language_of(class: &str) -> Option<String> {
+    class.split_whitespace().find_map(|class| {
+        class
+            .strip_prefix("language-")
+            .or_else(|| class.strip_prefix("lang-"))
+            .filter(|language| {
+                language
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '+')
+            })
+            .map(str::to_string)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::html_to_markdown;
+
+    #[test]
+    fn inline_formatting() {
+        assert_eq!(
+            html_to_markdown("<p>Some <b>bold</b>, <em>italic</em> and <del>gone</del> text</p>"),
+            "Some **bold**, *italic* and ~~gone~~ text"
+        );
+        assert_eq!(html_to_markdown("<strong></strong>plain"), "plain");
+    }
+
+    #[test]
+    fn whitespace_is_collapsed() {
+        assert_eq!(
+            html_to_markdown("<div>\n  spread   over\n  lines\n</div>"),
+            "spread over lines"
+        );
+    }
+
+    #[test]
+    fn paragraphs_and_breaks() {
+        assert_eq!(
+            html_to_markdown("<p>one</p><p>two<br>three</p><div>four</div>"),
+            "one\n\ntwo\nthree\n\nfour"
+        );
+    }
+
+    #[test]
+    fn markdown_characters_are_escaped() {
+        assert_eq!(
+            html_to_markdown("<span>2 * 3 and `x` and ~~y</span>"),
+            "2 \\* 3 and \\`x\\` and \\~\\~y"
+        );
+        assert_eq!(html_to_markdown("~/projects"), "~/projects");
+    }
+
+    #[test]
+    fn links() {
+        assert_eq!(
+            html_to_markdown(r#"<a href="https://example.com/docs">the docs</a>"#),
+            "[the docs](https://example.com/docs)"
+        );
+        assert_eq!(
+            html_to_markdown(r#"<a href="https://example.com/">https://example.com</a>"#),
+            "https://example.com/"
+        );
+        assert_eq!(
+            html_to_markdown(r#"<a href="javascript:alert(1)">click</a>"#),
+            "click"
+        );
+    }
+
+    #[test]
+    fn inline_code() {
+        assert_eq!(
+            html_to_markdown("run <code>cargo  test</code> first"),
+            "run `cargo test` first"
+        );
+        assert_eq!(html_to_markdown("<code>a`b</code>"), "`` a`b ``");
+    }
+
+    #[test]
+    fn code_blocks_keep_their_lines() {
+        assert_eq!(
+            html_to_markdown(
+                "<p>Try:</p><pre><code class=\"language-rust\">fn main() {\n    run();\n}\n</code></pre>"
+            ),
+            "Try:\n\n```rust\nfn main() {\n    run();\n}\n```"
+        );
+    }
+
+    #[test]
+    fn ide_code_is_a_code_block() {
+        let html = concat!(
+            "<div style=\"font-family: Consolas, monospace; white-space: pre;\">",
+            "<div><span>let x = 1;</span></div>",
+            "<div><span>&nbsp;&nbsp;x + 1</span></div>",
+            "</div>"
+        );
+        assert_eq!(html_to_markdown(html), "```\nlet x = 1;\n  x + 1\n```");
+    }
+
+    #[test]
+    fn lists() {
+        assert_eq!(
+            html_to_markdown("<ul><li>one</li><li>two<ol><li>a</li><li>b</li></ol></li></ul>"),
+            "- one\n- two\n  1. a\n  2. b"
+        );
+        assert_eq!(
+            html_to_markdown(r#"<ol start="3"><li>three</li></ol>"#),
+            "3. three"
+        );
+    }
+
+    #[test]
+    fn quotes() {
+        assert_eq!(
+            html_to_markdown("<blockquote><p>first</p><p>second</p></blockquote><p>after</p>"),
+            "> first\n>\n> second\n\nafter"
+        );
+    }
+
+    #[test]
+    fn headings_become_bold() {
+        assert_eq!(
+            html_to_markdown("<h2>Title</h2><p>body</p>"),
+            "**Title**\n\nbody"
+        );
+    }
+
+    #[test]
+    fn tables_are_flattened() {
+        assert_eq!(
+            html_to_markdown(
+                "<table><tr><th>a</th><th>b</th></tr><tr><td>1</td><td>2</td></tr></table>"
+            ),
+            "a | b\n1 | 2"
+        );
+    }
+
+    #[test]
+    fn browser_clipboard_fragment() {
+        let html = concat!(
+            "<html><head><meta charset=\"utf-8\"><style>p { color: red }</style></head><body>",
+            "<!--StartFragment--><span>Hello <b>there</b></span><!--EndFragment-->",
+            "<script>alert(1)</script></body></html>"
+        );
+        assert_eq!(html_to_markdown(html), "Hello **there**");
+    }
+}
@@ -0,0 +1,279 @@
+//! `commhub://` deep links for invites and message permalinks.
+//!
+//! The scheme is registered with the OS on every launch so it follows the
+//! app when it moves. Windows and Linux hand the link over as a command-line
+//! argument, which [`crate::instance`] forwards if CommHub is already
+//! running; macOS sends an Apple Event. Either way it is parsed and
+//! validated here ([`link`]) and reaches the webview as `deep-link://open`,
+//! or waits in [`DeepLinks`] if the webview hasn't loaded yet.
+
+mod link;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use tauri::{AppHandle, Manager, State};
+
+use crate::background::MAIN_WINDOW;
+use crate::tray;
+use link::{DeepLink, SCHEME};
+
+#[derive(Default)]
+pub struct DeepLinks {
+    pending: Mutex<Option<DeepLink>>,
+    // Whether the current webview has asked for pending links, and so is
+    // listening for new ones
+    ready: AtomicBool,
+}
+
+impl DeepLinks {
+    /// Picks up a link the app was launched with. It's held until the
+    /// webview asks, since nothing is listening yet.
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Self {
+        let links = Self::default();
+        *links.pending.lock().unwrap() = find(args);
+        links
+    }
+
+    /// Called when the main window goes away; its replacement has to load
+    /// before links can be emitted to it.
+    pub fn reset(&self) {
+        self.ready.store(false, Ordering::SeqCst);
+    }
+}
+
+/// The first valid `commhub:` link among command-line arguments.
+pub fn find(args: impl IntoIterator<Item = String>) -> Option<DeepLink> {
+    args.into_iter()
+        .filter(|arg| arg.starts_with(&format!("{SCHEME}:")))
+        .find_map(|arg| match arg.parse() {
+            Ok(link) => Some(link),
+            Err(e) => {
+                log!("[DeepLink] Ignoring {}: {}", arg, e);
+                None
+            }
+        })
+}
+
+/// Brings the main window forward and routes the link to the webview.
+pub fn open(app: &AppHandle, link: DeepLink) {
+    let links = app.state::<DeepLinks>();
+    let listening = app.get_window(MAIN_WINDOW).is_some() && links.ready.load(Ordering::SeqCst);
+    tray::show_main_window(app);
+    if listening {
+        let _ = app.emit_all("deep-link://open", link);
+    } else {
+        *links.pending.lock().unwrap() = Some(link);
+    }
+}
+
+/// Starts listening for links the OS delivers as events rather than
+/// arguments. Called from `main`, before the app is built.
+pub fn listen() {
+    #[cfg(target_os = "macos")]
+    platform::listen();
+}
+
+/// Makes the OS send `commhub://` links to this executable.
+pub fn register(app: &AppHandle) {
+    if let Err(e) = platform::register(app) {
+        log!("[DeepLink] Failed to register {}:// links: {}", SCHEME, e);
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::fs;
+    use std::io;
+    use std::process::Command;
+
+    use tauri::AppHandle;
+
+    use super::SCHEME;
+
+    const DESKTOP_FILE: &str = "commhub-handler.desktop";
+
+    pub fn register(_app: &AppHandle) -> io::Result<()> {
+        let dir = tauri::api::path::data_dir()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no data directory"))?
+            .join("applications");
+        // Inside an AppImage the executable lives on a temporary mount
+        let exec = match std::env::var_os("APPIMAGE") {
+            Some(path) => path.into(),
+            None => std::env::current_exe()?,
+        };
+        let entry = format!(
+            "[Desktop Entry]\n\
+             Type=Application\n\
+             Name=CommHub\n\
+             Exec=\"{}\" %u\n\
+             NoDisplay=true\n\
+             Terminal=false\n\
+             MimeType=x-scheme-handler/{};\n",
+            exec.display(),
+            SCHEME
+        );
+
+        let path = dir.join(DESKTOP_FILE);
+        if fs::read_to_string(&path).ok().as_deref() != Some(entry.as_str()) {
+            fs::create_dir_all(&dir)?;
+            fs::write(&path, entry)?;
+        }
+
+        // Only claim the scheme when nothing handles it yet, so a handler
+        // the user picked isn't replaced behind their back
+        let mime = format!("x-scheme-handler/{}", SCHEME);
+        let current = Command::new("xdg-mime")
+            .args(["query", "default", &mime])
+            .output()?;
+        let current = String::from_utf8_lossy(&current.stdout).trim().to_string();
+        if !current.is_empty() {
+            if current != DESKTOP_FILE {
+                log!("[DeepLink] {} links are handled by {}", SCHEME, current);
+            }
+            return Ok(());
+        }
+        let status = Command::new("xdg-mime")
+            .args(["default", DESKTOP_FILE, &mime])
+            .status()?;
+        if !status.success() {
+            return Err(io::Error::other(format!(
+                "xdg-mime default exited with {}",
+                status
+            )));
+        }
+        log!("[DeepLink] Registered as the handler for {} links", SCHEME);
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use std::io;
+
+    use tauri::AppHandle;
+    use winreg::enums::HKEY_CURRENT_USER;
+    use winreg::RegKey;
+
+    use super::SCHEME;
+
+    pub fn register(_app: &AppHandle) -> io::Result<()> {
+        let exe = std::env::current_exe()?;
+        let classes = RegKey::predef(HKEY_CURRENT_USER).open_subkey("Software\\Classes")?;
+        let (key, _) = classes.create_subkey(SCHEME)?;
+        key.set_value("", &"URL:CommHub")?;
+        key.set_value("URL Protocol", &"")?;
+        let (command, _) = key.create_subkey("shell\\open\\command")?;
+        command.set_value("", &format!("\"{}\" \"%1\"", exe.display()))?;
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use std::ffi::CStr;
+    use std::io;
+    use std::os::raw::c_char;
+    use std::sync::{Mutex, OnceLock};
+
+    use objc::declare::ClassDecl;
+    use objc::runtime::{Object, Sel};
+    use objc::{class, msg_send, sel, sel_impl};
+    use tauri::AppHandle;
+
+    use super::{find, open, DeepLink};
+
+    // Four-character codes from AppleEvents.h
+    const INTERNET_EVENT_CLASS: u32 = u32::from_be_bytes(*b"GURL");
+    const GET_URL: u32 = u32::from_be_bytes(*b"GURL");
+    const DIRECT_OBJECT: u32 = u32::from_be_bytes(*b"----");
+
+    static APP: OnceLock<AppHandle> = OnceLock::new();
+    // Links that arrived before the app handle existed
+    static EARLY: Mutex<Vec<DeepLink>> = Mutex::new(Vec::new());
+
+    /// Installs the handler for the Apple Event that delivers the link; the
+    /// scheme itself is declared in Info.plist. This has to happen before
+    /// the event loop starts, which is when the link a launch was for
+    /// arrives.
+    pub fn listen() {
+        let Some(mut decl) = ClassDecl::new("CommHubURLHandler", class!(NSObject)) else {
+            return;
+        };
+        unsafe {
+            decl.add_method(
+                sel!(handleGetURLEvent:withReplyEvent:),
+                handle_get_url as extern "C" fn(&Object, Sel, *mut Object, *mut Object),
+            );
+            let handler: *mut Object = msg_send![decl.register(), new];
+            let manager: *mut Object =
+                msg_send![class!(NSAppleEventManager), sharedAppleEventManager];
+            let _: () = msg_send![manager,
+                setEventHandler: handler
+                andSelector: sel!(handleGetURLEvent:withReplyEvent:)
+                forEventClass: INTERNET_EVENT_CLASS
+                andEventID: GET_URL];
+        }
+    }
+
+    /// Routes links from here on, and any that came in before.
+    pub fn register(app: &AppHandle) -> io::Result<()> {
+        if APP.set(app.clone()).is_ok() {
+            for link in EARLY.lock().unwrap().drain(..) {
+                open(app, link);
+            }
+        }
+        Ok(())
+    }
+
+    extern "C" fn handle_get_url(_: &Object, _: Sel, event: *mut Object, _: *mut Object) {
+        let url = unsafe {
+            let descriptor: *mut Object =
+                msg_send![event, paramDescriptorForKeyword: DIRECT_OBJECT];
+            let string: *mut Object = msg_send![descriptor, stringValue];
+            if string.is_null() {
+                return;
+            }
+            let utf8: *const c_char = msg_send![string, UTF8String];
+            CStr::from_ptr(utf8).to_string_lossy().into_owned()
+        };
+        let Some(link) = find([url]) else {
+            return;
+        };
+        match APP.get() {
+            Some(app) => open(app, link),
+            None => EARLY.lock().unwrap().push(link),
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows", target_os = "macos")))]
+mod platform {
+    use std::io;
+
+    use tauri::AppHandle;
+
+    pub fn register(_app: &AppHandle) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Hands a freshly loaded webview the link that launched or woke the app.
+#[tauri::command]
+pub fn deeplink_take_pending(links: State<'_, DeepLinks>) -> Option<DeepLink> {
+    links.ready.store(true, Ordering::SeqCst);
+    links.pending.lock().unwrap().take()
+}
+
+/// Builds a shareable link, validated so it parses back to the same thing.
+#[tauri::command]
+pub fn deeplink_build(link: DeepLink) -> Result<String, String> {
+    link.validate().map_err(|e| e.to_string())?;
+    Ok(link.to_string())
+}
+
+/// Parses a pasted link, e.g. in the join server dialog.
+#[tauri::command]
+pub fn deeplink_parse(url: String) -> Result<DeepLink, String> {
+    url.parse().map_err(|e: link::Error| e.to_string())
+}
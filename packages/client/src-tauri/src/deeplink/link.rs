@@ -0,0 +1,234 @@
+//! The `commhub://` link format, parsed and built in one place so the two
+//! can't drift apart.
+//!
+//! ```text
+//! commhub://invite/<code>
+//! commhub://channel/<channelId>
+//! commhub://channel/<channelId>/message/<messageId>
+//! ```
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+pub const SCHEME: &str = "commhub";
+
+// Matches JoinServerDto on the server
+const INVITE_CODE_LENGTH: std::ops::RangeInclusive<usize> = 6..=32;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("not a valid URL: {0}")]
+    Url(#[from] url::ParseError),
+    #[error("not a {SCHEME}:// link")]
+    Scheme,
+    #[error("unknown link: {0}")]
+    Unknown(String),
+    #[error("invalid invite code")]
+    InviteCode,
+    #[error("invalid id: {0}")]
+    Id(String),
+}
+
+/// Where a link points. Payload of `deep-link://open`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum DeepLink {
+    Invite {
+        code: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    Channel {
+        channel_id: i64,
+    },
+    #[serde(rename_all = "camelCase")]
+    Message {
+        channel_id: i64,
+        message_id: i64,
+    },
+}
+
+impl FromStr for DeepLink {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let url = Url::parse(s.trim())?;
+        if url.scheme() != SCHEME {
+            return Err(Error::Scheme);
+        }
+        // `commhub://invite/X` puts "invite" in the host; some launchers
+        // drop the slashes and hand over `commhub:invite/X` instead. Empty
+        // segments are skipped since Windows shells tend to append a slash
+        let segments: Vec<&str> = url
+            .host_str()
+            .into_iter()
+            .chain(url.path().split('/'))
+            .filter(|segment| !segment.is_empty())
+            .collect();
+        let link = match segments.as_slice() {
+            ["invite", code] => DeepLink::Invite {
+                code: invite_code(code)?,
+            },
+            ["channel", channel] => DeepLink::Channel {
+                channel_id: id(channel)?,
+            },
+            ["channel", channel, "message", message] => DeepLink::Message {
+                channel_id: id(channel)?,
+                message_id: id(message)?,
+            },
+            _ => return Err(Error::Unknown(s.to_string())),
+        };
+        Ok(link)
+    }
+}
+
+impl fmt::Display for DeepLink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeepLink::Invite { code } => write!(f, "{SCHEME}://invite/{code}"),
+            DeepLink::Channel { channel_id } => write!(f, "{SCHEME}://channel/{channel_id}"),
+            DeepLink::Message {
+                channel_id,
+                message_id,
+            } => write!(f, "{SCHEME}://channel/{channel_id}/message/{message_id}"),
+        }
+    }
+}
+
+impl DeepLink {
+    /// Checks a link built by the webview before it's handed out, so what
+    /// gets shared always parses back to the same thing.
+    pub fn validate(&self) -> Result<(), Error> {
+        match self {
+            DeepLink::Invite { code } => invite_code(code).map(|_| ()),
+            DeepLink::Channel { channel_id } => positive(*channel_id),
+            DeepLink::Message {
+                channel_id,
+                message_id,
+            } => positive(*channel_id).and(positive(*message_id)),
+        }
+    }
+}
+
+fn invite_code(code: &str) -> Result<String, Error> {
+    if INVITE_CODE_LENGTH.contains(&code.len()) && code.bytes().all(|b| b.is_ascii_alphanumeric()) {
+        Ok(code.to_string())
+    } else {
+        Err(Error::InviteCode)
+    }
+}
+
+fn id(segment: &str) -> Result<i64, Error> {
+    let id = segment
+        .parse()
+        .map_err(|_| Error::Id(segment.to_string()))?;
+    positive(id).map(|_| id)
+}
+
+fn positive(id: i64) -> Result<(), Error> {
+    if id > 0 {
+        Ok(())
+    } else {
+        Err(Error::Id(id.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> Result<DeepLink, Error> {
+        s.parse()
+    }
+
+    #[test]
+    fn round_trips_every_kind() {
+        let links = [
+            DeepLink::Invite {
+                code: "aB3dE6".into(),
+            },
+            DeepLink::Channel { channel_id: 42 },
+            DeepLink::Message {
+                channel_id: 42,
+                message_id: 9001,
+            },
+        ];
+        for link in links {
+            link.validate().unwrap();
+            assert_eq!(parse(&link.to_string()).unwrap(), link);
+        }
+    }
+
+    #[test]
+    fn accepts_launcher_variants() {
+        let invite = DeepLink::Invite {
+            code: "abc123".into(),
+        };
+        assert_eq!(parse("commhub://invite/abc123").unwrap(), invite);
+        assert_eq!(parse("commhub:invite/abc123").unwrap(), invite);
+        assert_eq!(parse("commhub://invite/abc123/").unwrap(), invite);
+        assert_eq!(parse("  commhub://invite/abc123\n").unwrap(), invite);
+        assert_eq!(
+            parse("commhub://channel/7/message/8/").unwrap(),
+            DeepLink::Message {
+                channel_id: 7,
+                message_id: 8
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_other_schemes_and_shapes() {
+        assert!(matches!(parse("https://invite/abc123"), Err(Error::Scheme)));
+        assert!(matches!(parse("not a link"), Err(Error::Url(_))));
+        assert!(matches!(
+            parse("commhub://server/1"),
+            Err(Error::Unknown(_))
+        ));
+        assert!(matches!(
+            parse("commhub://channel/1/message"),
+            Err(Error::Unknown(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_bad_codes_and_ids() {
+        assert!(matches!(
+            parse("commhub://invite/abc"),
+            Err(Error::InviteCode)
+        ));
+        assert!(matches!(
+            parse(&format!("commhub://invite/{}", "a".repeat(33))),
+            Err(Error::InviteCode)
+        ));
+        assert!(matches!(
+            parse("commhub://invite/abc-123"),
+            Err(Error::InviteCode)
+        ));
+        assert!(matches!(parse("commhub://channel/0"), Err(Error::Id(_))));
+        assert!(matches!(parse("commhub://channel/-4"), Err(Error::Id(_))));
+        assert!(matches!(parse("commhub://channel/abc"), Err(Error::Id(_))));
+        assert!(matches!(
+            parse("commhub://channel/1/message/99999999999999999999"),
+            Err(Error::Id(_))
+        ));
+    }
+
+    #[test]
+    fn validate_matches_parse() {
+        assert!(DeepLink::Invite {
+            code: "has space".into()
+        }
+        .validate()
+        .is_err());
+        assert!(DeepLink::Channel { channel_id: 0 }.validate().is_err());
+        assert!(DeepLink::Message {
+            channel_id: 1,
+            message_id: -1
+        }
+        .validate()
+        .is_err());
+    }
+}
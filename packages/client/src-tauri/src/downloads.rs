@@ -0,0 +1,519 @@
+//! Attachment downloads: "save as" with progress, and the local cache that
+//! viewed media renders from.
+//!
+//! A save streams to `<destination>.part` (see [`fetch`]), reporting
+//! `download://progress` to the window that asked. Pausing stops the stream
+//! and keeps the part, and resuming continues it with a range request; a
+//! failed download can be resumed the same way. The finished file is hashed
+//! and checked against the SHA-256 on the attachment record, where the
+//! server records one. Every download ends with `download://finished`, as
+//! done, failed, cancelled or paused.
+//!
+//! Media the webview shows is fetched once into a content-addressed cache
+//! under `$APPLOCALDATA/attachments` (see [`store`]) and loaded from there
+//! through the asset protocol afterwards.
+
+mod fetch;
+mod store;
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde::Serialize;
+use tauri::api::dialog::blocking::FileDialogBuilder;
+use tauri::{AppHandle, Manager, State, Window};
+use tokio_util::sync::CancellationToken;
+use url::Url;
+
+use fetch::Remote;
+use store::{Store, Usage};
+
+const CACHE_DIR: &str = "attachments";
+/// Reported without a known total, progress goes out once per this many bytes.
+const PROGRESS_STEP: u64 = 1024 * 1024;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Fetch(#[from] fetch::Error),
+    #[error(transparent)]
+    Store(#[from] store::Error),
+    #[error("failed to save download: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("invalid download url: {0}")]
+    Url(#[from] url::ParseError),
+    #[error("only http and https attachments can be downloaded")]
+    Scheme,
+    #[error("download {0} isn't paused")]
+    NotPaused(u64),
+    #[error("app directories are unavailable")]
+    NoAppDir,
+    #[error(transparent)]
+    Runtime(#[from] tauri::Error),
+}
+
+/// Payload of `download://progress`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadProgress {
+    pub id: u64,
+    pub received: u64,
+    pub total: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum DownloadOutcome {
+    #[serde(rename_all = "camelCase")]
+    Done {
+        path: PathBuf,
+        sha256: String,
+        /// Whether it matched the hash on the attachment record.
+        verified: bool,
+    },
+    Failed {
+        error: String,
+    },
+    Cancelled,
+    Paused,
+}
+
+/// Payload of `download://finished`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadFinished {
+    pub id: u64,
+    #[serde(flatten)]
+    pub outcome: DownloadOutcome,
+}
+
+/// A save, kept between a pause and the resume.
+#[derive(Debug, Clone)]
+struct Job {
+    url: Url,
+    destination: PathBuf,
+    /// Hex SHA-256 from the attachment record, if it has one.
+    sha256: Option<String>,
+    remote: Remote,
+}
+
+impl Job {
+    fn part(&self) -> PathBuf {
+        let mut part = self.destination.as_os_str().to_owned();
+        part.push(".part");
+        PathBuf::from(part)
+    }
+}
+
+struct Active {
+    cancel: CancellationToken,
+    pause: Arc<AtomicBool>,
+}
+
+pub struct Downloads {
+    client: reqwest::Client,
+    store: Store,
+    next_id: AtomicU64,
+    active: Mutex<HashMap<u64, Active>>,
+    paused: Mutex<HashMap<u64, Job>>,
+    // URLs being fetched into the cache
+    caching: Mutex<HashSet<String>>,
+}
+
+impl Downloads {
+    pub fn new(root: &Path, package_info: &tauri::PackageInfo) -> Result<Self, Error> {
+        let client = reqwest::Client::builder()
+            .user_agent(format!("CommHub/{}", package_info.version))
+            .connect_timeout(Duration::from_secs(15))
+            .build()
+            .unwrap_or_default();
+        Ok(Self {
+            client,
+            store: Store::open(root)?,
+            next_id: AtomicU64::new(1),
+            active: Mutex::new(HashMap::new()),
+            paused: Mutex::new(HashMap::new()),
+            caching: Mutex::new(HashSet::new()),
+        })
+    }
+
+    pub fn from_app(app: &AppHandle) -> Result<Self, Error> {
+        let root = app
+            .path_resolver()
+            .app_local_data_dir()
+            .ok_or(Error::NoAppDir)?
+            .join(CACHE_DIR);
+        Self::new(&root, app.package_info())
+    }
+
+    /// Starts saving `url` to `destination`. Returns the download's id.
+    pub fn save(
+        &self,
+        window: Window,
+        url: &str,
+        destination: PathBuf,
+        sha256: Option<String>,
+    ) -> Result<u64, Error> {
+        let job = Job {
+            url: parse_url(url)?,
+            destination,
+            sha256: hex_sha256(sha256),
+            remote: Remote::default(),
+        };
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.spawn(window, id, job);
+        Ok(id)
+    }
+
+    fn spawn(&self, window: Window, id: u64, mut job: Job) {
+        let cancel = CancellationToken::new();
+        let pause = Arc::new(AtomicBool::new(false));
+        self.active.lock().unwrap().insert(
+            id,
+            Active {
+                cancel: cancel.clone(),
+                pause: pause.clone(),
+            },
+        );
+
+        tauri::async_runtime::spawn(async move {
+            let downloads = window.state::<Downloads>();
+            let result = downloads.run(&window, id, &mut job, &cancel).await;
+            // Before `paused`, so an immediate resume isn't taken for this task
+            downloads.active.lock().unwrap().remove(&id);
+            let outcome = match result {
+                Ok(outcome) => outcome,
+                Err(Error::Fetch(fetch::Error::Cancelled)) if pause.load(Ordering::Relaxed) => {
+                    downloads.paused.lock().unwrap().insert(id, job);
+                    DownloadOutcome::Paused
+                }
+                Err(Error::Fetch(fetch::Error::Cancelled)) => {
+                    let _ = fs::remove_file(job.part());
+                    DownloadOutcome::Cancelled
+                }
+                Err(e) => {
+                    log!("[Downloads] Failed to download {}: {}", job.url, e);
+                    // Kept so resuming retries it
+                    downloads.paused.lock().unwrap().insert(id, job);
+                    DownloadOutcome::Failed {
+                        error: e.to_string(),
+                    }
+                }
+            };
+            let _ = window.emit("download://finished", DownloadFinished { id, outcome });
+        });
+    }
+
+    async fn run(
+        &self,
+        window: &Window,
+        id: u64,
+        job: &mut Job,
+        cancel: &CancellationToken,
+    ) -> Result<DownloadOutcome, Error> {
+        // Cached objects are named by their hash, which checks the copy. One
+        // cached under a different hash than the record's is fetched again.
+        let hit = self.store.lookup(job.url.as_str())?.filter(|hit| {
+            job.sha256
+                .as_ref()
+                .map_or(true, |expected| *expected == hit.sha256)
+        });
+        if let Some(hit) = hit {
+            let (from, to) = (hit.path, job.destination.clone());
+            let copied = tauri::async_runtime::spawn_blocking(move || {
+                fs::copy(&from, &to)?;
+                fetch::verify(&to, Some(&hit.sha256))
+            })
+            .await?;
+            let sha256 = match copied {
+                Ok(sha256) => sha256,
+                Err(e) => {
+                    let _ = fs::remove_file(&job.destination);
+                    return Err(e.into());
+                }
+            };
+            return Ok(DownloadOutcome::Done {
+                path: job.destination.clone(),
+                sha256,
+                verified: job.sha256.is_some(),
+            });
+        }
+
+        let window = window.clone();
+        let last_step = AtomicU64::new(u64::MAX);
+        let progress = move |received: u64, total: Option<u64>| {
+            let step = match total {
+                Some(total) => received * 100 / total.max(1),
+                None => received / PROGRESS_STEP,
+            };
+            if last_step.swap(step, Ordering::Relaxed) != step {
+                let _ = window.emit(
+                    "download://progress",
+                    DownloadProgress {
+                        id,
+                        received,
+                        total,
+                    },
+                );
+            }
+        };
+        let part = job.part();
+        fetch::fetch(
+            &self.client,
+            &job.url,
+            &part,
+            &mut job.remote,
+            cancel,
+            &progress,
+        )
+        .await?;
+
+        let (hashed, expected) = (part.clone(), job.sha256.clone());
+        let checked = tauri::async_runtime::spawn_blocking(move || {
+            fetch::verify(&hashed, expected.as_deref())
+        })
+        .await?;
+        let sha256 = match checked {
+            Err(e @ fetch::Error::Mismatch { .. }) => {
+                // Start over on a retry rather than trust any of it
+                let _ = fs::remove_file(&part);
+                job.remote = Remote::default();
+                return Err(e.into());
+            }
+            checked => checked?,
+        };
+        fs::rename(&part, &job.destination)?;
+        Ok(DownloadOutcome::Done {
+            path: job.destination.clone(),
+            sha256,
+            verified: job.sha256.is_some(),
+        })
+    }
+
+    pub fn pause(&self, id: u64) {
+        if let Some(active) = self.active.lock().unwrap().get(&id) {
+            active.pause.store(true, Ordering::Relaxed);
+            active.cancel.cancel();
+        }
+    }
+
+    pub fn resume(&self, window: Window, id: u64) -> Result<(), Error> {
+        let job = self
+            .paused
+            .lock()
+            .unwrap()
+            .remove(&id)
+            .ok_or(Error::NotPaused(id))?;
+        self.spawn(window, id, job);
+        Ok(())
+    }
+
+    pub fn cancel(&self, window: &Window, id: u64) {
+        if let Some(active) = self.active.lock().unwrap().get(&id) {
+            active.cancel.cancel();
+            return;
+        }
+        // A paused download has no task left to report it
+        if let Some(job) = self.paused.lock().unwrap().remove(&id) {
+            let _ = fs::remove_file(job.part());
+            let _ = window.emit(
+                "download://finished",
+                DownloadFinished {
+                    id,
+                    outcome: DownloadOutcome::Cancelled,
+                },
+            );
+        }
+    }
+
+    pub fn cancel_all(&self) {
+        for active in self.active.lock().unwrap().values() {
+            active.cancel.cancel();
+        }
+    }
+
+    /// The cached copy of `url`, if there is one.
+    pub fn cached(&self, url: &str) -> Result<Option<PathBuf>, Error> {
+        Ok(self.store.lookup(url)?.map(|hit| hit.path))
+    }
+
+    /// Fetches `url` into the cache in the background, unless it's there
+    /// already or on its way. A copy that doesn't match `sha256` is dropped.
+    pub fn cache(&self, app: &AppHandle, url: &str, sha256: Option<String>) -> Result<(), Error> {
+        let url = parse_url(url)?;
+        if self.store.lookup(url.as_str())?.is_some()
+            || !self.caching.lock().unwrap().insert(url.to_string())
+        {
+            return Ok(());
+        }
+
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            let downloads = app.state::<Downloads>();
+            if let Err(e) = downloads.fetch_into_cache(&url, hex_sha256(sha256)).await {
+                log!("[Downloads] Failed to cache {}: {}", url, e);
+            }
+            downloads.caching.lock().unwrap().remove(url.as_str());
+        });
+        Ok(())
+    }
+
+    async fn fetch_into_cache(&self, url: &Url, expected: Option<String>) -> Result<(), Error> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let part = self.store.partial_dir().join(format!("{}.part", id));
+        let mut remote = Remote::default();
+        let result = fetch::fetch(
+            &self.client,
+            url,
+            &part,
+            &mut remote,
+            &CancellationToken::new(),
+            &|_, _| {},
+        )
+        .await;
+        if let Err(e) = result {
+            let _ = fs::remove_file(&part);
+            return Err(e.into());
+        }
+
+        let hashed = part.clone();
+        let sha256 =
+            tauri::async_runtime::spawn_blocking(move || fetch::sha256_file(&hashed)).await??;
+        let too_large = fs::metadata(&part)?.len() > self.store.usage()?.limit;
+        if too_large || expected.is_some_and(|expected| expected != sha256) {
+            fs::remove_file(&part)?;
+            return Ok(());
+        }
+        self.store
+            .insert(url.as_str(), &part, &sha256, &extension(url))?;
+        Ok(())
+    }
+
+    pub fn cache_usage(&self) -> Result<Usage, Error> {
+        Ok(self.store.usage()?)
+    }
+
+    pub fn set_cache_limit(&self, limit: u64) -> Result<(), Error> {
+        Ok(self.store.set_limit(limit)?)
+    }
+
+    pub fn clear_cache(&self) -> Result<(), Error> {
+        Ok(self.store.clear()?)
+    }
+}
+
+fn parse_url(url: &str) -> Result<Url, Error> {
+    let url = Url::parse(url)?;
+    match url.scheme() {
+        "http" | "https" => Ok(url),
+        _ => Err(Error::Scheme),
+    }
+}
+
+/// A hash from an attachment record, lowercased to compare with ours.
+/// Records from before the server kept hashes have none.
+fn hex_sha256(sha256: Option<String>) -> Option<String> {
+    sha256
+        .filter(|sha256| sha256.len() == 64 && sha256.chars().all(|c| c.is_ascii_hexdigit()))
+        .map(|sha256| sha256.to_ascii_lowercase())
+}
+
+/// The URL's file extension, which the asset protocol serves the cached
+/// copy's content type by.
+fn extension(url: &Url) -> String {
+    Path::new(url.path())
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .filter(|extension| {
+            extension.len() <= 8 && extension.chars().all(|c| c.is_ascii_alphanumeric())
+        })
+        .unwrap_or("bin")
+        .to_ascii_lowercase()
+}
+
+/// Asks where to save an attachment and starts downloading it there, so
+/// only a path the user picked is ever written. `None` if the dialog was
+/// dismissed. Async so the dialog doesn't block the main thread.
+#[tauri::command]
+pub async fn download_save(
+    window: Window,
+    downloads: State<'_, Downloads>,
+    url: String,
+    filename: String,
+    sha256: Option<String>,
+) -> Result<Option<u64>, String> {
+    let Some(destination) = FileDialogBuilder::new()
+        .set_parent(&window)
+        .set_file_name(&filename)
+        .save_file()
+    else {
+        return Ok(None);
+    };
+    downloads
+        .save(window, &url, destination, sha256)
+        .map(Some)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn download_pause(downloads: State<'_, Downloads>, id: u64) {
+    downloads.pause(id);
+}
+
+#[tauri::command]
+pub fn download_resume(
+    window: Window,
+    downloads: State<'_, Downloads>,
+    id: u64,
+) -> Result<(), String> {
+    downloads.resume(window, id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn download_cancel(window: Window, downloads: State<'_, Downloads>, id: u64) {
+    downloads.cancel(&window, id);
+}
+
+/// The cached copy of an attachment, to load through the asset protocol.
+#[tauri::command]
+pub fn attachment_cached(
+    downloads: State<'_, Downloads>,
+    url: String,
+) -> Result<Option<PathBuf>, String> {
+    downloads.cached(&url).map_err(|e| e.to_string())
+}
+
+/// Caches an attachment the webview is showing, for next time.
+#[tauri::command]
+pub fn attachment_cache(
+    app: AppHandle,
+    downloads: State<'_, Downloads>,
+    url: String,
+    sha256: Option<String>,
+) -> Result<(), String> {
+    downloads
+        .cache(&app, &url, sha256)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn attachment_cache_usage(downloads: State<'_, Downloads>) -> Result<Usage, String> {
+    downloads.cache_usage().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn attachment_cache_set_limit(
+    downloads: State<'_, Downloads>,
+    limit: u64,
+) -> Result<(), String> {
+    downloads.set_cache_limit(limit).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn attachment_cache_clear(downloads: State<'_, Downloads>) -> Result<(), String> {
+    downloads.clear_cache().map_err(|e| e.to_string())
+}
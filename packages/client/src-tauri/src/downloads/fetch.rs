@@ -0,0 +1,476 @@
+//! Streams a URL to a file, continuing from where an earlier attempt
+//! stopped.
+//!
+//! A partial file is continued with a `Range` request, guarded by
+//! `If-Range` so a file that changed meanwhile comes back whole. Servers
+//! that ignore ranges answer 200 and the file starts over. The finished file
+//! is checked with [`verify`].
+
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use futures_util::StreamExt;
+use reqwest::header::{HeaderMap, CONTENT_RANGE, ETAG, IF_RANGE, LAST_MODIFIED, RANGE};
+use reqwest::{Client, StatusCode};
+use sha2::{Digest, Sha256};
+use tokio::fs::OpenOptions;
+use tokio::io::AsyncWriteExt;
+use tokio_util::sync::CancellationToken;
+use url::Url;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("failed to write download: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("download failed: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("download failed ({0})")]
+    Status(u16),
+    #[error("download ended early")]
+    Incomplete,
+    #[error("download cancelled")]
+    Cancelled,
+    #[error("download is corrupt: expected SHA-256 {expected}, got {actual}")]
+    Mismatch { expected: String, actual: String },
+}
+
+/// What's known about the remote file, kept across pauses.
+#[derive(Debug, Clone, Default)]
+pub struct Remote {
+    /// A strong `ETag`, or `Last-Modified`, for `If-Range`.
+    pub validator: Option<String>,
+    pub total: Option<u64>,
+}
+
+/// Called with the bytes on disk and the total, if known.
+pub type Progress<'a> = &'a (dyn Fn(u64, Option<u64>) + Send + Sync);
+
+/// Downloads `url` into `part`, appending to whatever is there already.
+pub async fn fetch(
+    client: &Client,
+    url: &Url,
+    part: &Path,
+    remote: &mut Remote,
+    cancel: &CancellationToken,
+    progress: Progress<'_>,
+) -> Result<(), Error> {
+    let offset = tokio::fs::metadata(part).await.map_or(0, |meta| meta.len());
+    if offset > 0 && remote.total == Some(offset) {
+        return Ok(());
+    }
+    let mut request = client.get(url.clone());
+    if offset > 0 {
+        request = request.header(RANGE, format!("bytes={}-", offset));
+        if let Some(validator) = &remote.validator {
+            request = request.header(IF_RANGE, validator);
+        }
+    }
+    let response = tokio::select! {
+        biased;
+        _ = cancel.cancelled() => return Err(Error::Cancelled),
+        response = request.send() => response?,
+    };
+
+    let (append, total) = match (response.status(), content_range(response.headers())) {
+        (StatusCode::PARTIAL_CONTENT, Some((start, total))) if start == offset => (true, total),
+        (StatusCode::OK, _) => (false, None),
+        (status, _) => return Err(Error::Status(status.as_u16())),
+    };
+    let start = if append { offset } else { 0 };
+    remote.validator = validator(response.headers());
+    remote.total = total.or_else(|| response.content_length().map(|length| start + length));
+
+    let mut file = if append {
+        OpenOptions::new().append(true).open(part).await?
+    } else {
+        tokio::fs::File::create(part).await?
+    };
+    let mut received = start;
+    progress(received, remote.total);
+    let mut stream = response.bytes_stream();
+    loop {
+        let chunk = tokio::select! {
+            biased;
+            _ = cancel.cancelled() => {
+                file.flush().await?;
+                return Err(Error::Cancelled);
+            }
+            chunk = stream.next() => chunk,
+        };
+        let Some(chunk) = chunk else {
+            break;
+        };
+        let chunk = chunk?;
+        file.write_all(&chunk).await?;
+        received += chunk.len() as u64;
+        progress(received, remote.total);
+    }
+    file.flush().await?;
+
+    if remote.total.is_some_and(|total| total != received) {
+        return Err(Error::Incomplete);
+    }
+    Ok(())
+}
+
+/// Hashes a finished download, failing if it isn't the `expected` hash.
+pub fn verify(path: &Path, expected: Option<&str>) -> Result<String, Error> {
+    let actual = sha256_file(path)?;
+    match expected {
+        Some(expected) if expected != actual => Err(Error::Mismatch {
+            expected: expected.to_string(),
+            actual,
+        }),
+        _ => Ok(actual),
+    }
+}
+
+/// Hex SHA-256 of a file on disk.
+pub fn sha256_file(path: &Path) -> std::io::Result<String> {
+    let mut hasher = Sha256::new();
+    let mut file = File::open(path)?;
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let read = file.read(&mut buf)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buf[..read]);
+    }
+    Ok(hex(&hasher.finalize()))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Where a 206 response's `Content-Range: bytes start-end/total` starts,
+/// and the whole file's size unless it's given as `*`.
+fn content_range(headers: &HeaderMap) -> Option<(u64, Option<u64>)> {
+    let range = headers.get(CONTENT_RANGE)?.to_str().ok()?;
+    let (span, total) = range.strip_prefix("bytes ")?.split_once('/')?;
+    let (start, _) = span.split_once('-')?;
+    Some((start.trim().parse().ok()?, total.trim().parse().ok()))
+}
+
+/// `If-Range` only takes strong validators, so weak ETags don't count.
+fn validator(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(ETAG)
+        .and_then(|etag| etag.to_str().ok())
+        .filter(|etag| !etag.starts_with("W/"))
+        .or_else(|| headers.get(LAST_MODIFIED)?.to_str().ok())
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+    use std::sync::{Arc, Mutex};
+
+    use crate::test_support::{Connection, Request, StandIn};
+
+    const FILE_SIZE: usize = 64 * 1024;
+
+    /// The file the stand-in serves, which tests change between attempts.
+    #[derive(Clone)]
+    struct Served {
+        body: Vec<u8>,
+        etag: String,
+        /// Whether `Range` requests are honoured.
+        ranges: bool,
+        /// Sends this many bytes of the body and then nothing more.
+        stall_after: Option<usize>,
+        /// Leaves out `Content-Length` and closes halfway through the body.
+        cut_short: bool,
+    }
+
+    /// A local stand-in for the attachment host, serving at `/file`.
+    struct Host {
+        url: Url,
+        served: Arc<Mutex<Served>>,
+        /// Headers of each request, lowercased.
+        requests: Arc<Mutex<Vec<HashMap<String, String>>>>,
+    }
+
+    impl Host {
+        async fn start(served: Served) -> Self {
+            let served = Arc::new(Mutex::new(served));
+            let requests = Arc::new(Mutex::new(Vec::new()));
+            let (state, seen) = (served.clone(), requests.clone());
+            let server = StandIn::start(move |request, connection| {
+                serve(request, connection, state.clone(), seen.clone())
+            })
+            .await;
+            Self {
+                url: Url::parse(&server.url("/file")).unwrap(),
+                served,
+                requests,
+            }
+        }
+
+        fn last_request(&self) -> HashMap<String, String> {
+            self.requests.lock().unwrap().last().cloned().unwrap()
+        }
+    }
+
+    async fn serve(
+        request: Request,
+        mut connection: Connection,
+        served: Arc<Mutex<Served>>,
+        requests: Arc<Mutex<Vec<HashMap<String, String>>>>,
+    ) {
+        let headers = request.headers;
+        requests.lock().unwrap().push(headers.clone());
+        let served = served.lock().unwrap().clone();
+
+        if request.path != "/file" {
+            return connection.not_found().await;
+        }
+        let offset = headers
+            .get("range")
+            .and_then(|range| {
+                range
+                    .strip_prefix("bytes=")?
+                    .strip_suffix('-')?
+                    .parse()
+                    .ok()
+            })
+            .filter(|_| served.ranges)
+            .filter(|_| {
+                headers
+                    .get("if-range")
+                    .map_or(true, |tag| *tag == served.etag)
+            });
+        let len = served.body.len();
+        let (status, range, body) = match offset {
+            Some(offset) => (
+                "206 Partial Content",
+                format!("Content-Range: bytes {}-{}/{}\r\n", offset, len - 1, len),
+                &served.body[offset..],
+            ),
+            None => ("200 OK", String::new(), &served.body[..]),
+        };
+        let (length, body) = if served.cut_short {
+            (String::new(), &body[..body.len() / 2])
+        } else {
+            (format!("Content-Length: {}\r\n", body.len()), body)
+        };
+        let head = format!(
+            "HTTP/1.1 {}\r\nETag: {}\r\n{}{}Connection: close\r\n\r\n",
+            status, served.etag, range, length
+        );
+        let _ = connection.write(head.as_bytes()).await;
+        match served.stall_after {
+            Some(sent) => {
+                let _ = connection.write(&body[..sent]).await;
+                std::future::pending::<()>().await;
+            }
+            None => {
+                let _ = connection.write(body).await;
+            }
+        }
+    }
+
+    fn served(fill: u8) -> Served {
+        Served {
+            body: (0..FILE_SIZE).map(|i| (i % 251) as u8 ^ fill).collect(),
+            etag: format!("\"v{}\"", fill),
+            ranges: true,
+            stall_after: None,
+            cut_short: false,
+        }
+    }
+
+    fn part(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "commhub-fetch-{}-{}.part",
+            std::process::id(),
+            name
+        ));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    /// Fetches until the stand-in stalls, then pauses the way the download
+    /// manager does, leaving `sent` bytes in the part.
+    async fn pause_after(server: &Host, part: &Path, remote: &mut Remote, sent: usize) {
+        server.served.lock().unwrap().stall_after = Some(sent);
+        let cancel = CancellationToken::new();
+        let pausing = cancel.clone();
+        let progress = move |received: u64, _: Option<u64>| {
+            if received == sent as u64 {
+                pausing.cancel();
+            }
+        };
+        let result = fetch(
+            &Client::new(),
+            &server.url,
+            part,
+            remote,
+            &cancel,
+            &progress,
+        )
+        .await;
+        assert!(matches!(result, Err(Error::Cancelled)), "{:?}", result);
+        assert_eq!(std::fs::metadata(part).unwrap().len(), sent as u64);
+        server.served.lock().unwrap().stall_after = None;
+    }
+
+    async fn fetch_all(server: &Host, part: &Path, remote: &mut Remote) -> Result<(), Error> {
+        fetch(
+            &Client::new(),
+            &server.url,
+            part,
+            remote,
+            &CancellationToken::new(),
+            &|_, _| {},
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn downloads_the_whole_file_with_progress() {
+        let server = Host::start(served(1)).await;
+        let part = part("whole");
+        let last = Arc::new(Mutex::new((0, None)));
+        let seen = last.clone();
+        let progress = move |received, total| *seen.lock().unwrap() = (received, total);
+        let mut remote = Remote::default();
+
+        fetch(
+            &Client::new(),
+            &server.url,
+            &part,
+            &mut remote,
+            &CancellationToken::new(),
+            &progress,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(std::fs::read(&part).unwrap(), served(1).body);
+        let size = Some(FILE_SIZE as u64);
+        assert_eq!(*last.lock().unwrap(), (FILE_SIZE as u64, size));
+        assert_eq!(remote.total, size);
+        assert_eq!(remote.validator.as_deref(), Some("\"v1\""));
+        assert!(!server.last_request().contains_key("range"));
+    }
+
+    #[tokio::test]
+    async fn resumes_a_paused_download_with_a_guarded_range() {
+        let server = Host::start(served(1)).await;
+        let part = part("resume");
+        let mut remote = Remote::default();
+        pause_after(&server, &part, &mut remote, 1000).await;
+
+        fetch_all(&server, &part, &mut remote).await.unwrap();
+
+        let request = server.last_request();
+        assert_eq!(request["range"], "bytes=1000-");
+        assert_eq!(request["if-range"], "\"v1\"");
+        assert_eq!(std::fs::read(&part).unwrap(), served(1).body);
+        assert_eq!(remote.total, Some(FILE_SIZE as u64));
+    }
+
+    #[tokio::test]
+    async fn a_file_that_changed_while_paused_starts_over() {
+        let server = Host::start(served(1)).await;
+        let part = part("changed");
+        let mut remote = Remote::default();
+        pause_after(&server, &part, &mut remote, 1000).await;
+        *server.served.lock().unwrap() = served(2);
+
+        fetch_all(&server, &part, &mut remote).await.unwrap();
+
+        assert_eq!(server.last_request()["if-range"], "\"v1\"");
+        assert_eq!(std::fs::read(&part).unwrap(), served(2).body);
+        assert_eq!(remote.validator.as_deref(), Some("\"v2\""));
+    }
+
+    #[tokio::test]
+    async fn servers_without_ranges_restart_the_file() {
+        let server = Host::start(Served {
+            ranges: false,
+            ..served(1)
+        })
+        .await;
+        let part = part("no-ranges");
+        let mut remote = Remote::default();
+        pause_after(&server, &part, &mut remote, 1000).await;
+
+        fetch_all(&server, &part, &mut remote).await.unwrap();
+
+        assert_eq!(server.last_request()["range"], "bytes=1000-");
+        assert_eq!(std::fs::read(&part).unwrap(), served(1).body);
+    }
+
+    #[tokio::test]
+    async fn a_finished_part_is_not_fetched_again() {
+        let server = Host::start(served(1)).await;
+        let part = part("finished");
+        let mut remote = Remote::default();
+        fetch_all(&server, &part, &mut remote).await.unwrap();
+
+        fetch_all(&server, &part, &mut remote).await.unwrap();
+
+        assert_eq!(server.requests.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn a_range_cut_short_is_incomplete() {
+        let server = Host::start(served(1)).await;
+        let part = part("cut-short");
+        let mut remote = Remote::default();
+        pause_after(&server, &part, &mut remote, 1000).await;
+        server.served.lock().unwrap().cut_short = true;
+
+        let result = fetch_all(&server, &part, &mut remote).await;
+
+        assert!(matches!(result, Err(Error::Incomplete)), "{:?}", result);
+        // What did arrive is kept for the next attempt
+        let kept = std::fs::metadata(&part).unwrap().len();
+        assert_eq!(kept, 1000 + (FILE_SIZE as u64 - 1000) / 2);
+    }
+
+    #[tokio::test]
+    async fn error_statuses_fail_the_download() {
+        let server = Host::start(served(1)).await;
+        let part = part("missing");
+        let mut url = server.url.clone();
+        url.set_path("/missing");
+
+        let result = fetch(
+            &Client::new(),
+            &url,
+            &part,
+            &mut Remote::default(),
+            &CancellationToken::new(),
+            &|_, _| {},
+        )
+        .await;
+
+        assert!(matches!(result, Err(Error::Status(404))), "{:?}", result);
+    }
+
+    #[test]
+    fn verify_checks_the_expected_hash() {
+        let path = part("verify");
+        std::fs::write(&path, b"abc").unwrap();
+        let abc = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+
+        assert_eq!(verify(&path, None).unwrap(), abc);
+        assert_eq!(verify(&path, Some(abc)).unwrap(), abc);
+        match verify(&path, Some(&"0".repeat(64))) {
+            Err(Error::Mismatch { expected, actual }) => {
+                assert_eq!(expected, "0".repeat(64));
+                assert_eq!(actual, abc);
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,360 @@
+//! Content-addressed attachment cache.
+//!
+//! Files live under `objects/`, named by their SHA-256, so an attachment
+//! posted twice is kept once. `index.sqlite3` maps URLs to them and records
+//! when each was last used; past the size limit, the least recently used go
+//! first.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+
+pub const DEFAULT_LIMIT: u64 = 1024 * 1024 * 1024;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("attachment cache database error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+    #[error("attachment cache io error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// A cached copy of an attachment.
+#[derive(Debug, Clone)]
+pub struct Hit {
+    pub path: PathBuf,
+    pub sha256: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Usage {
+    pub used: u64,
+    pub limit: u64,
+}
+
+pub struct Store {
+    objects: PathBuf,
+    partial: PathBuf,
+    conn: Mutex<Connection>,
+}
+
+impl Store {
+    pub fn open(root: &Path) -> Result<Self, Error> {
+        let objects = root.join("objects");
+        let partial = root.join("partial");
+        // Left by downloads cut short when the app last quit, which nothing
+        // would resume and the size limit doesn't see
+        if let Err(e) = fs::remove_dir_all(&partial) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                return Err(e.into());
+            }
+        }
+        fs::create_dir_all(&objects)?;
+        fs::create_dir_all(&partial)?;
+        let conn = Connection::open(root.join("index.sqlite3"))?;
+        conn.execute_batch(
+            "PRAGMA journal_mode = WAL;
+             PRAGMA synchronous = NORMAL;
+             PRAGMA foreign_keys = ON;
+             CREATE TABLE IF NOT EXISTS objects (
+                sha256 TEXT PRIMARY KEY,
+                file TEXT NOT NULL,
+                size INTEGER NOT NULL,
+                last_used INTEGER NOT NULL
+             );
+             CREATE INDEX IF NOT EXISTS objects_last_used ON objects (last_used);
+             CREATE TABLE IF NOT EXISTS urls (
+                url TEXT PRIMARY KEY,
+                sha256 TEXT NOT NULL REFERENCES objects (sha256) ON DELETE CASCADE
+             );
+             CREATE TABLE IF NOT EXISTS settings (
+                key TEXT PRIMARY KEY,
+                value INTEGER NOT NULL
+             );",
+        )?;
+        Ok(Self {
+            objects,
+            partial,
+            conn: Mutex::new(conn),
+        })
+    }
+
+    /// Where downloads go until they're inserted, on the same filesystem
+    /// as the objects so they can be moved in.
+    pub fn partial_dir(&self) -> &Path {
+        &self.partial
+    }
+
+    /// The cached copy of `url`, marking it used.
+    pub fn lookup(&self, url: &str) -> Result<Option<Hit>, Error> {
+        let conn = self.conn.lock().unwrap();
+        let found: Option<(String, String)> = conn
+            .query_row(
+                "SELECT objects.sha256, objects.file FROM urls
+                 JOIN objects ON objects.sha256 = urls.sha256
+                 WHERE urls.url = ?1",
+                [url],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        let Some((sha256, file)) = found else {
+            return Ok(None);
+        };
+
+        let path = self.objects.join(&file);
+        if !path.is_file() {
+            // Deleted from under us; forget it
+            conn.execute("DELETE FROM objects WHERE sha256 = ?1", [&sha256])?;
+            return Ok(None);
+        }
+        conn.execute(
+            "UPDATE objects SET last_used = ?1 WHERE sha256 = ?2",
+            params![now(), sha256],
+        )?;
+        Ok(Some(Hit { path, sha256 }))
+    }
+
+    /// Moves a downloaded file into the cache as the copy of `url`, then
+    /// evicts down to the limit.
+    pub fn insert(
+        &self,
+        url: &str,
+        downloaded: &Path,
+        sha256: &str,
+        extension: &str,
+    ) -> Result<Hit, Error> {
+        let size = fs::metadata(downloaded)?.len();
+        let conn = self.conn.lock().unwrap();
+        let existing: Option<String> = conn
+            .query_row(
+                "SELECT file FROM objects WHERE sha256 = ?1",
+                [sha256],
+                |row| row.get(0),
+            )
+            .optional()?;
+        let file = match existing {
+            // Already have these bytes, perhaps under another URL
+            Some(file) if self.objects.join(&file).is_file() => {
+                fs::remove_file(downloaded)?;
+                file
+            }
+            _ => {
+                let file = format!("{}.{}", sha256, extension);
+                fs::rename(downloaded, self.objects.join(&file))?;
+                file
+            }
+        };
+        // An upsert, since replacing the row would cascade to its URLs
+        conn.execute(
+            "INSERT INTO objects (sha256, file, size, last_used) VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT (sha256) DO UPDATE SET
+                file = excluded.file,
+                size = excluded.size,
+                last_used = excluded.last_used",
+            params![sha256, file, size as i64, now()],
+        )?;
+        conn.execute(
+            "INSERT OR REPLACE INTO urls (url, sha256) VALUES (?1, ?2)",
+            params![url, sha256],
+        )?;
+        drop(conn);
+
+        self.evict()?;
+        Ok(Hit {
+            path: self.objects.join(file),
+            sha256: sha256.to_string(),
+        })
+    }
+
+    pub fn usage(&self) -> Result<Usage, Error> {
+        let conn = self.conn.lock().unwrap();
+        Ok(Usage {
+            used: used(&conn)?,
+            limit: limit(&conn)?,
+        })
+    }
+
+    pub fn set_limit(&self, limit: u64) -> Result<(), Error> {
+        self.conn.lock().unwrap().execute(
+            "INSERT OR REPLACE INTO settings (key, value) VALUES ('limit', ?1)",
+            [limit as i64],
+        )?;
+        self.evict()
+    }
+
+    /// Evicts least recently used objects until the cache fits its limit.
+    pub fn evict(&self) -> Result<(), Error> {
+        let conn = self.conn.lock().unwrap();
+        let limit = limit(&conn)?;
+        let mut used = used(&conn)?;
+        while used > limit {
+            let oldest: Option<(String, String, i64)> = conn
+                .query_row(
+                    "SELECT sha256, file, size FROM objects ORDER BY last_used LIMIT 1",
+                    [],
+                    |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+                )
+                .optional()?;
+            let Some((sha256, file, size)) = oldest else {
+                break;
+            };
+            let _ = fs::remove_file(self.objects.join(file));
+            conn.execute("DELETE FROM objects WHERE sha256 = ?1", [sha256])?;
+            used = used.saturating_sub(size as u64);
+        }
+        Ok(())
+    }
+
+    pub fn clear(&self) -> Result<(), Error> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("DELETE FROM objects", [])?;
+        fs::remove_dir_all(&self.objects)?;
+        fs::create_dir_all(&self.objects)?;
+        Ok(())
+    }
+}
+
+fn used(conn: &Connection) -> Result<u64, Error> {
+    let used: i64 = conn.query_row("SELECT COALESCE(SUM(size), 0) FROM objects", [], |row| {
+        row.get(0)
+    })?;
+    Ok(used as u64)
+}
+
+fn limit(conn: &Connection) -> Result<u64, Error> {
+    let limit: Option<i64> = conn
+        .query_row(
+            "SELECT value FROM settings WHERE key = 'limit'",
+            [],
+            |row| row.get(0),
+        )
+        .optional()?;
+    Ok(limit.map_or(DEFAULT_LIMIT, |limit| limit as u64))
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as i64)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+
+    use std::thread::sleep;
+    use std::time::Duration;
+
+    /// Inserts `size` bytes of `fill` as the download of `url`. Sleeps a
+    /// little first so last-used times differ.
+    fn put(store: &Store, url: &str, fill: u8, size: usize) -> Hit {
+        sleep(Duration::from_millis(5));
+        let part = store.partial_dir().join(format!("{}.part", fill));
+        fs::write(&part, vec![fill; size]).unwrap();
+        store
+            .insert(url, &part, &format!("{:064x}", fill), "bin")
+            .unwrap()
+    }
+
+    fn cached(store: &Store, url: &str) -> bool {
+        sleep(Duration::from_millis(5));
+        store.lookup(url).unwrap().is_some()
+    }
+
+    #[test]
+    fn keeps_identical_files_once() {
+        let store = Store::open(&test_support::root("store", "dedup")).unwrap();
+        let first = put(&store, "https://a.test/1.png", 1, 100);
+        let second = put(&store, "https://b.test/copy.png", 1, 100);
+
+        assert_eq!(first.path, second.path);
+        assert_eq!(store.usage().unwrap().used, 100);
+        assert_eq!(fs::read_dir(&store.objects).unwrap().count(), 1);
+        assert_eq!(
+            store
+                .lookup("https://b.test/copy.png")
+                .unwrap()
+                .unwrap()
+                .path,
+            first.path
+        );
+        assert!(fs::read_dir(store.partial_dir()).unwrap().next().is_none());
+    }
+
+    #[test]
+    fn evicts_the_least_recently_used() {
+        let store = Store::open(&test_support::root("store", "lru")).unwrap();
+        store.set_limit(250).unwrap();
+        put(&store, "https://a.test/a", 1, 100);
+        put(&store, "https://a.test/b", 2, 100);
+        // Using it makes `b` the oldest
+        assert!(cached(&store, "https://a.test/a"));
+        put(&store, "https://a.test/c", 3, 100);
+
+        assert!(cached(&store, "https://a.test/a"));
+        assert!(!cached(&store, "https://a.test/b"));
+        assert!(cached(&store, "https://a.test/c"));
+        assert_eq!(store.usage().unwrap().used, 200);
+        assert_eq!(fs::read_dir(&store.objects).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn applies_a_new_limit_right_away() {
+        let store = Store::open(&test_support::root("store", "limit")).unwrap();
+        assert_eq!(store.usage().unwrap().limit, DEFAULT_LIMIT);
+        put(&store, "https://a.test/a", 1, 100);
+        put(&store, "https://a.test/b", 2, 100);
+        put(&store, "https://a.test/c", 3, 100);
+
+        store.set_limit(1000).unwrap();
+        assert_eq!(store.usage().unwrap().used, 300);
+        store.set_limit(150).unwrap();
+        assert_eq!(
+            store.usage().unwrap(),
+            Usage {
+                used: 100,
+                limit: 150
+            }
+        );
+        assert!(cached(&store, "https://a.test/c"));
+        assert!(!cached(&store, "https://a.test/a"));
+    }
+
+    #[test]
+    fn reopening_keeps_objects_and_drops_partial_files() {
+        let root = test_support::root("store", "reopen");
+        let store = Store::open(&root).unwrap();
+        store.set_limit(500).unwrap();
+        put(&store, "https://a.test/a", 1, 100);
+        fs::write(store.partial_dir().join("7.part"), [0u8; 300]).unwrap();
+        drop(store);
+
+        let store = Store::open(&root).unwrap();
+        assert!(fs::read_dir(store.partial_dir()).unwrap().next().is_none());
+        assert!(cached(&store, "https://a.test/a"));
+        assert_eq!(
+            store.usage().unwrap(),
+            Usage {
+                used: 100,
+                limit: 500
+            }
+        );
+    }
+
+    #[test]
+    fn forgets_objects_deleted_from_disk() {
+        let store = Store::open(&test_support::root("store", "deleted")).unwrap();
+        let hit = put(&store, "https://a.test/a", 1, 100);
+        fs::remove_file(&hit.path).unwrap();
+
+        assert!(!cached(&store, "https://a.test/a"));
+        assert_eq!(store.usage().unwrap().used, 0);
+    }
+}
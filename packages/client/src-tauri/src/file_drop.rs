@@ -0,0 +1,193 @@
+//! Files dropped onto a window, validated natively before anything is
+//! uploaded.
+//!
+//! Tauri takes over file drops, so the webview only ever sees paths. Each
+//! dropped file is checked by [`inspect`] and the window it landed on gets a
+//! `file-drop://preview` with metadata and image thumbnails; problems are
+//! reported per file rather than after the upload has failed. Accepted files
+//! are registered by id and handed to [`crate::uploads`], which streams them
+//! from disk, so they never pass through the webview. The attach button's
+//! file picker is opened here too, and what it picks is registered the
+//! same way.
+
+mod inspect;
+
+pub use inspect::{inspect, Problem, MAX_FILE_SIZE};
+
+use std::collections::HashMap;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use base64::Engine as _;
+use image::io::{Limits, Reader as ImageReader};
+use image::ImageOutputFormat;
+use serde::Serialize;
+use tauri::api::dialog::blocking::FileDialogBuilder;
+use tauri::{FileDropEvent, GlobalWindowEvent, Manager, State, Window, WindowEvent};
+
+const THUMBNAIL_SIZE: u32 = 160;
+// Decoding is skipped past this, however small the file
+const MAX_THUMBNAIL_SOURCE: u32 = 12_000;
+
+/// Images the `image` crate can decode for a thumbnail.
+const THUMBNAIL_MIME_TYPES: &[&str] = &[
+    "image/png",
+    "image/jpeg",
+    "image/gif",
+    "image/webp",
+    "image/bmp",
+];
+
+/// Payload of `file-drop://hover`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DropHover {
+    pub count: usize,
+}
+
+/// One dropped file in `file-drop://preview`. Only files without a problem
+/// can be uploaded.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DroppedFile {
+    pub id: u64,
+    pub name: String,
+    pub size: u64,
+    pub mime_type: String,
+    /// PNG data URL for images.
+    pub thumbnail: Option<String>,
+    pub problem: Option<String>,
+}
+
+/// Payload of `file-drop://preview`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DropPreview {
+    pub files: Vec<DroppedFile>,
+}
+
+#[derive(Default)]
+pub struct FileDrops {
+    next_id: AtomicU64,
+    accepted: Mutex<HashMap<u64, PathBuf>>,
+}
+
+impl FileDrops {
+    /// Validates dropped paths, registering the ones that can be uploaded.
+    fn preview(&self, paths: &[PathBuf]) -> DropPreview {
+        let files = paths
+            .iter()
+            .map(|path| {
+                let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+                let fallback_name = path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| path.display().to_string());
+                match inspect::inspect(path) {
+                    Ok(file) => {
+                        let thumbnail = THUMBNAIL_MIME_TYPES
+                            .contains(&file.mime.as_str())
+                            .then(|| thumbnail(path))
+                            .flatten();
+                        self.accepted.lock().unwrap().insert(id, path.clone());
+                        DroppedFile {
+                            id,
+                            name: file.name,
+                            size: file.size,
+                            mime_type: file.mime,
+                            thumbnail,
+                            problem: None,
+                        }
+                    }
+                    Err(problem) => DroppedFile {
+                        id,
+                        name: fallback_name,
+                        size: 0,
+                        mime_type: String::new(),
+                        thumbnail: None,
+                        problem: Some(problem.to_string()),
+                    },
+                }
+            })
+            .collect();
+        DropPreview { files }
+    }
+
+    /// Forgets dropped files the user decided not to send.
+    pub fn discard(&self, ids: &[u64]) {
+        let mut accepted = self.accepted.lock().unwrap();
+        for id in ids {
+            accepted.remove(id);
+        }
+    }
+
+    /// The path of an accepted file, which can only be uploaded once.
+    pub fn take(&self, id: u64) -> Option<PathBuf> {
+        self.accepted.lock().unwrap().remove(&id)
+    }
+}
+
+/// A PNG data URL of the image scaled to fit [`THUMBNAIL_SIZE`].
+fn thumbnail(path: &Path) -> Option<String> {
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_THUMBNAIL_SOURCE);
+    limits.max_image_height = Some(MAX_THUMBNAIL_SOURCE);
+    let mut reader = ImageReader::open(path).ok()?.with_guessed_format().ok()?;
+    reader.limits(limits);
+    let image = reader.decode().ok()?;
+
+    let mut png = Cursor::new(Vec::new());
+    image
+        .thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE)
+        .write_to(&mut png, ImageOutputFormat::Png)
+        .ok()?;
+    Some(format!(
+        "data:image/png;base64,{}",
+        base64::engine::general_purpose::STANDARD.encode(png.into_inner())
+    ))
+}
+
+pub fn handle_window_event(event: &GlobalWindowEvent) {
+    let WindowEvent::FileDrop(drop) = event.event() else {
+        return;
+    };
+    let window = event.window().clone();
+    match drop {
+        FileDropEvent::Hovered(paths) => {
+            let _ = window.emit("file-drop://hover", DropHover { count: paths.len() });
+        }
+        FileDropEvent::Dropped(paths) => {
+            let paths = paths.clone();
+            // Sniffing and thumbnails read from disk; keep them off the
+            // event loop
+            tauri::async_runtime::spawn_blocking(move || {
+                let preview = window.state::<FileDrops>().preview(&paths);
+                let _ = window.emit("file-drop://preview", preview);
+            });
+        }
+        _ => {
+            let _ = window.emit("file-drop://cancel", ());
+        }
+    }
+}
+
+#[tauri::command]
+pub fn file_drop_discard(drops: State<'_, FileDrops>, ids: Vec<u64>) {
+    drops.discard(&ids);
+}
+
+/// Opens the file picker for the attach button and checks what was picked
+/// like a drop. Async so the dialog doesn't block the main thread.
+#[tauri::command]
+pub async fn file_drop_pick(
+    window: Window,
+    drops: State<'_, FileDrops>,
+) -> Result<Vec<DroppedFile>, String> {
+    let picked = FileDialogBuilder::new()
+        .set_parent(&window)
+        .pick_files()
+        .unwrap_or_default();
+    Ok(drops.preview(&picked).files)
+}
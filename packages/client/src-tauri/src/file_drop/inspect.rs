@@ -0,0 +1,363 @@
+//! Validation of a dropped file: size, a blocked-types list and the content
+//! sniffed from its first bytes against what its extension claims.
+
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::Path;
+
+use infer::MatcherType;
+
+/// Matches the server's upload limit and `MAX_FILE_SIZE` in
+/// FileUploadButton.tsx.
+pub const MAX_FILE_SIZE: u64 = 20 * 1024 * 1024;
+
+/// Enough for every signature infer knows, including zip-based documents.
+const SNIFF_LEN: usize = 8 * 1024;
+
+const FALLBACK_MIME: &str = "application/octet-stream";
+
+/// Executables, scripts and installer packages that run when opened.
+const BLOCKED_EXTENSIONS: &[&str] = &[
+    "apk", "app", "appimage", "appx", "bat", "cmd", "com", "command", "cpl", "deb", "desktop",
+    "dll", "dmg", "exe", "hta", "jar", "js", "jse", "lnk", "msi", "msix", "msp", "pif", "pkg",
+    "ps1", "psm1", "reg", "rpm", "scr", "sh", "vb", "vbe", "vbs", "wsc", "wsf", "wsh",
+];
+
+/// Executable formats by what infer sniffs them as, whatever they're named.
+const BLOCKED_CONTENT: &[&str] = &["dex", "dey", "dll", "elf", "exe", "mach"];
+
+/// Extensions whose files always start with a signature, so content infer
+/// can't identify means the name is lying.
+const SIGNED_EXTENSIONS: &[&str] = &[
+    "7z", "bmp", "gif", "gz", "jpeg", "jpg", "pdf", "png", "rar", "webp", "zip",
+];
+
+/// Extensions that are interchangeable for the same content: containers
+/// shared by several formats, and alternative spellings.
+const EQUIVALENT: &[&[&str]] = &[
+    &["jpg", "jpeg", "jpe"],
+    &["tif", "tiff"],
+    &["heic", "heif"],
+    &["mp4", "m4v", "m4a", "m4b", "mov", "3gp", "3g2"],
+    &["mkv", "webm"],
+    &["ogg", "oga", "ogv", "opus", "ogx"],
+    &["gz", "tgz"],
+    &[
+        "zip", "docx", "xlsx", "pptx", "odt", "ods", "odp", "epub", "jar", "apk",
+    ],
+    &["doc", "xls", "ppt", "msi", "msg"],
+];
+
+const MIME_TYPES: &[(&str, &str)] = &[
+    ("png", "image/png"),
+    ("jpg", "image/jpeg"),
+    ("jpeg", "image/jpeg"),
+    ("gif", "image/gif"),
+    ("webp", "image/webp"),
+    ("bmp", "image/bmp"),
+    ("svg", "image/svg+xml"),
+    ("ico", "image/x-icon"),
+    ("tif", "image/tiff"),
+    ("tiff", "image/tiff"),
+    ("heic", "image/heic"),
+    ("avif", "image/avif"),
+    ("mp4", "video/mp4"),
+    ("m4v", "video/x-m4v"),
+    ("mov", "video/quicktime"),
+    ("webm", "video/webm"),
+    ("mkv", "video/x-matroska"),
+    ("avi", "video/x-msvideo"),
+    ("mp3", "audio/mpeg"),
+    ("m4a", "audio/mp4"),
+    ("wav", "audio/wav"),
+    ("ogg", "audio/ogg"),
+    ("oga", "audio/ogg"),
+    ("opus", "audio/opus"),
+    ("flac", "audio/flac"),
+    ("aac", "audio/aac"),
+    ("pdf", "application/pdf"),
+    ("txt", "text/plain"),
+    ("log", "text/plain"),
+    ("md", "text/markdown"),
+    ("csv", "text/csv"),
+    ("json", "application/json"),
+    ("rtf", "application/rtf"),
+    ("doc", "application/msword"),
+    (
+        "docx",
+        "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+    ),
+    ("xls", "application/vnd.ms-excel"),
+    (
+        "xlsx",
+        "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+    ),
+    ("ppt", "application/vnd.ms-powerpoint"),
+    (
+        "pptx",
+        "application/vnd.openxmlformats-officedocument.presentationml.presentation",
+    ),
+    ("odt", "application/vnd.oasis.opendocument.text"),
+    ("ods", "application/vnd.oasis.opendocument.spreadsheet"),
+    ("odp", "application/vnd.oasis.opendocument.presentation"),
+    ("epub", "application/epub+zip"),
+    ("zip", "application/zip"),
+    ("rar", "application/vnd.rar"),
+    ("7z", "application/x-7z-compressed"),
+    ("tar", "application/x-tar"),
+    ("gz", "application/gzip"),
+    ("tgz", "application/gzip"),
+    ("bz2", "application/x-bzip2"),
+    ("xz", "application/x-xz"),
+];
+
+/// Why a dropped file won't be uploaded, as shown next to it.
+#[derive(Debug, thiserror::Error)]
+pub enum Problem {
+    #[error("couldn't be read: {0}")]
+    Unreadable(#[from] std::io::Error),
+    #[error("is a folder")]
+    NotAFile,
+    #[error("is empty")]
+    Empty,
+    #[error("exceeds the {} MB limit", MAX_FILE_SIZE / 1024 / 1024)]
+    TooLarge,
+    #[error("is a blocked file type ({0})")]
+    Blocked(String),
+    #[error("is named .{extension} but contains {detected}")]
+    Mismatch { extension: String, detected: String },
+}
+
+/// A file that passed validation. Images may still exceed [`MAX_FILE_SIZE`]
+/// until they've been prepared for upload.
+#[derive(Debug, Clone)]
+pub struct Inspected {
+    pub name: String,
+    pub size: u64,
+    pub mime: String,
+}
+
+pub fn inspect(path: &Path) -> Result<Inspected, Problem> {
+    let metadata = fs::metadata(path)?;
+    if !metadata.is_file() {
+        return Err(Problem::NotAFile);
+    }
+    let size = metadata.len();
+    if size == 0 {
+        return Err(Problem::Empty);
+    }
+
+    let mut header = Vec::with_capacity(SNIFF_LEN);
+    File::open(path)?
+        .take(SNIFF_LEN as u64)
+        .read_to_end(&mut header)?;
+
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let extension = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_ascii_lowercase());
+    let mime = classify(extension.as_deref(), &header)?;
+    // Images are resized before upload, so the limit applies to what's sent
+    if size > MAX_FILE_SIZE && !mime.starts_with("image/") {
+        return Err(Problem::TooLarge);
+    }
+    Ok(Inspected {
+        name,
+        size,
+        mime: mime.to_string(),
+    })
+}
+
+/// The MIME type to upload as, given the extension and the first bytes.
+fn classify(extension: Option<&str>, header: &[u8]) -> Result<&'static str, Problem> {
+    if let Some(ext) = extension.filter(|ext| BLOCKED_EXTENSIONS.contains(ext)) {
+        return Err(Problem::Blocked(format!(".{}", ext)));
+    }
+
+    // infer's text matchers (html, xml, shebangs) say nothing about
+    // whether a name is honest
+    let detected = infer::get(header).filter(|kind| kind.matcher_type() != MatcherType::Text);
+    if let Some(kind) = detected {
+        if BLOCKED_CONTENT.contains(&kind.extension()) {
+            return Err(Problem::Blocked("executable".to_string()));
+        }
+    }
+
+    let declared = extension.and_then(|ext| {
+        MIME_TYPES
+            .iter()
+            .find(|(known, _)| *known == ext)
+            .map(|(_, mime)| *mime)
+    });
+    match (extension, detected) {
+        (Some(ext), Some(kind)) if declared.is_some() && !equivalent(ext, kind.extension()) => {
+            Err(Problem::Mismatch {
+                extension: ext.to_string(),
+                detected: kind.mime_type().to_string(),
+            })
+        }
+        (Some(ext), None) if SIGNED_EXTENSIONS.contains(&ext) => Err(Problem::Mismatch {
+            extension: ext.to_string(),
+            detected: "something else".to_string(),
+        }),
+        _ => Ok(declared
+            .or(detected.map(|kind| kind.mime_type()))
+            .unwrap_or(FALLBACK_MIME)),
+    }
+}
+
+fn equivalent(extension: &str, detected: &str) -> bool {
+    extension == detected
+        || EQUIVALENT
+            .iter()
+            .any(|group| group.contains(&extension) && group.contains(&detected))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PNG: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR\0\0\0\x01\0\0\0\x01\x08\x06\0\0\0";
+    const JPEG: &[u8] = b"\xff\xd8\xff\xe0\0\x10JFIF\0\x01\x01\0\0\x01\0\x01\0\0";
+    // A full 64-byte header; infer wants more than 52 bytes
+    const ELF: &[u8] = b"\x7fELF\x02\x01\x01\0\0\0\0\0\0\0\0\0\x02\0\x3e\0\x01\0\0\0\
+        \0\x10\x40\0\0\0\0\0\x40\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\
+        \0\0\0\0\x40\0\x38\0\x01\0\x40\0\0\0\0\0";
+    const ZIP: &[u8] =
+        b"PK\x03\x04\x14\0\0\0\x08\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\x08\0\0\0data.bin";
+    const HTML: &[u8] = b"<!DOCTYPE html><html><body>hi</body></html>";
+    const SHEBANG: &[u8] = b"#!/bin/sh\necho hi\n";
+    const PLAIN: &[u8] = b"just some words\n";
+
+    // Extension, first bytes, then the MIME type or the problem's message
+    type Case<'a> = (Option<&'a str>, &'a [u8], Result<&'a str, &'a str>);
+
+    fn outcome(extension: Option<&str>, header: &[u8]) -> Result<&'static str, String> {
+        classify(extension, header).map_err(|problem| problem.to_string())
+    }
+
+    #[test]
+    fn classifies_by_extension_and_content() {
+        let cases: &[Case] = &[
+            // Names that match their content
+            (Some("png"), PNG, Ok("image/png")),
+            (Some("jpg"), JPEG, Ok("image/jpeg")),
+            (Some("jpeg"), JPEG, Ok("image/jpeg")),
+            (Some("txt"), PLAIN, Ok("text/plain")),
+            // Zip-based documents sniff as zip
+            (
+                Some("docx"),
+                ZIP,
+                Ok("application/vnd.openxmlformats-officedocument.wordprocessingml.document"),
+            ),
+            (Some("zip"), ZIP, Ok("application/zip")),
+            // Executables, by name or by content
+            (Some("exe"), PLAIN, Err("is a blocked file type (.exe)")),
+            // Scripts and installer packages
+            (Some("js"), PLAIN, Err("is a blocked file type (.js)")),
+            (Some("sh"), SHEBANG, Err("is a blocked file type (.sh)")),
+            (
+                Some("command"),
+                SHEBANG,
+                Err("is a blocked file type (.command)"),
+            ),
+            (
+                Some("desktop"),
+                PLAIN,
+                Err("is a blocked file type (.desktop)"),
+            ),
+            (Some("wsc"), PLAIN, Err("is a blocked file type (.wsc)")),
+            (Some("vb"), PLAIN, Err("is a blocked file type (.vb)")),
+            (Some("msix"), ZIP, Err("is a blocked file type (.msix)")),
+            (Some("appx"), ZIP, Err("is a blocked file type (.appx)")),
+            (
+                Some("appimage"),
+                ELF,
+                Err("is a blocked file type (.appimage)"),
+            ),
+            (Some("deb"), PLAIN, Err("is a blocked file type (.deb)")),
+            (Some("rpm"), PLAIN, Err("is a blocked file type (.rpm)")),
+            (Some("pkg"), PLAIN, Err("is a blocked file type (.pkg)")),
+            (Some("dmg"), PLAIN, Err("is a blocked file type (.dmg)")),
+            (Some("png"), ELF, Err("is a blocked file type (executable)")),
+            (None, ELF, Err("is a blocked file type (executable)")),
+            // Names that lie about their content
+            (
+                Some("pdf"),
+                PNG,
+                Err("is named .pdf but contains image/png"),
+            ),
+            (
+                Some("jpg"),
+                ZIP,
+                Err("is named .jpg but contains application/zip"),
+            ),
+            (
+                Some("png"),
+                PLAIN,
+                Err("is named .png but contains something else"),
+            ),
+            // Text matchers don't vouch for a name either way
+            (Some("txt"), HTML, Ok("text/plain")),
+            (Some("md"), SHEBANG, Ok("text/markdown")),
+            (
+                Some("gif"),
+                HTML,
+                Err("is named .gif but contains something else"),
+            ),
+            // Unknown names fall back to the content, then to bytes
+            (None, PNG, Ok("image/png")),
+            (Some("dat"), PNG, Ok("image/png")),
+            (Some("dat"), PLAIN, Ok(FALLBACK_MIME)),
+            (None, PLAIN, Ok(FALLBACK_MIME)),
+            (None, HTML, Ok(FALLBACK_MIME)),
+        ];
+        for (extension, header, expected) in cases {
+            assert_eq!(
+                outcome(*extension, header),
+                expected.map_err(str::to_string),
+                "{:?}",
+                extension
+            );
+        }
+    }
+
+    #[test]
+    fn equivalence_is_symmetric_within_a_group() {
+        assert!(equivalent("jpeg", "jpg"));
+        assert!(equivalent("jpg", "jpeg"));
+        assert!(equivalent("docx", "zip"));
+        assert!(equivalent("zip", "docx"));
+        assert!(!equivalent("png", "jpg"));
+    }
+
+    #[test]
+    fn size_limit_waits_for_images_to_be_prepared() {
+        let oversized = |name: &str, header: &[u8]| {
+            let path = std::env::temp_dir().join(format!(
+                "commhub-inspect-{}-{}",
+                std::process::id(),
+                name
+            ));
+            fs::write(&path, header).unwrap();
+            File::options()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_len(MAX_FILE_SIZE + 1)
+                .unwrap();
+            let outcome = inspect(&path).map(|file| file.mime);
+            let _ = fs::remove_file(&path);
+            outcome
+        };
+
+        assert_eq!(oversized("photo.png", PNG).unwrap(), "image/png");
+        assert!(matches!(
+            oversized("archive.zip", ZIP),
+            Err(Problem::TooLarge)
+        ));
+    }
+}
@@ -0,0 +1,402 @@
+//! Global voice shortcuts: push-to-talk, mute, deafen, disconnect and the
+//! overlay's click-through.
+//!
+//! Shortcuts are registered with the OS so they work while another app has
+//! focus. Every key transition is sent to the webview as `hotkey://action`;
+//! the voice store gates the mic on push-to-talk press and release and acts
+//! on the toggles when they're pressed. The overlay toggle is handled here,
+//! since the overlay belongs to the backend.
+//!
+//! The platform manager isn't `Send` on every OS, so it lives on the main
+//! thread. `setup` and synchronous commands both run there.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use global_hotkey::hotkey::HotKey;
+use global_hotkey::{GlobalHotKeyEvent, GlobalHotKeyManager, HotKeyState};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, State};
+
+use crate::menu::AppMenu;
+use crate::overlay::Overlay;
+
+const BINDINGS_FILE: &str = "hotkeys.json";
+
+thread_local! {
+    static MANAGER: RefCell<Option<GlobalHotKeyManager>> = const { RefCell::new(None) };
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("hotkey settings io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("hotkey settings are malformed: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("app directories are unavailable")]
+    NoAppDir,
+    #[error("\"{accelerator}\" is not a valid shortcut: {reason}")]
+    Parse { accelerator: String, reason: String },
+    #[error("{first} and {second} are both bound to {accelerator}")]
+    Conflict {
+        first: HotkeyAction,
+        second: HotkeyAction,
+        accelerator: String,
+    },
+    #[error("{action} can't use {accelerator}, it's the menu shortcut for {item}")]
+    MenuConflict {
+        action: HotkeyAction,
+        item: String,
+        accelerator: String,
+    },
+    #[error("{accelerator} is already in use by another application")]
+    Unavailable { accelerator: String },
+    #[error("global shortcuts are unavailable: {0}")]
+    Unsupported(String),
+}
+
+/// What a shortcut does. Mirrors `HotkeyAction` in services/hotkeys.ts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum HotkeyAction {
+    PushToTalk,
+    ToggleMute,
+    ToggleDeafen,
+    Disconnect,
+    ToggleOverlayClickThrough,
+}
+
+impl std::fmt::Display for HotkeyAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            HotkeyAction::PushToTalk => "Push to talk",
+            HotkeyAction::ToggleMute => "Toggle mute",
+            HotkeyAction::ToggleDeafen => "Toggle deafen",
+            HotkeyAction::Disconnect => "Disconnect",
+            HotkeyAction::ToggleOverlayClickThrough => "Toggle overlay click-through",
+        })
+    }
+}
+
+/// Accelerators in global-hotkey syntax, e.g. `Ctrl+Shift+KeyM`. `None`
+/// leaves the action unbound, which is the default for all of them: a
+/// global shortcut steals the key from every other app.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct HotkeyBindings {
+    pub push_to_talk: Option<String>,
+    pub toggle_mute: Option<String>,
+    pub toggle_deafen: Option<String>,
+    pub disconnect: Option<String>,
+    pub toggle_overlay_click_through: Option<String>,
+}
+
+impl HotkeyBindings {
+    fn entries(&self) -> [(HotkeyAction, Option<&str>); 5] {
+        [
+            (HotkeyAction::PushToTalk, self.push_to_talk.as_deref()),
+            (HotkeyAction::ToggleMute, self.toggle_mute.as_deref()),
+            (HotkeyAction::ToggleDeafen, self.toggle_deafen.as_deref()),
+            (HotkeyAction::Disconnect, self.disconnect.as_deref()),
+            (
+                HotkeyAction::ToggleOverlayClickThrough,
+                self.toggle_overlay_click_through.as_deref(),
+            ),
+        ]
+    }
+
+    /// Parses every binding, leaving out the ones that don't parse, the
+    /// ones that would shadow a `menu` item and the later of two actions
+    /// sharing a shortcut, which come back as errors.
+    fn parse(&self, menu: &[(String, HotKey)]) -> (Vec<(HotkeyAction, HotKey)>, Vec<Error>) {
+        let mut parsed: Vec<(HotkeyAction, HotKey)> = Vec::new();
+        let mut errors = Vec::new();
+        for (action, accelerator) in self.entries() {
+            let Some(accelerator) = accelerator.map(str::trim).filter(|a| !a.is_empty()) else {
+                continue;
+            };
+            let hotkey: HotKey = match accelerator.parse() {
+                Ok(hotkey) => hotkey,
+                Err(e) => {
+                    errors.push(Error::Parse {
+                        accelerator: accelerator.to_string(),
+                        reason: format!("{}", e),
+                    });
+                    continue;
+                }
+            };
+            if let Some((item, _)) = menu.iter().find(|(_, other)| *other == hotkey) {
+                errors.push(Error::MenuConflict {
+                    action,
+                    item: item.clone(),
+                    accelerator: accelerator.to_string(),
+                });
+                continue;
+            }
+            if let Some((first, _)) = parsed.iter().find(|(_, other)| *other == hotkey) {
+                errors.push(Error::Conflict {
+                    first: *first,
+                    second: action,
+                    accelerator: accelerator.to_string(),
+                });
+                continue;
+            }
+            parsed.push((action, hotkey));
+        }
+        (parsed, errors)
+    }
+}
+
+/// Payload of `hotkey://action`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HotkeyEvent {
+    pub action: HotkeyAction,
+    pub pressed: bool,
+}
+
+pub struct Hotkeys {
+    path: PathBuf,
+    bindings: Mutex<HotkeyBindings>,
+    // Menu item label -> shortcut, fixed until restart like the keymap
+    menu: Vec<(String, HotKey)>,
+    // Hotkey id -> action, read from the platform's event thread
+    registered: Arc<Mutex<HashMap<u32, (HotkeyAction, HotKey)>>>,
+}
+
+impl Hotkeys {
+    pub fn from_app(app: &AppHandle) -> Result<Self, Error> {
+        let path = app
+            .path_resolver()
+            .app_config_dir()
+            .ok_or(Error::NoAppDir)?
+            .join(BINDINGS_FILE);
+        let bindings = match fs::read(&path) {
+            Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or_else(|e| {
+                log!("[Hotkeys] Ignoring malformed {}: {}", BINDINGS_FILE, e);
+                HotkeyBindings::default()
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => HotkeyBindings::default(),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(Self {
+            path,
+            bindings: Mutex::new(bindings),
+            menu: menu_hotkeys(&app.state::<AppMenu>()),
+            registered: Arc::default(),
+        })
+    }
+
+    /// Creates the platform manager and registers the saved bindings. Must
+    /// be called on the main thread. Each binding is registered on its own,
+    /// so one the OS refuses doesn't take the rest down; the ones left out
+    /// are returned.
+    pub fn start(&self, app: &AppHandle) -> Result<Vec<Error>, Error> {
+        let manager = GlobalHotKeyManager::new().map_err(|e| Error::Unsupported(e.to_string()))?;
+        MANAGER.with(|cell| *cell.borrow_mut() = Some(manager));
+
+        let app = app.clone();
+        let registered = self.registered.clone();
+        GlobalHotKeyEvent::set_event_handler(Some(move |event: GlobalHotKeyEvent| {
+            let action = registered.lock().unwrap().get(&event.id).map(|(a, _)| *a);
+            let Some(action) = action else {
+                return;
+            };
+            let pressed = event.state == HotKeyState::Pressed;
+            if action == HotkeyAction::ToggleOverlayClickThrough {
+                if pressed {
+                    Overlay::toggle_click_through(&app);
+                }
+                return;
+            }
+            let _ = app.emit_all("hotkey://action", HotkeyEvent { action, pressed });
+        }));
+
+        let (parsed, mut errors) = self.bindings.lock().unwrap().parse(&self.menu);
+        errors.extend(self.register(parsed)?);
+        Ok(errors)
+    }
+
+    pub fn bindings(&self) -> HotkeyBindings {
+        self.bindings.lock().unwrap().clone()
+    }
+
+    /// Swaps in new bindings and saves them. If any shortcut can't be
+    /// registered the previous set is restored.
+    pub fn set_bindings(&self, bindings: HotkeyBindings) -> Result<(), Error> {
+        let mut current = self.bindings.lock().unwrap();
+        let (parsed, errors) = bindings.parse(&self.menu);
+        if let Some(e) = errors.into_iter().next() {
+            return Err(e);
+        }
+        if let Some(e) = self.register(parsed)?.into_iter().next() {
+            let _ = self.register(current.parse(&self.menu).0);
+            return Err(e);
+        }
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&self.path, serde_json::to_vec_pretty(&bindings)?)?;
+        *current = bindings;
+        Ok(())
+    }
+
+    /// Replaces everything registered with `parsed`, returning the shortcuts
+    /// the OS refused.
+    fn register(&self, parsed: Vec<(HotkeyAction, HotKey)>) -> Result<Vec<Error>, Error> {
+        MANAGER.with(|cell| {
+            let cell = cell.borrow();
+            let manager = cell
+                .as_ref()
+                .ok_or_else(|| Error::Unsupported("not started on this thread".to_string()))?;
+
+            let mut registered = self.registered.lock().unwrap();
+            for (_, (_, hotkey)) in registered.drain() {
+                let _ = manager.unregister(hotkey);
+            }
+            let mut refused = Vec::new();
+            for (action, hotkey) in parsed {
+                match manager.register(hotkey) {
+                    Ok(()) => {
+                        registered.insert(hotkey.id(), (action, hotkey));
+                    }
+                    Err(_) => refused.push(Error::Unavailable {
+                        accelerator: hotkey.into_string(),
+                    }),
+                }
+            }
+            Ok(refused)
+        })
+    }
+}
+
+/// The menu's shortcuts as hotkeys, to compare whatever the spelling.
+/// Menu keys with no global equivalent, such as `Plus`, can't collide.
+fn menu_hotkeys(menu: &AppMenu) -> Vec<(String, HotKey)> {
+    menu.accelerators()
+        .into_iter()
+        .filter_map(|(label, accelerator)| {
+            let hotkey = accelerator.parse().ok()?;
+            Some((label.trim_end_matches('.').to_string(), hotkey))
+        })
+        .collect()
+}
+
+#[tauri::command]
+pub fn hotkeys_get(hotkeys: State<'_, Hotkeys>) -> HotkeyBindings {
+    hotkeys.bindings()
+}
+
+#[tauri::command]
+pub fn hotkeys_set(hotkeys: State<'_, Hotkeys>, bindings: HotkeyBindings) -> Result<(), String> {
+    hotkeys.set_bindings(bindings).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn actions(parsed: &[(HotkeyAction, HotKey)]) -> Vec<HotkeyAction> {
+        parsed.iter().map(|(action, _)| *action).collect()
+    }
+
+    #[test]
+    fn unbound_and_blank_shortcuts_are_skipped() {
+        let bindings = HotkeyBindings {
+            push_to_talk: Some("  ".to_string()),
+            toggle_mute: Some(" Ctrl+Shift+KeyM ".to_string()),
+            ..HotkeyBindings::default()
+        };
+        let (parsed, errors) = bindings.parse(&[]);
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(actions(&parsed), [HotkeyAction::ToggleMute]);
+        assert_eq!(parsed[0].1, "Ctrl+Shift+KeyM".parse::<HotKey>().unwrap());
+        assert!(HotkeyBindings::default().parse(&[]).0.is_empty());
+    }
+
+    #[test]
+    fn a_malformed_shortcut_leaves_the_others_bound() {
+        let bindings = HotkeyBindings {
+            push_to_talk: Some("Ctrl+Nonsense".to_string()),
+            toggle_deafen: Some("Ctrl+Shift+KeyD".to_string()),
+            disconnect: Some("Ctrl++".to_string()),
+            ..HotkeyBindings::default()
+        };
+        let (parsed, errors) = bindings.parse(&[]);
+        assert_eq!(actions(&parsed), [HotkeyAction::ToggleDeafen]);
+        assert_eq!(errors.len(), 2, "{:?}", errors);
+        assert!(matches!(
+            &errors[0],
+            Error::Parse { accelerator, .. } if accelerator == "Ctrl+Nonsense"
+        ));
+        assert!(matches!(&errors[1], Error::Parse { .. }));
+    }
+
+    #[test]
+    fn the_later_of_two_equal_shortcuts_is_left_out() {
+        let bindings = HotkeyBindings {
+            toggle_mute: Some("Ctrl+Shift+KeyM".to_string()),
+            toggle_deafen: Some("Ctrl+Alt+KeyD".to_string()),
+            // Same key, spelled differently
+            disconnect: Some("shift+control+m".to_string()),
+            ..HotkeyBindings::default()
+        };
+        let (parsed, errors) = bindings.parse(&[]);
+        assert_eq!(
+            actions(&parsed),
+            [HotkeyAction::ToggleMute, HotkeyAction::ToggleDeafen]
+        );
+        assert!(matches!(
+            errors.as_slice(),
+            [Error::Conflict {
+                first: HotkeyAction::ToggleMute,
+                second: HotkeyAction::Disconnect,
+                ..
+            }]
+        ));
+    }
+
+    #[test]
+    fn shortcuts_the_menu_uses_are_left_out() {
+        let menu = [
+            ("Quick Switcher".to_string(), "Ctrl+K".parse().unwrap()),
+            ("Toggle Mute".to_string(), "Ctrl+Shift+M".parse().unwrap()),
+        ];
+        let bindings = HotkeyBindings {
+            push_to_talk: Some("F13".to_string()),
+            // The menu's spelling of the same key
+            toggle_mute: Some("control+shift+KeyM".to_string()),
+            ..HotkeyBindings::default()
+        };
+        let (parsed, errors) = bindings.parse(&menu);
+        assert_eq!(actions(&parsed), [HotkeyAction::PushToTalk]);
+        assert!(matches!(
+            errors.as_slice(),
+            [Error::MenuConflict {
+                action: HotkeyAction::ToggleMute,
+                item,
+                ..
+            }] if item == "Toggle Mute"
+        ));
+        assert_eq!(
+            errors[0].to_string(),
+            "Toggle mute can't use control+shift+KeyM, it's the menu shortcut for Toggle Mute"
+        );
+    }
+
+    #[test]
+    fn bindings_read_back_from_json() {
+        let bindings: HotkeyBindings =
+            serde_json::from_str(r#"{"pushToTalk":"F13","toggleMute":null}"#).unwrap();
+        assert_eq!(bindings.push_to_talk.as_deref(), Some("F13"));
+        assert_eq!(bindings.toggle_mute, None);
+        assert_eq!(
+            serde_json::from_slice::<HotkeyBindings>(&serde_json::to_vec(&bindings).unwrap())
+                .unwrap(),
+            bindings
+        );
+    }
+}
@@ -0,0 +1,155 @@
+//! Automatic idle status from system-wide input.
+//!
+//! The webview only sees its own key and mouse events, so someone typing in
+//! another app looked idle and someone watching a stream looked active. A
+//! thread here polls the OS idle time ([`source`]), runs it through
+//! [`monitor`] and emits `idle-changed`; the webview applies the status it
+//! carries. The webview reports when the user is watching a stream, video
+//! or voice channel, which holds off going idle until it ends.
+
+mod monitor;
+mod source;
+
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, State};
+
+use crate::tray::Tray;
+use monitor::{Monitor, Thresholds};
+
+const SETTINGS_FILE: &str = "idle.json";
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("idle settings io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("idle settings are malformed: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("app directories are unavailable")]
+    NoAppDir,
+}
+
+/// Mirrors the auto idle fields of `UserSettings` in stores/settings.ts.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct IdleSettings {
+    pub enabled: bool,
+    pub idle_after_secs: u64,
+    pub resume_within_secs: u64,
+}
+
+impl Default for IdleSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            idle_after_secs: 15 * 60,
+            resume_within_secs: 10,
+        }
+    }
+}
+
+impl IdleSettings {
+    fn thresholds(&self) -> Thresholds {
+        Thresholds {
+            idle_after: Duration::from_secs(self.idle_after_secs),
+            resume_within: Duration::from_secs(self.resume_within_secs),
+        }
+    }
+}
+
+pub struct Idle {
+    path: PathBuf,
+    settings: Mutex<IdleSettings>,
+    watching: AtomicBool,
+}
+
+impl Idle {
+    pub fn new(path: PathBuf) -> Self {
+        // A missing or unreadable file just means the defaults
+        let settings = fs::read(&path)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .unwrap_or_default();
+        Self {
+            path,
+            settings: Mutex::new(settings),
+            watching: AtomicBool::new(false),
+        }
+    }
+
+    pub fn from_app(app: &AppHandle) -> Result<Self, Error> {
+        let dir = app
+            .path_resolver()
+            .app_config_dir()
+            .ok_or(Error::NoAppDir)?;
+        Ok(Self::new(dir.join(SETTINGS_FILE)))
+    }
+
+    pub fn settings(&self) -> IdleSettings {
+        *self.settings.lock().unwrap()
+    }
+
+    pub fn set_settings(&self, settings: IdleSettings) -> Result<(), Error> {
+        let mut current = self.settings.lock().unwrap();
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&self.path, serde_json::to_vec_pretty(&settings)?)?;
+        *current = settings;
+        Ok(())
+    }
+
+    pub fn set_watching(&self, watching: bool) {
+        self.watching.store(watching, Ordering::Relaxed);
+    }
+
+    /// Starts polling on its own thread; the platform sources block and
+    /// aren't all safe to share.
+    pub fn start(app: &AppHandle) {
+        let app = app.clone();
+        std::thread::spawn(move || {
+            let Some(mut source) = source::system() else {
+                log!("[Idle] No system idle source; auto idle is unavailable");
+                return;
+            };
+            let mut monitor = Monitor::default();
+            let mut last_watched: Option<Instant> = None;
+            loop {
+                std::thread::sleep(POLL_INTERVAL);
+                let idle = app.state::<Idle>();
+                if idle.watching.load(Ordering::Relaxed) {
+                    last_watched = Some(Instant::now());
+                }
+                let settings = idle.settings();
+                let status = app.state::<Tray>().status();
+                let thresholds = settings.enabled.then(|| settings.thresholds());
+                let watched = last_watched.map(|at| at.elapsed());
+                if let Some(change) = monitor.poll(source.as_mut(), status, thresholds, watched) {
+                    let _ = app.emit_all("idle-changed", change);
+                }
+            }
+        });
+    }
+}
+
+#[tauri::command]
+pub fn idle_get_settings(idle: State<'_, Idle>) -> IdleSettings {
+    idle.settings()
+}
+
+/// Whether the user is watching something, which counts as being present.
+#[tauri::command]
+pub fn idle_set_watching(idle: State<'_, Idle>, watching: bool) {
+    idle.set_watching(watching);
+}
+
+#[tauri::command]
+pub fn idle_set_settings(idle: State<'_, Idle>, settings: IdleSettings) -> Result<(), String> {
+    idle.set_settings(settings).map_err(|e| e.to_string())
+}
@@ -0,0 +1,324 @@
+//! The idle state machine, free of the OS and Tauri so it can be fed
+//! synthetic idle times.
+//!
+//! The user goes idle once system-wide input has been quiet for
+//! `idle_after`, and comes back when there has been input within
+//! `resume_within`. The gap between the two keeps a single stray nudge of
+//! the mouse from flipping the status back and forth. Watching a stream or
+//! video counts as input, so the quiet period starts once it stops.
+
+use std::time::Duration;
+
+use serde::Serialize;
+
+use super::source::IdleSource;
+use crate::tray::UserStatus;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Thresholds {
+    pub idle_after: Duration,
+    pub resume_within: Duration,
+}
+
+/// Payload of `idle-changed`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IdleChanged {
+    pub idle: bool,
+    pub idle_secs: u64,
+    /// The status to switch to, if any. `None` leaves the user's status
+    /// alone: they picked DND or invisible, or changed it while away.
+    pub status: Option<UserStatus>,
+}
+
+#[derive(Debug, Default)]
+pub struct Monitor {
+    idle: bool,
+    // What to put back on return; only set when the monitor changed it
+    previous: Option<UserStatus>,
+}
+
+impl Monitor {
+    /// Takes one reading from `source`. `thresholds` is `None` while
+    /// detection is turned off, which ends any idle period without reading
+    /// the source at all. `watched` is how long ago the user was last
+    /// watching something, if they have been.
+    pub fn poll(
+        &mut self,
+        source: &mut dyn IdleSource,
+        status: UserStatus,
+        thresholds: Option<Thresholds>,
+        watched: Option<Duration>,
+    ) -> Option<IdleChanged> {
+        match thresholds {
+            Some(thresholds) => {
+                let idle_time = source.idle_time()?;
+                let idle_time = watched.map_or(idle_time, |watched| idle_time.min(watched));
+                self.observe(idle_time, status, thresholds)
+            }
+            None if self.idle => Some(self.resume(Duration::ZERO, status)),
+            None => None,
+        }
+    }
+
+    /// Feeds one reading of the system idle time along with the status the
+    /// user currently has. Returns a change when the user left or came back.
+    pub fn observe(
+        &mut self,
+        idle_time: Duration,
+        status: UserStatus,
+        thresholds: Thresholds,
+    ) -> Option<IdleChanged> {
+        if !self.idle && idle_time >= thresholds.idle_after {
+            self.idle = true;
+            // DND and invisible were chosen on purpose; only online is moved
+            self.previous = (status == UserStatus::Online).then_some(status);
+            return Some(IdleChanged {
+                idle: true,
+                idle_secs: idle_time.as_secs(),
+                status: self.previous.map(|_| UserStatus::Idle),
+            });
+        }
+        if self.idle && idle_time < thresholds.resume_within {
+            return Some(self.resume(idle_time, status));
+        }
+        None
+    }
+
+    /// Ends an idle period regardless of input, e.g. when detection is
+    /// turned off.
+    pub fn resume(&mut self, idle_time: Duration, status: UserStatus) -> IdleChanged {
+        self.idle = false;
+        // Still the idle we set? Otherwise the user's own change wins
+        let restore = self.previous.take().filter(|_| status == UserStatus::Idle);
+        IdleChanged {
+            idle: false,
+            idle_secs: idle_time.as_secs(),
+            status: restore,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::VecDeque;
+
+    /// Idle times read in order; `None` for a reading that failed.
+    struct Scripted {
+        readings: VecDeque<Option<Duration>>,
+        reads: usize,
+    }
+
+    impl Scripted {
+        fn new(readings: &[Option<u64>]) -> Self {
+            Self {
+                readings: readings
+                    .iter()
+                    .map(|secs| secs.map(Duration::from_secs))
+                    .collect(),
+                reads: 0,
+            }
+        }
+    }
+
+    impl IdleSource for Scripted {
+        fn idle_time(&mut self) -> Option<Duration> {
+            self.reads += 1;
+            self.readings.pop_front().flatten()
+        }
+    }
+
+    fn thresholds() -> Option<Thresholds> {
+        Some(Thresholds {
+            idle_after: Duration::from_secs(600),
+            resume_within: Duration::from_secs(10),
+        })
+    }
+
+    fn changed(idle: bool, idle_secs: u64, status: Option<UserStatus>) -> Option<IdleChanged> {
+        Some(IdleChanged {
+            idle,
+            idle_secs,
+            status,
+        })
+    }
+
+    #[test]
+    fn goes_idle_at_the_threshold_and_comes_back() {
+        let mut source = Scripted::new(&[Some(30), Some(599), Some(600), Some(900), Some(2)]);
+        let mut monitor = Monitor::default();
+        let online = UserStatus::Online;
+
+        assert_eq!(monitor.poll(&mut source, online, thresholds(), None), None);
+        assert_eq!(monitor.poll(&mut source, online, thresholds(), None), None);
+        assert_eq!(
+            monitor.poll(&mut source, online, thresholds(), None),
+            changed(true, 600, Some(UserStatus::Idle))
+        );
+        assert!(monitor.idle);
+        // Still away; nothing new to report
+        assert_eq!(
+            monitor.poll(&mut source, UserStatus::Idle, thresholds(), None),
+            None
+        );
+        assert_eq!(
+            monitor.poll(&mut source, UserStatus::Idle, thresholds(), None),
+            changed(false, 2, Some(UserStatus::Online))
+        );
+        assert!(!monitor.idle);
+    }
+
+    #[test]
+    fn a_stray_nudge_does_not_count_as_back() {
+        // Input 30s ago is past `resume_within`, so the user is still away
+        let mut source = Scripted::new(&[Some(700), Some(30), Some(10), Some(9)]);
+        let mut monitor = Monitor::default();
+
+        assert!(monitor
+            .poll(&mut source, UserStatus::Online, thresholds(), None)
+            .is_some());
+        assert_eq!(
+            monitor.poll(&mut source, UserStatus::Idle, thresholds(), None),
+            None
+        );
+        assert_eq!(
+            monitor.poll(&mut source, UserStatus::Idle, thresholds(), None),
+            None
+        );
+        assert_eq!(
+            monitor.poll(&mut source, UserStatus::Idle, thresholds(), None),
+            changed(false, 9, Some(UserStatus::Online))
+        );
+    }
+
+    #[test]
+    fn leaves_chosen_statuses_alone() {
+        let mut source = Scripted::new(&[Some(700), Some(0)]);
+        let mut monitor = Monitor::default();
+
+        // Away, but DND was picked on purpose
+        assert_eq!(
+            monitor.poll(&mut source, UserStatus::Dnd, thresholds(), None),
+            changed(true, 700, None)
+        );
+        assert_eq!(
+            monitor.poll(&mut source, UserStatus::Dnd, thresholds(), None),
+            changed(false, 0, None)
+        );
+    }
+
+    #[test]
+    fn a_status_changed_while_away_wins() {
+        let mut source = Scripted::new(&[Some(700), Some(1)]);
+        let mut monitor = Monitor::default();
+
+        assert!(monitor
+            .poll(&mut source, UserStatus::Online, thresholds(), None)
+            .is_some());
+        // Set to invisible from another device while idle
+        assert_eq!(
+            monitor.poll(&mut source, UserStatus::Invisible, thresholds(), None),
+            changed(false, 1, None)
+        );
+    }
+
+    #[test]
+    fn failed_readings_change_nothing() {
+        let mut source = Scripted::new(&[None, Some(700), None, Some(1)]);
+        let mut monitor = Monitor::default();
+
+        assert_eq!(
+            monitor.poll(&mut source, UserStatus::Online, thresholds(), None),
+            None
+        );
+        assert!(monitor
+            .poll(&mut source, UserStatus::Online, thresholds(), None)
+            .is_some());
+        assert_eq!(
+            monitor.poll(&mut source, UserStatus::Idle, thresholds(), None),
+            None
+        );
+        assert!(monitor.idle);
+        assert!(monitor
+            .poll(&mut source, UserStatus::Idle, thresholds(), None)
+            .is_some());
+    }
+
+    #[test]
+    fn watching_keeps_the_user_present() {
+        let mut source = Scripted::new(&[Some(700), Some(900), Some(1000), Some(1300)]);
+        let mut monitor = Monitor::default();
+        let online = UserStatus::Online;
+        let watching = Some(Duration::ZERO);
+
+        assert_eq!(
+            monitor.poll(&mut source, online, thresholds(), watching),
+            None
+        );
+        assert_eq!(
+            monitor.poll(&mut source, online, thresholds(), watching),
+            None
+        );
+        assert!(!monitor.idle);
+        // The stream ended 300s ago; quiet since then, but not long enough
+        assert_eq!(
+            monitor.poll(
+                &mut source,
+                online,
+                thresholds(),
+                Some(Duration::from_secs(300))
+            ),
+            None
+        );
+        assert_eq!(
+            monitor.poll(
+                &mut source,
+                online,
+                thresholds(),
+                Some(Duration::from_secs(600))
+            ),
+            changed(true, 600, Some(UserStatus::Idle))
+        );
+    }
+
+    #[test]
+    fn watching_brings_the_user_back() {
+        let mut source = Scripted::new(&[Some(700), Some(800)]);
+        let mut monitor = Monitor::default();
+
+        assert!(monitor
+            .poll(&mut source, UserStatus::Online, thresholds(), None)
+            .is_some());
+        assert_eq!(
+            monitor.poll(
+                &mut source,
+                UserStatus::Idle,
+                thresholds(),
+                Some(Duration::ZERO)
+            ),
+            changed(false, 0, Some(UserStatus::Online))
+        );
+    }
+
+    #[test]
+    fn turning_detection_off_ends_idle() {
+        let mut source = Scripted::new(&[Some(700)]);
+        let mut monitor = Monitor::default();
+
+        assert!(monitor
+            .poll(&mut source, UserStatus::Online, thresholds(), None)
+            .is_some());
+        assert_eq!(
+            monitor.poll(&mut source, UserStatus::Idle, None, None),
+            changed(false, 0, Some(UserStatus::Online))
+        );
+        assert_eq!(
+            monitor.poll(&mut source, UserStatus::Online, None, None),
+            None
+        );
+        // Off means the source isn't read
+        assert_eq!(source.reads, 1);
+    }
+}
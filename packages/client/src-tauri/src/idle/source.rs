@@ -0,0 +1,216 @@
+//! Where the system-wide idle time comes from on each platform.
+
+use std::time::Duration;
+
+/// Reports how long it has been since the last keyboard or pointer input
+/// anywhere on the system, not just in CommHub.
+pub trait IdleSource: Send {
+    /// `None` when the time can't be read right now.
+    fn idle_time(&mut self) -> Option<Duration>;
+}
+
+/// The best source for this platform and session, if there is one.
+pub fn system() -> Option<Box<dyn IdleSource>> {
+    platform::system()
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::ffi::c_void;
+    use std::ptr;
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    use dbus::blocking::stdintf::org_freedesktop_dbus::Properties;
+    use dbus::blocking::Connection;
+    use x11_dl::xlib::{Display, Xlib};
+    use x11_dl::xss::{XScreenSaverInfo, Xss};
+
+    use super::IdleSource;
+
+    const LOGIND: &str = "org.freedesktop.login1";
+    const SESSION: &str = "/org/freedesktop/login1/session/auto";
+    const SESSION_INTERFACE: &str = "org.freedesktop.login1.Session";
+    const DBUS_TIMEOUT: Duration = Duration::from_secs(2);
+
+    pub fn system() -> Option<Box<dyn IdleSource>> {
+        // Under Wayland the X server only sees XWayland clients, so the
+        // compositor's own idle hint is the better signal
+        let wayland = std::env::var_os("WAYLAND_DISPLAY").is_some();
+        let x11 = || X11::open().map(|source| Box::new(source) as Box<dyn IdleSource>);
+        let logind = || Logind::open().map(|source| Box::new(source) as Box<dyn IdleSource>);
+        if wayland {
+            logind().or_else(x11)
+        } else {
+            x11().or_else(logind)
+        }
+    }
+
+    /// The MIT-SCREEN-SAVER extension, which tracks input on the display.
+    /// Loaded at runtime so a missing libXss only disables this source.
+    struct X11 {
+        xlib: Xlib,
+        xss: Xss,
+        display: *mut Display,
+        info: *mut XScreenSaverInfo,
+    }
+
+    // The display connection is only ever used from the monitor thread
+    unsafe impl Send for X11 {}
+
+    impl X11 {
+        fn open() -> Option<Self> {
+            let xlib = Xlib::open().ok()?;
+            let xss = Xss::open().ok()?;
+            unsafe {
+                let display = (xlib.XOpenDisplay)(ptr::null());
+                if display.is_null() {
+                    return None;
+                }
+                let (mut event_base, mut error_base) = (0, 0);
+                if (xss.XScreenSaverQueryExtension)(display, &mut event_base, &mut error_base) == 0
+                {
+                    (xlib.XCloseDisplay)(display);
+                    return None;
+                }
+                let info = (xss.XScreenSaverAllocInfo)();
+                if info.is_null() {
+                    (xlib.XCloseDisplay)(display);
+                    return None;
+                }
+                Some(Self {
+                    xlib,
+                    xss,
+                    display,
+                    info,
+                })
+            }
+        }
+    }
+
+    impl IdleSource for X11 {
+        fn idle_time(&mut self) -> Option<Duration> {
+            unsafe {
+                let root = (self.xlib.XDefaultRootWindow)(self.display);
+                if (self.xss.XScreenSaverQueryInfo)(self.display, root, self.info) == 0 {
+                    return None;
+                }
+                // `idle` is a c_ulong, 32 bits on some targets
+                Some(Duration::from_millis((*self.info).idle as _))
+            }
+        }
+    }
+
+    impl Drop for X11 {
+        fn drop(&mut self) {
+            unsafe {
+                (self.xlib.XFree)(self.info as *mut c_void);
+                (self.xlib.XCloseDisplay)(self.display);
+            }
+        }
+    }
+
+    /// systemd-logind's `IdleHint`, which the desktop sets after its own
+    /// idle delay. Coarser than X11: it reads zero until that delay passes.
+    struct Logind {
+        connection: Connection,
+    }
+
+    impl Logind {
+        fn open() -> Option<Self> {
+            let source = Self {
+                connection: Connection::new_system().ok()?,
+            };
+            // Make sure there's a session to ask about before settling on it
+            source.idle_time_inner().map(|_| source)
+        }
+
+        fn idle_time_inner(&self) -> Option<Duration> {
+            let proxy = self.connection.with_proxy(LOGIND, SESSION, DBUS_TIMEOUT);
+            let idle: bool = proxy.get(SESSION_INTERFACE, "IdleHint").ok()?;
+            if !idle {
+                return Some(Duration::ZERO);
+            }
+            let since: u64 = proxy.get(SESSION_INTERFACE, "IdleSinceHint").ok()?;
+            let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?;
+            Some(now.saturating_sub(Duration::from_micros(since)))
+        }
+    }
+
+    impl IdleSource for Logind {
+        fn idle_time(&mut self) -> Option<Duration> {
+            self.idle_time_inner()
+        }
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use std::time::Duration;
+
+    use windows_sys::Win32::System::SystemInformation::GetTickCount;
+    use windows_sys::Win32::UI::Input::KeyboardAndMouse::{GetLastInputInfo, LASTINPUTINFO};
+
+    use super::IdleSource;
+
+    pub fn system() -> Option<Box<dyn IdleSource>> {
+        Some(Box::new(LastInput))
+    }
+
+    /// `GetLastInputInfo`, which covers input to the whole session.
+    struct LastInput;
+
+    impl IdleSource for LastInput {
+        fn idle_time(&mut self) -> Option<Duration> {
+            let mut info = LASTINPUTINFO {
+                cbSize: std::mem::size_of::<LASTINPUTINFO>() as u32,
+                dwTime: 0,
+            };
+            if unsafe { GetLastInputInfo(&mut info) } == 0 {
+                return None;
+            }
+            // Both are 32-bit tick counts, so wrapping_sub survives rollover
+            let idle = unsafe { GetTickCount() }.wrapping_sub(info.dwTime);
+            Some(Duration::from_millis(idle as u64))
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use std::time::Duration;
+
+    use super::IdleSource;
+
+    const COMBINED_SESSION_STATE: i32 = 0;
+    const ANY_INPUT_EVENT: u32 = !0;
+
+    #[link(name = "CoreGraphics", kind = "framework")]
+    extern "C" {
+        fn CGEventSourceSecondsSinceLastEventType(state: i32, event_type: u32) -> f64;
+    }
+
+    pub fn system() -> Option<Box<dyn IdleSource>> {
+        Some(Box::new(EventSource))
+    }
+
+    /// Quartz event source state, which covers input to the whole session.
+    struct EventSource;
+
+    impl IdleSource for EventSource {
+        fn idle_time(&mut self) -> Option<Duration> {
+            let secs = unsafe {
+                CGEventSourceSecondsSinceLastEventType(COMBINED_SESSION_STATE, ANY_INPUT_EVENT)
+            };
+            Duration::try_from_secs_f64(secs).ok()
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows", target_os = "macos")))]
+mod platform {
+    use super::IdleSource;
+
+    pub fn system() -> Option<Box<dyn IdleSource>> {
+        None
+    }
+}
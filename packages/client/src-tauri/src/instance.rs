@@ -0,0 +1,442 @@
+//! Single-instance enforcement.
+//!
+//! The first launch listens on a per-user local socket (a Unix socket, or a
+//! named pipe on Windows). A later launch connects, forwards its arguments,
+//! including any `commhub://` link, and exits; the running instance focuses
+//! its window and opens the link. See [`protocol`] for the wire format.
+
+mod protocol;
+
+use std::io;
+use std::time::Duration;
+
+use tauri::{AppHandle, Config};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+
+use crate::{deeplink, tray};
+use protocol::{Action, Request, Response, Status, MAX_MESSAGE};
+
+const TIMEOUT: Duration = Duration::from_secs(3);
+/// Set on a process we start to replace ourselves, e.g. after an update.
+pub const RELAUNCH_ENV: &str = "COMMHUB_RELAUNCH";
+const RELAUNCH_WAIT: Duration = Duration::from_secs(5);
+
+/// The outcome of [`acquire`].
+pub enum Instance {
+    /// This is the only instance; serve later launches with [`serve`].
+    Primary(Option<platform::Listener>),
+    /// Another instance took over the launch.
+    Secondary,
+}
+
+/// Hands the launch to a running instance if there is one, otherwise
+/// claims the socket for this process.
+pub fn acquire(config: &Config) -> Instance {
+    let name = match platform::name(config) {
+        Ok(name) => name,
+        Err(e) => {
+            log!("[Instance] No private place for the socket: {}", e);
+            return Instance::Primary(None);
+        }
+    };
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let relaunch = std::env::var_os(RELAUNCH_ENV).is_some();
+    std::env::remove_var(RELAUNCH_ENV);
+
+    tauri::async_runtime::block_on(async {
+        if relaunch {
+            return claim(&name).await;
+        }
+        // Twice, since another launch may claim the socket between our
+        // connect and bind
+        for _ in 0..2 {
+            match forward(&name, args.clone()).await {
+                Ok(response) => {
+                    if response.status == Status::Unsupported {
+                        log!(
+                            "[Instance] Running instance speaks protocol v{}; it was focused but \
+                             can't take these arguments",
+                            response.version
+                        );
+                    }
+                    return Instance::Secondary;
+                }
+                // Nothing listening under the name: the usual first launch
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => match platform::clear_stale(&name, &e) {
+                    Ok(true) => {}
+                    // Someone owns the socket but didn't take the launch;
+                    // it isn't ours to remove
+                    Ok(false) => {
+                        log!("[Instance] Running instance didn't answer: {}", e);
+                        break;
+                    }
+                    Err(e) => {
+                        log!("[Instance] Failed to remove stale socket: {}", e);
+                        break;
+                    }
+                },
+            }
+            match platform::bind(&name) {
+                Ok(listener) => return Instance::Primary(Some(listener)),
+                Err(e) if e.kind() == io::ErrorKind::AddrInUse => continue,
+                Err(e) => {
+                    log!("[Instance] Failed to listen for other launches: {}", e);
+                    break;
+                }
+            }
+        }
+        // Better two clients than none
+        Instance::Primary(None)
+    })
+}
+
+/// Takes over from the process that started us, which may still be
+/// shutting down; handing it our arguments would leave nothing running.
+async fn claim(name: &str) -> Instance {
+    let deadline = tokio::time::Instant::now() + RELAUNCH_WAIT;
+    loop {
+        match platform::bind(name) {
+            Ok(listener) => return Instance::Primary(Some(listener)),
+            Err(e) if e.kind() == io::ErrorKind::AddrInUse => {
+                // Its socket is only cleared once it has stopped listening
+                if let Err(e) = platform::connect(name).await {
+                    if let Ok(true) = platform::clear_stale(name, &e) {
+                        continue;
+                    }
+                }
+                if tokio::time::Instant::now() >= deadline {
+                    log!("[Instance] Previous instance didn't exit; not listening");
+                    return Instance::Primary(None);
+                }
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+            Err(e) => {
+                log!("[Instance] Failed to listen for other launches: {}", e);
+                return Instance::Primary(None);
+            }
+        }
+    }
+}
+
+/// Marks the process about to be started by a restart as our successor.
+pub fn mark_relaunch() {
+    std::env::set_var(RELAUNCH_ENV, "1");
+}
+
+async fn forward(name: &str, args: Vec<String>) -> io::Result<Response> {
+    let stream = platform::connect(name).await?;
+    let exchange = async {
+        let (reader, mut writer) = tokio::io::split(stream);
+        writer.write_all(&Request::new(args).encode()).await?;
+        writer.flush().await?;
+        let line = read_line(reader).await?;
+        Response::decode(&line).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    };
+    tokio::time::timeout(TIMEOUT, exchange)
+        .await
+        .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "running instance didn't answer"))?
+}
+
+/// Answers later launches for as long as the app runs.
+pub fn serve(app: &AppHandle, listener: platform::Listener) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let mut listener = listener;
+        loop {
+            match platform::accept(&mut listener).await {
+                Ok(stream) => {
+                    let app = app.clone();
+                    tauri::async_runtime::spawn(async move {
+                        if let Err(e) = tokio::time::timeout(TIMEOUT, handle(&app, stream)).await {
+                            log!("[Instance] Dropped a slow launch request: {}", e);
+                        }
+                    });
+                }
+                Err(e) => {
+                    log!("[Instance] Failed to accept launch request: {}", e);
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                }
+            }
+        }
+    });
+}
+
+async fn handle(app: &AppHandle, stream: impl AsyncRead + AsyncWrite) {
+    let (reader, mut writer) = tokio::io::split(stream);
+    let request = match read_line(reader).await.map(|line| Request::decode(&line)) {
+        Ok(Ok(request)) => request,
+        // Someone else's traffic; don't let it raise the window
+        Ok(Err(e)) => {
+            log!("[Instance] Ignoring launch request: {}", e);
+            return;
+        }
+        Err(e) => {
+            log!("[Instance] Failed to read launch request: {}", e);
+            return;
+        }
+    };
+
+    let (action, response) = request.accept();
+    match action {
+        Action::Handle(args) => match deeplink::find(args) {
+            Some(link) => deeplink::open(app, link),
+            None => tray::show_main_window(app),
+        },
+        Action::FocusOnly => tray::show_main_window(app),
+    }
+    let _ = writer.write_all(&response.encode()).await;
+    let _ = writer.flush().await;
+}
+
+async fn read_line(reader: impl AsyncRead + Unpin) -> io::Result<String> {
+    let mut line = String::new();
+    BufReader::new(reader.take(MAX_MESSAGE))
+        .read_line(&mut line)
+        .await?;
+    if !line.ends_with('\n') {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "message too long or cut off",
+        ));
+    }
+    Ok(line)
+}
+
+#[cfg(unix)]
+pub mod platform {
+    use std::io;
+    use std::os::unix::fs::{DirBuilderExt, MetadataExt, PermissionsExt};
+    use std::path::{Path, PathBuf};
+
+    use tauri::Config;
+    use tokio::net::{UnixListener, UnixStream};
+
+    pub type Listener = UnixListener;
+
+    /// Per user: in the runtime dir, which only its user can enter, or
+    /// failing that in a directory of our own under the app's local data.
+    /// A shared dir like /tmp would let anyone claim the name first.
+    pub fn name(config: &Config) -> io::Result<String> {
+        let identifier = &config.tauri.bundle.identifier;
+        let path = match std::env::var_os("XDG_RUNTIME_DIR") {
+            Some(dir) => {
+                let user = std::env::var("USER").unwrap_or_default();
+                PathBuf::from(dir).join(format!("{}-{}.sock", identifier, user))
+            }
+            None => {
+                let dir = tauri::api::path::app_local_data_dir(config)
+                    .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no local data dir"))?
+                    .join("instance");
+                private_dir(&dir)?;
+                dir.join(format!("{}.sock", identifier))
+            }
+        };
+        Ok(path.to_string_lossy().into_owned())
+    }
+
+    /// Creates `dir` if needed and makes it ours alone.
+    pub(super) fn private_dir(dir: &Path) -> io::Result<()> {
+        std::fs::DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(dir)?;
+        check_owner(dir, current_uid())?;
+        std::fs::set_permissions(dir, std::fs::Permissions::from_mode(0o700))
+    }
+
+    /// Refuses a socket another user put there, which would be handed our
+    /// arguments, including any link.
+    pub async fn connect(name: &str) -> io::Result<UnixStream> {
+        check_owner(Path::new(name), current_uid())?;
+        UnixStream::connect(name).await
+    }
+
+    pub(super) fn check_owner(path: &Path, uid: u32) -> io::Result<()> {
+        if std::fs::symlink_metadata(path)?.uid() != uid {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!("{} belongs to another user", path.display()),
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn bind(name: &str) -> io::Result<Listener> {
+        let listener = UnixListener::bind(name)?;
+        restrict(name)?;
+        Ok(listener)
+    }
+
+    /// Removes the socket file if `error`, from connecting to it, shows it's
+    /// left over from a crash. Only a refused connection does; after a
+    /// timeout or any other failure a live instance may still own it.
+    pub fn clear_stale(name: &str, error: &io::Error) -> io::Result<bool> {
+        if error.kind() != io::ErrorKind::ConnectionRefused {
+            return Ok(false);
+        }
+        match std::fs::remove_file(name) {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(true),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub async fn accept(listener: &mut Listener) -> io::Result<UnixStream> {
+        listener.accept().await.map(|(stream, _)| stream)
+    }
+
+    fn restrict(name: &str) -> io::Result<()> {
+        std::fs::set_permissions(name, std::fs::Permissions::from_mode(0o600))
+    }
+
+    pub(super) fn current_uid() -> u32 {
+        // getuid always succeeds
+        unsafe { libc::getuid() }
+    }
+}
+
+#[cfg(windows)]
+pub mod platform {
+    use std::io;
+    use std::time::Duration;
+
+    use tauri::Config;
+    use tokio::net::windows::named_pipe::{
+        ClientOptions, NamedPipeClient, NamedPipeServer, ServerOptions,
+    };
+
+    const ERROR_PIPE_BUSY: i32 = 231;
+
+    /// The pipe instance waiting for the next client, and the name to
+    /// create its successor under.
+    pub struct Listener {
+        name: String,
+        server: NamedPipeServer,
+    }
+
+    pub fn name(config: &Config) -> io::Result<String> {
+        let user = std::env::var("USERNAME").unwrap_or_default();
+        Ok(format!(
+            r"\\.\pipe\{}-{}",
+            config.tauri.bundle.identifier, user
+        ))
+    }
+
+    pub async fn connect(name: &str) -> io::Result<NamedPipeClient> {
+        loop {
+            match ClientOptions::new().open(name) {
+                Err(e) if e.raw_os_error() == Some(ERROR_PIPE_BUSY) => {
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    pub fn bind(name: &str) -> io::Result<Listener> {
+        // Fails with access denied if another process already owns the name
+        let server = ServerOptions::new()
+            .first_pipe_instance(true)
+            .create(name)
+            .map_err(|e| match e.kind() {
+                io::ErrorKind::PermissionDenied => io::Error::new(io::ErrorKind::AddrInUse, e),
+                _ => e,
+            })?;
+        Ok(Listener {
+            name: name.to_string(),
+            server,
+        })
+    }
+
+    /// Pipes go away with the process that created them, so one that's
+    /// there belongs to a live instance.
+    pub fn clear_stale(_name: &str, _error: &io::Error) -> io::Result<bool> {
+        Ok(false)
+    }
+
+    /// Waits for a client, then swaps in a fresh pipe instance for the next
+    /// one and returns the connected one.
+    pub async fn accept(listener: &mut Listener) -> io::Result<NamedPipeServer> {
+        listener.server.connect().await?;
+        let next = ServerOptions::new().create(&listener.name)?;
+        Ok(std::mem::replace(&mut listener.server, next))
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::Path;
+
+    fn socket(name: &str) -> String {
+        let path = std::env::temp_dir().join(format!(
+            "commhub-instance-{}-{}.sock",
+            std::process::id(),
+            name
+        ));
+        let _ = std::fs::remove_file(&path);
+        path.to_string_lossy().into_owned()
+    }
+
+    #[tokio::test]
+    async fn clears_a_socket_nobody_listens_on() {
+        let name = socket("stale");
+        drop(platform::bind(&name).unwrap());
+        assert!(std::path::Path::new(&name).exists());
+
+        let refused = platform::connect(&name).await.unwrap_err();
+        assert_eq!(refused.kind(), io::ErrorKind::ConnectionRefused);
+        assert!(platform::clear_stale(&name, &refused).unwrap());
+        drop(platform::bind(&name).unwrap());
+        let _ = std::fs::remove_file(&name);
+    }
+
+    #[tokio::test]
+    async fn keeps_a_socket_that_did_not_answer() {
+        let name = socket("slow");
+        let _listener = platform::bind(&name).unwrap();
+
+        let timeout = io::Error::new(io::ErrorKind::TimedOut, "running instance didn't answer");
+        assert!(!platform::clear_stale(&name, &timeout).unwrap());
+        let other = io::Error::new(io::ErrorKind::InvalidData, "bad response");
+        assert!(!platform::clear_stale(&name, &other).unwrap());
+        assert!(std::path::Path::new(&name).exists());
+        assert_eq!(
+            platform::bind(&name).unwrap_err().kind(),
+            io::ErrorKind::AddrInUse
+        );
+        let _ = std::fs::remove_file(&name);
+    }
+
+    #[test]
+    fn the_fallback_directory_is_private() {
+        let root = crate::test_support::root("instance", "private");
+        let dir = root.join("instance");
+        platform::private_dir(&dir).unwrap();
+        let mode = |dir: &Path| std::fs::metadata(dir).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode(&dir), 0o700);
+
+        std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o755)).unwrap();
+        platform::private_dir(&dir).unwrap();
+        assert_eq!(mode(&dir), 0o700);
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn wont_connect_to_another_users_socket() {
+        let name = socket("owner");
+        let _listener = platform::bind(&name).unwrap();
+        let path = Path::new(&name);
+
+        platform::check_owner(path, platform::current_uid()).unwrap();
+        let refused = platform::check_owner(path, platform::current_uid() + 1).unwrap_err();
+        assert_eq!(refused.kind(), io::ErrorKind::PermissionDenied);
+        // Not taken for a crashed instance's socket either
+        assert!(!platform::clear_stale(&name, &refused).unwrap());
+        platform::connect(&name).await.unwrap();
+        let _ = std::fs::remove_file(&name);
+    }
+}
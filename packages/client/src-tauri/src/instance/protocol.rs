@@ -0,0 +1,193 @@
+//! The message a second launch sends to the running instance.
+//!
+//! One JSON line each way per connection. The envelope (`magic`, `version`)
+//! never changes shape, so builds on either side of an update can always
+//! tell each other apart: a request from a newer build is answered with
+//! `unsupported` and only focuses the window, instead of acting on fields
+//! this build would misread. New fields must be optional so older requests
+//! keep parsing.
+
+use serde::{Deserialize, Serialize};
+
+pub const MAGIC: &str = "commhub-instance";
+pub const VERSION: u32 = 1;
+/// Requests are a handful of arguments; anything bigger isn't ours.
+pub const MAX_MESSAGE: u64 = 64 * 1024;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("malformed instance message: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("not a CommHub instance message")]
+    Magic,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Request {
+    pub magic: String,
+    pub version: u32,
+    /// The second launch's arguments, without the executable path.
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    /// The arguments were handled.
+    Accepted,
+    /// The request came from a newer build; the window was focused but
+    /// the arguments were ignored.
+    Unsupported,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Response {
+    pub version: u32,
+    pub status: Status,
+}
+
+/// What the running instance should do with a request.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Action {
+    Handle(Vec<String>),
+    FocusOnly,
+}
+
+impl Request {
+    pub fn new(args: Vec<String>) -> Self {
+        Self {
+            magic: MAGIC.to_string(),
+            version: VERSION,
+            args,
+        }
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        line(self)
+    }
+
+    pub fn decode(line: &str) -> Result<Self, Error> {
+        let request: Request = serde_json::from_str(line)?;
+        if request.magic != MAGIC {
+            return Err(Error::Magic);
+        }
+        Ok(request)
+    }
+
+    /// Decides how to treat the request and what to answer.
+    pub fn accept(self) -> (Action, Response) {
+        if self.version > VERSION {
+            return (Action::FocusOnly, response(Status::Unsupported));
+        }
+        (Action::Handle(self.args), response(Status::Accepted))
+    }
+}
+
+impl Response {
+    pub fn encode(&self) -> Vec<u8> {
+        line(self)
+    }
+
+    pub fn decode(line: &str) -> Result<Self, Error> {
+        Ok(serde_json::from_str(line)?)
+    }
+}
+
+fn response(status: Status) -> Response {
+    Response {
+        version: VERSION,
+        status,
+    }
+}
+
+fn line(message: &impl Serialize) -> Vec<u8> {
+    let mut bytes = serde_json::to_vec(message).expect("instance messages serialize");
+    bytes.push(b'\n');
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(bytes: Vec<u8>) -> String {
+        String::from_utf8(bytes).unwrap()
+    }
+
+    #[test]
+    fn request_round_trips_as_one_line() {
+        let request = Request::new(vec![
+            "commhub://channel/4".to_string(),
+            "two\nlines".to_string(),
+            "ünïcode".to_string(),
+        ]);
+        let line = text(request.encode());
+        assert!(line.ends_with('\n'));
+        assert_eq!(line.matches('\n').count(), 1);
+        assert_eq!(Request::decode(&line).unwrap(), request);
+    }
+
+    #[test]
+    fn response_round_trips() {
+        for status in [Status::Accepted, Status::Unsupported] {
+            let line = text(response(status).encode());
+            assert_eq!(Response::decode(&line).unwrap(), response(status));
+        }
+        assert_eq!(
+            text(response(Status::Unsupported).encode()),
+            "{\"version\":1,\"status\":\"unsupported\"}\n"
+        );
+    }
+
+    #[test]
+    fn same_or_older_versions_are_handled() {
+        let args = vec!["--minimized".to_string()];
+        let (action, answer) = Request::new(args.clone()).accept();
+        assert_eq!(action, Action::Handle(args.clone()));
+        assert_eq!(answer, response(Status::Accepted));
+
+        let older = Request {
+            version: VERSION - 1,
+            ..Request::new(args.clone())
+        };
+        assert_eq!(older.accept().0, Action::Handle(args));
+    }
+
+    #[test]
+    fn newer_versions_only_focus() {
+        // Fields this build doesn't know about still parse
+        let line = format!(
+            "{{\"magic\":\"{}\",\"version\":{},\"args\":[\"x\"],\"cwd\":\"/tmp\"}}\n",
+            MAGIC,
+            VERSION + 1
+        );
+        let (action, answer) = Request::decode(&line).unwrap().accept();
+        assert_eq!(action, Action::FocusOnly);
+        assert_eq!(answer.status, Status::Unsupported);
+        assert_eq!(answer.version, VERSION);
+    }
+
+    #[test]
+    fn args_are_optional() {
+        let line = format!("{{\"magic\":\"{}\",\"version\":{}}}", MAGIC, VERSION);
+        assert_eq!(Request::decode(&line).unwrap(), Request::new(Vec::new()));
+    }
+
+    #[test]
+    fn rejects_foreign_messages() {
+        assert!(matches!(
+            Request::decode("{\"magic\":\"other-app\",\"version\":1,\"args\":[]}"),
+            Err(Error::Magic)
+        ));
+        assert!(matches!(
+            Request::decode("GET / HTTP/1.1"),
+            Err(Error::Json(_))
+        ));
+        assert!(matches!(
+            Request::decode("{\"version\":1}"),
+            Err(Error::Json(_))
+        ));
+        assert!(Response::decode("{\"version\":1,\"status\":\"maybe\"}").is_err());
+    }
+}
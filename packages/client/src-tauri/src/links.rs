@@ -0,0 +1,414 @@
+//! Opening links outside the app.
+//!
+//! Every link the webview opens goes through [`open_external`] instead of
+//! the shell API. Only http, https and mailto links are opened, and one
+//! whose domain could mislead ([`assess`]) comes back as an [`Assessment`]
+//! for the user to confirm first. Short links are followed ([`shortener`])
+//! so the confirmation can say where they really go. Domains the user
+//! trusts skip it, and are kept in `trusted-domains.json`. Public suffixes
+//! like `co.uk` or `github.io`, where anyone can have a name, and shorteners
+//! can't be trusted.
+
+mod assess;
+mod shortener;
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+use percent_encoding::percent_decode_str;
+use publicsuffix::{List, Psl};
+use reqwest::{redirect, Client};
+use serde::Serialize;
+use tauri::{AppHandle, State};
+use url::Url;
+
+use crate::unfurl::guard::PublicResolver;
+pub use assess::Risk;
+
+const TRUSTED_FILE: &str = "trusted-domains.json";
+const SHORTENER_TIMEOUT: Duration = Duration::from_secs(5);
+
+// https://publicsuffix.org/list/public_suffix_list.dat
+const PUBLIC_SUFFIX_LIST: &str = include_str!("links/public_suffix_list.dat");
+static PUBLIC_SUFFIXES: OnceLock<List> = OnceLock::new();
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("invalid link: {0}")]
+    Url(#[from] url::ParseError),
+    #[error("{0} links can't be opened")]
+    Scheme(String),
+    #[error("{0} isn't a domain that can be trusted")]
+    Domain(String),
+    #[error("failed to open link: {0}")]
+    Open(std::io::Error),
+    #[error("failed to set up following short links: {0}")]
+    Client(#[from] reqwest::Error),
+    #[error("trusted domains io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("trusted domains are malformed: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error(transparent)]
+    Runtime(#[from] tauri::Error),
+    #[error("app directories are unavailable")]
+    NoAppDir,
+}
+
+/// What a link's user should know before it's opened.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Assessment {
+    pub url: String,
+    pub scheme: String,
+    /// The domain as it's looked up, with any punycode intact.
+    pub domain: Option<String>,
+    /// The domain as it reads, with punycode decoded.
+    pub display_domain: Option<String>,
+    /// Where a short link leads, when it could be followed.
+    pub destination: Option<String>,
+    /// The domain "always trust" would add.
+    pub trust_domain: Option<String>,
+    pub risks: Vec<Risk>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum OpenOutcome {
+    Opened,
+    /// Not opened; the user should see the assessment and decide.
+    Confirm {
+        assessment: Assessment,
+    },
+}
+
+pub struct Links {
+    path: PathBuf,
+    trusted: Mutex<BTreeSet<String>>,
+    client: Client,
+}
+
+impl Links {
+    pub fn new(path: PathBuf, package_info: &tauri::PackageInfo) -> Result<Self, Error> {
+        // A missing or unreadable file just means nothing is trusted yet
+        let saved: Vec<String> = fs::read(&path)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .unwrap_or_default();
+        // Lookups expect the normalized spelling, and an edited file may
+        // name domains that can't be trusted at all
+        let trusted = saved
+            .into_iter()
+            .filter_map(|domain| {
+                let normalized = normalize(&domain);
+                if normalized.is_none() {
+                    log!("[Links] Dropping untrustable domain {:?}", domain);
+                }
+                normalized
+            })
+            .collect();
+        let client = Client::builder()
+            .user_agent(format!("CommHub/{}", package_info.version))
+            .timeout(SHORTENER_TIMEOUT)
+            // Redirects are read, not followed
+            .redirect(redirect::Policy::none())
+            .dns_resolver(Arc::new(PublicResolver {
+                allow_private: false,
+            }))
+            .no_proxy()
+            .build()?;
+        Ok(Self {
+            path,
+            trusted: Mutex::new(trusted),
+            client,
+        })
+    }
+
+    pub fn from_app(app: &AppHandle) -> Result<Self, Error> {
+        let dir = app
+            .path_resolver()
+            .app_config_dir()
+            .ok_or(Error::NoAppDir)?;
+        Self::new(dir.join(TRUSTED_FILE), app.package_info())
+    }
+
+    pub fn trusted(&self) -> Vec<String> {
+        self.trusted.lock().unwrap().iter().cloned().collect()
+    }
+
+    /// Whether `domain` or a domain it's under is trusted.
+    fn is_trusted(&self, domain: &str) -> bool {
+        let trusted = self.trusted.lock().unwrap();
+        let mut parent = Some(domain);
+        while let Some(candidate) = parent {
+            if trusted.contains(candidate) {
+                return true;
+            }
+            parent = candidate.split_once('.').map(|(_, rest)| rest);
+        }
+        false
+    }
+
+    pub fn trust(&self, domain: &str) -> Result<(), Error> {
+        let domain = normalize(domain).ok_or_else(|| Error::Domain(domain.to_string()))?;
+        let mut trusted = self.trusted.lock().unwrap();
+        let mut updated = trusted.clone();
+        updated.insert(domain);
+        self.save(&updated)?;
+        *trusted = updated;
+        Ok(())
+    }
+
+    /// Falls back to the domain as given when it no longer normalizes, so
+    /// whatever the list shows can always be removed.
+    pub fn untrust(&self, domain: &str) -> Result<(), Error> {
+        let mut trusted = self.trusted.lock().unwrap();
+        let domain = match normalize(domain) {
+            Some(normalized) => normalized,
+            None => {
+                let raw = domain.trim().to_lowercase();
+                if !trusted.contains(&raw) {
+                    return Err(Error::Domain(domain.to_string()));
+                }
+                raw
+            }
+        };
+        let mut updated = trusted.clone();
+        updated.remove(&domain);
+        self.save(&updated)?;
+        *trusted = updated;
+        Ok(())
+    }
+
+    fn save(&self, trusted: &BTreeSet<String>) -> Result<(), Error> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&self.path, serde_json::to_vec_pretty(trusted)?)?;
+        Ok(())
+    }
+
+    /// Looks `url` over, following it first if it's a short link.
+    pub async fn assess(&self, url: &Url) -> Result<Assessment, Error> {
+        let scheme = url.scheme().to_string();
+        if !matches!(scheme.as_str(), "http" | "https" | "mailto") {
+            return Err(Error::Scheme(scheme));
+        }
+        let domain = link_domain(url);
+        let mut assessment = Assessment {
+            url: url.to_string(),
+            display_domain: domain.as_deref().map(assess::display),
+            trust_domain: domain.as_deref().and_then(normalize),
+            domain: domain.clone(),
+            scheme,
+            destination: None,
+            risks: Vec::new(),
+        };
+        let Some(domain) = domain else {
+            return Ok(assessment);
+        };
+        if self.is_trusted(&domain) {
+            return Ok(assessment);
+        }
+
+        if !url.username().is_empty() || url.password().is_some() {
+            let text = decode(url.username());
+            assessment.risks.push(Risk::UserInfo { text });
+        }
+        assessment.risks.extend(self.domain_risks(&domain));
+
+        if shortener::is_shortener(&domain) {
+            let destination = shortener::follow(&self.client, url).await;
+            if let Some(target) = destination.as_ref().and_then(|url| url.host_str()) {
+                if self.is_trusted(target) {
+                    assessment.risks.clear();
+                } else {
+                    assessment.risks.push(Risk::Shortened);
+                    assessment.risks.extend(self.domain_risks(target));
+                }
+                assessment.trust_domain = normalize(target);
+            } else {
+                assessment.risks.push(Risk::Shortened);
+            }
+            assessment.destination = destination.map(String::from);
+        }
+        Ok(assessment)
+    }
+
+    fn domain_risks(&self, domain: &str) -> Vec<Risk> {
+        let trusted = self.trusted();
+        let known = assess::WELL_KNOWN
+            .iter()
+            .copied()
+            .chain(trusted.iter().map(String::as_str));
+        assess::domain_risks(domain, known)
+    }
+
+    /// Opens `url`, unless it needs confirming and hasn't been.
+    pub async fn open(&self, url: &str, confirmed: bool) -> Result<OpenOutcome, Error> {
+        let url = Url::parse(url.trim())?;
+        let assessment = self.assess(&url).await?;
+        if !confirmed && !assessment.risks.is_empty() {
+            return Ok(OpenOutcome::Confirm { assessment });
+        }
+        tauri::async_runtime::spawn_blocking(move || open::that(url.as_str()))
+            .await?
+            .map_err(Error::Open)?;
+        Ok(OpenOutcome::Opened)
+    }
+}
+
+/// The domain a link goes to: the host of a web link, or where a mailto
+/// link's address is.
+fn link_domain(url: &Url) -> Option<String> {
+    if url.scheme() == "mailto" {
+        let address = decode(url.path());
+        let (_, domain) = address.split(',').next()?.rsplit_once('@')?;
+        return idna::domain_to_ascii(domain.trim()).ok();
+    }
+    url.host_str().map(str::to_string)
+}
+
+/// `domain` as the trusted list stores it: ASCII, lowercase, without `www.`,
+/// or `None` if it isn't one site.
+fn normalize(domain: &str) -> Option<String> {
+    let domain = idna::domain_to_ascii(domain.trim().trim_end_matches('.')).ok()?;
+    let domain = domain.strip_prefix("www.").unwrap_or(&domain);
+    // A bare address, a public suffix or a shortener would trust far too
+    // much, and a lone label has no registrable domain either
+    (domain.parse::<std::net::IpAddr>().is_err()
+        && is_registrable(domain)
+        && !shortener::is_shortener(domain))
+    .then(|| domain.to_string())
+}
+
+/// Whether `domain` is, or is under, a name someone registered, rather than
+/// a suffix like `co.uk` or `github.io` that anyone can have names under.
+fn is_registrable(domain: &str) -> bool {
+    let list = PUBLIC_SUFFIXES.get_or_init(|| PUBLIC_SUFFIX_LIST.parse().unwrap_or_default());
+    list.domain(domain.as_bytes()).is_some()
+}
+
+fn decode(value: &str) -> String {
+    percent_decode_str(value).decode_utf8_lossy().into_owned()
+}
+
+/// Opens a link in the browser or mail client, or says why it should be
+/// confirmed first.
+#[tauri::command]
+pub async fn open_external(
+    links: State<'_, Links>,
+    url: String,
+    confirmed: Option<bool>,
+) -> Result<OpenOutcome, String> {
+    links
+        .open(&url, confirmed.unwrap_or(false))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn links_trusted(links: State<'_, Links>) -> Vec<String> {
+    links.trusted()
+}
+
+#[tauri::command]
+pub fn links_trust(links: State<'_, Links>, domain: String) -> Result<(), String> {
+    links.trust(&domain).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn links_untrust(links: State<'_, Links>, domain: String) -> Result<(), String> {
+    links.untrust(&domain).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn package_info() -> tauri::PackageInfo {
+        tauri::PackageInfo {
+            name: "CommHub".to_string(),
+            version: "1.2.3".parse().unwrap(),
+            authors: "",
+            description: "",
+        }
+    }
+
+    #[test]
+    fn normalizes_domains_for_the_trusted_list() {
+        assert_eq!(normalize("Example.COM").as_deref(), Some("example.com"));
+        assert_eq!(
+            normalize(" www.example.com. ").as_deref(),
+            Some("example.com")
+        );
+        assert_eq!(
+            normalize("münchen.de").as_deref(),
+            Some("xn--mnchen-3ya.de")
+        );
+        assert_eq!(
+            normalize("docs.github.com").as_deref(),
+            Some("docs.github.com")
+        );
+        assert_eq!(
+            normalize("someone.github.io").as_deref(),
+            Some("someone.github.io")
+        );
+        assert_eq!(normalize("bbc.co.uk").as_deref(), Some("bbc.co.uk"));
+    }
+
+    #[test]
+    fn wont_trust_more_than_one_site() {
+        for domain in [
+            "co.uk",
+            "www.co.uk",
+            "github.io",
+            "com",
+            "localhost",
+            "127.0.0.1",
+            "bit.ly",
+            "www.t.co",
+            "",
+        ] {
+            assert_eq!(normalize(domain), None, "{}", domain);
+        }
+    }
+
+    #[test]
+    fn untrusts_whatever_spelling_was_trusted() {
+        let path = std::env::temp_dir().join(format!("commhub-links-{}.json", std::process::id()));
+        let links = Links::new(path.clone(), &package_info()).unwrap();
+
+        links.trust("Example.COM").unwrap();
+        assert!(links.is_trusted("docs.example.com"));
+        links.untrust("www.example.com.").unwrap();
+        assert!(links.trusted().is_empty());
+        assert!(links.untrust("co.uk").is_err());
+
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn saved_domains_are_normalized_on_load() {
+        let root = crate::test_support::root("links", "normalized-on-load");
+        fs::create_dir_all(&root).unwrap();
+        let path = root.join(TRUSTED_FILE);
+        fs::write(
+            &path,
+            r#"["Example.COM", "www.foo.org.", "co.uk", "bit.ly", "127.0.0.1", ""]"#,
+        )
+        .unwrap();
+        let links = Links::new(path, &package_info()).unwrap();
+        assert_eq!(links.trusted(), ["example.com", "foo.org"]);
+        assert!(links.is_trusted("docs.example.com"));
+
+        // One that stopped normalizing while the app ran
+        links.trusted.lock().unwrap().insert("t.co".to_string());
+        links.untrust(" T.co ").unwrap();
+        assert_eq!(links.trusted(), ["example.com", "foo.org"]);
+
+        let _ = fs::remove_dir_all(&root);
+    }
+}
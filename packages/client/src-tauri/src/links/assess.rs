@@ -0,0 +1,223 @@
+//! What about a domain could mislead someone reading it.
+//!
+//! Domains are compared by their Unicode confusable skeletons (UTS #39), so
+//! `pаypal.com` with a Cyrillic `а`, `paypa1.com` and `rnicrosoft.com` all
+//! come out looking like the real thing. Labels that mix scripts are flagged
+//! whatever they resemble, since that's rarely innocent in a domain.
+
+use std::net::IpAddr;
+
+use serde::Serialize;
+use unicode_security::{skeleton, MixedScript};
+
+/// Domains people are commonly lured away from. The user's trusted domains
+/// are checked as well.
+pub const WELL_KNOWN: &[&str] = &[
+    "amazon.com",
+    "apple.com",
+    "binance.com",
+    "coinbase.com",
+    "discord.com",
+    "discord.gg",
+    "dropbox.com",
+    "epicgames.com",
+    "facebook.com",
+    "github.com",
+    "gmail.com",
+    "google.com",
+    "icloud.com",
+    "instagram.com",
+    "linkedin.com",
+    "live.com",
+    "microsoft.com",
+    "netflix.com",
+    "outlook.com",
+    "paypal.com",
+    "reddit.com",
+    "roblox.com",
+    "spotify.com",
+    "steamcommunity.com",
+    "steampowered.com",
+    "twitch.tv",
+    "twitter.com",
+    "x.com",
+    "youtube.com",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum Risk {
+    /// A label mixes scripts, like Latin letters with Cyrillic ones.
+    MixedScript { domain: String },
+    /// Reads like `resembles` without being it.
+    #[serde(rename_all = "camelCase")]
+    Confusable { domain: String, resembles: String },
+    /// A numeric address where a name would be.
+    Address { domain: String },
+    /// Text before an `@`, as in `https://bank.com@example.net`, which
+    /// reads like the destination but isn't.
+    UserInfo { text: String },
+    /// A link shortener, which hides where the link goes.
+    Shortened,
+}
+
+/// `domain` with any punycode labels decoded, as a reader would see it.
+pub fn display(domain: &str) -> String {
+    let (unicode, result) = idna::domain_to_unicode(domain);
+    if result.is_ok() {
+        unicode
+    } else {
+        domain.to_string()
+    }
+}
+
+/// The risks in how `domain` reads, given the domains it could be passing
+/// itself off as.
+pub fn domain_risks<'a>(domain: &str, known: impl IntoIterator<Item = &'a str>) -> Vec<Risk> {
+    let host = domain.trim_start_matches('[').trim_end_matches(']');
+    if host.parse::<IpAddr>().is_ok() {
+        return vec![Risk::Address {
+            domain: domain.to_string(),
+        }];
+    }
+
+    let unicode = display(domain);
+    let mut risks = Vec::new();
+    if unicode.split('.').any(|label| !label.is_single_script()) {
+        risks.push(Risk::MixedScript {
+            domain: unicode.clone(),
+        });
+    }
+    if let Some(resembles) = resembles(&unicode, known) {
+        risks.push(Risk::Confusable {
+            domain: unicode,
+            resembles,
+        });
+    }
+    risks
+}
+
+/// The known domain that `domain`, or the domain it's a subdomain of, can
+/// be mistaken for.
+fn resembles<'a>(domain: &str, known: impl IntoIterator<Item = &'a str>) -> Option<String> {
+    let domain = domain.to_lowercase();
+    let labels: Vec<&str> = domain.split('.').collect();
+    // login.paypa1.com is checked as paypa1.com, and com on its own is not
+    let suffixes: Vec<String> = (0..labels.len().saturating_sub(1))
+        .map(|start| labels[start..].join("."))
+        .collect();
+    let skeletons: Vec<String> = suffixes.iter().map(|suffix| fold(suffix)).collect();
+
+    known.into_iter().find_map(|known| {
+        let known_skeleton = fold(known);
+        suffixes
+            .iter()
+            .zip(&skeletons)
+            .any(|(suffix, skeleton)| *skeleton == known_skeleton && suffix != known)
+            .then(|| known.to_string())
+    })
+}
+
+/// The skeleton of `text`, with `i` and `l` taken as the same letter. The
+/// confusables data keeps them apart, so Cyrillic `ӏ` would otherwise turn
+/// `аррӏе.com` into something other than `apple.com`.
+fn fold(text: &str) -> String {
+    skeleton(text)
+        .map(|c| if c == 'i' { 'l' } else { c })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn risks(domain: &str) -> Vec<Risk> {
+        let ascii = idna::domain_to_ascii(domain).unwrap();
+        domain_risks(&ascii, WELL_KNOWN.iter().copied())
+    }
+
+    fn confusable(domain: &str, resembles: &str) -> Risk {
+        Risk::Confusable {
+            domain: domain.to_string(),
+            resembles: resembles.to_string(),
+        }
+    }
+
+    #[test]
+    fn displays_punycode_as_it_reads() {
+        assert_eq!(display("xn--mnchen-3ya.de"), "münchen.de");
+        assert_eq!(display("xn--pypal-4ve.com"), "pаypal.com");
+        assert_eq!(display("example.com"), "example.com");
+    }
+
+    #[test]
+    fn leaves_real_and_single_script_domains_alone() {
+        assert_eq!(risks("paypal.com"), []);
+        assert_eq!(risks("login.paypal.com"), []);
+        assert_eq!(risks("example.com"), []);
+        assert_eq!(risks("münchen.de"), []);
+        assert_eq!(risks("пример.рф"), []);
+    }
+
+    #[test]
+    fn flags_a_cyrillic_letter_in_a_latin_domain() {
+        assert_eq!(
+            risks("pаypal.com"),
+            [
+                Risk::MixedScript {
+                    domain: "pаypal.com".to_string()
+                },
+                confusable("pаypal.com", "paypal.com"),
+            ]
+        );
+    }
+
+    #[test]
+    fn flags_mixed_scripts_that_resemble_nothing() {
+        assert_eq!(
+            risks("exаmple.com"),
+            [Risk::MixedScript {
+                domain: "exаmple.com".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn flags_lookalike_letters_and_digits() {
+        assert_eq!(
+            risks("paypa1.com"),
+            [confusable("paypa1.com", "paypal.com")]
+        );
+        assert_eq!(
+            risks("rnicrosoft.com"),
+            [confusable("rnicrosoft.com", "microsoft.com")]
+        );
+        assert_eq!(
+            risks("login.paypa1.com"),
+            [confusable("login.paypa1.com", "paypal.com")]
+        );
+        // Wholly Cyrillic, so only its likeness gives it away
+        assert_eq!(risks("аррӏе.com"), [confusable("аррӏе.com", "apple.com")]);
+    }
+
+    #[test]
+    fn checks_against_the_given_domains() {
+        assert_eq!(
+            domain_risks("exarnple.com", ["example.com"]),
+            [confusable("exarnple.com", "example.com")]
+        );
+        assert_eq!(domain_risks("exarnple.com", WELL_KNOWN.iter().copied()), []);
+    }
+
+    #[test]
+    fn flags_addresses() {
+        for domain in ["127.0.0.1", "[::1]"] {
+            assert_eq!(
+                domain_risks(domain, WELL_KNOWN.iter().copied()),
+                [Risk::Address {
+                    domain: domain.to_string()
+                }]
+            );
+        }
+    }
+}
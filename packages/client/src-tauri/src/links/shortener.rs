@@ -0,0 +1,204 @@
+//! Following short links to where they lead.
+//!
+//! Only the shortener is asked: its redirects are read one at a time and
+//! followed while they point at another shortener, so the destination site
+//! never sees a request from someone who hasn't decided to visit it.
+
+use reqwest::header::LOCATION;
+use reqwest::{Client, Method, StatusCode};
+use url::Url;
+
+use crate::unfurl::guard;
+
+const MAX_HOPS: usize = 5;
+
+pub const SHORTENERS: &[&str] = &[
+    "bit.ly",
+    "bl.ink",
+    "buff.ly",
+    "cutt.ly",
+    "goo.gl",
+    "is.gd",
+    "lnkd.in",
+    "ow.ly",
+    "rb.gy",
+    "rebrand.ly",
+    "s.id",
+    "shorturl.at",
+    "t.co",
+    "t.ly",
+    "tiny.cc",
+    "tinyurl.com",
+    "v.gd",
+];
+
+pub fn is_shortener(domain: &str) -> bool {
+    let domain = domain.strip_prefix("www.").unwrap_or(domain);
+    SHORTENERS.contains(&domain)
+}
+
+/// Where `url` redirects once it's left the shorteners, or `None` if it
+/// couldn't be followed.
+pub async fn follow(client: &Client, url: &Url) -> Option<Url> {
+    let mut current = url.clone();
+    for _ in 0..MAX_HOPS {
+        guard::check(&current, false).ok()?;
+        let mut response = client.head(current.clone()).send().await.ok()?;
+        // Some only answer GET; the body is left unread
+        if matches!(
+            response.status(),
+            StatusCode::METHOD_NOT_ALLOWED | StatusCode::NOT_IMPLEMENTED
+        ) {
+            response = client
+                .request(Method::GET, current.clone())
+                .send()
+                .await
+                .ok()?;
+        }
+        if !response.status().is_redirection() {
+            // A shortener that doesn't redirect has nowhere to send us
+            return None;
+        }
+        let location = response.headers().get(LOCATION)?.to_str().ok()?;
+        let next = current.join(location).ok()?;
+        if !matches!(next.scheme(), "http" | "https") {
+            return None;
+        }
+        if !next.host_str().is_some_and(is_shortener) {
+            return Some(next);
+        }
+        current = next;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashMap;
+    use std::net::SocketAddr;
+    use std::sync::{Arc, Mutex};
+
+    use reqwest::redirect;
+
+    use crate::test_support::{Connection, Request, StandIn};
+
+    type Redirects = Arc<Mutex<HashMap<String, String>>>;
+
+    /// A local server standing in for every shortener. Paths it has a
+    /// redirect for answer with it, and the rest with an empty page. Paths
+    /// under `/get-only` refuse HEAD, as some shorteners do.
+    struct Shorteners {
+        server: StandIn,
+        redirects: Redirects,
+    }
+
+    impl Shorteners {
+        async fn start() -> Self {
+            let redirects = Redirects::default();
+            let shared = redirects.clone();
+            let server = StandIn::start(move |request, connection| {
+                serve(request, connection, shared.clone())
+            })
+            .await;
+            Self { server, redirects }
+        }
+
+        fn url(&self, host: &str, path: &str) -> Url {
+            Url::parse(&format!("http://{}:{}{}", host, self.server.port(), path)).unwrap()
+        }
+
+        fn redirect(&self, path: &str, to: &str) {
+            self.redirects
+                .lock()
+                .unwrap()
+                .insert(path.to_string(), to.to_string());
+        }
+
+        /// A client that finds every shortener at the stand-in.
+        fn client(&self) -> Client {
+            let local = SocketAddr::from(([127, 0, 0, 1], self.server.port()));
+            SHORTENERS
+                .iter()
+                .fold(Client::builder(), |builder, domain| {
+                    builder.resolve(domain, local)
+                })
+                .redirect(redirect::Policy::none())
+                .no_proxy()
+                .build()
+                .unwrap()
+        }
+    }
+
+    async fn serve(request: Request, mut connection: Connection, redirects: Redirects) {
+        if request.method == "HEAD" && request.path.starts_with("/get-only") {
+            return connection.respond("405 Method Not Allowed", &[], b"").await;
+        }
+        let to = redirects.lock().unwrap().get(&request.path).cloned();
+        match to {
+            Some(to) => {
+                connection
+                    .respond("301 Moved Permanently", &[("Location", &to)], b"")
+                    .await
+            }
+            None => connection.respond("200 OK", &[], b"").await,
+        }
+    }
+
+    #[test]
+    fn recognizes_shorteners() {
+        assert!(is_shortener("bit.ly"));
+        assert!(is_shortener("www.tinyurl.com"));
+        assert!(!is_shortener("example.com"));
+        assert!(!is_shortener("notbit.ly"));
+    }
+
+    #[tokio::test]
+    async fn follows_shorteners_to_the_destination() {
+        let server = Shorteners::start().await;
+        server.redirect("/a", server.url("tinyurl.com", "/b").as_str());
+        server.redirect("/b", "/c");
+        server.redirect("/c", "https://example.com/landing?ref=1");
+
+        let destination = follow(&server.client(), &server.url("bit.ly", "/a")).await;
+        assert_eq!(
+            destination.as_ref().map(Url::as_str),
+            Some("https://example.com/landing?ref=1")
+        );
+    }
+
+    #[tokio::test]
+    async fn asks_with_get_when_head_is_refused() {
+        let server = Shorteners::start().await;
+        server.redirect("/get-only/a", "https://example.com/");
+
+        let destination = follow(&server.client(), &server.url("t.co", "/get-only/a")).await;
+        assert_eq!(
+            destination.as_ref().map(Url::as_str),
+            Some("https://example.com/")
+        );
+    }
+
+    #[tokio::test]
+    async fn gives_up_on_links_that_lead_nowhere() {
+        let server = Shorteners::start().await;
+        let client = server.client();
+        server.redirect("/loop", "/loop");
+        server.redirect("/script", "javascript:alert(1)");
+
+        for path in ["/loop", "/script", "/page"] {
+            let destination = follow(&client, &server.url("bit.ly", path)).await;
+            assert_eq!(destination, None, "{}", path);
+        }
+    }
+
+    #[tokio::test]
+    async fn wont_ask_a_private_address() {
+        let server = Shorteners::start().await;
+        server.redirect("/a", "https://example.com/");
+
+        let url = server.url("127.0.0.1", "/a");
+        assert_eq!(follow(&server.client(), &url).await, None);
+    }
+}
@@ -0,0 +1,100 @@
+//! The backend's log file, kept in the app's log directory.
+//!
+//! Released builds have no console on Windows and are rarely started from a
+//! terminal elsewhere, so what the backend reports would otherwise be lost.
+//! [`log!`] prints a line to stderr and appends it, timestamped, to
+//! `commhub.log`; the previous run's file is kept beside it as
+//! `commhub.old.log`. The Help menu opens the folder.
+
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use chrono::DateTime;
+use tauri::Config;
+
+const LOG_FILE: &str = "commhub.log";
+const OLD_LOG_FILE: &str = "commhub.old.log";
+
+static FILE: OnceLock<Mutex<File>> = OnceLock::new();
+
+/// `eprintln!`, also written to the log file once [`init`] has opened it.
+macro_rules! log {
+    ($($arg:tt)*) => {
+        $crate::logs::write(format_args!($($arg)*))
+    };
+}
+
+/// Opens this run's log file. Called once the instance lock is held, so a
+/// second launch never rotates away the file the running client writes to.
+pub fn init(config: &Config) {
+    let Some(dir) = tauri::api::path::app_log_dir(config) else {
+        return;
+    };
+    match open(&dir) {
+        Ok(file) => {
+            let _ = FILE.set(Mutex::new(file));
+        }
+        Err(e) => eprintln!("[Logs] Failed to open {}: {}", LOG_FILE, e),
+    }
+}
+
+pub fn write(args: fmt::Arguments) {
+    eprintln!("{}", args);
+    if let Some(file) = FILE.get() {
+        let mut file = file.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let _ = writeln!(file, "{} {}", timestamp(SystemTime::now()), args);
+    }
+}
+
+/// Moves the last run's log aside and starts a new one.
+fn open(dir: &Path) -> io::Result<File> {
+    fs::create_dir_all(dir)?;
+    let path = dir.join(LOG_FILE);
+    if path.exists() {
+        fs::rename(&path, dir.join(OLD_LOG_FILE))?;
+    }
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+fn timestamp(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    DateTime::from_timestamp(since_epoch.as_secs() as i64, since_epoch.subsec_nanos())
+        .map(|time| time.format("%Y-%m-%d %H:%M:%S%.3f").to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+
+    use std::time::Duration;
+
+    #[test]
+    fn keeps_the_previous_run_aside() {
+        let dir = test_support::root("logs", "rotate");
+
+        writeln!(open(&dir).unwrap(), "first run").unwrap();
+        writeln!(open(&dir).unwrap(), "second run").unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.join(OLD_LOG_FILE)).unwrap(),
+            "first run\n"
+        );
+        assert_eq!(
+            fs::read_to_string(dir.join(LOG_FILE)).unwrap(),
+            "second run\n"
+        );
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn timestamps_in_utc_to_the_millisecond() {
+        let time = UNIX_EPOCH + Duration::from_millis(1_700_000_000_123);
+        assert_eq!(timestamp(time), "2023-11-14 22:13:20.123");
+    }
+}
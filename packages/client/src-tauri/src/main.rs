@@ -1,27 +1,215 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use tauri::{Manager, Window};
-
-#[tauri::command]
-fn flash_taskbar(window: Window) -> Result<(), String> {
-    // Request attention/flash the taskbar (works on Windows, macOS, Linux)
-    window.request_user_attention(Some(tauri::UserAttentionType::Informational))
-        .map_err(|e| e.to_string())?;
-    Ok(())
-}
+// First, so every module below can use `log!`
+#[macro_use]
+mod logs;
+
+mod attention;
+mod background;
+mod cache;
+mod clipboard;
+mod deeplink;
+mod downloads;
+mod file_drop;
+mod hotkeys;
+mod idle;
+mod instance;
+mod links;
+mod menu;
+mod notifications;
+mod overlay;
+mod popout;
+mod search;
+mod socket;
+#[cfg(test)]
+mod test_support;
+mod tray;
+mod unfurl;
+mod updater;
+mod uploads;
+mod vault;
+mod window_state;
+
+use tauri::Manager;
 
 fn main() {
+    let context = tauri::generate_context!();
+
+    // A second launch hands its arguments to the running client and exits,
+    // before it can touch anything the running one owns
+    let listener = match instance::acquire(context.config()) {
+        instance::Instance::Primary(listener) => listener,
+        instance::Instance::Secondary => return,
+    };
+    logs::init(context.config());
+
+    let updater = updater::Updater::new(context.config(), context.package_info())
+        .expect("error while initializing updater");
+    match updater.apply_staged() {
+        // The installer replaces this binary and relaunches it
+        Ok(true) => return,
+        Ok(false) => {}
+        Err(e) => log!("[Updater] Failed to apply staged update: {}", e),
+    }
+
+    deeplink::listen();
+
+    let app_menu = menu::AppMenu::load(context.config());
+
     tauri::Builder::default()
-        .setup(|_app| {
+        .menu(app_menu.build())
+        .on_menu_event(menu::handle_event)
+        .system_tray(tray::build())
+        .on_system_tray_event(tray::handle_event)
+        .on_window_event(|event| {
+            window_state::handle_window_event(&event);
+            popout::handle_window_event(&event);
+            overlay::handle_window_event(&event);
+            file_drop::handle_window_event(&event);
+            background::handle_window_event(event);
+        })
+        .setup(move |app| {
+            let vault = vault::Vault::from_app(&app.handle())?;
+            if let Err(e) = vault.migrate_legacy() {
+                log!("[Vault] Failed to migrate legacy auth.json: {}", e);
+            }
+            app.manage(vault);
+            app.manage(cache::Cache::from_app(&app.handle())?);
+            app.manage(socket::SocketManager::new());
+            app.manage(updater);
+            app.manage(tray::Tray::default());
+            app.manage(app_menu);
+            app.manage(attention::Attention::default());
+            app.manage(popout::Popouts::default());
+            app.manage(overlay::Overlay::from_app(&app.handle())?);
+            app.manage(clipboard::Clipboard::default());
+            app.manage(file_drop::FileDrops::default());
+            app.manage(uploads::Uploads::new(app.package_info()));
+            app.manage(downloads::Downloads::from_app(&app.handle())?);
+            app.manage(unfurl::Unfurler::from_app(&app.handle())?);
+            app.manage(links::Links::from_app(&app.handle())?);
+            app.manage(notifications::Notifications::new());
+            notifications::Notifications::start(&app.handle());
+            app.manage(background::Background::from_app(&app.handle())?);
+
+            // Hidden in tauri.conf.json so it doesn't jump into place
+            let window_state = window_state::WindowState::from_app(&app.handle())?;
+            if let Some(window) = app.get_window(background::MAIN_WINDOW) {
+                window_state.restore(&window);
+                window.show()?;
+            }
+            app.manage(window_state);
+
+            app.manage(deeplink::DeepLinks::from_args(std::env::args()));
+            deeplink::register(&app.handle());
+
+            let hotkeys = hotkeys::Hotkeys::from_app(&app.handle())?;
+            match hotkeys.start(&app.handle()) {
+                Ok(errors) => {
+                    for e in errors {
+                        log!("[Hotkeys] Skipping a global shortcut: {}", e);
+                    }
+                }
+                Err(e) => log!("[Hotkeys] Failed to register global shortcuts: {}", e),
+            }
+            app.manage(hotkeys);
+
+            app.manage(idle::Idle::from_app(&app.handle())?);
+            idle::Idle::start(&app.handle());
+
+            // Last, so everything a forwarded launch touches is managed
+            if let Some(listener) = listener {
+                instance::serve(&app.handle(), listener);
+            }
+
             #[cfg(debug_assertions)]
             {
-                let window = _app.get_window("main").unwrap();
+                let window = app.get_window(background::MAIN_WINDOW).unwrap();
                 window.open_devtools();
             }
             Ok(())
         })
-        .invoke_handler(tauri::generate_handler![flash_taskbar])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .invoke_handler(tauri::generate_handler![
+            attention::flash_taskbar,
+            attention::attention_set_unread,
+            attention::attention_clear,
+            vault::vault_save,
+            vault::vault_load,
+            vault::vault_clear,
+            cache::cache_open,
+            cache::cache_close,
+            cache::cache_store_channel_messages,
+            cache::cache_store_socket_message,
+            cache::cache_store_direct_messages,
+            cache::cache_store_users,
+            cache::cache_delete_channel_message,
+            cache::cache_delete_direct_message,
+            cache::cache_get_channel_messages,
+            cache::cache_get_conversation,
+            cache::cache_get_user,
+            search::search_messages,
+            socket::socket_connect,
+            socket::socket_disconnect,
+            socket::socket_status,
+            socket::socket_emit,
+            socket::socket_send_message,
+            socket::socket_status_change,
+            updater::updater_check,
+            updater::updater_download,
+            updater::updater_staged,
+            updater::updater_restart,
+            tray::tray_set_status,
+            tray::tray_set_unread,
+            tray::tray_set_voice,
+            background::background_get_settings,
+            background::background_set_settings,
+            hotkeys::hotkeys_get,
+            hotkeys::hotkeys_set,
+            idle::idle_get_settings,
+            idle::idle_set_settings,
+            idle::idle_set_watching,
+            deeplink::deeplink_take_pending,
+            deeplink::deeplink_build,
+            deeplink::deeplink_parse,
+            popout::open_popout,
+            popout::popout_subscribe,
+            popout::popout_unsubscribe,
+            popout::popout_set_always_on_top,
+            popout::popout_close,
+            popout::popout_close_all,
+            menu::menu_keymap,
+            menu::menu_open_keymap,
+            overlay::overlay_get_settings,
+            overlay::overlay_set_settings,
+            overlay::overlay_update,
+            overlay::overlay_snapshot,
+            clipboard::clipboard_read_image,
+            clipboard::clipboard_write_message,
+            clipboard::clipboard_html_to_markdown,
+            file_drop::file_drop_discard,
+            file_drop::file_drop_pick,
+            uploads::upload_dropped,
+            uploads::upload_cancel,
+            downloads::download_save,
+            downloads::download_pause,
+            downloads::download_resume,
+            downloads::download_cancel,
+            downloads::attachment_cached,
+            downloads::attachment_cache,
+            downloads::attachment_cache_usage,
+            downloads::attachment_cache_set_limit,
+            downloads::attachment_cache_clear,
+            unfurl::unfurl_link,
+            links::open_external,
+            links::links_trusted,
+            links::links_trust,
+            links::links_untrust,
+            notifications::notifications_set_viewing,
+            notifications::notifications_clear,
+            notifications::notifications_take_pending
+        ])
+        .build(context)
+        .expect("error while building tauri application")
+        .run(background::handle_run_event);
 }
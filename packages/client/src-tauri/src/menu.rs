@@ -0,0 +1,224 @@
+//! Native application menu: File, Edit, View, Voice and Help.
+//!
+//! Shortcuts come from [`keymap`], which the user can edit; the menu is
+//! built from it once at startup. Items that act on app state are sent to
+//! the main window as a typed `menu://action` event, the same way the tray
+//! does it. Reloading, quitting and opening folders are handled here.
+
+mod keymap;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use tauri::{Config, CustomMenuItem, Manager, Menu, MenuItem, State, Submenu, WindowMenuEvent};
+
+use crate::background::{self, MAIN_WINDOW};
+use crate::tray;
+use keymap::{Keymap, MenuAction};
+
+const KEYMAP_FILE: &str = "keymap.toml";
+
+/// Payload of `menu://action`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MenuEvent {
+    pub action: MenuAction,
+}
+
+/// One row of the shortcuts list in settings.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Shortcut {
+    pub action: MenuAction,
+    pub label: &'static str,
+    pub accelerator: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KeymapInfo {
+    pub path: PathBuf,
+    pub shortcuts: Vec<Shortcut>,
+    pub problems: Vec<String>,
+}
+
+pub struct AppMenu {
+    path: PathBuf,
+    keymap: Keymap,
+}
+
+impl AppMenu {
+    /// Loads keymap.toml before the app is built, since the menu has to be
+    /// handed to the builder. Writes the default file on first run.
+    pub fn load(config: &Config) -> Self {
+        let path = tauri::api::path::app_config_dir(config)
+            .unwrap_or_default()
+            .join(KEYMAP_FILE);
+        let keymap = match fs::read_to_string(&path) {
+            Ok(text) => Keymap::parse(&text),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                if let Err(e) = write_template(&path) {
+                    log!("[Menu] Failed to write {}: {}", KEYMAP_FILE, e);
+                }
+                Keymap::defaults()
+            }
+            Err(e) => {
+                log!("[Menu] Failed to read {}: {}", KEYMAP_FILE, e);
+                Keymap::defaults()
+            }
+        };
+        for problem in keymap.problems() {
+            log!("[Menu] {}", problem);
+        }
+        Self { path, keymap }
+    }
+
+    pub fn build(&self) -> Menu {
+        let item = |action: MenuAction| {
+            let item = CustomMenuItem::new(action.id(), action.label());
+            match self.keymap.accelerator(action) {
+                Some(accelerator) => item.accelerator(accelerator.to_string()),
+                None => item,
+            }
+        };
+
+        let mut file = Menu::new()
+            .add_item(item(MenuAction::QuickSwitcher))
+            .add_item(item(MenuAction::MarkAllRead))
+            .add_native_item(MenuItem::Separator)
+            .add_native_item(MenuItem::CloseWindow);
+        // macOS keeps Quit in the app menu
+        if !cfg!(target_os = "macos") {
+            file = file.add_item(item(MenuAction::Quit));
+        }
+        let edit = Menu::new()
+            .add_native_item(MenuItem::Undo)
+            .add_native_item(MenuItem::Redo)
+            .add_native_item(MenuItem::Separator)
+            .add_native_item(MenuItem::Cut)
+            .add_native_item(MenuItem::Copy)
+            .add_native_item(MenuItem::Paste)
+            .add_native_item(MenuItem::SelectAll);
+        let view = Menu::new()
+            .add_item(item(MenuAction::ZoomIn))
+            .add_item(item(MenuAction::ZoomOut))
+            .add_item(item(MenuAction::ZoomReset))
+            .add_native_item(MenuItem::Separator)
+            .add_item(item(MenuAction::Reload));
+        let voice = Menu::new()
+            .add_item(item(MenuAction::ToggleMute))
+            .add_item(item(MenuAction::ToggleDeafen));
+        let help = Menu::new()
+            .add_item(item(MenuAction::OpenKeymap))
+            .add_item(item(MenuAction::OpenLogs));
+
+        let mut menu = Menu::new();
+        if cfg!(target_os = "macos") {
+            let app = Menu::new()
+                .add_native_item(MenuItem::Hide)
+                .add_native_item(MenuItem::HideOthers)
+                .add_native_item(MenuItem::ShowAll)
+                .add_native_item(MenuItem::Separator)
+                .add_item(item(MenuAction::Quit));
+            menu = menu.add_submenu(Submenu::new("CommHub", app));
+        }
+        menu.add_submenu(Submenu::new("File", file))
+            .add_submenu(Submenu::new("Edit", edit))
+            .add_submenu(Submenu::new("View", view))
+            .add_submenu(Submenu::new("Voice", voice))
+            .add_submenu(Submenu::new("Help", help))
+    }
+
+    /// Each bound item's label and shortcut, in the syntax the platform
+    /// menu parses.
+    pub fn accelerators(&self) -> Vec<(&'static str, String)> {
+        MenuAction::ALL
+            .iter()
+            .filter_map(|action| {
+                let accelerator = self.keymap.accelerator(*action)?;
+                Some((action.label(), accelerator.to_string()))
+            })
+            .collect()
+    }
+
+    pub fn info(&self) -> KeymapInfo {
+        KeymapInfo {
+            path: self.path.clone(),
+            shortcuts: MenuAction::ALL
+                .iter()
+                .map(|action| Shortcut {
+                    action: *action,
+                    label: action.label(),
+                    accelerator: self.keymap.accelerator(*action).map(|a| a.to_string()),
+                })
+                .collect(),
+            problems: self
+                .keymap
+                .problems()
+                .iter()
+                .map(|problem| problem.to_string())
+                .collect(),
+        }
+    }
+}
+
+fn write_template(path: &Path) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, Keymap::template())
+}
+
+pub fn handle_event(event: WindowMenuEvent) {
+    let Some(action) = MenuAction::from_id(event.menu_item_id()) else {
+        return;
+    };
+    let window = event.window();
+    let app = window.app_handle();
+    match action {
+        MenuAction::Quit => background::quit(&app),
+        // Whichever window it was chosen in; pop-outs reload too
+        MenuAction::Reload => {
+            let _ = window.eval("window.location.reload()");
+        }
+        MenuAction::OpenLogs => {
+            if let Some(dir) = app.path_resolver().app_log_dir() {
+                if let Err(e) = fs::create_dir_all(&dir).and_then(|_| open::that(&dir)) {
+                    log!("[Menu] Failed to open logs folder: {}", e);
+                }
+            }
+        }
+        MenuAction::OpenKeymap => {
+            if let Err(e) = open_keymap(&app.state::<AppMenu>()) {
+                log!("[Menu] Failed to open {}: {}", KEYMAP_FILE, e);
+            }
+        }
+        _ => {
+            if action == MenuAction::QuickSwitcher {
+                tray::show_main_window(&app);
+            }
+            if let Some(main) = app.get_window(MAIN_WINDOW) {
+                let _ = main.emit("menu://action", MenuEvent { action });
+            }
+        }
+    }
+}
+
+/// Opens keymap.toml in the user's editor, recreating it if it was deleted.
+fn open_keymap(menu: &AppMenu) -> std::io::Result<()> {
+    if !menu.path.exists() {
+        write_template(&menu.path)?;
+    }
+    open::that(&menu.path)
+}
+
+#[tauri::command]
+pub fn menu_keymap(menu: State<'_, AppMenu>) -> KeymapInfo {
+    menu.info()
+}
+
+#[tauri::command]
+pub fn menu_open_keymap(menu: State<'_, AppMenu>) -> Result<(), String> {
+    open_keymap(&menu).map_err(|e| e.to_string())
+}
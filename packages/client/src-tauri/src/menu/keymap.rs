@@ -0,0 +1,602 @@
+//! Menu shortcuts, read from a user-editable `keymap.toml`.
+//!
+//! Every action has a default, so the file only needs the ones the user
+//! changed. A shortcut that's malformed, collides with another or would
+//! steal a key the webview needs is left unbound and reported rather than
+//! handed to the platform menu, which panics on accelerators it can't
+//! parse.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// What a menu item does. Mirrors `MenuAction` in services/menu.ts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum MenuAction {
+    QuickSwitcher,
+    MarkAllRead,
+    Quit,
+    ToggleMute,
+    ToggleDeafen,
+    ZoomIn,
+    ZoomOut,
+    ZoomReset,
+    Reload,
+    OpenLogs,
+    OpenKeymap,
+}
+
+impl MenuAction {
+    pub const ALL: [MenuAction; 11] = [
+        MenuAction::QuickSwitcher,
+        MenuAction::MarkAllRead,
+        MenuAction::Quit,
+        MenuAction::ToggleMute,
+        MenuAction::ToggleDeafen,
+        MenuAction::ZoomIn,
+        MenuAction::ZoomOut,
+        MenuAction::ZoomReset,
+        MenuAction::Reload,
+        MenuAction::OpenLogs,
+        MenuAction::OpenKeymap,
+    ];
+
+    /// Both the menu item id and the key in keymap.toml.
+    pub fn id(self) -> &'static str {
+        match self {
+            MenuAction::QuickSwitcher => "quick_switcher",
+            MenuAction::MarkAllRead => "mark_all_read",
+            MenuAction::Quit => "quit",
+            MenuAction::ToggleMute => "toggle_mute",
+            MenuAction::ToggleDeafen => "toggle_deafen",
+            MenuAction::ZoomIn => "zoom_in",
+            MenuAction::ZoomOut => "zoom_out",
+            MenuAction::ZoomReset => "zoom_reset",
+            MenuAction::Reload => "reload",
+            MenuAction::OpenLogs => "open_logs",
+            MenuAction::OpenKeymap => "open_keymap",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|action| action.id() == id)
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            MenuAction::QuickSwitcher => "Quick Switcher...",
+            MenuAction::MarkAllRead => "Mark All as Read",
+            MenuAction::Quit => "Quit CommHub",
+            MenuAction::ToggleMute => "Toggle Mute",
+            MenuAction::ToggleDeafen => "Toggle Deafen",
+            MenuAction::ZoomIn => "Zoom In",
+            MenuAction::ZoomOut => "Zoom Out",
+            MenuAction::ZoomReset => "Actual Size",
+            MenuAction::Reload => "Reload",
+            MenuAction::OpenLogs => "Open Logs Folder",
+            MenuAction::OpenKeymap => "Edit Keyboard Shortcuts...",
+        }
+    }
+
+    fn default_accelerator(self) -> Option<&'static str> {
+        match self {
+            MenuAction::QuickSwitcher => Some("CmdOrCtrl+K"),
+            MenuAction::MarkAllRead => Some("CmdOrCtrl+Shift+A"),
+            MenuAction::Quit => Some("CmdOrCtrl+Q"),
+            MenuAction::ToggleMute => Some("CmdOrCtrl+Shift+M"),
+            MenuAction::ToggleDeafen => Some("CmdOrCtrl+Shift+D"),
+            MenuAction::ZoomIn => Some("CmdOrCtrl+="),
+            MenuAction::ZoomOut => Some("CmdOrCtrl+-"),
+            MenuAction::ZoomReset => Some("CmdOrCtrl+0"),
+            MenuAction::Reload => Some("CmdOrCtrl+R"),
+            MenuAction::OpenLogs | MenuAction::OpenKeymap => None,
+        }
+    }
+}
+
+impl fmt::Display for MenuAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.id())
+    }
+}
+
+/// A shortcut resolved for this platform, so `CmdOrCtrl+K` and `Ctrl+K`
+/// compare equal off macOS.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Accelerator {
+    ctrl: bool,
+    alt: bool,
+    shift: bool,
+    command: bool,
+    key: String,
+}
+
+impl Accelerator {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut accelerator = Accelerator {
+            ctrl: false,
+            alt: false,
+            shift: false,
+            command: false,
+            key: String::new(),
+        };
+        for token in text.split('+').map(str::trim) {
+            if !accelerator.key.is_empty() {
+                return Err("the key must come last, after the modifiers".to_string());
+            }
+            match token.to_ascii_uppercase().as_str() {
+                "" => return Err("it has an empty part".to_string()),
+                "CTRL" | "CONTROL" => accelerator.ctrl = true,
+                "ALT" | "OPTION" => accelerator.alt = true,
+                "SHIFT" => accelerator.shift = true,
+                "CMD" | "COMMAND" | "SUPER" => accelerator.command = true,
+                "CMDORCTRL" | "COMMANDORCONTROL" | "COMMANDORCTRL" | "CMDORCONTROL" => {
+                    if cfg!(target_os = "macos") {
+                        accelerator.command = true;
+                    } else {
+                        accelerator.ctrl = true;
+                    }
+                }
+                other => {
+                    accelerator.key =
+                        key_name(other).ok_or_else(|| format!("\"{}\" is not a key", token))?;
+                }
+            }
+        }
+        if accelerator.key.is_empty() {
+            return Err("it has no key".to_string());
+        }
+        // Menu accelerators fire before the webview sees the key, so a
+        // plain or shifted character would stop it being typed
+        let function_key = accelerator.key.len() > 1
+            && accelerator.key.starts_with('F')
+            && accelerator.key[1..].chars().all(|c| c.is_ascii_digit());
+        if !(accelerator.ctrl || accelerator.alt || accelerator.command || function_key) {
+            return Err("it needs Ctrl, Alt or Cmd".to_string());
+        }
+        Ok(accelerator)
+    }
+
+    /// What the webview itself handles; a menu item would take it over.
+    fn reserved_for(&self) -> Option<&'static str> {
+        let primary = if cfg!(target_os = "macos") {
+            self.command && !self.ctrl
+        } else {
+            self.ctrl && !self.command
+        };
+        if !primary || self.alt {
+            return None;
+        }
+        match (self.key.as_str(), self.shift) {
+            ("Z", _) | ("Y", false) => Some("undo and redo"),
+            ("X", false) | ("C", false) | ("V", false) => Some("the clipboard"),
+            ("A", false) => Some("select all"),
+            _ => None,
+        }
+    }
+}
+
+/// Written in the syntax the platform menu parses.
+impl fmt::Display for Accelerator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let command = if cfg!(target_os = "macos") {
+            "Cmd"
+        } else {
+            "Super"
+        };
+        let modifiers = [
+            (self.ctrl, "Ctrl"),
+            (self.alt, "Alt"),
+            (self.shift, "Shift"),
+            (self.command, command),
+        ];
+        for (_, name) in modifiers.iter().filter(|(held, _)| *held) {
+            write!(f, "{}+", name)?;
+        }
+        f.write_str(&self.key)
+    }
+}
+
+/// Canonical name of a key the platform menu understands, from an
+/// uppercased token.
+fn key_name(token: &str) -> Option<String> {
+    if token.len() == 1 && token.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Some(token.to_string());
+    }
+    if let Some(number) = token.strip_prefix('F').and_then(|n| n.parse::<u8>().ok()) {
+        return (1..=24).contains(&number).then(|| format!("F{}", number));
+    }
+    let name = match token {
+        "=" | "EQUAL" => "=",
+        "-" | "MINUS" => "-",
+        "PLUS" => "Plus",
+        "," | "COMMA" => ",",
+        "." | "PERIOD" => ".",
+        "/" | "SLASH" => "/",
+        ";" | "SEMICOLON" => ";",
+        "'" | "QUOTE" => "'",
+        "`" | "BACKQUOTE" => "`",
+        "[" | "BRACKETLEFT" => "[",
+        "]" | "BRACKETRIGHT" => "]",
+        "BACKSLASH" => "Backslash",
+        "SPACE" => "Space",
+        "TAB" => "Tab",
+        "ENTER" => "Enter",
+        "ESC" | "ESCAPE" => "Escape",
+        "BACKSPACE" => "Backspace",
+        "DELETE" => "Delete",
+        "INSERT" => "Insert",
+        "HOME" => "Home",
+        "END" => "End",
+        "PAGEUP" => "PageUp",
+        "PAGEDOWN" => "PageDown",
+        "UP" | "ARROWUP" => "Up",
+        "DOWN" | "ARROWDOWN" => "Down",
+        "LEFT" | "ARROWLEFT" => "Left",
+        "RIGHT" | "ARROWRIGHT" => "Right",
+        _ => return None,
+    };
+    Some(name.to_string())
+}
+
+/// Something in keymap.toml that was ignored.
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum Problem {
+    #[error("keymap.toml line {line}: {message}; using the default shortcuts")]
+    Syntax { line: usize, message: String },
+    #[error("\"{0}\" is not a menu action")]
+    UnknownAction(String),
+    #[error("{action} should be a shortcut in quotes, or \"\" for none")]
+    NotAString { action: MenuAction },
+    #[error("{action}: \"{accelerator}\" is not a valid shortcut, {reason}")]
+    Invalid {
+        action: MenuAction,
+        accelerator: String,
+        reason: String,
+    },
+    #[error("{action}: {accelerator} is reserved for {reserved}")]
+    Reserved {
+        action: MenuAction,
+        accelerator: String,
+        reserved: &'static str,
+    },
+    #[error("{first} and {second} are both bound to {accelerator}, so {second} has none")]
+    Conflict {
+        first: MenuAction,
+        second: MenuAction,
+        accelerator: String,
+    },
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Keymap {
+    bindings: HashMap<MenuAction, Accelerator>,
+    problems: Vec<Problem>,
+}
+
+impl Keymap {
+    pub fn defaults() -> Self {
+        Self::parse("")
+    }
+
+    /// Reads a keymap, keeping every valid binding and collecting the rest
+    /// as problems. Explicit bindings are placed before defaults, so
+    /// rebinding one action to another's default shortcut unbinds the other.
+    pub fn parse(text: &str) -> Self {
+        let mut problems = Vec::new();
+        let table: toml::Table = match toml::from_str(text) {
+            Ok(table) => table,
+            Err(e) => {
+                let mut keymap = Self::defaults();
+                let line = e
+                    .span()
+                    .map_or(1, |span| text[..span.start].matches('\n').count() + 1);
+                let message = e.message().trim().replace('\n', ", ");
+                keymap.problems.push(Problem::Syntax { line, message });
+                return keymap;
+            }
+        };
+
+        let mut explicit: HashMap<MenuAction, String> = HashMap::new();
+        for (key, value) in &table {
+            let Some(action) = MenuAction::from_id(key) else {
+                problems.push(Problem::UnknownAction(key.clone()));
+                continue;
+            };
+            match value.as_str() {
+                Some(accelerator) => {
+                    explicit.insert(action, accelerator.trim().to_string());
+                }
+                None => problems.push(Problem::NotAString { action }),
+            }
+        }
+
+        let wanted = MenuAction::ALL
+            .iter()
+            .filter_map(|action| {
+                explicit
+                    .get(action)
+                    .map(|accelerator| (*action, accelerator.clone()))
+            })
+            .chain(MenuAction::ALL.iter().filter_map(|action| {
+                if explicit.contains_key(action) {
+                    return None;
+                }
+                action
+                    .default_accelerator()
+                    .map(|accelerator| (*action, accelerator.to_string()))
+            }));
+
+        let mut bindings: Vec<(MenuAction, Accelerator)> = Vec::new();
+        for (action, text) in wanted {
+            if text.is_empty() {
+                continue;
+            }
+            let accelerator = match Accelerator::parse(&text) {
+                Ok(accelerator) => accelerator,
+                Err(reason) => {
+                    problems.push(Problem::Invalid {
+                        action,
+                        accelerator: text,
+                        reason,
+                    });
+                    continue;
+                }
+            };
+            if let Some(reserved) = accelerator.reserved_for() {
+                problems.push(Problem::Reserved {
+                    action,
+                    accelerator: accelerator.to_string(),
+                    reserved,
+                });
+                continue;
+            }
+            if let Some((first, _)) = bindings.iter().find(|(_, other)| *other == accelerator) {
+                problems.push(Problem::Conflict {
+                    first: *first,
+                    second: action,
+                    accelerator: accelerator.to_string(),
+                });
+                continue;
+            }
+            bindings.push((action, accelerator));
+        }
+
+        Self {
+            bindings: bindings.into_iter().collect(),
+            problems,
+        }
+    }
+
+    pub fn accelerator(&self, action: MenuAction) -> Option<&Accelerator> {
+        self.bindings.get(&action)
+    }
+
+    pub fn problems(&self) -> &[Problem] {
+        &self.problems
+    }
+
+    /// The file written on first run, listing every action with its
+    /// default so there's something to edit.
+    pub fn template() -> String {
+        let mut text = String::from(
+            "# CommHub menu shortcuts. Changes apply the next time CommHub starts.\n\
+             #\n\
+             # Modifiers: CmdOrCtrl, Ctrl, Alt, Shift and Cmd (Super off macOS).\n\
+             # Keys: A-Z, 0-9, F1-F24, = - , . / ; ' ` [ ] and names such as Plus,\n\
+             # Space, Enter, Escape, Up or PageDown. Use \"\" for no shortcut.\n\n",
+        );
+        for action in MenuAction::ALL {
+            text.push_str(&format!(
+                "{} = \"{}\"\n",
+                action.id(),
+                action.default_accelerator().unwrap_or_default()
+            ));
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn primary() -> &'static str {
+        if cfg!(target_os = "macos") {
+            "Cmd"
+        } else {
+            "Ctrl"
+        }
+    }
+
+    #[test]
+    fn parses_modifiers_and_keys_in_any_case() {
+        let accelerator = Accelerator::parse("ctrl + shift + k").unwrap();
+        assert_eq!(accelerator.to_string(), "Ctrl+Shift+K");
+        assert_eq!(
+            Accelerator::parse("Alt+pagedown").unwrap().to_string(),
+            "Alt+PageDown"
+        );
+        assert_eq!(
+            Accelerator::parse("Control+Option+Equal")
+                .unwrap()
+                .to_string(),
+            "Ctrl+Alt+="
+        );
+    }
+
+    #[test]
+    fn resolves_cmd_or_ctrl_for_this_platform() {
+        let accelerator = Accelerator::parse("CmdOrCtrl+K").unwrap();
+        assert_eq!(accelerator.to_string(), format!("{}+K", primary()));
+        assert_eq!(
+            accelerator,
+            Accelerator::parse(&format!("{}+K", primary())).unwrap()
+        );
+    }
+
+    #[test]
+    fn allows_function_keys_without_modifiers() {
+        assert_eq!(Accelerator::parse("F5").unwrap().to_string(), "F5");
+        assert_eq!(
+            Accelerator::parse("shift+f12").unwrap().to_string(),
+            "Shift+F12"
+        );
+        assert!(Accelerator::parse("F25").is_err());
+    }
+
+    #[test]
+    fn rejects_malformed_accelerators() {
+        for text in [
+            "", "K", "Shift+K", "Ctrl", "Ctrl+", "Ctrl++K", "Ctrl+Foo", "K+Ctrl", "Ctrl+K+J",
+        ] {
+            assert!(Accelerator::parse(text).is_err(), "{:?} parsed", text);
+        }
+    }
+
+    #[test]
+    fn reserves_editing_shortcuts() {
+        for text in [
+            "CmdOrCtrl+C",
+            "CmdOrCtrl+V",
+            "CmdOrCtrl+Z",
+            "CmdOrCtrl+Shift+Z",
+        ] {
+            let accelerator = Accelerator::parse(text).unwrap();
+            assert!(
+                accelerator.reserved_for().is_some(),
+                "{} not reserved",
+                text
+            );
+        }
+        for text in ["CmdOrCtrl+Shift+C", "CmdOrCtrl+Alt+V", "CmdOrCtrl+K"] {
+            let accelerator = Accelerator::parse(text).unwrap();
+            assert_eq!(accelerator.reserved_for(), None, "{} reserved", text);
+        }
+    }
+
+    #[test]
+    fn defaults_bind_without_problems() {
+        let keymap = Keymap::defaults();
+        assert!(keymap.problems().is_empty(), "{:?}", keymap.problems());
+        for action in MenuAction::ALL {
+            let expected = action
+                .default_accelerator()
+                .map(|text| Accelerator::parse(text).unwrap());
+            assert_eq!(keymap.accelerator(action), expected.as_ref());
+        }
+    }
+
+    #[test]
+    fn template_reads_back_as_the_defaults() {
+        let keymap = Keymap::parse(&Keymap::template());
+        assert!(keymap.problems().is_empty(), "{:?}", keymap.problems());
+        for action in MenuAction::ALL {
+            assert_eq!(
+                keymap.accelerator(action),
+                Keymap::defaults().accelerator(action)
+            );
+        }
+    }
+
+    #[test]
+    fn explicit_binding_replaces_the_default() {
+        let keymap = Keymap::parse("quick_switcher = \"Alt+P\"\nreload = \"\"\n");
+        assert!(keymap.problems().is_empty(), "{:?}", keymap.problems());
+        assert_eq!(
+            keymap.accelerator(MenuAction::QuickSwitcher),
+            Some(&Accelerator::parse("Alt+P").unwrap())
+        );
+        assert_eq!(keymap.accelerator(MenuAction::Reload), None);
+    }
+
+    #[test]
+    fn taking_another_actions_default_unbinds_it() {
+        let keymap = Keymap::parse("quick_switcher = \"CmdOrCtrl+R\"\n");
+        assert_eq!(
+            keymap.accelerator(MenuAction::QuickSwitcher),
+            Some(&Accelerator::parse("CmdOrCtrl+R").unwrap())
+        );
+        assert_eq!(keymap.accelerator(MenuAction::Reload), None);
+        assert!(matches!(
+            keymap.problems(),
+            [Problem::Conflict {
+                first: MenuAction::QuickSwitcher,
+                second: MenuAction::Reload,
+                ..
+            }]
+        ));
+    }
+
+    #[test]
+    fn reports_explicit_conflicts_in_menu_order() {
+        let keymap = Keymap::parse("toggle_mute = \"Alt+M\"\nmark_all_read = \"alt+m\"\n");
+        assert!(keymap.accelerator(MenuAction::MarkAllRead).is_some());
+        assert_eq!(keymap.accelerator(MenuAction::ToggleMute), None);
+        assert!(matches!(
+            keymap.problems(),
+            [Problem::Conflict {
+                first: MenuAction::MarkAllRead,
+                second: MenuAction::ToggleMute,
+                ..
+            }]
+        ));
+    }
+
+    #[test]
+    fn leaves_reserved_and_invalid_bindings_unbound() {
+        let keymap = Keymap::parse("quick_switcher = \"CmdOrCtrl+C\"\nreload = \"R\"\n");
+        assert_eq!(keymap.accelerator(MenuAction::QuickSwitcher), None);
+        assert_eq!(keymap.accelerator(MenuAction::Reload), None);
+        assert!(matches!(
+            keymap.problems(),
+            [
+                Problem::Reserved {
+                    action: MenuAction::QuickSwitcher,
+                    reserved: "the clipboard",
+                    ..
+                },
+                Problem::Invalid {
+                    action: MenuAction::Reload,
+                    ..
+                },
+            ]
+        ));
+    }
+
+    #[test]
+    fn reports_unknown_actions_and_non_strings() {
+        let keymap = Keymap::parse("jump = \"Alt+J\"\nreload = 5\n");
+        assert_eq!(keymap.problems().len(), 2, "{:?}", keymap.problems());
+        assert!(keymap
+            .problems()
+            .iter()
+            .any(|problem| matches!(problem, Problem::UnknownAction(key) if key == "jump")));
+        assert!(keymap.problems().iter().any(|problem| matches!(
+            problem,
+            Problem::NotAString {
+                action: MenuAction::Reload
+            }
+        )));
+        // A value that isn't a string falls back to the default
+        assert_eq!(
+            keymap.accelerator(MenuAction::Reload),
+            Keymap::defaults().accelerator(MenuAction::Reload)
+        );
+    }
+
+    #[test]
+    fn syntax_error_falls_back_to_the_defaults() {
+        let keymap = Keymap::parse("reload = \"Alt+R\"\nquick_switcher = \n");
+        assert!(matches!(
+            keymap.problems(),
+            [Problem::Syntax { line: 2, .. }]
+        ));
+        assert_eq!(
+            keymap.accelerator(MenuAction::Reload),
+            Keymap::defaults().accelerator(MenuAction::Reload)
+        );
+    }
+}
@@ -0,0 +1,667 @@
+//! Native notifications for DMs and mentions.
+//!
+//! Gateway events reach this module from the backend socket whether or not a
+//! webview is loaded. [`coalesce`] decides what goes on screen; this side
+//! checks settings and focus, draws the notification and routes clicks and
+//! actions back as `notification://action`, recreating the main window first
+//! if the close policy destroyed it.
+//!
+//! Each platform draws notifications with the API that reports clicks and
+//! action buttons back to the process: a D-Bus notification server on Linux,
+//! toasts on Windows and `UNUserNotificationCenter` on macOS. A macOS build
+//! that isn't running from an app bundle can't use the latter, so it falls
+//! back to notifications that do nothing when clicked.
+
+mod coalesce;
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use serde_json::Value;
+use tauri::{AppHandle, Manager, State, UserAttentionType};
+
+use crate::background::{self, Background, MAIN_WINDOW};
+use crate::cache::Cache;
+pub use coalesce::Conversation;
+use coalesce::{Coalescer, Incoming, Limits, Notice};
+
+const BODY_LIMIT: usize = 200;
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+// Action ids as the platforms report them; a click on the body is `DEFAULT`
+const DEFAULT: &str = "default";
+const MARK_READ: &str = "mark-read";
+const REPLY: &str = "reply";
+
+/// Payload of `notification://action`.
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum NotificationAction {
+    Open { conversation: Conversation },
+    MarkRead { conversation: Conversation },
+    Reply { conversation: Conversation },
+}
+
+pub struct Notifications {
+    coalescer: Mutex<Coalescer>,
+    // What the webview has on screen; only counts while the window is focused
+    viewing: Mutex<Option<Conversation>>,
+    // Held for a window that's still loading and can't hear events yet
+    pending: Mutex<Option<NotificationAction>>,
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    desktop: platform::Desktop,
+}
+
+impl Notifications {
+    pub fn new() -> Self {
+        Self {
+            coalescer: Mutex::new(Coalescer::new(Limits::default())),
+            viewing: Mutex::new(None),
+            pending: Mutex::new(None),
+            #[cfg(any(target_os = "linux", target_os = "macos"))]
+            desktop: platform::Desktop::default(),
+        }
+    }
+
+    /// Periodically releases coalesced notifications.
+    pub fn start(app: &AppHandle) {
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            let mut interval = tokio::time::interval(FLUSH_INTERVAL);
+            loop {
+                interval.tick().await;
+                let notices = app
+                    .state::<Notifications>()
+                    .coalescer
+                    .lock()
+                    .unwrap()
+                    .flush(Instant::now());
+                for notice in notices {
+                    show(&app, notice);
+                }
+            }
+        });
+    }
+
+    pub fn set_dnd(&self, dnd: bool) {
+        self.coalescer.lock().unwrap().set_dnd(dnd);
+    }
+
+    fn is_viewing(&self, app: &AppHandle, conversation: Conversation) -> bool {
+        *self.viewing.lock().unwrap() == Some(conversation)
+            && app
+                .get_window(MAIN_WINDOW)
+                .and_then(|window| window.is_focused().ok())
+                .unwrap_or(false)
+    }
+}
+
+/// Turns a gateway event into a notification if it's a DM or mention the
+/// user isn't already looking at.
+pub fn handle_event(app: &AppHandle, event: &str, payload: &Value) {
+    let Some(incoming) = parse(app, event, payload) else {
+        return;
+    };
+    let notifications = app.state::<Notifications>();
+    if !app.state::<Background>().settings().notifications
+        || notifications.is_viewing(app, incoming.conversation)
+    {
+        return;
+    }
+    let notice = notifications
+        .coalescer
+        .lock()
+        .unwrap()
+        .push(incoming, Instant::now());
+    if let Some(notice) = notice {
+        show(app, notice);
+    }
+}
+
+fn parse(app: &AppHandle, event: &str, payload: &Value) -> Option<Incoming> {
+    let text = |value: &Value, key: &str| value.get(key)?.as_str().map(str::to_string);
+    let own = app.state::<Cache>().account_id();
+
+    let incoming = match event {
+        "direct-message" => {
+            let sender = payload.get("sender")?;
+            let sender_id = sender.get("id")?.as_i64()?;
+            if Some(sender_id) == own {
+                return None;
+            }
+            Incoming {
+                conversation: Conversation::Direct { user_id: sender_id },
+                sender: text(sender, "username")?,
+                body: text(payload, "content").unwrap_or_default(),
+                channel_name: None,
+            }
+        }
+        "mention" => Incoming {
+            conversation: Conversation::Channel {
+                channel_id: payload.get("channelId")?.as_i64()?,
+            },
+            sender: text(payload, "fromUsername")?,
+            body: text(payload, "content").unwrap_or_default(),
+            channel_name: Some(text(payload, "channelName")?),
+        },
+        _ => return None,
+    };
+    Some(Incoming {
+        body: summarize(&incoming.body),
+        ..incoming
+    })
+}
+
+fn summarize(body: &str) -> String {
+    let body = body.trim();
+    if body.is_empty() {
+        "Sent an attachment".to_string()
+    } else if body.chars().count() > BODY_LIMIT {
+        format!("{}…", body.chars().take(BODY_LIMIT).collect::<String>())
+    } else {
+        body.to_string()
+    }
+}
+
+fn show(app: &AppHandle, notice: Notice) {
+    if let Some(window) = app.get_window(MAIN_WINDOW) {
+        if !window.is_focused().unwrap_or(false) {
+            let _ = window.request_user_attention(Some(UserAttentionType::Informational));
+        }
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    app.state::<Notifications>().desktop.show(app, notice);
+
+    #[cfg(target_os = "windows")]
+    platform::show(app, notice);
+
+    #[cfg(not(any(target_os = "linux", target_os = "windows", target_os = "macos")))]
+    show_plain(app, notice);
+}
+
+/// Shows a notification that does nothing when clicked.
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+fn show_plain(app: &AppHandle, notice: Notice) {
+    if let Err(e) =
+        tauri::api::notification::Notification::new(&app.config().tauri.bundle.identifier)
+            .title(notice.title)
+            .body(notice.body)
+            .show()
+    {
+        log!("[Notifications] Failed to show notification: {}", e);
+    }
+}
+
+/// Maps an action id reported by the platform to what it does.
+#[cfg_attr(
+    not(any(target_os = "linux", target_os = "windows", target_os = "macos")),
+    allow(dead_code)
+)]
+fn action(id: &str, conversation: Conversation) -> Option<NotificationAction> {
+    match id {
+        DEFAULT => Some(NotificationAction::Open { conversation }),
+        MARK_READ => Some(NotificationAction::MarkRead { conversation }),
+        REPLY => Some(NotificationAction::Reply { conversation }),
+        _ => None,
+    }
+}
+
+/// Hands the action to the webview, bringing the main window forward for
+/// the actions that open a conversation. Marking read happens out of sight,
+/// in a window recreated hidden if the close policy destroyed it.
+#[cfg_attr(
+    not(any(target_os = "linux", target_os = "windows", target_os = "macos")),
+    allow(dead_code)
+)]
+fn dispatch(app: &AppHandle, action: NotificationAction) {
+    let loaded = app.get_window(MAIN_WINDOW).is_some();
+    // Recreated even when it stays hidden, since the webview does the work
+    let window = background::main_window(app);
+    let raise = !matches!(action, NotificationAction::MarkRead { .. });
+    if let Some(window) = window.filter(|_| raise) {
+        let _ = window.show();
+        let _ = window.unminimize();
+        let _ = window.set_focus();
+        crate::tray::sync_visibility(app);
+    }
+
+    let (NotificationAction::Open { conversation }
+    | NotificationAction::MarkRead { conversation }
+    | NotificationAction::Reply { conversation }) = action;
+    let notifications = app.state::<Notifications>();
+    notifications.coalescer.lock().unwrap().clear(conversation);
+    if loaded {
+        let _ = app.emit_all("notification://action", action);
+    } else {
+        *notifications.pending.lock().unwrap() = Some(action);
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::collections::HashMap;
+    use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use dbus::arg::{PropMap, RefArg, Variant};
+    use dbus::blocking::Connection;
+    use dbus::message::MatchRule;
+    use tauri::AppHandle;
+
+    use super::{action, dispatch, Conversation, Notice, DEFAULT, MARK_READ, REPLY};
+
+    const BUS_NAME: &str = "org.freedesktop.Notifications";
+    const OBJECT_PATH: &str = "/org/freedesktop/Notifications";
+    const INTERFACE: &str = "org.freedesktop.Notifications";
+    const CALL_TIMEOUT: Duration = Duration::from_secs(2);
+    // How long a new notice can wait behind the signal loop
+    const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+    /// Notification ids per conversation, so a new notice replaces the last
+    /// one instead of stacking. Entries go once the notification is acted on
+    /// or closed.
+    type Shown = Arc<Mutex<HashMap<Conversation, u32>>>;
+
+    /// Shows notifications from one thread holding one session bus
+    /// connection, which also hears their actions. Servers only send
+    /// `ActionInvoked` to the connection that showed the notification, and a
+    /// single listener means replacing a notification leaves nothing waiting
+    /// on the old one.
+    #[derive(Default)]
+    pub struct Desktop {
+        sender: Mutex<Option<Sender<Notice>>>,
+    }
+
+    impl Desktop {
+        pub fn show(&self, app: &AppHandle, notice: Notice) {
+            let mut sender = self.sender.lock().unwrap();
+            let notice = match sender.as_ref() {
+                Some(running) => match running.send(notice) {
+                    Ok(()) => return,
+                    // The thread gave up on the bus; try connecting again
+                    Err(mpsc::SendError(notice)) => notice,
+                },
+                None => notice,
+            };
+
+            let (tx, rx) = mpsc::channel();
+            let _ = tx.send(notice);
+            *sender = Some(tx);
+            let app = app.clone();
+            std::thread::spawn(move || {
+                if let Err(e) = run(app, rx) {
+                    log!("[Notifications] Notification server unavailable: {}", e);
+                }
+            });
+        }
+    }
+
+    fn run(app: AppHandle, notices: Receiver<Notice>) -> Result<(), dbus::Error> {
+        let connection = Connection::new_session()?;
+        let shown: Shown = Arc::default();
+
+        let invoked = shown.clone();
+        connection.add_match(
+            MatchRule::new_signal(INTERFACE, "ActionInvoked"),
+            move |(id, action_id): (u32, String), _: &Connection, _| {
+                if let Some(action) =
+                    take(&invoked, id).and_then(|conversation| action(&action_id, conversation))
+                {
+                    dispatch(&app, action);
+                }
+                true
+            },
+        )?;
+        let closed = shown.clone();
+        connection.add_match(
+            MatchRule::new_signal(INTERFACE, "NotificationClosed"),
+            move |(id, _reason): (u32, u32), _: &Connection, _| {
+                take(&closed, id);
+                true
+            },
+        )?;
+
+        loop {
+            loop {
+                match notices.try_recv() {
+                    Ok(notice) => {
+                        if let Err(e) = notify(&connection, &shown, notice) {
+                            log!("[Notifications] Failed to show notification: {}", e);
+                        }
+                    }
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => return Ok(()),
+                }
+            }
+            connection.process(POLL_INTERVAL)?;
+        }
+    }
+
+    fn notify(connection: &Connection, shown: &Shown, notice: Notice) -> Result<(), dbus::Error> {
+        let replaces = shown
+            .lock()
+            .unwrap()
+            .get(&notice.conversation)
+            .copied()
+            .unwrap_or(0);
+        let actions = vec![DEFAULT, "Open", MARK_READ, "Mark as read", REPLY, "Reply"];
+        let mut hints = PropMap::new();
+        hints.insert(
+            "category".to_string(),
+            Variant(Box::new("im.received".to_string()) as Box<dyn RefArg>),
+        );
+
+        let proxy = connection.with_proxy(BUS_NAME, OBJECT_PATH, CALL_TIMEOUT);
+        let (id,): (u32,) = proxy.method_call(
+            INTERFACE,
+            "Notify",
+            (
+                "CommHub",
+                replaces,
+                icon(),
+                notice.title.as_str(),
+                notice.body.as_str(),
+                actions,
+                hints,
+                // The server's default expiry
+                -1i32,
+            ),
+        )?;
+        shown.lock().unwrap().insert(notice.conversation, id);
+        Ok(())
+    }
+
+    fn take(shown: &Shown, id: u32) -> Option<Conversation> {
+        let mut shown = shown.lock().unwrap();
+        let conversation = shown
+            .iter()
+            .find_map(|(conversation, shown_id)| (*shown_id == id).then_some(*conversation))?;
+        shown.remove(&conversation);
+        Some(conversation)
+    }
+
+    /// The executable's name, which is what the desktop entry installs the
+    /// icon as.
+    fn icon() -> String {
+        std::env::current_exe()
+            .ok()
+            .and_then(|exe| {
+                exe.file_stem()
+                    .map(|stem| stem.to_string_lossy().into_owned())
+            })
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use std::path::Path;
+
+    use tauri::AppHandle;
+    use tauri_winrt_notification::Toast;
+
+    use super::{action, dispatch, Notice, DEFAULT, MARK_READ, REPLY};
+
+    /// Shows a toast whose clicks and buttons come back through its
+    /// `Activated` event, which Windows raises in this process while it's
+    /// running.
+    pub fn show(app: &AppHandle, notice: Notice) {
+        let app = app.clone();
+        let app_id = app_id(&app);
+        // Showing waits on WinRT, so keep it off the caller's thread
+        tauri::async_runtime::spawn_blocking(move || {
+            let conversation = notice.conversation;
+            let result = Toast::new(&app_id)
+                .title(&notice.title)
+                .text1(&notice.body)
+                .add_button("Mark as read", MARK_READ)
+                .add_button("Reply", REPLY)
+                .on_activated(move |id| {
+                    // A click on the toast itself carries no arguments
+                    if let Some(action) = action(id.as_deref().unwrap_or(DEFAULT), conversation) {
+                        dispatch(&app, action);
+                    }
+                    Ok(())
+                })
+                .show();
+            if let Err(e) = result {
+                log!("[Notifications] Failed to show notification: {}", e);
+            }
+        });
+    }
+
+    /// The bundle identifier, which the installer registers as the
+    /// AppUserModelID. Builds run from `target` aren't registered, so they
+    /// borrow PowerShell's, as Tauri's own notifications do.
+    fn app_id(app: &AppHandle) -> String {
+        let installed = std::env::current_exe()
+            .ok()
+            .and_then(|exe| exe.parent().map(Path::to_path_buf))
+            .map(|dir| !dir.ends_with("target/debug") && !dir.ends_with("target/release"))
+            .unwrap_or(true);
+        if installed {
+            app.config().tauri.bundle.identifier.clone()
+        } else {
+            Toast::POWERSHELL_APP_ID.to_string()
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use std::ffi::{CStr, CString};
+    use std::os::raw::c_char;
+    use std::sync::OnceLock;
+
+    use block::{Block, ConcreteBlock};
+    use objc::declare::ClassDecl;
+    use objc::rc::autoreleasepool;
+    use objc::runtime::{Object, Protocol, Sel, BOOL};
+    use objc::{class, msg_send, sel, sel_impl};
+    use tauri::AppHandle;
+
+    use super::{action, dispatch, show_plain, Conversation, Notice, DEFAULT, MARK_READ, REPLY};
+
+    #[link(name = "UserNotifications", kind = "framework")]
+    extern "C" {}
+
+    const CATEGORY: &str = "message";
+    const DEFAULT_ACTION: &str = "com.apple.UNNotificationDefaultActionIdentifier";
+    // UNAuthorizationOptionSound | UNAuthorizationOptionAlert
+    const AUTHORIZATION: usize = (1 << 1) | (1 << 2);
+    // UNNotificationPresentationOptionSound | UNNotificationPresentationOptionAlert
+    const PRESENTATION: usize = (1 << 1) | (1 << 2);
+    const ACTION_FOREGROUND: usize = 1 << 2;
+
+    static APP: OnceLock<AppHandle> = OnceLock::new();
+
+    /// Posts through `UNUserNotificationCenter`, whose delegate hears clicks
+    /// and action buttons. Each notification is identified by its
+    /// conversation, so a new notice replaces the last one and the delegate
+    /// knows where a click goes.
+    #[derive(Default)]
+    pub struct Desktop {
+        center: OnceLock<Option<Center>>,
+    }
+
+    struct Center(*mut Object);
+
+    // The notification center is documented as safe to use from any thread
+    unsafe impl Send for Center {}
+    unsafe impl Sync for Center {}
+
+    impl Desktop {
+        pub fn show(&self, app: &AppHandle, notice: Notice) {
+            let center = self.center.get_or_init(|| {
+                let _ = APP.set(app.clone());
+                listen()
+            });
+            let Some(Center(center)) = center else {
+                show_plain(app, notice);
+                return;
+            };
+            let Ok(identifier) = serde_json::to_string(&notice.conversation) else {
+                return;
+            };
+            autoreleasepool(|| unsafe {
+                let content: *mut Object = msg_send![class!(UNMutableNotificationContent), new];
+                let _: () = msg_send![content, setTitle: string(&notice.title)];
+                let _: () = msg_send![content, setBody: string(&notice.body)];
+                let _: () = msg_send![content, setCategoryIdentifier: string(CATEGORY)];
+                let sound: *mut Object = msg_send![class!(UNNotificationSound), defaultSound];
+                let _: () = msg_send![content, setSound: sound];
+                let nil: *mut Object = std::ptr::null_mut();
+                let request: *mut Object = msg_send![class!(UNNotificationRequest),
+                    requestWithIdentifier: string(&identifier)
+                    content: content
+                    trigger: nil];
+                let _: () =
+                    msg_send![*center, addNotificationRequest: request withCompletionHandler: nil];
+                let _: () = msg_send![content, release];
+            });
+        }
+    }
+
+    /// Sets up the delegate and the category that carries the action
+    /// buttons. `None` when the app isn't running from a bundle, which the
+    /// notification center requires.
+    fn listen() -> Option<Center> {
+        autoreleasepool(|| unsafe {
+            let bundle: *mut Object = msg_send![class!(NSBundle), mainBundle];
+            let identifier: *mut Object = msg_send![bundle, bundleIdentifier];
+            if identifier.is_null() {
+                return None;
+            }
+            let center: *mut Object =
+                msg_send![class!(UNUserNotificationCenter), currentNotificationCenter];
+
+            let mut decl = ClassDecl::new("CommHubNotificationDelegate", class!(NSObject))?;
+            if let Some(protocol) = Protocol::get("UNUserNotificationCenterDelegate") {
+                decl.add_protocol(protocol);
+            }
+            decl.add_method(
+                sel!(userNotificationCenter:didReceiveNotificationResponse:withCompletionHandler:),
+                did_receive as extern "C" fn(&Object, Sel, *mut Object, *mut Object, *mut Object),
+            );
+            decl.add_method(
+                sel!(userNotificationCenter:willPresentNotification:withCompletionHandler:),
+                will_present as extern "C" fn(&Object, Sel, *mut Object, *mut Object, *mut Object),
+            );
+            // Never released; the center only holds it weakly
+            let delegate: *mut Object = msg_send![decl.register(), new];
+            let _: () = msg_send![center, setDelegate: delegate];
+
+            let mark_read: *mut Object = msg_send![class!(UNNotificationAction),
+                actionWithIdentifier: string(MARK_READ)
+                title: string("Mark as read")
+                options: 0usize];
+            let reply: *mut Object = msg_send![class!(UNNotificationAction),
+                actionWithIdentifier: string(REPLY)
+                title: string("Reply")
+                options: ACTION_FOREGROUND];
+            let actions = [mark_read, reply];
+            let actions: *mut Object = msg_send![class!(NSArray),
+                arrayWithObjects: actions.as_ptr()
+                count: actions.len()];
+            let intents: *mut Object = msg_send![class!(NSArray), array];
+            let category: *mut Object = msg_send![class!(UNNotificationCategory),
+                categoryWithIdentifier: string(CATEGORY)
+                actions: actions
+                intentIdentifiers: intents
+                options: 0usize];
+            let categories: *mut Object = msg_send![class!(NSSet), setWithObject: category];
+            let _: () = msg_send![center, setNotificationCategories: categories];
+
+            let authorized = ConcreteBlock::new(|_granted: BOOL, _error: *mut Object| {}).copy();
+            let _: () = msg_send![center,
+                requestAuthorizationWithOptions: AUTHORIZATION
+                completionHandler: &*authorized];
+            Some(Center(center))
+        })
+    }
+
+    extern "C" fn did_receive(
+        _: &Object,
+        _: Sel,
+        _center: *mut Object,
+        response: *mut Object,
+        handler: *mut Object,
+    ) {
+        let (identifier, action_id) = unsafe {
+            let notification: *mut Object = msg_send![response, notification];
+            let request: *mut Object = msg_send![notification, request];
+            let identifier: *mut Object = msg_send![request, identifier];
+            let action_id: *mut Object = msg_send![response, actionIdentifier];
+            (read(identifier), read(action_id))
+        };
+        let action_id = if action_id == DEFAULT_ACTION {
+            DEFAULT
+        } else {
+            action_id.as_str()
+        };
+        let conversation = serde_json::from_str::<Conversation>(&identifier).ok();
+        if let (Some(app), Some(action)) = (
+            APP.get(),
+            conversation.and_then(|conversation| action(action_id, conversation)),
+        ) {
+            dispatch(app, action);
+        }
+        unsafe { (*(handler as *mut Block<(), ()>)).call(()) };
+    }
+
+    /// Shows notifications while the app is frontmost too; whether one is
+    /// needed was decided before it was posted.
+    extern "C" fn will_present(
+        _: &Object,
+        _: Sel,
+        _center: *mut Object,
+        _notification: *mut Object,
+        handler: *mut Object,
+    ) {
+        unsafe { (*(handler as *mut Block<(usize,), ()>)).call((PRESENTATION,)) };
+    }
+
+    /// An autoreleased `NSString`.
+    unsafe fn string(value: &str) -> *mut Object {
+        let value = CString::new(value.replace('\0', "")).unwrap_or_default();
+        msg_send![class!(NSString), stringWithUTF8String: value.as_ptr()]
+    }
+
+    unsafe fn read(string: *mut Object) -> String {
+        if string.is_null() {
+            return String::new();
+        }
+        let utf8: *const c_char = msg_send![string, UTF8String];
+        CStr::from_ptr(utf8).to_string_lossy().into_owned()
+    }
+}
+
+/// The webview reports which conversation is on screen so messages there
+/// don't notify.
+#[tauri::command]
+pub fn notifications_set_viewing(
+    notifications: State<'_, Notifications>,
+    conversation: Option<Conversation>,
+) {
+    if let Some(conversation) = conversation {
+        notifications.coalescer.lock().unwrap().clear(conversation);
+    }
+    *notifications.viewing.lock().unwrap() = conversation;
+}
+
+/// Drops anything held for a conversation that has been read elsewhere.
+#[tauri::command]
+pub fn notifications_clear(notifications: State<'_, Notifications>, conversation: Conversation) {
+    notifications.coalescer.lock().unwrap().clear(conversation);
+}
+
+/// Hands a freshly created window the action that opened it.
+#[tauri::command]
+pub fn notifications_take_pending(
+    notifications: State<'_, Notifications>,
+) -> Option<NotificationAction> {
+    notifications.pending.lock().unwrap().take()
+}
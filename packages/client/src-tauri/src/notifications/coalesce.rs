@@ -0,0 +1,378 @@
+//! Grouping and rate limiting, free of Tauri and the desktop so it can be
+//! driven with a synthetic clock.
+//!
+//! The first message in a conversation is shown straight away. Anything else
+//! from that conversation within `window` is held and later shown as one
+//! summary ("3 new messages from X"). A global budget of `burst`
+//! notifications per `period` keeps a busy server from flooding the desktop;
+//! held messages are released by `flush` once there's room.
+
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+/// Which conversation a notification belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum Conversation {
+    #[serde(rename_all = "camelCase")]
+    Direct { user_id: i64 },
+    #[serde(rename_all = "camelCase")]
+    Channel { channel_id: i64 },
+}
+
+/// A DM or mention that may become a notification.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Incoming {
+    pub conversation: Conversation,
+    pub sender: String,
+    pub body: String,
+    /// Set for mentions; names the channel in the title.
+    pub channel_name: Option<String>,
+}
+
+/// What to put on screen.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Notice {
+    pub conversation: Conversation,
+    pub title: String,
+    pub body: String,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Limits {
+    pub window: Duration,
+    pub burst: usize,
+    pub period: Duration,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            window: Duration::from_secs(8),
+            burst: 5,
+            period: Duration::from_secs(60),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Group {
+    last_shown: Option<Instant>,
+    held: u32,
+    latest: Option<Incoming>,
+}
+
+#[derive(Debug, Default)]
+pub struct Coalescer {
+    limits: Limits,
+    groups: HashMap<Conversation, Group>,
+    shown: VecDeque<Instant>,
+    dnd: bool,
+}
+
+impl Coalescer {
+    pub fn new(limits: Limits) -> Self {
+        Self {
+            limits,
+            ..Self::default()
+        }
+    }
+
+    /// Do Not Disturb drops everything, including what's being held.
+    pub fn set_dnd(&mut self, dnd: bool) {
+        self.dnd = dnd;
+        if dnd {
+            self.groups.clear();
+        }
+    }
+
+    /// Returns the notice to show now, or `None` if the message was held or
+    /// dropped.
+    pub fn push(&mut self, incoming: Incoming, now: Instant) -> Option<Notice> {
+        if self.dnd {
+            return None;
+        }
+        let window = self.limits.window;
+        let has_budget = self.has_budget(now);
+        let group = self.groups.entry(incoming.conversation).or_default();
+
+        let cooling = group
+            .last_shown
+            .is_some_and(|shown| now.duration_since(shown) < window);
+        if cooling || group.held > 0 || !has_budget {
+            group.held += 1;
+            group.latest = Some(incoming);
+            return None;
+        }
+
+        group.last_shown = Some(now);
+        self.shown.push_back(now);
+        Some(notice(incoming, 1))
+    }
+
+    /// Releases held groups whose window has passed, as budget allows.
+    pub fn flush(&mut self, now: Instant) -> Vec<Notice> {
+        let window = self.limits.window;
+        let mut ready: Vec<_> = self
+            .groups
+            .iter()
+            .filter(|(_, group)| {
+                group.held > 0
+                    && group
+                        .last_shown
+                        .map_or(true, |shown| now.duration_since(shown) >= window)
+            })
+            .map(|(conversation, group)| (group.last_shown, *conversation))
+            .collect();
+        // Longest-waiting first so a chatty conversation can't starve others
+        ready.sort_by_key(|(last_shown, _)| *last_shown);
+
+        let mut notices = Vec::new();
+        for (_, conversation) in ready {
+            if !self.has_budget(now) {
+                break;
+            }
+            let group = self.groups.get_mut(&conversation).unwrap();
+            if let Some(latest) = group.latest.take() {
+                notices.push(notice(latest, group.held));
+            }
+            group.held = 0;
+            group.last_shown = Some(now);
+            self.shown.push_back(now);
+        }
+
+        // A group holding nothing and past its window acts as if it weren't
+        // there, so it can go
+        self.groups.retain(|_, group| {
+            group.held > 0
+                || group
+                    .last_shown
+                    .is_some_and(|shown| now.duration_since(shown) < window)
+        });
+        notices
+    }
+
+    /// Forgets a conversation once it has been read or opened.
+    pub fn clear(&mut self, conversation: Conversation) {
+        self.groups.remove(&conversation);
+    }
+
+    fn has_budget(&mut self, now: Instant) -> bool {
+        while self
+            .shown
+            .front()
+            .is_some_and(|shown| now.duration_since(*shown) >= self.limits.period)
+        {
+            self.shown.pop_front();
+        }
+        self.shown.len() < self.limits.burst
+    }
+}
+
+fn notice(incoming: Incoming, count: u32) -> Notice {
+    let Incoming {
+        conversation,
+        sender,
+        body,
+        channel_name,
+    } = incoming;
+    let (title, body) = match (channel_name, count) {
+        (None, 1) => (format!("DM from {}", sender), body),
+        (None, count) => (format!("{} new messages from {}", count, sender), body),
+        (Some(channel), 1) => (format!("{} mentioned you in #{}", sender, channel), body),
+        (Some(channel), count) => (
+            format!("{} new mentions in #{}", count, channel),
+            format!("{}: {}", sender, body),
+        ),
+    };
+    Notice {
+        conversation,
+        title,
+        body,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dm(user_id: i64, sender: &str, body: &str) -> Incoming {
+        Incoming {
+            conversation: Conversation::Direct { user_id },
+            sender: sender.to_string(),
+            body: body.to_string(),
+            channel_name: None,
+        }
+    }
+
+    fn mention(channel_id: i64, sender: &str, body: &str) -> Incoming {
+        Incoming {
+            conversation: Conversation::Channel { channel_id },
+            sender: sender.to_string(),
+            body: body.to_string(),
+            channel_name: Some("general".to_string()),
+        }
+    }
+
+    fn limits() -> Limits {
+        Limits {
+            window: Duration::from_secs(8),
+            burst: 3,
+            period: Duration::from_secs(60),
+        }
+    }
+
+    fn secs(start: Instant, secs: u64) -> Instant {
+        start + Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn shows_the_first_message_and_groups_the_rest() {
+        let start = Instant::now();
+        let mut coalescer = Coalescer::new(limits());
+
+        let first = coalescer.push(dm(1, "ada", "hi"), start).unwrap();
+        assert_eq!(first.title, "DM from ada");
+        assert_eq!(first.body, "hi");
+
+        assert_eq!(
+            coalescer.push(dm(1, "ada", "are you"), secs(start, 1)),
+            None
+        );
+        assert_eq!(coalescer.push(dm(1, "ada", "there?"), secs(start, 2)), None);
+        // Still inside the window
+        assert!(coalescer.flush(secs(start, 7)).is_empty());
+
+        let summary = coalescer.flush(secs(start, 8));
+        assert_eq!(
+            summary,
+            vec![Notice {
+                conversation: Conversation::Direct { user_id: 1 },
+                title: "2 new messages from ada".to_string(),
+                body: "there?".to_string(),
+            }]
+        );
+        assert!(coalescer.flush(secs(start, 30)).is_empty());
+    }
+
+    #[test]
+    fn groups_conversations_separately() {
+        let start = Instant::now();
+        let mut coalescer = Coalescer::new(Limits::default());
+
+        assert!(coalescer.push(dm(1, "ada", "hi"), start).is_some());
+        // Another conversation isn't held back by the first one's window
+        assert!(coalescer.push(dm(2, "bob", "yo"), secs(start, 1)).is_some());
+        assert_eq!(
+            coalescer
+                .push(mention(9, "cy", "@you"), secs(start, 2))
+                .unwrap()
+                .title,
+            "cy mentioned you in #general"
+        );
+
+        assert_eq!(
+            coalescer.push(mention(9, "cy", "look"), secs(start, 3)),
+            None
+        );
+        assert_eq!(
+            coalescer.push(mention(9, "di", "here"), secs(start, 4)),
+            None
+        );
+        let summary = coalescer.flush(secs(start, 10));
+        assert_eq!(summary.len(), 1);
+        assert_eq!(summary[0].title, "2 new mentions in #general");
+        assert_eq!(summary[0].body, "di: here");
+    }
+
+    #[test]
+    fn throttles_to_the_burst_budget() {
+        let start = Instant::now();
+        let mut coalescer = Coalescer::new(limits());
+
+        for user_id in 1..=3 {
+            assert!(coalescer.push(dm(user_id, "ada", "hi"), start).is_some());
+        }
+        // Over budget: held even though the conversation is new
+        assert_eq!(coalescer.push(dm(4, "bob", "hi"), secs(start, 1)), None);
+        assert_eq!(coalescer.push(dm(5, "cy", "hi"), secs(start, 2)), None);
+        assert!(coalescer.flush(secs(start, 30)).is_empty());
+
+        // The period has passed
+        let mut released = coalescer.flush(secs(start, 60));
+        released.sort_by_key(|notice| notice.title.clone());
+        let conversations: Vec<_> = released.iter().map(|notice| notice.conversation).collect();
+        assert_eq!(
+            conversations,
+            vec![
+                Conversation::Direct { user_id: 4 },
+                Conversation::Direct { user_id: 5 },
+            ]
+        );
+        assert_eq!(released[0].title, "DM from bob");
+
+        // Those two count against the next period
+        assert!(coalescer.push(dm(6, "di", "hi"), secs(start, 61)).is_some());
+        assert_eq!(coalescer.push(dm(7, "ed", "hi"), secs(start, 62)), None);
+    }
+
+    #[test]
+    fn flushes_what_the_budget_allows() {
+        let start = Instant::now();
+        let mut coalescer = Coalescer::new(Limits {
+            burst: 1,
+            ..limits()
+        });
+
+        assert!(coalescer.push(dm(1, "ada", "hi"), start).is_some());
+        assert_eq!(coalescer.push(dm(2, "bob", "hi"), secs(start, 1)), None);
+        assert_eq!(coalescer.push(dm(3, "cy", "hi"), secs(start, 2)), None);
+
+        assert_eq!(coalescer.flush(secs(start, 60)).len(), 1);
+        assert!(coalescer.flush(secs(start, 61)).is_empty());
+        assert_eq!(coalescer.flush(secs(start, 120)).len(), 1);
+    }
+
+    #[test]
+    fn clearing_and_dnd_drop_held_messages() {
+        let start = Instant::now();
+        let mut coalescer = Coalescer::new(limits());
+
+        assert!(coalescer.push(dm(1, "ada", "hi"), start).is_some());
+        assert_eq!(coalescer.push(dm(1, "ada", "again"), secs(start, 1)), None);
+        coalescer.clear(Conversation::Direct { user_id: 1 });
+        assert!(coalescer.flush(secs(start, 10)).is_empty());
+        // Read, so the next message shows straight away
+        assert!(coalescer
+            .push(dm(1, "ada", "new"), secs(start, 11))
+            .is_some());
+
+        assert_eq!(coalescer.push(dm(1, "ada", "held"), secs(start, 12)), None);
+        coalescer.set_dnd(true);
+        assert_eq!(coalescer.push(dm(2, "bob", "hi"), secs(start, 13)), None);
+        assert!(coalescer.flush(secs(start, 30)).is_empty());
+        coalescer.set_dnd(false);
+        assert!(coalescer
+            .push(dm(2, "bob", "hi"), secs(start, 31))
+            .is_some());
+    }
+
+    #[test]
+    fn forgets_idle_groups() {
+        let start = Instant::now();
+        let mut coalescer = Coalescer::new(limits());
+
+        assert!(coalescer.push(dm(1, "ada", "hi"), start).is_some());
+        assert!(coalescer.push(dm(2, "bob", "hi"), start).is_some());
+        assert_eq!(coalescer.push(dm(2, "bob", "again"), secs(start, 1)), None);
+        assert_eq!(coalescer.groups.len(), 2);
+
+        // Ada's window has passed; Bob's summary was just shown
+        assert_eq!(coalescer.flush(secs(start, 8)).len(), 1);
+        assert_eq!(coalescer.groups.len(), 1);
+        assert!(coalescer.flush(secs(start, 16)).is_empty());
+        assert!(coalescer.groups.is_empty());
+    }
+}
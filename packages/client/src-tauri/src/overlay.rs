@@ -0,0 +1,296 @@
+//! Always-on-top voice overlay: who's in the call and who's talking, over
+//! fullscreen apps.
+//!
+//! The overlay is a small transparent window pinned to a corner of the
+//! screen CommHub is on. It's shown while the user is in a voice channel
+//! with the overlay enabled, and ignores the mouse unless click-through is
+//! toggled off. The call lives in the main window, which streams roster and
+//! speaking changes here with `overlay_update`; they're kept in [`roster`]
+//! and forwarded to the overlay as `overlay://update`.
+
+mod roster;
+
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use tauri::{
+    AppHandle, GlobalWindowEvent, Manager, PhysicalPosition, State, Window, WindowBuilder,
+    WindowEvent, WindowUrl,
+};
+
+use crate::background::MAIN_WINDOW;
+use roster::{Roster, RosterUpdate};
+
+pub const OVERLAY_WINDOW: &str = "overlay";
+
+const SETTINGS_FILE: &str = "overlay.json";
+// Logical pixels; the webview lays the roster out from the chosen corner
+const WIDTH: f64 = 240.0;
+const HEIGHT: f64 = 320.0;
+const MARGIN: f64 = 16.0;
+const MIN_OPACITY: f64 = 0.2;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("overlay settings io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("overlay settings are malformed: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("app directories are unavailable")]
+    NoAppDir,
+    #[error("overlay window error: {0}")]
+    Window(#[from] tauri::Error),
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Corner {
+    TopLeft,
+    #[default]
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+/// Mirrors `OverlaySettings` in services/overlay.ts.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct OverlaySettings {
+    pub enabled: bool,
+    pub corner: Corner,
+    /// Applied by the overlay webview, so the window stays fully
+    /// transparent around it.
+    pub opacity: f64,
+    pub click_through: bool,
+}
+
+impl Default for OverlaySettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            corner: Corner::default(),
+            opacity: 0.85,
+            click_through: true,
+        }
+    }
+}
+
+/// What a freshly loaded overlay renders first.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OverlaySnapshot {
+    pub settings: OverlaySettings,
+    pub roster: Roster,
+}
+
+pub struct Overlay {
+    path: PathBuf,
+    settings: Mutex<OverlaySettings>,
+    roster: Mutex<Roster>,
+}
+
+impl Overlay {
+    pub fn new(path: PathBuf) -> Self {
+        // A missing or unreadable file just means the defaults
+        let settings = fs::read(&path)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .unwrap_or_default();
+        Self {
+            path,
+            settings: Mutex::new(settings),
+            roster: Mutex::default(),
+        }
+    }
+
+    pub fn from_app(app: &AppHandle) -> Result<Self, Error> {
+        let dir = app
+            .path_resolver()
+            .app_config_dir()
+            .ok_or(Error::NoAppDir)?;
+        Ok(Self::new(dir.join(SETTINGS_FILE)))
+    }
+
+    pub fn settings(&self) -> OverlaySettings {
+        *self.settings.lock().unwrap()
+    }
+
+    pub fn snapshot(&self) -> OverlaySnapshot {
+        OverlaySnapshot {
+            settings: self.settings(),
+            roster: self.roster.lock().unwrap().clone(),
+        }
+    }
+
+    pub fn set_settings(&self, app: &AppHandle, settings: OverlaySettings) -> Result<(), Error> {
+        let settings = OverlaySettings {
+            opacity: settings.opacity.clamp(MIN_OPACITY, 1.0),
+            ..settings
+        };
+        {
+            let mut current = self.settings.lock().unwrap();
+            if let Some(dir) = self.path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            fs::write(&self.path, serde_json::to_vec_pretty(&settings)?)?;
+            *current = settings;
+        }
+        if let Some(window) = app.get_window(OVERLAY_WINDOW) {
+            let _ = window.emit("overlay://settings", settings);
+        }
+        self.sync(app)
+    }
+
+    /// Flips click-through from the global shortcut, so the overlay can be
+    /// scrolled without opening settings mid-game. Shortcut handlers run on
+    /// the main thread, and showing the overlay may create its window, so
+    /// the change is made off it like the commands do.
+    pub fn toggle_click_through(app: &AppHandle) {
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            let overlay = app.state::<Overlay>();
+            let settings = overlay.settings();
+            let toggled = OverlaySettings {
+                click_through: !settings.click_through,
+                ..settings
+            };
+            if let Err(e) = overlay.set_settings(&app, toggled) {
+                log!("[Overlay] Failed to toggle click-through: {}", e);
+            }
+        });
+    }
+
+    pub fn update(&self, app: &AppHandle, update: RosterUpdate) -> Result<(), Error> {
+        let (changed, was_in_call) = {
+            let mut roster = self.roster.lock().unwrap();
+            let was_in_call = roster.in_call();
+            (roster.apply(&update), was_in_call)
+        };
+        if !changed {
+            return Ok(());
+        }
+        if let Some(window) = app.get_window(OVERLAY_WINDOW) {
+            let _ = window.emit("overlay://update", &update);
+        }
+        if was_in_call != self.roster.lock().unwrap().in_call() {
+            self.sync(app)?;
+        }
+        Ok(())
+    }
+
+    /// Shows the overlay while in a call with it enabled, and hides it
+    /// otherwise. The window is created the first time it's needed.
+    fn sync(&self, app: &AppHandle) -> Result<(), Error> {
+        let settings = self.settings();
+        let visible = settings.enabled && self.roster.lock().unwrap().in_call();
+        let existing = app.get_window(OVERLAY_WINDOW);
+        if !visible {
+            if let Some(window) = existing {
+                window.hide()?;
+            }
+            return Ok(());
+        }
+
+        let window = match existing {
+            Some(window) => window,
+            None => create(app)?,
+        };
+        place(app, &window, settings.corner)?;
+        window.set_ignore_cursor_events(settings.click_through)?;
+        window.show()?;
+        Ok(())
+    }
+}
+
+fn create(app: &AppHandle) -> Result<Window, Error> {
+    let window = WindowBuilder::new(app, OVERLAY_WINDOW, WindowUrl::App("index.html".into()))
+        .title("CommHub Overlay")
+        .inner_size(WIDTH, HEIGHT)
+        .resizable(false)
+        .decorations(false)
+        .transparent(true)
+        .always_on_top(true)
+        .skip_taskbar(true)
+        .focused(false)
+        .visible(false)
+        .initialization_script("window.__COMMHUB_OVERLAY__ = true;")
+        .build()?;
+    let _ = window.menu_handle().hide();
+    Ok(window)
+}
+
+/// Pins the overlay to `corner` of the monitor the main window is on, or
+/// the primary one while the main window is hidden away.
+fn place(app: &AppHandle, window: &Window, corner: Corner) -> Result<(), Error> {
+    let main = app.get_window(MAIN_WINDOW);
+    let monitor = match main.and_then(|main| main.current_monitor().ok().flatten()) {
+        Some(monitor) => Some(monitor),
+        None => window.primary_monitor()?,
+    };
+    let Some(monitor) = monitor else {
+        return Ok(());
+    };
+
+    let scale = monitor.scale_factor();
+    let (width, height) = ((WIDTH * scale) as i32, (HEIGHT * scale) as i32);
+    let margin = (MARGIN * scale) as i32;
+    let (left, top) = (monitor.position().x, monitor.position().y);
+    let right = left + monitor.size().width as i32;
+    let bottom = top + monitor.size().height as i32;
+    let x = match corner {
+        Corner::TopLeft | Corner::BottomLeft => left + margin,
+        Corner::TopRight | Corner::BottomRight => right - width - margin,
+    };
+    let y = match corner {
+        Corner::TopLeft | Corner::TopRight => top + margin,
+        Corner::BottomLeft | Corner::BottomRight => bottom - height - margin,
+    };
+    window.set_position(PhysicalPosition::new(x, y))?;
+    Ok(())
+}
+
+/// The call lives in the main webview; when it's torn down, so is the call.
+pub fn handle_window_event(event: &GlobalWindowEvent) {
+    let window = event.window();
+    if window.label() != MAIN_WINDOW || !matches!(event.event(), WindowEvent::Destroyed) {
+        return;
+    }
+    let app = window.app_handle();
+    if let Err(e) = app.state::<Overlay>().update(&app, RosterUpdate::Clear) {
+        log!("[Overlay] Failed to hide overlay: {}", e);
+    }
+}
+
+#[tauri::command]
+pub fn overlay_get_settings(overlay: State<'_, Overlay>) -> OverlaySettings {
+    overlay.settings()
+}
+
+/// Async, like every command that may create a window, so it doesn't run
+/// on the main thread.
+#[tauri::command]
+pub async fn overlay_set_settings(
+    app: AppHandle,
+    overlay: State<'_, Overlay>,
+    settings: OverlaySettings,
+) -> Result<(), String> {
+    overlay
+        .set_settings(&app, settings)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn overlay_update(
+    app: AppHandle,
+    overlay: State<'_, Overlay>,
+    update: RosterUpdate,
+) -> Result<(), String> {
+    overlay.update(&app, update).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn overlay_snapshot(overlay: State<'_, Overlay>) -> OverlaySnapshot {
+    overlay.snapshot()
+}
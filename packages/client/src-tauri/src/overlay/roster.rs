@@ -0,0 +1,193 @@
+//! Who is in the voice channel and who is talking, as the overlay shows it.
+//!
+//! The main window owns the call and sends updates; the roster is kept
+//! here so an overlay webview that loads mid-call can start from a
+//! snapshot.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Participant {
+    pub user_id: i64,
+    pub username: String,
+    #[serde(default)]
+    pub speaking: bool,
+    #[serde(default)]
+    pub muted: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Roster {
+    pub channel_name: Option<String>,
+    pub participants: Vec<Participant>,
+}
+
+/// Payload of `overlay_update`, re-emitted to the overlay as
+/// `overlay://update`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum RosterUpdate {
+    /// Someone joined, left or (un)muted; replaces the whole roster.
+    #[serde(rename_all = "camelCase")]
+    Roster {
+        channel_name: Option<String>,
+        participants: Vec<Participant>,
+    },
+    /// Sent on every speaking change, so kept small.
+    #[serde(rename_all = "camelCase")]
+    Speaking { user_id: i64, speaking: bool },
+    /// Left the voice channel.
+    Clear,
+}
+
+impl Roster {
+    pub fn in_call(&self) -> bool {
+        !self.participants.is_empty()
+    }
+
+    /// Returns whether anything the overlay shows changed.
+    pub fn apply(&mut self, update: &RosterUpdate) -> bool {
+        match update {
+            RosterUpdate::Roster {
+                channel_name,
+                participants,
+            } => {
+                let next = Roster {
+                    channel_name: channel_name.clone(),
+                    participants: participants.clone(),
+                };
+                if *self == next {
+                    return false;
+                }
+                *self = next;
+                true
+            }
+            RosterUpdate::Speaking { user_id, speaking } => {
+                match self
+                    .participants
+                    .iter_mut()
+                    .find(|participant| participant.user_id == *user_id)
+                {
+                    Some(participant) if participant.speaking != *speaking => {
+                        participant.speaking = *speaking;
+                        true
+                    }
+                    _ => false,
+                }
+            }
+            RosterUpdate::Clear => {
+                let changed = self.in_call() || self.channel_name.is_some();
+                *self = Roster::default();
+                changed
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn participant(user_id: i64, username: &str) -> Participant {
+        Participant {
+            user_id,
+            username: username.to_string(),
+            speaking: false,
+            muted: false,
+        }
+    }
+
+    fn roster(participants: Vec<Participant>) -> RosterUpdate {
+        RosterUpdate::Roster {
+            channel_name: Some("lobby".to_string()),
+            participants,
+        }
+    }
+
+    fn speaking(user_id: i64, speaking: bool) -> RosterUpdate {
+        RosterUpdate::Speaking { user_id, speaking }
+    }
+
+    #[test]
+    fn joining_replaces_the_roster() {
+        let mut state = Roster::default();
+        assert!(!state.in_call());
+
+        assert!(state.apply(&roster(vec![participant(1, "ada")])));
+        assert!(state.in_call());
+        assert_eq!(state.channel_name.as_deref(), Some("lobby"));
+
+        assert!(state.apply(&roster(vec![participant(1, "ada"), participant(2, "bo")])));
+        assert_eq!(state.participants.len(), 2);
+    }
+
+    #[test]
+    fn leaving_drops_the_participant() {
+        let mut state = Roster::default();
+        state.apply(&roster(vec![participant(1, "ada"), participant(2, "bo")]));
+
+        assert!(state.apply(&roster(vec![participant(1, "ada")])));
+        assert_eq!(state.participants, [participant(1, "ada")]);
+    }
+
+    #[test]
+    fn an_identical_roster_is_not_a_change() {
+        let mut state = Roster::default();
+        state.apply(&roster(vec![participant(1, "ada")]));
+
+        assert!(!state.apply(&roster(vec![participant(1, "ada")])));
+
+        let mut muted = participant(1, "ada");
+        muted.muted = true;
+        assert!(state.apply(&roster(vec![muted])));
+    }
+
+    #[test]
+    fn speaking_changes_only_that_participant() {
+        let mut state = Roster::default();
+        state.apply(&roster(vec![participant(1, "ada"), participant(2, "bo")]));
+
+        assert!(state.apply(&speaking(2, true)));
+        assert!(!state.participants[0].speaking);
+        assert!(state.participants[1].speaking);
+
+        // Repeats and strangers change nothing
+        assert!(!state.apply(&speaking(2, true)));
+        assert!(!state.apply(&speaking(3, true)));
+
+        assert!(state.apply(&speaking(2, false)));
+        assert!(!state.participants[1].speaking);
+    }
+
+    #[test]
+    fn clear_only_reports_a_change_once() {
+        let mut state = Roster::default();
+        assert!(!state.apply(&RosterUpdate::Clear));
+
+        state.apply(&roster(vec![participant(1, "ada")]));
+        assert!(state.apply(&RosterUpdate::Clear));
+        assert_eq!(state, Roster::default());
+        assert!(!state.apply(&RosterUpdate::Clear));
+
+        // An empty channel still shows its name until cleared
+        state.apply(&roster(Vec::new()));
+        assert!(!state.in_call());
+        assert!(state.apply(&RosterUpdate::Clear));
+    }
+
+    #[test]
+    fn updates_read_the_webview_payloads() {
+        let update: RosterUpdate =
+            serde_json::from_str(r#"{"kind":"speaking","userId":4,"speaking":true}"#).unwrap();
+        assert_eq!(update, speaking(4, true));
+        let update: RosterUpdate = serde_json::from_str(
+            r#"{"kind":"roster","channelName":"lobby","participants":[{"userId":1,"username":"ada"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(update, roster(vec![participant(1, "ada")]));
+        let update: RosterUpdate = serde_json::from_str(r#"{"kind":"clear"}"#).unwrap();
+        assert_eq!(update, RosterUpdate::Clear);
+    }
+}
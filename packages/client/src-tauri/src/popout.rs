@@ -0,0 +1,222 @@
+//! Pop-out windows for a DM, a channel or a participant's stream.
+//!
+//! Each pop-out is its own webview, labelled after its target ([`target`])
+//! so its geometry is remembered like the main window's. It's told what to
+//! show through an initialization script, and socket events reach it only
+//! for the conversations it subscribed to ([`route`]). Streams can't cross
+//! webviews, so a stream pop-out asks the main window to relay the video
+//! over `popout://stream-*` events.
+
+mod route;
+mod target;
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+
+use serde_json::{json, Value};
+use tauri::{
+    AppHandle, GlobalWindowEvent, Manager, State, Window, WindowBuilder, WindowEvent, WindowUrl,
+};
+
+use crate::background::MAIN_WINDOW;
+use crate::notifications::Conversation;
+use crate::overlay::OVERLAY_WINDOW;
+use crate::window_state::WindowState;
+pub use target::{is_popout, PopoutTarget};
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Target(#[from] target::Error),
+    #[error("failed to create pop-out window: {0}")]
+    Window(#[from] tauri::Error),
+}
+
+struct Popout {
+    target: PopoutTarget,
+    subscriptions: HashSet<Conversation>,
+}
+
+#[derive(Default)]
+pub struct Popouts {
+    windows: Mutex<HashMap<String, Popout>>,
+}
+
+impl Popouts {
+    /// Opens a pop-out, or brings back the one already showing `target`.
+    /// Returns its label.
+    pub fn open(
+        &self,
+        app: &AppHandle,
+        target: PopoutTarget,
+        title: &str,
+        always_on_top: bool,
+    ) -> Result<String, Error> {
+        target.validate()?;
+        let label = target.label();
+        if let Some(window) = app.get_window(&label) {
+            let _ = window.unminimize();
+            let _ = window.show();
+            let _ = window.set_focus();
+            return Ok(label);
+        }
+
+        // Registered first so no event is missed while the webview loads
+        self.windows.lock().unwrap().insert(
+            label.clone(),
+            Popout {
+                target,
+                subscriptions: target.conversation().into_iter().collect(),
+            },
+        );
+        let (width, height) = target.default_size();
+        let script = format!(
+            "window.__COMMHUB_POPOUT__ = {};",
+            json!({ "label": label, "target": target, "alwaysOnTop": always_on_top })
+        );
+        let built = WindowBuilder::new(app, &label, WindowUrl::App("index.html".into()))
+            .title(title)
+            .inner_size(width, height)
+            .min_inner_size(320.0, 240.0)
+            .always_on_top(always_on_top)
+            .initialization_script(&script)
+            .center()
+            .visible(false)
+            .build();
+        let window = match built {
+            Ok(window) => window,
+            Err(e) => {
+                self.windows.lock().unwrap().remove(&label);
+                return Err(e.into());
+            }
+        };
+        // Every window is given the app menu; only the main one shows it
+        let _ = window.menu_handle().hide();
+        app.state::<WindowState>().restore(&window);
+        window.show()?;
+        Ok(label)
+    }
+
+    fn subscribe(&self, label: &str, conversation: Conversation, subscribed: bool) {
+        if let Some(popout) = self.windows.lock().unwrap().get_mut(label) {
+            if subscribed {
+                popout.subscriptions.insert(conversation);
+            } else {
+                popout.subscriptions.remove(&conversation);
+            }
+        }
+    }
+
+    /// Whether the window labelled `label` should get a socket event.
+    fn wants(&self, label: &str, audience: &route::Audience) -> bool {
+        let windows = self.windows.lock().unwrap();
+        let Some(popout) = windows.get(label) else {
+            // The overlay only listens to overlay:// events
+            return !is_popout(label) && label != OVERLAY_WINDOW;
+        };
+        audience.reaches(&popout.subscriptions)
+    }
+
+    /// Closes every pop-out, or only those showing streams, which can't
+    /// outlive the main window relaying them.
+    pub fn close_all(&self, app: &AppHandle, streams_only: bool) {
+        let labels: Vec<String> = self
+            .windows
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, popout)| {
+                !streams_only || matches!(popout.target, PopoutTarget::Stream { .. })
+            })
+            .map(|(label, _)| label.clone())
+            .collect();
+        for label in labels {
+            if let Some(window) = app.get_window(&label) {
+                let _ = window.close();
+            }
+        }
+    }
+
+    /// Forgets a closed pop-out and tells the main window to drop any relay
+    /// it was running for it.
+    pub fn closed(&self, app: &AppHandle, label: &str) {
+        if self.windows.lock().unwrap().remove(label).is_some() {
+            let _ = app.emit_all("popout://closed", label);
+        }
+    }
+}
+
+pub fn handle_window_event(event: &GlobalWindowEvent) {
+    let WindowEvent::Destroyed = event.event() else {
+        return;
+    };
+    let window = event.window();
+    let app = window.app_handle();
+    let popouts = app.state::<Popouts>();
+    if window.label() == MAIN_WINDOW {
+        popouts.close_all(&app, true);
+    } else {
+        popouts.closed(&app, window.label());
+    }
+}
+
+/// Emits a socket event to the windows it concerns.
+pub fn emit_socket_event(app: &AppHandle, name: &str, payload: Value) {
+    let audience = route::audience(name, &payload);
+    let popouts = app.state::<Popouts>();
+    let event = format!("socket://{}", name);
+    for (label, window) in app.windows() {
+        if popouts.wants(&label, &audience) {
+            let _ = window.emit(&event, payload.clone());
+        }
+    }
+}
+
+/// Async so the window isn't created on the main thread, which deadlocks
+/// on Windows.
+#[tauri::command]
+pub async fn open_popout(
+    app: AppHandle,
+    popouts: State<'_, Popouts>,
+    target: PopoutTarget,
+    title: String,
+    always_on_top: Option<bool>,
+) -> Result<String, String> {
+    popouts
+        .open(&app, target, &title, always_on_top.unwrap_or(false))
+        .map_err(|e| e.to_string())
+}
+
+/// Routes events for another conversation to the calling pop-out, e.g. a
+/// channel's chat next to a stream from it.
+#[tauri::command]
+pub fn popout_subscribe(window: Window, popouts: State<'_, Popouts>, conversation: Conversation) {
+    popouts.subscribe(window.label(), conversation, true);
+}
+
+#[tauri::command]
+pub fn popout_unsubscribe(window: Window, popouts: State<'_, Popouts>, conversation: Conversation) {
+    popouts.subscribe(window.label(), conversation, false);
+}
+
+#[tauri::command]
+pub fn popout_set_always_on_top(window: Window, enabled: bool) -> Result<(), String> {
+    if !is_popout(window.label()) {
+        return Err("not a pop-out window".to_string());
+    }
+    window.set_always_on_top(enabled).map_err(|e| e.to_string())
+}
+
+/// Closes the calling pop-out; the window API isn't allowlisted.
+#[tauri::command]
+pub fn popout_close(window: Window) -> Result<(), String> {
+    if !is_popout(window.label()) {
+        return Err("not a pop-out window".to_string());
+    }
+    window.close().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn popout_close_all(app: AppHandle, popouts: State<'_, Popouts>) {
+    popouts.close_all(&app, false);
+}
@@ -0,0 +1,167 @@
+//! Which windows a socket event is for.
+//!
+//! The main window gets everything, as it did when events were broadcast.
+//! A pop-out only gets connection lifecycle and presence, plus events for
+//! the conversations it subscribed to; voice signalling in particular must
+//! never reach a second webview, which would answer it.
+
+use serde_json::Value;
+
+use crate::notifications::Conversation;
+
+/// Events every window needs to render a conversation.
+const SHARED: &[&str] = &[
+    "connect",
+    "disconnect",
+    "connect_error",
+    "reconnect_attempt",
+    "status-update",
+    "friend-presence",
+    "online-friends",
+    "channel-updated",
+    "channel-deleted",
+    "version-mismatch",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Audience {
+    All,
+    Main,
+    Channel(i64),
+    /// Both participants; the other one is whichever isn't us.
+    Direct(i64, i64),
+}
+
+impl Audience {
+    /// Whether a pop-out subscribed to `subscriptions` gets the event; the
+    /// main window always does.
+    pub fn reaches<'a>(&self, subscriptions: impl IntoIterator<Item = &'a Conversation>) -> bool {
+        match self {
+            Audience::All => true,
+            Audience::Main => false,
+            _ => subscriptions
+                .into_iter()
+                .any(|conversation| self.includes(conversation)),
+        }
+    }
+
+    pub fn includes(&self, conversation: &Conversation) -> bool {
+        match (self, conversation) {
+            (Audience::Channel(id), Conversation::Channel { channel_id }) => id == channel_id,
+            (Audience::Direct(sender, receiver), Conversation::Direct { user_id }) => {
+                sender == user_id || receiver == user_id
+            }
+            _ => false,
+        }
+    }
+}
+
+pub fn audience(event: &str, payload: &Value) -> Audience {
+    let id = |key: &str| payload.get(key).and_then(Value::as_i64);
+    match event {
+        "message" | "message-edited" | "message-deleted" => match id("channelId") {
+            Some(channel_id) => Audience::Channel(channel_id),
+            None => Audience::Main,
+        },
+        "direct-message" | "direct-message-edited" | "direct-message-deleted" => {
+            match (id("senderId"), id("receiverId")) {
+                (Some(sender), Some(receiver)) => Audience::Direct(sender, receiver),
+                _ => Audience::Main,
+            }
+        }
+        _ if SHARED.contains(&event) => Audience::All,
+        _ => Audience::Main,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const GENERAL: Conversation = Conversation::Channel { channel_id: 1 };
+    const RANDOM: Conversation = Conversation::Channel { channel_id: 2 };
+    const BOB: Conversation = Conversation::Direct { user_id: 7 };
+
+    #[test]
+    fn routes_events_by_conversation() {
+        let cases: &[(&str, Value, Audience)] = &[
+            ("message", json!({ "channelId": 1 }), Audience::Channel(1)),
+            (
+                "message-edited",
+                json!({ "channelId": 2 }),
+                Audience::Channel(2),
+            ),
+            (
+                "message-deleted",
+                json!({ "channelId": 1, "messageId": 5 }),
+                Audience::Channel(1),
+            ),
+            (
+                "direct-message",
+                json!({ "senderId": 7, "receiverId": 1 }),
+                Audience::Direct(7, 1),
+            ),
+            (
+                "direct-message-deleted",
+                json!({ "messageId": 5, "senderId": 1, "receiverId": 7 }),
+                Audience::Direct(1, 7),
+            ),
+            ("connect", json!({}), Audience::All),
+            ("friend-presence", json!({ "userId": 7 }), Audience::All),
+            ("version-mismatch", json!({}), Audience::All),
+            // Without the ids there's no telling which pop-out it's for
+            ("message", json!({ "content": "hi" }), Audience::Main),
+            ("message", json!({ "channelId": "1" }), Audience::Main),
+            ("direct-message", json!({ "senderId": 7 }), Audience::Main),
+            ("direct-message", Value::Null, Audience::Main),
+            // Voice signalling is answered by whichever window gets it
+            ("voice-offer", json!({ "channelId": 1 }), Audience::Main),
+            ("voice-answer", json!({ "channelId": 1 }), Audience::Main),
+            (
+                "voice-ice-candidate",
+                json!({ "channelId": 1 }),
+                Audience::Main,
+            ),
+            (
+                "voice-user-joined",
+                json!({ "channelId": 1 }),
+                Audience::Main,
+            ),
+            ("typing", json!({ "channelId": 1 }), Audience::Main),
+        ];
+        for (event, payload, expected) in cases {
+            assert_eq!(audience(event, payload), *expected, "{} {}", event, payload);
+        }
+    }
+
+    #[test]
+    fn popouts_get_only_what_they_subscribed_to() {
+        // Audience, the pop-out's subscriptions, whether it gets the event
+        let cases: &[(Audience, &[Conversation], bool)] = &[
+            (Audience::Channel(1), &[GENERAL], true),
+            (Audience::Channel(1), &[RANDOM, BOB], false),
+            (Audience::Channel(1), &[RANDOM, GENERAL], true),
+            // Either direction of the DM
+            (Audience::Direct(7, 1), &[BOB], true),
+            (Audience::Direct(1, 7), &[BOB], true),
+            (Audience::Direct(1, 8), &[BOB], false),
+            // A DM and a channel with the same id are different conversations
+            (Audience::Direct(1, 8), &[GENERAL], false),
+            (Audience::Channel(7), &[BOB], false),
+            (Audience::All, &[], true),
+            (Audience::All, &[GENERAL], true),
+            (Audience::Main, &[GENERAL, BOB], false),
+            (Audience::Channel(1), &[], false),
+        ];
+        for (audience, subscriptions, expected) in cases {
+            assert_eq!(
+                audience.reaches(subscriptions.iter()),
+                *expected,
+                "{:?} {:?}",
+                audience,
+                subscriptions
+            );
+        }
+    }
+}
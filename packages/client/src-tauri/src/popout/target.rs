@@ -0,0 +1,79 @@
+//! What a pop-out window shows, and the window label derived from it.
+//!
+//! The label is the identity: opening the same DM twice focuses the
+//! existing window, and its geometry is saved under that label.
+
+use serde::{Deserialize, Serialize};
+
+use crate::notifications::Conversation;
+
+pub const LABEL_PREFIX: &str = "popout-";
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("invalid id: {0}")]
+    Id(i64),
+}
+
+/// Payload of `open_popout`, and what the pop-out webview is told it shows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum PopoutTarget {
+    #[serde(rename_all = "camelCase")]
+    Direct { user_id: i64 },
+    #[serde(rename_all = "camelCase")]
+    Channel { channel_id: i64 },
+    /// A participant's camera or screen share in a voice channel.
+    #[serde(rename_all = "camelCase")]
+    Stream { channel_id: i64, user_id: i64 },
+}
+
+impl PopoutTarget {
+    pub fn validate(&self) -> Result<(), Error> {
+        let ids: &[i64] = match self {
+            PopoutTarget::Direct { user_id } => &[*user_id],
+            PopoutTarget::Channel { channel_id } => &[*channel_id],
+            PopoutTarget::Stream {
+                channel_id,
+                user_id,
+            } => &[*channel_id, *user_id],
+        };
+        match ids.iter().find(|id| **id <= 0) {
+            Some(id) => Err(Error::Id(*id)),
+            None => Ok(()),
+        }
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            PopoutTarget::Direct { user_id } => format!("{LABEL_PREFIX}dm-{user_id}"),
+            PopoutTarget::Channel { channel_id } => format!("{LABEL_PREFIX}channel-{channel_id}"),
+            PopoutTarget::Stream {
+                channel_id,
+                user_id,
+            } => format!("{LABEL_PREFIX}stream-{channel_id}-{user_id}"),
+        }
+    }
+
+    /// The conversation whose socket events the window needs from the
+    /// start. Streams come from the main window, not the socket.
+    pub fn conversation(&self) -> Option<Conversation> {
+        match *self {
+            PopoutTarget::Direct { user_id } => Some(Conversation::Direct { user_id }),
+            PopoutTarget::Channel { channel_id } => Some(Conversation::Channel { channel_id }),
+            PopoutTarget::Stream { .. } => None,
+        }
+    }
+
+    /// Inner size for a window that has no saved geometry yet.
+    pub fn default_size(&self) -> (f64, f64) {
+        match self {
+            PopoutTarget::Direct { .. } | PopoutTarget::Channel { .. } => (480.0, 640.0),
+            PopoutTarget::Stream { .. } => (960.0, 540.0),
+        }
+    }
+}
+
+pub fn is_popout(label: &str) -> bool {
+    label.starts_with(LABEL_PREFIX)
+}
@@ -0,0 +1,777 @@
+//! Full-text search over the local message cache.
+//!
+//! Queries are free text plus optional operators:
+//!
+//! - `from:username` / `from:me`
+//! - `in:#channel` for a channel, `in:@username` for a DM conversation
+//! - `has:attachment`, `has:image`, `has:link`
+//! - `before:YYYY-MM-DD`, `after:YYYY-MM-DD`
+//! - `mentions:username` / `mentions:me`
+//!
+//! The FTS5 index is maintained by triggers in the cache schema, so anything
+//! the cache stores (history fetches and live socket events) is searchable
+//! right away.
+
+use chrono::NaiveDate;
+use rusqlite::types::Value;
+use serde::Serialize;
+use tauri::State;
+
+use crate::cache::{self, Cache, UserRef};
+
+const DEFAULT_LIMIT: u32 = 25;
+const MAX_LIMIT: u32 = 100;
+const SNIPPET_TOKENS: i64 = 16;
+const PREVIEW_CHARS: usize = 160;
+
+// Control characters can't appear in typed messages, so they make safe
+// highlight delimiters for snippet()
+const MARK_START: char = '\u{2}';
+const MARK_END: char = '\u{3}';
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Cache(#[from] cache::Error),
+    #[error("search failed: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+    #[error("invalid search: {0}")]
+    InvalidQuery(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Who {
+    Me,
+    Username(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Filter {
+    From(Who),
+    InChannel(String),
+    InConversation(Who),
+    HasAttachment,
+    HasImage,
+    HasLink,
+    Before(NaiveDate),
+    After(NaiveDate),
+    Mentions(Who),
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+struct ParsedQuery {
+    terms: Vec<String>,
+    phrases: Vec<String>,
+    filters: Vec<Filter>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum HitKind {
+    Channel,
+    Direct,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnippetSegment {
+    pub text: String,
+    pub highlighted: bool,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchHit {
+    pub kind: HitKind,
+    pub message_id: i64,
+    pub channel_id: Option<i64>,
+    pub channel_name: Option<String>,
+    /// The other participant when `kind` is `direct`.
+    pub conversation_user_id: Option<i64>,
+    pub author: UserRef,
+    pub created_at: String,
+    pub snippet: Vec<SnippetSegment>,
+    /// bm25 score; lower is a better match. Zero for filter-only searches.
+    pub rank: f64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchResults {
+    pub hits: Vec<SearchHit>,
+    pub has_more: bool,
+}
+
+fn parse_who(value: &str) -> Result<Who, Error> {
+    if value.eq_ignore_ascii_case("me") {
+        return Ok(Who::Me);
+    }
+    let name = value.trim_start_matches('@');
+    // Same charset the server accepts for @mentions
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        return Err(Error::InvalidQuery(format!(
+            "'{}' is not a username",
+            value
+        )));
+    }
+    Ok(Who::Username(name.to_string()))
+}
+
+fn parse_date(key: &str, value: &str) -> Result<NaiveDate, Error> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map_err(|_| Error::InvalidQuery(format!("{}: expects a date like 2025-01-31", key)))
+}
+
+fn parse_filter(key: &str, value: &str) -> Result<Option<Filter>, Error> {
+    let filter = match key.to_ascii_lowercase().as_str() {
+        "from" => Filter::From(parse_who(value)?),
+        "in" => match value.strip_prefix('@') {
+            Some(user) => Filter::InConversation(parse_who(user)?),
+            None => Filter::InChannel(value.trim_start_matches('#').to_string()),
+        },
+        "has" => match value.to_ascii_lowercase().as_str() {
+            "attachment" | "file" => Filter::HasAttachment,
+            "image" => Filter::HasImage,
+            "link" => Filter::HasLink,
+            _ => {
+                return Err(Error::InvalidQuery(format!(
+                    "has: expects attachment, image or link, not '{}'",
+                    value
+                )))
+            }
+        },
+        "before" => Filter::Before(parse_date(key, value)?),
+        "after" => Filter::After(parse_date(key, value)?),
+        "mentions" => Filter::Mentions(parse_who(value)?),
+        _ => return Ok(None),
+    };
+    Ok(Some(filter))
+}
+
+fn parse_query(input: &str) -> Result<ParsedQuery, Error> {
+    let mut parsed = ParsedQuery::default();
+    let mut chars = input.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+
+        if c == '"' {
+            chars.next();
+            let phrase: String = chars.by_ref().take_while(|&c| c != '"').collect();
+            if has_words(&phrase) {
+                parsed.phrases.push(phrase.trim().to_string());
+            }
+            continue;
+        }
+
+        let mut token = String::new();
+        while let Some(&c) = chars.peek() {
+            if c.is_whitespace() {
+                break;
+            }
+            token.push(c);
+            chars.next();
+        }
+
+        // `key:value` is an operator unless the key is unknown, e.g. "re:" or a URL
+        if let Some((key, value)) = token.split_once(':') {
+            if !key.is_empty() && !value.is_empty() {
+                if let Some(filter) = parse_filter(key, value)? {
+                    parsed.filters.push(filter);
+                    continue;
+                }
+            }
+        }
+        // Punctuation alone tokenizes to nothing, and `"?"*` isn't valid FTS5
+        if has_words(&token) {
+            parsed.terms.push(token);
+        }
+    }
+
+    Ok(parsed)
+}
+
+fn has_words(text: &str) -> bool {
+    text.chars().any(char::is_alphanumeric)
+}
+
+impl ParsedQuery {
+    /// FTS5 MATCH expression, or None when only filters were given. Every
+    /// term is quoted so user input can't inject FTS syntax; the last bare
+    /// term is a prefix match so results update while typing.
+    fn fts_expression(&self) -> Option<String> {
+        let quote = |s: &str| format!("\"{}\"", s.replace('"', "\"\""));
+        let mut parts: Vec<String> = self.phrases.iter().map(|p| quote(p)).collect();
+        let last = self.terms.len().saturating_sub(1);
+        for (i, term) in self.terms.iter().enumerate() {
+            if i == last {
+                parts.push(format!("{}*", quote(term)));
+            } else {
+                parts.push(quote(term));
+            }
+        }
+        if parts.is_empty() {
+            None
+        } else {
+            Some(parts.join(" "))
+        }
+    }
+}
+
+/// Resolves `me`/usernames against cached users. Unknown users resolve to an
+/// id that matches nothing rather than erroring, since the cache may simply
+/// not have seen them yet.
+fn user_id_clause(who: &Who, me: i64, params: &mut Vec<Value>) -> String {
+    match who {
+        Who::Me => {
+            params.push(Value::Integer(me));
+            "?".to_string()
+        }
+        Who::Username(name) => {
+            params.push(Value::Text(name.clone()));
+            "(SELECT id FROM users WHERE username = ? COLLATE NOCASE)".to_string()
+        }
+    }
+}
+
+fn where_clause(filters: &[Filter], me: i64, params: &mut Vec<Value>) -> String {
+    let mut clauses = Vec::new();
+
+    for filter in filters {
+        let clause = match filter {
+            Filter::From(who) => format!("a.author_id = {}", user_id_clause(who, me, params)),
+            Filter::InChannel(name) => {
+                params.push(Value::Text(name.clone()));
+                "a.kind = 'channel' AND a.channel_name = ? COLLATE NOCASE".to_string()
+            }
+            Filter::InConversation(who) => {
+                let id = user_id_clause(who, me, params);
+                let id_again = user_id_clause(who, me, params);
+                format!(
+                    "a.kind = 'direct' AND (a.author_id = {} OR a.receiver_id = {})",
+                    id, id_again
+                )
+            }
+            Filter::HasAttachment => "EXISTS (SELECT 1 FROM attachments t
+                    WHERE t.message_kind = a.kind AND t.message_id = a.id)"
+                .to_string(),
+            Filter::HasImage => "EXISTS (SELECT 1 FROM attachments t
+                    WHERE t.message_kind = a.kind AND t.message_id = a.id
+                      AND t.mime_type LIKE 'image/%')"
+                .to_string(),
+            Filter::HasLink => {
+                "(a.content LIKE '%http://%' OR a.content LIKE '%https://%')".to_string()
+            }
+            // created_at is an ISO-8601 UTC string, so plain string comparison works
+            Filter::Before(date) => {
+                params.push(Value::Text(date.format("%Y-%m-%d").to_string()));
+                "a.created_at < ?".to_string()
+            }
+            Filter::After(date) => {
+                let next = date.succ_opt().unwrap_or(*date);
+                params.push(Value::Text(next.format("%Y-%m-%d").to_string()));
+                "a.created_at >= ?".to_string()
+            }
+            Filter::Mentions(who) => {
+                let name = match who {
+                    Who::Me => {
+                        params.push(Value::Integer(me));
+                        "(SELECT lower(username) FROM users WHERE id = ?)".to_string()
+                    }
+                    Who::Username(name) => {
+                        params.push(Value::Text(name.to_lowercase()));
+                        "?".to_string()
+                    }
+                };
+                params.push(params.last().cloned().unwrap());
+                // Usernames are restricted to [A-Za-z0-9_-], so no GLOB escaping is needed
+                format!(
+                    "(lower(a.content) GLOB '*@' || {} || '[^a-z0-9_-]*'
+                      OR lower(a.content) GLOB '*@' || {})",
+                    name, name
+                )
+            }
+        };
+        clauses.push(clause);
+    }
+
+    if clauses.is_empty() {
+        "1".to_string()
+    } else {
+        clauses
+            .iter()
+            .map(|c| format!("({})", c))
+            .collect::<Vec<_>>()
+            .join(" AND ")
+    }
+}
+
+fn split_snippet(snippet: &str) -> Vec<SnippetSegment> {
+    let mut segments = Vec::new();
+    let mut current = String::new();
+    let mut highlighted = false;
+
+    for c in snippet.chars() {
+        if c == MARK_START || c == MARK_END {
+            if !current.is_empty() {
+                segments.push(SnippetSegment {
+                    text: std::mem::take(&mut current),
+                    highlighted,
+                });
+            }
+            highlighted = c == MARK_START;
+        } else {
+            current.push(c);
+        }
+    }
+    if !current.is_empty() {
+        segments.push(SnippetSegment {
+            text: current,
+            highlighted,
+        });
+    }
+    segments
+}
+
+fn preview(content: &str) -> Vec<SnippetSegment> {
+    let mut text: String = content.chars().take(PREVIEW_CHARS).collect();
+    if text.len() < content.len() {
+        text.push('…');
+    }
+    vec![SnippetSegment {
+        text,
+        highlighted: false,
+    }]
+}
+
+pub fn search(
+    cache: &Cache,
+    query: &str,
+    limit: Option<u32>,
+    offset: Option<u32>,
+) -> Result<SearchResults, Error> {
+    let parsed = parse_query(query)?;
+    let me = cache.account_id().ok_or(cache::Error::NotOpen)?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+    let offset = offset.unwrap_or(0);
+
+    let mut params = Vec::new();
+    let fts = parsed.fts_expression();
+
+    // Filter-only searches have nothing to rank by, so they list newest first
+    let sql = match &fts {
+        Some(expression) => {
+            params.push(Value::Text(MARK_START.to_string()));
+            params.push(Value::Text(MARK_END.to_string()));
+            params.push(Value::Integer(SNIPPET_TOKENS));
+            params.push(Value::Text(expression.clone()));
+            let filters = where_clause(&parsed.filters, me, &mut params);
+            format!(
+                "WITH hits AS (
+                    SELECT rowid, bm25(message_search) AS rank,
+                           snippet(message_search, 0, ?, ?, '…', ?) AS snippet
+                    FROM message_search WHERE message_search MATCH ?
+                 )
+                 SELECT a.kind, a.id, a.channel_id, a.channel_name, a.author_id, a.receiver_id,
+                        COALESCE(u.username, ''), a.created_at, h.snippet, h.rank
+                 FROM hits h
+                 JOIN searchable_messages a ON a.search_rowid = h.rowid
+                 LEFT JOIN users u ON u.id = a.author_id
+                 WHERE {}
+                 ORDER BY h.rank, a.created_at DESC
+                 LIMIT ? OFFSET ?",
+                filters
+            )
+        }
+        None => {
+            let filters = where_clause(&parsed.filters, me, &mut params);
+            format!(
+                "SELECT a.kind, a.id, a.channel_id, a.channel_name, a.author_id, a.receiver_id,
+                        COALESCE(u.username, ''), a.created_at, a.content, 0.0
+                 FROM searchable_messages a
+                 LEFT JOIN users u ON u.id = a.author_id
+                 WHERE {}
+                 ORDER BY a.created_at DESC
+                 LIMIT ? OFFSET ?",
+                filters
+            )
+        }
+    };
+    // Fetch one extra row to know whether another page exists
+    params.push(Value::Integer(i64::from(limit) + 1));
+    params.push(Value::Integer(i64::from(offset)));
+
+    let highlighted = fts.is_some();
+    let mut hits = cache.with_conn(|conn| {
+        let mut stmt = conn.prepare(&sql)?;
+        let rows = stmt.query_map(rusqlite::params_from_iter(params), |row| {
+            let kind: String = row.get(0)?;
+            let kind = if kind == "direct" {
+                HitKind::Direct
+            } else {
+                HitKind::Channel
+            };
+            let author_id: i64 = row.get(4)?;
+            let receiver_id: Option<i64> = row.get(5)?;
+            let text: String = row.get(8)?;
+            Ok(SearchHit {
+                kind,
+                message_id: row.get(1)?,
+                channel_id: row.get(2)?,
+                channel_name: row.get(3)?,
+                conversation_user_id: receiver_id.map(|receiver| {
+                    if author_id == me {
+                        receiver
+                    } else {
+                        author_id
+                    }
+                }),
+                author: UserRef {
+                    id: author_id,
+                    username: row.get(6)?,
+                },
+                created_at: row.get(7)?,
+                snippet: if highlighted {
+                    split_snippet(&text)
+                } else {
+                    preview(&text)
+                },
+                rank: row.get(9)?,
+            })
+        })?;
+        Ok(rows.collect::<Result<Vec<_>, _>>()?)
+    })?;
+
+    let has_more = hits.len() > limit as usize;
+    hits.truncate(limit as usize);
+    Ok(SearchResults { hits, has_more })
+}
+
+#[tauri::command]
+pub fn search_messages(
+    cache: State<'_, Cache>,
+    query: String,
+    limit: Option<u32>,
+    offset: Option<u32>,
+) -> Result<SearchResults, String> {
+    search(&cache, &query, limit, offset).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::{Attachment, ChannelMessage, ChannelRef, DirectMessage};
+
+    fn terms(words: &[&str]) -> Vec<String> {
+        words.iter().map(|word| word.to_string()).collect()
+    }
+
+    #[test]
+    fn terms_phrases_and_filters() {
+        assert_eq!(
+            parse_query("deploy \"release notes\" from:alice in:#general has:image").unwrap(),
+            ParsedQuery {
+                terms: terms(&["deploy"]),
+                phrases: terms(&["release notes"]),
+                filters: vec![
+                    Filter::From(Who::Username("alice".into())),
+                    Filter::InChannel("general".into()),
+                    Filter::HasImage,
+                ],
+            }
+        );
+        assert_eq!(
+            parse_query("in:@Me mentions:me after:2025-01-31")
+                .unwrap()
+                .filters,
+            vec![
+                Filter::InConversation(Who::Me),
+                Filter::Mentions(Who::Me),
+                Filter::After(NaiveDate::from_ymd_opt(2025, 1, 31).unwrap()),
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_keys_are_terms() {
+        assert_eq!(
+            parse_query("re: https://example.com/a").unwrap().terms,
+            terms(&["re:", "https://example.com/a"])
+        );
+    }
+
+    #[test]
+    fn bad_operators_are_errors() {
+        assert!(matches!(
+            parse_query("has:video"),
+            Err(Error::InvalidQuery(_))
+        ));
+        assert!(matches!(
+            parse_query("before:yesterday"),
+            Err(Error::InvalidQuery(_))
+        ));
+        assert!(matches!(
+            parse_query("from:bob!"),
+            Err(Error::InvalidQuery(_))
+        ));
+    }
+
+    #[test]
+    fn punctuation_is_dropped() {
+        assert_eq!(parse_query("? ... \"!!\"").unwrap(), ParsedQuery::default());
+        assert_eq!(
+            parse_query("why ?").unwrap().fts_expression().as_deref(),
+            Some("\"why\"*")
+        );
+    }
+
+    #[test]
+    fn expression_quotes_and_prefixes_the_last_term() {
+        let parsed = parse_query("\"exact words\" say\"AND hel").unwrap();
+        assert_eq!(
+            parsed.fts_expression().as_deref(),
+            Some("\"exact words\" \"say\"\"AND\" \"hel\"*")
+        );
+    }
+
+    #[test]
+    fn filters_alone_have_no_expression() {
+        assert_eq!(parse_query("has:link").unwrap().fts_expression(), None);
+        assert_eq!(parse_query("").unwrap().fts_expression(), None);
+    }
+
+    const ME: i64 = 1;
+    const BOB: i64 = 2;
+    const CAROL: i64 = 3;
+
+    fn user(id: i64) -> UserRef {
+        let username = match id {
+            ME => "ada",
+            BOB => "bob",
+            _ => "carol",
+        };
+        UserRef {
+            id,
+            username: username.to_string(),
+        }
+    }
+
+    fn post(id: i64, author: i64, content: &str) -> ChannelMessage {
+        ChannelMessage {
+            id,
+            content: content.to_string(),
+            user_id: author,
+            channel_id: 1,
+            created_at: "2025-01-01T00:00:00.000Z".to_string(),
+            is_edited: false,
+            edited_at: None,
+            reply_to_id: None,
+            reply_to: None,
+            user: user(author),
+            channel: ChannelRef {
+                id: 1,
+                name: "general".to_string(),
+            },
+            attachments: Vec::new(),
+        }
+    }
+
+    fn dm(id: i64, sender: i64, receiver: i64, content: &str) -> DirectMessage {
+        DirectMessage {
+            id,
+            content: content.to_string(),
+            sender_id: sender,
+            receiver_id: receiver,
+            created_at: "2025-01-01T00:00:00.000Z".to_string(),
+            is_edited: false,
+            edited_at: None,
+            is_read: false,
+            sender: user(sender),
+            receiver: user(receiver),
+            attachments: Vec::new(),
+        }
+    }
+
+    fn attachment(id: i64, filename: &str, mime_type: &str) -> Attachment {
+        Attachment {
+            id,
+            url: format!("/uploads/{}", filename),
+            filename: filename.to_string(),
+            mime_type: mime_type.to_string(),
+            size: 10,
+            created_at: "2025-01-01T00:00:00.000Z".to_string(),
+        }
+    }
+
+    /// A cache signed in as ada, who talks to bob and carol in DMs and in
+    /// #general.
+    fn seeded() -> Cache {
+        let cache = Cache::in_memory(ME);
+
+        let mut screenshot = post(30, BOB, "screenshot");
+        screenshot.attachments = vec![attachment(1, "shot.png", "image/png")];
+        let mut early = post(40, ME, "standup notes");
+        early.created_at = "2025-01-31T23:59:59.999Z".to_string();
+        let mut late = post(41, ME, "standup notes");
+        late.created_at = "2025-02-01T00:00:00.000Z".to_string();
+        cache
+            .store_channel_messages(&[
+                post(10, CAROL, "ping @bob please"),
+                post(11, CAROL, "thanks @Bob"),
+                post(12, CAROL, "@bobby is someone else"),
+                post(13, CAROL, "see @bob-2 about it"),
+                post(14, CAROL, "@ada can you look"),
+                post(23, BOB, "hello from general"),
+                screenshot,
+                early,
+                late,
+            ])
+            .unwrap();
+
+        let mut contract = dm(31, BOB, ME, "the contract");
+        contract.attachments = vec![attachment(2, "contract.pdf", "application/pdf")];
+        cache
+            .store_direct_messages(&[
+                dm(20, ME, BOB, "lunch today?"),
+                dm(21, BOB, ME, "sure"),
+                dm(22, ME, CAROL, "hello carol"),
+                contract,
+            ])
+            .unwrap();
+        cache
+    }
+
+    /// Hits as `#id` for channel messages and `@id` for DMs, sorted.
+    fn found(cache: &Cache, query: &str) -> Vec<String> {
+        let mut hits: Vec<String> = search(cache, query, Some(MAX_LIMIT), None)
+            .unwrap()
+            .hits
+            .iter()
+            .map(|hit| match hit.kind {
+                HitKind::Channel => format!("#{}", hit.message_id),
+                HitKind::Direct => format!("@{}", hit.message_id),
+            })
+            .collect();
+        hits.sort();
+        hits
+    }
+
+    #[test]
+    fn mentions_match_whole_usernames_only() {
+        let cache = seeded();
+        assert_eq!(found(&cache, "mentions:bob"), ["#10", "#11"]);
+        assert_eq!(found(&cache, "mentions:me"), ["#14"]);
+        assert_eq!(found(&cache, "mentions:bobby"), ["#12"]);
+    }
+
+    #[test]
+    fn conversations_and_authors_resolve_through_users() {
+        let cache = seeded();
+        assert_eq!(found(&cache, "in:@bob"), ["@20", "@21", "@31"]);
+        assert_eq!(found(&cache, "in:@carol"), ["@22"]);
+        assert_eq!(found(&cache, "from:bob"), ["#23", "#30", "@21", "@31"]);
+        assert_eq!(found(&cache, "from:me in:@bob"), ["@20"]);
+        assert_eq!(found(&cache, "hello in:#General"), ["#23"]);
+        // Nobody the cache has seen
+        assert!(found(&cache, "from:dave").is_empty());
+    }
+
+    #[test]
+    fn attachment_filters_look_at_mime_types() {
+        let cache = seeded();
+        assert_eq!(found(&cache, "has:image"), ["#30"]);
+        assert_eq!(found(&cache, "has:attachment"), ["#30", "@31"]);
+    }
+
+    #[test]
+    fn date_filters_cover_whole_days() {
+        let cache = seeded();
+        assert_eq!(found(&cache, "standup before:2025-02-01"), ["#40"]);
+        assert_eq!(found(&cache, "standup after:2025-01-31"), ["#41"]);
+        assert!(found(&cache, "standup before:2025-01-31").is_empty());
+        assert!(found(&cache, "standup after:2025-02-01").is_empty());
+    }
+
+    #[test]
+    fn snippets_mark_the_matched_words() {
+        let cache = seeded();
+        let results = search(&cache, "contr", None, None).unwrap();
+        let segments: Vec<_> = results.hits[0]
+            .snippet
+            .iter()
+            .map(|segment| (segment.text.as_str(), segment.highlighted))
+            .collect();
+        assert_eq!(segments, [("the ", false), ("contract", true)]);
+
+        // Filter-only searches show the message as it is
+        let results = search(&cache, "in:@carol", None, None).unwrap();
+        assert_eq!(results.hits[0].snippet.len(), 1);
+        assert_eq!(results.hits[0].snippet[0].text, "hello carol");
+        assert!(!results.hits[0].snippet[0].highlighted);
+    }
+
+    #[test]
+    fn closer_matches_rank_first() {
+        let cache = seeded();
+        cache
+            .store_channel_messages(&[
+                post(
+                    51,
+                    BOB,
+                    "we should deploy the new build sometime after lunch",
+                ),
+                post(50, BOB, "deploy deploy deploy"),
+            ])
+            .unwrap();
+        let hits = search(&cache, "deploy", None, None).unwrap().hits;
+        assert_eq!(
+            hits.iter().map(|hit| hit.message_id).collect::<Vec<_>>(),
+            [50, 51]
+        );
+        assert!(hits[0].rank < hits[1].rank);
+    }
+
+    #[test]
+    fn pages_report_whether_more_remain() {
+        let cache = seeded();
+        let first = search(&cache, "hello", Some(1), None).unwrap();
+        assert_eq!(first.hits.len(), 1);
+        assert!(first.has_more);
+        let second = search(&cache, "hello", Some(1), Some(1)).unwrap();
+        assert_eq!(second.hits.len(), 1);
+        assert!(!second.has_more);
+        assert_ne!(first.hits[0].message_id, second.hits[0].message_id);
+    }
+
+    #[test]
+    fn edits_and_deletions_reach_the_index() {
+        let cache = seeded();
+        cache
+            .store_direct_messages(&[dm(20, ME, BOB, "dinner tonight?")])
+            .unwrap();
+        assert!(found(&cache, "lunch").is_empty());
+        assert_eq!(found(&cache, "dinner"), ["@20"]);
+
+        cache.delete_direct_message(20).unwrap();
+        assert!(found(&cache, "dinner").is_empty());
+        cache.delete_channel_message(23).unwrap();
+        assert!(found(&cache, "hello").iter().all(|hit| hit != "#23"));
+    }
+
+    #[test]
+    fn direct_hits_name_the_other_participant() {
+        let cache = seeded();
+        let hits = search(&cache, "in:@bob", None, None).unwrap().hits;
+        assert_eq!(hits.len(), 3);
+        for hit in hits {
+            assert_eq!(hit.conversation_user_id, Some(BOB));
+        }
+    }
+}
@@ -0,0 +1,1058 @@
+//! Socket.IO connection to the `/chat` gateway, owned by the backend.
+//!
+//! Living here instead of in the webview means a reload or a closed window no
+//! longer drops the connection. Every server event is re-emitted as
+//! `socket://<event>` to the windows it concerns (see [`crate::popout`]),
+//! alongside the lifecycle events `socket://connect`, `socket://disconnect`,
+//! `socket://connect_error` and `socket://reconnect_attempt` that
+//! socket.io-client used to raise.
+
+mod protocol;
+
+use std::collections::{BTreeSet, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures_util::{SinkExt, StreamExt};
+use serde::Serialize;
+use serde_json::{json, Value};
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Manager, State};
+use tokio::sync::mpsc;
+use tokio::time::{sleep, Instant};
+use tokio_tungstenite::tungstenite::Message;
+use url::Url;
+
+use crate::cache::{self, Cache, DirectMessage, SocketMessage};
+use crate::notifications;
+use crate::popout;
+use crate::vault::Vault;
+use protocol::{EnginePacket, SocketPacket};
+
+/// The server the webview was built against (see build.rs). The gateway is
+/// its `/chat` namespace, and the token is never sent anywhere else.
+const API_URL: &str = env!("COMMHUB_API_URL");
+
+const BACKOFF_BASE: Duration = Duration::from_secs(1);
+const BACKOFF_MAX: Duration = Duration::from_secs(30);
+// Emits queued while offline; beyond this the oldest are dropped
+const MAX_PENDING: usize = 256;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("invalid socket url: {0}")]
+    Url(#[from] url::ParseError),
+    #[error("unsupported socket url scheme: {0}")]
+    Scheme(String),
+    #[error("not signed in")]
+    NoToken,
+    #[error("socket is not connected")]
+    NotConnected,
+    #[error(transparent)]
+    Vault(#[from] crate::vault::Error),
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ConnectionState {
+    #[default]
+    Disconnected,
+    Connecting,
+    Connected,
+    Reconnecting,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SocketStatus {
+    pub state: ConnectionState,
+    pub url: Option<String>,
+    pub joined_servers: Vec<i64>,
+    pub joined_channels: Vec<i64>,
+}
+
+enum Outgoing {
+    Emit {
+        event: String,
+        args: Vec<Value>,
+    },
+    /// Re-sends `ready` so a freshly loaded window gets `initial-sync`
+    Ready,
+    Close,
+}
+
+/// Where to connect, derived from a `https://host/namespace` URL.
+struct Endpoint {
+    websocket_url: Url,
+    namespace: String,
+}
+
+impl Endpoint {
+    fn parse(url: &str, client_version: &str) -> Result<Self, Error> {
+        let mut websocket_url = Url::parse(url)?;
+        let scheme = match websocket_url.scheme() {
+            "http" | "ws" => "ws",
+            "https" | "wss" => "wss",
+            other => return Err(Error::Scheme(other.to_string())),
+        };
+        let namespace = match websocket_url.path().trim_end_matches('/') {
+            "" => "/".to_string(),
+            path => path.to_string(),
+        };
+
+        websocket_url
+            .set_scheme(scheme)
+            .map_err(|_| Error::Scheme(scheme.to_string()))?;
+        websocket_url.set_path("/socket.io/");
+        websocket_url
+            .query_pairs_mut()
+            .clear()
+            .append_pair("EIO", "4")
+            .append_pair("transport", "websocket")
+            .append_pair("clientVersion", client_version);
+
+        Ok(Self {
+            websocket_url,
+            namespace,
+        })
+    }
+}
+
+#[derive(Default)]
+struct Shared {
+    state: ConnectionState,
+    url: Option<String>,
+    token: Option<String>,
+    servers: BTreeSet<i64>,
+    channels: BTreeSet<i64>,
+    outgoing: Option<mpsc::UnboundedSender<Outgoing>>,
+    task: Option<JoinHandle<()>>,
+    // Bumped per connect() so a superseded task can't clobber newer state
+    generation: u64,
+}
+
+impl Shared {
+    fn status(&self) -> SocketStatus {
+        SocketStatus {
+            state: self.state,
+            url: self.url.clone(),
+            joined_servers: self.servers.iter().copied().collect(),
+            joined_channels: self.channels.iter().copied().collect(),
+        }
+    }
+
+    /// Keeps the room sets in step with what the webview asked for so they
+    /// can be replayed after a reconnect.
+    fn track_rooms(&mut self, event: &str, args: &[Value]) {
+        let id = |key: &str| args.first().and_then(|data| data.get(key)?.as_i64());
+        match event {
+            "join-server" => id("serverId").map(|id| self.servers.insert(id)),
+            "leave-server" => id("serverId").map(|id| self.servers.remove(&id)),
+            "join-channel" => id("channelId").map(|id| self.channels.insert(id)),
+            "leave-channel" => id("channelId").map(|id| self.channels.remove(&id)),
+            _ => None,
+        };
+    }
+}
+
+#[derive(Default)]
+pub struct SocketManager {
+    shared: Arc<Mutex<Shared>>,
+}
+
+impl SocketManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts the connection task, or attaches to the running one. `token`
+    /// falls back to the vault so a background reconnect needs no webview.
+    pub fn connect(&self, app: &AppHandle, token: Option<String>) -> Result<SocketStatus, Error> {
+        let mut shared = self.shared.lock().unwrap();
+
+        if let Some(outgoing) = &shared.outgoing {
+            if token
+                .as_ref()
+                .map_or(true, |t| Some(t) == shared.token.as_ref())
+            {
+                if shared.state == ConnectionState::Connected {
+                    let _ = outgoing.send(Outgoing::Ready);
+                }
+                return Ok(shared.status());
+            }
+            // Different account: start over
+            let _ = outgoing.send(Outgoing::Close);
+            shared.outgoing = None;
+            shared.task = None;
+            shared.servers.clear();
+            shared.channels.clear();
+        }
+
+        let token = match token {
+            Some(token) => token,
+            None => app
+                .state::<Vault>()
+                .load()?
+                .map(|record| record.token)
+                .ok_or(Error::NoToken)?,
+        };
+        let url = gateway_url();
+        let endpoint = Endpoint::parse(&url, &app.package_info().version.to_string())?;
+
+        let sink = Arc::new(Windows::new(app.clone()));
+        Ok(self.start(&mut shared, sink, url, endpoint, token))
+    }
+
+    fn start(
+        &self,
+        shared: &mut Shared,
+        sink: Arc<dyn Sink>,
+        url: String,
+        endpoint: Endpoint,
+        token: String,
+    ) -> SocketStatus {
+        let (tx, rx) = mpsc::unbounded_channel();
+        shared.generation += 1;
+        shared.state = ConnectionState::Connecting;
+        shared.url = Some(url);
+        shared.token = Some(token.clone());
+        shared.outgoing = Some(tx);
+
+        let task = Task {
+            sink,
+            shared: self.shared.clone(),
+            generation: shared.generation,
+            endpoint,
+            token,
+        };
+        shared.task = Some(tauri::async_runtime::spawn(task.run(rx)));
+
+        shared.status()
+    }
+
+    /// Closes the connection and forgets the session; nothing reconnects
+    /// until the next `connect`.
+    pub fn disconnect(&self) {
+        let mut shared = self.shared.lock().unwrap();
+        if let Some(outgoing) = shared.outgoing.take() {
+            let _ = outgoing.send(Outgoing::Close);
+        }
+        shared.task = None;
+        shared.generation += 1;
+        shared.state = ConnectionState::Disconnected;
+        shared.token = None;
+        shared.servers.clear();
+        shared.channels.clear();
+    }
+
+    /// Disconnects and waits up to `timeout` for the task to say goodbye, so
+    /// quitting isn't mistaken for a dropped connection by the server.
+    pub fn shutdown(&self, timeout: Duration) {
+        let task = self.shared.lock().unwrap().task.take();
+        self.disconnect();
+        if let Some(task) = task {
+            let _ = tauri::async_runtime::block_on(tokio::time::timeout(timeout, task));
+        }
+    }
+
+    pub fn status(&self) -> SocketStatus {
+        self.shared.lock().unwrap().status()
+    }
+
+    /// Queues an event for the server. Emits made while reconnecting are
+    /// sent once the connection is back, as socket.io-client did.
+    pub fn emit(&self, event: &str, args: Vec<Value>) -> Result<(), Error> {
+        let mut shared = self.shared.lock().unwrap();
+        shared.track_rooms(event, &args);
+        let outgoing = shared.outgoing.as_ref().ok_or(Error::NotConnected)?;
+        outgoing
+            .send(Outgoing::Emit {
+                event: event.to_string(),
+                args,
+            })
+            .map_err(|_| Error::NotConnected)
+    }
+
+    pub fn send_message(
+        &self,
+        channel_id: i64,
+        content: String,
+        reply_to_id: Option<i64>,
+        attachments: Option<Vec<Value>>,
+    ) -> Result<(), Error> {
+        self.emit(
+            "send-message",
+            vec![json!({
+                "channelId": channel_id,
+                "content": content,
+                "replyToId": reply_to_id,
+                "attachments": attachments,
+            })],
+        )
+    }
+
+    pub fn status_change(&self, user_id: i64, status: &str) -> Result<(), Error> {
+        self.emit(
+            "status-change",
+            vec![json!({ "userId": user_id, "status": status })],
+        )
+    }
+}
+
+/// How a single websocket session ended.
+enum End {
+    /// We asked to close
+    Closed,
+    /// The server disconnected us (auth failure, version mismatch, replaced
+    /// by another session); reconnecting would just repeat it
+    Kicked,
+    Refused(Value),
+    Lost(&'static str),
+}
+
+/// Where a connection's events go. The app caches them and hands them to
+/// its windows; tests record them.
+trait Sink: Send + Sync + 'static {
+    /// An event from the server, with its arguments as sent.
+    fn event(&self, name: &str, args: Vec<Value>);
+    /// A lifecycle event like `connect` or `reconnect_attempt`.
+    fn lifecycle(&self, name: &str, payload: Value);
+}
+
+struct Windows {
+    app: AppHandle,
+    /// Events for [`Windows::record`], which runs on its own thread so the
+    /// socket task never waits on SQLite or the notification center.
+    records: std::sync::mpsc::Sender<(String, Value)>,
+}
+
+impl Windows {
+    fn new(app: AppHandle) -> Self {
+        let (records, pending) = std::sync::mpsc::channel::<(String, Value)>();
+        let worker = app.clone();
+        // In arrival order, so an edit or deletion never overtakes the message
+        // it's about; ends when the connection's sink is dropped
+        std::thread::spawn(move || {
+            for (name, payload) in pending {
+                Windows::record(&worker, &name, &payload);
+            }
+        });
+        Self { app, records }
+    }
+
+    /// Writes live messages through to the cache and raises notifications.
+    fn record(app: &AppHandle, name: &str, payload: &Value) {
+        match store(&app.state::<Cache>(), name, payload) {
+            Ok(()) | Err(cache::Error::NotOpen) => {}
+            Err(e) => log!("[Socket] Failed to cache {} event: {}", name, e),
+        }
+        notifications::handle_event(app, name, payload);
+    }
+}
+
+/// Applies an event that adds, changes or removes a message to the cache,
+/// and so to the search index.
+fn store(cache: &Cache, name: &str, payload: &Value) -> Result<(), cache::Error> {
+    let id = |key: &str| payload.get(key).and_then(Value::as_i64);
+    match name {
+        "message" | "message-edited" => {
+            cache.store_socket_message(serde_json::from_value::<SocketMessage>(payload.clone())?)
+        }
+        "direct-message" | "direct-message-edited" => cache
+            .store_direct_messages(&[serde_json::from_value::<DirectMessage>(payload.clone())?]),
+        "message-deleted" => id("messageId").map_or(Ok(()), |id| cache.delete_channel_message(id)),
+        "direct-message-deleted" => {
+            id("messageId").map_or(Ok(()), |id| cache.delete_direct_message(id))
+        }
+        _ => Ok(()),
+    }
+}
+
+impl Sink for Windows {
+    /// Queues the event for the cache and notifications, then hands it to
+    /// the windows.
+    fn event(&self, name: &str, args: Vec<Value>) {
+        if let Some(payload) = args.first() {
+            let _ = self.records.send((name.to_string(), payload.clone()));
+        }
+
+        // socket.io-client hands listeners each argument; every gateway
+        // event carries at most one
+        let payload = match args.len() {
+            0 => Value::Null,
+            1 => args.into_iter().next().unwrap(),
+            _ => Value::Array(args),
+        };
+        self.lifecycle(name, payload);
+    }
+
+    fn lifecycle(&self, name: &str, payload: Value) {
+        // Tauri panics on event names outside [A-Za-z0-9-/:_]
+        let valid = name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '/' | ':' | '_'));
+        if !valid {
+            log!("[Socket] Dropping event with unsupported name {:?}", name);
+            return;
+        }
+        popout::emit_socket_event(&self.app, name, payload);
+    }
+}
+
+struct Task {
+    sink: Arc<dyn Sink>,
+    shared: Arc<Mutex<Shared>>,
+    generation: u64,
+    endpoint: Endpoint,
+    token: String,
+}
+
+impl Task {
+    async fn run(self, mut rx: mpsc::UnboundedReceiver<Outgoing>) {
+        let mut pending = VecDeque::new();
+        let mut attempt: u32 = 0;
+
+        loop {
+            let mut connected = false;
+            let end = self.session(&mut rx, &mut pending, &mut connected).await;
+            if connected {
+                attempt = 0;
+            }
+
+            match end {
+                End::Closed => break,
+                End::Kicked => {
+                    self.emit("disconnect", json!("io server disconnect"));
+                    break;
+                }
+                End::Refused(data) => {
+                    self.emit("connect_error", data);
+                    break;
+                }
+                End::Lost(reason) => {
+                    if connected {
+                        self.emit("disconnect", json!(reason));
+                    } else {
+                        self.emit("connect_error", json!({ "message": reason }));
+                    }
+                }
+            }
+
+            attempt += 1;
+            if !self.set_state(ConnectionState::Reconnecting) {
+                return;
+            }
+            self.emit("reconnect_attempt", json!(attempt));
+
+            let delay = backoff(attempt);
+            let wait = sleep(delay);
+            tokio::pin!(wait);
+            loop {
+                tokio::select! {
+                    _ = &mut wait => break,
+                    message = rx.recv() => match message {
+                        Some(Outgoing::Emit { event, args }) => queue(&mut pending, event, args),
+                        Some(Outgoing::Ready) => {}
+                        Some(Outgoing::Close) | None => return,
+                    },
+                }
+            }
+        }
+
+        self.set_state(ConnectionState::Disconnected);
+        let mut shared = self.shared.lock().unwrap();
+        if shared.generation == self.generation {
+            shared.outgoing = None;
+        }
+    }
+
+    async fn session(
+        &self,
+        rx: &mut mpsc::UnboundedReceiver<Outgoing>,
+        pending: &mut VecDeque<(String, Vec<Value>)>,
+        connected: &mut bool,
+    ) -> End {
+        let namespace = self.endpoint.namespace.as_str();
+        let Ok((mut ws, _)) =
+            tokio_tungstenite::connect_async(self.endpoint.websocket_url.as_str()).await
+        else {
+            return End::Lost("transport error");
+        };
+
+        // Until the Engine.IO handshake arrives, fall back to the server's
+        // default 25s interval + 20s timeout
+        let mut heartbeat = Duration::from_secs(45);
+        let deadline = sleep(heartbeat);
+        tokio::pin!(deadline);
+
+        loop {
+            tokio::select! {
+                frame = ws.next() => {
+                    let text = match frame {
+                        Some(Ok(Message::Text(text))) => text,
+                        Some(Ok(Message::Close(_))) | None => return End::Lost("transport close"),
+                        Some(Ok(_)) => continue,
+                        Some(Err(_)) => return End::Lost("transport error"),
+                    };
+                    deadline.as_mut().reset(Instant::now() + heartbeat);
+
+                    let outgoing = match protocol::decode_engine(&text) {
+                        Some(EnginePacket::Open(handshake)) => {
+                            heartbeat = Duration::from_millis(
+                                handshake.ping_interval + handshake.ping_timeout,
+                            );
+                            deadline.as_mut().reset(Instant::now() + heartbeat);
+                            vec![protocol::connect(namespace, &json!({ "token": self.token }))]
+                        }
+                        Some(EnginePacket::Ping) => vec![protocol::PONG.to_string()],
+                        Some(EnginePacket::Close) => return End::Lost("transport close"),
+                        Some(EnginePacket::Message(payload)) => {
+                            match protocol::decode_socket(namespace, payload) {
+                                Some(SocketPacket::Connect) => {
+                                    *connected = true;
+                                    self.on_connect(pending)
+                                }
+                                Some(SocketPacket::Disconnect) => return End::Kicked,
+                                Some(SocketPacket::ConnectError(data)) => return End::Refused(data),
+                                Some(SocketPacket::Event { name, args }) => {
+                                    self.sink.event(&name, args);
+                                    Vec::new()
+                                }
+                                None => Vec::new(),
+                            }
+                        }
+                        _ => Vec::new(),
+                    };
+                    for text in outgoing {
+                        if ws.send(Message::Text(text)).await.is_err() {
+                            return End::Lost("transport error");
+                        }
+                    }
+                }
+                message = rx.recv() => {
+                    let text = match message {
+                        Some(Outgoing::Emit { event, args }) if *connected => {
+                            protocol::event(namespace, &event, &args)
+                        }
+                        Some(Outgoing::Emit { event, args }) => {
+                            queue(pending, event, args);
+                            continue;
+                        }
+                        Some(Outgoing::Ready) if *connected => protocol::event(namespace, "ready", &[]),
+                        Some(Outgoing::Ready) => continue,
+                        Some(Outgoing::Close) | None => {
+                            let _ = ws.send(Message::Text(protocol::disconnect(namespace))).await;
+                            let _ = ws.close(None).await;
+                            return End::Closed;
+                        }
+                    };
+                    if ws.send(Message::Text(text)).await.is_err() {
+                        return End::Lost("transport error");
+                    }
+                }
+                _ = &mut deadline => return End::Lost("ping timeout"),
+            }
+        }
+    }
+
+    /// Rejoins rooms and announces `ready`, then flushes emits queued while
+    /// offline. Returns the packets to send.
+    fn on_connect(&self, pending: &mut VecDeque<(String, Vec<Value>)>) -> Vec<String> {
+        let namespace = self.endpoint.namespace.as_str();
+        let mut packets = Vec::new();
+        {
+            let shared = self.shared.lock().unwrap();
+            for server_id in &shared.servers {
+                packets.push(protocol::event(
+                    namespace,
+                    "join-server",
+                    &[json!({ "serverId": server_id })],
+                ));
+            }
+            for channel_id in &shared.channels {
+                packets.push(protocol::event(
+                    namespace,
+                    "join-channel",
+                    &[json!({ "channelId": channel_id })],
+                ));
+            }
+        }
+        // `ready` joins every room the account belongs to, including the
+        // per-user room DMs and mentions are delivered through
+        packets.push(protocol::event(namespace, "ready", &[]));
+        packets.extend(
+            pending
+                .drain(..)
+                .map(|(event, args)| protocol::event(namespace, &event, &args)),
+        );
+
+        if self.set_state(ConnectionState::Connected) {
+            self.emit("connect", Value::Null);
+        }
+        packets
+    }
+
+    fn emit(&self, name: &str, payload: Value) {
+        self.sink.lifecycle(name, payload);
+    }
+
+    /// Updates the shared state unless a newer connect() has taken over.
+    fn set_state(&self, state: ConnectionState) -> bool {
+        let mut shared = self.shared.lock().unwrap();
+        if shared.generation != self.generation {
+            return false;
+        }
+        shared.state = state;
+        true
+    }
+}
+
+fn queue(pending: &mut VecDeque<(String, Vec<Value>)>, event: String, args: Vec<Value>) {
+    if pending.len() == MAX_PENDING {
+        pending.pop_front();
+    }
+    pending.push_back((event, args));
+}
+
+fn gateway_url() -> String {
+    format!("{}/chat", API_URL.trim_end_matches('/'))
+}
+
+fn backoff(attempt: u32) -> Duration {
+    BACKOFF_BASE
+        .saturating_mul(1 << attempt.saturating_sub(1).min(5))
+        .min(BACKOFF_MAX)
+}
+
+#[tauri::command]
+pub fn socket_connect(
+    app: AppHandle,
+    socket: State<'_, SocketManager>,
+    token: Option<String>,
+) -> Result<SocketStatus, String> {
+    socket.connect(&app, token).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn socket_disconnect(socket: State<'_, SocketManager>) {
+    socket.disconnect();
+}
+
+#[tauri::command]
+pub fn socket_status(socket: State<'_, SocketManager>) -> SocketStatus {
+    socket.status()
+}
+
+#[tauri::command]
+pub fn socket_emit(
+    socket: State<'_, SocketManager>,
+    event: String,
+    args: Vec<Value>,
+) -> Result<(), String> {
+    socket.emit(&event, args).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn socket_send_message(
+    socket: State<'_, SocketManager>,
+    channel_id: i64,
+    content: String,
+    reply_to_id: Option<i64>,
+    attachments: Option<Vec<Value>>,
+) -> Result<(), String> {
+    socket
+        .send_message(channel_id, content, reply_to_id, attachments)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn socket_status_change(
+    socket: State<'_, SocketManager>,
+    user_id: i64,
+    status: String,
+) -> Result<(), String> {
+    socket
+        .status_change(user_id, &status)
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::time::Instant as StdInstant;
+
+    use tokio::net::TcpStream;
+    use tokio::time::timeout;
+    use tokio_tungstenite::WebSocketStream;
+
+    use crate::test_support::StandIn;
+
+    const TOKEN: &str = "secret-token";
+    const WAIT: Duration = Duration::from_secs(5);
+
+    /// Records what the connection would hand the windows.
+    struct Recorder(mpsc::UnboundedSender<(String, Value)>);
+
+    impl Sink for Recorder {
+        fn event(&self, name: &str, args: Vec<Value>) {
+            let _ = self.0.send((name.to_string(), Value::Array(args)));
+        }
+
+        fn lifecycle(&self, name: &str, payload: Value) {
+            let _ = self.0.send((name.to_string(), payload));
+        }
+    }
+
+    /// A local Engine.IO v4 server speaking just enough Socket.IO for the
+    /// `/chat` namespace.
+    struct Gateway {
+        url: String,
+        connections: tokio::sync::Mutex<mpsc::UnboundedReceiver<WebSocketStream<TcpStream>>>,
+    }
+
+    impl Gateway {
+        async fn start() -> Self {
+            let (tx, rx) = mpsc::unbounded_channel();
+            let server = StandIn::start(move |request, connection| {
+                let connections = tx.clone();
+                async move {
+                    let _ = connections.send(connection.upgrade(&request).await);
+                }
+            })
+            .await;
+            Self {
+                url: server.url("/chat"),
+                connections: tokio::sync::Mutex::new(rx),
+            }
+        }
+
+        /// The next connection within `wait`, before any handshake.
+        async fn connection(&self, wait: Duration) -> Option<Peer> {
+            let mut connections = self.connections.lock().await;
+            let ws = timeout(wait, connections.recv()).await.ok()??;
+            Some(Peer { ws })
+        }
+
+        /// Accepts the next connection and completes both handshakes.
+        async fn accept(&self) -> Peer {
+            let mut peer = self.connection(WAIT).await.expect("client didn't connect");
+            peer.send(r#"0{"sid":"engine","pingInterval":25000,"pingTimeout":20000}"#)
+                .await;
+            assert_eq!(
+                peer.recv().await,
+                format!(r#"40/chat,{{"token":"{}"}}"#, TOKEN)
+            );
+            peer.send(r#"40/chat,{"sid":"socket"}"#).await;
+            peer
+        }
+    }
+
+    struct Peer {
+        ws: WebSocketStream<TcpStream>,
+    }
+
+    impl Peer {
+        async fn send(&mut self, text: &str) {
+            self.ws.send(Message::Text(text.to_string())).await.unwrap();
+        }
+
+        async fn recv(&mut self) -> String {
+            loop {
+                let frame = timeout(WAIT, self.ws.next())
+                    .await
+                    .expect("nothing from the client")
+                    .expect("client hung up")
+                    .unwrap();
+                if let Message::Text(text) = frame {
+                    return text;
+                }
+            }
+        }
+
+        /// The next event the client emitted, as `[name, ...args]`.
+        async fn recv_event(&mut self) -> Value {
+            let text = self.recv().await;
+            let json = text
+                .strip_prefix("42/chat,")
+                .unwrap_or_else(|| panic!("not an event: {}", text));
+            serde_json::from_str(json).unwrap()
+        }
+    }
+
+    struct Client {
+        manager: SocketManager,
+        events: mpsc::UnboundedReceiver<(String, Value)>,
+    }
+
+    impl Client {
+        fn connect(server: &Gateway) -> Self {
+            let manager = SocketManager::new();
+            let (tx, events) = mpsc::unbounded_channel();
+            let endpoint = Endpoint::parse(&server.url, "test").unwrap();
+            let mut shared = manager.shared.lock().unwrap();
+            manager.start(
+                &mut shared,
+                Arc::new(Recorder(tx)),
+                server.url.clone(),
+                endpoint,
+                TOKEN.to_string(),
+            );
+            drop(shared);
+            Self { manager, events }
+        }
+
+        async fn next(&mut self) -> (String, Value) {
+            timeout(WAIT, self.events.recv())
+                .await
+                .expect("no event from the connection")
+                .unwrap()
+        }
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_cap() {
+        let secs: Vec<u64> = (1..=8).map(|attempt| backoff(attempt).as_secs()).collect();
+        assert_eq!(secs, [1, 2, 4, 8, 16, 30, 30, 30]);
+    }
+
+    #[test]
+    fn endpoint_from_the_gateway_url() {
+        let endpoint = Endpoint::parse("https://chat.example.com/chat", "1.2.3").unwrap();
+        assert_eq!(endpoint.namespace, "/chat");
+        assert_eq!(
+            endpoint.websocket_url.as_str(),
+            "wss://chat.example.com/socket.io/?EIO=4&transport=websocket&clientVersion=1.2.3"
+        );
+        assert!(matches!(
+            Endpoint::parse("ftp://chat.example.com", "1"),
+            Err(Error::Scheme(_))
+        ));
+    }
+
+    #[test]
+    fn gateway_is_the_chat_namespace_of_the_api_server() {
+        let api = Url::parse(API_URL).unwrap();
+        let endpoint = Endpoint::parse(&gateway_url(), "1.2.3").unwrap();
+        assert_eq!(endpoint.namespace, "/chat");
+        assert_eq!(endpoint.websocket_url.host(), api.host());
+        assert_eq!(
+            endpoint.websocket_url.port_or_known_default(),
+            api.port_or_known_default()
+        );
+    }
+
+    #[tokio::test]
+    async fn handshakes_and_forwards_events() {
+        let server = Gateway::start().await;
+        let mut client = Client::connect(&server);
+        let mut peer = server.accept().await;
+
+        assert_eq!(peer.recv_event().await, json!(["ready"]));
+        assert_eq!(client.next().await, ("connect".to_string(), Value::Null));
+        assert_eq!(client.manager.status().state, ConnectionState::Connected);
+
+        // Heartbeats are answered
+        peer.send("2").await;
+        assert_eq!(peer.recv().await, protocol::PONG);
+
+        peer.send(r#"42/chat,["friend-online",{"userId":4}]"#).await;
+        assert_eq!(
+            client.next().await,
+            ("friend-online".to_string(), json!([{ "userId": 4 }]))
+        );
+
+        client
+            .manager
+            .status_change(4, "idle")
+            .expect("connected socket accepts emits");
+        assert_eq!(
+            peer.recv_event().await,
+            json!(["status-change", { "userId": 4, "status": "idle" }])
+        );
+
+        client.manager.disconnect();
+        assert_eq!(peer.recv().await, "41/chat,");
+    }
+
+    #[tokio::test]
+    async fn reconnects_and_replays_rooms_and_pending_emits() {
+        let server = Gateway::start().await;
+        let mut client = Client::connect(&server);
+        let mut peer = server.accept().await;
+        assert_eq!(peer.recv_event().await, json!(["ready"]));
+        assert_eq!(client.next().await.0, "connect");
+
+        client
+            .manager
+            .emit("join-server", vec![json!({ "serverId": 1 })])
+            .unwrap();
+        client
+            .manager
+            .emit("join-channel", vec![json!({ "channelId": 7 })])
+            .unwrap();
+        client
+            .manager
+            .emit("leave-channel", vec![json!({ "channelId": 7 })])
+            .unwrap();
+        client
+            .manager
+            .emit("join-channel", vec![json!({ "channelId": 9 })])
+            .unwrap();
+        for _ in 0..4 {
+            peer.recv_event().await;
+        }
+
+        // The connection drops
+        drop(peer);
+        let dropped = StdInstant::now();
+        assert_eq!(client.next().await.0, "disconnect");
+        assert_eq!(
+            client.next().await,
+            ("reconnect_attempt".to_string(), json!(1))
+        );
+        assert_eq!(client.manager.status().state, ConnectionState::Reconnecting);
+
+        // Emits while offline wait for the connection
+        client
+            .manager
+            .send_message(9, "while away".to_string(), None, None)
+            .unwrap();
+
+        let mut peer = server.accept().await;
+        assert!(
+            dropped.elapsed() >= backoff(1),
+            "reconnected after {:?}",
+            dropped.elapsed()
+        );
+        assert_eq!(
+            peer.recv_event().await,
+            json!(["join-server", { "serverId": 1 }])
+        );
+        assert_eq!(
+            peer.recv_event().await,
+            json!(["join-channel", { "channelId": 9 }])
+        );
+        assert_eq!(peer.recv_event().await, json!(["ready"]));
+        assert_eq!(
+            peer.recv_event().await,
+            json!(["send-message", {
+                "channelId": 9,
+                "content": "while away",
+                "replyToId": null,
+                "attachments": null,
+            }])
+        );
+        assert_eq!(client.next().await.0, "connect");
+
+        let status = client.manager.status();
+        assert_eq!(status.state, ConnectionState::Connected);
+        assert_eq!(status.joined_servers, [1]);
+        assert_eq!(status.joined_channels, [9]);
+        client.manager.disconnect();
+    }
+
+    #[tokio::test]
+    async fn stays_down_when_the_server_disconnects_us() {
+        let server = Gateway::start().await;
+        let mut client = Client::connect(&server);
+        let mut peer = server.accept().await;
+        peer.recv_event().await;
+        assert_eq!(client.next().await.0, "connect");
+
+        peer.send("41/chat,").await;
+        assert_eq!(
+            client.next().await,
+            ("disconnect".to_string(), json!("io server disconnect"))
+        );
+        assert!(
+            server
+                .connection(Duration::from_millis(1500))
+                .await
+                .is_none(),
+            "reconnected after being disconnected by the server"
+        );
+        assert_eq!(client.manager.status().state, ConnectionState::Disconnected);
+    }
+
+    #[tokio::test]
+    async fn refused_connections_report_the_reason() {
+        let server = Gateway::start().await;
+        let mut client = Client::connect(&server);
+
+        let mut peer = server.connection(WAIT).await.unwrap();
+        peer.send(r#"0{"sid":"engine","pingInterval":25000,"pingTimeout":20000}"#)
+            .await;
+        peer.recv().await;
+        peer.send(r#"44/chat,{"message":"Unauthorized"}"#).await;
+
+        assert_eq!(
+            client.next().await,
+            (
+                "connect_error".to_string(),
+                json!({ "message": "Unauthorized" })
+            )
+        );
+    }
+
+    fn direct_message(id: i64, content: &str) -> Value {
+        json!({
+            "id": id,
+            "content": content,
+            "senderId": 2,
+            "receiverId": 1,
+            "createdAt": "2025-01-01T00:00:00.000Z",
+            "sender": { "id": 2, "username": "bob" },
+            "receiver": { "id": 1, "username": "ada" },
+        })
+    }
+
+    fn found(cache: &Cache, query: &str) -> Vec<i64> {
+        crate::search::search(cache, query, None, None)
+            .unwrap()
+            .hits
+            .iter()
+            .map(|hit| hit.message_id)
+            .collect()
+    }
+
+    #[test]
+    fn message_events_reach_the_cache_and_its_index() {
+        let cache = Cache::in_memory(1);
+        store(&cache, "direct-message", &direct_message(5, "lunch today?")).unwrap();
+        store(
+            &cache,
+            "message",
+            &json!({
+                "id": 9,
+                "content": "lunch plans",
+                "userId": 2,
+                "username": "bob",
+                "channelId": 3,
+                "createdAt": "2025-01-01T00:00:00.000Z",
+            }),
+        )
+        .unwrap();
+        assert_eq!(found(&cache, "lunch from:bob").len(), 2);
+
+        let mut edited = direct_message(5, "dinner tonight?");
+        edited["isEdited"] = json!(true);
+        store(&cache, "direct-message-edited", &edited).unwrap();
+        assert_eq!(found(&cache, "lunch"), [9]);
+        assert_eq!(found(&cache, "dinner"), [5]);
+
+        store(&cache, "direct-message-deleted", &json!({ "messageId": 5 })).unwrap();
+        assert!(found(&cache, "dinner").is_empty());
+        store(
+            &cache,
+            "message-deleted",
+            &json!({ "messageId": 9, "channelId": 3 }),
+        )
+        .unwrap();
+        assert!(found(&cache, "lunch").is_empty());
+
+        // Anything else, or a deletion without an id, leaves the cache alone
+        store(&cache, "direct-message-deleted", &json!({})).unwrap();
+        store(&cache, "typing", &json!({ "channelId": 3 })).unwrap();
+    }
+}
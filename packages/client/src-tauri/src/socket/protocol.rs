@@ -0,0 +1,227 @@
+//! Engine.IO v4 / Socket.IO v5 framing over the websocket transport.
+//!
+//! Only what the `/chat` gateway needs is implemented: text packets, events
+//! without acknowledgements from our side, and no binary attachments.
+
+use serde::Deserialize;
+use serde_json::Value;
+
+pub const PONG: &str = "3";
+
+/// Payload of the Engine.IO `open` packet.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Handshake {
+    pub ping_interval: u64,
+    pub ping_timeout: u64,
+}
+
+#[derive(Debug)]
+pub enum EnginePacket<'a> {
+    Open(Handshake),
+    Close,
+    Ping,
+    Pong,
+    Message(&'a str),
+    Noop,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum SocketPacket {
+    Connect,
+    Disconnect,
+    Event { name: String, args: Vec<Value> },
+    ConnectError(Value),
+}
+
+pub fn decode_engine(text: &str) -> Option<EnginePacket<'_>> {
+    let mut chars = text.chars();
+    let packet = match chars.next()? {
+        '0' => EnginePacket::Open(serde_json::from_str(chars.as_str()).ok()?),
+        '1' => EnginePacket::Close,
+        '2' => EnginePacket::Ping,
+        '3' => EnginePacket::Pong,
+        '4' => EnginePacket::Message(chars.as_str()),
+        '6' => EnginePacket::Noop,
+        _ => return None,
+    };
+    Some(packet)
+}
+
+/// Decodes a Socket.IO packet, ignoring anything addressed to another
+/// namespace.
+///
+/// Format: `<type>[<attachments>-][<namespace>,][<ack id>][<json>]`
+pub fn decode_socket(namespace: &str, payload: &str) -> Option<SocketPacket> {
+    let kind = payload.chars().next()?;
+    let mut rest = payload.get(kind.len_utf8()..)?;
+
+    // Binary packets (5, 6) carry placeholders the gateway never sends
+    if matches!(kind, '5' | '6') {
+        return None;
+    }
+
+    let packet_namespace = if rest.starts_with('/') {
+        let end = rest.find(',').unwrap_or(rest.len());
+        let ns = &rest[..end];
+        rest = rest.get(end + 1..).unwrap_or("");
+        ns
+    } else {
+        "/"
+    };
+    if packet_namespace != namespace {
+        return None;
+    }
+
+    // Skip an ack id; we never request acknowledgements
+    rest = rest.trim_start_matches(|c: char| c.is_ascii_digit());
+    let data = || serde_json::from_str::<Value>(rest).ok();
+
+    match kind {
+        '0' => Some(SocketPacket::Connect),
+        '1' => Some(SocketPacket::Disconnect),
+        '2' => {
+            let Value::Array(mut items) = data()? else {
+                return None;
+            };
+            if items.is_empty() {
+                return None;
+            }
+            let Value::String(name) = items.remove(0) else {
+                return None;
+            };
+            Some(SocketPacket::Event { name, args: items })
+        }
+        '4' => Some(SocketPacket::ConnectError(data().unwrap_or(Value::Null))),
+        _ => None,
+    }
+}
+
+pub fn connect(namespace: &str, auth: &Value) -> String {
+    format!("40{}{}", prefix(namespace), auth)
+}
+
+pub fn disconnect(namespace: &str) -> String {
+    format!("41{}", prefix(namespace))
+}
+
+pub fn event(namespace: &str, name: &str, args: &[Value]) -> String {
+    let mut items = Vec::with_capacity(args.len() + 1);
+    items.push(Value::String(name.to_string()));
+    items.extend_from_slice(args);
+    format!("42{}{}", prefix(namespace), Value::Array(items))
+}
+
+fn prefix(namespace: &str) -> String {
+    if namespace == "/" {
+        String::new()
+    } else {
+        format!("{},", namespace)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn engine_packets() {
+        match decode_engine(r#"0{"sid":"a","pingInterval":25000,"pingTimeout":20000}"#) {
+            Some(EnginePacket::Open(handshake)) => {
+                assert_eq!(handshake.ping_interval, 25000);
+                assert_eq!(handshake.ping_timeout, 20000);
+            }
+            other => panic!("expected open, got {:?}", other),
+        }
+        assert!(matches!(decode_engine("2"), Some(EnginePacket::Ping)));
+        assert!(matches!(
+            decode_engine("42[\"x\"]"),
+            Some(EnginePacket::Message("2[\"x\"]"))
+        ));
+        assert!(decode_engine("").is_none());
+        assert!(decode_engine("9").is_none());
+        assert!(decode_engine("0not json").is_none());
+    }
+
+    #[test]
+    fn events_in_our_namespace() {
+        assert_eq!(
+            decode_socket("/chat", r#"2/chat,["message",{"id":1}]"#),
+            Some(SocketPacket::Event {
+                name: "message".to_string(),
+                args: vec![json!({ "id": 1 })],
+            })
+        );
+        // An ack id is skipped
+        assert_eq!(
+            decode_socket("/chat", r#"2/chat,17["ping"]"#),
+            Some(SocketPacket::Event {
+                name: "ping".to_string(),
+                args: Vec::new(),
+            })
+        );
+        assert_eq!(
+            decode_socket("/", r#"2["typing",1,2]"#),
+            Some(SocketPacket::Event {
+                name: "typing".to_string(),
+                args: vec![json!(1), json!(2)],
+            })
+        );
+    }
+
+    #[test]
+    fn other_namespaces_and_binary_are_ignored() {
+        assert_eq!(decode_socket("/chat", r#"2/admin,["message"]"#), None);
+        assert_eq!(decode_socket("/chat", r#"2["message"]"#), None);
+        assert_eq!(decode_socket("/chat", r#"51-/chat,["file",{}]"#), None);
+    }
+
+    #[test]
+    fn lifecycle_packets() {
+        assert_eq!(
+            decode_socket("/chat", r#"0/chat,{"sid":"b"}"#),
+            Some(SocketPacket::Connect)
+        );
+        assert_eq!(
+            decode_socket("/chat", "1/chat,"),
+            Some(SocketPacket::Disconnect)
+        );
+        assert_eq!(
+            decode_socket("/chat", r#"4/chat,{"message":"Unauthorized"}"#),
+            Some(SocketPacket::ConnectError(
+                json!({ "message": "Unauthorized" })
+            ))
+        );
+    }
+
+    #[test]
+    fn malformed_payloads_are_rejected() {
+        // The server controls these, so none of them may panic
+        for payload in [
+            "",
+            "é",
+            "ü/chat,",
+            "2/chat",
+            "2/chat,",
+            "2/chat,[]",
+            "2/chat,[1]",
+        ] {
+            assert_eq!(decode_socket("/chat", payload), None, "{:?}", payload);
+        }
+    }
+
+    #[test]
+    fn encoding() {
+        assert_eq!(
+            connect("/chat", &json!({ "token": "t" })),
+            r#"40/chat,{"token":"t"}"#
+        );
+        assert_eq!(disconnect("/chat"), "41/chat,");
+        assert_eq!(
+            event("/chat", "join-channel", &[json!({ "channelId": 3 })]),
+            r#"42/chat,["join-channel",{"channelId":3}]"#
+        );
+        assert_eq!(event("/", "ready", &[]), r#"42["ready"]"#);
+    }
+}
@@ -0,0 +1,179 @@
+//! Helpers shared by tests across modules: a scratch directory per test and
+//! a local server standing in for the hosts the backend talks to.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_tungstenite::tungstenite::handshake::derive_accept_key;
+use tokio_tungstenite::tungstenite::protocol::Role;
+use tokio_tungstenite::WebSocketStream;
+
+/// An empty directory for one test, named after the module and the test
+/// so parallel tests never share one.
+pub fn root(module: &str, name: &str) -> PathBuf {
+    let root = std::env::temp_dir().join(format!(
+        "commhub-{}-{}-{}",
+        module,
+        std::process::id(),
+        name
+    ));
+    let _ = std::fs::remove_dir_all(&root);
+    root
+}
+
+/// A request's line and headers; the body is left for the handler to read.
+#[derive(Debug, Clone)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    /// Keyed by lowercased name.
+    pub headers: HashMap<String, String>,
+}
+
+impl Request {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).map(String::as_str)
+    }
+
+    pub fn content_length(&self) -> usize {
+        self.header("content-length")
+            .and_then(|length| length.parse().ok())
+            .unwrap_or(0)
+    }
+}
+
+/// The connection a request came in on, for the handler to answer however
+/// the test needs: in full, in part, never, or by hanging up.
+pub struct Connection {
+    stream: BufReader<TcpStream>,
+}
+
+impl Connection {
+    pub async fn read_body(&mut self, len: usize) -> io::Result<Vec<u8>> {
+        let mut body = vec![0; len];
+        self.stream.read_exact(&mut body).await?;
+        Ok(body)
+    }
+
+    pub async fn write(&mut self, bytes: &[u8]) -> io::Result<()> {
+        let stream = self.stream.get_mut();
+        stream.write_all(bytes).await?;
+        stream.flush().await
+    }
+
+    /// A complete response, after which the connection closes.
+    pub async fn respond(&mut self, status: &str, headers: &[(&str, &str)], body: &[u8]) {
+        let mut head = format!("HTTP/1.1 {}\r\n", status);
+        for (name, value) in headers {
+            head.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        head.push_str(&format!(
+            "Content-Length: {}\r\nConnection: close\r\n\r\n",
+            body.len()
+        ));
+        let _ = self.write(head.as_bytes()).await;
+        let _ = self.write(body).await;
+    }
+
+    pub async fn not_found(&mut self) {
+        self.respond("404 Not Found", &[], b"").await;
+    }
+
+    /// Accepts a WebSocket upgrade for `request`.
+    pub async fn upgrade(mut self, request: &Request) -> WebSocketStream<TcpStream> {
+        let key = request
+            .header("sec-websocket-key")
+            .expect("not a WebSocket upgrade");
+        let head = format!(
+            "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+             Sec-WebSocket-Accept: {}\r\n\r\n",
+            derive_accept_key(key.as_bytes())
+        );
+        self.write(head.as_bytes()).await.unwrap();
+        WebSocketStream::from_raw_socket(self.stream.into_inner(), Role::Server, None).await
+    }
+}
+
+/// A local HTTP server handing each request to the test's handler, and
+/// counting them.
+pub struct StandIn {
+    addr: SocketAddr,
+    requests: Arc<AtomicUsize>,
+}
+
+impl StandIn {
+    pub async fn start<F, Fut>(handler: F) -> Self
+    where
+        F: Fn(Request, Connection) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = requests.clone();
+        let handler = Arc::new(handler);
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let (counter, handler) = (counter.clone(), handler.clone());
+                tokio::spawn(async move {
+                    let mut stream = BufReader::new(stream);
+                    if let Some(request) = read_request(&mut stream).await {
+                        counter.fetch_add(1, Ordering::SeqCst);
+                        handler(request, Connection { stream }).await;
+                    }
+                });
+            }
+        });
+        Self { addr, requests }
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{}", self.addr, path)
+    }
+
+    pub fn port(&self) -> u16 {
+        self.addr.port()
+    }
+
+    pub fn requests(&self) -> usize {
+        self.requests.load(Ordering::SeqCst)
+    }
+}
+
+/// `None` when the client hangs up before the headers end.
+async fn read_request(stream: &mut BufReader<TcpStream>) -> Option<Request> {
+    let mut line = String::new();
+    if stream.read_line(&mut line).await.ok()? == 0 {
+        return None;
+    }
+    let mut parts = line.split_whitespace();
+    let method = parts.next()?.to_string();
+    let path = parts.next()?.to_string();
+
+    let mut headers = HashMap::new();
+    loop {
+        let mut line = String::new();
+        if stream.read_line(&mut line).await.ok()? == 0 {
+            return None;
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.insert(name.trim().to_ascii_lowercase(), value.trim().to_string());
+        }
+    }
+    Some(Request {
+        method,
+        path,
+        headers,
+    })
+}
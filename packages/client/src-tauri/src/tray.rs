@@ -0,0 +1,172 @@
+//! System tray: presence switching, unread summary and voice shortcuts.
+//!
+//! Clicks that change app state are sent to the webview as a typed
+//! `tray://action` event. The stores apply them and report back through the
+//! `tray_set_*` commands, which redraw the icon and menu, so the tray never
+//! shows a state the webview hasn't confirmed.
+
+pub(crate) mod icon;
+mod summary;
+
+use std::sync::Mutex;
+
+use tauri::{
+    AppHandle, CustomMenuItem, Manager, State, SystemTray, SystemTrayEvent, SystemTrayMenu,
+    SystemTrayMenuItem, SystemTraySubmenu,
+};
+
+use crate::background::{self, MAIN_WINDOW};
+use crate::notifications::Notifications;
+pub use summary::UserStatus;
+use summary::{Click, Summary, TrayAction, DEAFEN, MUTE, QUIT, SHOW_HIDE, UNREAD};
+
+#[derive(Default)]
+pub struct Tray {
+    state: Mutex<Summary>,
+}
+
+pub fn build() -> SystemTray {
+    let statuses = UserStatus::ALL
+        .iter()
+        .fold(SystemTrayMenu::new(), |menu, status| {
+            let item = CustomMenuItem::new(status.menu_id(), status.label());
+            menu.add_item(if *status == UserStatus::default() {
+                item.selected()
+            } else {
+                item
+            })
+        });
+
+    let menu = SystemTrayMenu::new()
+        .add_item(CustomMenuItem::new(UNREAD, Summary::default().unread_title()).disabled())
+        .add_native_item(SystemTrayMenuItem::Separator)
+        .add_submenu(SystemTraySubmenu::new("Status", statuses))
+        .add_native_item(SystemTrayMenuItem::Separator)
+        .add_item(CustomMenuItem::new(MUTE, "Mute"))
+        .add_item(CustomMenuItem::new(DEAFEN, "Deafen"))
+        .add_native_item(SystemTrayMenuItem::Separator)
+        .add_item(CustomMenuItem::new(SHOW_HIDE, "Hide CommHub"))
+        .add_item(CustomMenuItem::new(QUIT, "Quit CommHub"));
+
+    SystemTray::new()
+        .with_icon(icon::render(Summary::default().icon()))
+        .with_tooltip("CommHub")
+        .with_menu(menu)
+}
+
+pub fn handle_event(app: &AppHandle, event: SystemTrayEvent) {
+    match event {
+        SystemTrayEvent::LeftClick { .. } => show_main_window(app),
+        SystemTrayEvent::MenuItemClick { id, .. } => match Click::from_id(&id) {
+            Some(Click::ShowHide) => toggle_main_window(app),
+            Some(Click::Quit) => background::quit(app),
+            Some(Click::Action(action)) => emit_action(app, action),
+            None => {}
+        },
+        _ => {}
+    }
+}
+
+fn emit_action(app: &AppHandle, action: TrayAction) {
+    let _ = app.emit_all("tray://action", action);
+}
+
+/// Shows the main window, recreating it if the close policy destroyed it.
+pub fn show_main_window(app: &AppHandle) {
+    if let Some(window) = background::main_window(app) {
+        let _ = window.show();
+        let _ = window.unminimize();
+        let _ = window.set_focus();
+    }
+    sync_visibility(app);
+}
+
+fn toggle_main_window(app: &AppHandle) {
+    match app.get_window(MAIN_WINDOW) {
+        Some(window) if window.is_visible().unwrap_or(false) => {
+            let _ = window.hide();
+            sync_visibility(app);
+        }
+        _ => show_main_window(app),
+    }
+}
+
+/// Keeps the Show/Hide label in step with the main window.
+pub fn sync_visibility(app: &AppHandle) {
+    let visible = app
+        .get_window(MAIN_WINDOW)
+        .and_then(|window| window.is_visible().ok())
+        .unwrap_or(false);
+    let title = if visible {
+        "Hide CommHub"
+    } else {
+        "Show CommHub"
+    };
+    let _ = app.tray_handle().get_item(SHOW_HIDE).set_title(title);
+}
+
+impl Tray {
+    /// The status the webview last confirmed.
+    pub fn status(&self) -> UserStatus {
+        self.state.lock().unwrap().status
+    }
+
+    /// Redraws the icon, tooltip and menu from the current state.
+    fn refresh(&self, app: &AppHandle) -> tauri::Result<()> {
+        let state = self.state.lock().unwrap();
+        let handle = app.tray_handle();
+        handle.set_icon(icon::render(state.icon()))?;
+        handle.set_tooltip(&state.tooltip())?;
+        handle.get_item(UNREAD).set_title(state.unread_title())?;
+        for status in UserStatus::ALL {
+            handle
+                .get_item(status.menu_id())
+                .set_selected(status == state.status)?;
+        }
+        handle.get_item(MUTE).set_selected(state.muted)?;
+        handle.get_item(DEAFEN).set_selected(state.deafened)?;
+        Ok(())
+    }
+}
+
+#[tauri::command]
+pub fn tray_set_status(
+    app: AppHandle,
+    tray: State<'_, Tray>,
+    status: UserStatus,
+) -> Result<(), String> {
+    tray.state.lock().unwrap().status = status;
+    app.state::<Notifications>()
+        .set_dnd(status == UserStatus::Dnd);
+    tray.refresh(&app).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn tray_set_unread(
+    app: AppHandle,
+    tray: State<'_, Tray>,
+    direct_messages: u32,
+    mentions: u32,
+) -> Result<(), String> {
+    {
+        let mut state = tray.state.lock().unwrap();
+        state.unread_direct = direct_messages;
+        state.unread_mentions = mentions;
+    }
+    tray.refresh(&app).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn tray_set_voice(
+    app: AppHandle,
+    tray: State<'_, Tray>,
+    muted: bool,
+    deafened: bool,
+) -> Result<(), String> {
+    {
+        let mut state = tray.state.lock().unwrap();
+        state.muted = muted;
+        state.deafened = deafened;
+    }
+    tray.refresh(&app).map_err(|e| e.to_string())
+}
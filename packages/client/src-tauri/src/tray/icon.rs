@@ -0,0 +1,114 @@
+//! Tray artwork: the app icon with a presence dot and an unread badge.
+
+use std::sync::OnceLock;
+
+use image::imageops::{self, FilterType};
+use image::{ImageFormat, Rgba, RgbaImage};
+
+use super::summary::Icon;
+use super::UserStatus;
+
+const SIZE: u32 = 32;
+
+// Dark ring that separates the dot and badge from the artwork underneath
+pub(crate) const RING: Rgba<u8> = Rgba([24, 24, 27, 255]);
+const ONLINE: Rgba<u8> = Rgba([35, 165, 90, 255]);
+const IDLE: Rgba<u8> = Rgba([240, 178, 50, 255]);
+const DND: Rgba<u8> = Rgba([242, 63, 67, 255]);
+const INVISIBLE: Rgba<u8> = Rgba([128, 132, 142, 255]);
+pub(crate) const UNREAD: Rgba<u8> = Rgba([242, 63, 67, 255]);
+pub(crate) const WHITE: Rgba<u8> = Rgba([255, 255, 255, 255]);
+
+static BASE: OnceLock<RgbaImage> = OnceLock::new();
+
+/// The bundled app icon scaled to `size` pixels square.
+pub(crate) fn app_icon(size: u32) -> RgbaImage {
+    let icon = image::load_from_memory_with_format(
+        include_bytes!("../../icons/icon.ico"),
+        ImageFormat::Ico,
+    )
+    .expect("bundled icon.ico is valid")
+    .to_rgba8();
+    imageops::resize(&icon, size, size, FilterType::Lanczos3)
+}
+
+fn base() -> &'static RgbaImage {
+    BASE.get_or_init(|| app_icon(SIZE))
+}
+
+pub fn render(icon: Icon) -> tauri::Icon {
+    let mut image = base().clone();
+
+    let (x, y) = (25.0, 25.0);
+    fill_circle(&mut image, x, y, 7.0, RING);
+    match icon.status {
+        UserStatus::Online => fill_circle(&mut image, x, y, 5.0, ONLINE),
+        UserStatus::Idle => {
+            // Crescent: a dot with a bite taken out of the top-left
+            fill_circle(&mut image, x, y, 5.0, IDLE);
+            fill_circle(&mut image, x - 2.5, y - 2.5, 3.0, RING);
+        }
+        UserStatus::Dnd => {
+            fill_circle(&mut image, x, y, 5.0, DND);
+            fill_rect(&mut image, x - 3.0, y - 1.0, 6.0, 2.0, WHITE);
+        }
+        UserStatus::Invisible => {
+            fill_circle(&mut image, x, y, 5.0, INVISIBLE);
+            fill_circle(&mut image, x, y, 2.5, RING);
+        }
+    }
+
+    // Too small for digits; the count is in the tooltip and menu
+    if icon.badge {
+        fill_circle(&mut image, 25.0, 7.0, 7.0, RING);
+        fill_circle(&mut image, 25.0, 7.0, 5.0, UNREAD);
+    }
+
+    tauri::Icon::Rgba {
+        rgba: image.into_raw(),
+        width: SIZE,
+        height: SIZE,
+    }
+}
+
+/// Anti-aliased disc, blended over whatever is already there.
+pub(crate) fn fill_circle(image: &mut RgbaImage, cx: f32, cy: f32, radius: f32, color: Rgba<u8>) {
+    for (px, py, pixel) in image.enumerate_pixels_mut() {
+        let dx = px as f32 + 0.5 - cx;
+        let dy = py as f32 + 0.5 - cy;
+        let coverage = (radius + 0.5 - (dx * dx + dy * dy).sqrt()).clamp(0.0, 1.0);
+        blend(pixel, color, coverage);
+    }
+}
+
+pub(crate) fn fill_rect(
+    image: &mut RgbaImage,
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+    color: Rgba<u8>,
+) {
+    for (px, py, pixel) in image.enumerate_pixels_mut() {
+        let (px, py) = (px as f32, py as f32);
+        if px >= x && px < x + width && py >= y && py < y + height {
+            blend(pixel, color, 1.0);
+        }
+    }
+}
+
+fn blend(pixel: &mut Rgba<u8>, color: Rgba<u8>, coverage: f32) {
+    if coverage <= 0.0 {
+        return;
+    }
+    // Porter-Duff "over" so edges against transparent pixels don't darken
+    let alpha = coverage * color[3] as f32 / 255.0;
+    let under_alpha = pixel[3] as f32 / 255.0;
+    let out_alpha = alpha + under_alpha * (1.0 - alpha);
+    for channel in 0..3 {
+        let value =
+            color[channel] as f32 * alpha + pixel[channel] as f32 * under_alpha * (1.0 - alpha);
+        pixel[channel] = (value / out_alpha).round() as u8;
+    }
+    pixel[3] = (out_alpha * 255.0).round() as u8;
+}
@@ -0,0 +1,267 @@
+//! What the tray shows and what its menu items mean, kept apart from the
+//! tray handle so it can be checked directly.
+//!
+//! [`Summary`] is the state the webview last confirmed; the unread title,
+//! tooltip and which icon to draw all follow from it. Menu item ids are
+//! mapped back to a [`Click`] here too.
+
+use serde::{Deserialize, Serialize};
+
+pub const UNREAD: &str = "unread";
+pub const MUTE: &str = "mute";
+pub const DEAFEN: &str = "deafen";
+pub const SHOW_HIDE: &str = "show-hide";
+pub const QUIT: &str = "quit";
+
+/// Mirrors `UserStatus` in stores/status.ts.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UserStatus {
+    #[default]
+    Online,
+    Idle,
+    Dnd,
+    Invisible,
+}
+
+impl UserStatus {
+    pub const ALL: [UserStatus; 4] = [
+        UserStatus::Online,
+        UserStatus::Idle,
+        UserStatus::Dnd,
+        UserStatus::Invisible,
+    ];
+
+    pub fn menu_id(self) -> &'static str {
+        match self {
+            UserStatus::Online => "status-online",
+            UserStatus::Idle => "status-idle",
+            UserStatus::Dnd => "status-dnd",
+            UserStatus::Invisible => "status-invisible",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            UserStatus::Online => "Online",
+            UserStatus::Idle => "Idle",
+            UserStatus::Dnd => "Do Not Disturb",
+            UserStatus::Invisible => "Invisible",
+        }
+    }
+}
+
+/// Payload of `tray://action`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum TrayAction {
+    SetStatus { status: UserStatus },
+    ToggleMute,
+    ToggleDeafen,
+}
+
+/// A menu item that was clicked.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Click {
+    ShowHide,
+    Quit,
+    /// Sent to the webview as `tray://action`.
+    Action(TrayAction),
+}
+
+impl Click {
+    /// The click for a menu item id, or none for items that do nothing.
+    pub fn from_id(id: &str) -> Option<Click> {
+        let click = match id {
+            SHOW_HIDE => Click::ShowHide,
+            QUIT => Click::Quit,
+            MUTE => Click::Action(TrayAction::ToggleMute),
+            DEAFEN => Click::Action(TrayAction::ToggleDeafen),
+            id => {
+                let status = UserStatus::ALL.into_iter().find(|s| s.menu_id() == id)?;
+                Click::Action(TrayAction::SetStatus { status })
+            }
+        };
+        Some(click)
+    }
+}
+
+/// Which icon to draw: the presence dot, and a badge while anything is
+/// unread.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Icon {
+    pub status: UserStatus,
+    pub badge: bool,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Summary {
+    pub status: UserStatus,
+    pub unread_direct: u32,
+    pub unread_mentions: u32,
+    pub muted: bool,
+    pub deafened: bool,
+}
+
+impl Summary {
+    pub fn icon(&self) -> Icon {
+        Icon {
+            status: self.status,
+            badge: self.unread_direct > 0 || self.unread_mentions > 0,
+        }
+    }
+
+    /// The disabled first menu item.
+    pub fn unread_title(&self) -> String {
+        match (self.unread_direct, self.unread_mentions) {
+            (0, 0) => "No unread messages".to_string(),
+            (direct, 0) => format!("{} unread DM{}", direct, plural(direct)),
+            (0, mentions) => format!("{} mention{}", mentions, plural(mentions)),
+            (direct, mentions) => format!(
+                "{} unread DM{}, {} mention{}",
+                direct,
+                plural(direct),
+                mentions,
+                plural(mentions)
+            ),
+        }
+    }
+
+    pub fn tooltip(&self) -> String {
+        format!(
+            "CommHub - {} ({})",
+            self.status.label(),
+            self.unread_title().to_lowercase()
+        )
+    }
+}
+
+fn plural(count: u32) -> &'static str {
+    if count == 1 {
+        ""
+    } else {
+        "s"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unread(direct: u32, mentions: u32) -> Summary {
+        Summary {
+            unread_direct: direct,
+            unread_mentions: mentions,
+            ..Summary::default()
+        }
+    }
+
+    #[test]
+    fn titles_count_dms_and_mentions() {
+        let cases: &[(u32, u32, &str)] = &[
+            (0, 0, "No unread messages"),
+            (1, 0, "1 unread DM"),
+            (3, 0, "3 unread DMs"),
+            (0, 1, "1 mention"),
+            (0, 2, "2 mentions"),
+            (1, 1, "1 unread DM, 1 mention"),
+            (2, 5, "2 unread DMs, 5 mentions"),
+        ];
+        for (direct, mentions, expected) in cases {
+            assert_eq!(unread(*direct, *mentions).unread_title(), *expected);
+        }
+    }
+
+    #[test]
+    fn tooltip_names_the_status_and_the_unread() {
+        let summary = Summary {
+            status: UserStatus::Dnd,
+            ..unread(2, 1)
+        };
+        assert_eq!(
+            summary.tooltip(),
+            "CommHub - Do Not Disturb (2 unread dms, 1 mention)"
+        );
+        assert_eq!(
+            Summary::default().tooltip(),
+            "CommHub - Online (no unread messages)"
+        );
+    }
+
+    #[test]
+    fn icon_follows_status_and_badges_any_unread() {
+        for status in UserStatus::ALL {
+            let summary = Summary {
+                status,
+                ..Summary::default()
+            };
+            assert_eq!(
+                summary.icon(),
+                Icon {
+                    status,
+                    badge: false
+                }
+            );
+        }
+        assert!(unread(1, 0).icon().badge);
+        assert!(unread(0, 1).icon().badge);
+        assert!(unread(u32::MAX, u32::MAX).icon().badge);
+        // Muting has no icon of its own
+        let muted = Summary {
+            muted: true,
+            deafened: true,
+            ..Summary::default()
+        };
+        assert_eq!(muted.icon(), Summary::default().icon());
+    }
+
+    #[test]
+    fn menu_ids_map_to_clicks() {
+        let cases: &[(&str, Option<Click>)] = &[
+            (SHOW_HIDE, Some(Click::ShowHide)),
+            (QUIT, Some(Click::Quit)),
+            (MUTE, Some(Click::Action(TrayAction::ToggleMute))),
+            (DEAFEN, Some(Click::Action(TrayAction::ToggleDeafen))),
+            (
+                "status-idle",
+                Some(Click::Action(TrayAction::SetStatus {
+                    status: UserStatus::Idle,
+                })),
+            ),
+            (
+                "status-invisible",
+                Some(Click::Action(TrayAction::SetStatus {
+                    status: UserStatus::Invisible,
+                })),
+            ),
+            // The unread summary is disabled, and the rest aren't ours
+            (UNREAD, None),
+            ("status-away", None),
+            ("", None),
+        ];
+        for (id, expected) in cases {
+            assert_eq!(Click::from_id(id), *expected, "{}", id);
+        }
+        for status in UserStatus::ALL {
+            assert_eq!(
+                Click::from_id(status.menu_id()),
+                Some(Click::Action(TrayAction::SetStatus { status }))
+            );
+        }
+    }
+
+    #[test]
+    fn actions_serialize_for_the_webview() {
+        assert_eq!(
+            serde_json::to_value(TrayAction::SetStatus {
+                status: UserStatus::Dnd
+            })
+            .unwrap(),
+            serde_json::json!({ "type": "setStatus", "status": "dnd" })
+        );
+        assert_eq!(
+            serde_json::to_value(TrayAction::ToggleMute).unwrap(),
+            serde_json::json!({ "type": "toggleMute" })
+        );
+    }
+}
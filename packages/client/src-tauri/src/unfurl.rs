@@ -0,0 +1,949 @@
+//! Link previews for URLs posted in messages.
+//!
+//! Pages are fetched here rather than from the webview, within a size and
+//! time limit and never from private addresses (see [`guard`]). Their
+//! OpenGraph and Twitter Card tags, and oEmbed where the page offers it,
+//! become a [`Preview`]; links straight to an image or video become one
+//! without downloading them. Results are kept on disk (see [`store`]).
+
+pub(crate) mod guard;
+mod meta;
+mod store;
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures_util::StreamExt;
+use reqwest::header::{ACCEPT, CONTENT_TYPE};
+use reqwest::{redirect, Client, Response};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, State};
+use tokio::sync::OnceCell;
+use url::Url;
+
+use guard::{Blocked, PublicResolver};
+use meta::{Meta, OEmbed};
+use store::Store;
+
+/// Enough for any `<head>`; the rest of the page is never needed.
+const MAX_PAGE_BYTES: u64 = 1024 * 1024;
+const MAX_OEMBED_BYTES: u64 = 64 * 1024;
+const TIMEOUT: Duration = Duration::from_secs(8);
+const MAX_REDIRECTS: usize = 5;
+const MAX_TITLE: usize = 300;
+const MAX_DESCRIPTION: usize = 1000;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("invalid link: {0}")]
+    Url(#[from] url::ParseError),
+    #[error("only http and https links have previews")]
+    Scheme,
+    #[error(transparent)]
+    Blocked(#[from] Blocked),
+    #[error("failed to fetch link: {0}")]
+    Http(reqwest::Error),
+    #[error("link returned {0}")]
+    Status(u16),
+    #[error("link took too long to load")]
+    Timeout,
+    #[error("invalid oEmbed response: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error(transparent)]
+    Store(#[from] store::Error),
+    #[error("app directories are unavailable")]
+    NoAppDir,
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_timeout() {
+            return Error::Timeout;
+        }
+        // The resolver's refusal arrives wrapped in the connect error
+        let mut source = std::error::Error::source(&e);
+        while let Some(cause) = source {
+            if let Some(blocked) = cause.downcast_ref::<Blocked>() {
+                return Error::Blocked(Blocked(blocked.0.clone()));
+            }
+            source = cause.source();
+        }
+        Error::Http(e)
+    }
+}
+
+/// What a link points at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Kind {
+    /// A page, described by its metadata.
+    Link,
+    /// An image file, shown as is.
+    Image,
+    /// A video file, played as is.
+    Video,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Image {
+    pub url: String,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub alt: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Player {
+    /// A page to frame, like a YouTube embed.
+    Embed,
+    /// A file for a `<video>` element.
+    File,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Video {
+    pub url: String,
+    pub player: Player,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Preview {
+    /// Where the link ended up, after redirects.
+    pub url: String,
+    pub kind: Kind,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub site_name: Option<String>,
+    pub image: Option<Image>,
+    pub video: Option<Video>,
+}
+
+struct Config {
+    max_bytes: u64,
+    timeout: Duration,
+    /// Only for tests, which serve pages from loopback.
+    allow_private: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            max_bytes: MAX_PAGE_BYTES,
+            timeout: TIMEOUT,
+            allow_private: false,
+        }
+    }
+}
+
+pub struct Unfurler {
+    client: Client,
+    store: Store,
+    config: Config,
+    // The same link in several messages is fetched once
+    in_flight: Mutex<HashMap<String, Arc<OnceCell<Option<Preview>>>>>,
+}
+
+impl Unfurler {
+    pub fn new(root: &Path, package_info: &tauri::PackageInfo) -> Result<Self, Error> {
+        Self::with_config(root, &package_info.version.to_string(), Config::default())
+    }
+
+    fn with_config(root: &Path, version: &str, config: Config) -> Result<Self, Error> {
+        let allow_private = config.allow_private;
+        let client = Client::builder()
+            .user_agent(format!(
+                "Mozilla/5.0 (compatible; CommHub/{}; link preview)",
+                version
+            ))
+            .timeout(config.timeout)
+            .redirect(redirect::Policy::custom(move |attempt| {
+                if attempt.previous().len() >= MAX_REDIRECTS {
+                    return attempt.error("too many redirects");
+                }
+                if !matches!(attempt.url().scheme(), "http" | "https") {
+                    return attempt.stop();
+                }
+                match guard::check(attempt.url(), allow_private) {
+                    Ok(()) => attempt.follow(),
+                    Err(blocked) => attempt.error(blocked),
+                }
+            }))
+            .dns_resolver(Arc::new(PublicResolver { allow_private }))
+            // Straight to the site, so the address checked is the one used
+            .no_proxy()
+            .build()?;
+        Ok(Self {
+            client,
+            store: Store::open(root)?,
+            config,
+            in_flight: Mutex::new(HashMap::new()),
+        })
+    }
+
+    pub fn from_app(app: &AppHandle) -> Result<Self, Error> {
+        let root = app
+            .path_resolver()
+            .app_local_data_dir()
+            .ok_or(Error::NoAppDir)?
+            .join("unfurl");
+        Self::new(&root, app.package_info())
+    }
+
+    /// The preview for `url`, or `None` if it has none or couldn't be
+    /// fetched.
+    pub async fn unfurl(&self, url: &str) -> Result<Option<Preview>, Error> {
+        let url = Url::parse(url)?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(Error::Scheme);
+        }
+        guard::check(&url, self.config.allow_private)?;
+        if let Some(cached) = self.store.get(url.as_str())? {
+            return Ok(cached);
+        }
+
+        let cell = self
+            .in_flight
+            .lock()
+            .unwrap()
+            .entry(url.to_string())
+            .or_default()
+            .clone();
+        let preview = cell
+            .get_or_init(|| async {
+                let preview = match self.fetch(&url).await {
+                    Ok(preview) => preview,
+                    Err(e) => {
+                        log!("[Unfurl] No preview for {}: {}", url, e);
+                        None
+                    }
+                };
+                if let Err(e) = self.store.put(url.as_str(), preview.as_ref()) {
+                    log!("[Unfurl] Failed to cache preview for {}: {}", url, e);
+                }
+                preview
+            })
+            .await
+            .clone();
+        self.in_flight.lock().unwrap().remove(url.as_str());
+        Ok(preview)
+    }
+
+    async fn fetch(&self, url: &Url) -> Result<Option<Preview>, Error> {
+        let response = self
+            .get(url, "text/html,application/xhtml+xml;q=0.9,*/*;q=0.8")
+            .await?;
+        let page = response.url().clone();
+        let content_type = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.split(';').next())
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase();
+
+        // Media is described by its URL alone; the file is left unread
+        if content_type.starts_with("image/") {
+            return Ok(Some(Preview::media(&page, Kind::Image)));
+        }
+        if content_type.starts_with("video/") {
+            return Ok(Some(Preview::media(&page, Kind::Video)));
+        }
+        if content_type != "text/html" && content_type != "application/xhtml+xml" {
+            return Ok(None);
+        }
+
+        let body = read(response, self.config.max_bytes).await?;
+        let meta = meta::parse(&String::from_utf8_lossy(&body), &page);
+        let mut preview = self.preview(&page, &meta);
+
+        // oEmbed is another request, so only when the page said too little
+        let sparse =
+            preview.title.is_none() || (preview.image.is_none() && preview.video.is_none());
+        if let Some(endpoint) = meta.oembed.as_ref().filter(|_| sparse) {
+            match self.oembed(endpoint).await {
+                Ok(oembed) => self.merge(&mut preview, &page, oembed),
+                Err(e) => log!("[Unfurl] Ignoring oEmbed for {}: {}", page, e),
+            }
+        }
+        if preview.site_name.is_none() {
+            preview.site_name = host_name(&page);
+        }
+        Ok(Some(preview))
+    }
+
+    async fn get(&self, url: &Url, accept: &str) -> Result<Response, Error> {
+        guard::check(url, self.config.allow_private)?;
+        let response = self
+            .client
+            .get(url.clone())
+            .header(ACCEPT, accept)
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            return Err(Error::Status(response.status().as_u16()));
+        }
+        Ok(response)
+    }
+
+    async fn oembed(&self, endpoint: &Url) -> Result<OEmbed, Error> {
+        let response = self.get(endpoint, "application/json").await?;
+        let body = read(response, MAX_OEMBED_BYTES).await?;
+        Ok(serde_json::from_slice(&body)?)
+    }
+
+    fn preview(&self, page: &Url, meta: &Meta) -> Preview {
+        let image = meta
+            .get(&[
+                "og:image:secure_url",
+                "og:image",
+                "og:image:url",
+                "twitter:image",
+                "twitter:image:src",
+            ])
+            .and_then(|image| self.link(page, image))
+            .map(|url| Image {
+                url: url.to_string(),
+                width: meta.dimension(&["og:image:width"]),
+                height: meta.dimension(&["og:image:height"]),
+                alt: meta
+                    .get(&["og:image:alt", "twitter:image:alt"])
+                    .and_then(|alt| text(alt, MAX_TITLE)),
+            });
+
+        Preview {
+            url: page.to_string(),
+            kind: Kind::Link,
+            title: meta
+                .get(&["og:title", "twitter:title"])
+                .or(meta.title.as_deref())
+                .and_then(|title| text(title, MAX_TITLE)),
+            description: meta
+                .get(&["og:description", "twitter:description", "description"])
+                .and_then(|description| text(description, MAX_DESCRIPTION)),
+            site_name: meta
+                .get(&["og:site_name", "application-name"])
+                .and_then(|site| text(site, MAX_TITLE)),
+            image,
+            video: self.video(page, meta),
+        }
+    }
+
+    fn video(&self, page: &Url, meta: &Meta) -> Option<Video> {
+        let width = meta.dimension(&["og:video:width", "twitter:player:width"]);
+        let height = meta.dimension(&["og:video:height", "twitter:player:height"]);
+        let video = |url: Url, player: Player| {
+            // Framed players have to be https to load in the webview
+            (player == Player::File || url.scheme() == "https").then(|| Video {
+                url: url.to_string(),
+                player,
+                width,
+                height,
+            })
+        };
+
+        let og = meta
+            .get(&["og:video:secure_url", "og:video:url", "og:video"])
+            .and_then(|url| self.link(page, url));
+        if let Some(url) = og {
+            let mime = meta.get(&["og:video:type"]).unwrap_or_default();
+            let player = if mime.to_ascii_lowercase().starts_with("video/")
+                || (mime.is_empty() && is_video_file(&url))
+            {
+                Player::File
+            } else {
+                Player::Embed
+            };
+            if let Some(video) = video(url, player) {
+                return Some(video);
+            }
+        }
+        if let Some(url) = meta
+            .get(&["twitter:player"])
+            .and_then(|url| self.link(page, url))
+        {
+            return video(url, Player::Embed);
+        }
+        meta.get(&["twitter:player:stream"])
+            .and_then(|url| self.link(page, url))
+            .and_then(|url| video(url, Player::File))
+    }
+
+    /// Fills in what the page's own tags left out.
+    fn merge(&self, preview: &mut Preview, page: &Url, oembed: OEmbed) {
+        if preview.title.is_none() {
+            preview.title = oembed
+                .title
+                .as_deref()
+                .and_then(|title| text(title, MAX_TITLE));
+        }
+        if preview.site_name.is_none() {
+            preview.site_name = oembed
+                .provider_name
+                .as_deref()
+                .and_then(|site| text(site, MAX_TITLE));
+        }
+        if preview.image.is_none() {
+            preview.image = oembed
+                .thumbnail_url
+                .as_deref()
+                .and_then(|url| self.link(page, url))
+                .map(|url| Image {
+                    url: url.to_string(),
+                    width: oembed.thumbnail_width,
+                    height: oembed.thumbnail_height,
+                    alt: None,
+                });
+        }
+        if preview.video.is_none() {
+            preview.video = oembed
+                .player()
+                .and_then(|url| self.link(page, &url))
+                .filter(|url| url.scheme() == "https")
+                .map(|url| Video {
+                    url: url.to_string(),
+                    player: Player::Embed,
+                    width: oembed.width,
+                    height: oembed.height,
+                });
+        }
+    }
+
+    /// A URL from the page, made absolute. The webview loads these itself,
+    /// so they're held to the same rules as the page.
+    fn link(&self, page: &Url, value: &str) -> Option<Url> {
+        let url = page.join(value.trim()).ok()?;
+        (matches!(url.scheme(), "http" | "https")
+            && guard::check(&url, self.config.allow_private).is_ok())
+        .then_some(url)
+    }
+}
+
+impl Preview {
+    fn media(url: &Url, kind: Kind) -> Self {
+        Self {
+            url: url.to_string(),
+            kind,
+            title: None,
+            description: None,
+            site_name: host_name(url),
+            image: (kind == Kind::Image).then(|| Image {
+                url: url.to_string(),
+                width: None,
+                height: None,
+                alt: None,
+            }),
+            video: (kind == Kind::Video).then(|| Video {
+                url: url.to_string(),
+                player: Player::File,
+                width: None,
+                height: None,
+            }),
+        }
+    }
+}
+
+/// Reads at most `limit` bytes of the body and drops the rest.
+async fn read(response: Response, limit: u64) -> Result<Vec<u8>, Error> {
+    let limit = limit as usize;
+    let mut body = Vec::new();
+    let mut stream = response.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk?;
+        let room = limit - body.len();
+        body.extend_from_slice(&chunk[..chunk.len().min(room)]);
+        if body.len() >= limit {
+            break;
+        }
+    }
+    Ok(body)
+}
+
+/// Whitespace collapsed and cut to `max` characters; `None` if empty.
+fn text(value: &str, max: usize) -> Option<String> {
+    let collapsed = value.split_whitespace().collect::<Vec<_>>().join(" ");
+    if collapsed.is_empty() {
+        return None;
+    }
+    if collapsed.chars().count() <= max {
+        return Some(collapsed);
+    }
+    let mut cut: String = collapsed.chars().take(max - 1).collect();
+    cut.truncate(cut.trim_end().len());
+    cut.push('…');
+    Some(cut)
+}
+
+fn host_name(url: &Url) -> Option<String> {
+    let host = url.host_str()?;
+    Some(host.strip_prefix("www.").unwrap_or(host).to_string())
+}
+
+fn is_video_file(url: &Url) -> bool {
+    let path = url.path().to_ascii_lowercase();
+    [".mp4", ".webm", ".mov", ".m4v", ".ogv"]
+        .iter()
+        .any(|extension| path.ends_with(extension))
+}
+
+/// The preview for a link in a message, or `None` to show it plainly.
+#[tauri::command]
+pub async fn unfurl_link(
+    unfurler: State<'_, Unfurler>,
+    url: String,
+) -> Result<Option<Preview>, String> {
+    unfurler.unfurl(&url).await.map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::net::IpAddr;
+    use std::time::Instant;
+
+    use reqwest::dns::Resolve;
+
+    use crate::test_support::{self, Connection, Request, StandIn};
+
+    /// What the stand-in server answers at a path.
+    enum Page {
+        Html(String),
+        Json(String),
+        /// Headers for a large file of this type, and then no body.
+        Media(&'static str),
+        /// Headers for an HTML page, and then no body.
+        Stall,
+        /// An HTML page that never ends.
+        Endless(String),
+    }
+
+    /// A local web server with canned pages.
+    async fn stand_in(pages: Vec<(&'static str, Page)>) -> StandIn {
+        let pages: Arc<HashMap<&'static str, Page>> = Arc::new(pages.into_iter().collect());
+        StandIn::start(move |request, connection| serve(request, connection, pages.clone())).await
+    }
+
+    async fn serve(
+        request: Request,
+        mut connection: Connection,
+        pages: Arc<HashMap<&'static str, Page>>,
+    ) {
+        let (content_type, body) = match pages.get(request.path.as_str()) {
+            Some(Page::Html(html)) => ("text/html; charset=utf-8", html.clone()),
+            Some(Page::Json(json)) => ("application/json", json.clone()),
+            Some(Page::Media(content_type)) => {
+                let head = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: 50000000\r\n\r\n",
+                    content_type
+                );
+                let _ = connection.write(head.as_bytes()).await;
+                std::future::pending::<()>().await;
+                return;
+            }
+            Some(Page::Stall) => {
+                let head =
+                    "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nContent-Length: 100\r\n\r\n";
+                let _ = connection.write(head.as_bytes()).await;
+                std::future::pending::<()>().await;
+                return;
+            }
+            Some(Page::Endless(head)) => {
+                let start = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nConnection: close\r\n\r\n{}",
+                    head
+                );
+                if connection.write(start.as_bytes()).await.is_err() {
+                    return;
+                }
+                let filler = "<p>filler</p>".repeat(1000);
+                while connection.write(filler.as_bytes()).await.is_ok() {}
+                return;
+            }
+            None => return connection.not_found().await,
+        };
+        connection
+            .respond("200 OK", &[("Content-Type", content_type)], body.as_bytes())
+            .await;
+    }
+
+    /// Loopback allowed, since that's where the stand-in is.
+    fn local() -> Config {
+        Config {
+            max_bytes: 64 * 1024,
+            timeout: Duration::from_millis(500),
+            allow_private: true,
+        }
+    }
+
+    fn unfurler(name: &str) -> Unfurler {
+        Unfurler::with_config(&test_support::root("unfurl", name), "test", local()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn reads_opengraph_tags() {
+        let server = stand_in(vec![(
+            "/article",
+            Page::Html(
+                r#"<html><head>
+                <title>Fallback title</title>
+                <meta property="og:title" content="The &amp; Title">
+                <meta property="og:description" content="  What it's
+                    about  ">
+                <meta property="og:site_name" content="Example News">
+                <meta property="og:image" content="/cover.png">
+                <meta property="og:image:width" content="1200">
+                <meta property="og:image:height" content="630">
+                <meta property="og:image:alt" content="A cover">
+                </head><body>Body</body></html>"#
+                    .to_string(),
+            ),
+        )])
+        .await;
+
+        let preview = unfurler("opengraph")
+            .unfurl(&server.url("/article"))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(preview.kind, Kind::Link);
+        assert_eq!(preview.title.as_deref(), Some("The & Title"));
+        assert_eq!(preview.description.as_deref(), Some("What it's about"));
+        assert_eq!(preview.site_name.as_deref(), Some("Example News"));
+        assert_eq!(
+            preview.image,
+            Some(Image {
+                url: server.url("/cover.png"),
+                width: Some(1200),
+                height: Some(630),
+                alt: Some("A cover".to_string()),
+            })
+        );
+        assert_eq!(preview.video, None);
+    }
+
+    #[tokio::test]
+    async fn falls_back_to_twitter_cards_and_the_title() {
+        let server = stand_in(vec![(
+            "/post",
+            Page::Html(
+                r#"<html><head>
+                <title>
+                    Spaced   out
+                </title>
+                <meta name="twitter:card" content="player">
+                <meta name="twitter:description" content="A clip">
+                <meta name="twitter:image" content="https://cdn.example.com/still.jpg">
+                <meta name="twitter:player" content="https://player.example.com/embed/7">
+                <meta name="twitter:player:width" content="640">
+                <meta name="twitter:player:height" content="360">
+                </head></html>"#
+                    .to_string(),
+            ),
+        )])
+        .await;
+
+        let preview = unfurler("twitter")
+            .unfurl(&server.url("/post"))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(preview.title.as_deref(), Some("Spaced out"));
+        assert_eq!(preview.description.as_deref(), Some("A clip"));
+        assert_eq!(preview.site_name.as_deref(), Some("127.0.0.1"));
+        assert_eq!(
+            preview.image.map(|image| image.url).as_deref(),
+            Some("https://cdn.example.com/still.jpg")
+        );
+        assert_eq!(
+            preview.video,
+            Some(Video {
+                url: "https://player.example.com/embed/7".to_string(),
+                player: Player::Embed,
+                width: Some(640),
+                height: Some(360),
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn fills_gaps_from_oembed() {
+        let server = stand_in(vec![
+            (
+                "/watch",
+                Page::Html(
+                    r#"<html><head><title>Watch</title>
+                    <link rel="alternate" type="application/json+oembed" href="/oembed.json">
+                    </head></html>"#
+                        .to_string(),
+                ),
+            ),
+            (
+                "/oembed.json",
+                Page::Json(
+                    serde_json::json!({
+                        "type": "video",
+                        "title": "Ignored, the page has a title",
+                        "provider_name": "VideoSite",
+                        "thumbnail_url": "https://i.example.com/abc.jpg",
+                        "thumbnail_width": 480,
+                        "thumbnail_height": 360,
+                        "html": "<iframe width=\"560\" height=\"315\" src=\"https://videos.example.com/embed/abc\"></iframe><script>alert(1)</script>",
+                        "width": 560,
+                        "height": 315
+                    })
+                    .to_string(),
+                ),
+            ),
+        ])
+        .await;
+
+        let preview = unfurler("oembed")
+            .unfurl(&server.url("/watch"))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(preview.title.as_deref(), Some("Watch"));
+        assert_eq!(preview.site_name.as_deref(), Some("VideoSite"));
+        assert_eq!(
+            preview.image,
+            Some(Image {
+                url: "https://i.example.com/abc.jpg".to_string(),
+                width: Some(480),
+                height: Some(360),
+                alt: None,
+            })
+        );
+        assert_eq!(
+            preview.video,
+            Some(Video {
+                url: "https://videos.example.com/embed/abc".to_string(),
+                player: Player::Embed,
+                width: Some(560),
+                height: Some(315),
+            })
+        );
+        assert_eq!(server.requests(), 2);
+    }
+
+    #[tokio::test]
+    async fn drops_media_urls_the_webview_should_not_load() {
+        let server = stand_in(vec![(
+            "/page",
+            Page::Html(
+                r#"<html><head>
+                <meta property="og:title" content="Sneaky">
+                <meta property="og:image" content="javascript:alert(1)">
+                <meta property="og:video" content="http://player.example.com/embed/1">
+                <meta property="og:video:type" content="text/html">
+                </head></html>"#
+                    .to_string(),
+            ),
+        )])
+        .await;
+
+        let preview = unfurler("unsafe")
+            .unfurl(&server.url("/page"))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(preview.title.as_deref(), Some("Sneaky"));
+        assert_eq!(preview.image, None);
+        // Framed players must be https
+        assert_eq!(preview.video, None);
+    }
+
+    #[tokio::test]
+    async fn describes_media_links_without_downloading_them() {
+        let server = stand_in(vec![
+            ("/photo", Page::Media("image/jpeg")),
+            ("/clip", Page::Media("video/mp4")),
+        ])
+        .await;
+        let unfurler = unfurler("media");
+
+        let started = Instant::now();
+        let photo = unfurler
+            .unfurl(&server.url("/photo"))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(photo.kind, Kind::Image);
+        assert_eq!(photo.image.unwrap().url, server.url("/photo"));
+
+        let clip = unfurler
+            .unfurl(&server.url("/clip"))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(clip.kind, Kind::Video);
+        assert_eq!(clip.video.unwrap().player, Player::File);
+        // Reading either body would have run into the timeout
+        assert!(started.elapsed() < Duration::from_millis(500));
+    }
+
+    #[tokio::test]
+    async fn stops_reading_at_the_size_limit() {
+        let padding = "x".repeat(100 * 1024);
+        let server = stand_in(vec![
+            (
+                "/endless",
+                Page::Endless(
+                    r#"<html><head><meta property="og:title" content="Endless"></head><body>"#
+                        .to_string(),
+                ),
+            ),
+            (
+                "/padded",
+                Page::Html(format!(
+                    r#"<html><head><meta property="og:title" content="Early">
+                    <!-- {} -->
+                    <meta property="og:description" content="Past the limit">
+                    </head></html>"#,
+                    padding
+                )),
+            ),
+        ])
+        .await;
+        let unfurler = unfurler("limit");
+
+        let endless = unfurler
+            .unfurl(&server.url("/endless"))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(endless.title.as_deref(), Some("Endless"));
+
+        let padded = unfurler
+            .unfurl(&server.url("/padded"))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(padded.title.as_deref(), Some("Early"));
+        assert_eq!(padded.description, None);
+    }
+
+    #[tokio::test]
+    async fn gives_up_on_slow_pages() {
+        let server = stand_in(vec![("/slow", Page::Stall)]).await;
+        let unfurler = unfurler("slow");
+
+        let url = Url::parse(&server.url("/slow")).unwrap();
+        assert!(matches!(unfurler.fetch(&url).await, Err(Error::Timeout)));
+        assert_eq!(unfurler.unfurl(url.as_str()).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn refuses_private_addresses() {
+        let server = stand_in(vec![(
+            "/",
+            Page::Html("<title>Internal</title>".to_string()),
+        )])
+        .await;
+        let unfurler = Unfurler::with_config(
+            &test_support::root("unfurl", "private"),
+            "test",
+            Config::default(),
+        )
+        .unwrap();
+        let port = server.port();
+
+        for url in [
+            format!("http://127.0.0.1:{}/", port),
+            format!("http://localhost:{}/", port),
+            format!("http://[::1]:{}/", port),
+            format!("http://[::ffff:127.0.0.1]:{}/", port),
+            "http://169.254.169.254/latest/meta-data/".to_string(),
+            "http://10.0.0.1/".to_string(),
+        ] {
+            assert!(
+                matches!(unfurler.unfurl(&url).await, Err(Error::Blocked(_))),
+                "{} wasn't refused",
+                url
+            );
+        }
+        assert!(matches!(
+            unfurler.unfurl("file:///etc/passwd").await,
+            Err(Error::Scheme)
+        ));
+        assert_eq!(server.requests(), 0);
+
+        // Names are checked by what they resolve to
+        let resolver = PublicResolver {
+            allow_private: false,
+        };
+        assert!(resolver
+            .resolve("localhost".parse().unwrap())
+            .await
+            .is_err());
+    }
+
+    #[test]
+    fn classifies_addresses() {
+        let public = |ip: &str| guard::is_public(ip.parse::<IpAddr>().unwrap());
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "255.255.255.255",
+            "::1",
+            "::",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:192.168.0.1",
+            "64:ff9b::a00:1",
+            "2002:c0a8:101::1",
+        ] {
+            assert!(!public(ip), "{} should be private", ip);
+        }
+        for ip in [
+            "8.8.8.8",
+            "1.1.1.1",
+            "2606:4700:4700::1111",
+            "::ffff:8.8.8.8",
+        ] {
+            assert!(public(ip), "{} should be public", ip);
+        }
+    }
+
+    #[tokio::test]
+    async fn keeps_previews_on_disk() {
+        let server = stand_in(vec![(
+            "/cached",
+            Page::Html(r#"<meta property="og:title" content="Cached">"#.to_string()),
+        )])
+        .await;
+        let root = test_support::root("unfurl", "store");
+
+        let first = Unfurler::with_config(&root, "test", local()).unwrap();
+        let url = server.url("/cached");
+        let (a, b) = tokio::join!(first.unfurl(&url), first.unfurl(&url));
+        assert_eq!(a.unwrap(), b.unwrap());
+        // Links without a preview are remembered too
+        assert_eq!(first.unfurl(&server.url("/missing")).await.unwrap(), None);
+        assert_eq!(server.requests(), 2);
+        drop(first);
+
+        let second = Unfurler::with_config(&root, "test", local()).unwrap();
+        let preview = second
+            .unfurl(&server.url("/cached"))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(preview.title.as_deref(), Some("Cached"));
+        assert_eq!(second.unfurl(&server.url("/missing")).await.unwrap(), None);
+        assert_eq!(server.requests(), 2);
+    }
+}
@@ -0,0 +1,102 @@
+//! Preview metadata from a page's `<head>`: OpenGraph, Twitter Card, the
+//! plain `<title>` and description, and where to find its oEmbed.
+
+use std::collections::HashMap;
+
+use kuchikiki::traits::TendrilSink;
+use serde::Deserialize;
+use url::Url;
+
+#[derive(Debug, Default)]
+pub struct Meta {
+    /// `<meta>` content by lowercased `property` or `name`, first one wins.
+    pub properties: HashMap<String, String>,
+    pub title: Option<String>,
+    /// Discovered through `<link rel="alternate" type="application/json+oembed">`.
+    pub oembed: Option<Url>,
+}
+
+impl Meta {
+    /// The first of `keys` that the page set to something non-empty.
+    pub fn get(&self, keys: &[&str]) -> Option<&str> {
+        keys.iter()
+            .filter_map(|key| self.properties.get(*key))
+            .map(|value| value.trim())
+            .find(|value| !value.is_empty())
+    }
+
+    pub fn dimension(&self, keys: &[&str]) -> Option<u32> {
+        self.get(keys)?.parse().ok()
+    }
+}
+
+pub fn parse(html: &str, base: &Url) -> Meta {
+    let document = kuchikiki::parse_html().one(html);
+    let mut meta = Meta::default();
+
+    if let Ok(tags) = document.select("meta") {
+        for tag in tags {
+            let attributes = tag.attributes.borrow();
+            let Some(key) = attributes
+                .get("property")
+                .or_else(|| attributes.get("name"))
+            else {
+                continue;
+            };
+            let Some(content) = attributes.get("content") else {
+                continue;
+            };
+            meta.properties
+                .entry(key.trim().to_ascii_lowercase())
+                .or_insert_with(|| content.to_string());
+        }
+    }
+
+    if let Ok(mut titles) = document.select("title") {
+        meta.title = titles.next().map(|title| title.text_contents());
+    }
+
+    if let Ok(links) = document.select("link") {
+        meta.oembed = links
+            .filter(|link| {
+                let attributes = link.attributes.borrow();
+                let rel = attributes.get("rel").unwrap_or_default();
+                let kind = attributes.get("type").unwrap_or_default();
+                rel.split_whitespace()
+                    .any(|rel| rel.eq_ignore_ascii_case("alternate"))
+                    && kind.eq_ignore_ascii_case("application/json+oembed")
+            })
+            .find_map(|link| base.join(link.attributes.borrow().get("href")?).ok());
+    }
+
+    meta
+}
+
+/// The fields of an oEmbed response a preview uses.
+#[derive(Debug, Default, Deserialize)]
+pub struct OEmbed {
+    #[serde(rename = "type")]
+    pub kind: Option<String>,
+    pub title: Option<String>,
+    pub provider_name: Option<String>,
+    pub thumbnail_url: Option<String>,
+    pub thumbnail_width: Option<u32>,
+    pub thumbnail_height: Option<u32>,
+    pub html: Option<String>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+}
+
+impl OEmbed {
+    /// The player a video embed's HTML frames. Only the `<iframe>`'s
+    /// address is used; the HTML itself never reaches the webview.
+    pub fn player(&self) -> Option<String> {
+        if self.kind.as_deref() != Some("video") {
+            return None;
+        }
+        let document = kuchikiki::parse_html().one(self.html.as_deref()?);
+        let iframe = document.select_first("iframe").ok()?;
+        let src = iframe.attributes.borrow().get("src")?.to_string();
+        Some(src)
+    }
+}
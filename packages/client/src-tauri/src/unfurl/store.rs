@@ -0,0 +1,101 @@
+//! Previews already fetched, kept on disk so scrolling back through history
+//! or restarting doesn't fetch every link again.
+//!
+//! Links without a preview are remembered too, for a shorter time, since a
+//! page that failed may come back.
+
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use rusqlite::{params, Connection, OptionalExtension};
+
+use super::Preview;
+
+/// How long a preview is trusted, in seconds.
+const PREVIEW_TTL: i64 = 24 * 60 * 60;
+const MISS_TTL: i64 = 60 * 60;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("preview cache database error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+    #[error("preview cache io error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+pub struct Store {
+    conn: Mutex<Connection>,
+}
+
+impl Store {
+    pub fn open(root: &Path) -> Result<Self, Error> {
+        fs::create_dir_all(root)?;
+        let conn = Connection::open(root.join("previews.sqlite3"))?;
+        conn.execute_batch(
+            "PRAGMA journal_mode = WAL;
+             PRAGMA synchronous = NORMAL;
+             CREATE TABLE IF NOT EXISTS previews (
+                url TEXT PRIMARY KEY,
+                preview TEXT,
+                fetched_at INTEGER NOT NULL
+             );",
+        )?;
+        conn.execute(
+            "DELETE FROM previews WHERE fetched_at < ?1",
+            [now() - PREVIEW_TTL],
+        )?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    /// `Some(None)` for a link that recently had no preview, `None` if it
+    /// needs fetching.
+    pub fn get(&self, url: &str) -> Result<Option<Option<Preview>>, Error> {
+        let found: Option<(Option<String>, i64)> = self
+            .conn
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT preview, fetched_at FROM previews WHERE url = ?1",
+                [url],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        let Some((preview, fetched_at)) = found else {
+            return Ok(None);
+        };
+
+        let ttl = if preview.is_some() {
+            PREVIEW_TTL
+        } else {
+            MISS_TTL
+        };
+        if now() - fetched_at > ttl {
+            return Ok(None);
+        }
+        match preview.map(|json| serde_json::from_str(&json)).transpose() {
+            Ok(preview) => Ok(Some(preview)),
+            // Written by a version with a different shape; fetch it again
+            Err(_) => Ok(None),
+        }
+    }
+
+    pub fn put(&self, url: &str, preview: Option<&Preview>) -> Result<(), Error> {
+        let json = preview.and_then(|preview| serde_json::to_string(preview).ok());
+        self.conn.lock().unwrap().execute(
+            "INSERT OR REPLACE INTO previews (url, preview, fetched_at) VALUES (?1, ?2, ?3)",
+            params![url, json, now()],
+        )?;
+        Ok(())
+    }
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs() as i64)
+        .unwrap_or_default()
+}
@@ -0,0 +1,932 @@
+//! Self-updater driven by the `latest.json` manifest published with each
+//! release.
+//!
+//! Endpoints and the minisign (ed25519) public key come from
+//! `tauri.updater` in tauri.conf.json; Tauri's own updater stays inactive. A
+//! downloaded artifact is verified, staged under $APPLOCALDATA/updates and
+//! installed on the next launch before any window opens, so an update never
+//! cuts into a call.
+
+mod install;
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use base64::Engine as _;
+use minisign_verify::{PublicKey, Signature};
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tauri::{AppHandle, Config, Manager, PackageInfo, State};
+
+const STAGED_FILE: &str = "staged.json";
+// Keeps progress events to a rate the webview can render
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("update request failed: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("update io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("update data is malformed: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("update version is invalid: {0}")]
+    Version(#[from] semver::Error),
+    #[error("no update endpoints are configured")]
+    NoEndpoint,
+    #[error("updater public key is not configured")]
+    NoPublicKey,
+    #[error("updates aren't available in this build")]
+    Disabled,
+    #[error("no update is published for {0}")]
+    NoPlatform(String),
+    #[error("no update is available")]
+    NoUpdate,
+    #[error("an update is already downloading")]
+    Busy,
+    #[error("update signature is invalid: {0}")]
+    Signature(String),
+    #[error("update checksum doesn't match the manifest")]
+    Checksum,
+    #[error("{0} updates can't be installed on this platform")]
+    Unsupported(String),
+    #[error("app directories are unavailable")]
+    NoAppDir,
+}
+
+/// `latest.json`, in the format Tauri's updater reads.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Manifest {
+    pub version: String,
+    #[serde(default)]
+    pub notes: Option<String>,
+    #[serde(default)]
+    pub pub_date: Option<String>,
+    pub platforms: HashMap<String, PlatformUpdate>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PlatformUpdate {
+    /// Base64 of the minisign signature file for the artifact
+    pub signature: String,
+    pub url: String,
+    /// Hex SHA-256 of the artifact, written by commhub-release
+    #[serde(default)]
+    pub sha256: Option<String>,
+}
+
+impl Manifest {
+    pub fn version(&self) -> Result<Version, Error> {
+        Ok(Version::parse(self.version.trim_start_matches('v'))?)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateInfo {
+    pub version: String,
+    pub current_version: String,
+    pub notes: Option<String>,
+    pub pub_date: Option<String>,
+}
+
+/// Result of `updater_check`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum UpdateCheck {
+    Available {
+        update: UpdateInfo,
+    },
+    UpToDate,
+    /// Built without a public key, so no update could be verified.
+    Disabled,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StagedUpdate {
+    pub version: String,
+    pub notes: Option<String>,
+    pub path: PathBuf,
+    pub signature: String,
+    #[serde(default)]
+    pub sha256: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadProgress {
+    pub version: String,
+    pub downloaded: u64,
+    pub total: Option<u64>,
+}
+
+pub struct Updater {
+    endpoints: Vec<String>,
+    pubkey: String,
+    current: Version,
+    dir: PathBuf,
+    client: reqwest::Client,
+    // Result of the last check, downloaded on request
+    available: Mutex<Option<(Manifest, PlatformUpdate)>>,
+    downloading: AtomicBool,
+}
+
+impl Updater {
+    /// Built from the config rather than an AppHandle so a staged update can
+    /// be applied before the app starts.
+    pub fn new(config: &Config, package_info: &PackageInfo) -> Result<Self, Error> {
+        let updater = &config.tauri.updater;
+        let dir = tauri::api::path::app_local_data_dir(config)
+            .ok_or(Error::NoAppDir)?
+            .join("updates");
+        if updater.pubkey.trim().is_empty() {
+            log!("[Updater] No public key is configured; updates are off");
+        }
+        let endpoints = updater
+            .endpoints
+            .iter()
+            .flatten()
+            .map(|endpoint| endpoint.to_string())
+            .collect();
+        Self::with_settings(
+            endpoints,
+            updater.pubkey.clone(),
+            package_info.version.clone(),
+            dir,
+        )
+    }
+
+    fn with_settings(
+        endpoints: Vec<String>,
+        pubkey: String,
+        current: Version,
+        dir: PathBuf,
+    ) -> Result<Self, Error> {
+        let client = reqwest::Client::builder()
+            .user_agent(format!("CommHub/{}", current))
+            .connect_timeout(Duration::from_secs(15))
+            .build()?;
+
+        Ok(Self {
+            endpoints,
+            pubkey,
+            current,
+            dir,
+            client,
+            available: Mutex::new(None),
+            downloading: AtomicBool::new(false),
+        })
+    }
+
+    /// Fetches the manifest and returns the update for this platform if it
+    /// is newer. Fails with [`Error::Disabled`] when there's no key to
+    /// verify it with, rather than claiming there's nothing newer.
+    /// Pre-releases order before their release (1.3.0-beta.1 < 1.3.0), so a
+    /// beta build still moves on to the final release.
+    pub async fn check(&self) -> Result<Option<UpdateInfo>, Error> {
+        if self.pubkey.trim().is_empty() {
+            return Err(Error::Disabled);
+        }
+        let manifest = self.fetch_manifest().await?;
+        let version = manifest.version()?;
+        if version <= self.current {
+            *self.available.lock().unwrap() = None;
+            return Ok(None);
+        }
+
+        let target = platform_key();
+        let platform = manifest
+            .platforms
+            .get(&target)
+            .cloned()
+            .ok_or(Error::NoPlatform(target))?;
+        let info = UpdateInfo {
+            version: version.to_string(),
+            current_version: self.current.to_string(),
+            notes: manifest.notes.clone(),
+            pub_date: manifest.pub_date.clone(),
+        };
+        *self.available.lock().unwrap() = Some((manifest, platform));
+        Ok(Some(info))
+    }
+
+    /// Downloads and verifies the update found by the last check, then
+    /// stages it for the next launch.
+    pub async fn download(&self, app: &AppHandle) -> Result<StagedUpdate, Error> {
+        // Nothing would ever launch it, so don't download it
+        if let Some((_, platform)) = &*self.available.lock().unwrap() {
+            let name = artifact_name(&platform.url);
+            let path = Path::new(&name);
+            if !install::supported(path) {
+                return Err(Error::Unsupported(install::extension(path)));
+            }
+        }
+
+        let staged = self
+            .stage(|progress| {
+                let _ = app.emit_all("updater://progress", progress);
+            })
+            .await?;
+        let _ = app.emit_all("updater://staged", &staged);
+        Ok(staged)
+    }
+
+    async fn stage(&self, on_progress: impl Fn(&DownloadProgress)) -> Result<StagedUpdate, Error> {
+        let (manifest, platform) = self
+            .available
+            .lock()
+            .unwrap()
+            .clone()
+            .ok_or(Error::NoUpdate)?;
+        let version = manifest.version()?.to_string();
+
+        if let Some(staged) = self.staged()? {
+            if staged.version == version && staged.path.exists() {
+                return Ok(staged);
+            }
+        }
+
+        if self.downloading.swap(true, Ordering::SeqCst) {
+            return Err(Error::Busy);
+        }
+        let result = self.fetch_artifact(&version, &platform, on_progress).await;
+        self.downloading.store(false, Ordering::SeqCst);
+        let path = result?;
+
+        let staged = StagedUpdate {
+            version,
+            notes: manifest.notes,
+            path,
+            signature: platform.signature,
+            sha256: platform.sha256,
+        };
+        fs::write(self.staged_path(), serde_json::to_vec_pretty(&staged)?)?;
+        Ok(staged)
+    }
+
+    pub fn staged(&self) -> Result<Option<StagedUpdate>, Error> {
+        match fs::read(self.staged_path()) {
+            Ok(bytes) => match serde_json::from_slice(&bytes) {
+                Ok(staged) => Ok(Some(staged)),
+                // The artifact it pointed at can't be found or checked, so
+                // drop both rather than failing on every launch
+                Err(e) => {
+                    fs::remove_dir_all(&self.dir)?;
+                    Err(e.into())
+                }
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Launches the installer for a staged update. Returns true when it was
+    /// started and this process should exit to let it replace the binary.
+    pub fn apply_staged(&self) -> Result<bool, Error> {
+        let Some(staged) = self.staged()? else {
+            return Ok(false);
+        };
+
+        // Already installed, or staged by a build that has since been replaced
+        if Version::parse(&staged.version)? <= self.current {
+            fs::remove_dir_all(&self.dir)?;
+            return Ok(false);
+        }
+
+        // The artifact sat on disk since it was downloaded; check it again,
+        // and drop it if it no longer passes so it's fetched afresh instead
+        // of failing on every launch
+        let checked = self.verify(&staged.path, &staged.signature, staged.sha256.as_deref());
+        if let Err(e) = checked {
+            fs::remove_dir_all(&self.dir)?;
+            return Err(e);
+        }
+
+        match install::launch(&staged.path) {
+            Ok(()) => {
+                // Only once the installer is running, and so that a failing
+                // one doesn't relaunch on every start
+                if let Err(e) = fs::remove_file(self.staged_path()) {
+                    log!("[Updater] Failed to clear the staged update: {}", e);
+                }
+                Ok(true)
+            }
+            Err(e @ Error::Unsupported(_)) => {
+                fs::remove_dir_all(&self.dir)?;
+                Err(e)
+            }
+            // Left staged to try again next launch
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn fetch_manifest(&self) -> Result<Manifest, Error> {
+        let mut last_error = Error::NoEndpoint;
+        for endpoint in &self.endpoints {
+            let response = self
+                .client
+                .get(endpoint)
+                .timeout(Duration::from_secs(30))
+                .send()
+                .await
+                .and_then(|response| response.error_for_status());
+            match response {
+                Ok(response) => return Ok(response.json().await?),
+                Err(e) => last_error = e.into(),
+            }
+        }
+        Err(last_error)
+    }
+
+    async fn fetch_artifact(
+        &self,
+        version: &str,
+        platform: &PlatformUpdate,
+        on_progress: impl Fn(&DownloadProgress),
+    ) -> Result<PathBuf, Error> {
+        if self.pubkey.trim().is_empty() {
+            return Err(Error::NoPublicKey);
+        }
+
+        let dir = self.dir.join(version);
+        fs::create_dir_all(&dir)?;
+        let path = dir.join(artifact_name(&platform.url));
+        let partial = path.with_extension("part");
+
+        let result = self
+            .download_to(&partial, version, platform, on_progress)
+            .await
+            .and_then(|()| self.verify(&partial, &platform.signature, platform.sha256.as_deref()));
+        if let Err(e) = result {
+            let _ = fs::remove_file(&partial);
+            return Err(e);
+        }
+        fs::rename(&partial, &path)?;
+        Ok(path)
+    }
+
+    async fn download_to(
+        &self,
+        partial: &Path,
+        version: &str,
+        platform: &PlatformUpdate,
+        on_progress: impl Fn(&DownloadProgress),
+    ) -> Result<(), Error> {
+        let mut response = self
+            .client
+            .get(&platform.url)
+            .send()
+            .await?
+            .error_for_status()?;
+        let mut progress = DownloadProgress {
+            version: version.to_string(),
+            downloaded: 0,
+            total: response.content_length(),
+        };
+
+        let mut file = File::create(partial)?;
+        let mut last_emit = Instant::now();
+        while let Some(chunk) = response.chunk().await? {
+            file.write_all(&chunk)?;
+            progress.downloaded += chunk.len() as u64;
+            if last_emit.elapsed() >= PROGRESS_INTERVAL {
+                on_progress(&progress);
+                last_emit = Instant::now();
+            }
+        }
+        file.sync_all()?;
+        on_progress(&progress);
+        Ok(())
+    }
+
+    /// Checks `path` against the minisign signature from the manifest, and
+    /// against its checksum when the manifest has one.
+    fn verify(&self, path: &Path, signature: &str, sha256: Option<&str>) -> Result<(), Error> {
+        let public_key = decode_public_key(&self.pubkey)?;
+        let signature = Signature::decode(&decode_base64_text(signature)?)
+            .map_err(|e| Error::Signature(e.to_string()))?;
+        // Only prehashed signatures can be checked without loading the
+        // whole artifact into memory; `tauri signer` produces those
+        let mut verifier = public_key
+            .verify_stream(&signature)
+            .map_err(|e| Error::Signature(e.to_string()))?;
+
+        let mut hasher = Sha256::new();
+        let mut file = File::open(path)?;
+        let mut buf = vec![0u8; 64 * 1024];
+        loop {
+            let read = file.read(&mut buf)?;
+            if read == 0 {
+                break;
+            }
+            verifier.update(&buf[..read]);
+            hasher.update(&buf[..read]);
+        }
+        verifier
+            .finalize()
+            .map_err(|e| Error::Signature(e.to_string()))?;
+
+        // Older manifests, and hand-written ones, leave it empty
+        let expected = sha256.map(str::trim).filter(|hex| !hex.is_empty());
+        if let Some(expected) = expected {
+            let actual: String = hasher
+                .finalize()
+                .iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect();
+            if !actual.eq_ignore_ascii_case(expected) {
+                return Err(Error::Checksum);
+            }
+        }
+        Ok(())
+    }
+
+    fn staged_path(&self) -> PathBuf {
+        self.dir.join(STAGED_FILE)
+    }
+}
+
+/// Key into `platforms`, using Tauri's names (`windows-x86_64`,
+/// `darwin-aarch64`, `linux-x86_64`).
+fn platform_key() -> String {
+    let os = match std::env::consts::OS {
+        "macos" => "darwin",
+        other => other,
+    };
+    format!("{}-{}", os, std::env::consts::ARCH)
+}
+
+/// File name for a downloaded artifact, taken from the last URL segment.
+fn artifact_name(url: &str) -> String {
+    let name = url
+        .split(['?', '#'])
+        .next()
+        .and_then(|path| path.rsplit('/').next())
+        .unwrap_or_default();
+    let safe = !name.is_empty()
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_' | '+'));
+    if safe {
+        name.to_string()
+    } else {
+        "update".to_string()
+    }
+}
+
+/// The config and manifest hold base64 of the full minisign key/signature
+/// files, as produced by `tauri signer`.
+fn decode_base64_text(value: &str) -> Result<String, Error> {
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(value.trim())
+        .map_err(|e| Error::Signature(e.to_string()))?;
+    String::from_utf8(bytes).map_err(|e| Error::Signature(e.to_string()))
+}
+
+fn decode_public_key(pubkey: &str) -> Result<PublicKey, Error> {
+    if pubkey.trim().is_empty() {
+        return Err(Error::NoPublicKey);
+    }
+    // Accept both the encoded key file and the bare key line
+    decode_base64_text(pubkey)
+        .ok()
+        .and_then(|text| PublicKey::decode(&text).ok())
+        .or_else(|| PublicKey::from_base64(pubkey.trim()).ok())
+        .ok_or_else(|| Error::Signature("updater public key is malformed".into()))
+}
+
+#[tauri::command]
+pub async fn updater_check(updater: State<'_, Updater>) -> Result<UpdateCheck, String> {
+    match updater.check().await {
+        Ok(Some(update)) => Ok(UpdateCheck::Available { update }),
+        Ok(None) => Ok(UpdateCheck::UpToDate),
+        Err(Error::Disabled) => Ok(UpdateCheck::Disabled),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+#[tauri::command]
+pub async fn updater_download(
+    app: AppHandle,
+    updater: State<'_, Updater>,
+) -> Result<StagedUpdate, String> {
+    updater.download(&app).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn updater_staged(updater: State<'_, Updater>) -> Result<Option<StagedUpdate>, String> {
+    updater.staged().map_err(|e| e.to_string())
+}
+
+/// Restarts into the staged update.
+#[tauri::command]
+pub fn updater_restart(app: AppHandle) {
+    crate::instance::mark_relaunch();
+    app.restart();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::{Arc, OnceLock};
+
+    use base64::engine::general_purpose::STANDARD;
+    use blake2::Blake2b512;
+    use ed25519_dalek::{Signer as _, SigningKey};
+
+    use crate::test_support::{self, Connection, Request, StandIn};
+
+    const ARTIFACT_PATH: &str = "/download/CommHub_2.0.0_amd64.AppImage";
+
+    /// Signs the way `tauri signer` does: prehashed minisign signatures,
+    /// base64-encoded whole.
+    struct Signer {
+        key_id: [u8; 8],
+        key: SigningKey,
+    }
+
+    impl Signer {
+        fn new(seed: u8) -> Self {
+            Self {
+                key_id: [seed; 8],
+                key: SigningKey::from_bytes(&[seed; 32]),
+            }
+        }
+
+        fn public_key(&self) -> String {
+            let mut raw = b"Ed".to_vec();
+            raw.extend_from_slice(&self.key_id);
+            raw.extend_from_slice(self.key.verifying_key().as_bytes());
+            let file = format!(
+                "untrusted comment: minisign public key\n{}\n",
+                STANDARD.encode(raw)
+            );
+            STANDARD.encode(file)
+        }
+
+        fn sign(&self, data: &[u8]) -> String {
+            let signature = self.key.sign(&Blake2b512::digest(data)).to_bytes();
+            let mut raw = b"ED".to_vec();
+            raw.extend_from_slice(&self.key_id);
+            raw.extend_from_slice(&signature);
+
+            let trusted_comment = "timestamp:0\tfile:update";
+            let mut global = signature.to_vec();
+            global.extend_from_slice(trusted_comment.as_bytes());
+            let file = format!(
+                "untrusted comment: signature\n{}\ntrusted comment: {}\n{}\n",
+                STANDARD.encode(raw),
+                trusted_comment,
+                STANDARD.encode(self.key.sign(&global).to_bytes())
+            );
+            STANDARD.encode(file)
+        }
+    }
+
+    fn sha256_hex(data: &[u8]) -> String {
+        Sha256::digest(data)
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+
+    /// What the stand-in server answers at a path.
+    enum Page {
+        Json(String),
+        File(Vec<u8>),
+        /// Headers for the whole file, then only the first half of it.
+        Truncated(Vec<u8>),
+    }
+
+    /// Set once the stand-in's address is known, since the manifest links
+    /// to it.
+    type Pages = Arc<OnceLock<HashMap<&'static str, Page>>>;
+
+    async fn serve(request: Request, mut connection: Connection, pages: Pages) {
+        let page = pages
+            .get()
+            .and_then(|pages| pages.get(request.path.as_str()));
+        let (content_type, body, sent) = match page {
+            Some(Page::Json(json)) => ("application/json", json.as_bytes(), json.len()),
+            Some(Page::File(bytes)) => ("application/octet-stream", &bytes[..], bytes.len()),
+            Some(Page::Truncated(bytes)) => {
+                ("application/octet-stream", &bytes[..], bytes.len() / 2)
+            }
+            None => return connection.not_found().await,
+        };
+        let head = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            content_type,
+            body.len()
+        );
+        let _ = connection.write(head.as_bytes()).await;
+        let _ = connection.write(&body[..sent]).await;
+    }
+
+    fn artifact() -> Vec<u8> {
+        (0..200_000u32).map(|i| (i % 251) as u8).collect()
+    }
+
+    /// A release host publishing `version` for this platform, with
+    /// `signature` and `sha256` in the manifest for the `served` artifact.
+    async fn release(
+        version: &str,
+        served: Page,
+        signature: String,
+        sha256: Option<String>,
+    ) -> StandIn {
+        let pages = Pages::default();
+        let shared = pages.clone();
+        let server =
+            StandIn::start(move |request, connection| serve(request, connection, shared.clone()))
+                .await;
+
+        let mut platforms = serde_json::Map::new();
+        platforms.insert(
+            platform_key(),
+            serde_json::json!({
+                "signature": signature,
+                "url": server.url(ARTIFACT_PATH),
+                "sha256": sha256,
+            }),
+        );
+        let manifest = serde_json::json!({
+            "version": version,
+            "notes": "Fixes",
+            "pub_date": "2026-10-01T00:00:00Z",
+            "platforms": platforms,
+        });
+        let _ = pages.set(HashMap::from([
+            ("/latest.json", Page::Json(manifest.to_string())),
+            (ARTIFACT_PATH, served),
+        ]));
+        server
+    }
+
+    fn updater(server: &StandIn, signer: &Signer, current: &str, name: &str) -> Updater {
+        let dir = test_support::root("updater", name);
+        Updater::with_settings(
+            vec![server.url("/latest.json")],
+            signer.public_key(),
+            Version::parse(current).unwrap(),
+            dir,
+        )
+        .unwrap()
+    }
+
+    /// Nothing but `staged.json` and finished artifacts may be left behind.
+    fn leftovers(updater: &Updater) -> Vec<PathBuf> {
+        let mut found = Vec::new();
+        let mut pending = vec![updater.dir.clone()];
+        while let Some(dir) = pending.pop() {
+            for entry in fs::read_dir(dir).into_iter().flatten().flatten() {
+                let path = entry.path();
+                if path.is_dir() {
+                    pending.push(path);
+                } else {
+                    found.push(path);
+                }
+            }
+        }
+        found
+    }
+
+    #[tokio::test]
+    async fn stages_a_signed_update() {
+        let signer = Signer::new(1);
+        let bytes = artifact();
+        let server = release(
+            "v2.0.0",
+            Page::File(bytes.clone()),
+            signer.sign(&bytes),
+            Some(sha256_hex(&bytes)),
+        )
+        .await;
+        let updater = updater(&server, &signer, "1.2.3", "good");
+
+        let info = updater.check().await.unwrap().unwrap();
+        assert_eq!(info.version, "2.0.0");
+        assert_eq!(info.current_version, "1.2.3");
+        assert_eq!(info.notes.as_deref(), Some("Fixes"));
+
+        let last = Mutex::new(None);
+        let staged = updater
+            .stage(|progress| *last.lock().unwrap() = Some(progress.clone()))
+            .await
+            .unwrap();
+        let last = last.into_inner().unwrap().unwrap();
+        assert_eq!(last.downloaded, bytes.len() as u64);
+        assert_eq!(last.total, Some(bytes.len() as u64));
+
+        assert_eq!(staged.version, "2.0.0");
+        assert_eq!(fs::read(&staged.path).unwrap(), bytes);
+        assert_eq!(updater.staged().unwrap().unwrap().path, staged.path);
+        assert_eq!(leftovers(&updater).len(), 2);
+
+        // Asking again reuses what's staged
+        let again = updater.stage(|_| {}).await.unwrap();
+        assert_eq!(again.path, staged.path);
+        let _ = fs::remove_dir_all(&updater.dir);
+    }
+
+    #[tokio::test]
+    async fn rejects_a_bad_signature() {
+        let signer = Signer::new(1);
+        let bytes = artifact();
+        let mut tampered = bytes.clone();
+        tampered[1000] ^= 0xff;
+
+        for (name, served, signature) in [
+            // Signed by someone else
+            ("other-key", bytes.clone(), Signer::new(2).sign(&bytes)),
+            // Changed after signing
+            ("tampered", tampered, signer.sign(&bytes)),
+            ("garbage", bytes.clone(), STANDARD.encode("not a signature")),
+        ] {
+            let server = release("2.0.0", Page::File(served), signature, None).await;
+            let updater = updater(&server, &signer, "1.2.3", name);
+            updater.check().await.unwrap().unwrap();
+
+            let result = updater.stage(|_| {}).await;
+            assert!(matches!(result, Err(Error::Signature(_))), "{}", name);
+            assert!(updater.staged().unwrap().is_none());
+            assert!(leftovers(&updater).is_empty(), "{}", name);
+            let _ = fs::remove_dir_all(&updater.dir);
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_a_wrong_checksum() {
+        let signer = Signer::new(1);
+        let bytes = artifact();
+        let server = release(
+            "2.0.0",
+            Page::File(bytes.clone()),
+            signer.sign(&bytes),
+            Some(sha256_hex(b"something else")),
+        )
+        .await;
+        let updater = updater(&server, &signer, "1.2.3", "checksum");
+        updater.check().await.unwrap().unwrap();
+
+        let result = updater.stage(|_| {}).await;
+        assert!(matches!(result, Err(Error::Checksum)));
+        assert!(updater.staged().unwrap().is_none());
+        assert!(leftovers(&updater).is_empty());
+        let _ = fs::remove_dir_all(&updater.dir);
+    }
+
+    #[tokio::test]
+    async fn rejects_a_truncated_artifact() {
+        let signer = Signer::new(1);
+        let bytes = artifact();
+        let server = release(
+            "2.0.0",
+            Page::Truncated(bytes.clone()),
+            signer.sign(&bytes),
+            None,
+        )
+        .await;
+        let updater = updater(&server, &signer, "1.2.3", "truncated");
+        updater.check().await.unwrap().unwrap();
+
+        assert!(updater.stage(|_| {}).await.is_err());
+        assert!(updater.staged().unwrap().is_none());
+        assert!(leftovers(&updater).is_empty());
+
+        // Cut short and served as if complete, only the signature catches it
+        let half = bytes[..bytes.len() / 2].to_vec();
+        let server = release("2.0.0", Page::File(half), signer.sign(&bytes), None).await;
+        let updater = self::updater(&server, &signer, "1.2.3", "short");
+        updater.check().await.unwrap().unwrap();
+        let result = updater.stage(|_| {}).await;
+        assert!(matches!(result, Err(Error::Signature(_))));
+        assert!(leftovers(&updater).is_empty());
+        let _ = fs::remove_dir_all(&updater.dir);
+    }
+
+    #[tokio::test]
+    async fn ignores_a_downgrade() {
+        let signer = Signer::new(1);
+        let bytes = artifact();
+        for (published, current, offered) in [
+            ("1.0.0", "1.2.3", false),
+            ("1.2.3", "1.2.3", false),
+            ("1.3.0-beta.1", "1.3.0", false),
+            ("1.3.0", "1.3.0-beta.1", true),
+            ("1.3.0-beta.2", "1.3.0-beta.1", true),
+        ] {
+            let server = release(
+                published,
+                Page::File(bytes.clone()),
+                signer.sign(&bytes),
+                None,
+            )
+            .await;
+            let updater = updater(&server, &signer, current, "downgrade");
+            let update = updater.check().await.unwrap();
+            assert_eq!(update.is_some(), offered, "{} from {}", published, current);
+            if !offered {
+                assert!(matches!(updater.stage(|_| {}).await, Err(Error::NoUpdate)));
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn drops_staged_updates_that_no_longer_apply() {
+        let signer = Signer::new(1);
+        let bytes = artifact();
+        let server = release(
+            "2.0.0",
+            Page::File(bytes.clone()),
+            signer.sign(&bytes),
+            Some(sha256_hex(&bytes)),
+        )
+        .await;
+
+        // Damaged on disk after it was staged
+        let updater = updater(&server, &signer, "1.2.3", "damaged");
+        updater.check().await.unwrap().unwrap();
+        let staged = updater.stage(|_| {}).await.unwrap();
+        fs::write(&staged.path, b"damaged").unwrap();
+        assert!(updater.apply_staged().is_err());
+        assert!(updater.staged().unwrap().is_none());
+        assert!(leftovers(&updater).is_empty());
+
+        // Staged by an older build that has since been replaced
+        updater.check().await.unwrap().unwrap();
+        updater.stage(|_| {}).await.unwrap();
+        let installed = Updater::with_settings(
+            Vec::new(),
+            signer.public_key(),
+            Version::new(2, 0, 0),
+            updater.dir.clone(),
+        )
+        .unwrap();
+        assert!(!installed.apply_staged().unwrap());
+        assert!(updater.staged().unwrap().is_none());
+        assert!(leftovers(&updater).is_empty());
+
+        // A staged.json that no longer parses
+        updater.check().await.unwrap().unwrap();
+        updater.stage(|_| {}).await.unwrap();
+        fs::write(updater.staged_path(), b"{\"version\":").unwrap();
+        assert!(matches!(updater.apply_staged(), Err(Error::Json(_))));
+        assert!(updater.staged().unwrap().is_none());
+        assert!(leftovers(&updater).is_empty());
+    }
+
+    #[tokio::test]
+    async fn reports_updates_disabled_without_a_public_key() {
+        let signer = Signer::new(1);
+        let bytes = artifact();
+        let server = release(
+            "2.0.0",
+            Page::File(bytes.clone()),
+            signer.sign(&bytes),
+            None,
+        )
+        .await;
+
+        let dir = test_support::root("updater", "nokey");
+        let updater = Updater::with_settings(
+            vec![server.url("/latest.json")],
+            String::new(),
+            Version::new(1, 2, 3),
+            dir,
+        )
+        .unwrap();
+        assert!(matches!(updater.check().await, Err(Error::Disabled)));
+        assert!(matches!(updater.stage(|_| {}).await, Err(Error::NoUpdate)));
+    }
+
+    #[test]
+    fn artifact_names_are_safe_file_names() {
+        assert_eq!(
+            artifact_name("https://example.com/v2/CommHub_2.0.0_x64.msi?token=1"),
+            "CommHub_2.0.0_x64.msi"
+        );
+        assert_eq!(artifact_name("https://example.com/a/../.."), "update");
+        assert_eq!(artifact_name("https://example.com/.hidden"), "update");
+        assert_eq!(artifact_name("https://example.com/"), "update");
+    }
+
+    #[test]
+    fn needs_a_public_key() {
+        assert!(matches!(decode_public_key(" "), Err(Error::NoPublicKey)));
+        assert!(matches!(
+            decode_public_key("bm90IGEga2V5"),
+            Err(Error::Signature(_))
+        ));
+        assert!(decode_public_key(&Signer::new(3).public_key()).is_ok());
+    }
+}
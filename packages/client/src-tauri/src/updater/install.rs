@@ -0,0 +1,152 @@
+//! Hands a verified artifact to the platform installer.
+
+use std::path::Path;
+#[cfg(target_os = "macos")]
+use std::path::PathBuf;
+#[cfg(any(windows, target_os = "linux", target_os = "macos"))]
+use std::process::Command;
+
+use super::Error;
+
+pub fn extension(path: &Path) -> String {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase()
+}
+
+/// Whether `launch` can install an artifact with this name here.
+#[cfg(windows)]
+pub fn supported(path: &Path) -> bool {
+    matches!(extension(path).as_str(), "msi" | "exe")
+}
+
+/// Only AppImages update themselves; distro packages are left to the package
+/// manager.
+#[cfg(target_os = "linux")]
+pub fn supported(path: &Path) -> bool {
+    extension(path) == "appimage" && std::env::var_os("APPIMAGE").is_some()
+}
+
+/// The `.app.tar.gz` archive Tauri bundles for the updater, when running
+/// from an app bundle it can replace.
+#[cfg(target_os = "macos")]
+pub fn supported(path: &Path) -> bool {
+    is_app_archive(path) && current_bundle().is_some()
+}
+
+#[cfg(not(any(windows, target_os = "linux", target_os = "macos")))]
+pub fn supported(_path: &Path) -> bool {
+    false
+}
+
+#[cfg(windows)]
+pub fn launch(path: &Path) -> Result<(), Error> {
+    match extension(path).as_str() {
+        // AUTOLAUNCHAPP is read by Tauri's WiX template to reopen the app
+        "msi" => Command::new("msiexec.exe")
+            .arg("/i")
+            .arg(path)
+            .args(["/passive", "AUTOLAUNCHAPP=True"])
+            .spawn()?,
+        "exe" => Command::new(path).arg("/P").spawn()?,
+        other => return Err(Error::Unsupported(other.to_string())),
+    };
+    Ok(())
+}
+
+/// Replaces the running AppImage in place and starts the new one.
+#[cfg(target_os = "linux")]
+pub fn launch(path: &Path) -> Result<(), Error> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let ext = extension(path);
+    let target = match std::env::var_os("APPIMAGE") {
+        Some(target) if ext == "appimage" => std::path::PathBuf::from(target),
+        // Distro packages are updated by the package manager
+        _ => return Err(Error::Unsupported(ext)),
+    };
+
+    let tmp = target.with_extension("new");
+    std::fs::copy(path, &tmp)?;
+    std::fs::set_permissions(&tmp, std::fs::Permissions::from_mode(0o755))?;
+    std::fs::rename(&tmp, &target)?;
+    Command::new(&target)
+        .env(crate::instance::RELAUNCH_ENV, "1")
+        .spawn()?;
+    Ok(())
+}
+
+/// Unpacks the new bundle beside the running one, swaps the two and starts
+/// the new one.
+#[cfg(target_os = "macos")]
+pub fn launch(path: &Path) -> Result<(), Error> {
+    let unsupported = || Error::Unsupported(extension(path));
+    if !is_app_archive(path) {
+        return Err(unsupported());
+    }
+    let bundle = current_bundle().ok_or_else(unsupported)?;
+    let exe = std::env::current_exe()?;
+    let parent = bundle.parent().ok_or_else(unsupported)?;
+
+    // On the bundle's volume, so the swap is a pair of renames
+    let unpacked = parent.join(".commhub-update");
+    let _ = std::fs::remove_dir_all(&unpacked);
+    std::fs::create_dir_all(&unpacked)?;
+    let result = swap_bundle(path, &bundle, &unpacked);
+    let _ = std::fs::remove_dir_all(&unpacked);
+    result?;
+
+    Command::new(&exe)
+        .env(crate::instance::RELAUNCH_ENV, "1")
+        .spawn()?;
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+fn swap_bundle(archive: &Path, bundle: &Path, unpacked: &Path) -> Result<(), Error> {
+    let status = Command::new("/usr/bin/tar")
+        .arg("-xzf")
+        .arg(archive)
+        .arg("-C")
+        .arg(unpacked)
+        .status()?;
+    if !status.success() {
+        return Err(std::io::Error::other(format!("tar exited with {}", status)).into());
+    }
+    let update = std::fs::read_dir(unpacked)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .find(|path| path.extension().is_some_and(|ext| ext == "app"))
+        .ok_or_else(|| std::io::Error::other("the update archive has no app bundle"))?;
+
+    let previous = unpacked.join("previous.app");
+    std::fs::rename(bundle, &previous)?;
+    if let Err(e) = std::fs::rename(&update, bundle) {
+        // Put the running app back rather than leave nothing installed
+        let _ = std::fs::rename(&previous, bundle);
+        return Err(e.into());
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+fn is_app_archive(path: &Path) -> bool {
+    path.file_name()
+        .is_some_and(|name| name.to_string_lossy().ends_with(".app.tar.gz"))
+}
+
+/// The `.app` the running binary is in, from `CommHub.app/Contents/MacOS`.
+#[cfg(target_os = "macos")]
+fn current_bundle() -> Option<PathBuf> {
+    let exe = std::env::current_exe().ok()?;
+    let bundle = exe.ancestors().nth(3)?;
+    bundle
+        .extension()
+        .is_some_and(|ext| ext == "app")
+        .then(|| bundle.to_path_buf())
+}
+
+#[cfg(not(any(windows, target_os = "linux", target_os = "macos")))]
+pub fn launch(path: &Path) -> Result<(), Error> {
+    Err(Error::Unsupported(extension(path)))
+}
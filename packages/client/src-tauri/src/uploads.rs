@@ -0,0 +1,398 @@
+//! Attachment uploads, run by the backend from files on disk.
+//!
+//! Uploads queue behind a small concurrency limit. Each reports
+//! `upload://progress` to the window that started it and ends with
+//! `upload://finished`, carrying the attachment, the error or the
+//! cancellation. Failed attempts are retried with backoff and, where the
+//! server supports it, resumed rather than restarted (see [`transfer`]).
+//!
+//! Images are first resized and stripped of metadata (see [`images`]); the
+//! result goes up from a temporary file in place of the original, and
+//! `upload://prepared` carries its thumbnail and blurhash. An image whose
+//! metadata can't be removed fails rather than going up with it. The size limit
+//! applies to what is actually sent, so a large photo that shrinks under it
+//! still goes up.
+
+mod images;
+mod transfer;
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use base64::Engine as _;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::{AppHandle, Manager, PackageInfo, State, Window};
+use tokio::sync::Semaphore;
+use tokio_util::sync::CancellationToken;
+use url::Url;
+
+use crate::file_drop::{self, FileDrops};
+use crate::vault::Vault;
+pub use images::ImageOptions;
+use images::Outcome;
+use transfer::{Retry, Transfer, Upload};
+
+const MAX_CONCURRENT: usize = 3;
+const TEMP_DIR: &str = "commhub-uploads";
+/// The server the webview was built against (see build.rs); the token is
+/// never sent anywhere else.
+const API_URL: &str = env!("COMMHUB_API_URL");
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("{name} {problem}")]
+    Invalid {
+        name: String,
+        problem: file_drop::Problem,
+    },
+    #[error("dropped file is no longer available")]
+    UnknownDrop,
+    #[error("not signed in")]
+    NoToken,
+    #[error("invalid upload url: {0}")]
+    Url(#[from] url::ParseError),
+    #[error(transparent)]
+    Vault(#[from] crate::vault::Error),
+    #[error(transparent)]
+    Transfer(#[from] transfer::Error),
+    #[error(transparent)]
+    Image(#[from] images::Error),
+    #[error("failed to prepare image: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("failed to prepare image: {0}")]
+    Runtime(#[from] tauri::Error),
+}
+
+/// Where an upload goes; the fields of the `/uploads` form.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadTarget {
+    pub channel_id: Option<i64>,
+    pub receiver_id: Option<i64>,
+}
+
+/// Payload of `upload://progress`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadProgress {
+    pub id: u64,
+    pub sent: u64,
+    pub total: u64,
+}
+
+/// Payload of `upload://prepared`, once an image has been prepared.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadPrepared {
+    pub id: u64,
+    pub name: String,
+    pub size: u64,
+    pub width: u32,
+    pub height: u32,
+    /// A data URL.
+    pub thumbnail: String,
+    pub blurhash: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum UploadOutcome {
+    Done { attachment: Value },
+    Failed { error: String },
+    Cancelled,
+}
+
+/// Payload of `upload://finished`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadFinished {
+    pub id: u64,
+    #[serde(flatten)]
+    pub outcome: UploadOutcome,
+}
+
+pub struct Uploads {
+    client: reqwest::Client,
+    permits: Arc<Semaphore>,
+    next_id: AtomicU64,
+    active: Mutex<HashMap<u64, CancellationToken>>,
+    // Whether each API server takes resumable uploads, asked once
+    resumable: Mutex<HashMap<String, bool>>,
+}
+
+impl Uploads {
+    pub fn new(package_info: &PackageInfo) -> Self {
+        let client = reqwest::Client::builder()
+            .user_agent(format!("CommHub/{}", package_info.version))
+            .connect_timeout(Duration::from_secs(15))
+            .build()
+            .unwrap_or_default();
+        // Prepared images left behind if the app quit mid-upload
+        let _ = fs::remove_dir_all(std::env::temp_dir().join(TEMP_DIR));
+        Self {
+            client,
+            permits: Arc::new(Semaphore::new(MAX_CONCURRENT)),
+            next_id: AtomicU64::new(1),
+            active: Mutex::new(HashMap::new()),
+            resumable: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Validates the file and queues its upload. Returns the upload's id;
+    /// the outcome arrives as `upload://finished`.
+    pub fn start(
+        &self,
+        window: Window,
+        path: PathBuf,
+        target: &UploadTarget,
+        token: String,
+        images: ImageOptions,
+    ) -> Result<u64, Error> {
+        // Dropped files were checked already, but may have changed since
+        let file = file_drop::inspect(&path).map_err(|problem| Error::Invalid {
+            name: path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            problem,
+        })?;
+        let mut fields = Vec::new();
+        if let Some(channel_id) = target.channel_id {
+            fields.push(("channelId", channel_id.to_string()));
+        }
+        if let Some(receiver_id) = target.receiver_id {
+            fields.push(("receiverId", receiver_id.to_string()));
+        }
+        let upload = Upload {
+            path,
+            name: file.name,
+            mime_type: file.mime,
+            size: file.size,
+            fields,
+        };
+
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let transfer = Transfer {
+            client: self.client.clone(),
+            api_url: Url::parse(API_URL)?,
+            token,
+            retry: Retry::default(),
+            cancel: CancellationToken::new(),
+        };
+        self.active
+            .lock()
+            .unwrap()
+            .insert(id, transfer.cancel.clone());
+
+        tauri::async_runtime::spawn(async move {
+            let uploads = window.state::<Uploads>();
+            let name = upload.name.clone();
+            let outcome = match uploads.run(&window, id, &transfer, upload, images).await {
+                Ok(attachment) => UploadOutcome::Done { attachment },
+                Err(Error::Transfer(transfer::Error::Cancelled)) => UploadOutcome::Cancelled,
+                Err(e) => {
+                    log!("[Uploads] Failed to upload {}: {}", name, e);
+                    UploadOutcome::Failed {
+                        error: e.to_string(),
+                    }
+                }
+            };
+            uploads.active.lock().unwrap().remove(&id);
+            let _ = window.emit("upload://finished", UploadFinished { id, outcome });
+        });
+        Ok(id)
+    }
+
+    async fn run(
+        &self,
+        window: &Window,
+        id: u64,
+        transfer: &Transfer,
+        upload: Upload,
+        images: ImageOptions,
+    ) -> Result<Value, Error> {
+        let _permit = tokio::select! {
+            _ = transfer.cancel.cancelled() => return Err(transfer::Error::Cancelled.into()),
+            permit = self.permits.clone().acquire_owned() => permit,
+        };
+
+        let (upload, _temp_file) = if upload.mime_type.starts_with("image/") {
+            let original = upload.clone();
+            let prepared =
+                tauri::async_runtime::spawn_blocking(move || prepare_image(id, original, &images))
+                    .await?;
+            let (upload, temp_file, payload) = prepared.map_err(|e| {
+                log!("[Uploads] Failed to prepare {}: {}", upload.name, e);
+                e
+            })?;
+            if let Some(payload) = payload {
+                let _ = window.emit("upload://prepared", payload);
+            }
+            (upload, temp_file)
+        } else {
+            (upload, None)
+        };
+        if upload.size > file_drop::MAX_FILE_SIZE {
+            return Err(Error::Invalid {
+                name: upload.name,
+                problem: file_drop::Problem::TooLarge,
+            });
+        }
+
+        let server = transfer.api_url.to_string();
+        let known = self.resumable.lock().unwrap().get(&server).copied();
+        let resumable = match known {
+            Some(resumable) => resumable,
+            None => {
+                let resumable = transfer.negotiate().await;
+                self.resumable.lock().unwrap().insert(server, resumable);
+                resumable
+            }
+        };
+
+        // One event per percent is plenty for a progress bar
+        let total = upload.size;
+        let window = window.clone();
+        let last_percent = AtomicU64::new(u64::MAX);
+        let progress = Arc::new(move |sent: u64| {
+            let percent = sent * 100 / total.max(1);
+            if last_percent.swap(percent, Ordering::Relaxed) != percent {
+                let _ = window.emit("upload://progress", UploadProgress { id, sent, total });
+            }
+        });
+        Ok(transfer.run(&upload, resumable, progress).await?)
+    }
+
+    pub fn cancel(&self, id: u64) {
+        if let Some(cancel) = self.active.lock().unwrap().get(&id) {
+            cancel.cancel();
+        }
+    }
+
+    pub fn cancel_all(&self) {
+        for cancel in self.active.lock().unwrap().values() {
+            cancel.cancel();
+        }
+    }
+}
+
+/// Removed once the upload it was written for is over.
+struct TempFile(PathBuf);
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+/// Writes the prepared or stripped image to a temporary file and describes
+/// the upload of it instead. Only a prepared image has a payload for
+/// `upload://prepared`; formats without metadata go up untouched.
+fn prepare_image(
+    id: u64,
+    upload: Upload,
+    options: &ImageOptions,
+) -> Result<(Upload, Option<TempFile>, Option<UploadPrepared>), Error> {
+    let data = fs::read(&upload.path)?;
+    let prepared = match images::prepare(&data, &upload.mime_type, options)? {
+        Outcome::Prepared(prepared) => prepared,
+        Outcome::Stripped(stripped) => {
+            let extension = Path::new(&upload.name)
+                .extension()
+                .map(|ext| ext.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let (path, temp_file) = write_temp(id, &extension, &stripped)?;
+            let upload = Upload {
+                path,
+                size: stripped.len() as u64,
+                ..upload
+            };
+            return Ok((upload, Some(temp_file), None));
+        }
+        Outcome::Unchanged => return Ok((upload, None, None)),
+    };
+    let image = prepared.image;
+    let extension = image.format.extension();
+    let (path, temp_file) = write_temp(id, extension, &image.bytes)?;
+
+    let stem = Path::new(&upload.name)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let name = format!("{}.{}", stem, extension);
+    let size = image.bytes.len() as u64;
+    let thumbnail = format!(
+        "data:{};base64,{}",
+        prepared.thumbnail.format.mime_type(),
+        base64::engine::general_purpose::STANDARD.encode(&prepared.thumbnail.bytes)
+    );
+    let payload = UploadPrepared {
+        id,
+        name: name.clone(),
+        size,
+        width: image.width,
+        height: image.height,
+        thumbnail,
+        blurhash: prepared.blurhash,
+    };
+    let upload = Upload {
+        path,
+        name,
+        mime_type: image.format.mime_type().to_string(),
+        size,
+        ..upload
+    };
+    Ok((upload, Some(temp_file), Some(payload)))
+}
+
+fn write_temp(id: u64, extension: &str, bytes: &[u8]) -> std::io::Result<(PathBuf, TempFile)> {
+    let dir = std::env::temp_dir().join(TEMP_DIR);
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("{}-{}.{}", std::process::id(), id, extension));
+    fs::write(&path, bytes)?;
+    Ok((path.clone(), TempFile(path)))
+}
+
+/// The token the webview passed, or the one saved in the vault.
+fn resolve_token(app: &AppHandle, token: Option<String>) -> Result<String, Error> {
+    match token {
+        Some(token) => Ok(token),
+        None => app
+            .state::<Vault>()
+            .load()?
+            .map(|record| record.token)
+            .ok_or(Error::NoToken),
+    }
+}
+
+/// Uploads a file from a `file-drop://preview` or the file picker. Only
+/// files the backend registered can be uploaded, never a path the webview
+/// names.
+#[tauri::command]
+pub fn upload_dropped(
+    window: Window,
+    uploads: State<'_, Uploads>,
+    id: u64,
+    target: UploadTarget,
+    token: Option<String>,
+    images: Option<ImageOptions>,
+) -> Result<u64, String> {
+    let token = resolve_token(&window.app_handle(), token).map_err(|e| e.to_string())?;
+    let path = window
+        .state::<FileDrops>()
+        .take(id)
+        .ok_or_else(|| Error::UnknownDrop.to_string())?;
+    uploads
+        .start(window, path, &target, token, images.unwrap_or_default())
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn upload_cancel(uploads: State<'_, Uploads>, id: u64) {
+    uploads.cancel(id);
+}
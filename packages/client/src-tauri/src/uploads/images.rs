@@ -0,0 +1,713 @@
+//! Photos are prepared before they're uploaded. They're turned upright by
+//! their EXIF orientation, shrunk to fit [`ImageOptions::max_dimension`] and
+//! re-encoded until they fit [`ImageOptions::target_size`], with a thumbnail
+//! and a blurhash for the composer to show meanwhile.
+//!
+//! Metadata, GPS position included, is stripped by the re-encoding itself:
+//! only pixels are decoded, and the `image` encoders write nothing else.
+//! Animations, formats the `image` crate can't write and images it can't
+//! decode or encode have their metadata cut out instead (see [`metadata`]),
+//! and can't be uploaded if that isn't possible either.
+
+mod metadata;
+
+use std::io::Cursor;
+
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::{CompressionType, FilterType as PngFilter, PngDecoder, PngEncoder};
+use image::codecs::webp::{WebPDecoder, WebPEncoder};
+use image::imageops::FilterType;
+use image::io::{Limits, Reader as ImageReader};
+use image::{DynamicImage, GenericImageView, ImageEncoder, ImageFormat};
+use serde::Deserialize;
+
+// Decoding is refused past this, however small the file
+const MAX_SOURCE_DIMENSION: u32 = 12_000;
+/// Tried in turn on photos until one fits the target size.
+const JPEG_QUALITIES: &[u8] = &[85, 75, 65, 50];
+/// Low bits dropped from each color channel, in turn, to fit screenshots
+/// and transparent images in the target size as WebP: lossy, but without
+/// JPEG's ringing around sharp edges, and alpha is kept exactly.
+const NEAR_LOSSLESS_BITS: &[u8] = &[1, 2, 3];
+const THUMBNAIL_QUALITY: u8 = 80;
+/// How much each round shrinks an image that no quality could fit.
+const SHRINK_FACTOR: f64 = 0.75;
+/// Images aren't shrunk below this to meet the target size.
+const MIN_DIMENSION: u32 = 320;
+const BLURHASH_SOURCE: u32 = 64;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("image could not be encoded: {0}")]
+    Encode(image::ImageError),
+    #[error("blurhash failed: {0}")]
+    Blurhash(#[from] blurhash::Error),
+    #[error(transparent)]
+    Metadata(#[from] metadata::Error),
+}
+
+/// Mirrors the `images` argument built in services/uploads.ts.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ImageOptions {
+    /// The longest side an uploaded image keeps.
+    pub max_dimension: u32,
+    /// Bytes the encoded image should fit in.
+    pub target_size: u64,
+    pub thumbnail_size: u32,
+}
+
+impl Default for ImageOptions {
+    fn default() -> Self {
+        Self {
+            max_dimension: 2560,
+            target_size: 4 * 1024 * 1024,
+            thumbnail_size: 256,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Jpeg,
+    Png,
+    WebP,
+}
+
+impl Format {
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            Format::Jpeg => "image/jpeg",
+            Format::Png => "image/png",
+            Format::WebP => "image/webp",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Jpeg => "jpg",
+            Format::Png => "png",
+            Format::WebP => "webp",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Encoded {
+    pub bytes: Vec<u8>,
+    pub format: Format,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Encoded {
+    fn size(&self) -> u64 {
+        self.bytes.len() as u64
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Prepared {
+    pub image: Encoded,
+    pub thumbnail: Encoded,
+    pub blurhash: String,
+}
+
+/// What to upload in place of an image.
+#[derive(Debug, Clone)]
+pub enum Outcome {
+    Prepared(Prepared),
+    /// The original without its metadata, for images that couldn't be
+    /// re-encoded.
+    Stripped(Vec<u8>),
+    /// A format with nowhere to keep metadata, which goes up as it is.
+    Unchanged,
+}
+
+/// Prepares an image for upload. One that can't be re-encoded, e.g. a
+/// panorama past [`MAX_SOURCE_DIMENSION`], only has its metadata removed;
+/// if even that fails, it isn't uploaded.
+pub fn prepare(data: &[u8], mime_type: &str, options: &ImageOptions) -> Result<Outcome, Error> {
+    match reencode(data, options) {
+        Ok(Some(prepared)) => return Ok(Outcome::Prepared(prepared)),
+        Ok(None) => {}
+        Err(e) => log!("[Uploads] Failed to re-encode image: {}", e),
+    }
+    Ok(match metadata::strip(data, mime_type)? {
+        Some(stripped) => Outcome::Stripped(stripped),
+        None => Outcome::Unchanged,
+    })
+}
+
+/// Decodes, turns, shrinks and re-encodes an image. `None` for animations,
+/// formats not handled here and images that can't be decoded.
+fn reencode(data: &[u8], options: &ImageOptions) -> Result<Option<Prepared>, Error> {
+    let format = match image::guess_format(data) {
+        Ok(
+            format @ (ImageFormat::Jpeg | ImageFormat::Png | ImageFormat::WebP | ImageFormat::Bmp),
+        ) => format,
+        _ => return Ok(None),
+    };
+    if is_animated(data, format) {
+        return Ok(None);
+    }
+
+    let image = match decode(data, format) {
+        Ok(image) => image,
+        Err(e) => {
+            log!("[Uploads] Failed to decode image: {}", e);
+            return Ok(None);
+        }
+    };
+    let image = orient(image, orientation(data));
+    let image = downscale(image, options.max_dimension);
+    let thumbnail = thumbnail(&image, options.thumbnail_size)?;
+    let blurhash = blurhash(&image)?;
+    // Screenshots and the like keep their sharp edges if they can
+    let lossless = format != ImageFormat::Jpeg;
+    let image = encode(image, options.target_size, lossless)?;
+    Ok(Some(Prepared {
+        image,
+        thumbnail,
+        blurhash,
+    }))
+}
+
+fn is_animated(data: &[u8], format: ImageFormat) -> bool {
+    match format {
+        ImageFormat::Png => PngDecoder::new(Cursor::new(data)).is_ok_and(|png| png.is_apng()),
+        ImageFormat::WebP => {
+            WebPDecoder::new(Cursor::new(data)).is_ok_and(|webp| webp.has_animation())
+        }
+        _ => false,
+    }
+}
+
+fn decode(data: &[u8], format: ImageFormat) -> image::ImageResult<DynamicImage> {
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_SOURCE_DIMENSION);
+    limits.max_image_height = Some(MAX_SOURCE_DIMENSION);
+    let mut reader = ImageReader::with_format(Cursor::new(data), format);
+    reader.limits(limits);
+    reader.decode()
+}
+
+/// The EXIF orientation, 1 (upright) to 8. 1 when there's none.
+fn orientation(data: &[u8]) -> u32 {
+    exif::Reader::new()
+        .read_from_container(&mut Cursor::new(data))
+        .ok()
+        .and_then(|exif| {
+            exif.get_field(exif::Tag::Orientation, exif::In::PRIMARY)?
+                .value
+                .get_uint(0)
+        })
+        .unwrap_or(1)
+}
+
+/// Turns an image upright. Orientations are numbered as in the EXIF spec.
+fn orient(image: DynamicImage, orientation: u32) -> DynamicImage {
+    match orientation {
+        2 => image.fliph(),
+        3 => image.rotate180(),
+        4 => image.flipv(),
+        5 => image.rotate90().fliph(),
+        6 => image.rotate90(),
+        7 => image.rotate270().fliph(),
+        8 => image.rotate270(),
+        _ => image,
+    }
+}
+
+fn downscale(image: DynamicImage, max_dimension: u32) -> DynamicImage {
+    let (width, height) = image.dimensions();
+    if width.max(height) <= max_dimension {
+        return image;
+    }
+    image.resize(max_dimension, max_dimension, FilterType::Lanczos3)
+}
+
+fn is_transparent(image: &DynamicImage) -> bool {
+    image.color().has_alpha() && image.to_rgba8().pixels().any(|pixel| pixel[3] < u8::MAX)
+}
+
+/// Encodes an image to fit `target_size`, shrinking it if no format or
+/// quality manages that. Lossless sources and transparent images try PNG
+/// and WebP, then WebP with less color precision; opaque ones fall back to
+/// JPEG after that. Photos go straight to JPEG.
+fn encode(image: DynamicImage, target_size: u64, lossless: bool) -> Result<Encoded, Error> {
+    let transparent = is_transparent(&image);
+    // An alpha channel with nothing in it would only cost bytes
+    let mut image = if transparent {
+        DynamicImage::ImageRgba8(image.to_rgba8())
+    } else {
+        DynamicImage::ImageRgb8(image.to_rgb8())
+    };
+
+    loop {
+        let mut best: Option<Encoded> = None;
+        let fits =
+            |best: &Option<Encoded>| best.as_ref().is_some_and(|best| best.size() <= target_size);
+        if transparent || lossless {
+            for format in [Format::Png, Format::WebP] {
+                best = Some(smaller(best, write(&image, format, 0)?));
+            }
+            for &bits in NEAR_LOSSLESS_BITS {
+                if fits(&best) {
+                    break;
+                }
+                best = Some(smaller(
+                    best,
+                    write(&reduce(&image, bits), Format::WebP, 0)?,
+                ));
+            }
+        }
+        if !transparent {
+            for &quality in JPEG_QUALITIES {
+                if fits(&best) {
+                    break;
+                }
+                best = Some(smaller(best, write(&image, Format::Jpeg, quality)?));
+            }
+        }
+        let best = best.expect("every image is tried in at least one format");
+
+        let (width, height) = image.dimensions();
+        if best.size() <= target_size || width.max(height) <= MIN_DIMENSION {
+            return Ok(best);
+        }
+        image = image.resize_exact(shrink(width), shrink(height), FilterType::Lanczos3);
+    }
+}
+
+/// Drops the low `bits` of every color channel, rounding to the middle of
+/// what they covered, which leaves far less for a lossless encoder to
+/// store. Alpha is untouched.
+fn reduce(image: &DynamicImage, bits: u8) -> DynamicImage {
+    let mask = u8::MAX << bits;
+    let middle = (1u8 << bits) >> 1;
+    let channel = |value: u8| (value & mask) | middle;
+    match image {
+        DynamicImage::ImageRgba8(rgba) => {
+            let mut rgba = rgba.clone();
+            for pixel in rgba.pixels_mut() {
+                for value in &mut pixel.0[..3] {
+                    *value = channel(*value);
+                }
+            }
+            DynamicImage::ImageRgba8(rgba)
+        }
+        _ => {
+            let mut rgb = image.to_rgb8();
+            for value in rgb.iter_mut() {
+                *value = channel(*value);
+            }
+            DynamicImage::ImageRgb8(rgb)
+        }
+    }
+}
+
+fn shrink(dimension: u32) -> u32 {
+    ((dimension as f64 * SHRINK_FACTOR) as u32).max(1)
+}
+
+fn smaller(best: Option<Encoded>, candidate: Encoded) -> Encoded {
+    match best {
+        Some(best) if best.size() <= candidate.size() => best,
+        _ => candidate,
+    }
+}
+
+/// Writes an image that's already 8-bit RGB or RGBA. `quality` is only
+/// used by JPEG; the `image` crate only writes lossless WebP, which
+/// [`reduce`] makes up for.
+fn write(image: &DynamicImage, format: Format, quality: u8) -> Result<Encoded, Error> {
+    let (width, height) = image.dimensions();
+    let color = image.color();
+    let pixels = image.as_bytes();
+    let mut bytes = Vec::new();
+    match format {
+        Format::Jpeg => JpegEncoder::new_with_quality(&mut bytes, quality)
+            .write_image(pixels, width, height, color),
+        Format::Png => {
+            PngEncoder::new_with_quality(&mut bytes, CompressionType::Default, PngFilter::Adaptive)
+                .write_image(pixels, width, height, color)
+        }
+        Format::WebP => WebPEncoder::new_lossless(&mut bytes).encode(pixels, width, height, color),
+    }
+    .map_err(Error::Encode)?;
+    Ok(Encoded {
+        bytes,
+        format,
+        width,
+        height,
+    })
+}
+
+fn thumbnail(image: &DynamicImage, size: u32) -> Result<Encoded, Error> {
+    let thumbnail = image.thumbnail(size, size);
+    if is_transparent(&thumbnail) {
+        write(
+            &DynamicImage::ImageRgba8(thumbnail.to_rgba8()),
+            Format::Png,
+            0,
+        )
+    } else {
+        write(
+            &DynamicImage::ImageRgb8(thumbnail.to_rgb8()),
+            Format::Jpeg,
+            THUMBNAIL_QUALITY,
+        )
+    }
+}
+
+/// A blurhash with more components along the longer side.
+fn blurhash(image: &DynamicImage) -> Result<String, Error> {
+    let small = image.thumbnail(BLURHASH_SOURCE, BLURHASH_SOURCE).to_rgba8();
+    let (width, height) = small.dimensions();
+    let (x, y) = if width >= height { (4, 3) } else { (3, 4) };
+    Ok(blurhash::encode(x, y, width, height, small.as_raw())?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use image::{Rgb, RgbImage, Rgba, RgbaImage};
+
+    fn options(max_dimension: u32, target_size: u64) -> ImageOptions {
+        ImageOptions {
+            max_dimension,
+            target_size,
+            thumbnail_size: 64,
+        }
+    }
+
+    /// Noise over a gradient, which compresses about as badly as a photo.
+    pub(super) fn photo(width: u32, height: u32) -> RgbImage {
+        let mut seed = 0x2545_f491_u32;
+        RgbImage::from_fn(width, height, |x, y| {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            let noise = (seed >> 24) as u8 / 4;
+            Rgb([
+                (x * 255 / width) as u8 / 2 + noise,
+                (y * 255 / height) as u8 / 2 + noise,
+                128 + noise,
+            ])
+        })
+    }
+
+    /// A red circle on a transparent square.
+    fn sticker(size: u32) -> RgbaImage {
+        let centre = size as i64 / 2;
+        RgbaImage::from_fn(size, size, |x, y| {
+            let (dx, dy) = (x as i64 - centre, y as i64 - centre);
+            if dx * dx + dy * dy < centre * centre {
+                Rgba([220, 30, 30, 255])
+            } else {
+                Rgba([0, 0, 0, 0])
+            }
+        })
+    }
+
+    fn encode_as(image: DynamicImage, format: ImageFormat) -> Vec<u8> {
+        let mut bytes = Cursor::new(Vec::new());
+        image.write_to(&mut bytes, format).unwrap();
+        bytes.into_inner()
+    }
+
+    pub(super) fn jpeg(image: RgbImage) -> Vec<u8> {
+        encode_as(DynamicImage::ImageRgb8(image), ImageFormat::Jpeg)
+    }
+
+    /// A JPEG carrying an orientation and a GPS position, as phones write.
+    pub(super) fn jpeg_with_exif(image: RgbImage, orientation: u16) -> Vec<u8> {
+        use exif::{Field, In, Rational, Tag, Value};
+
+        let degrees = |d: u32| Rational { num: d, denom: 1 };
+        let fields = [
+            Field {
+                tag: Tag::Orientation,
+                ifd_num: In::PRIMARY,
+                value: Value::Short(vec![orientation]),
+            },
+            Field {
+                tag: Tag::GPSLatitudeRef,
+                ifd_num: In::PRIMARY,
+                value: Value::Ascii(vec![b"N".to_vec()]),
+            },
+            Field {
+                tag: Tag::GPSLatitude,
+                ifd_num: In::PRIMARY,
+                value: Value::Rational(vec![degrees(51), degrees(30), degrees(12)]),
+            },
+        ];
+        let mut writer = exif::experimental::Writer::new();
+        for field in &fields {
+            writer.push_field(field);
+        }
+        let mut tiff = Cursor::new(Vec::new());
+        writer.write(&mut tiff, false).unwrap();
+        let tiff = tiff.into_inner();
+
+        // An APP1 segment straight after the start-of-image marker
+        let jpeg = jpeg(image);
+        let length = (2 + 6 + tiff.len()) as u16;
+        let mut with_exif = jpeg[..2].to_vec();
+        with_exif.extend_from_slice(&[0xFF, 0xE1]);
+        with_exif.extend_from_slice(&length.to_be_bytes());
+        with_exif.extend_from_slice(b"Exif\0\0");
+        with_exif.extend_from_slice(&tiff);
+        with_exif.extend_from_slice(&jpeg[2..]);
+        with_exif
+    }
+
+    pub(super) fn has_exif(data: &[u8]) -> bool {
+        exif::Reader::new()
+            .read_from_container(&mut Cursor::new(data))
+            .is_ok()
+    }
+
+    fn decoded(encoded: &Encoded) -> DynamicImage {
+        image::load_from_memory(&encoded.bytes).unwrap()
+    }
+
+    #[test]
+    fn reads_the_exif_orientation() {
+        assert_eq!(orientation(&jpeg_with_exif(photo(40, 30), 6)), 6);
+        assert_eq!(orientation(&jpeg(photo(40, 30))), 1);
+    }
+
+    #[test]
+    fn turns_every_orientation_upright() {
+        // How a camera stores a 3x2 image whose upright top-left pixel is
+        // marked, for each orientation, and where that pixel ends up
+        let marker = Rgb([255, 0, 0]);
+        let stored = |width, height, x, y| {
+            let mut image = RgbImage::from_pixel(width, height, Rgb([0, 0, 0]));
+            image.put_pixel(x, y, marker);
+            DynamicImage::ImageRgb8(image)
+        };
+        let cases = [
+            (1, stored(3, 2, 0, 0)),
+            (2, stored(3, 2, 2, 0)),
+            (3, stored(3, 2, 2, 1)),
+            (4, stored(3, 2, 0, 1)),
+            (5, stored(2, 3, 0, 0)),
+            (6, stored(2, 3, 0, 2)),
+            (7, stored(2, 3, 1, 2)),
+            (8, stored(2, 3, 1, 0)),
+        ];
+        for (orientation, image) in cases {
+            let upright = orient(image, orientation).to_rgb8();
+            assert_eq!(upright.dimensions(), (3, 2), "orientation {orientation}");
+            assert_eq!(
+                upright.get_pixel(0, 0),
+                &marker,
+                "orientation {orientation}"
+            );
+        }
+    }
+
+    #[test]
+    fn strips_metadata_after_applying_it() {
+        let data = jpeg_with_exif(photo(60, 40), 6);
+        assert!(has_exif(&data));
+
+        let prepared = reencode(&data, &options(1000, 1024 * 1024))
+            .unwrap()
+            .unwrap();
+        assert!(!has_exif(&prepared.image.bytes));
+        assert!(!has_exif(&prepared.thumbnail.bytes));
+        assert_eq!((prepared.image.width, prepared.image.height), (40, 60));
+    }
+
+    #[test]
+    fn downscales_to_the_maximum_dimension() {
+        let large = downscale(DynamicImage::ImageRgb8(photo(800, 600)), 400);
+        assert_eq!(large.dimensions(), (400, 300));
+        let tall = downscale(DynamicImage::ImageRgb8(photo(300, 900)), 400);
+        assert_eq!(tall.dimensions(), (133, 400));
+        let small = downscale(DynamicImage::ImageRgb8(photo(200, 100)), 400);
+        assert_eq!(small.dimensions(), (200, 100));
+    }
+
+    #[test]
+    fn photos_become_jpegs_within_the_target() {
+        let data = encode_as(DynamicImage::ImageRgb8(photo(400, 300)), ImageFormat::Png);
+        let target = data.len() as u64 / 4;
+
+        let prepared = reencode(&data, &options(1000, target)).unwrap().unwrap();
+        assert_eq!(prepared.image.format, Format::Jpeg);
+        assert!(prepared.image.size() <= target);
+        assert_eq!((prepared.image.width, prepared.image.height), (400, 300));
+    }
+
+    #[test]
+    fn lossless_sources_stay_lossless_when_they_fit() {
+        let flat = RgbImage::from_fn(300, 200, |x, _| {
+            if x < 150 {
+                Rgb([20, 20, 20])
+            } else {
+                Rgb([240, 240, 240])
+            }
+        });
+        let data = encode_as(DynamicImage::ImageRgb8(flat.clone()), ImageFormat::Png);
+
+        let prepared = reencode(&data, &options(1000, 1024 * 1024))
+            .unwrap()
+            .unwrap();
+        assert_ne!(prepared.image.format, Format::Jpeg);
+        assert_eq!(decoded(&prepared.image).to_rgb8(), flat);
+    }
+
+    #[test]
+    fn transparency_survives_reencoding() {
+        let data = encode_as(DynamicImage::ImageRgba8(sticker(200)), ImageFormat::Png);
+
+        let prepared = reencode(&data, &options(1000, 1024 * 1024))
+            .unwrap()
+            .unwrap();
+        assert_ne!(prepared.image.format, Format::Jpeg);
+        assert_eq!(decoded(&prepared.image).to_rgba8(), sticker(200));
+        assert_eq!(prepared.thumbnail.format, Format::Png);
+    }
+
+    #[test]
+    fn shrinks_images_no_quality_can_fit() {
+        let data = jpeg(photo(1200, 900));
+        let target = 40 * 1024;
+
+        let prepared = reencode(&data, &options(2000, target)).unwrap().unwrap();
+        let image = &prepared.image;
+        assert!(image.size() <= target, "{} bytes", image.size());
+        assert!(image.width < 1200);
+        let aspect = image.width as f64 / image.height as f64;
+        assert!((aspect - 4.0 / 3.0).abs() < 0.02, "aspect {aspect}");
+        assert_eq!(decoded(image).dimensions(), (image.width, image.height));
+    }
+
+    #[test]
+    fn never_shrinks_below_the_minimum() {
+        let encoded = encode(DynamicImage::ImageRgb8(photo(400, 400)), 1, false).unwrap();
+        assert_eq!(encoded.format, Format::Jpeg);
+        assert!(encoded.width >= MIN_DIMENSION * 3 / 4);
+        assert!(encoded.width <= MIN_DIMENSION);
+    }
+
+    #[test]
+    fn thumbnails_fit_the_thumbnail_size() {
+        let data = jpeg(photo(400, 200));
+
+        let prepared = reencode(&data, &options(1000, 1024 * 1024))
+            .unwrap()
+            .unwrap();
+        let thumbnail = &prepared.thumbnail;
+        assert_eq!(thumbnail.format, Format::Jpeg);
+        assert_eq!((thumbnail.width, thumbnail.height), (64, 32));
+        assert_eq!(decoded(thumbnail).dimensions(), (64, 32));
+    }
+
+    #[test]
+    fn blurhash_follows_the_image() {
+        let red = DynamicImage::ImageRgb8(RgbImage::from_pixel(120, 80, Rgb([200, 20, 20])));
+        let hash = blurhash(&red).unwrap();
+        // 4x3 components: a size flag, a maximum, a DC and 11 AC values
+        assert_eq!(hash.len(), 1 + 1 + 4 + 11 * 2);
+
+        let pixels = blurhash::decode(&hash, 4, 4, 1.0).unwrap();
+        let [r, g, b, _] = pixels[..4] else {
+            unreachable!()
+        };
+        assert!(r > 180 && g < 40 && b < 40, "decoded as {r},{g},{b}");
+
+        let tall = DynamicImage::ImageRgb8(photo(80, 120));
+        assert_ne!(blurhash(&tall).unwrap()[..1], hash[..1]);
+    }
+
+    fn stripped(outcome: Outcome) -> Vec<u8> {
+        match outcome {
+            Outcome::Stripped(stripped) => stripped,
+            other => panic!("expected a stripped image, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn strips_images_it_cannot_decode() {
+        let panorama = jpeg_with_exif(photo(MAX_SOURCE_DIMENSION + 1, 2), 1);
+        assert!(has_exif(&panorama));
+        assert!(reencode(&panorama, &ImageOptions::default())
+            .unwrap()
+            .is_none());
+        let sent = stripped(prepare(&panorama, "image/jpeg", &ImageOptions::default()).unwrap());
+        assert!(!has_exif(&sent));
+
+        let data = jpeg_with_exif(photo(200, 150), 6);
+        let truncated = &data[..data.len() / 3];
+        assert!(has_exif(truncated));
+        assert!(reencode(truncated, &ImageOptions::default())
+            .unwrap()
+            .is_none());
+        let sent = stripped(prepare(truncated, "image/jpeg", &ImageOptions::default()).unwrap());
+        assert!(!has_exif(&sent));
+
+        // Cut off where the segments can't be told apart, it isn't sent
+        assert!(matches!(
+            prepare(&data[..40], "image/jpeg", &ImageOptions::default()),
+            Err(Error::Metadata(_))
+        ));
+    }
+
+    #[test]
+    fn reducing_precision_keeps_alpha() {
+        let reduced = reduce(&DynamicImage::ImageRgba8(sticker(40)), 3).to_rgba8();
+        for (before, after) in sticker(40).pixels().zip(reduced.pixels()) {
+            assert_eq!(after[3], before[3]);
+            for channel in 0..3 {
+                assert_eq!(after[channel] & 0b111, 0b100);
+                assert_eq!(after[channel] >> 3, before[channel] >> 3);
+            }
+        }
+    }
+
+    #[test]
+    fn transparent_images_fit_as_lossy_webp() {
+        // Noisy, so lossless encoders can't squeeze it
+        let noisy = photo(300, 300);
+        let image = RgbaImage::from_fn(300, 300, |x, y| {
+            let [r, g, b] = noisy.get_pixel(x, y).0;
+            Rgba([r, g, b, if x < 20 { 0 } else { 255 }])
+        });
+        let image = DynamicImage::ImageRgba8(image);
+        let lossless = write(&image, Format::WebP, 0).unwrap().size();
+        let reduced = write(&reduce(&image, 3), Format::WebP, 0).unwrap().size();
+        assert!(reduced < lossless);
+
+        let data = encode_as(image.clone(), ImageFormat::Png);
+        let prepared = reencode(&data, &options(1000, reduced)).unwrap().unwrap();
+        assert_eq!(prepared.image.format, Format::WebP);
+        assert!(prepared.image.size() <= reduced);
+        // Met by color precision, not by shrinking
+        assert_eq!((prepared.image.width, prepared.image.height), (300, 300));
+        let decoded = decoded(&prepared.image).to_rgba8();
+        assert_eq!(decoded.get_pixel(10, 10)[3], 0);
+        assert_eq!(decoded.get_pixel(200, 10)[3], 255);
+    }
+
+    #[test]
+    fn animations_and_other_formats_are_not_reencoded() {
+        let gif = encode_as(DynamicImage::ImageRgb8(photo(20, 20)), ImageFormat::Gif);
+        assert!(reencode(&gif, &ImageOptions::default()).unwrap().is_none());
+        assert!(matches!(
+            prepare(&gif, "image/gif", &ImageOptions::default()).unwrap(),
+            Outcome::Unchanged
+        ));
+        assert!(reencode(b"not an image", &ImageOptions::default())
+            .unwrap()
+            .is_none());
+        assert!(matches!(
+            prepare(b"II*\0", "image/tiff", &ImageOptions::default()),
+            Err(Error::Metadata(_))
+        ));
+    }
+}
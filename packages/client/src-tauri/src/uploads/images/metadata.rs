@@ -0,0 +1,308 @@
+//! Metadata removed from images that can't be re-encoded, without touching
+//! their pixels.
+//!
+//! The file is walked segment by segment and everything that describes the
+//! photo rather than how to decode it is left out: EXIF, XMP, IPTC, color
+//! profiles and comments, along with anything after the image ends. Formats
+//! that can't be walked here are refused rather than sent as they are.
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("metadata can't be removed from {0} files")]
+    Unsupported(String),
+    #[error("image is damaged, so its metadata can't be removed")]
+    Malformed,
+}
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+/// PNG chunks describing the image rather than its pixels.
+const PNG_METADATA: &[&[u8]] = &[b"eXIf", b"tEXt", b"zTXt", b"iTXt", b"tIME"];
+/// WebP chunks describing the image rather than its pixels.
+const WEBP_METADATA: &[&[u8]] = &[b"EXIF", b"XMP "];
+/// The VP8X flags announcing EXIF and XMP chunks.
+const WEBP_METADATA_FLAGS: u8 = 0x08 | 0x04;
+
+/// The image without its metadata. `None` for formats with nowhere to keep
+/// any, which can go up as they are.
+pub fn strip(data: &[u8], mime_type: &str) -> Result<Option<Vec<u8>>, Error> {
+    match mime_type {
+        "image/jpeg" => jpeg(data).map(Some),
+        "image/png" => png(data).map(Some),
+        "image/webp" => webp(data).map(Some),
+        "image/gif" | "image/bmp" | "image/x-icon" | "image/svg+xml" => Ok(None),
+        other => Err(Error::Unsupported(other.to_string())),
+    }
+}
+
+/// Keeps the JFIF and Adobe segments, which say how to read the pixels, and
+/// drops every other application segment and comment. The orientation goes
+/// with EXIF, so a sideways photo stays sideways.
+fn jpeg(data: &[u8]) -> Result<Vec<u8>, Error> {
+    if !data.starts_with(&[0xFF, 0xD8]) {
+        return Err(Error::Malformed);
+    }
+    let mut stripped = Vec::with_capacity(data.len());
+    stripped.extend_from_slice(&data[..2]);
+    let mut pos = 2;
+    loop {
+        if data.get(pos) != Some(&0xFF) {
+            return Err(Error::Malformed);
+        }
+        // Markers may be padded with any number of 0xFF
+        while data.get(pos + 1) == Some(&0xFF) {
+            pos += 1;
+        }
+        let marker = *data.get(pos + 1).ok_or(Error::Malformed)?;
+        match marker {
+            // Anything after the end, e.g. a phone's extra images with
+            // their own EXIF, goes too
+            0xD9 => {
+                stripped.extend_from_slice(&[0xFF, 0xD9]);
+                return Ok(stripped);
+            }
+            0x01 | 0xD0..=0xD7 => {
+                stripped.extend_from_slice(&data[pos..pos + 2]);
+                pos += 2;
+                continue;
+            }
+            _ => {}
+        }
+
+        let length = data
+            .get(pos + 2..pos + 4)
+            .map(|length| u16::from_be_bytes([length[0], length[1]]) as usize)
+            .ok_or(Error::Malformed)?;
+        let end = pos + 2 + length;
+        if length < 2 || end > data.len() {
+            return Err(Error::Malformed);
+        }
+        if !matches!(marker, 0xE1..=0xED | 0xEF | 0xFE) {
+            stripped.extend_from_slice(&data[pos..end]);
+        }
+        pos = end;
+
+        if marker == 0xDA {
+            // Scan data runs to the next marker that isn't an escaped 0xFF
+            // or a restart
+            let start = pos;
+            while pos < data.len() && !starts_marker(data, pos) {
+                pos += 1;
+            }
+            stripped.extend_from_slice(&data[start..pos]);
+            // A truncated scan has nothing after it to strip
+            if pos == data.len() {
+                return Ok(stripped);
+            }
+        }
+    }
+}
+
+fn starts_marker(data: &[u8], pos: usize) -> bool {
+    data[pos] == 0xFF
+        && data
+            .get(pos + 1)
+            .is_some_and(|&next| next != 0x00 && !(0xD0..=0xD7).contains(&next))
+}
+
+fn png(data: &[u8]) -> Result<Vec<u8>, Error> {
+    if !data.starts_with(PNG_SIGNATURE) {
+        return Err(Error::Malformed);
+    }
+    let mut stripped = PNG_SIGNATURE.to_vec();
+    let mut pos = PNG_SIGNATURE.len();
+    loop {
+        let header = data.get(pos..pos + 8).ok_or(Error::Malformed)?;
+        let length = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        let kind = &header[4..8];
+        // Length, type, data and CRC
+        let end = pos + 12 + length;
+        if end > data.len() {
+            return Err(Error::Malformed);
+        }
+        if !PNG_METADATA.contains(&kind) {
+            stripped.extend_from_slice(&data[pos..end]);
+        }
+        pos = end;
+        if kind == b"IEND" {
+            return Ok(stripped);
+        }
+    }
+}
+
+fn webp(data: &[u8]) -> Result<Vec<u8>, Error> {
+    if data.len() < 12 || &data[..4] != b"RIFF" || &data[8..12] != b"WEBP" {
+        return Err(Error::Malformed);
+    }
+    let riff_end = 8 + u32::from_le_bytes([data[4], data[5], data[6], data[7]]) as usize;
+    if riff_end > data.len() {
+        return Err(Error::Malformed);
+    }
+    let mut stripped = data[..12].to_vec();
+    let mut pos = 12;
+    while pos < riff_end {
+        let header = data.get(pos..pos + 8).ok_or(Error::Malformed)?;
+        let size = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as usize;
+        // Chunks are padded to an even size
+        let end = pos + 8 + size + (size & 1);
+        if end > riff_end {
+            return Err(Error::Malformed);
+        }
+        let kind = &header[..4];
+        if !WEBP_METADATA.contains(&kind) {
+            let start = stripped.len();
+            stripped.extend_from_slice(&data[pos..end]);
+            if kind == b"VP8X" && size > 0 {
+                stripped[start + 8] &= !WEBP_METADATA_FLAGS;
+            }
+        }
+        pos = end;
+    }
+    let riff_size = (stripped.len() - 8) as u32;
+    stripped[4..8].copy_from_slice(&riff_size.to_le_bytes());
+    Ok(stripped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::uploads::images::tests::{has_exif, jpeg_with_exif, photo};
+
+    use std::io::Cursor;
+
+    use image::{DynamicImage, ImageFormat};
+
+    fn png_chunk(kind: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut chunk = (data.len() as u32).to_be_bytes().to_vec();
+        chunk.extend_from_slice(kind);
+        chunk.extend_from_slice(data);
+        // Never checked, since the chunks carrying it are left out
+        chunk.extend_from_slice(&[0; 4]);
+        chunk
+    }
+
+    fn webp_chunk(kind: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut chunk = kind.to_vec();
+        chunk.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        chunk.extend_from_slice(data);
+        if data.len() % 2 == 1 {
+            chunk.push(0);
+        }
+        chunk
+    }
+
+    fn riff(chunks: &[Vec<u8>]) -> Vec<u8> {
+        let body = chunks.concat();
+        let mut riff = b"RIFF".to_vec();
+        riff.extend_from_slice(&(4 + body.len() as u32).to_le_bytes());
+        riff.extend_from_slice(b"WEBP");
+        riff.extend_from_slice(&body);
+        riff
+    }
+
+    #[test]
+    fn jpegs_lose_exif_but_keep_their_pixels() {
+        // With a second image after the first, as some phones add
+        let mut data = jpeg_with_exif(photo(60, 40), 6);
+        data.extend_from_slice(&jpeg_with_exif(photo(8, 8), 1));
+        assert!(has_exif(&data));
+
+        let stripped = strip(&data, "image/jpeg").unwrap().unwrap();
+        assert!(!has_exif(&stripped));
+        assert!(stripped.ends_with(&[0xFF, 0xD9]));
+        assert_eq!(
+            image::load_from_memory(&stripped).unwrap(),
+            image::load_from_memory(&data).unwrap()
+        );
+    }
+
+    #[test]
+    fn truncated_jpeg_scans_are_kept_as_far_as_they_go() {
+        let data = jpeg_with_exif(photo(200, 150), 1);
+        let truncated = &data[..data.len() - 100];
+
+        let stripped = strip(truncated, "image/jpeg").unwrap().unwrap();
+        assert!(!has_exif(&stripped));
+        assert!(stripped.ends_with(&truncated[truncated.len() - 50..]));
+        // Cut off before the scan, what follows can't be told apart
+        assert!(matches!(
+            strip(&data[..40], "image/jpeg"),
+            Err(Error::Malformed)
+        ));
+    }
+
+    #[test]
+    fn pngs_lose_text_and_exif_chunks() {
+        let mut plain = Cursor::new(Vec::new());
+        DynamicImage::ImageRgb8(photo(20, 10))
+            .write_to(&mut plain, ImageFormat::Png)
+            .unwrap();
+        let plain = plain.into_inner();
+
+        // After the signature and IHDR
+        let ihdr_end = 8 + 12 + 13;
+        let mut data = plain[..ihdr_end].to_vec();
+        data.extend(png_chunk(b"eXIf", b"MM\0*GPS"));
+        data.extend(png_chunk(b"tEXt", b"Comment\0Taken at home"));
+        data.extend(png_chunk(b"iTXt", b"XML:com.adobe.xmp\0\0\0\0\0<x/>"));
+        data.extend_from_slice(&plain[ihdr_end..]);
+        data.extend_from_slice(b"trailing");
+
+        assert_eq!(strip(&data, "image/png").unwrap().unwrap(), plain);
+        assert!(matches!(
+            strip(&data[..ihdr_end + 10], "image/png"),
+            Err(Error::Malformed)
+        ));
+    }
+
+    #[test]
+    fn webps_lose_exif_and_xmp_chunks_and_flags() {
+        let mut lossless = Cursor::new(Vec::new());
+        DynamicImage::ImageRgb8(photo(20, 10))
+            .write_to(&mut lossless, ImageFormat::WebP)
+            .unwrap();
+        let lossless = lossless.into_inner();
+        let vp8l = lossless[12..].to_vec();
+
+        // Flags, three reserved bytes, then the canvas size less one
+        let vp8x = |flags: u8| {
+            let mut vp8x = vec![flags, 0, 0, 0];
+            vp8x.extend_from_slice(&19u32.to_le_bytes()[..3]);
+            vp8x.extend_from_slice(&9u32.to_le_bytes()[..3]);
+            webp_chunk(b"VP8X", &vp8x)
+        };
+        let data = riff(&[
+            vp8x(0x08 | 0x04),
+            vp8l.clone(),
+            webp_chunk(b"EXIF", b"MM\0*GPS"),
+            webp_chunk(b"XMP ", b"<x/>"),
+        ]);
+
+        let stripped = strip(&data, "image/webp").unwrap().unwrap();
+        assert_eq!(stripped, riff(&[vp8x(0), vp8l]));
+        assert_eq!(
+            image::load_from_memory(&stripped).unwrap(),
+            image::load_from_memory(&lossless).unwrap()
+        );
+        assert!(matches!(
+            strip(&data[..data.len() - 2], "image/webp"),
+            Err(Error::Malformed)
+        ));
+    }
+
+    #[test]
+    fn other_formats_are_refused_unless_they_carry_none() {
+        let gif = b"GIF89a";
+        assert!(strip(gif, "image/gif").unwrap().is_none());
+        assert!(strip(b"<svg/>", "image/svg+xml").unwrap().is_none());
+        for mime_type in ["image/tiff", "image/heic", "image/avif"] {
+            assert!(matches!(
+                strip(b"II*\0", mime_type),
+                Err(Error::Unsupported(refused)) if refused == mime_type
+            ));
+        }
+        assert!(matches!(
+            strip(b"not an image", "image/jpeg"),
+            Err(Error::Malformed)
+        ));
+    }
+}
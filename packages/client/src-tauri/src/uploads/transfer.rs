@@ -0,0 +1,680 @@
+//! One upload to the `/uploads` endpoints, streamed from disk and retried
+//! with backoff.
+//!
+//! Servers that speak tus 1.0 advertise it on `OPTIONS /uploads/resumable`.
+//! They get a resumable upload: the file is created with a `POST`, sent with
+//! `PATCH`, and after a dropped connection continues from the offset a
+//! `HEAD` reports instead of starting over. Once complete, a `GET` on the
+//! upload returns the attachment. Other servers get the multipart form the
+//! webview used to post, restarted from the beginning on failure.
+
+use std::io::SeekFrom;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use base64::Engine as _;
+use futures_util::StreamExt;
+use reqwest::header::{CONTENT_LENGTH, CONTENT_TYPE, LOCATION};
+use reqwest::{Body, Client, Response, StatusCode};
+use serde_json::Value;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio_util::io::ReaderStream;
+use tokio_util::sync::CancellationToken;
+use url::Url;
+
+const TUS_VERSION: &str = "1.0.0";
+const TUS_RESUMABLE: &str = "Tus-Resumable";
+const UPLOAD_OFFSET: &str = "Upload-Offset";
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(300);
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("failed to read file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("upload failed: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("invalid upload url: {0}")]
+    Url(#[from] url::ParseError),
+    #[error("upload rejected ({status}): {message}")]
+    Rejected { status: u16, message: String },
+    #[error("upload server misbehaved: {0}")]
+    Protocol(&'static str),
+    #[error("resumable upload expired")]
+    Expired,
+    #[error("upload cancelled")]
+    Cancelled,
+}
+
+impl Error {
+    /// Whether trying again might work: dropped connections, timeouts and
+    /// server-side failures, but not a rejected file or a local error.
+    fn is_transient(&self) -> bool {
+        match self {
+            Error::Http(e) => !e.is_builder() && !e.is_decode(),
+            Error::Rejected { status, .. } => *status >= 500 || *status == 408 || *status == 429,
+            Error::Expired => true,
+            _ => false,
+        }
+    }
+}
+
+/// Called with the number of bytes the server has, as the upload goes.
+pub type Progress = Arc<dyn Fn(u64) + Send + Sync>;
+
+/// A validated file and the form fields that say where it goes.
+#[derive(Debug, Clone)]
+pub struct Upload {
+    pub path: PathBuf,
+    pub name: String,
+    pub mime_type: String,
+    pub size: u64,
+    /// `channelId` or `receiverId`, as the multipart form or tus metadata.
+    pub fields: Vec<(&'static str, String)>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Retry {
+    pub attempts: u32,
+    pub base: Duration,
+    pub max: Duration,
+}
+
+impl Default for Retry {
+    fn default() -> Self {
+        Self {
+            attempts: 5,
+            base: Duration::from_secs(1),
+            max: Duration::from_secs(30),
+        }
+    }
+}
+
+impl Retry {
+    fn delay(&self, attempt: u32) -> Duration {
+        self.base
+            .saturating_mul(1 << attempt.saturating_sub(1).min(5))
+            .min(self.max)
+    }
+}
+
+pub struct Transfer {
+    pub client: Client,
+    /// The API root, e.g. `https://commhub.example`.
+    pub api_url: Url,
+    pub token: String,
+    pub retry: Retry,
+    pub cancel: CancellationToken,
+}
+
+impl Transfer {
+    fn endpoint(&self, path: &str) -> Result<Url, Error> {
+        let base = self.api_url.as_str().trim_end_matches('/');
+        Ok(Url::parse(&format!("{}/{}", base, path))?)
+    }
+
+    /// Whether the server accepts resumable uploads. Anything but a clear
+    /// yes means no.
+    pub async fn negotiate(&self) -> bool {
+        let Ok(url) = self.endpoint("uploads/resumable") else {
+            return false;
+        };
+        let response = self
+            .client
+            .request(reqwest::Method::OPTIONS, url)
+            .header(TUS_RESUMABLE, TUS_VERSION)
+            .timeout(Duration::from_secs(15))
+            .send()
+            .await;
+        match response {
+            Ok(response) if response.status().is_success() => response
+                .headers()
+                .get("Tus-Version")
+                .and_then(|versions| versions.to_str().ok())
+                .is_some_and(|versions| versions.split(',').any(|v| v.trim() == TUS_VERSION)),
+            _ => false,
+        }
+    }
+
+    /// Uploads the file and returns the attachment the server created.
+    pub async fn run(
+        &self,
+        upload: &Upload,
+        resumable: bool,
+        progress: Progress,
+    ) -> Result<Value, Error> {
+        // The tus upload, once created, survives failed attempts
+        let mut location = None;
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            let result = if resumable {
+                self.cancellable(self.resumable(upload, &mut location, progress.clone()))
+                    .await
+            } else {
+                self.cancellable(self.multipart(upload, progress.clone()))
+                    .await
+            };
+            match result {
+                Err(e) if e.is_transient() && attempt < self.retry.attempts => {
+                    log!(
+                        "[Uploads] Attempt {} for {} failed, retrying: {}",
+                        attempt,
+                        upload.name,
+                        e
+                    );
+                    self.cancellable(async {
+                        tokio::time::sleep(self.retry.delay(attempt)).await;
+                        Ok(())
+                    })
+                    .await?;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    async fn cancellable<T>(
+        &self,
+        future: impl std::future::Future<Output = Result<T, Error>>,
+    ) -> Result<T, Error> {
+        tokio::select! {
+            biased;
+            _ = self.cancel.cancelled() => Err(Error::Cancelled),
+            result = future => result,
+        }
+    }
+
+    async fn multipart(&self, upload: &Upload, progress: Progress) -> Result<Value, Error> {
+        progress(0);
+        let part = reqwest::multipart::Part::stream_with_length(
+            file_body(upload, 0, progress).await?,
+            upload.size,
+        )
+        .file_name(upload.name.clone())
+        .mime_str(&upload.mime_type)?;
+        let mut form = reqwest::multipart::Form::new().part("file", part);
+        for (name, value) in &upload.fields {
+            form = form.text(*name, value.clone());
+        }
+
+        let response = self
+            .client
+            .post(self.endpoint("uploads")?)
+            .bearer_auth(&self.token)
+            .multipart(form)
+            .timeout(REQUEST_TIMEOUT)
+            .send()
+            .await?;
+        Ok(check(response).await?.json().await?)
+    }
+
+    async fn resumable(
+        &self,
+        upload: &Upload,
+        location: &mut Option<Url>,
+        progress: Progress,
+    ) -> Result<Value, Error> {
+        let (url, mut offset) = match location.clone() {
+            Some(url) => {
+                let offset = self.offset(&url).await;
+                if matches!(offset, Err(Error::Expired)) {
+                    *location = None;
+                }
+                (url, offset?)
+            }
+            None => {
+                let url = self.create(upload).await?;
+                *location = Some(url.clone());
+                (url, 0)
+            }
+        };
+
+        while offset < upload.size {
+            progress(offset);
+            let response = self
+                .client
+                .patch(url.clone())
+                .bearer_auth(&self.token)
+                .header(TUS_RESUMABLE, TUS_VERSION)
+                .header(UPLOAD_OFFSET, offset)
+                .header(CONTENT_TYPE, "application/offset+octet-stream")
+                .header(CONTENT_LENGTH, upload.size - offset)
+                .body(file_body(upload, offset, progress.clone()).await?)
+                .timeout(REQUEST_TIMEOUT)
+                .send()
+                .await?;
+            let next = upload_offset(&check(response).await?)?;
+            // A server may keep less than was sent, but never go backwards
+            if next <= offset || next > upload.size {
+                return Err(Error::Protocol("upload offset did not advance"));
+            }
+            offset = next;
+        }
+        progress(upload.size);
+
+        let response = self
+            .client
+            .get(url)
+            .bearer_auth(&self.token)
+            .header(TUS_RESUMABLE, TUS_VERSION)
+            .timeout(Duration::from_secs(30))
+            .send()
+            .await?;
+        Ok(check(response).await?.json().await?)
+    }
+
+    /// Creates the tus upload and returns its URL.
+    async fn create(&self, upload: &Upload) -> Result<Url, Error> {
+        let endpoint = self.endpoint("uploads/resumable")?;
+        let encode = |value: &str| base64::engine::general_purpose::STANDARD.encode(value);
+        let metadata = [
+            ("filename", upload.name.as_str()),
+            ("filetype", &upload.mime_type),
+        ]
+        .into_iter()
+        .chain(
+            upload
+                .fields
+                .iter()
+                .map(|(name, value)| (*name, value.as_str())),
+        )
+        .map(|(key, value)| format!("{} {}", key, encode(value)))
+        .collect::<Vec<_>>()
+        .join(",");
+
+        let response = self
+            .client
+            .post(endpoint.clone())
+            .bearer_auth(&self.token)
+            .header(TUS_RESUMABLE, TUS_VERSION)
+            .header("Upload-Length", upload.size)
+            .header("Upload-Metadata", metadata)
+            .header(CONTENT_LENGTH, 0)
+            .timeout(Duration::from_secs(30))
+            .send()
+            .await?;
+        let response = check(response).await?;
+        let location = response
+            .headers()
+            .get(LOCATION)
+            .and_then(|location| location.to_str().ok())
+            .ok_or(Error::Protocol("created upload has no location"))?;
+        Ok(endpoint.join(location)?)
+    }
+
+    /// How much of the upload the server has.
+    async fn offset(&self, url: &Url) -> Result<u64, Error> {
+        let response = self
+            .client
+            .head(url.clone())
+            .bearer_auth(&self.token)
+            .header(TUS_RESUMABLE, TUS_VERSION)
+            .timeout(Duration::from_secs(30))
+            .send()
+            .await?;
+        if matches!(response.status(), StatusCode::NOT_FOUND | StatusCode::GONE) {
+            return Err(Error::Expired);
+        }
+        upload_offset(&check(response).await?)
+    }
+}
+
+/// The file from `offset` on, reporting progress as the body is sent.
+async fn file_body(upload: &Upload, offset: u64, progress: Progress) -> Result<Body, Error> {
+    let mut file = tokio::fs::File::open(&upload.path).await?;
+    file.seek(SeekFrom::Start(offset)).await?;
+    // Never more than was validated, even if the file has grown since
+    let file = file.take(upload.size - offset);
+
+    let mut sent = offset;
+    let stream = ReaderStream::new(file).map(move |chunk| {
+        if let Ok(chunk) = &chunk {
+            sent += chunk.len() as u64;
+            progress(sent);
+        }
+        chunk
+    });
+    Ok(Body::wrap_stream(stream))
+}
+
+async fn check(response: Response) -> Result<Response, Error> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let body: Value = response.json().await.unwrap_or_default();
+    Err(Error::Rejected {
+        status: status.as_u16(),
+        message: body["message"]
+            .as_str()
+            .or(status.canonical_reason())
+            .unwrap_or_default()
+            .to_string(),
+    })
+}
+
+fn upload_offset(response: &Response) -> Result<u64, Error> {
+    response
+        .headers()
+        .get(UPLOAD_OFFSET)
+        .and_then(|offset| offset.to_str().ok())
+        .and_then(|offset| offset.parse().ok())
+        .ok_or(Error::Protocol("missing upload offset"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::Mutex;
+
+    use serde_json::json;
+
+    use crate::test_support::{Connection, Request, StandIn};
+
+    const FILE_SIZE: usize = 256 * 1024;
+
+    /// What the stand-in server does with uploads.
+    #[derive(Clone, Copy)]
+    enum Mode {
+        Multipart,
+        Resumable,
+        /// Rejects every upload as too large.
+        TooLarge,
+        /// Accepts the connection and never answers.
+        Stall,
+    }
+
+    #[derive(Default)]
+    struct Seen {
+        requests: Vec<String>,
+        /// Bytes of each upload body the server read, dropped or not.
+        bodies: Vec<Vec<u8>>,
+        /// The tus upload as stored so far.
+        stored: Vec<u8>,
+        patch_offsets: Vec<u64>,
+    }
+
+    /// A local stand-in for the API that drops the connection halfway
+    /// through the first `drops` upload bodies it receives.
+    struct Api {
+        server: StandIn,
+        seen: Arc<Mutex<Seen>>,
+    }
+
+    impl Api {
+        async fn start(mode: Mode, drops: usize) -> Self {
+            let seen = Arc::new(Mutex::new(Seen::default()));
+            let drops = Arc::new(AtomicU64::new(drops as u64));
+            let state = seen.clone();
+            let server = StandIn::start(move |request, connection| {
+                serve(request, connection, mode, drops.clone(), state.clone())
+            })
+            .await;
+            Self { server, seen }
+        }
+
+        fn url(&self) -> Url {
+            Url::parse(&self.server.url("/")).unwrap()
+        }
+
+        fn requests(&self) -> Vec<String> {
+            self.seen.lock().unwrap().requests.clone()
+        }
+    }
+
+    async fn serve(
+        request: Request,
+        mut connection: Connection,
+        mode: Mode,
+        drops: Arc<AtomicU64>,
+        seen: Arc<Mutex<Seen>>,
+    ) {
+        let length = request.content_length();
+        let Request {
+            method,
+            path,
+            headers,
+        } = request;
+        seen.lock()
+            .unwrap()
+            .requests
+            .push(format!("{} {}", method, path));
+
+        let uploading = length > 0 && matches!(method.as_str(), "POST" | "PATCH");
+        if uploading && matches!(mode, Mode::Stall) {
+            std::future::pending::<()>().await;
+        }
+        let dropping = uploading
+            && drops
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+                .is_ok();
+        let body = connection
+            .read_body(if dropping { length / 2 } else { length })
+            .await
+            .unwrap();
+        if uploading {
+            seen.lock().unwrap().bodies.push(body.clone());
+        }
+
+        if method == "PATCH" {
+            let offset: u64 = headers[&UPLOAD_OFFSET.to_ascii_lowercase()]
+                .parse()
+                .unwrap();
+            let mut seen = seen.lock().unwrap();
+            assert_eq!(
+                offset,
+                seen.stored.len() as u64,
+                "patch at the wrong offset"
+            );
+            seen.patch_offsets.push(offset);
+            // Like a real tus server, keep what arrived before the drop
+            seen.stored.extend_from_slice(&body);
+        }
+        if dropping {
+            // Closing with unread data resets the connection mid-upload
+            return;
+        }
+
+        let stored = seen.lock().unwrap().stored.len();
+        let (status, extra, json) = match (mode, method.as_str(), path.as_str()) {
+            (Mode::Resumable, "OPTIONS", _) => ("204 No Content", "Tus-Version: 1.0.0\r\n", None),
+            (_, "OPTIONS", _) => ("404 Not Found", "", None),
+            (Mode::TooLarge, _, _) => (
+                "413 Payload Too Large",
+                "",
+                Some(json!({ "message": "File too large" })),
+            ),
+            (_, "POST", "/uploads") => (
+                "200 OK",
+                "",
+                Some(json!({ "url": "/files/1", "filename": "a.bin", "size": length })),
+            ),
+            (_, "POST", "/uploads/resumable") => {
+                ("201 Created", "Location: /uploads/resumable/1\r\n", None)
+            }
+            (_, "HEAD" | "PATCH", _) => ("204 No Content", "", None),
+            (_, "GET", _) => (
+                "200 OK",
+                "",
+                Some(json!({ "url": "/files/1", "filename": "a.bin", "size": stored })),
+            ),
+            _ => ("404 Not Found", "", None),
+        };
+        let body = json.map(|json| json.to_string()).unwrap_or_default();
+        let response = format!(
+            "HTTP/1.1 {}\r\n{}Upload-Offset: {}\r\nContent-Type: application/json\r\n\
+             Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            extra,
+            stored,
+            body.len(),
+            body
+        );
+        let _ = connection.write(response.as_bytes()).await;
+    }
+
+    fn test_file(name: &str) -> (PathBuf, Vec<u8>) {
+        let contents: Vec<u8> = (0..FILE_SIZE).map(|i| (i % 251) as u8).collect();
+        let path = std::env::temp_dir().join(format!(
+            "commhub-upload-{}-{}.bin",
+            std::process::id(),
+            name
+        ));
+        std::fs::write(&path, &contents).unwrap();
+        (path, contents)
+    }
+
+    fn upload(path: PathBuf) -> Upload {
+        Upload {
+            path,
+            name: "a.bin".to_string(),
+            mime_type: "application/octet-stream".to_string(),
+            size: FILE_SIZE as u64,
+            fields: vec![("channelId", "7".to_string())],
+        }
+    }
+
+    fn transfer(server: &Api, attempts: u32) -> Transfer {
+        Transfer {
+            client: Client::new(),
+            api_url: server.url(),
+            token: "token".to_string(),
+            retry: Retry {
+                attempts,
+                base: Duration::from_millis(10),
+                max: Duration::from_millis(50),
+            },
+            cancel: CancellationToken::new(),
+        }
+    }
+
+    fn recorder() -> (Progress, Arc<AtomicU64>) {
+        let last = Arc::new(AtomicU64::new(0));
+        let sink = last.clone();
+        (
+            Arc::new(move |sent| sink.store(sent, Ordering::SeqCst)),
+            last,
+        )
+    }
+
+    fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+        haystack
+            .windows(needle.len())
+            .any(|window| window == needle)
+    }
+
+    #[tokio::test]
+    async fn negotiates_resumable_only_when_advertised() {
+        let tus = Api::start(Mode::Resumable, 0).await;
+        assert!(transfer(&tus, 1).negotiate().await);
+        let plain = Api::start(Mode::Multipart, 0).await;
+        assert!(!transfer(&plain, 1).negotiate().await);
+    }
+
+    #[tokio::test]
+    async fn multipart_upload_restarts_after_dropped_connections() {
+        let server = Api::start(Mode::Multipart, 2).await;
+        let (path, contents) = test_file("multipart");
+        let (progress, last) = recorder();
+
+        let attachment = transfer(&server, 5)
+            .run(&upload(path), false, progress)
+            .await
+            .unwrap();
+
+        assert_eq!(attachment["url"], "/files/1");
+        assert_eq!(server.requests(), vec!["POST /uploads"; 3]);
+        let seen = server.seen.lock().unwrap();
+        let complete = seen.bodies.last().unwrap();
+        assert!(contains(complete, &contents));
+        assert!(contains(complete, b"name=\"channelId\"\r\n\r\n7"));
+        assert_eq!(last.load(Ordering::SeqCst), FILE_SIZE as u64);
+    }
+
+    #[tokio::test]
+    async fn resumable_upload_continues_from_the_server_offset() {
+        let server = Api::start(Mode::Resumable, 2).await;
+        let (path, contents) = test_file("resumable");
+        let (progress, last) = recorder();
+
+        let attachment = transfer(&server, 5)
+            .run(&upload(path), true, progress)
+            .await
+            .unwrap();
+
+        assert_eq!(attachment["size"], FILE_SIZE);
+        let requests = server.requests();
+        assert_eq!(
+            requests
+                .iter()
+                .filter(|r| *r == "POST /uploads/resumable")
+                .count(),
+            1
+        );
+        assert_eq!(requests.iter().filter(|r| r.starts_with("HEAD")).count(), 2);
+        let seen = server.seen.lock().unwrap();
+        assert_eq!(seen.stored, contents);
+        // Each attempt picked up where the last one was cut off
+        assert_eq!(
+            seen.patch_offsets,
+            vec![0, FILE_SIZE as u64 / 2, FILE_SIZE as u64 * 3 / 4]
+        );
+        assert_eq!(last.load(Ordering::SeqCst), FILE_SIZE as u64);
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_the_last_attempt() {
+        let server = Api::start(Mode::Multipart, usize::MAX).await;
+        let (path, _) = test_file("exhausted");
+
+        let result = transfer(&server, 3)
+            .run(&upload(path), false, Arc::new(|_| {}))
+            .await;
+
+        assert!(matches!(result, Err(Error::Http(_))), "{:?}", result);
+        assert_eq!(server.requests().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn rejected_uploads_are_not_retried() {
+        let server = Api::start(Mode::TooLarge, 0).await;
+        let (path, _) = test_file("rejected");
+
+        let result = transfer(&server, 5)
+            .run(&upload(path), false, Arc::new(|_| {}))
+            .await;
+
+        match result {
+            Err(Error::Rejected { status, message }) => {
+                assert_eq!(status, 413);
+                assert_eq!(message, "File too large");
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn cancelling_stops_a_stalled_upload() {
+        let server = Api::start(Mode::Stall, 0).await;
+        let (path, _) = test_file("cancelled");
+        let transfer = transfer(&server, 5);
+        let cancel = transfer.cancel.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            cancel.cancel();
+        });
+
+        let result = tokio::time::timeout(
+            Duration::from_secs(5),
+            transfer.run(&upload(path), false, Arc::new(|_| {})),
+        )
+        .await
+        .expect("cancelling should end the upload");
+
+        assert!(matches!(result, Err(Error::Cancelled)), "{:?}", result);
+    }
+}
@@ -0,0 +1,564 @@
+//! Credential vault for the auth token and cached user profile.
+//!
+//! Credentials go to the OS secret service (Windows Credential Manager, macOS
+//! Keychain, Secret Service on Linux) when one is reachable. Machines without
+//! one fall back to an XChaCha20-Poly1305 encrypted file in the config dir whose
+//! key lives in the local data dir, so a copied or synced config folder alone
+//! does not reveal the token.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, State};
+
+const KEYRING_SERVICE: &str = "com.commhub.app";
+const KEYRING_ACCOUNT: &str = "auth";
+
+const VAULT_FILE: &str = "vault.bin";
+const KEY_FILE: &str = "vault.key";
+const LEGACY_AUTH_FILE: &str = "auth.json";
+
+// File layout: MAGIC | 24-byte nonce | ciphertext
+const MAGIC: &[u8; 4] = b"CHV1";
+const NONCE_LEN: usize = 24;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("vault io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("vault data is malformed: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("vault file is corrupt or was encrypted with a different key")]
+    Decrypt,
+    #[error("app directories are unavailable")]
+    NoAppDir,
+}
+
+/// What the webview persists between launches.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuthRecord {
+    pub token: String,
+    pub user: serde_json::Value,
+}
+
+/// Shape written by the old `StorageService.saveAuth`.
+#[derive(Deserialize)]
+struct LegacyAuthFile {
+    token: Option<String>,
+    user: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Backend {
+    SecretService,
+    EncryptedFile,
+}
+
+/// Where credentials go when the OS has a secret service.
+trait Secrets: Send + Sync {
+    fn set(&self, secret: &str) -> bool;
+    fn get(&self) -> Option<String>;
+    fn delete(&self);
+}
+
+struct Keyring;
+
+impl Secrets for Keyring {
+    fn set(&self, secret: &str) -> bool {
+        keyring_entry()
+            .map(|entry| entry.set_password(secret).is_ok())
+            .unwrap_or(false)
+    }
+
+    fn get(&self) -> Option<String> {
+        keyring_entry().and_then(|entry| entry.get_password().ok())
+    }
+
+    fn delete(&self) {
+        if let Some(entry) = keyring_entry() {
+            // NoEntry and an unreachable secret service are both fine here
+            let _ = entry.delete_credential();
+        }
+    }
+}
+
+pub struct Vault {
+    config_dir: PathBuf,
+    key_dir: PathBuf,
+    secrets: Box<dyn Secrets>,
+    // Serializes keyring and file access so concurrent invokes can't interleave
+    lock: Mutex<()>,
+    // The sign-in as last saved or loaded, including one that isn't
+    // remembered on disk, and only ever written under `lock`.
+    current: Mutex<Option<AuthRecord>>,
+}
+
+impl Vault {
+    pub fn new(config_dir: PathBuf, key_dir: PathBuf) -> Self {
+        Self::with_secrets(config_dir, key_dir, Box::new(Keyring))
+    }
+
+    fn with_secrets(config_dir: PathBuf, key_dir: PathBuf, secrets: Box<dyn Secrets>) -> Self {
+        Self {
+            config_dir,
+            key_dir,
+            secrets,
+            lock: Mutex::new(()),
+            current: Mutex::new(None),
+        }
+    }
+
+    pub fn from_app(app: &AppHandle) -> Result<Self, Error> {
+        let resolver = app.path_resolver();
+        // Same directory the webview used for auth.json ($APPCONFIG/commhub)
+        let config_dir = resolver
+            .app_config_dir()
+            .ok_or(Error::NoAppDir)?
+            .join("commhub");
+        let key_dir = resolver.app_local_data_dir().ok_or(Error::NoAppDir)?;
+        Ok(Self::new(config_dir, key_dir))
+    }
+
+    pub fn save(&self, record: &AuthRecord) -> Result<Backend, Error> {
+        let _guard = self.lock.lock().unwrap();
+        let saved = self.store(record);
+        *self.current.lock().unwrap() = saved.is_ok().then(|| record.clone());
+        saved
+    }
+
+    fn store(&self, record: &AuthRecord) -> Result<Backend, Error> {
+        let json = serde_json::to_string(record)?;
+        if self.secrets.set(&json) {
+            // Don't leave an older encrypted copy around to be loaded later
+            remove_if_exists(&self.vault_path())?;
+            return Ok(Backend::SecretService);
+        }
+
+        // Likewise an older keyring entry, which `load` would prefer
+        self.secrets.delete();
+        self.write_encrypted(json.as_bytes())?;
+        Ok(Backend::EncryptedFile)
+    }
+
+    /// Holds `record` for this run only, and forgets any saved sign-in.
+    pub fn hold(&self, record: AuthRecord) -> Result<(), Error> {
+        let _guard = self.lock.lock().unwrap();
+        *self.current.lock().unwrap() = Some(record);
+        self.forget()
+    }
+
+    /// The saved sign-in, read from the keyring or file only the first time.
+    pub fn load(&self) -> Result<Option<AuthRecord>, Error> {
+        if let Some(record) = self.current.lock().unwrap().clone() {
+            return Ok(Some(record));
+        }
+        let _guard = self.lock.lock().unwrap();
+        // Saved or loaded while this waited for the lock
+        if let Some(record) = self.current.lock().unwrap().clone() {
+            return Ok(Some(record));
+        }
+
+        let record = match self.secrets.get() {
+            Some(json) => Some(serde_json::from_str(&json)?),
+            None => match self.read_encrypted()? {
+                Some(plain) => Some(serde_json::from_slice(&plain)?),
+                None => None,
+            },
+        };
+        *self.current.lock().unwrap() = record.clone();
+        Ok(record)
+    }
+
+    pub fn clear(&self) -> Result<(), Error> {
+        let _guard = self.lock.lock().unwrap();
+        *self.current.lock().unwrap() = None;
+        self.forget()
+    }
+
+    /// Removes the saved sign-in; the caller holds `lock`.
+    fn forget(&self) -> Result<(), Error> {
+        self.secrets.delete();
+        remove_if_exists(&self.vault_path())?;
+        Ok(())
+    }
+
+    /// Moves a plaintext auth.json left by older builds into the vault and
+    /// scrubs it from disk. Returns true when a file was migrated.
+    pub fn migrate_legacy(&self) -> Result<bool, Error> {
+        let legacy = self.config_dir.join(LEGACY_AUTH_FILE);
+        if !legacy.exists() {
+            return Ok(false);
+        }
+
+        let parsed: Option<LegacyAuthFile> = fs::read(&legacy)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok());
+
+        let mut migrated = false;
+        if let Some(LegacyAuthFile {
+            token: Some(token),
+            user,
+        }) = parsed
+        {
+            self.save(&AuthRecord {
+                token,
+                user: user.unwrap_or(serde_json::Value::Null),
+            })?;
+            migrated = true;
+        }
+
+        // An unreadable or token-less file is still plaintext we don't want around
+        secure_delete(&legacy)?;
+        Ok(migrated)
+    }
+
+    fn vault_path(&self) -> PathBuf {
+        self.config_dir.join(VAULT_FILE)
+    }
+
+    fn key_path(&self) -> PathBuf {
+        self.key_dir.join(KEY_FILE)
+    }
+
+    fn cipher(&self, create: bool) -> Result<Option<XChaCha20Poly1305>, Error> {
+        let path = self.key_path();
+        if let Ok(bytes) = fs::read(&path) {
+            if let Ok(cipher) = XChaCha20Poly1305::new_from_slice(&bytes) {
+                return Ok(Some(cipher));
+            }
+        }
+        if !create {
+            return Ok(None);
+        }
+
+        let key = XChaCha20Poly1305::generate_key(&mut OsRng);
+        fs::create_dir_all(&self.key_dir)?;
+        write_private(&path, &key)?;
+        Ok(Some(XChaCha20Poly1305::new(&key)))
+    }
+
+    fn write_encrypted(&self, plain: &[u8]) -> Result<(), Error> {
+        let cipher = self.cipher(true)?.ok_or(Error::Decrypt)?;
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let sealed = cipher.encrypt(&nonce, plain).map_err(|_| Error::Decrypt)?;
+
+        let mut out = Vec::with_capacity(MAGIC.len() + NONCE_LEN + sealed.len());
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&nonce);
+        out.extend_from_slice(&sealed);
+
+        fs::create_dir_all(&self.config_dir)?;
+        // Write then rename so a crash never leaves a truncated vault behind
+        let tmp = self.vault_path().with_extension("tmp");
+        write_private(&tmp, &out)?;
+        fs::rename(&tmp, self.vault_path())?;
+        Ok(())
+    }
+
+    fn read_encrypted(&self) -> Result<Option<Vec<u8>>, Error> {
+        let bytes = match fs::read(self.vault_path()) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        if bytes.len() < MAGIC.len() + NONCE_LEN || &bytes[..MAGIC.len()] != MAGIC {
+            return Err(Error::Decrypt);
+        }
+
+        // Without the key the file is useless; treat it as signed out
+        let Some(cipher) = self.cipher(false)? else {
+            return Ok(None);
+        };
+        let (nonce, sealed) = bytes[MAGIC.len()..].split_at(NONCE_LEN);
+        let nonce: [u8; NONCE_LEN] = nonce.try_into().map_err(|_| Error::Decrypt)?;
+        cipher
+            .decrypt(&XNonce::from(nonce), sealed)
+            .map(Some)
+            .map_err(|_| Error::Decrypt)
+    }
+}
+
+fn keyring_entry() -> Option<keyring::Entry> {
+    keyring::Entry::new(KEYRING_SERVICE, KEYRING_ACCOUNT).ok()
+}
+
+fn remove_if_exists(path: &Path) -> std::io::Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+fn write_private(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(path)?;
+    file.write_all(bytes)?;
+    file.sync_all()
+}
+
+/// Overwrites a file with zeros before unlinking it. Journaling filesystems
+/// and SSD wear levelling can still keep old blocks, so this is best effort.
+fn secure_delete(path: &Path) -> std::io::Result<()> {
+    let len = fs::metadata(path)?.len();
+    {
+        let mut file = File::options().write(true).open(path)?;
+        file.seek(SeekFrom::Start(0))?;
+        let zeros = vec![0u8; 4096];
+        let mut remaining = len;
+        while remaining > 0 {
+            let chunk = remaining.min(zeros.len() as u64) as usize;
+            file.write_all(&zeros[..chunk])?;
+            remaining -= chunk as u64;
+        }
+        file.sync_all()?;
+    }
+    fs::remove_file(path)
+}
+
+/// Saves the sign-in, or with `persist` false keeps it only until exit.
+/// Returns where it was saved, or `None` when it wasn't.
+#[tauri::command]
+pub fn vault_save(
+    vault: State<'_, Vault>,
+    token: String,
+    user: serde_json::Value,
+    persist: Option<bool>,
+) -> Result<Option<Backend>, String> {
+    let record = AuthRecord { token, user };
+    let saved = if persist.unwrap_or(true) {
+        vault.save(&record).map(Some)
+    } else {
+        vault.hold(record).map(|_| None)
+    };
+    saved.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn vault_load(vault: State<'_, Vault>) -> Result<Option<AuthRecord>, String> {
+    vault.load().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn vault_clear(vault: State<'_, Vault>) -> Result<(), String> {
+    vault.clear().map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    /// No secret service, as on a machine without one.
+    struct NoSecrets;
+
+    impl Secrets for NoSecrets {
+        fn set(&self, _secret: &str) -> bool {
+            false
+        }
+
+        fn get(&self) -> Option<String> {
+            None
+        }
+
+        fn delete(&self) {}
+    }
+
+    /// A secret service shared by every vault given a clone, counting reads.
+    /// Writes fail once `refuse` is set, as for a secret over the size limit.
+    #[derive(Clone, Default)]
+    struct FakeKeyring {
+        secret: Arc<Mutex<Option<String>>>,
+        reads: Arc<AtomicUsize>,
+        refuse: Arc<AtomicBool>,
+    }
+
+    impl Secrets for FakeKeyring {
+        fn set(&self, secret: &str) -> bool {
+            if self.refuse.load(Ordering::SeqCst) {
+                return false;
+            }
+            *self.secret.lock().unwrap() = Some(secret.to_string());
+            true
+        }
+
+        fn get(&self) -> Option<String> {
+            self.reads.fetch_add(1, Ordering::SeqCst);
+            self.secret.lock().unwrap().clone()
+        }
+
+        fn delete(&self) {
+            *self.secret.lock().unwrap() = None;
+        }
+    }
+
+    fn vault(root: &Path, secrets: impl Secrets + 'static) -> Vault {
+        Vault::with_secrets(root.join("config"), root.join("data"), Box::new(secrets))
+    }
+
+    fn record() -> AuthRecord {
+        AuthRecord {
+            token: "secret-token".to_string(),
+            user: serde_json::json!({ "id": 7, "username": "ada" }),
+        }
+    }
+
+    #[test]
+    fn saves_to_an_encrypted_file_without_a_secret_service() {
+        let root = test_support::root("vault", "file");
+        let vault = vault(&root, NoSecrets);
+        assert_eq!(vault.save(&record()).unwrap(), Backend::EncryptedFile);
+
+        let bytes = fs::read(vault.vault_path()).unwrap();
+        assert_eq!(&bytes[..MAGIC.len()], MAGIC);
+        assert!(!bytes.windows(12).any(|window| window == b"secret-token"));
+
+        // A fresh vault has nothing cached, so this reads the file
+        let reopened = self::vault(&root, NoSecrets);
+        assert_eq!(reopened.load().unwrap(), Some(record()));
+
+        reopened.clear().unwrap();
+        assert!(!reopened.vault_path().exists());
+        assert_eq!(reopened.load().unwrap(), None);
+        assert_eq!(self::vault(&root, NoSecrets).load().unwrap(), None);
+    }
+
+    #[test]
+    fn rejects_a_corrupt_or_foreign_vault() {
+        let root = test_support::root("vault", "corrupt");
+        vault(&root, NoSecrets).save(&record()).unwrap();
+        let path = root.join("config").join(VAULT_FILE);
+        let sealed = fs::read(&path).unwrap();
+
+        let mut flipped = sealed.clone();
+        *flipped.last_mut().unwrap() ^= 1;
+        fs::write(&path, &flipped).unwrap();
+        assert!(matches!(
+            vault(&root, NoSecrets).load(),
+            Err(Error::Decrypt)
+        ));
+
+        fs::write(&path, b"CHV1 too short").unwrap();
+        assert!(matches!(
+            vault(&root, NoSecrets).load(),
+            Err(Error::Decrypt)
+        ));
+
+        let mut foreign = sealed.clone();
+        foreign[..MAGIC.len()].copy_from_slice(b"XXXX");
+        fs::write(&path, &foreign).unwrap();
+        assert!(matches!(
+            vault(&root, NoSecrets).load(),
+            Err(Error::Decrypt)
+        ));
+
+        // Sealed with another machine's key
+        fs::write(&path, &sealed).unwrap();
+        let key_path = root.join("data").join(KEY_FILE);
+        fs::write(&key_path, XChaCha20Poly1305::generate_key(&mut OsRng)).unwrap();
+        assert!(matches!(
+            vault(&root, NoSecrets).load(),
+            Err(Error::Decrypt)
+        ));
+
+        // Without any key the file can't be read, which is signed out
+        fs::remove_file(&key_path).unwrap();
+        assert_eq!(vault(&root, NoSecrets).load().unwrap(), None);
+    }
+
+    #[test]
+    fn reads_the_keyring_once() {
+        let root = test_support::root("vault", "keyring");
+        let keyring = FakeKeyring::default();
+        let vault = vault(&root, keyring.clone());
+        assert_eq!(vault.save(&record()).unwrap(), Backend::SecretService);
+        assert!(!vault.vault_path().exists());
+        for _ in 0..3 {
+            assert_eq!(vault.load().unwrap(), Some(record()));
+        }
+        assert_eq!(keyring.reads.load(Ordering::SeqCst), 0);
+
+        let reopened = self::vault(&root, keyring.clone());
+        for _ in 0..3 {
+            assert_eq!(reopened.load().unwrap(), Some(record()));
+        }
+        assert_eq!(keyring.reads.load(Ordering::SeqCst), 1);
+
+        reopened.clear().unwrap();
+        assert_eq!(*keyring.secret.lock().unwrap(), None);
+        assert_eq!(reopened.load().unwrap(), None);
+    }
+
+    #[test]
+    fn a_refused_keyring_write_drops_the_older_entry() {
+        let root = test_support::root("vault", "refused");
+        let keyring = FakeKeyring::default();
+        let vault = vault(&root, keyring.clone());
+        assert_eq!(vault.save(&record()).unwrap(), Backend::SecretService);
+
+        keyring.refuse.store(true, Ordering::SeqCst);
+        let newer = AuthRecord {
+            token: "newer-token".to_string(),
+            user: serde_json::Value::Null,
+        };
+        assert_eq!(vault.save(&newer).unwrap(), Backend::EncryptedFile);
+        assert_eq!(*keyring.secret.lock().unwrap(), None);
+        // The next launch reads the file, not the stale keyring entry
+        assert_eq!(self::vault(&root, keyring).load().unwrap(), Some(newer));
+    }
+
+    #[test]
+    fn holds_a_sign_in_that_isnt_remembered() {
+        let root = test_support::root("vault", "hold");
+        let keyring = FakeKeyring::default();
+        let vault = vault(&root, keyring.clone());
+        vault.save(&record()).unwrap();
+
+        let held = AuthRecord {
+            token: "held".to_string(),
+            user: serde_json::Value::Null,
+        };
+        vault.hold(held.clone()).unwrap();
+        assert_eq!(vault.load().unwrap(), Some(held));
+        assert_eq!(*keyring.secret.lock().unwrap(), None);
+        assert_eq!(self::vault(&root, keyring).load().unwrap(), None);
+    }
+
+    #[test]
+    fn migrates_and_scrubs_legacy_auth() {
+        let root = test_support::root("vault", "legacy");
+        let vault = vault(&root, NoSecrets);
+        let legacy = root.join("config").join(LEGACY_AUTH_FILE);
+        assert!(!vault.migrate_legacy().unwrap());
+
+        fs::create_dir_all(legacy.parent().unwrap()).unwrap();
+        fs::write(&legacy, serde_json::to_vec(&record()).unwrap()).unwrap();
+        assert!(vault.migrate_legacy().unwrap());
+        assert!(!legacy.exists());
+        assert_eq!(
+            self::vault(&root, NoSecrets).load().unwrap(),
+            Some(record())
+        );
+
+        // Nothing to migrate, but still plaintext that shouldn't stay
+        for contents in [&br#"{"user":{"id":7}}"#[..], b"not json"] {
+            fs::write(&legacy, contents).unwrap();
+            assert!(!vault.migrate_legacy().unwrap());
+            assert!(!legacy.exists());
+        }
+    }
+}
@@ -0,0 +1,199 @@
+//! Window size, position and maximized state, remembered per window label.
+//!
+//! Geometry is captured as windows move and resize and written to
+//! window-state.json once they settle, or right away when one closes. A
+//! window is restored before it's first shown, fitted to the monitors that
+//! are connected now ([`geometry`]) so it never opens off-screen.
+
+mod geometry;
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use tauri::{
+    AppHandle, GlobalWindowEvent, Manager, PhysicalPosition, PhysicalSize, Window, WindowEvent,
+};
+
+use crate::overlay::OVERLAY_WINDOW;
+use geometry::{Geometry, Rect};
+
+const STATE_FILE: &str = "window-state.json";
+/// Moving or resizing fires an event per frame; wait for it to stop.
+const SAVE_DELAY: Duration = Duration::from_millis(500);
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("window state io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("window state is malformed: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("app directories are unavailable")]
+    NoAppDir,
+}
+
+pub struct WindowState {
+    path: PathBuf,
+    windows: Mutex<HashMap<String, Geometry>>,
+    // Bumped on every change so only the last scheduled save writes
+    generation: AtomicU64,
+}
+
+impl WindowState {
+    pub fn new(path: PathBuf) -> Self {
+        // A missing or unreadable file just means the configured defaults
+        let windows: HashMap<String, Geometry> = fs::read(&path)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .unwrap_or_default();
+        Self {
+            path,
+            windows: Mutex::new(windows),
+            generation: AtomicU64::new(0),
+        }
+    }
+
+    pub fn from_app(app: &AppHandle) -> Result<Self, Error> {
+        let dir = app
+            .path_resolver()
+            .app_config_dir()
+            .ok_or(Error::NoAppDir)?;
+        Ok(Self::new(dir.join(STATE_FILE)))
+    }
+
+    /// Puts a window back where it was last left, or leaves the size from
+    /// tauri.conf.json if it's never been seen.
+    pub fn restore(&self, window: &Window) {
+        let Some(saved) = self.windows.lock().unwrap().get(window.label()).copied() else {
+            return;
+        };
+        let geometry = geometry::fit(saved, &monitors(window));
+        // The saved size includes the frame, which is what has to fit on the
+        // monitor, but windows are sized by their content
+        let (frame_width, frame_height) = match (window.outer_size(), window.inner_size()) {
+            (Ok(outer), Ok(inner)) => (
+                outer.width.saturating_sub(inner.width),
+                outer.height.saturating_sub(inner.height),
+            ),
+            _ => (0, 0),
+        };
+        let _ = window.set_size(PhysicalSize::new(
+            geometry.width.saturating_sub(frame_width),
+            geometry.height.saturating_sub(frame_height),
+        ));
+        let _ = window.set_position(PhysicalPosition::new(geometry.x, geometry.y));
+        if geometry.maximized {
+            let _ = window.maximize();
+        }
+    }
+
+    /// Writes everything captured so far.
+    pub fn save(&self) -> Result<(), Error> {
+        let windows = self.windows.lock().unwrap();
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&self.path, serde_json::to_vec_pretty(&*windows)?)?;
+        Ok(())
+    }
+
+    /// Records where a window is now. Returns whether anything changed.
+    fn capture(&self, window: &Window) -> bool {
+        // Minimizing moves some windows far off-screen; keep the last
+        // position they were visible at
+        if window.is_minimized().unwrap_or(false) {
+            return false;
+        }
+        let (Ok(maximized), Ok(position), Ok(size)) = (
+            window.is_maximized(),
+            window.outer_position(),
+            window.outer_size(),
+        ) else {
+            return false;
+        };
+
+        let mut windows = self.windows.lock().unwrap();
+        let previous = windows.get(window.label()).copied();
+        let geometry = match previous {
+            // The maximized bounds are the monitor's; keep the size to
+            // unmaximize back to
+            Some(previous) if maximized => Geometry {
+                maximized: true,
+                ..previous
+            },
+            _ => Geometry {
+                x: position.x,
+                y: position.y,
+                width: size.width,
+                height: size.height,
+                maximized,
+            },
+        };
+        if previous == Some(geometry) {
+            return false;
+        }
+        windows.insert(window.label().to_string(), geometry);
+        true
+    }
+
+    fn schedule_save(&self, app: &AppHandle) {
+        let generation = self.generation.fetch_add(1, Ordering::SeqCst) + 1;
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            tokio::time::sleep(SAVE_DELAY).await;
+            let state = app.state::<WindowState>();
+            if state.generation.load(Ordering::SeqCst) == generation {
+                if let Err(e) = state.save() {
+                    log!("[WindowState] Failed to save window state: {}", e);
+                }
+            }
+        });
+    }
+}
+
+/// The connected monitors, primary first, in physical pixels.
+fn monitors(window: &Window) -> Vec<Rect> {
+    let rect = |monitor: &tauri::Monitor| Rect {
+        x: monitor.position().x,
+        y: monitor.position().y,
+        width: monitor.size().width,
+        height: monitor.size().height,
+    };
+    let primary = window.primary_monitor().ok().flatten().map(|m| rect(&m));
+    let others: Vec<Rect> = window
+        .available_monitors()
+        .unwrap_or_default()
+        .iter()
+        .map(rect)
+        .filter(|monitor| Some(*monitor) != primary)
+        .collect();
+    primary.into_iter().chain(others).collect()
+}
+
+pub fn handle_window_event(event: &GlobalWindowEvent) {
+    let window = event.window();
+    // Pinned to a corner by the overlay itself whenever it's shown
+    if window.label() == OVERLAY_WINDOW {
+        return;
+    }
+    let app = window.app_handle();
+    let state = app.state::<WindowState>();
+    match event.event() {
+        WindowEvent::Moved(_) | WindowEvent::Resized(_) if state.capture(window) => {
+            state.schedule_save(&app);
+        }
+        // Hiding or destroying it comes next; don't wait for a timer that
+        // may not fire before exit
+        WindowEvent::CloseRequested { .. } => {
+            state.capture(window);
+            state.generation.fetch_add(1, Ordering::SeqCst);
+            if let Err(e) = state.save() {
+                log!("[WindowState] Failed to save window state: {}", e);
+            }
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,209 @@
+//! Saved window geometry and fitting it onto the monitors that are
+//! connected now. Free of Tauri so layouts can be checked without a display.
+//!
+//! Everything is in physical pixels on the virtual desktop, which is what
+//! the window and monitor APIs report. Window bounds are the outer ones,
+//! title bar and borders included, so they compare with monitor bounds.
+
+use serde::{Deserialize, Serialize};
+
+/// How much of the window has to stay on a monitor, measured from its top
+/// left, for the title bar to still be reachable.
+const MIN_VISIBLE_WIDTH: i64 = 120;
+const MIN_VISIBLE_HEIGHT: i64 = 40;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Geometry {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    /// The other fields keep the restored size so unmaximizing goes back
+    /// to it.
+    #[serde(default)]
+    pub maximized: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rect {
+    fn right(&self) -> i64 {
+        self.x as i64 + self.width as i64
+    }
+
+    fn bottom(&self) -> i64 {
+        self.y as i64 + self.height as i64
+    }
+
+    /// Width and height of the overlap with `other`, zero if none.
+    fn overlap(&self, other: &Rect) -> (i64, i64) {
+        let width = self.right().min(other.right()) - (self.x.max(other.x) as i64);
+        let height = self.bottom().min(other.bottom()) - (self.y.max(other.y) as i64);
+        (width.max(0), height.max(0))
+    }
+}
+
+impl Geometry {
+    fn rect(&self) -> Rect {
+        Rect {
+            x: self.x,
+            y: self.y,
+            width: self.width,
+            height: self.height,
+        }
+    }
+}
+
+/// Moves and shrinks `geometry` so it opens on one of `monitors`, the
+/// primary one first. A window whose title bar is still reachable is pulled
+/// fully onto the monitor it mostly covers; one left on an unplugged monitor
+/// is centered on the primary.
+pub fn fit(geometry: Geometry, monitors: &[Rect]) -> Geometry {
+    // Can't tell where anything is; trust the saved state
+    let Some(primary) = monitors.first() else {
+        return geometry;
+    };
+
+    let window = geometry.rect();
+    let reachable = monitors
+        .iter()
+        .map(|monitor| (monitor, window.overlap(monitor)))
+        .filter(|(monitor, (width, height))| {
+            *width >= MIN_VISIBLE_WIDTH.min(window.width as i64)
+                && *height >= MIN_VISIBLE_HEIGHT.min(window.height as i64)
+                && (monitor.y as i64..monitor.bottom()).contains(&(window.y as i64))
+        })
+        .max_by_key(|(_, (width, height))| width * height)
+        .map(|(monitor, _)| monitor);
+
+    let monitor = reachable.unwrap_or(primary);
+    let width = geometry.width.min(monitor.width);
+    let height = geometry.height.min(monitor.height);
+
+    let (x, y) = if reachable.is_some() {
+        // Pull it fully onto the monitor where it pokes off an edge
+        (
+            clamp(geometry.x, monitor.x, monitor.right() - width as i64),
+            clamp(geometry.y, monitor.y, monitor.bottom() - height as i64),
+        )
+    } else {
+        (
+            (monitor.x as i64 + (monitor.width - width) as i64 / 2) as i32,
+            (monitor.y as i64 + (monitor.height - height) as i64 / 2) as i32,
+        )
+    };
+
+    Geometry {
+        x,
+        y,
+        width,
+        height,
+        maximized: geometry.maximized,
+    }
+}
+
+fn clamp(value: i32, min: i32, max: i64) -> i32 {
+    (value as i64).clamp(min as i64, max.max(min as i64)) as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PRIMARY: Rect = Rect {
+        x: 0,
+        y: 0,
+        width: 1920,
+        height: 1080,
+    };
+    // To the right of the primary, lower and smaller
+    const SECOND: Rect = Rect {
+        x: 1920,
+        y: 200,
+        width: 1280,
+        height: 720,
+    };
+
+    fn at(x: i32, y: i32, width: u32, height: u32) -> Geometry {
+        Geometry {
+            x,
+            y,
+            width,
+            height,
+            maximized: false,
+        }
+    }
+
+    #[test]
+    fn leaves_a_window_that_fits() {
+        let geometry = at(100, 100, 1200, 800);
+        assert_eq!(fit(geometry, &[PRIMARY, SECOND]), geometry);
+        let geometry = at(2000, 300, 800, 600);
+        assert_eq!(fit(geometry, &[PRIMARY, SECOND]), geometry);
+    }
+
+    #[test]
+    fn trusts_the_saved_state_without_monitors() {
+        let geometry = at(-5000, -5000, 800, 600);
+        assert_eq!(fit(geometry, &[]), geometry);
+    }
+
+    #[test]
+    fn pulls_a_window_off_an_edge_back_on() {
+        assert_eq!(
+            fit(at(1500, 600, 800, 600), &[PRIMARY]),
+            at(1120, 480, 800, 600)
+        );
+        // Title bar above the top of the monitor can't be reached
+        assert_eq!(
+            fit(at(-200, -10, 800, 600), &[PRIMARY]),
+            at(560, 240, 800, 600)
+        );
+    }
+
+    #[test]
+    fn moves_to_the_monitor_it_mostly_covers() {
+        // Mostly on the second monitor, poking off its bottom
+        assert_eq!(
+            fit(at(1800, 500, 900, 600), &[PRIMARY, SECOND]),
+            at(1920, 320, 900, 600)
+        );
+    }
+
+    #[test]
+    fn shrinks_a_window_larger_than_its_monitor() {
+        assert_eq!(
+            fit(at(1950, 250, 1600, 900), &[PRIMARY, SECOND]),
+            at(1920, 200, 1280, 720)
+        );
+    }
+
+    #[test]
+    fn centers_a_window_left_on_an_unplugged_monitor() {
+        assert_eq!(
+            fit(at(2200, 300, 800, 600), &[PRIMARY]),
+            at(560, 240, 800, 600)
+        );
+        // Shrunk to the primary as well
+        assert_eq!(
+            fit(at(4000, 0, 2560, 1440), &[PRIMARY]),
+            at(0, 0, 1920, 1080)
+        );
+    }
+
+    #[test]
+    fn keeps_the_maximized_flag() {
+        let geometry = Geometry {
+            maximized: true,
+            ..at(2200, 300, 800, 600)
+        };
+        assert!(fit(geometry, &[PRIMARY]).maximized);
+    }
+}
@@ -8,11 +8,34 @@ import SettingsModal from './components/SettingsModal'
 import ServerSettingsModal from './components/ServerSettingsModal'
 import FriendsPanel from './components/FriendsPanel'
 import UpdateNotification from './components/UpdateNotification'
+import QuickSwitcher from './components/QuickSwitcher'
+import ExternalLinkModal from './components/ExternalLinkModal'
 import { useAuthStore } from './stores/auth'
 import { useServersStore } from './stores/servers'
 import { useDirectMessagesStore } from './stores/directMessages'
 import { useStatusStore } from './stores/status'
 import { wsManager } from './services/websocket-manager'
+import { trayService } from './services/tray'
+import { attentionService } from './services/attention'
+import { backgroundService } from './services/background'
+import { hotkeyService } from './services/hotkeys'
+import { idleService } from './services/idle'
+import { notificationService } from './services/notifications'
+import { deepLinkService } from './services/deeplink'
+import { streamRelayService } from './services/popout-stream'
+import { overlayService } from './services/overlay'
+import { menuService } from './services/menu'
+import { downloadService } from './services/downloads'
+import { linkService } from './services/links'
+import {
+  NAVIGATE_EVENT,
+  FOCUS_INPUT_EVENT,
+  JUMP_TO_MESSAGE_EVENT,
+  JOIN_INVITE_EVENT,
+  QUICK_SWITCHER_EVENT,
+  NavigationRequest,
+} from './services/navigation'
+import { useChannelsStore } from './stores/channels'
 import type { Server, Channel } from './services/api'
 import './app.css'
 
@@ -39,9 +62,11 @@ function App() {
   const [selectedServer, setSelectedServer] = useState<Server | null>(null)
   const [selectedDMUserId, setSelectedDMUserId] = useState<number | null>(null)
   const [showServerModal, setShowServerModal] = useState(false)
+  const [inviteCode, setInviteCode] = useState<string | null>(null)
   const [showChannelModal, setShowChannelModal] = useState(false)
   const [showServerSettings, setShowServerSettings] = useState(false)
   const [showAppSettings, setShowAppSettings] = useState(false)
+  const [showQuickSwitcher, setShowQuickSwitcher] = useState(false)
   const [showFriendsPanel, setShowFriendsPanel] = useState(false)
   const [showUpdateNotification, setShowUpdateNotification] = useState(false)
 
@@ -64,6 +89,42 @@ function App() {
         // Initialize WebSocket manager
         wsManager.initialize()
 
+        // Keep the system tray in sync with status, unread and voice state
+        trayService.initialize()
+
+        // Badge the window icon and request attention for unread messages
+        attentionService.initialize()
+
+        // Tell the backend what closing the window should do
+        backgroundService.initialize()
+
+        // Global voice shortcuts that work while the window is unfocused
+        hotkeyService.initialize()
+
+        // Automatic idle from system-wide input rather than in-app activity
+        idleService.initialize()
+
+        // Route clicks on native notifications back into the app
+        notificationService.initialize()
+
+        // Open commhub:// invite and message links
+        deepLinkService.initialize()
+
+        // Feed video to streams popped out into their own windows
+        streamRelayService.initialize()
+
+        // Show who's talking in the always-on-top voice overlay
+        overlayService.initialize()
+
+        // Apply items and shortcuts from the native application menu
+        menuService.initialize()
+
+        // Save attachments to disk and keep viewed media in the local cache
+        downloadService.initialize()
+
+        // Open clicked links outside the app, checking them first
+        linkService.initialize()
+
         // Check authentication status
         await useAuthStore.getState().checkAuth()
 
@@ -116,6 +177,71 @@ function App() {
     }
   }, [])
 
+  // Open conversations requested from outside the React tree
+  useEffect(() => {
+    const handleNavigate = async (event: CustomEvent<NavigationRequest>) => {
+      const { target, focusInput, messageId } = event.detail
+      if (target.kind === 'direct') {
+        handleDMSelect(target.userId)
+      } else {
+        const findChannel = () =>
+          useChannelsStore.getState().channels.find((c) => c.id === target.channelId)
+        if (!findChannel()) {
+          await useChannelsStore.getState().fetchChannels()
+        }
+        const channel = findChannel()
+        if (useServersStore.getState().servers.length === 0) {
+          await useServersStore.getState().fetchServers()
+        }
+        const server = useServersStore.getState().servers.find((s) => s.id === channel?.serverId)
+        if (!channel || !server) {
+          return
+        }
+        setSelectedServer(server)
+        setSelectedChannel(channel)
+        setShowFriendsPanel(false)
+        if (messageId !== undefined) {
+          const detail = { channelId: channel.id, messageId }
+          setTimeout(
+            () => window.dispatchEvent(new CustomEvent(JUMP_TO_MESSAGE_EVENT, { detail })),
+            0
+          )
+        }
+      }
+      if (focusInput) {
+        // Let the conversation render before moving focus
+        setTimeout(() => window.dispatchEvent(new Event(FOCUS_INPUT_EVENT)), 0)
+      }
+    }
+
+    const handleJoinInvite = (event: CustomEvent<string>) => {
+      setInviteCode(event.detail)
+      setShowServerModal(true)
+    }
+
+    const handleQuickSwitcher = () => setShowQuickSwitcher(true)
+
+    window.addEventListener(NAVIGATE_EVENT, handleNavigate as unknown as EventListener)
+    window.addEventListener(JOIN_INVITE_EVENT, handleJoinInvite as EventListener)
+    window.addEventListener(QUICK_SWITCHER_EVENT, handleQuickSwitcher)
+    return () => {
+      window.removeEventListener(NAVIGATE_EVENT, handleNavigate as unknown as EventListener)
+      window.removeEventListener(JOIN_INVITE_EVENT, handleJoinInvite as EventListener)
+      window.removeEventListener(QUICK_SWITCHER_EVENT, handleQuickSwitcher)
+    }
+  }, [])
+
+  // Keep native notifications quiet for the conversation on screen
+  useEffect(() => {
+    if (showFriendsPanel && selectedDMUserId !== null) {
+      notificationService.setViewing({ kind: 'direct', userId: selectedDMUserId })
+    } else if (!showFriendsPanel && selectedChannel?.type === 'text') {
+      notificationService.setViewing({ kind: 'channel', channelId: selectedChannel.id })
+    } else {
+      notificationService.setViewing(null)
+    }
+  }, [showFriendsPanel, selectedDMUserId, selectedChannel])
+
   // Join server websocket room when server is selected
   useEffect(() => {
     if (selectedServer) {
@@ -233,13 +359,21 @@ function App() {
         </main>
 
         {/* Modals */}
-        <ServerModal isOpen={showServerModal} onClose={() => setShowServerModal(false)} />
+        <ServerModal
+          isOpen={showServerModal}
+          initialInviteCode={inviteCode}
+          onClose={() => {
+            setShowServerModal(false)
+            setInviteCode(null)
+          }}
+        />
         <ChannelModal
           isOpen={showChannelModal}
           serverId={selectedServer?.id || null}
           onClose={() => setShowChannelModal(false)}
         />
         <SettingsModal isOpen={showAppSettings} onClose={() => setShowAppSettings(false)} />
+        <QuickSwitcher isOpen={showQuickSwitcher} onClose={() => setShowQuickSwitcher(false)} />
 
         {/* Server Settings Modal */}
         <ServerSettingsModal
@@ -252,6 +386,9 @@ function App() {
         {showUpdateNotification && (
           <UpdateNotification onDismiss={handleDismissUpdateNotification} />
         )}
+
+        {/* Confirmation for links that could mislead */}
+        <ExternalLinkModal />
       </>
     )
   }
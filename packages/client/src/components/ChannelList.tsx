@@ -23,6 +23,7 @@ import { useVoiceStore } from '../stores/voice'
 import { useVoiceMembersStore } from '../stores/voiceMembers'
 import { useMentionsStore } from '../stores/mentions'
 import { apiService, type Conversation } from '../services/api'
+import { deepLinkService } from '../services/deeplink'
 import StatusIndicator from './StatusIndicator'
 import VoiceStatus from './VoiceStatus'
 import type { Channel, Server } from '../services/api'
@@ -63,6 +64,7 @@ const ChannelList: React.FC<ChannelListProps> = ({
   const [showServerMenu, setShowServerMenu] = React.useState(false)
   const [inviteCode, setInviteCode] = React.useState<string | null>(null)
   const [copySuccess, setCopySuccess] = React.useState(false)
+  const [copyLinkSuccess, setCopyLinkSuccess] = React.useState(false)
   const [contextMenuChannelId, setContextMenuChannelId] = React.useState<number | null>(null)
   const [editingChannel, setEditingChannel] = React.useState<Channel | null>(null)
   const [editChannelName, setEditChannelName] = React.useState('')
@@ -168,6 +170,14 @@ const ChannelList: React.FC<ChannelListProps> = ({
     }
   }
 
+  const handleCopyInviteLink = async () => {
+    if (inviteCode) {
+      await deepLinkService.copy({ kind: 'invite', code: inviteCode })
+      setCopyLinkSuccess(true)
+      setTimeout(() => setCopyLinkSuccess(false), 2000)
+    }
+  }
+
   const handleEditChannel = (channel: Channel) => {
     setEditingChannel(channel)
     setEditChannelName(channel.name)
@@ -305,6 +315,12 @@ const ChannelList: React.FC<ChannelListProps> = ({
                     {copySuccess ? 'Copied!' : 'Copy'}
                   </button>
                 </div>
+                <button
+                  onClick={handleCopyInviteLink}
+                  className="w-full mt-2 px-4 py-2 bg-grey-800 text-white border-2 border-grey-700 hover:border-white transition-colors font-medium"
+                >
+                  {copyLinkSuccess ? 'Link Copied!' : 'Copy Invite Link'}
+                </button>
               </div>
               <div className="border-t-2 border-grey-800 p-4 flex justify-end">
                 <button
@@ -1,5 +1,5 @@
 import React, { useEffect, useRef, useState } from 'react'
-import { Hash, Users, Volume2, PhoneCall, ChevronDown } from 'lucide-react'
+import { Hash, Users, Volume2, PhoneCall, ChevronDown, ExternalLink } from 'lucide-react'
 import { useMessagesStore } from '../stores/messages'
 import { useAuthStore } from '../stores/auth'
 import { useVoiceStore } from '../stores/voice'
@@ -18,6 +18,8 @@ import { MessageItem } from './chat/MessageItem'
 import type { Channel, Server, Message } from '../services/api'
 import { apiService } from '../services/api'
 import { wsService } from '../services/websocket'
+import { JUMP_TO_MESSAGE_EVENT } from '../services/navigation'
+import { popoutService, isPopoutWindow } from '../services/popout'
 
 interface ChatAreaProps {
   selectedChannel: Channel | null
@@ -35,6 +37,10 @@ const ChatArea: React.FC<ChatAreaProps> = ({ selectedChannel, server }) => {
   const [showEmojiPicker, setShowEmojiPicker] = useState(false)
   const [replyingTo, setReplyingTo] = useState<Message | null>(null)
   const [isScrolledUp, setIsScrolledUp] = useState(false)
+  const [jumpTarget, setJumpTarget] = useState<{ channelId: number; messageId: number } | null>(
+    null
+  )
+  const [highlightedMessageId, setHighlightedMessageId] = useState<number | null>(null)
   const messagesEndRef = useRef<HTMLDivElement>(null)
   const messagesContainerRef = useRef<HTMLDivElement>(null)
   const isInitialChannelLoad = useRef(true)
@@ -158,6 +164,39 @@ const ChatArea: React.FC<ChatAreaProps> = ({ selectedChannel, server }) => {
     }
   }, [selectedChannel?.id, messages.length])
 
+  // Message links open the channel first, then ask for the message
+  useEffect(() => {
+    const handleJump = (event: CustomEvent<{ channelId: number; messageId: number }>) => {
+      setJumpTarget(event.detail)
+    }
+
+    window.addEventListener(JUMP_TO_MESSAGE_EVENT, handleJump as EventListener)
+    return () => window.removeEventListener(JUMP_TO_MESSAGE_EVENT, handleJump as EventListener)
+  }, [])
+
+  // Scroll to the linked message once it has loaded
+  useEffect(() => {
+    if (!jumpTarget || jumpTarget.channelId !== selectedChannel?.id) return
+    const element = messagesContainerRef.current?.querySelector(
+      `[data-message-id="${jumpTarget.messageId}"]`
+    )
+    if (!element) return
+
+    setJumpTarget(null)
+    setIsScrolledUp(true)
+    setHighlightedMessageId(jumpTarget.messageId)
+    // After the initial scroll to the bottom has run
+    requestAnimationFrame(() => {
+      requestAnimationFrame(() => element.scrollIntoView({ block: 'center' }))
+    })
+  }, [jumpTarget, selectedChannel?.id, messages])
+
+  useEffect(() => {
+    if (highlightedMessageId === null) return
+    const timeout = setTimeout(() => setHighlightedMessageId(null), 2000)
+    return () => clearTimeout(timeout)
+  }, [highlightedMessageId])
+
   // Initialize voice manager
   useEffect(() => {
     voiceManager.initialize()
@@ -385,6 +424,7 @@ const ChatArea: React.FC<ChatAreaProps> = ({ selectedChannel, server }) => {
   }
 
   const isServerOwner = !!(server && user && server.ownerId === user.id)
+  const canPopOut = popoutService.isAvailable && !isPopoutWindow()
 
   // Close context menu when clicking outside
   useEffect(() => {
@@ -495,6 +535,21 @@ const ChatArea: React.FC<ChatAreaProps> = ({ selectedChannel, server }) => {
                 stream={focusedStream}
                 isScreenShare={focusedUser.hasScreenShare}
                 onClose={() => useVoiceStore.getState().setFocusedStreamUserId(null)}
+                onPopOut={
+                  canPopOut
+                    ? () => {
+                        popoutService.open(
+                          {
+                            kind: 'stream',
+                            channelId: selectedChannel.id,
+                            userId: focusedUser.userId,
+                          },
+                          focusedUser.username
+                        )
+                        useVoiceStore.getState().setFocusedStreamUserId(null)
+                      }
+                    : undefined
+                }
               />
             ) : (
               <VoiceChannelParticipants />
@@ -522,19 +577,35 @@ const ChatArea: React.FC<ChatAreaProps> = ({ selectedChannel, server }) => {
             <Hash className="w-5 h-5 text-grey-400" />
             <h2 className="font-bold text-white text-lg">{selectedChannel.name}</h2>
           </div>
-          {server && (
-            <button
-              onClick={() => setShowMembersPane(!showMembersPane)}
-              className={`p-2 transition-colors border-2 ${
-                showMembersPane
-                  ? 'text-white bg-grey-850 border-grey-700'
-                  : 'text-grey-400 hover:text-white hover:bg-grey-850 border-transparent hover:border-grey-700'
-              }`}
-              title={showMembersPane ? 'Hide Members' : 'Show Members'}
-            >
-              <Users className="w-5 h-5" />
-            </button>
-          )}
+          <div className="flex items-center gap-1">
+            {canPopOut && (
+              <button
+                onClick={() =>
+                  popoutService.open(
+                    { kind: 'channel', channelId: selectedChannel.id },
+                    `#${selectedChannel.name}`
+                  )
+                }
+                className="p-2 text-grey-400 hover:text-white hover:bg-grey-850 border-2 border-transparent hover:border-grey-700 transition-colors"
+                title="Open in new window"
+              >
+                <ExternalLink className="w-5 h-5" />
+              </button>
+            )}
+            {server && (
+              <button
+                onClick={() => setShowMembersPane(!showMembersPane)}
+                className={`p-2 transition-colors border-2 ${
+                  showMembersPane
+                    ? 'text-white bg-grey-850 border-grey-700'
+                    : 'text-grey-400 hover:text-white hover:bg-grey-850 border-transparent hover:border-grey-700'
+                }`}
+                title={showMembersPane ? 'Hide Members' : 'Show Members'}
+              >
+                <Users className="w-5 h-5" />
+              </button>
+            )}
+          </div>
         </div>
 
         {/* Messages Area */}
@@ -573,29 +644,36 @@ const ChatArea: React.FC<ChatAreaProps> = ({ selectedChannel, server }) => {
                 const isEditing = editingMessageId === message.id
 
                 return (
-                  <MessageItem
+                  <div
                     key={`message-${message.id}-${index}`}
-                    message={message}
-                    showUserInfo={showUserInfo}
-                    isOwnMessage={isOwnMessage}
-                    canModify={canModify}
-                    canDelete={canDelete}
-                    isEditing={isEditing}
-                    editContent={editContent}
-                    setEditContent={setEditContent}
-                    onEditMessage={handleEditMessage}
-                    onCancelEditing={cancelEditing}
-                    onDeleteMessage={handleDeleteMessage}
-                    onReplyTo={handleReplyTo}
-                    onStartEditing={startEditingMessage}
-                    formatTimestamp={formatTimestamp}
-                    formatTimeOnly={formatTimeOnly}
-                    isUserBlocked={isUserBlocked}
-                    contextMenuMessageId={contextMenuMessageId}
-                    setContextMenuMessageId={setContextMenuMessageId}
-                    editAttachmentsToRemove={editAttachmentsToRemove}
-                    onRemoveAttachmentFromEdit={handleRemoveAttachmentFromEdit}
-                  />
+                    data-message-id={message.id}
+                    className={`transition-colors ${
+                      highlightedMessageId === message.id ? 'bg-grey-800' : ''
+                    }`}
+                  >
+                    <MessageItem
+                      message={message}
+                      showUserInfo={showUserInfo}
+                      isOwnMessage={isOwnMessage}
+                      canModify={canModify}
+                      canDelete={canDelete}
+                      isEditing={isEditing}
+                      editContent={editContent}
+                      setEditContent={setEditContent}
+                      onEditMessage={handleEditMessage}
+                      onCancelEditing={cancelEditing}
+                      onDeleteMessage={handleDeleteMessage}
+                      onReplyTo={handleReplyTo}
+                      onStartEditing={startEditingMessage}
+                      formatTimestamp={formatTimestamp}
+                      formatTimeOnly={formatTimeOnly}
+                      isUserBlocked={isUserBlocked}
+                      contextMenuMessageId={contextMenuMessageId}
+                      setContextMenuMessageId={setContextMenuMessageId}
+                      editAttachmentsToRemove={editAttachmentsToRemove}
+                      onRemoveAttachmentFromEdit={handleRemoveAttachmentFromEdit}
+                    />
+                  </div>
                 )
               })}
             </div>
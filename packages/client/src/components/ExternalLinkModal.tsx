@@ -0,0 +1,132 @@
+import React, { useEffect, useState } from 'react'
+import { X, AlertTriangle, ExternalLink } from 'lucide-react'
+import { useLinksStore } from '../stores/links'
+import { linkService, type LinkRisk } from '../services/links'
+import { logger } from '../utils/logger'
+
+function describeRisk(risk: LinkRisk): string {
+  switch (risk.kind) {
+    case 'mixedScript':
+      return `${risk.domain} mixes letters from different alphabets, which can make it look like another site.`
+    case 'confusable':
+      return `${risk.domain} looks like ${risk.resembles}, but it isn't.`
+    case 'address':
+      return `This link goes to the address ${risk.domain} instead of a named site.`
+    case 'userInfo':
+      return `The link starts with "${risk.text}", which isn't where it goes.`
+    case 'shortened':
+      return 'This is a shortened link, which hides where it goes.'
+  }
+}
+
+const ExternalLinkModal: React.FC = () => {
+  const pending = useLinksStore((state) => state.pending)
+  const setPending = useLinksStore((state) => state.setPending)
+  const [alwaysTrust, setAlwaysTrust] = useState(false)
+
+  useEffect(() => {
+    setAlwaysTrust(false)
+  }, [pending])
+
+  if (!pending) return null
+
+  const handleClose = () => setPending(null)
+
+  const handleOpen = async () => {
+    setPending(null)
+    if (alwaysTrust && pending.trustDomain) {
+      await linkService.trust(pending.trustDomain).catch((error) => {
+        logger.warn('Links', 'Failed to trust domain', { domain: pending.trustDomain, error })
+      })
+    }
+    await linkService.open(pending.url, true)
+  }
+
+  const asciiDiffers =
+    pending.domain !== null &&
+    pending.displayDomain !== null &&
+    pending.domain !== pending.displayDomain
+
+  return (
+    <div className="fixed inset-0 bg-black/80 flex items-center justify-center z-50 animate-fade-in p-4">
+      <div className="bg-grey-900 border-2 border-white w-full max-w-md animate-slide-up">
+        {/* Header */}
+        <div className="border-b-2 border-grey-800 p-4 flex items-center justify-between">
+          <h2 className="font-bold text-white text-xl flex items-center gap-2">
+            <AlertTriangle className="w-5 h-5 text-yellow-400" />
+            Check this link
+          </h2>
+          <button
+            onClick={handleClose}
+            className="p-1 text-grey-400 hover:text-white transition-colors"
+          >
+            <X className="w-5 h-5" />
+          </button>
+        </div>
+
+        {/* Content */}
+        <div className="p-6 space-y-4">
+          <div>
+            <label className="block text-grey-300 text-sm font-bold mb-2">
+              {pending.scheme === 'mailto' ? 'EMAIL DOMAIN' : 'SITE'}
+            </label>
+            <p className="text-white font-bold break-all">
+              {pending.displayDomain ?? pending.url}
+            </p>
+            {asciiDiffers && (
+              <p className="text-grey-500 text-xs mt-1 break-all">Spelled as {pending.domain}</p>
+            )}
+          </div>
+
+          {pending.destination && (
+            <div>
+              <label className="block text-grey-300 text-sm font-bold mb-2">GOES TO</label>
+              <p className="text-white text-sm break-all">{pending.destination}</p>
+            </div>
+          )}
+
+          <div className="bg-yellow-900/30 border-2 border-yellow-500 p-3 space-y-1">
+            {pending.risks.map((risk, index) => (
+              <p key={index} className="text-yellow-300 text-sm">
+                {describeRisk(risk)}
+              </p>
+            ))}
+          </div>
+
+          <p className="text-grey-500 text-xs break-all">{pending.url}</p>
+
+          {pending.trustDomain && (
+            <label className="flex items-center gap-2 text-grey-300 text-sm cursor-pointer">
+              <input
+                type="checkbox"
+                checked={alwaysTrust}
+                onChange={(e) => setAlwaysTrust(e.target.checked)}
+              />
+              Always trust {pending.trustDomain}
+            </label>
+          )}
+
+          <div className="flex gap-3 pt-2">
+            <button
+              type="button"
+              onClick={handleClose}
+              className="flex-1 px-4 py-2 bg-grey-850 text-white border-2 border-grey-700 hover:border-white transition-colors font-medium"
+            >
+              Cancel
+            </button>
+            <button
+              type="button"
+              onClick={handleOpen}
+              className="flex-1 px-4 py-2 bg-white text-black border-2 border-white hover:bg-grey-100 transition-colors font-medium flex items-center justify-center gap-2"
+            >
+              <ExternalLink className="w-4 h-4" />
+              Open link
+            </button>
+          </div>
+        </div>
+      </div>
+    </div>
+  )
+}
+
+export default ExternalLinkModal
@@ -10,6 +10,7 @@ import {
   ArrowLeft,
   MoreVertical,
   Edit2,
+  ExternalLink,
 } from 'lucide-react'
 import { useFriendsStore } from '../stores/friends'
 import { useAuthStore } from '../stores/auth'
@@ -23,6 +24,7 @@ import EmojiPicker from './EmojiPicker'
 import MediaEmbed from './MediaEmbed'
 import { FileAttachment } from './chat/FileAttachment'
 import { parseMentionsInMessage } from '../utils/mentionUtils'
+import { popoutService, isPopoutWindow } from '../services/popout'
 
 interface FriendsPanelProps {
   selectedDMUserId?: number | null
@@ -511,13 +513,15 @@ const FriendsPanel: React.FC<FriendsPanelProps> = ({
       <div className="flex-1 bg-grey-900 flex flex-col h-full">
         {/* DM Header */}
         <div className="h-14 border-b-2 border-grey-800 px-4 flex items-center gap-3">
-          <button
-            onClick={handleBackFromDM}
-            className="p-2 hover:bg-grey-800 transition-colors"
-            title="Back to friends"
-          >
-            <ArrowLeft className="w-5 h-5 text-white" />
-          </button>
+          {!isPopoutWindow() && (
+            <button
+              onClick={handleBackFromDM}
+              className="p-2 hover:bg-grey-800 transition-colors"
+              title="Back to friends"
+            >
+              <ArrowLeft className="w-5 h-5 text-white" />
+            </button>
+          )}
           <div className="relative">
             <div className="w-8 h-8 bg-white flex items-center justify-center">
               <span className="text-black font-bold text-sm">
@@ -532,6 +536,20 @@ const FriendsPanel: React.FC<FriendsPanelProps> = ({
             <h2 className="font-bold text-white">{selectedFriend.username}</h2>
             <p className="text-grey-500 text-xs">Direct Message</p>
           </div>
+          {popoutService.isAvailable && !isPopoutWindow() && (
+            <button
+              onClick={() =>
+                popoutService.open(
+                  { kind: 'direct', userId: selectedFriend.id },
+                  selectedFriend.username
+                )
+              }
+              className="ml-auto p-2 text-grey-400 hover:text-white hover:bg-grey-800 transition-colors"
+              title="Open in new window"
+            >
+              <ExternalLink className="w-5 h-5" />
+            </button>
+          )}
         </div>
 
         {/* Messages */}
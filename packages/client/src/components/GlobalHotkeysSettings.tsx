@@ -0,0 +1,110 @@
+import React, { useState, useEffect } from 'react'
+import { Keyboard } from 'lucide-react'
+import { hotkeyService, HotkeyAction, HotkeyBindings } from '../services/hotkeys'
+
+const ACTIONS: { action: HotkeyAction; label: string }[] = [
+  { action: 'pushToTalk', label: 'Push to Talk' },
+  { action: 'toggleMute', label: 'Toggle Mute' },
+  { action: 'toggleDeafen', label: 'Toggle Deafen' },
+  { action: 'disconnect', label: 'Disconnect' },
+  { action: 'toggleOverlayClickThrough', label: 'Toggle Overlay Click-Through' },
+]
+
+const MODIFIER_CODES = ['Control', 'Alt', 'Shift', 'Meta', 'OS']
+
+// Builds an accelerator in the backend's syntax from a key event, using the
+// physical key so it matches regardless of keyboard layout
+const toAccelerator = (event: KeyboardEvent): string | null => {
+  if (MODIFIER_CODES.some((code) => event.code.startsWith(code))) {
+    return null
+  }
+  const modifiers = []
+  if (event.ctrlKey) modifiers.push('Ctrl')
+  if (event.altKey) modifiers.push('Alt')
+  if (event.shiftKey) modifiers.push('Shift')
+  if (event.metaKey) modifiers.push('Super')
+  return [...modifiers, event.code].join('+')
+}
+
+const formatAccelerator = (accelerator?: string | null): string =>
+  accelerator ? accelerator.replace(/Key([A-Z])/, '$1').replace(/Digit(\d)/, '$1') : 'Not set'
+
+const GlobalHotkeysSettings: React.FC = () => {
+  const [bindings, setBindings] = useState<HotkeyBindings>(hotkeyService.getBindings())
+  const [recording, setRecording] = useState<HotkeyAction | null>(null)
+  const [error, setError] = useState<string | null>(null)
+
+  const save = async (next: HotkeyBindings) => {
+    try {
+      await hotkeyService.setBindings(next)
+      setBindings(next)
+      setError(null)
+    } catch (err) {
+      setError(String(err))
+    }
+  }
+
+  useEffect(() => {
+    if (!recording) {
+      return
+    }
+
+    const handleKeyDown = (event: KeyboardEvent) => {
+      event.preventDefault()
+      if (event.code === 'Escape') {
+        setRecording(null)
+        return
+      }
+      const accelerator = toAccelerator(event)
+      if (accelerator) {
+        setRecording(null)
+        save({ ...bindings, [recording]: accelerator })
+      }
+    }
+
+    document.addEventListener('keydown', handleKeyDown)
+    return () => document.removeEventListener('keydown', handleKeyDown)
+  }, [recording, bindings])
+
+  return (
+    <div className="pt-6 border-t border-grey-700">
+      <div className="flex items-center gap-3 mb-4">
+        <Keyboard className="w-5 h-5 text-grey-400" />
+        <div className="flex-1">
+          <p className="text-white text-base font-medium">Global Shortcuts</p>
+          <p className="text-grey-500 text-sm">Work even when CommHub isn't focused</p>
+        </div>
+      </div>
+      <div className="space-y-3">
+        {ACTIONS.map(({ action, label }) => (
+          <div key={action} className="flex items-center gap-3">
+            <span className="flex-1 text-grey-300 text-sm">{label}</span>
+            <span className="text-white text-sm font-mono">
+              {recording === action ? 'Press a key...' : formatAccelerator(bindings[action])}
+            </span>
+            <button
+              onClick={() => setRecording(recording === action ? null : action)}
+              className={`px-3 py-1 border-2 transition-colors uppercase text-xs font-bold tracking-wider ${
+                recording === action
+                  ? 'bg-red-900 border-red-700 text-white'
+                  : 'bg-white text-black border-white hover:bg-grey-100'
+              }`}
+            >
+              {recording === action ? 'Cancel' : 'Set'}
+            </button>
+            <button
+              onClick={() => save({ ...bindings, [action]: null })}
+              disabled={!bindings[action]}
+              className="px-3 py-1 border-2 border-grey-700 text-grey-400 hover:border-grey-600 disabled:opacity-50 uppercase text-xs font-bold tracking-wider"
+            >
+              Clear
+            </button>
+          </div>
+        ))}
+      </div>
+      {error && <p className="text-red-400 text-sm mt-3">{error}</p>}
+    </div>
+  )
+}
+
+export default GlobalHotkeysSettings
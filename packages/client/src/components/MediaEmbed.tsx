@@ -1,164 +1,55 @@
 import React, { useState, useEffect } from 'react'
 import { ExternalLink, Play, Download, X } from 'lucide-react'
+import { unfurlService, type LinkPreview } from '../services/unfurl'
+import { downloadService } from '../services/downloads'
 
 interface MediaEmbedProps {
   url: string
 }
 
-interface EmbedData {
-  type: 'image' | 'video' | 'youtube' | 'twitter' | 'link'
-  url: string
-  title?: string
-  description?: string
-  thumbnail?: string
-  embedUrl?: string
-}
-
 const MediaEmbed: React.FC<MediaEmbedProps> = ({ url }) => {
-  const [embedData, setEmbedData] = useState<EmbedData | null>(null)
+  const [preview, setPreview] = useState<LinkPreview | null>(null)
   const [isLoading, setIsLoading] = useState(true)
   const [showVideo, setShowVideo] = useState(false)
   const [isImageExpanded, setIsImageExpanded] = useState(false)
 
   useEffect(() => {
-    parseUrl(url)
-  }, [url])
-
-  const parseUrl = async (urlString: string) => {
+    let cancelled = false
     setIsLoading(true)
-    try {
-      const urlObj = new URL(urlString)
-      const hostname = urlObj.hostname.toLowerCase().replace('www.', '')
-
-      // Image extensions
-      if (/\.(jpg|jpeg|png|gif|webp|bmp|svg)$/i.test(urlObj.pathname)) {
-        setEmbedData({
-          type: 'image',
-          url: urlString,
-        })
-        setIsLoading(false)
-        return
-      }
-
-      // Video extensions
-      if (/\.(mp4|webm|ogg|mov)$/i.test(urlObj.pathname)) {
-        setEmbedData({
-          type: 'video',
-          url: urlString,
-        })
-        setIsLoading(false)
-        return
-      }
-
-      // YouTube
-      if (hostname.includes('youtube.com') || hostname.includes('youtu.be')) {
-        const videoId = extractYouTubeVideoId(urlString)
-        if (videoId) {
-          setEmbedData({
-            type: 'youtube',
-            url: urlString,
-            embedUrl: `https://www.youtube.com/embed/${videoId}`,
-            thumbnail: `https://img.youtube.com/vi/${videoId}/maxresdefault.jpg`,
-          })
-          setIsLoading(false)
-          return
-        }
-      }
-
-      // Twitter/X
-      if (hostname.includes('twitter.com') || hostname.includes('x.com')) {
-        setEmbedData({
-          type: 'twitter',
-          url: urlString,
-        })
+    setShowVideo(false)
+    unfurlService.preview(url).then((preview) => {
+      if (!cancelled) {
+        setPreview(preview)
         setIsLoading(false)
-        return
       }
-
-      // Imgur
-      if (hostname.includes('imgur.com')) {
-        const imgurId = urlObj.pathname
-          .split('/')
-          .pop()
-          ?.replace(/\.(jpg|jpeg|png|gif)$/i, '')
-        if (imgurId) {
-          setEmbedData({
-            type: 'image',
-            url: `https://i.imgur.com/${imgurId}.jpg`,
-          })
-          setIsLoading(false)
-          return
-        }
-      }
-
-      // Reddit images/videos
-      if (hostname.includes('reddit.com') || hostname.includes('redd.it')) {
-        // Reddit preview images
-        if (hostname.includes('i.redd.it') || hostname.includes('preview.redd.it')) {
-          setEmbedData({
-            type: 'image',
-            url: urlString,
-          })
-          setIsLoading(false)
-          return
-        }
-        // Reddit videos
-        if (hostname.includes('v.redd.it')) {
-          setEmbedData({
-            type: 'video',
-            url: urlString,
-          })
-          setIsLoading(false)
-          return
-        }
-      }
-
-      // Generic link
-      setEmbedData({
-        type: 'link',
-        url: urlString,
-        title: hostname,
-      })
-    } catch (error) {
-      console.error('Error parsing URL:', error)
-    } finally {
-      setIsLoading(false)
-    }
-  }
-
-  const extractYouTubeVideoId = (url: string): string | null => {
-    const patterns = [
-      /(?:youtube\.com\/watch\?v=|youtu\.be\/)([a-zA-Z0-9_-]{11})/,
-      /youtube\.com\/embed\/([a-zA-Z0-9_-]{11})/,
-    ]
-
-    for (const pattern of patterns) {
-      const match = url.match(pattern)
-      if (match) return match[1]
+    })
+    return () => {
+      cancelled = true
     }
-    return null
-  }
+  }, [url])
 
-  const handleDownload = async (imageUrl: string, filename?: string) => {
+  const hostname = (() => {
     try {
-      const response = await fetch(imageUrl)
-      const blob = await response.blob()
-      const url = window.URL.createObjectURL(blob)
-
-      // Extract filename from URL or use a default
-      const fileName = filename || imageUrl.split('/').pop() || 'image.jpg'
+      return new URL(url).hostname.replace(/^www\./, '')
+    } catch {
+      return url
+    }
+  })()
 
-      const a = document.createElement('a')
-      a.style.display = 'none'
-      a.href = url
-      a.download = fileName
-      document.body.appendChild(a)
-      a.click()
-      window.URL.revokeObjectURL(url)
-      document.body.removeChild(a)
-    } catch (error) {
-      console.error('Failed to download image:', error)
+  const handleDownload = (imageUrl: string) => {
+    const fileName = new URL(imageUrl).pathname.split('/').pop() || 'image.jpg'
+    if (window.__TAURI__) {
+      downloadService.save(imageUrl, fileName).catch((error) => {
+        console.error('Failed to download image:', error)
+      })
+      return
     }
+    const a = document.createElement('a')
+    a.href = imageUrl
+    a.download = fileName
+    a.target = '_blank'
+    a.rel = 'noopener noreferrer'
+    a.click()
   }
 
   if (isLoading) {
@@ -170,10 +61,9 @@ const MediaEmbed: React.FC<MediaEmbedProps> = ({ url }) => {
     )
   }
 
-  if (!embedData) return null
-
   // Image Embed
-  if (embedData.type === 'image') {
+  if (preview?.kind === 'image' && preview.image) {
+    const imageUrl = preview.image.url
     return (
       <>
         {/* Thumbnail view */}
@@ -181,7 +71,7 @@ const MediaEmbed: React.FC<MediaEmbedProps> = ({ url }) => {
           <div className="mt-2 max-w-md animate-slide-up">
             <div className="bg-grey-850 border-2 border-grey-700 overflow-hidden inline-block cursor-pointer hover:border-grey-600 transition-colors">
               <img
-                src={embedData.url}
+                src={imageUrl}
                 alt="Embedded image"
                 className="block h-auto max-h-96 max-w-full"
                 onClick={() => setIsImageExpanded(true)}
@@ -216,7 +106,7 @@ const MediaEmbed: React.FC<MediaEmbedProps> = ({ url }) => {
               <button
                 onClick={(e) => {
                   e.stopPropagation()
-                  handleDownload(embedData.url)
+                  handleDownload(imageUrl)
                 }}
                 className="absolute top-2 left-2 z-10 p-2 bg-grey-800 text-white hover:bg-grey-700 transition-colors border-2 border-grey-700 hover:border-grey-600"
                 title="Download image"
@@ -226,7 +116,7 @@ const MediaEmbed: React.FC<MediaEmbedProps> = ({ url }) => {
 
               {/* Image */}
               <img
-                src={embedData.url}
+                src={imageUrl}
                 alt="Embedded image"
                 className="max-w-full max-h-full object-contain"
                 onClick={(e) => e.stopPropagation()}
@@ -239,12 +129,12 @@ const MediaEmbed: React.FC<MediaEmbedProps> = ({ url }) => {
   }
 
   // Video Embed
-  if (embedData.type === 'video') {
+  if (preview?.kind === 'video' && preview.video) {
     return (
       <div className="mt-2 max-w-md animate-slide-up">
         <div className="bg-grey-850 border-2 border-grey-700 overflow-hidden">
           <video
-            src={embedData.url}
+            src={preview.video.url}
             controls
             className="w-full h-auto max-h-96"
             onError={(e) => {
@@ -259,21 +149,33 @@ const MediaEmbed: React.FC<MediaEmbedProps> = ({ url }) => {
     )
   }
 
-  // YouTube Embed
-  if (embedData.type === 'youtube' && embedData.embedUrl) {
+  // Page with a player, like a YouTube video
+  if (preview?.video) {
+    const { video, image } = preview
     return (
       <div className="mt-2 max-w-md animate-slide-up">
         <div className="bg-grey-850 border-2 border-grey-700 overflow-hidden">
-          {!showVideo && embedData.thumbnail ? (
+          {video.player === 'file' ? (
+            <video
+              src={video.url}
+              poster={image?.url}
+              controls
+              className="w-full h-auto max-h-96"
+            >
+              Your browser does not support the video tag.
+            </video>
+          ) : !showVideo ? (
             <button
               onClick={() => setShowVideo(true)}
-              className="relative w-full aspect-video group"
+              className="relative w-full aspect-video group bg-grey-900"
             >
-              <img
-                src={embedData.thumbnail}
-                alt="YouTube thumbnail"
-                className="w-full h-full object-cover"
-              />
+              {image && (
+                <img
+                  src={image.url}
+                  alt={image.alt ?? preview.title ?? 'Video thumbnail'}
+                  className="w-full h-full object-cover"
+                />
+              )}
               <div className="absolute inset-0 bg-black/40 flex items-center justify-center group-hover:bg-black/60 transition-colors">
                 <div className="w-16 h-16 bg-red-600 border-2 border-white flex items-center justify-center group-hover:scale-110 transition-transform">
                   <Play className="w-8 h-8 text-white ml-1" fill="white" />
@@ -282,21 +184,24 @@ const MediaEmbed: React.FC<MediaEmbedProps> = ({ url }) => {
             </button>
           ) : (
             <iframe
-              src={embedData.embedUrl}
+              src={video.url}
               className="w-full aspect-video"
               allow="accelerometer; autoplay; clipboard-write; encrypted-media; gyroscope; picture-in-picture"
               allowFullScreen
-              title="YouTube video"
+              title={preview.title ?? 'Embedded video'}
             />
           )}
           <div className="p-3 border-t-2 border-grey-800">
+            <p className="text-grey-500 text-xs uppercase tracking-wider mb-1">
+              {preview.siteName ?? hostname}
+            </p>
             <a
-              href={embedData.url}
+              href={preview.url}
               target="_blank"
               rel="noopener noreferrer"
               className="text-white hover:text-grey-300 text-sm font-bold flex items-center gap-2 group"
             >
-              <span className="truncate">YouTube Video</span>
+              <span className="truncate">{preview.title ?? preview.url}</span>
               <ExternalLink className="w-4 h-4 flex-shrink-0 opacity-0 group-hover:opacity-100 transition-opacity" />
             </a>
           </div>
@@ -305,54 +210,64 @@ const MediaEmbed: React.FC<MediaEmbedProps> = ({ url }) => {
     )
   }
 
-  // Twitter Embed
-  if (embedData.type === 'twitter') {
+  // Page described by its metadata
+  if (preview && (preview.title || preview.description || preview.image)) {
     return (
-      <div className="mt-2 animate-slide-up">
-        <div className="bg-grey-850 border-2 border-grey-700 p-4">
-          <div className="flex items-center gap-2 mb-2">
-            <div className="w-5 h-5 bg-grey-700 flex items-center justify-center">
-              <span className="text-white text-xs font-bold">𝕏</span>
-            </div>
-            <span className="text-white font-bold text-sm">Twitter/X Post</span>
-          </div>
-          <a
-            href={embedData.url}
-            target="_blank"
-            rel="noopener noreferrer"
-            className="text-grey-300 hover:text-white text-sm flex items-center gap-2 group"
-          >
-            <span className="truncate">{embedData.url}</span>
-            <ExternalLink className="w-4 h-4 flex-shrink-0 opacity-0 group-hover:opacity-100 transition-opacity" />
-          </a>
+      <div className="mt-2 max-w-md animate-slide-up">
+        <div className="bg-grey-850 border-2 border-grey-700 border-l-4 border-l-grey-500 p-3">
+          <p className="text-grey-500 text-xs uppercase tracking-wider mb-1">
+            {preview.siteName ?? hostname}
+          </p>
+          {preview.title && (
+            <a
+              href={preview.url}
+              target="_blank"
+              rel="noopener noreferrer"
+              className="text-white hover:text-grey-300 text-sm font-bold flex items-center gap-2 group"
+            >
+              <span className="truncate">{preview.title}</span>
+              <ExternalLink className="w-4 h-4 flex-shrink-0 opacity-0 group-hover:opacity-100 transition-opacity" />
+            </a>
+          )}
+          {preview.description && (
+            <p className="text-grey-400 text-sm mt-1 line-clamp-3">{preview.description}</p>
+          )}
+          {preview.image && (
+            <img
+              src={preview.image.url}
+              alt={preview.image.alt ?? preview.title ?? ''}
+              className="mt-2 block h-auto max-h-64 max-w-full border-2 border-grey-800"
+              loading="lazy"
+              onError={(e) => {
+                const target = e.target as HTMLImageElement
+                target.style.display = 'none'
+              }}
+            />
+          )}
         </div>
       </div>
     )
   }
 
   // Generic Link Embed
-  if (embedData.type === 'link') {
-    return (
-      <div className="mt-2 animate-slide-up">
-        <a
-          href={embedData.url}
-          target="_blank"
-          rel="noopener noreferrer"
-          className="bg-grey-850 border-2 border-grey-700 hover:border-white p-3 flex items-center gap-3 group transition-colors"
-        >
-          <div className="w-10 h-10 bg-grey-800 flex items-center justify-center flex-shrink-0">
-            <ExternalLink className="w-5 h-5 text-grey-400 group-hover:text-white transition-colors" />
-          </div>
-          <div className="flex-1 min-w-0">
-            <p className="text-white font-bold text-sm truncate">{embedData.title}</p>
-            <p className="text-grey-400 text-xs truncate">{embedData.url}</p>
-          </div>
-        </a>
-      </div>
-    )
-  }
-
-  return null
+  return (
+    <div className="mt-2 animate-slide-up">
+      <a
+        href={url}
+        target="_blank"
+        rel="noopener noreferrer"
+        className="bg-grey-850 border-2 border-grey-700 hover:border-white p-3 flex items-center gap-3 group transition-colors"
+      >
+        <div className="w-10 h-10 bg-grey-800 flex items-center justify-center flex-shrink-0">
+          <ExternalLink className="w-5 h-5 text-grey-400 group-hover:text-white transition-colors" />
+        </div>
+        <div className="flex-1 min-w-0">
+          <p className="text-white font-bold text-sm truncate">{preview?.siteName ?? hostname}</p>
+          <p className="text-grey-400 text-xs truncate">{url}</p>
+        </div>
+      </a>
+    </div>
+  )
 }
 
 export default MediaEmbed
@@ -0,0 +1,66 @@
+import React, { useEffect, useState } from 'react'
+import { Command } from 'lucide-react'
+import { menuService, KeymapInfo } from '../services/menu'
+
+/**
+ * The menu shortcuts from keymap.toml, and anything in it that was ignored.
+ * They're edited in the file itself and apply on the next start.
+ */
+const MenuShortcutsSettings: React.FC = () => {
+  const [keymap, setKeymap] = useState<KeymapInfo | null>(null)
+  const [error, setError] = useState<string | null>(null)
+
+  useEffect(() => {
+    menuService
+      .getKeymap()
+      .then(setKeymap)
+      .catch((err) => setError(String(err)))
+  }, [])
+
+  const openKeymap = async () => {
+    try {
+      await menuService.openKeymap()
+      setError(null)
+    } catch (err) {
+      setError(String(err))
+    }
+  }
+
+  return (
+    <div className="bg-grey-850 border-2 border-grey-700 p-6">
+      <div className="flex items-center gap-3 mb-4">
+        <Command className="w-6 h-6 text-grey-400" />
+        <div>
+          <p className="text-white text-lg font-medium">Menu Shortcuts</p>
+          <p className="text-grey-500 text-sm">
+            Edited in keymap.toml and applied the next time CommHub starts
+          </p>
+        </div>
+      </div>
+      {keymap && (
+        <div className="space-y-2 mb-4">
+          {keymap.shortcuts.map(({ action, label, accelerator }) => (
+            <div key={action} className="flex justify-between items-center text-sm">
+              <span className="text-grey-300">{label.replace(/\.\.\.$/, '')}</span>
+              <span className="text-white font-mono">{accelerator ?? 'Not set'}</span>
+            </div>
+          ))}
+        </div>
+      )}
+      {keymap?.problems.map((problem) => (
+        <p key={problem} className="text-red-400 text-sm mb-2">
+          {problem}
+        </p>
+      ))}
+      <button
+        onClick={openKeymap}
+        className="w-full px-6 py-3 bg-grey-800 text-white border-2 border-grey-700 hover:border-white transition-colors text-sm font-bold uppercase tracking-wide"
+      >
+        Edit Shortcuts
+      </button>
+      {error && <p className="text-red-400 text-sm mt-3">{error}</p>}
+    </div>
+  )
+}
+
+export default MenuShortcutsSettings
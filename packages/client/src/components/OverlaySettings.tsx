@@ -0,0 +1,114 @@
+import React, { useState, useEffect } from 'react'
+import { Layers } from 'lucide-react'
+import { overlayService, OverlayCorner, OverlaySettings as Settings } from '../services/overlay'
+
+const CORNERS: { corner: OverlayCorner; label: string }[] = [
+  { corner: 'topLeft', label: 'Top Left' },
+  { corner: 'topRight', label: 'Top Right' },
+  { corner: 'bottomLeft', label: 'Bottom Left' },
+  { corner: 'bottomRight', label: 'Bottom Right' },
+]
+
+const Toggle: React.FC<{ enabled: boolean; onChange: (enabled: boolean) => void }> = ({
+  enabled,
+  onChange,
+}) => (
+  <button
+    onClick={() => onChange(!enabled)}
+    className={`relative w-14 h-7 border-2 transition-colors ${
+      enabled ? 'bg-white border-white' : 'bg-grey-700 border-grey-600'
+    }`}
+  >
+    <div
+      className={`absolute top-0.5 w-5 h-5 bg-black transition-transform ${
+        enabled ? 'translate-x-7' : 'translate-x-0.5'
+      }`}
+    />
+  </button>
+)
+
+const OverlaySettings: React.FC = () => {
+  const [settings, setSettings] = useState<Settings | null>(null)
+  const [error, setError] = useState<string | null>(null)
+
+  useEffect(() => {
+    overlayService
+      .getSettings()
+      .then(setSettings)
+      .catch((err) => setError(String(err)))
+  }, [])
+
+  const save = async (next: Settings) => {
+    setSettings(next)
+    try {
+      await overlayService.setSettings(next)
+      setError(null)
+    } catch (err) {
+      setError(String(err))
+    }
+  }
+
+  if (!settings) {
+    return null
+  }
+
+  return (
+    <div className="pt-6 border-t border-grey-700">
+      <div className="flex items-center gap-3 mb-4">
+        <Layers className="w-5 h-5 text-grey-400" />
+        <div className="flex-1">
+          <p className="text-white text-base font-medium">Voice Overlay</p>
+          <p className="text-grey-500 text-sm">Show who's talking on top of games and other apps</p>
+        </div>
+        <Toggle enabled={settings.enabled} onChange={(enabled) => save({ ...settings, enabled })} />
+      </div>
+      {settings.enabled && (
+        <div className="space-y-4">
+          <div>
+            <label className="block text-grey-300 text-sm mb-2">Position</label>
+            <select
+              value={settings.corner}
+              onChange={(e) => save({ ...settings, corner: e.target.value as OverlayCorner })}
+              className="w-full bg-grey-800 border-2 border-grey-700 px-4 py-3 text-white focus:border-white text-sm"
+            >
+              {CORNERS.map(({ corner, label }) => (
+                <option key={corner} value={corner}>
+                  {label}
+                </option>
+              ))}
+            </select>
+          </div>
+          <div>
+            <label className="block text-grey-300 text-sm mb-2">
+              Opacity ({Math.round(settings.opacity * 100)}%)
+            </label>
+            <input
+              type="range"
+              min="20"
+              max="100"
+              value={Math.round(settings.opacity * 100)}
+              onChange={(e) => save({ ...settings, opacity: parseInt(e.target.value) / 100 })}
+              className="w-full h-3 bg-grey-700 appearance-none slider"
+            />
+          </div>
+          <div className="flex items-center justify-between">
+            <div>
+              <p className="text-white text-base font-medium">Click-Through</p>
+              <p className="text-grey-500 text-sm">
+                Let clicks pass through to the app underneath. Can also be toggled with a global
+                shortcut.
+              </p>
+            </div>
+            <Toggle
+              enabled={settings.clickThrough}
+              onChange={(clickThrough) => save({ ...settings, clickThrough })}
+            />
+          </div>
+        </div>
+      )}
+      {error && <p className="text-red-400 text-sm mt-3">{error}</p>}
+    </div>
+  )
+}
+
+export default OverlaySettings
@@ -0,0 +1,146 @@
+import React, { useEffect, useMemo, useRef, useState } from 'react'
+import { Hash, MessageSquare } from 'lucide-react'
+import { useChannelsStore } from '../stores/channels'
+import { useServersStore } from '../stores/servers'
+import { useDirectMessagesStore } from '../stores/directMessages'
+import { navigate, NavigationTarget } from '../services/navigation'
+
+interface QuickSwitcherProps {
+  isOpen: boolean
+  onClose: () => void
+}
+
+interface Entry {
+  key: string
+  name: string
+  detail: string
+  target: NavigationTarget
+}
+
+const MAX_RESULTS = 8
+
+/**
+ * Jump to any text channel or DM by name. Opened from the app menu.
+ */
+const QuickSwitcher: React.FC<QuickSwitcherProps> = ({ isOpen, onClose }) => {
+  const channels = useChannelsStore((state) => state.channels)
+  const servers = useServersStore((state) => state.servers)
+  const conversations = useDirectMessagesStore((state) => state.conversations)
+  const [query, setQuery] = useState('')
+  const [selected, setSelected] = useState(0)
+  const inputRef = useRef<HTMLInputElement>(null)
+
+  useEffect(() => {
+    if (isOpen) {
+      setQuery('')
+      setSelected(0)
+      setTimeout(() => inputRef.current?.focus(), 0)
+    }
+  }, [isOpen])
+
+  const results = useMemo(() => {
+    const entries: Entry[] = [
+      ...conversations.map((conv) => ({
+        key: `dm-${conv.user.id}`,
+        name: conv.user.username,
+        detail: 'Direct message',
+        target: { kind: 'direct' as const, userId: conv.user.id },
+      })),
+      ...channels
+        .filter((channel) => channel.type === 'text')
+        .map((channel) => ({
+          key: `channel-${channel.id}`,
+          name: channel.name,
+          detail: servers.find((s) => s.id === channel.serverId)?.name ?? '',
+          target: { kind: 'channel' as const, channelId: channel.id },
+        })),
+    ]
+    const needle = query.trim().toLowerCase()
+    return entries
+      .filter((entry) => entry.name.toLowerCase().includes(needle))
+      .sort(
+        (a, b) =>
+          Number(!a.name.toLowerCase().startsWith(needle)) -
+          Number(!b.name.toLowerCase().startsWith(needle))
+      )
+      .slice(0, MAX_RESULTS)
+  }, [query, channels, servers, conversations])
+
+  if (!isOpen) return null
+
+  const open = (entry: Entry | undefined) => {
+    if (!entry) return
+    navigate(entry.target, { focusInput: true })
+    onClose()
+  }
+
+  const handleKeyDown = (e: React.KeyboardEvent) => {
+    switch (e.key) {
+      case 'ArrowDown':
+        e.preventDefault()
+        setSelected((index) => Math.min(index + 1, results.length - 1))
+        break
+      case 'ArrowUp':
+        e.preventDefault()
+        setSelected((index) => Math.max(index - 1, 0))
+        break
+      case 'Enter':
+        e.preventDefault()
+        open(results[selected])
+        break
+      case 'Escape':
+        onClose()
+        break
+    }
+  }
+
+  return (
+    <div
+      className="fixed inset-0 bg-black/80 flex items-start justify-center pt-32 z-50 animate-fade-in"
+      onClick={onClose}
+    >
+      <div
+        className="bg-grey-900 border-2 border-white w-[28rem] animate-slide-up"
+        onClick={(e) => e.stopPropagation()}
+      >
+        <input
+          ref={inputRef}
+          value={query}
+          onChange={(e) => {
+            setQuery(e.target.value)
+            setSelected(0)
+          }}
+          onKeyDown={handleKeyDown}
+          placeholder="Where would you like to go?"
+          className="w-full bg-grey-800 border-b-2 border-grey-700 px-4 py-3 text-white focus:outline-none"
+        />
+        <div className="p-2">
+          {results.length === 0 ? (
+            <p className="text-grey-500 text-sm px-2 py-3">No matching channels or messages</p>
+          ) : (
+            results.map((entry, index) => (
+              <button
+                key={entry.key}
+                onClick={() => open(entry)}
+                onMouseEnter={() => setSelected(index)}
+                className={`w-full flex items-center gap-3 px-2 py-2 text-left transition-colors ${
+                  index === selected ? 'bg-grey-800 text-white' : 'text-grey-300'
+                }`}
+              >
+                {entry.target.kind === 'channel' ? (
+                  <Hash className="w-4 h-4 text-grey-500 flex-shrink-0" />
+                ) : (
+                  <MessageSquare className="w-4 h-4 text-grey-500 flex-shrink-0" />
+                )}
+                <span className="truncate">{entry.name}</span>
+                <span className="ml-auto text-grey-500 text-xs truncate">{entry.detail}</span>
+              </button>
+            ))
+          )}
+        </div>
+      </div>
+    </div>
+  )
+}
+
+export default QuickSwitcher
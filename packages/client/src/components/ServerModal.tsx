@@ -1,13 +1,15 @@
-import React, { useState } from 'react'
+import React, { useState, useEffect } from 'react'
 import { X } from 'lucide-react'
 import { useServersStore } from '../stores/servers'
 
 interface ServerModalProps {
   isOpen: boolean
   onClose: () => void
+  // Opens straight into the join form, e.g. from a commhub://invite link
+  initialInviteCode?: string | null
 }
 
-const ServerModal: React.FC<ServerModalProps> = ({ isOpen, onClose }) => {
+const ServerModal: React.FC<ServerModalProps> = ({ isOpen, onClose, initialInviteCode }) => {
   const [mode, setMode] = useState<'choose' | 'create' | 'join'>('choose')
   const [serverName, setServerName] = useState('')
   const [serverDescription, setServerDescription] = useState('')
@@ -18,6 +20,14 @@ const ServerModal: React.FC<ServerModalProps> = ({ isOpen, onClose }) => {
   const createServer = useServersStore((state) => state.createServer)
   const joinServer = useServersStore((state) => state.joinServer)
 
+  useEffect(() => {
+    if (isOpen && initialInviteCode) {
+      setMode('join')
+      setInviteCode(initialInviteCode)
+      setError(null)
+    }
+  }, [isOpen, initialInviteCode])
+
   const handleClose = () => {
     setMode('choose')
     setServerName('')
@@ -1,12 +1,33 @@
 import React, { useState, useEffect } from 'react'
-import { X, Bell, Volume2, Type, Clock, Mic, Zap, Shield, Gauge, Camera } from 'lucide-react'
+import {
+  X,
+  Bell,
+  Volume2,
+  Type,
+  Clock,
+  Mic,
+  Zap,
+  Shield,
+  Gauge,
+  Camera,
+  Power,
+  Moon,
+  Image as ImageIcon,
+  HardDrive,
+  Link as LinkIcon,
+} from 'lucide-react'
 import { useVoiceSettingsStore } from '../stores/voice-settings'
 import { voiceManager } from '../services/voice-manager'
 import { webrtcService } from '../services/webrtc'
-import { useSettingsStore } from '../stores/settings'
+import { useSettingsStore, ClosePolicy } from '../stores/settings'
 import { useStatusStore } from '../stores/status'
 import { apiService } from '../services/api'
+import { downloadService } from '../services/downloads'
+import { linkService } from '../services/links'
 import { config } from '../config/environment'
+import GlobalHotkeysSettings from './GlobalHotkeysSettings'
+import OverlaySettings from './OverlaySettings'
+import MenuShortcutsSettings from './MenuShortcutsSettings'
 
 interface SettingsModalProps {
   isOpen: boolean
@@ -36,6 +57,7 @@ const SettingsModal: React.FC<SettingsModalProps> = ({ isOpen, onClose }) => {
   // User preferences from global store
   const settings = useSettingsStore()
   const updateSetting = useSettingsStore((state) => state.updateSetting)
+  const updateSettings = useSettingsStore((state) => state.updateSettings)
 
   // Status settings
   const { updateStatus, getUserStatus } = useStatusStore()
@@ -47,6 +69,50 @@ const SettingsModal: React.FC<SettingsModalProps> = ({ isOpen, onClose }) => {
     apiService.getUser().then(setCurrentUser)
   }, [])
 
+  // Attachment cache usage, in bytes
+  const [cacheUsed, setCacheUsed] = useState<number | null>(null)
+
+  useEffect(() => {
+    if (!isOpen || activeTab !== 'application' || !window.__TAURI__) {
+      return
+    }
+    downloadService
+      .cacheUsage()
+      .then((usage) => setCacheUsed(usage.used))
+      .catch(() => setCacheUsed(null))
+  }, [isOpen, activeTab, settings.attachmentCacheMb])
+
+  const clearAttachmentCache = async () => {
+    try {
+      await downloadService.clearCache()
+      setCacheUsed(0)
+    } catch (error) {
+      console.error('[Settings] Failed to clear attachment cache:', error)
+    }
+  }
+
+  // Domains whose links open without asking
+  const [trustedDomains, setTrustedDomains] = useState<string[]>([])
+
+  useEffect(() => {
+    if (!isOpen || activeTab !== 'application' || !window.__TAURI__) {
+      return
+    }
+    linkService
+      .trusted()
+      .then(setTrustedDomains)
+      .catch(() => setTrustedDomains([]))
+  }, [isOpen, activeTab])
+
+  const untrustDomain = async (domain: string) => {
+    try {
+      await linkService.untrust(domain)
+      setTrustedDomains((domains) => domains.filter((d) => d !== domain))
+    } catch (error) {
+      console.error('[Settings] Failed to untrust domain:', error)
+    }
+  }
+
   // Voice settings state
   const voiceSettings = useVoiceSettingsStore((state) => state.settings)
   const voiceSettingsStore = useVoiceSettingsStore()
@@ -475,6 +541,46 @@ const SettingsModal: React.FC<SettingsModalProps> = ({ isOpen, onClose }) => {
                   </button>
                 </div>
               </div>
+
+              {/* Automatic Idle */}
+              {window.__TAURI__ && (
+                <div className="bg-grey-850 border-2 border-grey-700 p-6">
+                  <div className="flex items-center gap-3 mb-4">
+                    <Moon className="w-6 h-6 text-grey-400" />
+                    <div>
+                      <p className="text-white text-lg font-medium">Automatic Idle</p>
+                      <p className="text-grey-500 text-sm">
+                        Go idle when there's no keyboard or mouse input anywhere on your computer
+                      </p>
+                    </div>
+                  </div>
+                  <div className="flex gap-3">
+                    {([null, 5, 15, 30] as (number | null)[]).map((minutes) => {
+                      const selected =
+                        minutes === null
+                          ? !settings.autoIdle
+                          : settings.autoIdle && settings.idleAfterMinutes === minutes
+                      return (
+                        <button
+                          key={minutes ?? 'off'}
+                          onClick={() =>
+                            minutes === null
+                              ? updateSetting('autoIdle', false)
+                              : updateSettings({ autoIdle: true, idleAfterMinutes: minutes })
+                          }
+                          className={`flex-1 py-3 border-2 transition-colors uppercase text-sm font-bold tracking-wider ${
+                            selected
+                              ? 'bg-white text-black border-white'
+                              : 'bg-transparent text-grey-400 border-grey-700 hover:border-grey-600'
+                          }`}
+                        >
+                          {minutes === null ? 'Off' : `${minutes} min`}
+                        </button>
+                      )
+                    })}
+                  </div>
+                </div>
+              )}
             </div>
           )}
 
@@ -797,6 +903,12 @@ const SettingsModal: React.FC<SettingsModalProps> = ({ isOpen, onClose }) => {
                   </div>
                 )}
 
+                {/* Global Shortcuts */}
+                {window.__TAURI__ && <GlobalHotkeysSettings />}
+
+                {/* Voice Overlay */}
+                {window.__TAURI__ && <OverlaySettings />}
+
                 {/* Sensitivity Slider */}
                 <div className="pt-6 border-t border-grey-700">
                   <div className="flex items-center gap-3 mb-4">
@@ -1152,6 +1264,151 @@ const SettingsModal: React.FC<SettingsModalProps> = ({ isOpen, onClose }) => {
                 </div>
               </div>
 
+              {/* Close Behavior */}
+              {window.__TAURI__ && (
+                <div className="bg-grey-850 border-2 border-grey-700 p-6">
+                  <div className="flex items-center gap-3 mb-4">
+                    <Power className="w-6 h-6 text-grey-400" />
+                    <div>
+                      <p className="text-white text-lg font-medium">Closing the Window</p>
+                      <p className="text-grey-500 text-sm">
+                        Keep CommHub in the tray to stay connected and notified
+                      </p>
+                    </div>
+                  </div>
+                  <div className="flex gap-3">
+                    {(
+                      [
+                        ['hide', 'Keep in Tray'],
+                        ['destroy', 'Tray (Save Memory)'],
+                        ['quit', 'Quit'],
+                      ] as [ClosePolicy, string][]
+                    ).map(([policy, label]) => (
+                      <button
+                        key={policy}
+                        onClick={() => updateSetting('closePolicy', policy)}
+                        className={`flex-1 py-3 border-2 transition-colors uppercase text-sm font-bold tracking-wider ${
+                          settings.closePolicy === policy
+                            ? 'bg-white text-black border-white'
+                            : 'bg-transparent text-grey-400 border-grey-700 hover:border-grey-600'
+                        }`}
+                      >
+                        {label}
+                      </button>
+                    ))}
+                  </div>
+                </div>
+              )}
+
+              {/* Image Uploads */}
+              {window.__TAURI__ && (
+                <div className="bg-grey-850 border-2 border-grey-700 p-6">
+                  <div className="flex items-center gap-3 mb-4">
+                    <ImageIcon className="w-6 h-6 text-grey-400" />
+                    <div>
+                      <p className="text-white text-lg font-medium">Image Uploads</p>
+                      <p className="text-grey-500 text-sm">
+                        Larger images are scaled down; location and camera data are always removed
+                      </p>
+                    </div>
+                  </div>
+                  <div className="flex gap-3">
+                    {[1280, 2560, 4096].map((dimension) => (
+                      <button
+                        key={dimension}
+                        onClick={() => updateSetting('imageMaxDimension', dimension)}
+                        className={`flex-1 py-3 border-2 transition-colors uppercase text-sm font-bold tracking-wider ${
+                          settings.imageMaxDimension === dimension
+                            ? 'bg-white text-black border-white'
+                            : 'bg-transparent text-grey-400 border-grey-700 hover:border-grey-600'
+                        }`}
+                      >
+                        {dimension} px
+                      </button>
+                    ))}
+                  </div>
+                </div>
+              )}
+
+              {/* Attachment Cache */}
+              {window.__TAURI__ && (
+                <div className="bg-grey-850 border-2 border-grey-700 p-6">
+                  <div className="flex items-center gap-3 mb-4">
+                    <HardDrive className="w-6 h-6 text-grey-400" />
+                    <div className="flex-1">
+                      <p className="text-white text-lg font-medium">Attachment Cache</p>
+                      <p className="text-grey-500 text-sm">
+                        {cacheUsed === null
+                          ? 'Images and media you have viewed are kept on disk'
+                          : `${(cacheUsed / (1024 * 1024)).toFixed(1)} MB of ${
+                              settings.attachmentCacheMb
+                            } MB used`}
+                      </p>
+                    </div>
+                    <button
+                      onClick={clearAttachmentCache}
+                      className="px-4 py-2 bg-transparent text-grey-400 border-2 border-grey-700 hover:border-red-600 hover:text-red-500 transition-colors uppercase text-sm font-bold tracking-wider"
+                    >
+                      Clear
+                    </button>
+                  </div>
+                  <div className="flex gap-3">
+                    {[256, 1024, 4096].map((megabytes) => (
+                      <button
+                        key={megabytes}
+                        onClick={() => updateSetting('attachmentCacheMb', megabytes)}
+                        className={`flex-1 py-3 border-2 transition-colors uppercase text-sm font-bold tracking-wider ${
+                          settings.attachmentCacheMb === megabytes
+                            ? 'bg-white text-black border-white'
+                            : 'bg-transparent text-grey-400 border-grey-700 hover:border-grey-600'
+                        }`}
+                      >
+                        {megabytes >= 1024 ? `${megabytes / 1024} GB` : `${megabytes} MB`}
+                      </button>
+                    ))}
+                  </div>
+                </div>
+              )}
+
+              {/* Trusted Links */}
+              {window.__TAURI__ && (
+                <div className="bg-grey-850 border-2 border-grey-700 p-6">
+                  <div className="flex items-center gap-3 mb-4">
+                    <LinkIcon className="w-6 h-6 text-grey-400" />
+                    <div>
+                      <p className="text-white text-lg font-medium">Trusted Links</p>
+                      <p className="text-grey-500 text-sm">
+                        Links to these sites open without asking first
+                      </p>
+                    </div>
+                  </div>
+                  {trustedDomains.length === 0 ? (
+                    <p className="text-grey-500 text-sm">No trusted sites yet</p>
+                  ) : (
+                    <div className="space-y-2">
+                      {trustedDomains.map((domain) => (
+                        <div
+                          key={domain}
+                          className="flex items-center justify-between gap-3 border-2 border-grey-700 px-3 py-2"
+                        >
+                          <span className="text-white text-sm break-all">{domain}</span>
+                          <button
+                            onClick={() => untrustDomain(domain)}
+                            className="p-1 text-grey-400 hover:text-red-500 transition-colors"
+                            title="Stop trusting"
+                          >
+                            <X className="w-4 h-4" />
+                          </button>
+                        </div>
+                      ))}
+                    </div>
+                  )}
+                </div>
+              )}
+
+              {/* Menu Shortcuts */}
+              {window.__TAURI__ && <MenuShortcutsSettings />}
+
               {/* About Section */}
               <div className="bg-grey-850 border-2 border-grey-700 p-6">
                 <h4 className="text-white text-lg font-medium mb-4">About</h4>
@@ -1,12 +1,37 @@
 import React, { useState, useEffect } from 'react'
 import { Download, X, AlertCircle } from 'lucide-react'
+import { invoke } from '@tauri-apps/api/tauri'
+import { listen } from '@tauri-apps/api/event'
 import { config } from '../config/environment'
 
-// Define our own UpdateManifest interface since we're not using Tauri's
+// Mirrors UpdateInfo / UpdateCheck / StagedUpdate / DownloadProgress in src-tauri/src/updater.rs
+interface UpdateInfo {
+  version: string
+  currentVersion: string
+  notes: string | null
+  pubDate: string | null
+}
+
+type UpdateCheck =
+  | { status: 'available'; update: UpdateInfo }
+  | { status: 'upToDate' }
+  | { status: 'disabled' }
+
+interface StagedUpdate {
+  version: string
+  notes: string | null
+}
+
+interface DownloadProgress {
+  version: string
+  downloaded: number
+  total: number | null
+}
+
 interface UpdateManifest {
   version: string
-  date: string
-  body: string
+  date: string | null
+  body: string | null
 }
 
 interface UpdateNotificationProps {
@@ -17,6 +42,9 @@ const UpdateNotification: React.FC<UpdateNotificationProps> = ({ onDismiss }) =>
   const [manifest, setManifest] = useState<UpdateManifest | null>(null)
   const [error, setError] = useState<string | null>(null)
   const [checkComplete, setCheckComplete] = useState(false)
+  const [progress, setProgress] = useState<DownloadProgress | null>(null)
+  const [staged, setStaged] = useState(false)
+  const [disabled, setDisabled] = useState(false)
 
   useEffect(() => {
     // Check for updates when component mounts
@@ -24,72 +52,69 @@ const UpdateNotification: React.FC<UpdateNotificationProps> = ({ onDismiss }) =>
   }, [])
 
   const checkForUpdates = async () => {
+    setError(null)
+    setCheckComplete(false)
     try {
-      // Fetch the latest.json file from the repository
-      const response = await fetch(
-        'https://raw.githubusercontent.com/AnthonyRandom/commhub/main/latest.json'
-      )
-
-      if (!response.ok) {
-        throw new Error(`Failed to fetch latest.json: ${response.status} ${response.statusText}`)
+      // An update downloaded earlier only needs a restart
+      const stagedUpdate = await invoke<StagedUpdate | null>('updater_staged')
+      if (stagedUpdate) {
+        setManifest({ version: stagedUpdate.version, date: null, body: stagedUpdate.notes })
+        setStaged(true)
+        setCheckComplete(true)
+        return
       }
 
-      const latestData = await response.json()
-
-      const latestVersion = latestData.version
-      const currentVersion = config.CLIENT_VERSION // Get version from environment config
-
-      console.log('[UpdateCheck] Current version:', currentVersion)
-      console.log('[UpdateCheck] Latest version from GitHub:', latestVersion)
-      console.log('[UpdateCheck] Fetched data:', latestData)
+      // The backend fetches latest.json and compares versions (pre-releases included)
+      const check = await invoke<UpdateCheck>('updater_check')
+      console.log('[UpdateCheck] Current version:', config.CLIENT_VERSION)
+      console.log('[UpdateCheck] Result:', check)
 
-      // Simple version comparison (assuming semantic versioning)
-      const isLatest = compareVersions(currentVersion, latestVersion) >= 0
-      console.log('[UpdateCheck] Is latest?', isLatest, `(${currentVersion} >= ${latestVersion})`)
-
-      if (isLatest) {
-        // No update available - will show "no updates" message
-      } else {
-        // Create a mock manifest for display
-        setManifest({
-          version: latestVersion,
-          date: latestData.pub_date,
-          body: latestData.notes,
-        })
+      if (check.status === 'available') {
+        const { update } = check
+        setManifest({ version: update.version, date: update.pubDate, body: update.notes })
       }
+      setDisabled(check.status === 'disabled')
 
       setCheckComplete(true)
     } catch (err) {
       console.error('Failed to check for updates:', err)
-      setError(
-        `Failed to check for updates: ${err instanceof Error ? err.message : 'Unknown error'}`
-      )
+      setError(`Failed to check for updates: ${err instanceof Error ? err.message : String(err)}`)
       setCheckComplete(true)
     }
   }
 
-  // Simple version comparison function
-  const compareVersions = (version1: string, version2: string): number => {
-    const parts1 = version1.split('.').map(Number)
-    const parts2 = version2.split('.').map(Number)
-
-    for (let i = 0; i < Math.max(parts1.length, parts2.length); i++) {
-      const part1 = parts1[i] || 0
-      const part2 = parts2[i] || 0
+  const handleInstallUpdate = async () => {
+    if (!manifest) return
 
-      if (part1 > part2) return 1
-      if (part1 < part2) return -1
+    if (staged) {
+      // The staged installer runs before the app opens again
+      await invoke('updater_restart')
+      return
     }
 
-    return 0
+    const unlisten = await listen<DownloadProgress>('updater://progress', (event) => {
+      setProgress(event.payload)
+    })
+    try {
+      setProgress({ version: manifest.version, downloaded: 0, total: null })
+      await invoke<StagedUpdate>('updater_download')
+      setStaged(true)
+    } catch (err) {
+      console.error('Failed to download update:', err)
+      setError(`Failed to download update: ${err instanceof Error ? err.message : String(err)}`)
+    } finally {
+      unlisten()
+      setProgress(null)
+    }
   }
 
-  const handleInstallUpdate = () => {
-    if (!manifest) return
-
-    // Open GitHub releases page for manual download
-    window.open('https://github.com/AnthonyRandom/commhub/releases', '_blank')
-    onDismiss() // Close the notification
+  const formatProgress = (value: DownloadProgress): string => {
+    const mb = (bytes: number) => (bytes / (1024 * 1024)).toFixed(1)
+    if (value.total) {
+      const percent = Math.floor((value.downloaded / value.total) * 100)
+      return `${percent}% (${mb(value.downloaded)} / ${mb(value.total)} MB)`
+    }
+    return `${mb(value.downloaded)} MB`
   }
 
   // Show a loading state while checking for updates
@@ -122,7 +147,8 @@ const UpdateNotification: React.FC<UpdateNotificationProps> = ({ onDismiss }) =>
     )
   }
 
-  // Show "no updates available" message when check is complete but no update found
+  // Show "no updates available" message when check is complete but no update found,
+  // or that this build can't update at all
   if (checkComplete && !manifest && !error) {
     return (
       <div className="fixed bottom-4 right-4 z-50 animate-slide-up">
@@ -131,7 +157,9 @@ const UpdateNotification: React.FC<UpdateNotificationProps> = ({ onDismiss }) =>
             <div className="flex items-center justify-between">
               <div className="flex items-center gap-2">
                 <Download className="w-5 h-5 text-green-400" />
-                <h3 className="font-bold text-white text-lg">No Updates Available</h3>
+                <h3 className="font-bold text-white text-lg">
+                  {disabled ? 'Updates Unavailable' : 'No Updates Available'}
+                </h3>
               </div>
               <button
                 onClick={onDismiss}
@@ -144,7 +172,9 @@ const UpdateNotification: React.FC<UpdateNotificationProps> = ({ onDismiss }) =>
           </div>
           <div className="p-4">
             <p className="text-grey-300 text-sm mb-4">
-              You are running the latest version of CommHub (v{config.CLIENT_VERSION}).
+              {disabled
+                ? `This build of CommHub (v${config.CLIENT_VERSION}) can't update itself.`
+                : `You are running the latest version of CommHub (v${config.CLIENT_VERSION}).`}
             </p>
             <p className="text-grey-400 text-xs mb-4">Check for new releases manually:</p>
             <a
@@ -216,12 +246,29 @@ const UpdateNotification: React.FC<UpdateNotificationProps> = ({ onDismiss }) =>
                 </div>
               )}
 
+              {progress && (
+                <div className="mb-4">
+                  <div className="h-2 bg-grey-800 border border-grey-700">
+                    <div
+                      className="h-full bg-green-700 transition-all"
+                      style={{
+                        width: progress.total
+                          ? `${Math.min(100, (progress.downloaded / progress.total) * 100)}%`
+                          : '0%',
+                      }}
+                    />
+                  </div>
+                  <p className="text-grey-400 text-xs mt-1">{formatProgress(progress)}</p>
+                </div>
+              )}
+
               <div className="flex gap-2">
                 <button
                   onClick={handleInstallUpdate}
-                  className="flex-1 px-4 py-2 bg-green-900 text-white border-2 border-green-700 hover:bg-green-800 hover:border-green-500 transition-colors font-bold text-sm"
+                  disabled={progress !== null}
+                  className="flex-1 px-4 py-2 bg-green-900 text-white border-2 border-green-700 hover:bg-green-800 hover:border-green-500 transition-colors font-bold text-sm disabled:opacity-50"
                 >
-                  Download Update
+                  {staged ? 'Restart to Update' : progress ? 'Downloading...' : 'Download Update'}
                 </button>
                 <button
                   onClick={onDismiss}
@@ -231,7 +278,9 @@ const UpdateNotification: React.FC<UpdateNotificationProps> = ({ onDismiss }) =>
                 </button>
               </div>
               <p className="text-grey-500 text-xs mt-2">
-                Download the latest version from GitHub releases.
+                {staged
+                  ? 'The update is verified and will install when CommHub restarts.'
+                  : 'The update downloads in the background and installs on the next restart.'}
               </p>
             </>
           ) : null}
@@ -0,0 +1,73 @@
+import React from 'react'
+import { File, AlertTriangle, Upload, X } from 'lucide-react'
+import type { DroppedFile } from '../../services/file-drop'
+
+interface DropPreviewProps {
+  files: DroppedFile[]
+  onUpload: (files: DroppedFile[]) => void
+  onCancel: () => void
+}
+
+const formatFileSize = (bytes: number): string => {
+  if (bytes < 1024) return `${bytes} B`
+  if (bytes < 1024 * 1024) return `${(bytes / 1024).toFixed(1)} KB`
+  return `${(bytes / (1024 * 1024)).toFixed(1)} MB`
+}
+
+/**
+ * Files dropped onto the window, as validated by the backend, waiting to be
+ * confirmed. Rejected files are listed with the reason and never uploaded.
+ */
+export const DropPreview: React.FC<DropPreviewProps> = ({ files, onUpload, onCancel }) => {
+  const accepted = files.filter((file) => !file.problem)
+
+  return (
+    <div className="px-4 pt-3 pb-2 bg-grey-850 border-b-2 border-grey-800 animate-slide-down">
+      <div className="flex flex-wrap gap-2 mb-3">
+        {files.map((file) => (
+          <div
+            key={file.id}
+            className={`bg-grey-800 border-2 p-2 flex items-center gap-3 max-w-xs ${
+              file.problem ? 'border-red-600' : 'border-grey-700'
+            }`}
+          >
+            {file.thumbnail ? (
+              <img src={file.thumbnail} alt={file.name} className="w-12 h-12 object-cover" />
+            ) : (
+              <div className="w-12 h-12 flex items-center justify-center text-grey-400">
+                {file.problem ? (
+                  <AlertTriangle className="w-6 h-6 text-red-400" />
+                ) : (
+                  <File className="w-6 h-6" />
+                )}
+              </div>
+            )}
+            <div className="min-w-0">
+              <p className="text-white text-sm truncate">{file.name}</p>
+              <p className={`text-xs ${file.problem ? 'text-red-400' : 'text-grey-400'}`}>
+                {file.problem ? `This file ${file.problem}` : formatFileSize(file.size)}
+              </p>
+            </div>
+          </div>
+        ))}
+      </div>
+      <div className="flex gap-2">
+        <button
+          onClick={() => onUpload(accepted)}
+          disabled={accepted.length === 0}
+          className="px-4 py-2 bg-white text-black hover:bg-grey-100 disabled:bg-grey-700 disabled:text-grey-500 disabled:cursor-not-allowed transition-colors text-sm font-bold flex items-center gap-2"
+        >
+          <Upload className="w-4 h-4" />
+          Upload {accepted.length === 1 ? '1 file' : `${accepted.length} files`}
+        </button>
+        <button
+          onClick={onCancel}
+          className="px-4 py-2 bg-grey-800 text-white border-2 border-grey-700 hover:border-white transition-colors text-sm font-bold flex items-center gap-2"
+        >
+          <X className="w-4 h-4" />
+          Cancel
+        </button>
+      </div>
+    </div>
+  )
+}
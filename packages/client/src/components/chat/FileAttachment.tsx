@@ -1,7 +1,23 @@
-import React, { useState } from 'react'
-import { FileText, Film, Music, Image as ImageIcon, Download, X, Archive, File } from 'lucide-react'
+import React, { useEffect, useState } from 'react'
+import {
+  FileText,
+  Film,
+  Music,
+  Image as ImageIcon,
+  Download,
+  X,
+  Archive,
+  File,
+  Pause,
+  Play,
+  RotateCw,
+  ShieldCheck,
+  Check,
+} from 'lucide-react'
 import type { Attachment } from '../../services/api'
 import config from '../../config/environment'
+import { downloadService } from '../../services/downloads'
+import { useDownloadsStore } from '../../stores/downloads'
 import { GifHoverActions } from './GifHoverActions'
 
 interface FileAttachmentProps {
@@ -24,6 +40,46 @@ export const FileAttachment: React.FC<FileAttachmentProps> = ({
     ? attachment.url
     : `${config.API_URL}${attachment.url}`
 
+  // Media renders from the local attachment cache once it's been fetched
+  const isMedia = /^(image|video|audio)\//.test(attachment.mimeType)
+  const [src, setSrc] = useState(fullUrl)
+  const download = useDownloadsStore((state) => state.downloads[fullUrl])
+
+  useEffect(() => {
+    setSrc(fullUrl)
+    if (!isMedia) {
+      return
+    }
+    let cancelled = false
+    downloadService.resolve(fullUrl, attachment.sha256).then((resolved) => {
+      if (!cancelled) {
+        setSrc(resolved)
+      }
+    })
+    return () => {
+      cancelled = true
+    }
+  }, [fullUrl, isMedia, attachment.sha256])
+
+  // A cached copy that fails to load falls back to the server before giving up
+  const handleMediaError = (giveUp: () => void) => () => {
+    if (src !== fullUrl) {
+      setSrc(fullUrl)
+    } else {
+      giveUp()
+    }
+  }
+
+  const handleDownload = (e: React.MouseEvent) => {
+    if (!window.__TAURI__) {
+      return
+    }
+    e.preventDefault()
+    downloadService.save(fullUrl, attachment.filename, attachment.sha256).catch((error) => {
+      console.error('[FileAttachment] Failed to start download:', error)
+    })
+  }
+
   const formatFileSize = (bytes: number): string => {
     if (bytes < 1024) return `${bytes} B`
     if (bytes < 1024 * 1024) return `${(bytes / 1024).toFixed(1)} KB`
@@ -56,11 +112,11 @@ export const FileAttachment: React.FC<FileAttachmentProps> = ({
         <div className="relative inline-block animate-slide-up">
           <div className="bg-grey-850 border-2 border-grey-700 overflow-hidden inline-block group relative">
             <img
-              src={fullUrl}
+              src={src}
               alt={attachment.filename}
               className="block h-auto max-h-96 max-w-full cursor-pointer hover:opacity-90 transition-opacity"
               onClick={() => setLightboxOpen(true)}
-              onError={() => setImageError(true)}
+              onError={handleMediaError(() => setImageError(true))}
               loading="lazy"
             />
             {/* Show favorite button for GIFs (positioned on left when editing, right when not) */}
@@ -114,7 +170,7 @@ export const FileAttachment: React.FC<FileAttachmentProps> = ({
               <X className="w-6 h-6" />
             </button>
             <img
-              src={fullUrl}
+              src={src}
               alt={attachment.filename}
               className="max-w-full max-h-full object-contain"
               onClick={(e) => e.stopPropagation()}
@@ -147,6 +203,7 @@ export const FileAttachment: React.FC<FileAttachmentProps> = ({
               <a
                 href={fullUrl}
                 download={attachment.filename}
+                onClick={handleDownload}
                 className="inline-flex items-center gap-2 px-4 py-2 bg-white hover:bg-grey-200 text-black border-2 border-white transition-colors text-sm"
                 title="Try downloading the file"
               >
@@ -163,10 +220,10 @@ export const FileAttachment: React.FC<FileAttachmentProps> = ({
       <div className="mt-2 max-w-md animate-slide-up">
         <div className="bg-grey-850 border-2 border-grey-700 overflow-hidden relative group">
           <video
-            src={fullUrl}
+            src={src}
             controls
             className="w-full h-auto max-h-96"
-            onError={() => setVideoError(true)}
+            onError={handleMediaError(() => setVideoError(true))}
           >
             Your browser does not support the video tag.
           </video>
@@ -221,6 +278,7 @@ export const FileAttachment: React.FC<FileAttachmentProps> = ({
               <a
                 href={fullUrl}
                 download={attachment.filename}
+                onClick={handleDownload}
                 className="inline-flex items-center gap-2 px-4 py-2 bg-white hover:bg-grey-200 text-black border-2 border-white transition-colors text-sm"
                 title="Try downloading the file"
               >
@@ -254,7 +312,12 @@ export const FileAttachment: React.FC<FileAttachmentProps> = ({
               </button>
             )}
           </div>
-          <audio src={fullUrl} controls className="w-full" onError={() => setAudioError(true)}>
+          <audio
+            src={src}
+            controls
+            className="w-full"
+            onError={handleMediaError(() => setAudioError(true))}
+          >
             Your browser does not support the audio tag.
           </audio>
         </div>
@@ -271,7 +334,11 @@ export const FileAttachment: React.FC<FileAttachmentProps> = ({
         </div>
         <div className="flex-1 min-w-0">
           <p className="text-white font-bold text-sm truncate">{attachment.filename}</p>
-          <p className="text-grey-400 text-xs">{formatFileSize(attachment.size)}</p>
+          {download ? (
+            <DownloadStatusLine url={fullUrl} size={attachment.size} />
+          ) : (
+            <p className="text-grey-400 text-xs">{formatFileSize(attachment.size)}</p>
+          )}
         </div>
         <div className="flex items-center gap-2">
           {showRemove && onRemove ? (
@@ -282,10 +349,13 @@ export const FileAttachment: React.FC<FileAttachmentProps> = ({
             >
               <X className="w-4 h-4" />
             </button>
+          ) : download ? (
+            <DownloadControls url={fullUrl} />
           ) : (
             <a
               href={fullUrl}
               download={attachment.filename}
+              onClick={handleDownload}
               className="p-2 bg-white hover:bg-grey-200 text-black border-2 border-white transition-colors"
               title="Download"
             >
@@ -297,3 +367,99 @@ export const FileAttachment: React.FC<FileAttachmentProps> = ({
     </div>
   )
 }
+
+const formatProgress = (received: number, total: number | null): string => {
+  if (total) {
+    return `${Math.floor((received * 100) / total)}%`
+  }
+  return `${(received / (1024 * 1024)).toFixed(1)} MB`
+}
+
+/** Progress, or the outcome, of a save started from a file card. */
+const DownloadStatusLine: React.FC<{ url: string; size: number }> = ({ url, size }) => {
+  const download = useDownloadsStore((state) => state.downloads[url])
+  if (!download) {
+    return null
+  }
+  const total = download.total ?? (size || null)
+
+  switch (download.status) {
+    case 'downloading':
+    case 'paused':
+      return (
+        <div className="flex items-center gap-2">
+          <div className="flex-1 h-1.5 bg-grey-800 max-w-40">
+            <div
+              className="h-full bg-white transition-all"
+              style={{ width: total ? `${(download.received * 100) / total}%` : '0%' }}
+            />
+          </div>
+          <p className="text-grey-400 text-xs">
+            {download.status === 'paused' ? 'Paused' : formatProgress(download.received, total)}
+          </p>
+        </div>
+      )
+    case 'done':
+      return (
+        <p className="text-grey-400 text-xs flex items-center gap-1">
+          {download.verified ? (
+            <>
+              <ShieldCheck className="w-3 h-3 text-green-500" /> Saved and verified
+            </>
+          ) : (
+            <>
+              <Check className="w-3 h-3" /> Saved
+            </>
+          )}
+        </p>
+      )
+    case 'failed':
+      return (
+        <p className="text-red-400 text-xs truncate" title={download.error}>
+          {download.error ?? 'Download failed'}
+        </p>
+      )
+    default:
+      return null
+  }
+}
+
+/** Pause, resume, retry and cancel buttons for a save in progress. */
+const DownloadControls: React.FC<{ url: string }> = ({ url }) => {
+  const download = useDownloadsStore((state) => state.downloads[url])
+  if (!download) {
+    return null
+  }
+  const button =
+    'p-2 bg-grey-800 hover:bg-grey-700 text-white border-2 border-grey-700 hover:border-white transition-colors'
+
+  if (download.status === 'done') {
+    return (
+      <button onClick={() => downloadService.dismiss(url)} className={button} title="Dismiss">
+        <X className="w-4 h-4" />
+      </button>
+    )
+  }
+  return (
+    <>
+      {download.status === 'downloading' && (
+        <button onClick={() => downloadService.pause(url)} className={button} title="Pause">
+          <Pause className="w-4 h-4" />
+        </button>
+      )}
+      {download.status === 'paused' && (
+        <button onClick={() => downloadService.resume(url)} className={button} title="Resume">
+          <Play className="w-4 h-4" />
+        </button>
+      )}
+      {download.status === 'failed' && (
+        <button onClick={() => downloadService.resume(url)} className={button} title="Retry">
+          <RotateCw className="w-4 h-4" />
+        </button>
+      )}
+      <button onClick={() => downloadService.cancel(url)} className={button} title="Cancel">
+        <X className="w-4 h-4" />
+      </button>
+    </>
+  )
+}
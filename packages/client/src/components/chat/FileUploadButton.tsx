@@ -1,21 +1,33 @@
 import React, { useRef, useState } from 'react'
 import { Paperclip, X } from 'lucide-react'
+import { fileDropService, type DroppedFile } from '../../services/file-drop'
 
+// Mirrors MAX_FILE_SIZE in src-tauri/src/file_drop/inspect.rs, which checks native uploads
 const MAX_FILE_SIZE = 20 * 1024 * 1024 // 20MB
 
 interface FileUploadButtonProps {
   onFilesSelected: (files: File[]) => void
+  // Under Tauri, the backend opens the picker and uploads what was picked
+  onPicked?: (files: DroppedFile[]) => void
   disabled?: boolean
 }
 
 export const FileUploadButton: React.FC<FileUploadButtonProps> = ({
   onFilesSelected,
+  onPicked,
   disabled = false,
 }) => {
   const fileInputRef = useRef<HTMLInputElement>(null)
   const [error, setError] = useState<string | null>(null)
 
-  const handleClick = () => {
+  const handleClick = async () => {
+    if (window.__TAURI__ && onPicked) {
+      const picked = await fileDropService.pick()
+      if (picked.length > 0) {
+        onPicked(picked)
+      }
+      return
+    }
     if (fileInputRef.current) {
       fileInputRef.current.click()
     }
@@ -1,5 +1,5 @@
 import React, { useEffect, useRef, useState } from 'react'
-import { MicOff, VolumeX, Volume2, Volume1, X } from 'lucide-react'
+import { MicOff, VolumeX, Volume2, Volume1, X, ExternalLink } from 'lucide-react'
 import { voiceManager } from '../../services/voice-manager'
 import type { VoiceUser } from '../../stores/voice'
 
@@ -8,6 +8,8 @@ interface FocusedStreamViewProps {
   stream: MediaStream | undefined
   isScreenShare: boolean
   onClose: () => void
+  // Moves the stream into its own window, where supported
+  onPopOut?: () => void
 }
 
 export const FocusedStreamView: React.FC<FocusedStreamViewProps> = ({
@@ -15,6 +17,7 @@ export const FocusedStreamView: React.FC<FocusedStreamViewProps> = ({
   stream,
   isScreenShare,
   onClose,
+  onPopOut,
 }) => {
   const videoRef = useRef<HTMLVideoElement>(null)
   const [showContextMenu, setShowContextMenu] = useState(false)
@@ -81,13 +84,27 @@ export const FocusedStreamView: React.FC<FocusedStreamViewProps> = ({
       onContextMenu={handleRightClick}
     >
       {/* Close button */}
-      <button
-        onClick={onClose}
-        className="absolute top-4 right-4 z-50 p-2 bg-grey-900 border-2 border-grey-700 text-white hover:border-white transition-all"
-        title="Close focused view"
-      >
-        <X className="w-5 h-5" />
-      </button>
+      <div className="absolute top-4 right-4 z-50 flex gap-2">
+        {onPopOut && (
+          <button
+            onClick={(e) => {
+              e.stopPropagation()
+              onPopOut()
+            }}
+            className="p-2 bg-grey-900 border-2 border-grey-700 text-white hover:border-white transition-all"
+            title="Open in new window"
+          >
+            <ExternalLink className="w-5 h-5" />
+          </button>
+        )}
+        <button
+          onClick={onClose}
+          className="p-2 bg-grey-900 border-2 border-grey-700 text-white hover:border-white transition-all"
+          title="Close focused view"
+        >
+          <X className="w-5 h-5" />
+        </button>
+      </div>
 
       {/* Video stream */}
       <div className="w-full h-full flex items-center justify-center video-container">
@@ -7,6 +7,16 @@ import { MentionAutocomplete } from './MentionAutocomplete'
 import { apiService } from '../../services/api'
 import { useServersStore } from '../../stores/servers'
 import { parseMentionsInMessage } from '../../utils/mentionUtils'
+import { FOCUS_INPUT_EVENT } from '../../services/navigation'
+import { clipboardService } from '../../services/clipboard'
+import { fileDropService, DroppedFile } from '../../services/file-drop'
+import { DropPreview } from './DropPreview'
+import {
+  uploadService,
+  UploadHandle,
+  UploadPrepared,
+  UploadProgress,
+} from '../../services/uploads'
 
 interface MessageInputProps {
   messageInput: string
@@ -42,6 +52,10 @@ export const MessageInput: React.FC<MessageInputProps> = ({
     Array<{ url: string; filename: string; mimeType: string; size: number }>
   >([])
   const [uploadingFiles, setUploadingFiles] = useState<File[]>([])
+  const [droppedFiles, setDroppedFiles] = useState<DroppedFile[]>([])
+  const [nativeUploads, setNativeUploads] = useState<
+    Array<{ id: number; name: string; progress: number; thumbnail?: string }>
+  >([])
   const [uploadError, setUploadError] = useState<string | null>(null)
   const [showMentionAutocomplete, setShowMentionAutocomplete] = useState(false)
   const [mentionQuery, setMentionQuery] = useState('')
@@ -57,10 +71,18 @@ export const MessageInput: React.FC<MessageInputProps> = ({
     inputRef.current?.focus()
   }, [channelName])
 
+  // Focus input when asked to, e.g. by a notification's Reply action
+  useEffect(() => {
+    const focusInput = () => inputRef.current?.focus()
+    window.addEventListener(FOCUS_INPUT_EVENT, focusInput)
+    return () => window.removeEventListener(FOCUS_INPUT_EVENT, focusInput)
+  }, [])
+
   // Clear attachments when channel changes
   useEffect(() => {
     setAttachments([])
     setUploadingFiles([])
+    setDroppedFiles([])
     setUploadError(null)
     setShowMentionAutocomplete(false)
   }, [channelId])
@@ -102,6 +124,21 @@ export const MessageInput: React.FC<MessageInputProps> = ({
     }
   }, [uploadingFiles.length])
 
+  // Under Tauri, drops are intercepted natively and arrive already validated
+  useEffect(() => {
+    const unsubscribe = fileDropService.subscribe({
+      onHover: () => setIsDragging(true),
+      onCancel: () => setIsDragging(false),
+      onPreview: (files) => {
+        setIsDragging(false)
+        setDroppedFiles(files)
+      },
+    })
+    return () => {
+      unsubscribe.then((fn) => fn())
+    }
+  }, [])
+
   // Handle mention autocomplete
   useEffect(() => {
     if (!showMentionAutocomplete) {
@@ -231,6 +268,107 @@ export const MessageInput: React.FC<MessageInputProps> = ({
     }
   }
 
+  const insertAtCursor = (text: string) => {
+    const input = inputRef.current
+    const start = input?.selectionStart ?? messageInput.length
+    const end = input?.selectionEnd ?? messageInput.length
+    const newValue = (messageInput.slice(0, start) + text + messageInput.slice(end)).slice(0, 2000)
+    setMessageInput(newValue)
+
+    setTimeout(() => {
+      if (inputRef.current) {
+        const cursorPos = Math.min(start + text.length, newValue.length)
+        inputRef.current.selectionStart = cursorPos
+        inputRef.current.selectionEnd = cursorPos
+        inputRef.current.style.height = 'auto'
+        inputRef.current.style.height = `${Math.min(inputRef.current.scrollHeight, 200)}px`
+      }
+    }, 0)
+  }
+
+  const handlePaste = async (e: React.ClipboardEvent<HTMLTextAreaElement>) => {
+    const files = Array.from(e.clipboardData.files)
+    if (files.length > 0) {
+      e.preventDefault()
+      if (uploadingFiles.length === 0) handleFilesSelected(files)
+      return
+    }
+
+    // Keep the formatting of text copied from browsers and editors
+    const html = e.clipboardData.getData('text/html')
+    if (window.__TAURI__ && html) {
+      e.preventDefault()
+      const plain = e.clipboardData.getData('text/plain')
+      const markdown = await clipboardService.htmlToMarkdown(html).catch(() => plain)
+      insertAtCursor(markdown || plain)
+      return
+    }
+
+    // Screenshots reach the webview as neither files nor text
+    if (!e.clipboardData.getData('text/plain') && uploadingFiles.length === 0) {
+      const image = await clipboardService.readImage()
+      if (image) handleFilesSelected([image])
+    }
+  }
+
+  // For DMs, use receiverId; for channels, use channelId
+  const uploadTarget = () =>
+    dmUsers && dmUsers.length > 0 ? { receiverId: dmUsers[0].id } : { channelId }
+
+  const updateProgress = ({ id, sent, total }: UploadProgress) => {
+    const progress = total > 0 ? Math.round((sent / total) * 100) : 0
+    setNativeUploads((prev) => prev.map((u) => (u.id === id ? { ...u, progress } : u)))
+  }
+
+  // Images are renamed when they're re-encoded, e.g. photo.png to photo.jpg
+  const updatePrepared = ({ id, name, thumbnail }: UploadPrepared) => {
+    setNativeUploads((prev) => prev.map((u) => (u.id === id ? { ...u, name, thumbnail } : u)))
+  }
+
+  const uploadCallbacks = { onProgress: updateProgress, onPrepared: updatePrepared }
+
+  // Uploads run by the backend; they queue and report progress until done
+  const trackUpload = async (name: string, started: Promise<UploadHandle>) => {
+    try {
+      const { id, done } = await started
+      setNativeUploads((prev) => [...prev, { id, name, progress: 0 }])
+      try {
+        const uploadedFile = await done
+        if (uploadedFile) setAttachments((prev) => [...prev, uploadedFile])
+      } finally {
+        setNativeUploads((prev) => prev.filter((u) => u.id !== id))
+      }
+    } catch (error) {
+      console.error('Failed to upload file:', error)
+      setUploadError(`Failed to upload ${name}: ${error instanceof Error ? error.message : error}`)
+    }
+  }
+
+  const handlePicked = (files: DroppedFile[]) => {
+    const problems = files.filter((file) => file.problem)
+    setUploadError(
+      problems.length > 0
+        ? problems.map((file) => `${file.name} ${file.problem}`).join(', ')
+        : null
+    )
+    for (const file of files.filter((file) => !file.problem)) {
+      trackUpload(file.name, uploadService.uploadDropped(file, uploadTarget(), uploadCallbacks))
+    }
+  }
+
+  const handleUploadDropped = (files: DroppedFile[]) => {
+    setDroppedFiles([])
+    setUploadError(null)
+    for (const file of files) {
+      trackUpload(file.name, uploadService.uploadDropped(file, uploadTarget(), uploadCallbacks))
+    }
+  }
+
+  const handleCancelDropped = () => {
+    fileDropService.discard(droppedFiles)
+    setDroppedFiles([])
+  }
+
   const handleRemoveAttachment = (index: number) => {
     setAttachments((prev) => prev.filter((_, i) => i !== index))
     setUploadError(null)
@@ -357,8 +495,17 @@ export const MessageInput: React.FC<MessageInputProps> = ({
         </div>
       )}
 
+      {/* Dropped files awaiting confirmation */}
+      {droppedFiles.length > 0 && (
+        <DropPreview
+          files={droppedFiles}
+          onUpload={handleUploadDropped}
+          onCancel={handleCancelDropped}
+        />
+      )}
+
       {/* Attachments Preview */}
-      {(attachments.length > 0 || uploadingFiles.length > 0) && (
+      {(attachments.length > 0 || uploadingFiles.length > 0 || nativeUploads.length > 0) && (
         <div className="px-4 pt-3 pb-2 bg-grey-850 border-b-2 border-grey-800">
           <div className="flex flex-wrap gap-2">
             {attachments.map((attachment, index) => (
@@ -386,6 +533,27 @@ export const MessageInput: React.FC<MessageInputProps> = ({
                 </button>
               </div>
             ))}
+            {nativeUploads.map((upload) => (
+              <div
+                key={`native-${upload.id}`}
+                className="bg-grey-800 border-2 border-grey-700 p-3 flex items-center gap-3 rounded"
+              >
+                {upload.thumbnail ? (
+                  <img src={upload.thumbnail} alt={upload.name} className="w-8 h-8 object-cover" />
+                ) : (
+                  <Loader className="w-5 h-5 text-grey-400 animate-spin" />
+                )}
+                <span className="text-grey-300 text-sm">{upload.name}</span>
+                <span className="text-grey-500 text-xs">{upload.progress}%</span>
+                <button
+                  onClick={() => uploadService.cancel(upload.id)}
+                  className="ml-2 p-1 hover:bg-grey-700 text-grey-400 hover:text-white transition-colors rounded"
+                  title="Cancel upload"
+                >
+                  <X className="w-4 h-4" />
+                </button>
+              </div>
+            ))}
           </div>
           {uploadError && (
             <div className="mt-2 text-red-400 text-sm flex items-center gap-2">
@@ -436,6 +604,7 @@ export const MessageInput: React.FC<MessageInputProps> = ({
             value={messageInput}
             onChange={handleInputChange}
             onKeyDown={handleKeyDown}
+            onPaste={handlePaste}
             placeholder={dmUsers ? `Message ${channelName}` : `Message #${channelName}`}
             className="w-full bg-grey-850 border-2 border-grey-700 px-4 py-3 pr-32 text-white resize-none focus:border-white placeholder:text-grey-500"
             rows={1}
@@ -453,6 +622,7 @@ export const MessageInput: React.FC<MessageInputProps> = ({
           <div className="absolute right-2 top-[10px] flex items-center gap-1 z-10">
             <FileUploadButton
               onFilesSelected={handleFilesSelected}
+              onPicked={handlePicked}
               disabled={uploadingFiles.length > 0}
             />
             <button
@@ -1,9 +1,18 @@
 import React, { useRef, useEffect } from 'react'
-import { MoreVertical, Edit2, Trash2, Reply as ReplyIcon } from 'lucide-react'
+import {
+  MoreVertical,
+  Edit2,
+  Trash2,
+  Reply as ReplyIcon,
+  Link as LinkIcon,
+  Copy,
+} from 'lucide-react'
 import MediaEmbed from '../MediaEmbed'
 import { FileAttachment } from './FileAttachment'
 import { GifHoverActions } from './GifHoverActions'
 import { parseMentionsInMessage } from '../../utils/mentionUtils'
+import { deepLinkService } from '../../services/deeplink'
+import { clipboardService } from '../../services/clipboard'
 import type { Message } from '../../services/api'
 
 interface MessageItemProps {
@@ -258,6 +267,30 @@ export const MessageItem: React.FC<MessageItemProps> = ({
                     <ReplyIcon className="w-4 h-4" />
                     Reply
                   </button>
+                  <button
+                    onClick={() => {
+                      clipboardService.copyMessage(message.content)
+                      setContextMenuMessageId(null)
+                    }}
+                    className="w-full px-4 py-2 text-left text-white hover:bg-grey-800 flex items-center gap-2 transition-colors"
+                  >
+                    <Copy className="w-4 h-4" />
+                    Copy Text
+                  </button>
+                  <button
+                    onClick={() => {
+                      deepLinkService.copy({
+                        kind: 'message',
+                        channelId: message.channelId,
+                        messageId: message.id,
+                      })
+                      setContextMenuMessageId(null)
+                    }}
+                    className="w-full px-4 py-2 text-left text-white hover:bg-grey-800 flex items-center gap-2 transition-colors"
+                  >
+                    <LinkIcon className="w-4 h-4" />
+                    Copy Link
+                  </button>
                   {canModify && (
                     <button
                       onClick={() => onStartEditing(message)}
@@ -423,6 +456,30 @@ export const MessageItem: React.FC<MessageItemProps> = ({
                   <ReplyIcon className="w-4 h-4" />
                   Reply
                 </button>
+                <button
+                  onClick={() => {
+                    clipboardService.copyMessage(message.content)
+                    setContextMenuMessageId(null)
+                  }}
+                  className="w-full px-4 py-2 text-left text-white hover:bg-grey-800 flex items-center gap-2 transition-colors"
+                >
+                  <Copy className="w-4 h-4" />
+                  Copy Text
+                </button>
+                <button
+                  onClick={() => {
+                    deepLinkService.copy({
+                      kind: 'message',
+                      channelId: message.channelId,
+                      messageId: message.id,
+                    })
+                    setContextMenuMessageId(null)
+                  }}
+                  className="w-full px-4 py-2 text-left text-white hover:bg-grey-800 flex items-center gap-2 transition-colors"
+                >
+                  <LinkIcon className="w-4 h-4" />
+                  Copy Link
+                </button>
                 {canModify && (
                   <button
                     onClick={() => onStartEditing(message)}
@@ -0,0 +1,108 @@
+import { useEffect, useState } from 'react'
+import { listen } from '@tauri-apps/api/event'
+import { invoke } from '@tauri-apps/api/tauri'
+import { MicOff } from 'lucide-react'
+import type {
+  OverlayRoster,
+  OverlaySettings,
+  OverlaySnapshot,
+  OverlayUpdate,
+} from '../../services/overlay'
+import { logger } from '../../utils/logger'
+
+// Same rules as Roster::apply in src-tauri/src/overlay/roster.rs
+const applyUpdate = (roster: OverlayRoster, update: OverlayUpdate): OverlayRoster => {
+  switch (update.kind) {
+    case 'roster':
+      return { channelName: update.channelName, participants: update.participants }
+    case 'speaking':
+      return {
+        ...roster,
+        participants: roster.participants.map((p) =>
+          p.userId === update.userId ? { ...p, speaking: update.speaking } : p
+        ),
+      }
+    case 'clear':
+      return { channelName: null, participants: [] }
+  }
+}
+
+const ALIGNMENT: Record<OverlaySettings['corner'], string> = {
+  topLeft: 'justify-start items-start',
+  topRight: 'justify-start items-end',
+  bottomLeft: 'justify-end items-start',
+  bottomRight: 'justify-end items-end',
+}
+
+/**
+ * The root of the voice overlay window: a compact list of who's in the call,
+ * lit up while they talk. Everything it shows comes from the backend.
+ */
+function OverlayApp() {
+  const [settings, setSettings] = useState<OverlaySettings | null>(null)
+  const [roster, setRoster] = useState<OverlayRoster>({ channelName: null, participants: [] })
+
+  useEffect(() => {
+    // The window is transparent; only the roster itself should draw
+    document.documentElement.style.background = 'transparent'
+    document.body.style.background = 'transparent'
+
+    const unlisten = [
+      listen<OverlayUpdate>('overlay://update', (event) =>
+        setRoster((current) => applyUpdate(current, event.payload))
+      ),
+      listen<OverlaySettings>('overlay://settings', (event) => setSettings(event.payload)),
+    ]
+
+    invoke<OverlaySnapshot>('overlay_snapshot')
+      .then((snapshot) => {
+        setSettings(snapshot.settings)
+        setRoster(snapshot.roster)
+      })
+      .catch((error) => logger.error('Overlay', 'Failed to load overlay', { error }))
+
+    return () => unlisten.forEach((promise) => promise.then((fn) => fn()))
+  }, [])
+
+  if (!settings) {
+    return null
+  }
+
+  return (
+    <main
+      className={`flex flex-col h-screen p-1 overflow-hidden ${ALIGNMENT[settings.corner]}`}
+      style={{ opacity: settings.opacity }}
+    >
+      <div
+        className={`bg-black/80 text-white px-2 py-1.5 max-w-full border-2 ${
+          settings.clickThrough ? 'border-transparent' : 'border-white overflow-y-auto'
+        }`}
+      >
+        {roster.channelName && (
+          <p className="text-grey-400 text-xs font-bold uppercase tracking-wider truncate mb-1">
+            {roster.channelName}
+          </p>
+        )}
+        {roster.participants.map((participant) => (
+          <div key={participant.userId} className="flex items-center gap-2 py-0.5">
+            <span
+              className={`w-2 h-2 flex-shrink-0 ${
+                participant.speaking ? 'bg-green-400' : 'bg-grey-700'
+              }`}
+            />
+            <span
+              className={`text-sm truncate ${
+                participant.speaking ? 'text-white font-bold' : 'text-grey-300'
+              }`}
+            >
+              {participant.username}
+            </span>
+            {participant.muted && <MicOff className="w-3 h-3 text-red-400 flex-shrink-0" />}
+          </div>
+        ))}
+      </div>
+    </main>
+  )
+}
+
+export default OverlayApp
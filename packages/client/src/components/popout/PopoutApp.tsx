@@ -0,0 +1,103 @@
+import { useEffect, useState } from 'react'
+import { Pin, PinOff, X } from 'lucide-react'
+import ChatArea from '../ChatArea'
+import FriendsPanel from '../FriendsPanel'
+import { PopoutStreamView } from './PopoutStreamView'
+import { useAuthStore } from '../../stores/auth'
+import { useChannelsStore } from '../../stores/channels'
+import { useServersStore } from '../../stores/servers'
+import { wsManager } from '../../services/websocket-manager'
+import { popoutService, type PopoutContext } from '../../services/popout'
+import type { Channel, Server } from '../../services/api'
+
+interface PopoutAppProps {
+  popout: PopoutContext
+}
+
+/**
+ * The root of a pop-out window: one DM, channel or stream, without the
+ * sidebar. Shares the session and socket with the main window.
+ */
+function PopoutApp({ popout }: PopoutAppProps) {
+  const { target, label } = popout
+  const { isAuthenticated, isLoading } = useAuthStore()
+  const [channel, setChannel] = useState<Channel | null>(null)
+  const [server, setServer] = useState<Server | null>(null)
+  const [alwaysOnTop, setAlwaysOnTop] = useState(popout.alwaysOnTop)
+
+  useEffect(() => {
+    const initialize = async () => {
+      wsManager.initialize()
+      await useAuthStore.getState().checkAuth()
+    }
+    initialize()
+  }, [])
+
+  // Channel pop-outs need the channel and its server, which the main
+  // window had loaded but this webview hasn't
+  useEffect(() => {
+    if (!isAuthenticated || target.kind !== 'channel') {
+      return
+    }
+    const load = async () => {
+      await Promise.all([
+        useChannelsStore.getState().fetchChannels(),
+        useServersStore.getState().fetchServers(),
+      ])
+      const found = useChannelsStore.getState().channels.find((c) => c.id === target.channelId)
+      setChannel(found ?? null)
+      setServer(useServersStore.getState().servers.find((s) => s.id === found?.serverId) ?? null)
+    }
+    load()
+  }, [isAuthenticated, target])
+
+  const toggleAlwaysOnTop = () => {
+    const enabled = !alwaysOnTop
+    setAlwaysOnTop(enabled)
+    popoutService.setAlwaysOnTop(enabled)
+  }
+
+  const renderContent = () => {
+    if (isLoading || !isAuthenticated) {
+      return (
+        <div className="flex-1 flex items-center justify-center">
+          <div className="w-10 h-10 border-4 border-white border-t-transparent animate-spin"></div>
+        </div>
+      )
+    }
+    switch (target.kind) {
+      case 'direct':
+        return <FriendsPanel selectedDMUserId={target.userId} />
+      case 'channel':
+        return <ChatArea selectedChannel={channel} server={server} />
+      case 'stream':
+        return <PopoutStreamView label={label} userId={target.userId} />
+    }
+  }
+
+  return (
+    <main className="flex flex-col h-screen bg-grey-950 text-white overflow-hidden">
+      <div className="h-8 border-b-2 border-grey-800 px-2 flex items-center justify-end gap-1 flex-shrink-0">
+        <button
+          onClick={toggleAlwaysOnTop}
+          className={`p-1 transition-colors ${
+            alwaysOnTop ? 'text-white bg-grey-800' : 'text-grey-400 hover:text-white'
+          }`}
+          title={alwaysOnTop ? 'Stop keeping on top' : 'Keep on top'}
+        >
+          {alwaysOnTop ? <PinOff className="w-4 h-4" /> : <Pin className="w-4 h-4" />}
+        </button>
+        <button
+          onClick={() => popoutService.close()}
+          className="p-1 text-grey-400 hover:text-white transition-colors"
+          title="Close window"
+        >
+          <X className="w-4 h-4" />
+        </button>
+      </div>
+      <div className="flex-1 flex min-h-0">{renderContent()}</div>
+    </main>
+  )
+}
+
+export default PopoutApp
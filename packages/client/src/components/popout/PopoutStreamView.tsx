@@ -0,0 +1,52 @@
+import React, { useEffect, useRef, useState } from 'react'
+import { receiveStream } from '../../services/popout-stream'
+
+interface PopoutStreamViewProps {
+  label: string
+  userId: number
+}
+
+/**
+ * A participant's camera or screen share, relayed from the main window.
+ * Audio stays in the main window with the rest of the call.
+ */
+export const PopoutStreamView: React.FC<PopoutStreamViewProps> = ({ label, userId }) => {
+  const videoRef = useRef<HTMLVideoElement>(null)
+  const [stream, setStream] = useState<MediaStream | null>(null)
+  const [ended, setEnded] = useState(false)
+
+  useEffect(() => {
+    return receiveStream(label, userId, {
+      onStream: (next) => {
+        setEnded(false)
+        setStream(next)
+      },
+      onEnded: () => {
+        setStream(null)
+        setEnded(true)
+      },
+    })
+  }, [label, userId])
+
+  useEffect(() => {
+    if (videoRef.current) {
+      videoRef.current.srcObject = stream
+    }
+  }, [stream])
+
+  return (
+    <div className="flex-1 bg-black flex items-center justify-center">
+      {stream ? (
+        <video
+          ref={videoRef}
+          autoPlay
+          playsInline
+          muted
+          className="max-w-full max-h-full object-contain"
+        />
+      ) : (
+        <p className="text-grey-400">{ended ? 'Stream ended' : 'Connecting to stream...'}</p>
+      )}
+    </div>
+  )
+}
@@ -11,10 +11,14 @@ if (typeof process === 'undefined') {
 import React from 'react'
 import { createRoot } from 'react-dom/client'
 import App from './App'
+import PopoutApp from './components/popout/PopoutApp'
+import OverlayApp from './components/overlay/OverlayApp'
 import { ErrorBoundary } from './components/ErrorBoundary'
 import './app.css'
 import { validateConfig } from './config/environment'
 import { logger } from './utils/logger'
+import { getPopout } from './services/popout'
+import { isOverlayWindow } from './services/overlay'
 
 // Validate environment configuration before starting app
 try {
@@ -43,11 +47,18 @@ try {
 
 const container = document.getElementById('app')!
 const root = createRoot(container)
+// Pop-out and overlay windows load the same bundle and render just their part
+const popout = getPopout()
+const content = isOverlayWindow() ? (
+  <OverlayApp />
+) : popout ? (
+  <PopoutApp popout={popout} />
+) : (
+  <App />
+)
 
 root.render(
   <React.StrictMode>
-    <ErrorBoundary>
-      <App />
-    </ErrorBoundary>
+    <ErrorBoundary>{content}</ErrorBoundary>
   </React.StrictMode>
 )
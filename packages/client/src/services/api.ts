@@ -43,6 +43,8 @@ export interface Attachment {
   filename: string
   mimeType: string
   size: number
+  // Hex SHA-256 of the stored file, where the server records one
+  sha256?: string | null
   createdAt: string
 }
 
@@ -122,6 +124,7 @@ export interface DirectMessage {
     filename: string
     mimeType: string
     size: number
+    sha256?: string | null
     createdAt: string
   }>
 }
@@ -139,6 +142,8 @@ export interface Conversation {
 
 class ApiService {
   private axiosInstance: AxiosInstance
+  // A token from signing in that the vault doesn't have yet
+  private pendingToken: string | null = null
 
   constructor() {
     this.axiosInstance = axios.create({
@@ -151,18 +156,7 @@ class ApiService {
     // Add request interceptor to include auth token
     this.axiosInstance.interceptors.request.use(
       async (config) => {
-        // Try to get token from persistent storage first, fallback to localStorage
-        try {
-          const { storageService } = await import('./storage')
-          const { token } = await storageService.loadAuth()
-          if (token) {
-            config.headers.Authorization = `Bearer ${token}`
-            return config
-          }
-        } catch (error) {
-          // Fallback to localStorage
-        }
-        const token = localStorage.getItem('auth_token')
+        const token = await this.getAuthToken()
         if (token) {
           config.headers.Authorization = `Bearer ${token}`
         }
@@ -575,12 +569,21 @@ class ApiService {
 
   // Utility methods
   async setAuthToken(token: string): Promise<void> {
+    if (window.__TAURI__) {
+      // Reaches the vault along with the user in setUser()
+      this.pendingToken = token
+      return
+    }
     // Always save to localStorage as immediate fallback
     // Persistent storage is handled by setUser() based on rememberMe flag
     localStorage.setItem('auth_token', token)
   }
 
   async getAuthToken(): Promise<string | null> {
+    // A sign-in in progress replaces whatever the vault holds
+    if (this.pendingToken) {
+      return this.pendingToken
+    }
     try {
       // Try persistent storage first
       const { storageService } = await import('./storage')
@@ -591,10 +594,11 @@ class ApiService {
     } catch (error) {
       // Fallback to localStorage
     }
-    return localStorage.getItem('auth_token')
+    return window.__TAURI__ ? null : localStorage.getItem('auth_token')
   }
 
   async removeAuthToken(): Promise<void> {
+    this.pendingToken = null
     try {
       const { storageService } = await import('./storage')
       await storageService.removeAuth()
@@ -606,6 +610,22 @@ class ApiService {
   }
 
   async setUser(user: User, shouldPersist: boolean = true): Promise<void> {
+    if (window.__TAURI__) {
+      const token = await this.getAuthToken()
+      if (!token) {
+        return
+      }
+      // The vault keeps a sign-in that isn't remembered until exit
+      try {
+        const { storageService } = await import('./storage')
+        await storageService.saveAuth(token, user, shouldPersist)
+        this.pendingToken = null
+      } catch (error) {
+        console.warn('Failed to save to persistent storage:', error)
+      }
+      return
+    }
+
     // Always save to localStorage as immediate fallback
     localStorage.setItem('user', JSON.stringify(user))
 
@@ -643,6 +663,9 @@ class ApiService {
     } catch (error) {
       // Fallback to localStorage
     }
+    if (window.__TAURI__) {
+      return null
+    }
     const userStr = localStorage.getItem('user')
     return userStr ? JSON.parse(userStr) : null
   }
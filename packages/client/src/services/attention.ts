@@ -0,0 +1,75 @@
+import { invoke } from '@tauri-apps/api/tauri'
+import { useDirectMessagesStore } from '../stores/directMessages'
+import { useMentionsStore } from '../stores/mentions'
+import { logger } from '../utils/logger'
+
+interface Unread {
+  count: number
+  urgent: boolean
+}
+
+/**
+ * Reports unread counts per conversation to the backend attention manager,
+ * which badges the window icon and asks for attention while it's unfocused.
+ * Mentions are urgent and escalate to a critical request.
+ */
+class AttentionService {
+  private initialized = false
+  private last = new Map<string, Unread>()
+
+  initialize(): void {
+    if (!window.__TAURI__ || this.initialized) {
+      return
+    }
+    this.initialized = true
+
+    useDirectMessagesStore.subscribe(() => this.sync())
+    useMentionsStore.subscribe(() => this.sync())
+    this.sync()
+  }
+
+  private collect(): Map<string, Unread> {
+    const sources = new Map<string, Unread>()
+    for (const conv of useDirectMessagesStore.getState().conversations) {
+      if (conv.unreadCount) {
+        sources.set(`dm:${conv.user.id}`, { count: conv.unreadCount, urgent: false })
+      }
+    }
+    for (const [channelId, count] of Object.entries(
+      useMentionsStore.getState().channelMentionCounts
+    )) {
+      if (count) {
+        sources.set(`mentions:${channelId}`, { count, urgent: true })
+      }
+    }
+    return sources
+  }
+
+  // Only sources whose count moved are sent, so growth is what escalates
+  private sync(): void {
+    const current = this.collect()
+
+    for (const [source, unread] of current) {
+      const previous = this.last.get(source)
+      if (previous?.count !== unread.count || previous.urgent !== unread.urgent) {
+        this.call('attention_set_unread', { source, ...unread })
+      }
+    }
+    for (const source of this.last.keys()) {
+      if (!current.has(source)) {
+        this.call('attention_clear', { source })
+      }
+    }
+
+    this.last = current
+  }
+
+  private call(command: string, args: Record<string, unknown>): void {
+    invoke(command, args).catch((error) => {
+      logger.warn('Attention', 'Failed to update unread attention', { command, error })
+    })
+  }
+}
+
+export const attentionService = new AttentionService()
+export default attentionService
@@ -0,0 +1,38 @@
+import { invoke } from '@tauri-apps/api/tauri'
+import { useSettingsStore } from '../stores/settings'
+import { logger } from '../utils/logger'
+
+/**
+ * Mirrors the settings the backend needs while no webview is loaded: the
+ * close policy and whether native notifications are wanted.
+ */
+class BackgroundService {
+  private initialized = false
+  private last = ''
+
+  initialize(): void {
+    if (!window.__TAURI__ || this.initialized) {
+      return
+    }
+    this.initialized = true
+
+    const sync = () => {
+      const { closePolicy, notifications } = useSettingsStore.getState()
+      const settings = { closePolicy, notifications }
+      const key = JSON.stringify(settings)
+      if (key === this.last) {
+        return
+      }
+      this.last = key
+      invoke('background_set_settings', { settings }).catch((error) => {
+        logger.warn('Background', 'Failed to save background settings', { error })
+      })
+    }
+
+    useSettingsStore.subscribe(sync)
+    sync()
+  }
+}
+
+export const backgroundService = new BackgroundService()
+export default backgroundService
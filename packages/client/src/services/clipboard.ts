@@ -0,0 +1,46 @@
+import { invoke } from '@tauri-apps/api/tauri'
+import { logger } from '../utils/logger'
+
+/**
+ * Clipboard access the webview can't do itself: pasted screenshots, copying
+ * messages with their formatting and turning pasted HTML into markdown.
+ * Outside Tauri it degrades to what `navigator.clipboard` offers.
+ */
+class ClipboardService {
+  /** The clipboard image as a PNG file ready to upload, if there is one. */
+  async readImage(): Promise<File | null> {
+    if (!window.__TAURI__) {
+      return null
+    }
+    try {
+      const png = await invoke<string | null>('clipboard_read_image')
+      if (!png) {
+        return null
+      }
+      const bytes = Uint8Array.from(atob(png), (c) => c.charCodeAt(0))
+      return new File([bytes], `pasted-image-${Date.now()}.png`, { type: 'image/png' })
+    } catch (error) {
+      logger.warn('Clipboard', 'Failed to read image', { error })
+      return null
+    }
+  }
+
+  async copyMessage(content: string): Promise<void> {
+    try {
+      if (window.__TAURI__) {
+        await invoke('clipboard_write_message', { content })
+      } else {
+        await navigator.clipboard.writeText(content)
+      }
+    } catch (error) {
+      logger.warn('Clipboard', 'Failed to copy message', { error })
+    }
+  }
+
+  htmlToMarkdown(html: string): Promise<string> {
+    return invoke<string>('clipboard_html_to_markdown', { html })
+  }
+}
+
+export const clipboardService = new ClipboardService()
+export default clipboardService
@@ -0,0 +1,92 @@
+import { invoke } from '@tauri-apps/api/tauri'
+import { listen } from '@tauri-apps/api/event'
+import { navigate, joinInvite } from './navigation'
+import { useAuthStore } from '../stores/auth'
+import { logger } from '../utils/logger'
+
+// Mirrors DeepLink in src-tauri/src/deeplink/link.rs
+export type DeepLink =
+  | { kind: 'invite'; code: string }
+  | { kind: 'channel'; channelId: number }
+  | { kind: 'message'; channelId: number; messageId: number }
+
+/**
+ * Opens commhub:// links routed in by the backend, which parses and
+ * validates them, and builds shareable ones.
+ */
+class DeepLinkService {
+  private initialized = false
+
+  async initialize(): Promise<void> {
+    if (!window.__TAURI__ || this.initialized) {
+      return
+    }
+    this.initialized = true
+
+    listen<DeepLink>('deep-link://open', (event) => this.open(event.payload))
+
+    // The link this window was launched or recreated for
+    try {
+      const pending = await invoke<DeepLink | null>('deeplink_take_pending')
+      if (pending) {
+        this.open(pending)
+      }
+    } catch (error) {
+      logger.warn('DeepLink', 'Failed to read pending link', { error })
+    }
+  }
+
+  /**
+   * Builds a link that opens the given invite, channel or message. Falls
+   * back to formatting it here in the browser, where there's no backend.
+   */
+  async build(link: DeepLink): Promise<string> {
+    if (window.__TAURI__) {
+      return invoke<string>('deeplink_build', { link })
+    }
+    switch (link.kind) {
+      case 'invite':
+        return `commhub://invite/${link.code}`
+      case 'channel':
+        return `commhub://channel/${link.channelId}`
+      case 'message':
+        return `commhub://channel/${link.channelId}/message/${link.messageId}`
+    }
+  }
+
+  async copy(link: DeepLink): Promise<void> {
+    try {
+      await navigator.clipboard.writeText(await this.build(link))
+    } catch (error) {
+      logger.warn('DeepLink', 'Failed to copy link', { link, error })
+    }
+  }
+
+  private open(link: DeepLink): void {
+    // A cold start delivers the link before the session is restored
+    if (!useAuthStore.getState().isAuthenticated) {
+      const unsubscribe = useAuthStore.subscribe((state) => {
+        if (state.isAuthenticated) {
+          unsubscribe()
+          this.open(link)
+        }
+      })
+      return
+    }
+
+    switch (link.kind) {
+      case 'invite':
+        joinInvite(link.code)
+        break
+      case 'channel':
+        navigate({ kind: 'channel', channelId: link.channelId })
+        break
+      case 'message':
+        navigate({ kind: 'channel', channelId: link.channelId }, { messageId: link.messageId })
+        break
+    }
+  }
+}
+
+export const deepLinkService = new DeepLinkService()
+export default deepLinkService
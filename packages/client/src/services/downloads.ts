@@ -0,0 +1,183 @@
+import { invoke, convertFileSrc } from '@tauri-apps/api/tauri'
+import { listen } from '@tauri-apps/api/event'
+import { useSettingsStore } from '../stores/settings'
+import { useDownloadsStore } from '../stores/downloads'
+import { logger } from '../utils/logger'
+
+// Mirrors DownloadProgress in src-tauri/src/downloads.rs
+interface DownloadProgress {
+  id: number
+  received: number
+  total: number | null
+}
+
+// Mirrors DownloadFinished in src-tauri/src/downloads.rs
+type DownloadFinished = { id: number } & (
+  | { status: 'done'; path: string; sha256: string; verified: boolean }
+  | { status: 'failed'; error: string }
+  | { status: 'cancelled' }
+  | { status: 'paused' }
+)
+
+// Mirrors Usage in src-tauri/src/downloads/store.rs
+export interface CacheUsage {
+  used: number
+  limit: number
+}
+
+/**
+ * Saves attachments to disk through the backend, which can pause, resume
+ * and verify them, and serves media that was shown before from the local
+ * attachment cache. The browser build falls back to plain links.
+ */
+class DownloadService {
+  private initialized = false
+  private urls = new Map<number, string>()
+  // Cached copies, so re-rendering a message doesn't ask again
+  private resolved = new Map<string, string>()
+  private lastLimit = 0
+
+  initialize(): void {
+    if (!window.__TAURI__ || this.initialized) {
+      return
+    }
+    this.initialized = true
+
+    listen<DownloadProgress>('download://progress', (event) => {
+      const { id, received, total } = event.payload
+      const url = this.urls.get(id)
+      if (url) {
+        useDownloadsStore.getState().updateDownload(url, { received, total })
+      }
+    })
+    listen<DownloadFinished>('download://finished', (event) => this.finish(event.payload))
+
+    const sync = () => {
+      const limit = useSettingsStore.getState().attachmentCacheMb * 1024 * 1024
+      if (limit === this.lastLimit) {
+        return
+      }
+      this.lastLimit = limit
+      invoke('attachment_cache_set_limit', { limit }).catch((error) => {
+        logger.warn('Downloads', 'Failed to set attachment cache limit', { error })
+      })
+    }
+
+    useSettingsStore.subscribe(sync)
+    sync()
+  }
+
+  /**
+   * Asks where to save an attachment and starts downloading it there. The
+   * backend opens the dialog and checks the file against `sha256`, the
+   * attachment's hash, when there is one. Returns false if the dialog was
+   * dismissed.
+   */
+  async save(url: string, filename: string, sha256?: string | null): Promise<boolean> {
+    const id = await invoke<number | null>('download_save', { url, filename, sha256 })
+    if (id === null) {
+      return false
+    }
+    this.urls.set(id, url)
+    useDownloadsStore
+      .getState()
+      .setDownload(url, { id, received: 0, total: null, status: 'downloading' })
+    return true
+  }
+
+  pause(url: string): Promise<void> {
+    return this.withId(url, (id) => invoke('download_pause', { id }))
+  }
+
+  resume(url: string): Promise<void> {
+    return this.withId(url, async (id) => {
+      useDownloadsStore.getState().updateDownload(url, { status: 'downloading', error: undefined })
+      await invoke('download_resume', { id })
+    })
+  }
+
+  cancel(url: string): Promise<void> {
+    return this.withId(url, (id) => invoke('download_cancel', { id }))
+  }
+
+  /** Forgets a finished download so its card goes back to the download button. */
+  dismiss(url: string): void {
+    const download = useDownloadsStore.getState().downloads[url]
+    if (download) {
+      this.urls.delete(download.id)
+      useDownloadsStore.getState().removeDownload(url)
+    }
+  }
+
+  /**
+   * Where the webview should load an attachment from: the cached copy if
+   * there is one, else the remote URL, which is then cached for next time.
+   */
+  async resolve(url: string, sha256?: string | null): Promise<string> {
+    if (!window.__TAURI__) {
+      return url
+    }
+    const known = this.resolved.get(url)
+    if (known) {
+      return known
+    }
+    try {
+      const path = await invoke<string | null>('attachment_cached', { url })
+      if (path) {
+        const src = convertFileSrc(path)
+        this.resolved.set(url, src)
+        return src
+      }
+      await invoke('attachment_cache', { url, sha256 })
+    } catch (error) {
+      logger.warn('Downloads', 'Attachment cache unavailable', { url, error })
+    }
+    return url
+  }
+
+  cacheUsage(): Promise<CacheUsage> {
+    return invoke<CacheUsage>('attachment_cache_usage')
+  }
+
+  async clearCache(): Promise<void> {
+    await invoke('attachment_cache_clear')
+    this.resolved.clear()
+  }
+
+  private async withId(url: string, action: (id: number) => Promise<unknown>): Promise<void> {
+    const download = useDownloadsStore.getState().downloads[url]
+    if (download) {
+      await action(download.id)
+    }
+  }
+
+  private finish(finished: DownloadFinished): void {
+    const url = this.urls.get(finished.id)
+    if (!url) {
+      return
+    }
+    const store = useDownloadsStore.getState()
+    switch (finished.status) {
+      case 'done':
+        logger.info('Downloads', 'Saved attachment', {
+          path: finished.path,
+          verified: finished.verified,
+        })
+        store.updateDownload(url, { status: 'done', verified: finished.verified })
+        break
+      case 'failed':
+        store.updateDownload(url, { status: 'failed', error: finished.error })
+        break
+      case 'paused':
+        store.updateDownload(url, { status: 'paused' })
+        break
+      case 'cancelled':
+        this.urls.delete(finished.id)
+        store.removeDownload(url)
+        break
+    }
+  }
+}
+
+export const downloadService = new DownloadService()
+export default downloadService
@@ -0,0 +1,52 @@
+import { invoke } from '@tauri-apps/api/tauri'
+import { listen, UnlistenFn } from '@tauri-apps/api/event'
+
+// Mirrors DroppedFile in src-tauri/src/file_drop.rs
+export interface DroppedFile {
+  id: number
+  name: string
+  size: number
+  mimeType: string
+  thumbnail: string | null
+  problem: string | null
+}
+
+interface DropHandlers {
+  onHover: (count: number) => void
+  onCancel: () => void
+  onPreview: (files: DroppedFile[]) => void
+}
+
+/**
+ * Files dropped onto the window or picked with the attach button. The
+ * backend validates them and sends a preview; accepted ones are uploaded by
+ * id with `uploadService`.
+ */
+class FileDropService {
+  /** Subscribes to this window's drops. Resolves to an unsubscribe function. */
+  async subscribe({ onHover, onCancel, onPreview }: DropHandlers): Promise<UnlistenFn> {
+    if (!window.__TAURI__) {
+      return () => {}
+    }
+    const unlisten = await Promise.all([
+      listen<{ count: number }>('file-drop://hover', (event) => onHover(event.payload.count)),
+      listen('file-drop://cancel', () => onCancel()),
+      listen<{ files: DroppedFile[] }>('file-drop://preview', (event) =>
+        onPreview(event.payload.files)
+      ),
+    ])
+    return () => unlisten.forEach((fn) => fn())
+  }
+
+  /** Opens the file picker. Empty if it was dismissed. */
+  pick(): Promise<DroppedFile[]> {
+    return invoke<DroppedFile[]>('file_drop_pick')
+  }
+
+  discard(files: DroppedFile[]): Promise<void> {
+    return invoke('file_drop_discard', { ids: files.map((file) => file.id) })
+  }
+}
+
+export const fileDropService = new FileDropService()
+export default fileDropService
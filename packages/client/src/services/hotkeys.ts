@@ -0,0 +1,112 @@
+import { invoke } from '@tauri-apps/api/tauri'
+import { listen } from '@tauri-apps/api/event'
+import { useVoiceStore } from '../stores/voice'
+import { useVoiceSettingsStore } from '../stores/voice-settings'
+import { voiceManager } from './voice-manager'
+import { soundManager } from './sound-manager'
+import { webrtcService } from './webrtc'
+import { logger } from '../utils/logger'
+
+// Mirrors HotkeyAction and HotkeyBindings in src-tauri/src/hotkeys.rs
+// toggleOverlayClickThrough is handled by the backend and never emitted
+export type HotkeyAction =
+  | 'pushToTalk'
+  | 'toggleMute'
+  | 'toggleDeafen'
+  | 'disconnect'
+  | 'toggleOverlayClickThrough'
+
+export type HotkeyBindings = Partial<Record<HotkeyAction, string | null>>
+
+interface HotkeyEvent {
+  action: HotkeyAction
+  pressed: boolean
+}
+
+/**
+ * Applies the global voice shortcuts registered by the backend. They fire
+ * while CommHub is in the background, so push-to-talk gates the mic track
+ * itself instead of relying on window key events.
+ */
+class HotkeyService {
+  private initialized = false
+  private bindings: HotkeyBindings = {}
+
+  async initialize(): Promise<void> {
+    if (!window.__TAURI__ || this.initialized) {
+      return
+    }
+    this.initialized = true
+
+    listen<HotkeyEvent>('hotkey://action', (event) => this.handle(event.payload))
+
+    // Start a push-to-talk session with the mic closed until the key is held
+    useVoiceStore.subscribe((state, previous) => {
+      if (state.localStream && state.localStream !== previous.localStream && this.pushToTalk) {
+        state.setPushToTalkActive(false)
+      }
+    })
+
+    try {
+      this.bindings = await invoke<HotkeyBindings>('hotkeys_get')
+    } catch (error) {
+      logger.warn('Hotkeys', 'Failed to load global shortcuts', { error })
+    }
+  }
+
+  getBindings(): HotkeyBindings {
+    return { ...this.bindings }
+  }
+
+  /**
+   * Registers and saves new bindings. Rejects with a readable message when
+   * two actions share a shortcut or another app already owns one.
+   */
+  async setBindings(bindings: HotkeyBindings): Promise<void> {
+    await invoke('hotkeys_set', { bindings })
+    this.bindings = { ...bindings }
+  }
+
+  private get pushToTalk(): boolean {
+    return (
+      !!this.bindings.pushToTalk &&
+      useVoiceSettingsStore.getState().settings.detection.mode === 'push_to_talk'
+    )
+  }
+
+  private handle({ action, pressed }: HotkeyEvent): void {
+    const voice = useVoiceStore.getState()
+    if (voice.connectedChannelId === null) {
+      return
+    }
+
+    switch (action) {
+      case 'pushToTalk':
+        if (this.pushToTalk) {
+          voice.setPushToTalkActive(pressed)
+          webrtcService.getSpeakingDetector()?.setPushToTalkPressed(pressed)
+        }
+        break
+      case 'toggleMute':
+        if (pressed) {
+          voiceManager.toggleMute()
+          soundManager.playMuteToggle()
+        }
+        break
+      case 'toggleDeafen':
+        if (pressed) {
+          voiceManager.toggleDeafen()
+          soundManager.playDeafenToggle()
+        }
+        break
+      case 'disconnect':
+        if (pressed) {
+          voiceManager.leaveVoiceChannel()
+        }
+        break
+    }
+  }
+}
+
+export const hotkeyService = new HotkeyService()
+export default hotkeyService
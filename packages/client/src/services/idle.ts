@@ -0,0 +1,97 @@
+import { invoke } from '@tauri-apps/api/tauri'
+import { listen } from '@tauri-apps/api/event'
+import { useSettingsStore } from '../stores/settings'
+import { useStatusStore, UserStatus } from '../stores/status'
+import { useVoiceStore } from '../stores/voice'
+import { streamRelayService } from './popout-stream'
+import { logger } from '../utils/logger'
+
+// Mirrors IdleChanged in src-tauri/src/idle/monitor.rs
+interface IdleChanged {
+  idle: boolean
+  idleSecs: number
+  status: UserStatus | null
+}
+
+// Videos don't say when they start or stop, so playback is checked this often
+const WATCHING_INTERVAL = 2000
+
+/**
+ * Applies the backend's system-wide idle detection to the user's status and
+ * keeps its thresholds in sync with settings. The backend decides what to
+ * restore on return, so a status picked while away is left alone. It's told
+ * while the user is watching something, which holds off going idle.
+ */
+class IdleService {
+  private initialized = false
+  private last = ''
+  private watching = false
+
+  initialize(): void {
+    if (!window.__TAURI__ || this.initialized) {
+      return
+    }
+    this.initialized = true
+
+    listen<IdleChanged>('idle-changed', (event) => this.handle(event.payload))
+
+    const sync = () => {
+      const { autoIdle, idleAfterMinutes } = useSettingsStore.getState()
+      const settings = { enabled: autoIdle, idleAfterSecs: idleAfterMinutes * 60 }
+      const key = JSON.stringify(settings)
+      if (key === this.last) {
+        return
+      }
+      this.last = key
+      invoke('idle_set_settings', { settings }).catch((error) => {
+        logger.warn('Idle', 'Failed to save idle settings', { error })
+      })
+    }
+
+    useSettingsStore.subscribe(sync)
+    sync()
+
+    const report = () => {
+      const watching = this.isWatching()
+      if (watching === this.watching) {
+        return
+      }
+      this.watching = watching
+      invoke('idle_set_watching', { watching }).catch((error) => {
+        logger.warn('Idle', 'Failed to report watching', { error })
+      })
+    }
+
+    useVoiceStore.subscribe(report)
+    window.setInterval(report, WATCHING_INTERVAL)
+    report()
+  }
+
+  private handle({ idle, idleSecs, status }: IdleChanged): void {
+    logger.info('Idle', idle ? 'User went idle' : 'User returned', { idleSecs, status })
+    if (!status) {
+      return
+    }
+    useStatusStore.getState().updateStatus(status)
+  }
+
+  /**
+   * Sitting in a voice channel, or watching a stream, screen share or video
+   * without touching anything still counts as present.
+   */
+  private isWatching(): boolean {
+    const voice = useVoiceStore.getState()
+    if (voice.connectedChannelId !== null || voice.focusedStreamUserId !== null) {
+      return true
+    }
+    if (streamRelayService.isRelaying()) {
+      return true
+    }
+    return Array.from(document.querySelectorAll('video')).some(
+      (video) => !video.paused && !video.ended
+    )
+  }
+}
+
+export const idleService = new IdleService()
+export default idleService
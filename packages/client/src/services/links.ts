@@ -0,0 +1,95 @@
+import { invoke } from '@tauri-apps/api/tauri'
+import { useLinksStore } from '../stores/links'
+import { logger } from '../utils/logger'
+
+// Mirrors Risk in src-tauri/src/links/assess.rs
+export type LinkRisk =
+  | { kind: 'mixedScript'; domain: string }
+  | { kind: 'confusable'; domain: string; resembles: string }
+  | { kind: 'address'; domain: string }
+  | { kind: 'userInfo'; text: string }
+  | { kind: 'shortened' }
+
+// Mirrors Assessment in src-tauri/src/links.rs
+export interface LinkAssessment {
+  url: string
+  scheme: string
+  domain: string | null
+  displayDomain: string | null
+  destination: string | null
+  trustDomain: string | null
+  risks: LinkRisk[]
+}
+
+// Mirrors OpenOutcome in src-tauri/src/links.rs
+type OpenOutcome = { status: 'opened' } | { status: 'confirm'; assessment: LinkAssessment }
+
+/**
+ * Opens links outside the app through the backend, which only opens web and
+ * mail links and holds back ones whose domain could mislead until the user
+ * confirms them. Every link clicked in the window comes through here rather
+ * than the webview's own handling.
+ */
+class LinkService {
+  private initialized = false
+
+  initialize(): void {
+    if (!window.__TAURI__ || this.initialized) {
+      return
+    }
+    this.initialized = true
+
+    // Capturing, so this runs before Tauri's own listener opens the link
+    document.addEventListener(
+      'click',
+      (event) => {
+        const anchor = (event.target as Element | null)?.closest?.('a')
+        if (!anchor || !anchor.href || event.defaultPrevented) {
+          return
+        }
+        const url = new URL(anchor.href)
+        const external =
+          url.protocol === 'mailto:' ||
+          ((url.protocol === 'http:' || url.protocol === 'https:') &&
+            url.origin !== window.location.origin)
+        if (!external) {
+          return
+        }
+        event.preventDefault()
+        event.stopPropagation()
+        this.open(url.href)
+      },
+      true
+    )
+  }
+
+  async open(url: string, confirmed = false): Promise<void> {
+    if (!window.__TAURI__) {
+      window.open(url, '_blank', 'noopener,noreferrer')
+      return
+    }
+    try {
+      const outcome = await invoke<OpenOutcome>('open_external', { url, confirmed })
+      if (outcome.status === 'confirm') {
+        useLinksStore.getState().setPending(outcome.assessment)
+      }
+    } catch (error) {
+      logger.warn('Links', 'Failed to open link', { url, error })
+    }
+  }
+
+  trusted(): Promise<string[]> {
+    return invoke<string[]>('links_trusted')
+  }
+
+  trust(domain: string): Promise<void> {
+    return invoke('links_trust', { domain })
+  }
+
+  untrust(domain: string): Promise<void> {
+    return invoke('links_untrust', { domain })
+  }
+}
+
+export const linkService = new LinkService()
+export default linkService
@@ -0,0 +1,118 @@
+import { invoke } from '@tauri-apps/api/tauri'
+import { listen } from '@tauri-apps/api/event'
+import { useSettingsStore } from '../stores/settings'
+import { useDirectMessagesStore } from '../stores/directMessages'
+import { useMentionsStore } from '../stores/mentions'
+import { voiceManager } from './voice-manager'
+import { soundManager } from './sound-manager'
+import { openQuickSwitcher } from './navigation'
+import { logger } from '../utils/logger'
+
+// Mirrors MenuAction in src-tauri/src/menu/keymap.rs. Quit, reload and the
+// folder items are handled by the backend and never emitted.
+export type MenuAction =
+  | 'quickSwitcher'
+  | 'markAllRead'
+  | 'quit'
+  | 'toggleMute'
+  | 'toggleDeafen'
+  | 'zoomIn'
+  | 'zoomOut'
+  | 'zoomReset'
+  | 'reload'
+  | 'openLogs'
+  | 'openKeymap'
+
+// Mirrors KeymapInfo in src-tauri/src/menu.rs
+export interface KeymapInfo {
+  path: string
+  shortcuts: { action: MenuAction; label: string; accelerator: string | null }[]
+  problems: string[]
+}
+
+interface MenuEvent {
+  action: MenuAction
+}
+
+const ZOOM_STEP = 0.1
+const MIN_ZOOM = 0.5
+const MAX_ZOOM = 2
+
+/**
+ * Applies items chosen from the native application menu, or triggered by
+ * their shortcuts from keymap.toml.
+ */
+class MenuService {
+  private initialized = false
+
+  async initialize(): Promise<void> {
+    if (!window.__TAURI__ || this.initialized) {
+      return
+    }
+    this.initialized = true
+
+    listen<MenuEvent>('menu://action', (event) => this.handle(event.payload.action))
+
+    const keymap = await this.getKeymap().catch(() => null)
+    keymap?.problems.forEach((problem) => logger.warn('Menu', problem))
+  }
+
+  getKeymap(): Promise<KeymapInfo> {
+    return invoke<KeymapInfo>('menu_keymap')
+  }
+
+  openKeymap(): Promise<void> {
+    return invoke('menu_open_keymap')
+  }
+
+  private handle(action: MenuAction): void {
+    switch (action) {
+      case 'quickSwitcher':
+        openQuickSwitcher()
+        break
+      case 'markAllRead':
+        this.markAllRead()
+        break
+      case 'toggleMute':
+        voiceManager.toggleMute()
+        soundManager.playMuteToggle()
+        break
+      case 'toggleDeafen':
+        voiceManager.toggleDeafen()
+        soundManager.playDeafenToggle()
+        break
+      case 'zoomIn':
+        this.zoom(useSettingsStore.getState().zoom + ZOOM_STEP)
+        break
+      case 'zoomOut':
+        this.zoom(useSettingsStore.getState().zoom - ZOOM_STEP)
+        break
+      case 'zoomReset':
+        this.zoom(1)
+        break
+    }
+  }
+
+  private zoom(level: number): void {
+    // Round so repeated steps don't drift away from 100%
+    const zoom = Math.round(Math.min(MAX_ZOOM, Math.max(MIN_ZOOM, level)) * 10) / 10
+    useSettingsStore.getState().updateSetting('zoom', zoom)
+  }
+
+  private async markAllRead(): Promise<void> {
+    const { conversations, markConversationAsRead } = useDirectMessagesStore.getState()
+    const { channelMentionCounts, markChannelMentionsAsRead } = useMentionsStore.getState()
+    await Promise.all([
+      ...conversations
+        .filter((conv) => conv.unreadCount > 0)
+        .map((conv) => markConversationAsRead(conv.user.id)),
+      ...Object.entries(channelMentionCounts)
+        .filter(([, count]) => count > 0)
+        .map(([channelId]) => markChannelMentionsAsRead(Number(channelId))),
+    ])
+    logger.info('Menu', 'Marked all conversations as read')
+  }
+}
+
+export const menuService = new MenuService()
+export default menuService
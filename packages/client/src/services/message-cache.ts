@@ -0,0 +1,62 @@
+import { invoke } from '@tauri-apps/api/tauri'
+import type { Message, DirectMessage, User } from './api'
+import { logger } from '../utils/logger'
+
+/**
+ * History kept on disk by the backend, so conversations paint before the
+ * network answers and stay readable offline. Everything fetched over REST
+ * is written through here. Reads come back empty in the browser build, or
+ * before the signed-in account's cache is open.
+ */
+class MessageCacheService {
+  async channelMessages(channelId: number, before?: number, limit?: number): Promise<Message[]> {
+    if (!window.__TAURI__) {
+      return []
+    }
+    return invoke<Message[]>('cache_get_channel_messages', { channelId, before, limit }).catch(
+      (error) => {
+        logger.debug('Cache', 'No cached channel history', { channelId, error })
+        return []
+      }
+    )
+  }
+
+  async conversation(userId: number, before?: number, limit?: number): Promise<DirectMessage[]> {
+    if (!window.__TAURI__) {
+      return []
+    }
+    return invoke<DirectMessage[]>('cache_get_conversation', { userId, before, limit }).catch(
+      (error) => {
+        logger.debug('Cache', 'No cached conversation', { userId, error })
+        return []
+      }
+    )
+  }
+
+  storeChannelMessages(messages: Message[]): void {
+    if (window.__TAURI__ && messages.length > 0) {
+      invoke('cache_store_channel_messages', { messages }).catch((error) => {
+        logger.debug('Cache', 'Failed to cache channel history', { error })
+      })
+    }
+  }
+
+  storeDirectMessages(messages: DirectMessage[]): void {
+    if (window.__TAURI__ && messages.length > 0) {
+      invoke('cache_store_direct_messages', { messages }).catch((error) => {
+        logger.debug('Cache', 'Failed to cache conversation', { error })
+      })
+    }
+  }
+
+  storeUsers(users: User[]): void {
+    if (window.__TAURI__ && users.length > 0) {
+      invoke('cache_store_users', { users }).catch((error) => {
+        logger.debug('Cache', 'Failed to cache users', { error })
+      })
+    }
+  }
+}
+
+export const messageCache = new MessageCacheService()
+export default messageCache
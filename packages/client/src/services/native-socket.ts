@@ -0,0 +1,128 @@
+import { invoke } from '@tauri-apps/api/tauri'
+import { listen, UnlistenFn } from '@tauri-apps/api/event'
+import { logger } from '../utils/logger'
+
+type Listener = (...args: any[]) => void
+
+export interface NativeSocketStatus {
+  state: 'disconnected' | 'connecting' | 'connected' | 'reconnecting'
+  url: string | null
+  joinedServers: number[]
+  joinedChannels: number[]
+}
+
+/**
+ * Drop-in for the socket.io-client Socket when running under Tauri.
+ *
+ * The connection itself lives in the Rust backend (src-tauri/src/socket.rs)
+ * so it survives webview reloads. Server events arrive as `socket://<event>`
+ * Tauri events and emits are routed through the `socket_emit` command.
+ */
+export class NativeSocket {
+  connected = false
+  private listeners = new Map<string, Set<Listener>>()
+  private subscriptions = new Map<string, Promise<UnlistenFn>>()
+
+  constructor(
+    private token: string,
+    // Pop-outs ride on the main window's connection without managing it
+    private observeOnly = false
+  ) {
+    // Always track lifecycle so `connected` is right even without listeners
+    this.subscribe('connect')
+    this.subscribe('disconnect')
+  }
+
+  on(event: string, listener: Listener): this {
+    if (!this.listeners.has(event)) {
+      this.listeners.set(event, new Set())
+    }
+    this.listeners.get(event)!.add(listener)
+    this.subscribe(event)
+    return this
+  }
+
+  off(event: string, listener?: Listener): this {
+    if (listener) {
+      this.listeners.get(event)?.delete(listener)
+    } else {
+      this.listeners.delete(event)
+    }
+    return this
+  }
+
+  emit(event: string, ...args: any[]): this {
+    invoke('socket_emit', { event, args }).catch((error) => {
+      logger.warn('NativeSocket', 'Failed to emit event', { event, error })
+    })
+    return this
+  }
+
+  connect(): this {
+    this.attach().catch((error) => {
+      logger.error('NativeSocket', 'Failed to connect', { error })
+      this.dispatch('connect_error', new Error(String(error)))
+    })
+    return this
+  }
+
+  disconnect(): this {
+    if (!this.observeOnly) {
+      invoke('socket_disconnect').catch(() => {})
+    }
+    this.subscriptions.forEach((unlisten) => unlisten.then((fn) => fn()))
+    this.subscriptions.clear()
+    if (this.connected) {
+      this.dispatch('disconnect', 'io client disconnect')
+    }
+    return this
+  }
+
+  private async attach(): Promise<void> {
+    // Don't miss the connect event of a fresh connection
+    await Promise.all(this.subscriptions.values())
+
+    // Attaching with socket_connect re-sends `ready`, which would replay
+    // initial-sync into the main window
+    const status = this.observeOnly
+      ? await invoke<NativeSocketStatus>('socket_status')
+      : await invoke<NativeSocketStatus>('socket_connect', { token: this.token })
+
+    // After a reload the backend is usually already connected; replay the
+    // connect so listeners run their setup as they would on a new socket
+    if (status.state === 'connected' && !this.connected) {
+      this.dispatch('connect')
+    }
+  }
+
+  private subscribe(event: string): void {
+    if (this.subscriptions.has(event)) {
+      return
+    }
+    this.subscriptions.set(
+      event,
+      listen(`socket://${event}`, (e) => this.dispatch(event, e.payload))
+    )
+  }
+
+  private dispatch(event: string, payload?: unknown): void {
+    if (event === 'connect') {
+      this.connected = true
+    } else if (event === 'disconnect') {
+      this.connected = false
+    }
+
+    const listeners = this.listeners.get(event)
+    if (!listeners) {
+      return
+    }
+    // Copy so listeners can unsubscribe themselves
+    ;[...listeners].forEach((listener) => {
+      try {
+        listener(payload)
+      } catch (error) {
+        logger.error('NativeSocket', 'Listener threw', { event, error })
+      }
+    })
+  }
+}
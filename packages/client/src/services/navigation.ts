@@ -0,0 +1,45 @@
+// Mirrors Conversation in src-tauri/src/notifications/coalesce.rs
+export type NavigationTarget =
+  | { kind: 'direct'; userId: number }
+  | { kind: 'channel'; channelId: number }
+
+export interface NavigationRequest {
+  target: NavigationTarget
+  // Put the cursor in the message box once the conversation is open
+  focusInput?: boolean
+  // Scroll to and highlight this message once the channel is open
+  messageId?: number
+}
+
+export const NAVIGATE_EVENT = 'commhub:navigate'
+export const FOCUS_INPUT_EVENT = 'commhub:focus-input'
+export const JUMP_TO_MESSAGE_EVENT = 'commhub:jump-to-message'
+export const JOIN_INVITE_EVENT = 'commhub:join-invite'
+export const QUICK_SWITCHER_EVENT = 'commhub:quick-switcher'
+
+/**
+ * Asks App to open a conversation. Used by sources outside the React tree,
+ * such as native notifications and deep links.
+ */
+export function navigate(
+  target: NavigationTarget,
+  options: { focusInput?: boolean; messageId?: number } = {}
+): void {
+  window.dispatchEvent(
+    new CustomEvent<NavigationRequest>(NAVIGATE_EVENT, { detail: { target, ...options } })
+  )
+}
+
+/**
+ * Asks App to open the join server dialog with an invite code filled in.
+ */
+export function joinInvite(code: string): void {
+  window.dispatchEvent(new CustomEvent<string>(JOIN_INVITE_EVENT, { detail: code }))
+}
+
+/**
+ * Asks App to open the quick switcher.
+ */
+export function openQuickSwitcher(): void {
+  window.dispatchEvent(new Event(QUICK_SWITCHER_EVENT))
+}
@@ -1,13 +1,85 @@
 import { invoke } from '@tauri-apps/api/tauri'
+import { listen } from '@tauri-apps/api/event'
+import { navigate, NavigationTarget } from './navigation'
+import { useDirectMessagesStore } from '../stores/directMessages'
+import { useMentionsStore } from '../stores/mentions'
+import { logger } from '../utils/logger'
+
+// Mirrors NotificationAction in src-tauri/src/notifications.rs
+type NotificationAction =
+  | { type: 'open'; conversation: NavigationTarget }
+  | { type: 'markRead'; conversation: NavigationTarget }
+  | { type: 'reply'; conversation: NavigationTarget }
 
 class NotificationService {
   private audioContext: AudioContext | null = null
+  private initialized = false
+  private viewing = ''
 
   constructor() {
     // Initialize Web Audio API context (don't play anything yet)
     this.initializeAudioContext()
   }
 
+  /**
+   * Handle clicks and actions on native notifications. The backend raises
+   * them for DMs and mentions, grouped per conversation and rate limited
+   */
+  async initialize(): Promise<void> {
+    if (!window.__TAURI__ || this.initialized) {
+      return
+    }
+    this.initialized = true
+
+    listen<NotificationAction>('notification://action', (event) =>
+      this.handleAction(event.payload)
+    )
+
+    // A notification click may have recreated this window
+    try {
+      const pending = await invoke<NotificationAction | null>('notifications_take_pending')
+      if (pending) {
+        this.handleAction(pending)
+      }
+    } catch (error) {
+      logger.warn('Notifications', 'Failed to read pending notification action', { error })
+    }
+  }
+
+  /**
+   * Tell the backend which conversation is on screen so it doesn't notify
+   * about messages the user is already reading
+   */
+  setViewing(conversation: NavigationTarget | null): void {
+    const key = JSON.stringify(conversation)
+    if (!window.__TAURI__ || key === this.viewing) {
+      return
+    }
+    this.viewing = key
+    invoke('notifications_set_viewing', { conversation }).catch((error) => {
+      logger.warn('Notifications', 'Failed to report viewed conversation', { error })
+    })
+  }
+
+  private handleAction(action: NotificationAction): void {
+    const { conversation } = action
+    switch (action.type) {
+      case 'open':
+        navigate(conversation)
+        break
+      case 'reply':
+        navigate(conversation, { focusInput: true })
+        break
+      case 'markRead':
+        if (conversation.kind === 'direct') {
+          useDirectMessagesStore.getState().markConversationAsRead(conversation.userId)
+        } else {
+          useMentionsStore.getState().markChannelMentionsAsRead(conversation.channelId)
+        }
+        break
+    }
+  }
+
   /**
    * Initialize the Web Audio API context
    */
@@ -51,6 +123,14 @@ class NotificationService {
       return
     }
 
+    // The backend shows the notification and requests attention
+    if (window.__TAURI__) {
+      if (settings.sounds) {
+        this.playNotificationSound()
+      }
+      return
+    }
+
     try {
       // Show desktop notification
       if ('Notification' in window && Notification.permission === 'granted') {
@@ -0,0 +1,127 @@
+import { invoke } from '@tauri-apps/api/tauri'
+import { useAuthStore } from '../stores/auth'
+import { useChannelsStore } from '../stores/channels'
+import { useVoiceStore } from '../stores/voice'
+import { logger } from '../utils/logger'
+
+// Mirrors OverlaySettings and Corner in src-tauri/src/overlay.rs
+export type OverlayCorner = 'topLeft' | 'topRight' | 'bottomLeft' | 'bottomRight'
+
+export interface OverlaySettings {
+  enabled: boolean
+  corner: OverlayCorner
+  opacity: number
+  clickThrough: boolean
+}
+
+// Mirrors Participant, Roster and RosterUpdate in src-tauri/src/overlay/roster.rs
+export interface OverlayParticipant {
+  userId: number
+  username: string
+  speaking: boolean
+  muted: boolean
+}
+
+export interface OverlayRoster {
+  channelName: string | null
+  participants: OverlayParticipant[]
+}
+
+export type OverlayUpdate =
+  | ({ kind: 'roster' } & OverlayRoster)
+  | { kind: 'speaking'; userId: number; speaking: boolean }
+  | { kind: 'clear' }
+
+export interface OverlaySnapshot {
+  settings: OverlaySettings
+  roster: OverlayRoster
+}
+
+declare global {
+  interface Window {
+    // Set by the overlay window's initialization script
+    __COMMHUB_OVERLAY__?: boolean
+  }
+}
+
+export const isOverlayWindow = (): boolean => !!window.__COMMHUB_OVERLAY__
+
+/**
+ * Streams the voice call from the main window to the backend's overlay.
+ * Speaking flips as often as the detector fires, so those go out on their
+ * own; anything else resends the whole roster.
+ */
+class OverlayService {
+  private initialized = false
+  private lastRoster = ''
+  private speaking = new Map<number, boolean>()
+
+  initialize(): void {
+    if (!window.__TAURI__ || this.initialized) {
+      return
+    }
+    this.initialized = true
+
+    useVoiceStore.subscribe(() => this.sync())
+    this.sync()
+  }
+
+  async getSettings(): Promise<OverlaySettings> {
+    return invoke<OverlaySettings>('overlay_get_settings')
+  }
+
+  async setSettings(settings: OverlaySettings): Promise<void> {
+    await invoke('overlay_set_settings', { settings })
+  }
+
+  private sync(): void {
+    const { connectedChannelId, connectedUsers, isMuted } = useVoiceStore.getState()
+    if (connectedChannelId === null || connectedUsers.size === 0) {
+      if (this.lastRoster) {
+        this.lastRoster = ''
+        this.speaking.clear()
+        this.send({ kind: 'clear' })
+      }
+      return
+    }
+
+    const localUserId = useAuthStore.getState().user?.id
+    const participants = [...connectedUsers.values()].map((user) => ({
+      userId: user.userId,
+      username: user.username,
+      speaking: user.isSpeaking,
+      // The local entry isn't updated on mute; the store's own flag is
+      muted: user.userId === localUserId ? isMuted : user.isMuted,
+    }))
+    const channelName =
+      useChannelsStore.getState().channels.find((c) => c.id === connectedChannelId)?.name ?? null
+
+    // Compare without speaking so talking doesn't resend everyone
+    const roster = JSON.stringify({
+      channelName,
+      participants: participants.map((p) => [p.userId, p.username, p.muted]),
+    })
+    if (roster !== this.lastRoster) {
+      this.lastRoster = roster
+      this.speaking = new Map(participants.map((p) => [p.userId, p.speaking]))
+      this.send({ kind: 'roster', channelName, participants })
+      return
+    }
+
+    participants.forEach(({ userId, speaking }) => {
+      if (this.speaking.get(userId) !== speaking) {
+        this.speaking.set(userId, speaking)
+        this.send({ kind: 'speaking', userId, speaking })
+      }
+    })
+  }
+
+  private send(update: OverlayUpdate): void {
+    invoke('overlay_update', { update }).catch((error) => {
+      logger.warn('Overlay', 'Failed to update overlay', { kind: update.kind, error })
+    })
+  }
+}
+
+export const overlayService = new OverlayService()
+export default overlayService
@@ -0,0 +1,225 @@
+import { emit, listen, UnlistenFn } from '@tauri-apps/api/event'
+import { useVoiceStore } from '../stores/voice'
+import { useAuthStore } from '../stores/auth'
+import { isPopoutWindow } from './popout'
+import { logger } from '../utils/logger'
+
+const REQUEST_EVENT = 'popout://stream-request'
+const SIGNAL_EVENT = 'popout://stream-signal'
+const ENDED_EVENT = 'popout://stream-ended'
+const CLOSED_EVENT = 'popout://closed'
+
+interface StreamRequest {
+  label: string
+  userId: number
+}
+
+interface StreamSignal {
+  label: string
+  from: 'main' | 'popout'
+  description?: RTCSessionDescriptionInit
+  candidate?: RTCIceCandidateInit
+}
+
+interface Relay {
+  userId: number
+  stream: MediaStream
+  peer: RTCPeerConnection
+}
+
+/**
+ * The video a participant is showing, as rendered in the focused view.
+ */
+function streamFor(userId: number): MediaStream | undefined {
+  const voice = useVoiceStore.getState()
+  if (voice.connectedChannelId === null) {
+    return undefined
+  }
+  if (userId === useAuthStore.getState().user?.id) {
+    if (voice.localScreenShareEnabled && voice.localScreenShareStream) {
+      return voice.localScreenShareStream
+    }
+    return voice.localVideoEnabled ? voice.localVideoStream || undefined : undefined
+  }
+  const user = voice.connectedUsers.get(userId)
+  if (user?.hasScreenShare) {
+    return user.screenShareStream || user.stream
+  }
+  return user?.hasVideo ? user.videoStream || user.stream : undefined
+}
+
+/**
+ * Relays streams from the main window to stream pop-outs.
+ *
+ * MediaStreams can't cross webviews, and the voice connection lives in the
+ * main window, so each pop-out gets a loopback peer connection carrying
+ * just the video; audio keeps playing here. Signalling runs over Tauri
+ * events, and the relay is renegotiated when the participant switches
+ * between camera and screen share.
+ */
+class StreamRelayService {
+  private initialized = false
+  private relays = new Map<string, Relay>()
+
+  initialize(): void {
+    if (!window.__TAURI__ || isPopoutWindow() || this.initialized) {
+      return
+    }
+    this.initialized = true
+
+    listen<StreamRequest>(REQUEST_EVENT, (event) => {
+      this.start(event.payload.label, event.payload.userId)
+    })
+    listen<StreamSignal>(SIGNAL_EVENT, (event) => {
+      if (event.payload.from === 'popout') {
+        this.apply(event.payload)
+      }
+    })
+    listen<string>(CLOSED_EVENT, (event) => this.stop(event.payload))
+
+    useVoiceStore.subscribe(() => this.sync())
+  }
+
+  /**
+   * Whether a pop-out is showing a stream right now.
+   */
+  isRelaying(): boolean {
+    return this.relays.size > 0
+  }
+
+  private async start(label: string, userId: number): Promise<void> {
+    this.stop(label)
+    const stream = streamFor(userId)
+    if (!stream) {
+      emit(ENDED_EVENT, { label })
+      return
+    }
+
+    const peer = new RTCPeerConnection()
+    stream.getVideoTracks().forEach((track) => peer.addTrack(track, stream))
+    peer.onicecandidate = (event) => {
+      if (event.candidate) {
+        this.signal({ label, from: 'main', candidate: event.candidate.toJSON() })
+      }
+    }
+    this.relays.set(label, { userId, stream, peer })
+
+    try {
+      await peer.setLocalDescription(await peer.createOffer())
+      this.signal({ label, from: 'main', description: peer.localDescription!.toJSON() })
+    } catch (error) {
+      logger.error('StreamRelay', 'Failed to offer stream to pop-out', { label, error })
+      this.stop(label)
+    }
+  }
+
+  private async apply(signal: StreamSignal): Promise<void> {
+    const relay = this.relays.get(signal.label)
+    if (!relay) {
+      return
+    }
+    try {
+      if (signal.description) {
+        await relay.peer.setRemoteDescription(signal.description)
+      } else if (signal.candidate) {
+        await relay.peer.addIceCandidate(signal.candidate)
+      }
+    } catch (error) {
+      logger.warn('StreamRelay', 'Failed to apply pop-out signal', { label: signal.label, error })
+    }
+  }
+
+  /**
+   * Follows participants who stop streaming or switch streams.
+   */
+  private sync(): void {
+    this.relays.forEach((relay, label) => {
+      const stream = streamFor(relay.userId)
+      if (stream === relay.stream) {
+        return
+      }
+      if (stream) {
+        this.start(label, relay.userId)
+      } else {
+        this.stop(label)
+        emit(ENDED_EVENT, { label })
+      }
+    })
+  }
+
+  private stop(label: string): void {
+    this.relays.get(label)?.peer.close()
+    this.relays.delete(label)
+  }
+
+  private signal(signal: StreamSignal): void {
+    emit(SIGNAL_EVENT, signal)
+  }
+}
+
+/**
+ * Asks the main window for a participant's video. `onStream` is called
+ * again if the relay is renegotiated; returns a cleanup function.
+ */
+export function receiveStream(
+  label: string,
+  userId: number,
+  handlers: { onStream: (stream: MediaStream) => void; onEnded: () => void }
+): () => void {
+  let peer: RTCPeerConnection | null = null
+  const unlisteners: Promise<UnlistenFn>[] = []
+
+  const signal = (payload: Omit<StreamSignal, 'label' | 'from'>) =>
+    emit(SIGNAL_EVENT, { label, from: 'popout', ...payload })
+
+  const answer = async (offer: RTCSessionDescriptionInit) => {
+    // Each offer starts a fresh relay
+    peer?.close()
+    const current = new RTCPeerConnection()
+    peer = current
+    current.ontrack = (event) => handlers.onStream(event.streams[0])
+    current.onicecandidate = (event) => {
+      if (event.candidate) {
+        signal({ candidate: event.candidate.toJSON() })
+      }
+    }
+    await current.setRemoteDescription(offer)
+    await current.setLocalDescription(await current.createAnswer())
+    signal({ description: current.localDescription!.toJSON() })
+  }
+
+  unlisteners.push(
+    listen<StreamSignal>(SIGNAL_EVENT, (event) => {
+      const payload = event.payload
+      if (payload.label !== label || payload.from !== 'main') {
+        return
+      }
+      const apply = payload.description
+        ? answer(payload.description)
+        : payload.candidate && peer
+          ? peer.addIceCandidate(payload.candidate)
+          : Promise.resolve()
+      apply.catch((error) => logger.warn('StreamRelay', 'Failed to apply relay signal', { error }))
+    })
+  )
+  unlisteners.push(
+    listen<{ label: string }>(ENDED_EVENT, (event) => {
+      if (event.payload.label === label) {
+        peer?.close()
+        peer = null
+        handlers.onEnded()
+      }
+    })
+  )
+
+  // Ask only once we're listening for the answer
+  Promise.all(unlisteners).then(() => emit(REQUEST_EVENT, { label, userId }))
+
+  return () => {
+    peer?.close()
+    unlisteners.forEach((unlisten) => unlisten.then((fn) => fn()))
+  }
+}
+
+export const streamRelayService = new StreamRelayService()
+export default streamRelayService
@@ -0,0 +1,98 @@
+import { invoke } from '@tauri-apps/api/tauri'
+import type { NavigationTarget } from './navigation'
+import { logger } from '../utils/logger'
+
+// Mirrors PopoutTarget in src-tauri/src/popout/target.rs
+export type PopoutTarget =
+  | { kind: 'direct'; userId: number }
+  | { kind: 'channel'; channelId: number }
+  | { kind: 'stream'; channelId: number; userId: number }
+
+export interface PopoutContext {
+  label: string
+  target: PopoutTarget
+  alwaysOnTop: boolean
+}
+
+declare global {
+  interface Window {
+    // Set by the backend before a pop-out's scripts run
+    __COMMHUB_POPOUT__?: PopoutContext
+  }
+}
+
+/**
+ * This window's label and what it was popped out for, or null in the main
+ * window.
+ */
+export function getPopout(): PopoutContext | null {
+  return window.__COMMHUB_POPOUT__ ?? null
+}
+
+export function isPopoutWindow(): boolean {
+  return getPopout() !== null
+}
+
+/**
+ * Opens DMs, channels and streams in their own windows. The backend owns
+ * the windows and only routes socket events for a pop-out's conversation
+ * to it; see src-tauri/src/popout.rs.
+ */
+class PopoutService {
+  get isAvailable(): boolean {
+    return !!window.__TAURI__
+  }
+
+  async open(target: PopoutTarget, title: string, alwaysOnTop = false): Promise<void> {
+    if (!this.isAvailable) {
+      return
+    }
+    try {
+      await invoke<string>('open_popout', { target, title, alwaysOnTop })
+    } catch (error) {
+      logger.error('Popout', 'Failed to open pop-out', { target, error })
+    }
+  }
+
+  /**
+   * Also routes another conversation's events to this pop-out.
+   */
+  async subscribe(conversation: NavigationTarget): Promise<void> {
+    await invoke('popout_subscribe', { conversation }).catch((error) =>
+      logger.warn('Popout', 'Failed to subscribe', { conversation, error })
+    )
+  }
+
+  async unsubscribe(conversation: NavigationTarget): Promise<void> {
+    await invoke('popout_unsubscribe', { conversation }).catch((error) =>
+      logger.warn('Popout', 'Failed to unsubscribe', { conversation, error })
+    )
+  }
+
+  async setAlwaysOnTop(enabled: boolean): Promise<void> {
+    await invoke('popout_set_always_on_top', { enabled }).catch((error) =>
+      logger.warn('Popout', 'Failed to change always on top', { error })
+    )
+  }
+
+  /**
+   * Closes this pop-out.
+   */
+  async close(): Promise<void> {
+    await invoke('popout_close').catch((error) =>
+      logger.warn('Popout', 'Failed to close pop-out', { error })
+    )
+  }
+
+  async closeAll(): Promise<void> {
+    if (!this.isAvailable) {
+      return
+    }
+    await invoke('popout_close_all').catch((error) =>
+      logger.warn('Popout', 'Failed to close pop-outs', { error })
+    )
+  }
+}
+
+export const popoutService = new PopoutService()
+export default popoutService
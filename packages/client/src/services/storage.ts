@@ -1,78 +1,43 @@
-import { readTextFile, writeTextFile, exists, createDir, removeFile } from '@tauri-apps/api/fs'
-import { appConfigDir } from '@tauri-apps/api/path'
+import { invoke } from '@tauri-apps/api/tauri'
 import { logger } from '../utils/logger'
 
+interface VaultRecord {
+  token: string
+  user: any
+}
+
+// Where builds before the vault kept the token, in plaintext
+const LEGACY_TOKEN_KEY = 'auth_token'
+const LEGACY_USER_KEY = 'user'
+
 /**
- * Persistent storage service using Tauri's filesystem API
- * This ensures data persists across app updates, unlike localStorage
+ * Persistent storage service backed by the native credential vault
+ * (OS secret service, or an encrypted file when none is available).
+ * This ensures data persists across app updates, unlike localStorage.
+ * Under Tauri nothing is written to localStorage; the browser build has
+ * nowhere else to keep it.
  */
 class StorageService {
-  private configDir: string | null = null
-  private authFile = 'auth.json'
-
-  /**
-   * Initialize storage directory
-   */
-  private async ensureConfigDir(): Promise<string> {
-    if (this.configDir) {
-      return this.configDir
-    }
-
-    try {
-      const configDirPath = await appConfigDir()
-      const commhubDir = `${configDirPath}commhub`
-
-      // Check if directory exists, create if not
-      const dirExists = await exists(commhubDir)
-      if (!dirExists) {
-        await createDir(commhubDir, { recursive: true })
-        logger.info('Storage', 'Created CommHub config directory', { path: commhubDir })
-      }
-
-      this.configDir = commhubDir
-      return commhubDir
-    } catch (error) {
-      logger.error('Storage', 'Failed to initialize config directory', { error })
-      // Fallback to localStorage if Tauri API fails
-      throw error
-    }
-  }
-
-  /**
-   * Get full path to auth file
-   */
-  private async getAuthFilePath(): Promise<string> {
-    const dir = await this.ensureConfigDir()
-    return `${dir}/${this.authFile}`
-  }
-
   /**
-   * Save authentication data
+   * Save authentication data. Without `persist` the sign-in lasts until the
+   * app exits.
    */
-  async saveAuth(token: string, user: any): Promise<void> {
+  async saveAuth(token: string, user: any, persist: boolean = true): Promise<void> {
     // Check if we're in Tauri environment
     if (!window.__TAURI__) {
       // Not in Tauri, use localStorage only
-      localStorage.setItem('auth_token', token)
-      localStorage.setItem('user', JSON.stringify(user))
+      localStorage.setItem(LEGACY_TOKEN_KEY, token)
+      localStorage.setItem(LEGACY_USER_KEY, JSON.stringify(user))
       logger.info('Storage', 'Saved auth data to localStorage (not in Tauri environment)')
       return
     }
 
     try {
-      const filePath = await this.getAuthFilePath()
-      const data = { token, user, timestamp: Date.now() }
-      await writeTextFile(filePath, JSON.stringify(data))
-      logger.info('Storage', 'Saved auth data', { filePath })
-
-      // Also save to localStorage as backup
-      localStorage.setItem('auth_token', token)
-      localStorage.setItem('user', JSON.stringify(user))
+      const backend = await invoke<string | null>('vault_save', { token, user, persist })
+      logger.info('Storage', 'Saved auth data', { backend })
     } catch (error) {
       logger.error('Storage', 'Failed to save auth data to persistent storage', { error })
-      // Fallback to localStorage
-      localStorage.setItem('auth_token', token)
-      localStorage.setItem('user', JSON.stringify(user))
+      throw error
     }
   }
 
@@ -83,8 +48,8 @@ class StorageService {
     // Check if we're in Tauri environment
     if (!window.__TAURI__) {
       // Not in Tauri, use localStorage only
-      const token = localStorage.getItem('auth_token')
-      const userStr = localStorage.getItem('user')
+      const token = localStorage.getItem(LEGACY_TOKEN_KEY)
+      const userStr = localStorage.getItem(LEGACY_USER_KEY)
       return {
         token,
         user: userStr ? JSON.parse(userStr) : null,
@@ -92,43 +57,18 @@ class StorageService {
     }
 
     try {
-      const filePath = await this.getAuthFilePath()
-      const fileExists = await exists(filePath)
-
-      if (!fileExists) {
-        // Try to migrate from localStorage if it exists
-        const oldToken = localStorage.getItem('auth_token')
-        const oldUser = localStorage.getItem('user')
-        if (oldToken && oldUser) {
-          logger.info('Storage', 'Migrating auth data from localStorage')
-          await this.saveAuth(oldToken, JSON.parse(oldUser))
-          return { token: oldToken, user: JSON.parse(oldUser) }
-        }
-        return { token: null, user: null }
-      }
-
-      const content = await readTextFile(filePath)
-      const data = JSON.parse(content)
-      logger.info('Storage', 'Loaded auth data', { hasToken: !!data.token, hasUser: !!data.user })
+      const data = await invoke<VaultRecord | null>('vault_load')
 
-      // Also sync to localStorage as backup
-      if (data.token) {
-        localStorage.setItem('auth_token', data.token)
-      }
-      if (data.user) {
-        localStorage.setItem('user', JSON.stringify(data.user))
+      if (!data) {
+        return await this.migrateLegacy()
       }
 
+      // Older builds mirrored the vault into localStorage
+      this.clearLegacy()
       return { token: data.token || null, user: data.user || null }
     } catch (error) {
       logger.error('Storage', 'Failed to load auth data from persistent storage', { error })
-      // Fallback to localStorage
-      const token = localStorage.getItem('auth_token')
-      const userStr = localStorage.getItem('user')
-      return {
-        token,
-        user: userStr ? JSON.parse(userStr) : null,
-      }
+      return { token: null, user: null }
     }
   }
 
@@ -139,26 +79,46 @@ class StorageService {
     // Check if we're in Tauri environment
     if (!window.__TAURI__) {
       // Not in Tauri, just clear localStorage
-      localStorage.removeItem('auth_token')
-      localStorage.removeItem('user')
+      this.clearLegacy()
       logger.info('Storage', 'Removed auth data from localStorage (not in Tauri environment)')
       return
     }
 
     try {
-      const filePath = await this.getAuthFilePath()
-      const fileExists = await exists(filePath)
-      if (fileExists) {
-        await removeFile(filePath)
-        logger.info('Storage', 'Removed auth data', { filePath })
-      }
+      await invoke('vault_clear')
+      logger.info('Storage', 'Removed auth data')
     } catch (error) {
       logger.error('Storage', 'Failed to remove auth data', { error })
     }
+    this.clearLegacy()
+  }
+
+  /**
+   * Moves a token left in localStorage by an older build into the vault
+   */
+  private async migrateLegacy(): Promise<{ token: string | null; user: any | null }> {
+    const oldToken = localStorage.getItem(LEGACY_TOKEN_KEY)
+    const oldUser = localStorage.getItem(LEGACY_USER_KEY)
+    if (!oldToken || !oldUser) {
+      this.clearLegacy()
+      return { token: null, user: null }
+    }
+
+    logger.info('Storage', 'Migrating auth data from localStorage')
+    const user = JSON.parse(oldUser)
+    try {
+      await this.saveAuth(oldToken, user)
+    } catch {
+      // Signed in for this launch only; the plaintext copy goes either way
+      await invoke('vault_save', { token: oldToken, user, persist: false }).catch(() => {})
+    }
+    this.clearLegacy()
+    return { token: oldToken, user }
+  }
 
-    // Also clear localStorage as fallback
-    localStorage.removeItem('auth_token')
-    localStorage.removeItem('user')
+  private clearLegacy(): void {
+    localStorage.removeItem(LEGACY_TOKEN_KEY)
+    localStorage.removeItem(LEGACY_USER_KEY)
   }
 }
 
@@ -0,0 +1,100 @@
+import { invoke } from '@tauri-apps/api/tauri'
+import { listen } from '@tauri-apps/api/event'
+import { useAuthStore } from '../stores/auth'
+import { useStatusStore, UserStatus } from '../stores/status'
+import { useDirectMessagesStore } from '../stores/directMessages'
+import { useMentionsStore } from '../stores/mentions'
+import { useVoiceStore } from '../stores/voice'
+import { voiceManager } from './voice-manager'
+import { soundManager } from './sound-manager'
+import { logger } from '../utils/logger'
+
+// Mirrors TrayAction in src-tauri/src/tray.rs
+export type TrayAction =
+  | { type: 'setStatus'; status: UserStatus }
+  | { type: 'toggleMute' }
+  | { type: 'toggleDeafen' }
+
+/**
+ * Bridges the native system tray and the stores: applies tray clicks and
+ * pushes status, unread and voice state back so the tray reflects them.
+ */
+class TrayService {
+  private initialized = false
+  private last: Record<string, string> = {}
+
+  initialize(): void {
+    if (!window.__TAURI__ || this.initialized) {
+      return
+    }
+    this.initialized = true
+
+    listen<TrayAction>('tray://action', (event) => this.handleAction(event.payload))
+
+    const syncStatus = () => {
+      const userId = useAuthStore.getState().user?.id
+      const status = userId ? useStatusStore.getState().userStatuses.get(userId) : undefined
+      if (status) {
+        this.push('tray_set_status', { status })
+      }
+    }
+    const syncUnread = () => {
+      const directMessages = useDirectMessagesStore
+        .getState()
+        .conversations.reduce((total, conv) => total + (conv.unreadCount || 0), 0)
+      const mentions = Object.values(useMentionsStore.getState().channelMentionCounts).reduce(
+        (total, count) => total + count,
+        0
+      )
+      this.push('tray_set_unread', { directMessages, mentions })
+    }
+    const syncVoice = () => {
+      const { isMuted, isDeafened } = useVoiceStore.getState()
+      this.push('tray_set_voice', { muted: isMuted, deafened: isDeafened })
+    }
+
+    useAuthStore.subscribe(syncStatus)
+    useStatusStore.subscribe(syncStatus)
+    useDirectMessagesStore.subscribe(syncUnread)
+    useMentionsStore.subscribe(syncUnread)
+    useVoiceStore.subscribe(syncVoice)
+
+    syncStatus()
+    syncUnread()
+    syncVoice()
+  }
+
+  private handleAction(action: TrayAction): void {
+    switch (action.type) {
+      case 'setStatus':
+        useStatusStore.getState().updateStatus(action.status)
+        break
+      case 'toggleMute':
+        voiceManager.toggleMute()
+        soundManager.playMuteToggle()
+        break
+      case 'toggleDeafen':
+        voiceManager.toggleDeafen()
+        soundManager.playDeafenToggle()
+        break
+    }
+  }
+
+  /**
+   * Store subscriptions fire on every change; only call into Rust when the
+   * tray's view of the state actually moved
+   */
+  private push(command: string, args: Record<string, unknown>): void {
+    const key = JSON.stringify(args)
+    if (this.last[command] === key) {
+      return
+    }
+    this.last[command] = key
+    invoke(command, args).catch((error) => {
+      logger.warn('Tray', 'Failed to update tray', { command, error })
+    })
+  }
+}
+
+export const trayService = new TrayService()
+export default trayService
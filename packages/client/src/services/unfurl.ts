@@ -0,0 +1,83 @@
+import { invoke } from '@tauri-apps/api/tauri'
+import { logger } from '../utils/logger'
+
+// Mirrors Image in src-tauri/src/unfurl.rs
+export interface PreviewImage {
+  url: string
+  width: number | null
+  height: number | null
+  alt: string | null
+}
+
+// Mirrors Video in src-tauri/src/unfurl.rs
+export interface PreviewVideo {
+  url: string
+  player: 'embed' | 'file'
+  width: number | null
+  height: number | null
+}
+
+// Mirrors Preview in src-tauri/src/unfurl.rs
+export interface LinkPreview {
+  url: string
+  kind: 'link' | 'image' | 'video'
+  title: string | null
+  description: string | null
+  siteName: string | null
+  image: PreviewImage | null
+  video: PreviewVideo | null
+}
+
+/**
+ * Link previews from the backend, which fetches pages within limits, never
+ * from private addresses, and keeps the results on disk. The browser build
+ * can't read other sites, so it only recognises links to media files.
+ */
+class UnfurlService {
+  // One request per link, however many messages show it
+  private previews = new Map<string, Promise<LinkPreview | null>>()
+
+  preview(url: string): Promise<LinkPreview | null> {
+    let preview = this.previews.get(url)
+    if (!preview) {
+      preview = window.__TAURI__
+        ? invoke<LinkPreview | null>('unfurl_link', { url }).catch((error) => {
+            logger.debug('Unfurl', 'No preview for link', { url, error })
+            return null
+          })
+        : Promise.resolve(mediaPreview(url))
+      this.previews.set(url, preview)
+    }
+    return preview
+  }
+}
+
+function mediaPreview(url: string): LinkPreview | null {
+  let pathname: string
+  try {
+    pathname = new URL(url).pathname
+  } catch {
+    return null
+  }
+  const base = { url, title: null, description: null, siteName: null }
+  if (/\.(jpe?g|png|gif|webp|bmp|svg)$/i.test(pathname)) {
+    return {
+      ...base,
+      kind: 'image',
+      image: { url, width: null, height: null, alt: null },
+      video: null,
+    }
+  }
+  if (/\.(mp4|webm|ogg|mov)$/i.test(pathname)) {
+    return {
+      ...base,
+      kind: 'video',
+      image: null,
+      video: { url, player: 'file', width: null, height: null },
+    }
+  }
+  return null
+}
+
+export const unfurlService = new UnfurlService()
+export default unfurlService
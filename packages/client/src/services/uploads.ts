@@ -0,0 +1,145 @@
+import { invoke } from '@tauri-apps/api/tauri'
+import { listen } from '@tauri-apps/api/event'
+import { apiService } from './api'
+import { useSettingsStore } from '../stores/settings'
+import type { DroppedFile } from './file-drop'
+
+// Mirrors UploadTarget in src-tauri/src/uploads.rs
+export interface UploadTarget {
+  channelId?: number
+  receiverId?: number
+}
+
+export interface UploadedAttachment {
+  url: string
+  filename: string
+  mimeType: string
+  size: number
+}
+
+// Mirrors UploadProgress in src-tauri/src/uploads.rs
+export interface UploadProgress {
+  id: number
+  sent: number
+  total: number
+}
+
+// Mirrors UploadPrepared in src-tauri/src/uploads.rs
+export interface UploadPrepared {
+  id: number
+  name: string
+  size: number
+  width: number
+  height: number
+  thumbnail: string
+  blurhash: string
+}
+
+// Mirrors UploadFinished in src-tauri/src/uploads.rs
+type UploadFinished = { id: number } & (
+  | { status: 'done'; attachment: UploadedAttachment }
+  | { status: 'failed'; error: string }
+  | { status: 'cancelled' }
+)
+
+export interface UploadHandle {
+  id: number
+  /** The attachment, or null if the upload was cancelled. */
+  done: Promise<UploadedAttachment | null>
+}
+
+export interface UploadCallbacks {
+  onProgress?: (progress: UploadProgress) => void
+  // Images are resized and stripped of metadata before they go up
+  onPrepared?: (prepared: UploadPrepared) => void
+}
+
+interface Tracked extends UploadCallbacks {
+  settle: (finished: UploadFinished) => void
+}
+
+/**
+ * Uploads run by the backend from files on disk, with progress, retries
+ * and cancellation. Pasted files and the browser build still go through
+ * `apiService.uploadFile`.
+ */
+class UploadService {
+  private tracked = new Map<number, Tracked>()
+  // Uploads can finish before invoke returns their id
+  private unclaimed = new Map<number, UploadFinished>()
+  private listening: Promise<unknown> | null = null
+
+  private initialize(): Promise<unknown> {
+    this.listening ??= Promise.all([
+      listen<UploadProgress>('upload://progress', (event) =>
+        this.tracked.get(event.payload.id)?.onProgress?.(event.payload)
+      ),
+      listen<UploadPrepared>('upload://prepared', (event) =>
+        this.tracked.get(event.payload.id)?.onPrepared?.(event.payload)
+      ),
+      listen<UploadFinished>('upload://finished', (event) => {
+        const tracked = this.tracked.get(event.payload.id)
+        if (tracked) {
+          tracked.settle(event.payload)
+        } else {
+          this.unclaimed.set(event.payload.id, event.payload)
+        }
+      }),
+    ])
+    return this.listening
+  }
+
+  /** Uploads a dropped or picked file. */
+  uploadDropped(
+    file: DroppedFile,
+    target: UploadTarget,
+    callbacks: UploadCallbacks
+  ): Promise<UploadHandle> {
+    return this.start('upload_dropped', { id: file.id, target }, callbacks)
+  }
+
+  cancel(id: number): Promise<void> {
+    return invoke('upload_cancel', { id })
+  }
+
+  private async start(
+    command: string,
+    args: Record<string, unknown>,
+    callbacks: UploadCallbacks
+  ): Promise<UploadHandle> {
+    await this.initialize()
+    const token = await apiService.getAuthToken()
+    // Mirrors ImageOptions in src-tauri/src/uploads/images.rs; the rest keep their defaults
+    const images = { maxDimension: useSettingsStore.getState().imageMaxDimension }
+    // The backend uploads to the API_URL it was built with, never one passed here
+    const id = await invoke<number>(command, { ...args, token, images })
+
+    const done = new Promise<UploadedAttachment | null>((resolve, reject) => {
+      const settle = (finished: UploadFinished) => {
+        this.tracked.delete(id)
+        switch (finished.status) {
+          case 'done':
+            resolve(finished.attachment)
+            break
+          case 'failed':
+            reject(new Error(finished.error))
+            break
+          case 'cancelled':
+            resolve(null)
+            break
+        }
+      }
+      this.tracked.set(id, { ...callbacks, settle })
+
+      const early = this.unclaimed.get(id)
+      if (early) {
+        this.unclaimed.delete(id)
+        settle(early)
+      }
+    })
+    return { id, done }
+  }
+}
+
+export const uploadService = new UploadService()
+export default uploadService
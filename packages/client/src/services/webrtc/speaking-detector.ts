@@ -103,6 +103,17 @@ export class EnhancedSpeakingDetector {
     this.keyListeners = [keyDownListener, keyUpListener]
   }
 
+  /**
+   * Drive push-to-talk from outside the window, e.g. a global hotkey
+   */
+  setPushToTalkPressed(pressed: boolean): void {
+    if (this.speakingConfig.mode !== 'push_to_talk' || this.pttPressed === pressed) {
+      return
+    }
+    this.pttPressed = pressed
+    this.updateSpeakingState(pressed)
+  }
+
   /**
    * Check if the pressed key matches PTT configuration
    */
@@ -1,5 +1,7 @@
+import { invoke } from '@tauri-apps/api/tauri'
 import { wsService } from './websocket'
 import { apiService } from './api'
+import { isPopoutWindow } from './popout'
 import { useMessagesStore } from '../stores/messages'
 import { useServersStore } from '../stores/servers'
 import { useDirectMessagesStore } from '../stores/directMessages'
@@ -171,11 +173,31 @@ class WebSocketManager {
       // Refresh conversations to update last message and unread count
       useDirectMessagesStore.getState().fetchConversations()
     })
+
+    // Edits and deletions made by either participant
+    wsService.onDirectMessageEdited((data) => {
+      useDirectMessagesStore.getState().updateDirectMessage(data)
+    })
+
+    wsService.onDirectMessageDeleted((data) => {
+      const currentUserId = useAuthStore.getState().user?.id
+      const otherUserId = data.senderId === currentUserId ? data.receiverId : data.senderId
+      useDirectMessagesStore.getState().removeDirectMessage(otherUserId, data.messageId)
+    })
   }
 
   async connect() {
     const token = await apiService.getAuthToken()
     if (token) {
+      // Open this account's local message cache so live events get indexed.
+      // Pop-outs share the one the main window opened
+      const user = await apiService.getUser()
+      if (user && window.__TAURI__ && !isPopoutWindow()) {
+        await invoke('cache_open', { userId: user.id }).catch((error) => {
+          console.warn('[WebSocket] Failed to open message cache:', error)
+        })
+      }
+
       wsService.connect(token)
       const socket = wsService.getSocket()
       if (socket) {
@@ -186,6 +208,10 @@ class WebSocketManager {
         socket.on('connect', () => {
           console.log('[WebSocket] Connected, emitting ready and reattaching listeners')
           this.attachSocketListeners(socket)
+          // The native socket sends ready itself on every (re)connect
+          if (window.__TAURI__) {
+            return
+          }
           // Emit ready on every connection/reconnection to join all rooms
           console.log('[WebSocket] Emitting ready event to server')
           socket.emit('ready')
@@ -203,6 +229,9 @@ class WebSocketManager {
 
   disconnect() {
     wsService.disconnect()
+    if (window.__TAURI__) {
+      invoke('cache_close').catch(() => {})
+    }
   }
 
   async checkAndConnect() {
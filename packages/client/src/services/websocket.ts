@@ -1,8 +1,11 @@
 import { io, Socket } from 'socket.io-client'
+import { invoke } from '@tauri-apps/api/tauri'
 import config from '../config/environment'
 import { logger } from '../utils/logger'
 import { handleError, NetworkError } from '../utils/errors'
 import { voiceSignalingHandler } from './voice/signaling-handler'
+import { NativeSocket } from './native-socket'
+import { isPopoutWindow } from './popout'
 
 // WebSocket URL from environment configuration
 const WS_BASE_URL = config.WS_URL
@@ -33,6 +36,7 @@ export interface WSMessage {
     filename: string
     mimeType: string
     size: number
+    sha256?: string | null
     createdAt: string
   }>
 }
@@ -74,6 +78,12 @@ export interface DirectMessageWS {
   }
 }
 
+export interface DirectMessageDeletedWS {
+  messageId: number
+  senderId: number
+  receiverId: number
+}
+
 class WebSocketService {
   private socket: Socket | null = null
   private reconnectAttempts = 0
@@ -90,6 +100,8 @@ class WebSocketService {
   private userLeftListeners: ((data: UserLeft) => void)[] = []
   private onlineFriendsListeners: ((friends: any[]) => void)[] = []
   private directMessageListeners: ((message: DirectMessageWS) => void)[] = []
+  private directMessageEditedListeners: ((message: DirectMessageWS) => void)[] = []
+  private directMessageDeletedListeners: ((data: DirectMessageDeletedWS) => void)[] = []
   private errorListeners: ((error: any) => void)[] = []
   private statusUpdateListeners: ((data: { userId: number; status: string }) => void)[] = []
   private mentionListeners: ((notification: any) => void)[] = []
@@ -99,26 +111,32 @@ class WebSocketService {
       return
     }
 
-    this.socket = io(WS_BASE_URL, {
-      auth: {
-        token,
-      },
-      query: {
-        clientVersion: CLIENT_VERSION,
-      },
-      transports: ['websocket', 'polling'],
-      // Match server timeout settings for stable connections
-      timeout: 60000, // 60 seconds - match server pingTimeout
-      // Reconnection settings for better resilience
-      reconnection: true,
-      reconnectionAttempts: 10, // Increased from implicit default of Infinity (we manage this separately)
-      reconnectionDelay: 1000, // Start with 1 second
-      reconnectionDelayMax: 5000, // Max 5 seconds between attempts
-      // Enable automatic upgrades from polling to websocket
-      upgrade: true,
-      // Disable autoconnect since we control connection lifecycle
-      autoConnect: false,
-    })
+    if (this.isNative) {
+      // The backend owns the connection and its reconnects, so it outlives
+      // webview reloads. NativeSocket covers the Socket API used in the app.
+      this.socket = new NativeSocket(token, isPopoutWindow()) as unknown as Socket
+    } else {
+      this.socket = io(WS_BASE_URL, {
+        auth: {
+          token,
+        },
+        query: {
+          clientVersion: CLIENT_VERSION,
+        },
+        transports: ['websocket', 'polling'],
+        // Match server timeout settings for stable connections
+        timeout: 60000, // 60 seconds - match server pingTimeout
+        // Reconnection settings for better resilience
+        reconnection: true,
+        reconnectionAttempts: 10, // Increased from implicit default of Infinity (we manage this separately)
+        reconnectionDelay: 1000, // Start with 1 second
+        reconnectionDelayMax: 5000, // Max 5 seconds between attempts
+        // Enable automatic upgrades from polling to websocket
+        upgrade: true,
+        // Disable autoconnect since we control connection lifecycle
+        autoConnect: false,
+      })
+    }
 
     this.socket.on('connect', () => {
       logger.info('WebSocket', 'Connected successfully')
@@ -138,7 +156,9 @@ class WebSocketService {
         // Server or client initiated disconnect, don't reconnect
         return
       }
-      this.attemptReconnect(token)
+      if (!this.isNative) {
+        this.attemptReconnect(token)
+      }
     })
 
     this.socket.on('connect_error', (error) => {
@@ -147,7 +167,9 @@ class WebSocketService {
         error instanceof Error ? error : new NetworkError('Connection error'),
         'WebSocket'
       )
-      this.attemptReconnect(token)
+      if (!this.isNative) {
+        this.attemptReconnect(token)
+      }
     })
 
     // Handle version mismatch
@@ -180,7 +202,10 @@ class WebSocketService {
       try {
         const { default: notificationService } = await import('./notifications')
         await notificationService.playMentionSound()
-        await notificationService.flashTaskbar()
+        // Natively the backend notifies and requests attention itself
+        if (!this.isNative) {
+          await notificationService.flashTaskbar()
+        }
       } catch (error) {
         logger.error('WebSocket', 'Failed to play mention notification', { error })
       }
@@ -219,6 +244,14 @@ class WebSocketService {
       this.directMessageListeners.forEach((listener) => listener(message))
     })
 
+    this.socket.on('direct-message-edited', (message: DirectMessageWS) => {
+      this.directMessageEditedListeners.forEach((listener) => listener(message))
+    })
+
+    this.socket.on('direct-message-deleted', (data: DirectMessageDeletedWS) => {
+      this.directMessageDeletedListeners.forEach((listener) => listener(data))
+    })
+
     this.socket.on('status-update', (data: { userId: number; status: string }) => {
       this.statusUpdateListeners.forEach((listener) => listener(data))
     })
@@ -231,6 +264,10 @@ class WebSocketService {
     this.socket.connect()
   }
 
+  private get isNative(): boolean {
+    return !!window.__TAURI__
+  }
+
   private attemptReconnect(token: string): void {
     if (this.reconnectAttempts >= this.maxReconnectAttempts) {
       logger.error('WebSocket', 'Max reconnection attempts reached', {
@@ -264,6 +301,12 @@ class WebSocketService {
    * Restore connection state after reconnection
    */
   private restoreConnectionState(): void {
+    // The native socket replays its own room joins
+    if (this.isNative) {
+      this.restoreVoiceChannel()
+      return
+    }
+
     // Rejoin server rooms
     this.joinedServers.forEach((serverId) => {
       if (this.socket) {
@@ -280,7 +323,13 @@ class WebSocketService {
       }
     })
 
-    // Rejoin voice channel if we were in one
+    this.restoreVoiceChannel()
+  }
+
+  /**
+   * Rejoin the voice channel we were in before the connection dropped
+   */
+  private restoreVoiceChannel(): void {
     if (this.wasInVoiceChannel && this.voiceChannelId) {
       logger.info('WebSocket', 'Rejoining voice channel', { voiceChannelId: this.voiceChannelId })
       // Import voice manager factory to avoid circular dependency
@@ -367,7 +416,11 @@ class WebSocketService {
     replyToId?: number,
     attachments?: Array<{ url: string; filename: string; mimeType: string; size: number }>
   ): void {
-    if (this.socket) {
+    if (this.isNative) {
+      invoke('socket_send_message', { channelId, content, replyToId, attachments }).catch((error) =>
+        logger.error('WebSocket', 'Failed to send message', { error })
+      )
+    } else if (this.socket) {
       this.socket.emit('send-message', { channelId, content, replyToId, attachments })
     }
   }
@@ -381,7 +434,11 @@ class WebSocketService {
 
   // Status
   notifyStatusChange(userId: number, status: string): void {
-    if (this.socket) {
+    if (this.isNative) {
+      invoke('socket_status_change', { userId, status }).catch((error) =>
+        logger.error('WebSocket', 'Failed to send status change', { error })
+      )
+    } else if (this.socket) {
       this.socket.emit('status-change', { userId, status })
     }
   }
@@ -457,6 +514,26 @@ class WebSocketService {
     }
   }
 
+  onDirectMessageEdited(listener: (message: DirectMessageWS) => void): () => void {
+    this.directMessageEditedListeners.push(listener)
+    return () => {
+      const index = this.directMessageEditedListeners.indexOf(listener)
+      if (index > -1) {
+        this.directMessageEditedListeners.splice(index, 1)
+      }
+    }
+  }
+
+  onDirectMessageDeleted(listener: (data: DirectMessageDeletedWS) => void): () => void {
+    this.directMessageDeletedListeners.push(listener)
+    return () => {
+      const index = this.directMessageDeletedListeners.indexOf(listener)
+      if (index > -1) {
+        this.directMessageDeletedListeners.splice(index, 1)
+      }
+    }
+  }
+
   onStatusUpdate(listener: (data: { userId: number; status: string }) => void): () => void {
     this.statusUpdateListeners.push(listener)
     return () => {
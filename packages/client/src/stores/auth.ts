@@ -1,6 +1,7 @@
 import { create } from 'zustand'
 import { apiService, type User } from '../services/api'
 import { wsManager } from '../services/websocket-manager'
+import { popoutService } from '../services/popout'
 
 interface AuthState {
   user: User | null
@@ -100,6 +101,8 @@ export const useAuthStore = create<AuthState>((set, _get) => ({
     } finally {
       // Disconnect from WebSocket
       wsManager.disconnect()
+      // Pop-outs belong to the session
+      popoutService.closeAll()
 
       await apiService.removeAuthToken()
       set({
@@ -4,6 +4,7 @@ import { useAuthStore } from './auth'
 import { useSettingsStore } from './settings'
 import { useStatusStore } from './status'
 import { notificationService } from '../services/notifications'
+import { messageCache } from '../services/message-cache'
 
 interface DirectMessagesState {
   conversations: Conversation[]
@@ -56,8 +57,19 @@ export const useDirectMessagesStore = create<DirectMessagesState>((set, get) =>
 
   fetchConversation: async (userId: number) => {
     set({ isLoading: true, error: null })
+
+    // Paint what's on disk while the server answers
+    const cached = await messageCache.conversation(userId)
+    if (cached.length > 0 && !get().messages[userId]?.length) {
+      set((state) => ({
+        messages: { ...state.messages, [userId]: cached },
+        isLoading: false,
+      }))
+    }
+
     try {
       const messages = await apiService.getConversation(userId)
+      messageCache.storeDirectMessages(messages)
       set((state) => ({
         messages: {
           ...state.messages,
@@ -66,6 +78,11 @@ export const useDirectMessagesStore = create<DirectMessagesState>((set, get) =>
         isLoading: false,
       }))
     } catch (error: any) {
+      // Offline, the cached conversation stands in
+      if (cached.length > 0 && !error.response) {
+        set({ isLoading: false })
+        return
+      }
       const errorMessage = error.response?.data?.message || 'Failed to fetch conversation'
       set({ isLoading: false, error: errorMessage })
     }
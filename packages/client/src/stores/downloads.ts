@@ -0,0 +1,46 @@
+import { create } from 'zustand'
+
+export type DownloadStatus = 'downloading' | 'paused' | 'done' | 'failed' | 'cancelled'
+
+export interface Download {
+  id: number
+  received: number
+  total: number | null
+  status: DownloadStatus
+  // Set once done: whether the file was checked against a known hash
+  verified?: boolean
+  error?: string
+}
+
+interface DownloadsState {
+  // Keyed by attachment URL, so every copy of an attachment shows its save
+  downloads: Record<string, Download>
+
+  // Actions
+  setDownload: (url: string, download: Download) => void
+  updateDownload: (url: string, update: Partial<Download>) => void
+  removeDownload: (url: string) => void
+}
+
+export const useDownloadsStore = create<DownloadsState>((set) => ({
+  downloads: {},
+
+  setDownload: (url, download) =>
+    set((state) => ({ downloads: { ...state.downloads, [url]: download } })),
+
+  updateDownload: (url, update) =>
+    set((state) => {
+      const download = state.downloads[url]
+      if (!download) {
+        return state
+      }
+      return { downloads: { ...state.downloads, [url]: { ...download, ...update } } }
+    }),
+
+  removeDownload: (url) =>
+    set((state) => {
+      const downloads = { ...state.downloads }
+      delete downloads[url]
+      return { downloads }
+    }),
+}))
@@ -1,6 +1,7 @@
 import { create } from 'zustand'
 import { apiService, type Friend, type FriendRequest } from '../services/api'
 import { wsService } from '../services/websocket'
+import { messageCache } from '../services/message-cache'
 
 interface FriendsState {
   friends: Friend[]
@@ -63,6 +64,7 @@ export const useFriendsStore = create<FriendsState>((set, get) => ({
     try {
       const friends = await apiService.getFriends(userId)
       console.log(`[FriendsStore] API returned ${friends.length} friends`)
+      messageCache.storeUsers(friends)
 
       // Preserve any existing status updates from WebSocket that came before fetch
       const currentFriends = get().friends
@@ -0,0 +1,16 @@
+import { create } from 'zustand'
+import type { LinkAssessment } from '../services/links'
+
+interface LinksState {
+  // A link the backend wouldn't open without the user confirming it
+  pending: LinkAssessment | null
+
+  // Actions
+  setPending: (assessment: LinkAssessment | null) => void
+}
+
+export const useLinksStore = create<LinksState>((set) => ({
+  pending: null,
+
+  setPending: (assessment) => set({ pending: assessment }),
+}))
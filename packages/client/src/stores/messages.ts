@@ -1,6 +1,7 @@
 import { create } from 'zustand'
 import { apiService, type Message } from '../services/api'
 import { wsService, type WSMessage } from '../services/websocket'
+import { messageCache } from '../services/message-cache'
 import { useAuthStore } from './auth'
 
 interface MessagesState {
@@ -37,8 +38,20 @@ export const useMessagesStore = create<MessagesState>((set, get) => ({
 
   fetchMessages: async (channelId: number) => {
     set({ isLoading: true, error: null })
+
+    // Paint what's on disk while the server answers
+    const cached = await messageCache.channelMessages(channelId, undefined, 100)
+    if (cached.length > 0 && !get().messages[channelId]?.length) {
+      set((state) => ({
+        messages: { ...state.messages, [channelId]: cached },
+        hasMoreMessages: { ...state.hasMoreMessages, [channelId]: cached.length >= 100 },
+        isLoading: false,
+      }))
+    }
+
     try {
       const messages = await apiService.getChannelMessages(channelId, undefined, 100)
+      messageCache.storeChannelMessages(messages)
       set((state) => ({
         messages: {
           ...state.messages,
@@ -51,6 +64,11 @@ export const useMessagesStore = create<MessagesState>((set, get) => ({
         isLoading: false,
       }))
     } catch (error: any) {
+      // Offline, the cached history stands in
+      if (cached.length > 0 && !error.response) {
+        set({ isLoading: false })
+        return
+      }
       const errorMessage = error.response?.data?.message || 'Failed to fetch messages'
       set({
         isLoading: false,
@@ -80,7 +98,22 @@ export const useMessagesStore = create<MessagesState>((set, get) => ({
     }))
 
     try {
-      const olderMessages = await apiService.getChannelMessages(channelId, oldestMessageId, 100)
+      const olderMessages = await apiService
+        .getChannelMessages(channelId, oldestMessageId, 100)
+        .then((messages) => {
+          messageCache.storeChannelMessages(messages)
+          return messages
+        })
+        .catch(async (error) => {
+          // Offline, keep scrolling through what's on disk
+          const cached = error.response
+            ? []
+            : await messageCache.channelMessages(channelId, oldestMessageId, 100)
+          if (cached.length === 0) {
+            throw error
+          }
+          return cached
+        })
       set((state) => {
         const existingMessages = state.messages[channelId] || []
         const existingMessageIds = new Set(existingMessages.map((m) => m.id))
@@ -1,11 +1,22 @@
 import { create } from 'zustand'
 import { persist } from 'zustand/middleware'
 
+// Mirrors ClosePolicy in src-tauri/src/background.rs
+export type ClosePolicy = 'quit' | 'hide' | 'destroy'
+
 export interface UserSettings {
   notifications: boolean
   sounds: boolean
   fontSize: 'small' | 'medium' | 'large'
   timestampFormat: '12h' | '24h'
+  closePolicy: ClosePolicy
+  autoIdle: boolean
+  idleAfterMinutes: number
+  // Longest side of uploaded images, in pixels
+  imageMaxDimension: number
+  // Size limit of the local attachment cache, in megabytes
+  attachmentCacheMb: number
+  zoom: number
   audioInputDeviceId?: string
   audioOutputDeviceId?: string
 }
@@ -15,6 +26,7 @@ interface SettingsState extends UserSettings {
   updateSettings: (settings: Partial<UserSettings>) => void
   updateSetting: <K extends keyof UserSettings>(key: K, value: UserSettings[K]) => void
   applyFontSize: (size: 'small' | 'medium' | 'large') => void
+  applyZoom: (zoom: number) => void
   getTimeFormat: () => (date: Date) => string
 }
 
@@ -23,6 +35,12 @@ const defaultSettings: UserSettings = {
   sounds: true,
   fontSize: 'medium',
   timestampFormat: '12h',
+  closePolicy: 'quit',
+  autoIdle: true,
+  idleAfterMinutes: 15,
+  imageMaxDimension: 2560,
+  attachmentCacheMb: 1024,
+  zoom: 1,
 }
 
 export const useSettingsStore = create<SettingsState>()(
@@ -37,6 +55,9 @@ export const useSettingsStore = create<SettingsState>()(
         if (newSettings.fontSize) {
           get().applyFontSize(newSettings.fontSize)
         }
+        if (newSettings.zoom) {
+          get().applyZoom(newSettings.zoom)
+        }
       },
 
       updateSetting: (key, value) => {
@@ -47,6 +68,9 @@ export const useSettingsStore = create<SettingsState>()(
         if (key === 'fontSize') {
           get().applyFontSize(value as 'small' | 'medium' | 'large')
         }
+        if (key === 'zoom') {
+          get().applyZoom(value as number)
+        }
       },
 
       applyFontSize: (size) => {
@@ -71,6 +95,11 @@ export const useSettingsStore = create<SettingsState>()(
         }
       },
 
+      applyZoom: (zoom) => {
+        // The webview has no zoom of its own under Tauri 1, so scale the page
+        document.documentElement.style.setProperty('zoom', String(zoom))
+      },
+
       getTimeFormat: () => {
         const format = get().timestampFormat
         return (date: Date) => {
@@ -96,6 +125,7 @@ export const useSettingsStore = create<SettingsState>()(
         // Apply settings on rehydration
         if (state) {
           state.applyFontSize(state.fontSize)
+          state.applyZoom(state.zoom ?? 1)
         }
       },
     }
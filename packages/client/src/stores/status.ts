@@ -86,6 +86,16 @@ export const useStatusStore = create<StatusState>((set, get) => ({
     get().setUserStatus(user.id, 'online')
     await get().updateStatus('online')
 
+    // Listen for WebSocket status updates
+    wsManager.onStatusUpdate((data: { userId: number; status: string }) => {
+      get().setUserStatus(data.userId, data.status as UserStatus)
+    })
+
+    // The desktop app watches system-wide input instead (services/idle.ts)
+    if (window.__TAURI__) {
+      return
+    }
+
     // Track user activity
     const handleActivity = () => {
       get().updateLastActivity()
@@ -97,11 +107,6 @@ export const useStatusStore = create<StatusState>((set, get) => ({
     window.addEventListener('scroll', handleActivity)
     window.addEventListener('touchstart', handleActivity)
 
-    // Listen for WebSocket status updates
-    wsManager.onStatusUpdate((data: { userId: number; status: string }) => {
-      get().setUserStatus(data.userId, data.status as UserStatus)
-    })
-
     // Auto-set idle status after 15 minutes of inactivity
     const checkIdleStatus = async () => {
       const { lastActivity } = get()
@@ -38,6 +38,7 @@ interface VoiceState {
   // User state
   isMuted: boolean
   isDeafened: boolean
+  isPushToTalkActive: boolean
   localStream: MediaStream | null
   localVideoEnabled: boolean
   localVideoStream: MediaStream | null
@@ -56,6 +57,7 @@ interface VoiceState {
   setConnectionError: (error: string | null) => void
   setIsMuted: (isMuted: boolean) => void
   setIsDeafened: (isDeafened: boolean) => void
+  setPushToTalkActive: (active: boolean) => void
   setLocalStream: (stream: MediaStream | null) => void
   setLocalVideoEnabled: (enabled: boolean) => void
   setLocalVideoStream: (stream: MediaStream | null) => void
@@ -92,6 +94,7 @@ export const useVoiceStore = create<VoiceState>((set) => ({
   qualityWarnings: [],
   isMuted: false,
   isDeafened: false,
+  isPushToTalkActive: false,
   localStream: null,
   localVideoEnabled: false,
   localVideoStream: null,
@@ -136,6 +139,17 @@ export const useVoiceStore = create<VoiceState>((set) => ({
       return { isDeafened, isMuted: isDeafened ? true : state.isMuted }
     }),
 
+  setPushToTalkActive: (active) =>
+    set((state) => {
+      // The mic only opens while the key is held, and never while muted
+      if (state.localStream) {
+        state.localStream.getAudioTracks().forEach((track) => {
+          track.enabled = active && !state.isMuted
+        })
+      }
+      return { isPushToTalkActive: active }
+    }),
+
   setLocalStream: (stream) => set({ localStream: stream }),
 
   setLocalVideoEnabled: (enabled) => set({ localVideoEnabled: enabled }),
@@ -349,6 +363,7 @@ export const useVoiceStore = create<VoiceState>((set) => ({
         qualityWarnings: [],
         isMuted: false,
         isDeafened: false,
+        isPushToTalkActive: false,
         localStream: null,
         localVideoEnabled: false,
         localVideoStream: null,
@@ -96,17 +96,37 @@ export class DirectMessagesController {
   }
 
   @Patch(':id')
-  update(
+  async update(
     @Param('id', ParseIntPipe) id: number,
     @Body() updateDto: UpdateDirectMessageDto,
     @Request() req
   ) {
-    return this.directMessagesService.update(id, updateDto, req.user.id);
+    const message = await this.directMessagesService.update(
+      id,
+      updateDto,
+      req.user.id
+    );
+
+    // Both users' clients keep the message cached and searchable
+    this.chatGateway.emitDirectMessageEdited(message.receiverId, message);
+    this.chatGateway.emitDirectMessageEdited(message.senderId, message);
+
+    return message;
   }
 
   @Delete(':id')
-  remove(@Param('id', ParseIntPipe) id: number, @Request() req) {
-    return this.directMessagesService.remove(id, req.user.id);
+  async remove(@Param('id', ParseIntPipe) id: number, @Request() req) {
+    const message = await this.directMessagesService.remove(id, req.user.id);
+
+    const deleted = {
+      messageId: message.id,
+      senderId: message.senderId,
+      receiverId: message.receiverId,
+    };
+    this.chatGateway.emitDirectMessageDeleted(message.receiverId, deleted);
+    this.chatGateway.emitDirectMessageDeleted(message.senderId, deleted);
+
+    return { message: 'Message deleted successfully' };
   }
 
   @Post('conversation/:userId/read')
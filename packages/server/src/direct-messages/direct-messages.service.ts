@@ -297,6 +297,7 @@ export class DirectMessagesService {
             username: true,
           },
         },
+        attachments: true,
       },
     });
   }
@@ -314,11 +315,9 @@ export class DirectMessagesService {
       throw new ForbiddenException('You can only delete your own messages');
     }
 
-    await this.prisma.directMessage.delete({
+    return this.prisma.directMessage.delete({
       where: { id },
     });
-
-    return { message: 'Message deleted successfully' };
   }
 
   async markAsRead(conversationUserId: number, userId: number) {
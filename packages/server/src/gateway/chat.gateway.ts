@@ -525,6 +525,27 @@ export class ChatGateway
     );
   }
 
+  public emitDirectMessageEdited(userId: number, messageDto: any) {
+    this.directMessagesHandler.emitDirectMessageEdited(
+      this.server,
+      this.onlineUsers,
+      userId,
+      messageDto
+    );
+  }
+
+  public emitDirectMessageDeleted(
+    userId: number,
+    data: { messageId: number; senderId: number; receiverId: number }
+  ) {
+    this.directMessagesHandler.emitDirectMessageDeleted(
+      this.server,
+      this.onlineUsers,
+      userId,
+      data
+    );
+  }
+
   public emitDMThreadCreated(userId: number) {
     this.directMessagesHandler.emitDMThreadCreated(
       this.server,
@@ -184,6 +184,36 @@ export class DirectMessagesHandler {
     }
   }
 
+  /**
+   * Public method to emit direct-message-edited events from HTTP controller
+   */
+  emitDirectMessageEdited(
+    server: Server,
+    onlineUsers: LRUCache<number, string>,
+    userId: number,
+    messageDto: any
+  ) {
+    const socketId = onlineUsers.get(userId);
+    if (socketId) {
+      server.to(socketId).emit('direct-message-edited', messageDto);
+    }
+  }
+
+  /**
+   * Public method to emit direct-message-deleted events from HTTP controller
+   */
+  emitDirectMessageDeleted(
+    server: Server,
+    onlineUsers: LRUCache<number, string>,
+    userId: number,
+    data: { messageId: number; senderId: number; receiverId: number }
+  ) {
+    const socketId = onlineUsers.get(userId);
+    if (socketId) {
+      server.to(socketId).emit('direct-message-deleted', data);
+    }
+  }
+
   /**
    * Public method to emit dm-thread-created events from HTTP controller
    */